@@ -0,0 +1,37 @@
+//! Throughput of `Exec::update` on synthetic machines.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use ultimate_scale::bench;
+use ultimate_scale::exec::Exec;
+use ultimate_scale::machine::Machine;
+
+/// Number of ticks that are run before measuring, so that the machine is
+/// filled with wind and blips.
+const WARM_UP_TICKS: usize = 100;
+
+fn warm_exec(machine: Machine) -> Exec {
+    let mut exec = Exec::new(machine, &mut rand::thread_rng());
+
+    for _ in 0..WARM_UP_TICKS {
+        exec.update();
+    }
+
+    exec
+}
+
+fn bench_machine(c: &mut Criterion, name: &str, machine: Machine) {
+    // The machines are constantly fed with blips, so they stay busy while
+    // they are being measured.
+    let mut exec = warm_exec(machine);
+
+    c.bench_function(name, |b| b.iter(|| exec.update()));
+}
+
+fn exec_benchmark(c: &mut Criterion) {
+    bench_machine(c, "long_pipeline_1000", bench::long_pipeline(1000));
+    bench_machine(c, "duplicator_grid_10x10", bench::duplicator_grid(10, 10));
+}
+
+criterion_group!(benches, exec_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,54 @@
+//! Cost of preparing the instances of large machines for drawing.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use nalgebra as na;
+
+use ultimate_scale::bench;
+use ultimate_scale::exec::TickTime;
+use ultimate_scale::machine::Machine;
+use ultimate_scale::render::blip_style::BlipStyle;
+use ultimate_scale::render::{self, Stage};
+
+fn render_stage(machine: &Machine) -> Stage {
+    let mut stage = Stage::default();
+
+    render::machine::render_machine(
+        machine,
+        &TickTime::zero(),
+        None,
+        |_| true,
+        |_| 1.0,
+        |_| false,
+        |_| None,
+        &BlipStyle::default(),
+        &mut stage,
+    );
+
+    stage
+}
+
+fn bench_machine(c: &mut Criterion, name: &str, machine: Machine) {
+    // Look at the machine at an angle from above one of its corners, which
+    // is about as far from sorted insertion order as the editor camera gets.
+    let size: na::Vector3<f32> = na::convert(machine.size());
+    let eye = na::Point3::new(-size.x / 2.0, -size.y / 2.0, size.x.max(size.y));
+
+    c.bench_function(&format!("{}_render", name), |b| {
+        b.iter(|| render_stage(&machine))
+    });
+    c.bench_function(&format!("{}_render_sorted", name), |b| {
+        b.iter(|| {
+            let mut stage = render_stage(&machine);
+            stage.sort(&eye);
+            stage
+        })
+    });
+}
+
+fn render_benchmark(c: &mut Criterion) {
+    bench_machine(c, "duplicator_grid_20x20", bench::duplicator_grid(20, 20));
+    bench_machine(c, "long_pipeline_5000", bench::long_pipeline(5000));
+}
+
+criterion_group!(benches, render_benchmark);
+criterion_main!(benches);
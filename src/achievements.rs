@@ -0,0 +1,212 @@
+//! Achievements that the player can unlock, e.g. by completing a level
+//! quickly or by running many ticks in total.
+//!
+//! Definitions are data-driven: they are loaded from a JSON file if it
+//! exists, and otherwise the builtin definitions are used. Which achievements
+//! have been unlocked is stored in the player's `Progress`.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::exec::Score;
+use crate::machine::stats::Stats;
+use crate::machine::{BlockType, Level, TickNum};
+use crate::progress::LifetimeStats;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// File with custom definitions, replacing the builtin ones.
+    pub definitions_path: PathBuf,
+
+    /// How long the notification of an unlocked achievement is shown.
+    pub toast_secs: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            definitions_path: PathBuf::from("achievements.json"),
+            toast_secs: 5.0,
+        }
+    }
+}
+
+/// What the player needs to do to unlock an achievement.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Condition {
+    /// Complete a level. If `level` is given, only the level with this id
+    /// counts. The other fields are optional limits on the score.
+    CompleteLevel {
+        level: Option<String>,
+        max_ticks: Option<TickNum>,
+        max_blocks: Option<usize>,
+    },
+
+    /// Complete any level with a machine whose player blocks are only of the
+    /// given types, each appearing at most the given number of times.
+    CompleteLevelWithBlocks { max_blocks: Vec<(BlockType, usize)> },
+
+    /// Run at least this many ticks in total, according to the player's
+    /// lifetime stats.
+    TotalTicks { ticks: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Definition {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub condition: Condition,
+}
+
+/// Something that happened in the game which may unlock achievements.
+#[derive(Debug, Clone)]
+pub enum Event<'a> {
+    LevelCompleted {
+        level: &'a Level,
+        score: &'a Score,
+        stats: &'a Stats,
+    },
+
+    /// Execution has run ticks, which have been added to the player's
+    /// lifetime stats.
+    TicksRun,
+}
+
+/// The player's progress in achievements, persisted as part of `Progress`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct State {
+    /// Ids of the achievements that have been unlocked, in the order in which
+    /// they were unlocked.
+    pub unlocked: Vec<String>,
+}
+
+impl State {
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked.iter().any(|unlocked| unlocked == id)
+    }
+
+    /// Updates the state with the event, returning the achievements that have
+    /// been unlocked by it.
+    pub fn on_event<'a>(
+        &mut self,
+        definitions: &'a [Definition],
+        lifetime: &LifetimeStats,
+        event: &Event,
+    ) -> Vec<&'a Definition> {
+        let mut unlocked = Vec::new();
+
+        for definition in definitions {
+            if !self.is_unlocked(&definition.id) && is_met(&definition.condition, lifetime, event) {
+                info!("Unlocked achievement {:?}", definition.id);
+
+                self.unlocked.push(definition.id.clone());
+                unlocked.push(definition);
+            }
+        }
+
+        unlocked
+    }
+}
+
+fn is_met(condition: &Condition, lifetime: &LifetimeStats, event: &Event) -> bool {
+    match (condition, event) {
+        (
+            Condition::CompleteLevel {
+                level: level_id,
+                max_ticks,
+                max_blocks,
+            },
+            Event::LevelCompleted { level, score, .. },
+        ) => {
+            level_id.as_ref().map_or(true, |id| *id == level.id())
+                && max_ticks.map_or(true, |max| score.num_ticks <= max)
+                && max_blocks.map_or(true, |max| score.num_blocks <= max)
+        }
+        (
+            Condition::CompleteLevelWithBlocks { max_blocks },
+            Event::LevelCompleted { stats, .. },
+        ) => stats.blocks_by_type.iter().all(|(block_type, count)| {
+            max_blocks
+                .iter()
+                .any(|(allowed_type, max)| allowed_type == block_type && count <= max)
+        }),
+        (Condition::TotalTicks { ticks }, _) => lifetime.ticks_simulated >= *ticks,
+        _ => false,
+    }
+}
+
+/// Loads the definitions from the configured file, falling back to the
+/// builtin definitions if it does not exist or is invalid.
+pub fn load_definitions(config: &Config) -> Vec<Definition> {
+    let path = &config.definitions_path;
+
+    if !path.exists() {
+        return definitions();
+    }
+
+    load_json(path).unwrap_or_else(definitions)
+}
+
+pub fn definitions() -> Vec<Definition> {
+    vec![
+        Definition {
+            id: "first_steps".to_string(),
+            title: "First steps".to_string(),
+            description: "Complete a level.".to_string(),
+            condition: Condition::CompleteLevel {
+                level: None,
+                max_ticks: None,
+                max_blocks: None,
+            },
+        },
+        Definition {
+            id: "clockwork".to_string(),
+            title: "Clockwork".to_string(),
+            description: "Complete the clock level in at most 30 ticks.".to_string(),
+            condition: Condition::CompleteLevel {
+                level: Some("clock".to_string()),
+                max_ticks: Some(30),
+                max_blocks: None,
+            },
+        },
+        Definition {
+            id: "minimalist".to_string(),
+            title: "Minimalist".to_string(),
+            description: "Complete a level using only pipes and at most one duplicator."
+                .to_string(),
+            condition: Condition::CompleteLevelWithBlocks {
+                max_blocks: vec![
+                    (BlockType::Pipe, std::usize::MAX),
+                    (BlockType::BlipDuplicator, 1),
+                ],
+            },
+        },
+        Definition {
+            id: "marathon".to_string(),
+            title: "Marathon".to_string(),
+            description: "Run one million ticks in total.".to_string(),
+            condition: Condition::TotalTicks { ticks: 1_000_000 },
+        },
+    ]
+}
+
+fn load_json<T: serde::de::DeserializeOwned>(path: &Path) -> Option<T> {
+    match File::open(path) {
+        Ok(file) => match serde_json::from_reader(BufReader::new(file)) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                warn!("Error while loading file {:?}: {}", path, err);
+                None
+            }
+        },
+        Err(err) => {
+            warn!("Could not open file {:?} for reading: {}", path, err);
+            None
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! Synthetic machines for measuring the throughput of the simulation.
+
+use std::time::Instant;
+
+use crate::exec::Exec;
+use crate::machine::string_util::machine_from_string;
+use crate::machine::Machine;
+
+/// A single pipe of the given length, which is constantly fed with blips.
+pub fn long_pipeline(length: usize) -> Machine {
+    let m = format!("◉{}\n ┻\n", "-".repeat(length));
+
+    machine_from_string(&m)
+}
+
+/// A grid of blip duplicators, each of which is constantly fed with blips.
+pub fn duplicator_grid(width: usize, height: usize) -> Machine {
+    // Tiles are separated by a column and a row of empty space, so that their
+    // pipes do not connect. Empty rows are marked with dots, since blank lines
+    // are skipped.
+    let tile = ["◉-------┐  ", " ┻     -┿- ", "..........."];
+
+    let m: String = (0..height)
+        .flat_map(|_| tile.iter())
+        .map(|row| format!("{}\n", row.repeat(width)))
+        .collect();
+
+    machine_from_string(&m)
+}
+
+/// Runs the machine for the given number of ticks, returning the number of
+/// ticks that were run per second.
+pub fn ticks_per_sec(machine: Machine, num_ticks: usize) -> f64 {
+    let mut exec = Exec::new(machine, &mut rand::thread_rng());
+
+    let start = Instant::now();
+    for _ in 0..num_ticks {
+        exec.update();
+    }
+
+    num_ticks as f64 / start.elapsed().as_secs_f64()
+}
@@ -0,0 +1,207 @@
+//! Runs a saved machine without opening a window, printing the results as
+//! JSON. The exit code is non-zero if the machine has a level that it does
+//! not complete, so that solutions can be verified in CI.
+//!
+//! With `--analyze`, the machine is instead run for a number of generated
+//! examples of its level, writing a report for level authors to a file.
+//!
+//! With `--export-graph`, the connectivity of the machine's blocks is written
+//! to a file instead, in the DOT language if the file ends in `.dot` and as
+//! JSON otherwise.
+
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use clap::{App, Arg};
+use rand::SeedableRng;
+
+use ultimate_scale::content::{self, Content};
+use ultimate_scale::edit::solutions;
+use ultimate_scale::exec::LevelStatus;
+use ultimate_scale::exec::{analysis, headless};
+use ultimate_scale::machine::graph::Graph;
+
+/// Number of ticks to run if none are given.
+const DEFAULT_MAX_TICKS: &str = "10000";
+
+/// Number of examples to run in analysis mode if none are given.
+const DEFAULT_NUM_RUNS: &str = "20";
+
+fn main() {
+    let args = App::new("Ultimate Scale CLI")
+        .version("0.0.1")
+        .author("leod <subtle.frustration@proton.me>")
+        .about("Runs a saved machine headlessly and prints the results as JSON")
+        .arg(
+            Arg::with_name("file")
+                .value_name("FILE")
+                .help("The saved machine to run")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("level")
+                .short("l")
+                .long("level")
+                .value_name("LEVEL")
+                .help("Run the machine for this level instead of the one it was saved for")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("ticks")
+                .short("t")
+                .long("ticks")
+                .value_name("TICKS")
+                .help("Maximal number of ticks to run")
+                .default_value(DEFAULT_MAX_TICKS)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .short("s")
+                .long("seed")
+                .value_name("SEED")
+                .help("Seed for generating the level's inputs")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("analyze")
+                .short("a")
+                .long("analyze")
+                .value_name("REPORT")
+                .help("Analyze the machine as a reference solution, writing a report to this file")
+                .conflicts_with("seed")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("export-graph")
+                .long("export-graph")
+                .value_name("GRAPH")
+                .help("Write the connectivity of the machine's blocks to this file, as DOT or JSON")
+                .conflicts_with("analyze")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("runs")
+                .short("r")
+                .long("runs")
+                .value_name("RUNS")
+                .help("Number of examples to run when analyzing")
+                .default_value(DEFAULT_NUM_RUNS)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .help("Print log messages"),
+        )
+        .get_matches();
+
+    if args.is_present("verbose") {
+        simple_logger::init_with_level(log::Level::Info).unwrap();
+    }
+
+    let max_ticks = args
+        .value_of("ticks")
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|_| fail("Number of ticks must be a non-negative integer"));
+
+    let file = args.value_of("file").unwrap();
+    let mut machine = solutions::load(Path::new(file))
+        .unwrap_or_else(|| fail(&format!("Could not load machine from file `{}'", file)));
+
+    if let Some(id) = args.value_of("level") {
+        let content = Content::load(&content::Config::default());
+        let level = content
+            .levels()
+            .into_iter()
+            .find(|level| level.id() == id)
+            .unwrap_or_else(|| fail(&format!("Unknown level \"{}\"", id)));
+
+        machine.level = Some(level);
+    }
+
+    if let Some(graph_path) = args.value_of("export-graph") {
+        let graph = Graph::from_machine(&machine);
+        let contents = if Path::new(graph_path)
+            .extension()
+            .map_or(false, |ext| ext == "dot")
+        {
+            graph.to_dot()
+        } else {
+            serde_json::to_string_pretty(&graph).unwrap()
+        };
+
+        fs::write(graph_path, contents).unwrap_or_else(|err| {
+            fail(&format!(
+                "Could not write graph to `{}': {}",
+                graph_path, err
+            ))
+        });
+
+        println!(
+            "Graph with {} blocks and {} edges written to `{}'",
+            graph.nodes.len(),
+            graph.edges.len(),
+            graph_path,
+        );
+
+        return;
+    }
+
+    if let Some(report_path) = args.value_of("analyze") {
+        let num_runs = args
+            .value_of("runs")
+            .unwrap()
+            .parse()
+            .unwrap_or_else(|_| fail("Number of runs must be a non-negative integer"));
+
+        let analysis = analysis::analyze(&machine, num_runs, max_ticks);
+        let json = serde_json::to_string_pretty(&analysis).unwrap();
+        fs::write(report_path, json).unwrap_or_else(|err| {
+            fail(&format!(
+                "Could not write report to `{}': {}",
+                report_path, err
+            ))
+        });
+
+        println!(
+            "{} of {} runs completed, report written to `{}'",
+            analysis.num_completed, analysis.num_runs, report_path,
+        );
+
+        if analysis.num_completed < analysis.num_runs {
+            process::exit(1);
+        }
+
+        return;
+    }
+
+    let has_level = machine.level.is_some();
+
+    let report = match args.value_of("seed") {
+        Some(seed) => {
+            let seed = seed
+                .parse()
+                .unwrap_or_else(|_| fail("Seed must be a non-negative integer"));
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+
+            headless::run(machine, max_ticks, &mut rng)
+        }
+        None => headless::run(machine, max_ticks, &mut rand::thread_rng()),
+    };
+
+    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+
+    if has_level && report.status != LevelStatus::Completed {
+        process::exit(1);
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(2);
+}
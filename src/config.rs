@@ -1,13 +1,69 @@
+use std::time::Duration;
+
 use glium::glutin;
 
+use crate::achievements;
+use crate::content;
 use crate::edit;
 use crate::edit_camera_view;
 use crate::exec;
+use crate::locale;
+use crate::net::share;
+use crate::photo_mode;
+use crate::progress;
+use crate::render;
+use crate::render::blip_style::BlipStyle;
+use crate::tutorial;
+use crate::workshop;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowMode {
+    Windowed,
+
+    /// A window without decorations that covers the whole monitor.
+    Borderless,
+
+    Fullscreen,
+}
+
+impl WindowMode {
+    pub const ALL: &'static [WindowMode] = &[
+        WindowMode::Windowed,
+        WindowMode::Borderless,
+        WindowMode::Fullscreen,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WindowMode::Windowed => "Windowed",
+            WindowMode::Borderless => "Borderless",
+            WindowMode::Fullscreen => "Fullscreen",
+        }
+    }
+
+    /// Returns the mode to switch to with the fullscreen toggle.
+    pub fn toggled(self) -> WindowMode {
+        match self {
+            WindowMode::Windowed => WindowMode::Fullscreen,
+            WindowMode::Borderless | WindowMode::Fullscreen => WindowMode::Windowed,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct ViewConfig {
+    /// Size of the window in windowed mode.
     pub window_size: glutin::dpi::LogicalSize,
     pub fov_degrees: f64,
+    pub window_mode: WindowMode,
+
+    /// Index of the monitor to use in borderless and fullscreen mode. If
+    /// `None`, the primary monitor is used.
+    pub monitor: Option<usize>,
+
+    /// Whether instances are sorted by their distance from the camera before
+    /// drawing.
+    pub sort_instances: bool,
 }
 
 impl Default for ViewConfig {
@@ -15,6 +71,30 @@ impl Default for ViewConfig {
         ViewConfig {
             window_size: glutin::dpi::LogicalSize::new(1920.0, 1080.0),
             fov_degrees: 60.0,
+            window_mode: WindowMode::Fullscreen,
+            monitor: None,
+            sort_instances: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClockConfig {
+    /// Duration of one step of the game's simulation. The game state is
+    /// always advanced by whole steps, regardless of the frame rate.
+    pub step: Duration,
+
+    /// Frames that take longer than this are treated as if they took this
+    /// long. This way, the game slows down instead of trying to catch up
+    /// with more and more steps after a stall.
+    pub max_frame_time: Duration,
+}
+
+impl Default for ClockConfig {
+    fn default() -> ClockConfig {
+        ClockConfig {
+            step: Duration::from_micros(1_000_000 / 120),
+            max_frame_time: Duration::from_millis(250),
         }
     }
 }
@@ -23,8 +103,19 @@ impl Default for ViewConfig {
 pub struct Config {
     pub camera: edit_camera_view::Config,
     pub view: ViewConfig,
-    pub render_pipeline: rendology::Config,
+    pub clock: ClockConfig,
+    pub render_pipeline: render::Config,
     pub editor: edit::Config,
     pub exec: exec::view::Config,
     pub play: exec::play::Config,
+    pub race: exec::race::Config,
+    pub progress: progress::Config,
+    pub share: share::Config,
+    pub tutorial: tutorial::Config,
+    pub achievements: achievements::Config,
+    pub content: content::Config,
+    pub workshop: workshop::Config,
+    pub blip_style: BlipStyle,
+    pub locale: locale::Config,
+    pub photo_mode: photo_mode::Config,
 }
@@ -0,0 +1,380 @@
+//! Discovery and loading of content packs.
+//!
+//! Each subdirectory of the content directory is a pack. The name of the
+//! directory is used as the pack's namespace, so that ids of different packs
+//! can not collide. A pack may contain the following:
+//!
+//! ```text
+//! <content_dir>/<pack>/pack.json        Optional `Manifest`.
+//! <content_dir>/<pack>/levels/*.json    Levels, played in file name order.
+//! <content_dir>/<pack>/scripts/*.lua    Script for the level of the same name.
+//! <content_dir>/<pack>/blueprints/*.json  Saved machines to place in the editor.
+//! <content_dir>/<pack>/meshes/*.obj     Meshes, textured by PNG files of the same name.
+//! ```
+//!
+//! Which packs are enabled, and the order in which they are loaded, is stored
+//! separately in a load order file.
+
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::edit::Piece;
+use crate::machine::{level, Level, SavedMachine};
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub dir: PathBuf,
+    pub load_order_path: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("content"),
+            load_order_path: PathBuf::from("content.json"),
+        }
+    }
+}
+
+/// Optional description of a pack, given in `pack.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Manifest {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadOrderEntry {
+    pub name: String,
+    pub enabled: bool,
+}
+
+/// The order in which packs are loaded, persisted between sessions. Packs that
+/// are not listed here are appended in alphabetical order and are enabled.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoadOrder {
+    pub packs: Vec<LoadOrderEntry>,
+}
+
+/// A machine from a pack that can be placed in the editor.
+#[derive(Debug, Clone)]
+pub struct Blueprint {
+    /// Namespaced id of the blueprint, e.g. `my_pack.counter`.
+    pub id: String,
+    pub piece: Piece,
+}
+
+#[derive(Debug, Clone)]
+pub struct Pack {
+    /// Name of the pack's directory, which serves as its namespace.
+    pub name: String,
+    pub path: PathBuf,
+    pub manifest: Manifest,
+    pub enabled: bool,
+    pub levels: Vec<Level>,
+    pub blueprints: Vec<Blueprint>,
+
+    /// Namespaced ids and paths of the pack's meshes.
+    pub meshes: Vec<(String, PathBuf)>,
+}
+
+impl Pack {
+    pub fn title(&self) -> &str {
+        self.manifest.title.as_ref().unwrap_or(&self.name)
+    }
+
+    pub fn load(name: &str, path: &Path) -> Pack {
+        info!("Loading content pack {:?} from {:?}", name, path);
+
+        let manifest_path = path.join("pack.json");
+        let manifest = if manifest_path.exists() {
+            load_json(&manifest_path).unwrap_or_default()
+        } else {
+            Manifest::default()
+        };
+
+        let levels = list_files(&path.join("levels"), "json")
+            .into_iter()
+            .filter_map(|(stem, level_path)| {
+                let mut level: Level = load_json(&level_path)?;
+                level.pack = Some(name.to_string());
+
+                // The level's id becomes part of paths, e.g. for storing
+                // solutions, so the name must not be able to escape them.
+                if let level::Spec::Scripted {
+                    name: level_name, ..
+                } = &level.spec
+                {
+                    if !is_valid_name(level_name) {
+                        warn!("Ignoring level with invalid name at {:?}", level_path);
+                        return None;
+                    }
+                }
+
+                let script_path = path.join("scripts").join(format!("{}.lua", stem));
+                if level.script.is_none() && script_path.exists() {
+                    level.script = load_string(&script_path);
+                }
+
+                Some(level)
+            })
+            .collect();
+
+        let blueprints = list_files(&path.join("blueprints"), "json")
+            .into_iter()
+            .filter_map(|(stem, blueprint_path)| {
+                let saved_machine: SavedMachine = load_json(&blueprint_path)?;
+                let blocks: Vec<_> = saved_machine
+                    .block_data
+                    .into_iter()
+                    .filter(|(_, placed_block)| placed_block.block.is_player_block())
+                    .collect();
+
+                if blocks.is_empty() {
+                    warn!("Ignoring empty blueprint {:?}", blueprint_path);
+                    return None;
+                }
+
+                Some(Blueprint {
+                    id: format!("{}.{}", name, stem),
+                    piece: Piece::new(blocks),
+                })
+            })
+            .collect();
+
+        let meshes = list_files(&path.join("meshes"), "obj")
+            .into_iter()
+            .map(|(stem, mesh_path)| (format!("{}.{}", name, stem), mesh_path))
+            .collect();
+
+        Pack {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+            manifest,
+            enabled: true,
+            levels,
+            blueprints,
+            meshes,
+        }
+    }
+}
+
+/// All packs that have been discovered, in load order.
+pub struct Content {
+    config: Config,
+    packs: Vec<Pack>,
+}
+
+impl Content {
+    pub fn load(config: &Config) -> Content {
+        let load_order = load_load_order(&config.load_order_path);
+        let mut found = discover(&config.dir);
+
+        let mut packs = Vec::new();
+
+        // First take the packs in the stored order, then all new ones.
+        for entry in load_order.packs.iter() {
+            if let Some(index) = found.iter().position(|(name, _)| *name == entry.name) {
+                let (name, path) = found.remove(index);
+                let mut pack = Pack::load(&name, &path);
+                pack.enabled = entry.enabled;
+                packs.push(pack);
+            }
+        }
+        for (name, path) in found {
+            packs.push(Pack::load(&name, &path));
+        }
+
+        Content {
+            config: config.clone(),
+            packs,
+        }
+    }
+
+    pub fn packs(&self) -> &[Pack] {
+        &self.packs
+    }
+
+    fn enabled_packs(&self) -> impl Iterator<Item = &Pack> {
+        self.packs.iter().filter(|pack| pack.enabled)
+    }
+
+    /// Returns the builtin levels, followed by the levels of all enabled
+    /// packs.
+    pub fn levels(&self) -> Vec<Level> {
+        level::levels()
+            .into_iter()
+            .chain(
+                self.enabled_packs()
+                    .flat_map(|pack| pack.levels.iter().cloned()),
+            )
+            .collect()
+    }
+
+    pub fn blueprints(&self) -> Vec<Blueprint> {
+        self.enabled_packs()
+            .flat_map(|pack| pack.blueprints.iter().cloned())
+            .collect()
+    }
+
+    pub fn meshes(&self) -> Vec<(String, PathBuf)> {
+        self.enabled_packs()
+            .flat_map(|pack| pack.meshes.iter().cloned())
+            .collect()
+    }
+
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        self.packs[index].enabled = enabled;
+        self.save_load_order();
+    }
+
+    /// Moves the pack one position earlier in the load order.
+    pub fn move_up(&mut self, index: usize) {
+        if index > 0 && index < self.packs.len() {
+            self.packs.swap(index - 1, index);
+            self.save_load_order();
+        }
+    }
+
+    /// Moves the pack one position later in the load order.
+    pub fn move_down(&mut self, index: usize) {
+        if index + 1 < self.packs.len() {
+            self.packs.swap(index, index + 1);
+            self.save_load_order();
+        }
+    }
+
+    fn save_load_order(&self) {
+        let path = &self.config.load_order_path;
+        info!("Saving content load order to file {:?}", path);
+
+        let load_order = LoadOrder {
+            packs: self
+                .packs
+                .iter()
+                .map(|pack| LoadOrderEntry {
+                    name: pack.name.clone(),
+                    enabled: pack.enabled,
+                })
+                .collect(),
+        };
+
+        match File::create(path) {
+            Ok(file) => {
+                if let Err(err) = serde_json::to_writer_pretty(file, &load_order) {
+                    warn!("Error while saving load order to file {:?}: {}", path, err);
+                }
+            }
+            Err(err) => {
+                warn!("Could not open file {:?} for writing: {}", path, err);
+            }
+        }
+    }
+}
+
+/// Returns true if the name can be used as a pack namespace or as the name of
+/// a scripted level.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Returns the names and paths of all packs in the content directory, sorted
+/// by name.
+fn discover(dir: &Path) -> Vec<(String, PathBuf)> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => {
+            info!("No content directory at {:?}", dir);
+            return Vec::new();
+        }
+    };
+
+    let mut packs: Vec<(String, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?.to_string();
+
+            if is_valid_name(&name) {
+                Some((name, path))
+            } else {
+                warn!("Ignoring content pack with invalid name at {:?}", path);
+                None
+            }
+        })
+        .collect();
+    packs.sort();
+
+    packs
+}
+
+/// Returns the file stems and paths of all files with the given extension in
+/// the directory, sorted by file name.
+fn list_files(dir: &Path, extension: &str) -> Vec<(String, PathBuf)> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut files: Vec<(String, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == extension))
+        .filter_map(|path| {
+            let stem = path.file_stem()?.to_str()?.to_string();
+            Some((stem, path))
+        })
+        .collect();
+    files.sort();
+
+    files
+}
+
+fn load_load_order(path: &Path) -> LoadOrder {
+    if path.exists() {
+        load_json(path).unwrap_or_default()
+    } else {
+        LoadOrder::default()
+    }
+}
+
+fn load_json<T: serde::de::DeserializeOwned>(path: &Path) -> Option<T> {
+    match File::open(path) {
+        Ok(file) => {
+            let reader = BufReader::new(file);
+            match serde_json::from_reader(reader) {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    warn!("Error while loading file {:?}: {}", path, err);
+                    None
+                }
+            }
+        }
+        Err(err) => {
+            warn!("Could not open file {:?} for reading: {}", path, err);
+            None
+        }
+    }
+}
+
+fn load_string(path: &Path) -> Option<String> {
+    let mut source = String::new();
+
+    match File::open(path).and_then(|mut file| file.read_to_string(&mut source)) {
+        Ok(_) => Some(source),
+        Err(err) => {
+            warn!("Could not read file {:?}: {}", path, err);
+            None
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! Connects pipes to their surroundings as they are placed, so that pipes can
+//! be laid out without rotating each one by hand.
+
+use std::collections::HashMap;
+
+use crate::machine::grid::{self, Dir3, DirMap3};
+use crate::machine::{Block, Machine, PlacedBlock};
+
+/// Extends blocks that are about to be set such that the pipes among them
+/// connect to their neighbors. Pipes are turned towards neighbors that have a
+/// hole facing them, or towards the loose ends of neighboring pipes. In turn,
+/// neighboring pipes are bent or merged to connect to the placed pipes. The
+/// changed neighbors are included in the result, so that everything is
+/// undone at once.
+pub fn connect_pipes(
+    machine: &Machine,
+    blocks: HashMap<grid::Point3, Option<PlacedBlock>>,
+) -> HashMap<grid::Point3, Option<PlacedBlock>> {
+    // Go through the pipes in a fixed order, so that the outcome does not
+    // depend on the order of the map.
+    let mut pipe_positions: Vec<grid::Point3> = blocks
+        .iter()
+        .filter(|(_, placed_block)| {
+            placed_block
+                .as_ref()
+                .map_or(false, |placed_block| is_pipe(&placed_block.block))
+        })
+        .map(|(pos, _)| *pos)
+        .collect();
+    pipe_positions.sort_by_key(|pos| (pos.z, pos.y, pos.x));
+
+    let mut result = blocks;
+
+    for pos in pipe_positions {
+        let block = match get(machine, &result, &pos) {
+            Some(block) => block,
+            None => continue,
+        };
+
+        let targets: Vec<Dir3> = Dir3::ALL
+            .iter()
+            .cloned()
+            .filter(|&dir| wants_connection(machine, &result, &pos, dir))
+            .collect();
+        let new_block = oriented_pipe(&block, &targets);
+
+        result.insert(
+            pos,
+            Some(PlacedBlock {
+                block: new_block.clone(),
+            }),
+        );
+
+        for &dir in Dir3::ALL.iter() {
+            if !new_block.has_wind_hole(dir, false) {
+                continue;
+            }
+
+            let neighbor_pos = pos + dir.to_vector();
+            let bent = get(machine, &result, &neighbor_pos).and_then(|neighbor| {
+                bent_pipe(machine, &result, &neighbor_pos, &neighbor, dir.invert())
+            });
+
+            if let Some(bent) = bent {
+                result.insert(neighbor_pos, Some(PlacedBlock { block: bent }));
+            }
+        }
+    }
+
+    result
+}
+
+fn is_pipe(block: &Block) -> bool {
+    match block {
+        Block::Pipe(_, _) | Block::GeneralPipe(_) => true,
+        _ => false,
+    }
+}
+
+/// Returns the block at `pos`, taking blocks that are about to be set into
+/// account.
+fn get(
+    machine: &Machine,
+    blocks: &HashMap<grid::Point3, Option<PlacedBlock>>,
+    pos: &grid::Point3,
+) -> Option<Block> {
+    match blocks.get(pos) {
+        Some(placed_block) => placed_block
+            .as_ref()
+            .map(|placed_block| placed_block.block.clone()),
+        None => machine
+            .get(pos)
+            .map(|placed_block| placed_block.block.clone()),
+    }
+}
+
+/// Returns true if `block` has an opening in direction `dir` that a pipe
+/// could connect to.
+fn has_opening(block: &Block, dir: Dir3) -> bool {
+    match block {
+        Block::BlipSpawn { out_dir, .. } => *out_dir == dir,
+        _ => block.has_wind_hole(dir, false),
+    }
+}
+
+/// Returns true if the pipe `block` at `pos` has a hole in direction `dir`
+/// that does not lead into another block.
+fn is_loose_end(
+    machine: &Machine,
+    blocks: &HashMap<grid::Point3, Option<PlacedBlock>>,
+    pos: &grid::Point3,
+    block: &Block,
+    dir: Dir3,
+) -> bool {
+    block.has_wind_hole(dir, false)
+        && get(machine, blocks, &(pos + dir.to_vector()))
+            .map_or(true, |neighbor| !has_opening(&neighbor, dir.invert()))
+}
+
+/// Returns true if the pipe at `pos` should connect to its neighbor in
+/// direction `dir`.
+fn wants_connection(
+    machine: &Machine,
+    blocks: &HashMap<grid::Point3, Option<PlacedBlock>>,
+    pos: &grid::Point3,
+    dir: Dir3,
+) -> bool {
+    let neighbor_pos = pos + dir.to_vector();
+
+    match get(machine, blocks, &neighbor_pos) {
+        Some(neighbor) => {
+            has_opening(&neighbor, dir.invert())
+                || (is_pipe(&neighbor)
+                    && Dir3::ALL.iter().any(|&neighbor_dir| {
+                        neighbor_dir != dir.invert()
+                            && is_loose_end(machine, blocks, &neighbor_pos, &neighbor, neighbor_dir)
+                    }))
+        }
+        None => false,
+    }
+}
+
+/// Turns a pipe towards the given directions. With a single direction, the
+/// pipe goes straight through it.
+fn oriented_pipe(block: &Block, targets: &[Dir3]) -> Block {
+    match (block, targets) {
+        (_, []) => block.clone(),
+        (Block::Pipe(dir_a, dir_b), [target]) => {
+            if dir_a == target || dir_b == target {
+                block.clone()
+            } else {
+                Block::Pipe(*target, target.invert())
+            }
+        }
+        (Block::Pipe(_, _), targets) => {
+            let is_xy = targets.iter().all(|dir| dir.0 != grid::Axis3::Z);
+
+            if targets.len() > 2 && is_xy {
+                Block::PipeMergeXY
+            } else {
+                Block::Pipe(targets[0], targets[1])
+            }
+        }
+        (Block::GeneralPipe(dirs), [target]) => {
+            if dirs[*target] {
+                block.clone()
+            } else {
+                Block::GeneralPipe(DirMap3::from_fn(|dir| {
+                    dir == *target || dir == target.invert()
+                }))
+            }
+        }
+        (Block::GeneralPipe(_), targets) => {
+            Block::GeneralPipe(DirMap3::from_fn(|dir| targets.contains(&dir)))
+        }
+        _ => block.clone(),
+    }
+}
+
+/// Returns the pipe `block` at `pos` changed such that it connects in
+/// direction `dir`, if it does not already. Loose ends of the pipe are bent
+/// towards `dir`. If there are none, general pipes get an additional hole,
+/// while other pipes are turned into a merge if possible.
+fn bent_pipe(
+    machine: &Machine,
+    blocks: &HashMap<grid::Point3, Option<PlacedBlock>>,
+    pos: &grid::Point3,
+    block: &Block,
+    dir: Dir3,
+) -> Option<Block> {
+    if block.has_wind_hole(dir, false) {
+        return None;
+    }
+
+    let is_loose = |hole_dir: Dir3| is_loose_end(machine, blocks, pos, block, hole_dir);
+
+    match block {
+        Block::Pipe(dir_a, dir_b) => {
+            if is_loose(*dir_a) {
+                Some(Block::Pipe(dir, *dir_b))
+            } else if is_loose(*dir_b) {
+                Some(Block::Pipe(*dir_a, dir))
+            } else if [*dir_a, *dir_b, dir]
+                .iter()
+                .all(|dir| dir.0 != grid::Axis3::Z)
+            {
+                Some(Block::PipeMergeXY)
+            } else {
+                None
+            }
+        }
+        Block::GeneralPipe(dirs) => {
+            let bent = DirMap3::from_fn(|hole_dir| {
+                hole_dir == dir || (dirs[hole_dir] && !is_loose(hole_dir))
+            });
+
+            if bent.values().filter(|&&hole| hole).count() >= 2 {
+                Some(Block::GeneralPipe(bent))
+            } else {
+                Some(Block::GeneralPipe(DirMap3::from_fn(|hole_dir| {
+                    hole_dir == dir || dirs[hole_dir]
+                })))
+            }
+        }
+        _ => None,
+    }
+}
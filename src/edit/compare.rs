@@ -0,0 +1,39 @@
+use crate::machine::diff::{BlockChange, ChangeKind};
+use crate::machine::Machine;
+
+/// Differences between the machine being edited and another machine, e.g. a
+/// different solution on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparison {
+    /// Name of the solution that is compared against.
+    pub name: String,
+
+    pub other: Machine,
+
+    /// Changes that turn the other machine into the edited one.
+    pub changes: Vec<BlockChange>,
+}
+
+impl Comparison {
+    pub fn new(name: &str, other: Machine, machine: &Machine) -> Self {
+        let changes = other.diff(machine);
+
+        Self {
+            name: name.to_string(),
+            other,
+            changes,
+        }
+    }
+
+    /// Compares again, e.g. after the machine has been edited.
+    pub fn update(&mut self, machine: &Machine) {
+        self.changes = self.other.diff(machine);
+    }
+
+    pub fn count(&self, kind: ChangeKind) -> usize {
+        self.changes
+            .iter()
+            .filter(|change| change.kind() == kind)
+            .count()
+    }
+}
@@ -1,9 +1,10 @@
 use std::fmt;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use glium::glutin::VirtualKeyCode;
 
-use crate::machine::grid::{Axis3, Dir3, DirMap3};
+use crate::machine::grid::{self, Axis3, Dir3, DirMap3};
 use crate::machine::{BlipKind, Block};
 
 // TODO: Shift does not work for some reason, we don't get any key press events
@@ -70,7 +71,37 @@ impl fmt::Display for ModifiedKey {
 
 #[derive(Debug, Clone)]
 pub struct Config {
-    pub default_save_path: PathBuf,
+    pub solutions_dir: PathBuf,
+
+    /// Size of the grid of new sandbox machines, which is the default when
+    /// creating a machine in the menu.
+    pub sandbox_size: grid::Vector3,
+
+    /// Largest size that can be chosen when creating a machine in the menu.
+    pub max_new_machine_size: grid::Vector3,
+
+    /// Maximal number of edits that can be undone.
+    pub max_undos: usize,
+
+    /// Maximal number of bytes that the undo history may use. The oldest
+    /// edits are forgotten first.
+    pub max_undo_bytes: usize,
+
+    /// Placements of single blocks that follow each other within this
+    /// duration are undone at once.
+    pub undo_coalesce_duration: Duration,
+
+    /// Whether placed blocks take over the orientation of the blocks that
+    /// they replace.
+    pub quick_replace: bool,
+
+    /// Whether clicking on the face of a block places the new block next to
+    /// that face, instead of in the current layer.
+    pub build_on_faces: bool,
+
+    /// Whether placed pipes are turned to connect to adjacent holes, bending
+    /// neighboring pipes towards them if needed.
+    pub auto_connect_pipes: bool,
 
     pub cancel_key: ModifiedKey,
 
@@ -78,6 +109,13 @@ pub struct Config {
     pub rotate_block_ccw_key: ModifiedKey,
     pub mirror_y_key: ModifiedKey,
     pub block_kind_key: ModifiedKey,
+    pub pipette_key: ModifiedKey,
+    pub isolate_key: ModifiedKey,
+    pub search_key: ModifiedKey,
+
+    /// Focuses on the next search result. This takes precedence over other
+    /// shortcuts while the search is open.
+    pub search_next_key: ModifiedKey,
 
     pub undo_key: ModifiedKey,
     pub redo_key: ModifiedKey,
@@ -91,12 +129,20 @@ pub struct Config {
 
     pub layer_up_key: ModifiedKey,
     pub layer_down_key: ModifiedKey,
+    pub layer_visibility_key: ModifiedKey,
+    pub collision_policy_key: ModifiedKey,
+    pub symmetry_key: ModifiedKey,
+    pub symmetry_center_key: ModifiedKey,
 
     pub select_all_key: ModifiedKey,
 
     pub select_key: ModifiedKey,
     pub select_layer_bound_key: ModifiedKey,
     pub pipe_tool_key: ModifiedKey,
+    pub measure_key: ModifiedKey,
+    pub erase_key: ModifiedKey,
+    pub wall_key: ModifiedKey,
+    pub wall_selection_key: ModifiedKey,
     pub block_keys: Vec<(ModifiedKey, Block)>,
     pub layer_keys: Vec<(ModifiedKey, isize)>,
 }
@@ -104,12 +150,24 @@ pub struct Config {
 impl Default for Config {
     fn default() -> Config {
         Config {
-            default_save_path: PathBuf::from("machine.json"),
+            solutions_dir: PathBuf::from("solutions"),
+            sandbox_size: grid::Vector3::new(60, 60, 40),
+            max_new_machine_size: grid::Vector3::new(200, 200, 100),
+            max_undos: 1000,
+            max_undo_bytes: 64 * 1024 * 1024,
+            undo_coalesce_duration: Duration::from_millis(500),
+            quick_replace: true,
+            build_on_faces: true,
+            auto_connect_pipes: false,
             cancel_key: ModifiedKey::new(VirtualKeyCode::Escape),
             rotate_block_cw_key: ModifiedKey::new(VirtualKeyCode::R),
             rotate_block_ccw_key: ModifiedKey::shift(VirtualKeyCode::R),
             mirror_y_key: ModifiedKey::new(VirtualKeyCode::M),
             block_kind_key: ModifiedKey::new(VirtualKeyCode::C),
+            pipette_key: ModifiedKey::new(VirtualKeyCode::I),
+            isolate_key: ModifiedKey::ctrl(VirtualKeyCode::T),
+            search_key: ModifiedKey::ctrl(VirtualKeyCode::F),
+            search_next_key: ModifiedKey::new(VirtualKeyCode::F3),
             undo_key: ModifiedKey::ctrl(VirtualKeyCode::Z),
             redo_key: ModifiedKey::ctrl(VirtualKeyCode::Y),
             copy_key: ModifiedKey::ctrl(VirtualKeyCode::C),
@@ -119,10 +177,18 @@ impl Default for Config {
             save_key: ModifiedKey::ctrl(VirtualKeyCode::S),
             layer_up_key: ModifiedKey::new(VirtualKeyCode::Tab),
             layer_down_key: ModifiedKey::shift(VirtualKeyCode::Tab),
+            layer_visibility_key: ModifiedKey::new(VirtualKeyCode::V),
+            collision_policy_key: ModifiedKey::new(VirtualKeyCode::O),
+            symmetry_key: ModifiedKey::new(VirtualKeyCode::K),
+            symmetry_center_key: ModifiedKey::shift(VirtualKeyCode::K),
             select_all_key: ModifiedKey::ctrl(VirtualKeyCode::A),
             select_key: ModifiedKey::new(VirtualKeyCode::Key1),
             select_layer_bound_key: ModifiedKey::ctrl(VirtualKeyCode::Key1),
             pipe_tool_key: ModifiedKey::new(VirtualKeyCode::Key2),
+            measure_key: ModifiedKey::new(VirtualKeyCode::L),
+            erase_key: ModifiedKey::new(VirtualKeyCode::X),
+            wall_key: ModifiedKey::new(VirtualKeyCode::J),
+            wall_selection_key: ModifiedKey::shift(VirtualKeyCode::J),
             block_keys: vec![
                 (
                     ModifiedKey::new(VirtualKeyCode::Key3),
@@ -1,5 +1,13 @@
-use crate::edit::{Edit, Editor, Mode, Piece};
-use crate::machine::{grid, Block, PlacedBlock};
+use log::warn;
+
+use crate::edit::compare::Comparison;
+use crate::edit::search::Search;
+use crate::edit::{
+    isolate, solutions, CollisionPolicy, Edit, Editor, LayerVisibility, Mode, Piece, SelectionMode,
+    Symmetry,
+};
+use crate::machine::level::Violation;
+use crate::machine::{grid, Block, BlockType, PlacedBlock};
 
 #[allow(unused)]
 /// Actions that can be accessed by buttons and shortcuts in the editor.
@@ -14,13 +22,43 @@ pub enum Action {
     Paste,
     Delete,
     Save,
+    SaveSolutionAs(String),
+    LoadSolution(String),
+    CompareWithSolution(String),
+    StopComparing,
+    RevertToComparison,
     LayerUp,
     LayerDown,
+    SetLayerVisibility(LayerVisibility),
+    NextLayerVisibility,
+    SetCollisionPolicy(CollisionPolicy),
+    NextCollisionPolicy,
+    SetQuickReplace(bool),
+    SetBuildOnFaces(bool),
+    SetAutoConnectPipes(bool),
+    SetSymmetry(Symmetry),
+    NextSymmetry,
+    SetSymmetryCenter,
     SelectAll,
     SelectMode,
     SelectLayerBoundMode,
     PipeToolMode,
+    MeasureMode,
+    EraseMode,
+    SetEraseFilter(Option<BlockType>),
+    WallMode,
+    WallSelection,
+    DismissMeasurement,
+    FocusOn(grid::Point3),
     PlaceBlockMode(Block),
+    Pipette,
+    IsolateSelection,
+    OpenSearch,
+    CloseSearch,
+    Search(String),
+    SearchNext,
+    SearchSelect(usize),
+    PlaceBlueprint(String),
     Cancel,
     RotateCW,
     RotateCCW,
@@ -38,13 +76,47 @@ impl Editor {
             Action::Paste => self.action_paste(),
             Action::Delete => self.action_delete(),
             Action::Save => self.action_save(),
+            Action::SaveSolutionAs(name) => self.action_save_solution_as(name),
+            Action::LoadSolution(name) => self.action_load_solution(name),
+            Action::CompareWithSolution(name) => self.action_compare_with_solution(name),
+            Action::StopComparing => self.action_stop_comparing(),
+            Action::RevertToComparison => self.action_revert_to_comparison(),
             Action::LayerUp => self.action_layer_up(),
             Action::LayerDown => self.action_layer_down(),
+            Action::SetLayerVisibility(visibility) => self.action_set_layer_visibility(visibility),
+            Action::NextLayerVisibility => self.action_next_layer_visibility(),
+            Action::SetCollisionPolicy(policy) => self.action_set_collision_policy(policy),
+            Action::NextCollisionPolicy => self.action_next_collision_policy(),
+            Action::SetQuickReplace(quick_replace) => self.action_set_quick_replace(quick_replace),
+            Action::SetBuildOnFaces(build_on_faces) => {
+                self.action_set_build_on_faces(build_on_faces)
+            }
+            Action::SetAutoConnectPipes(auto_connect_pipes) => {
+                self.action_set_auto_connect_pipes(auto_connect_pipes)
+            }
+            Action::SetSymmetry(symmetry) => self.action_set_symmetry(symmetry),
+            Action::NextSymmetry => self.action_next_symmetry(),
+            Action::SetSymmetryCenter => self.action_set_symmetry_center(),
             Action::SelectAll => self.action_select_all(),
             Action::SelectMode => self.action_select_mode(),
             Action::SelectLayerBoundMode => self.action_select_layer_bound_mode(),
             Action::PipeToolMode => self.action_pipe_tool_mode(),
+            Action::MeasureMode => self.action_measure_mode(),
+            Action::EraseMode => self.action_erase_mode(),
+            Action::SetEraseFilter(filter) => self.action_set_erase_filter(filter),
+            Action::WallMode => self.action_wall_mode(),
+            Action::WallSelection => self.action_wall_selection(),
+            Action::DismissMeasurement => self.action_dismiss_measurement(),
+            Action::FocusOn(pos) => self.action_focus_on(pos),
             Action::PlaceBlockMode(block) => self.action_place_block_mode(block),
+            Action::Pipette => self.action_pipette(),
+            Action::IsolateSelection => self.action_isolate_selection(),
+            Action::OpenSearch => self.action_open_search(),
+            Action::CloseSearch => self.action_close_search(),
+            Action::Search(query) => self.action_search(query),
+            Action::SearchNext => self.action_search_next(),
+            Action::SearchSelect(index) => self.action_search_select(index),
+            Action::PlaceBlueprint(id) => self.action_place_blueprint(id),
             Action::Cancel => self.action_cancel(),
             Action::RotateCW => self.action_rotate_cw(),
             Action::RotateCCW => self.action_rotate_ccw(),
@@ -54,16 +126,22 @@ impl Editor {
     }
 
     pub fn action_undo(&mut self) {
-        if let Some(undo_edit) = self.undo.pop_back() {
+        self.commit();
+
+        if let Some(undo_edit) = self.history.pop_undo() {
             let redo_edit = self.run_edit(undo_edit);
-            self.redo.push(redo_edit);
+            self.history.push_redo(redo_edit);
+            self.last_placement = None;
         }
     }
 
     pub fn action_redo(&mut self) {
-        if let Some(redo_edit) = self.redo.pop() {
+        self.commit();
+
+        if let Some(redo_edit) = self.history.pop_redo() {
             let undo_edit = self.run_edit(redo_edit);
-            self.undo.push_back(undo_edit);
+            self.history.push_undo(undo_edit);
+            self.last_placement = None;
         }
     }
 
@@ -102,36 +180,52 @@ impl Editor {
     }
 
     pub fn action_paste(&mut self) {
-        if let Some(clipboard) = &self.clipboard {
-            let mut piece = clipboard.clone();
-
-            // Kinda center the piece at the mouse
-            let mut extent = piece.extent();
-            extent.z = 0;
+        if let Some(clipboard) = self.clipboard.clone() {
+            self.place_piece_at_mouse(clipboard);
+        }
+    }
 
-            piece.shift(&(-piece.min_pos().coords - extent / 2));
+    pub fn action_place_blueprint(&mut self, id: String) {
+        let piece = self
+            .blueprints
+            .iter()
+            .find(|blueprint| blueprint.id == id)
+            .map(|blueprint| blueprint.piece.clone());
 
-            // Bias towards positive direction for even sizes.
-            // Just feels more natural.
-            // TODO: Bias actually needs to depend on the view position?
-            if extent.x > 0 && extent.x % 2 == 0 {
-                piece.shift(&grid::Vector3::x());
-            }
-            if extent.y > 0 && extent.y % 2 == 0 {
-                piece.shift(&grid::Vector3::y());
-            }
+        match piece {
+            Some(piece) => self.place_piece_at_mouse(piece),
+            None => warn!("Unknown blueprint {}", id),
+        }
+    }
 
-            // If we are placing in an upper layer, it could be that the piece
-            // sticks out at the top. Shift down if that is the case.
-            let max_z = piece.blocks().iter().map(|(p, _)| p.z).max().unwrap_or(0)
-                + self.mouse_grid_pos.map_or(0, |p| p.z);
-            let too_high = (max_z - self.machine().size().z + 1).max(0);
+    /// Switches to placing the piece, centered at the mouse.
+    fn place_piece_at_mouse(&mut self, mut piece: Piece) {
+        // Kinda center the piece at the mouse
+        let mut extent = piece.extent();
+        extent.z = 0;
 
-            self.current_layer -= too_high.min(self.current_layer);
-            assert!(self.machine.is_valid_layer(self.current_layer));
+        piece.shift(&(-piece.min_pos().coords - extent / 2));
 
-            self.mode = self.mode.clone().switch_to_place_piece(piece, true);
+        // Bias towards positive direction for even sizes.
+        // Just feels more natural.
+        // TODO: Bias actually needs to depend on the view position?
+        if extent.x > 0 && extent.x % 2 == 0 {
+            piece.shift(&grid::Vector3::x());
         }
+        if extent.y > 0 && extent.y % 2 == 0 {
+            piece.shift(&grid::Vector3::y());
+        }
+
+        // If we are placing in an upper layer, it could be that the piece
+        // sticks out at the top. Shift down if that is the case.
+        let max_z = piece.blocks().iter().map(|(p, _)| p.z).max().unwrap_or(0)
+            + self.mouse_grid_pos.map_or(0, |p| p.z);
+        let too_high = (max_z - self.machine().size().z + 1).max(0);
+
+        self.current_layer -= too_high.min(self.current_layer);
+        assert!(self.machine.is_valid_layer(self.current_layer));
+
+        self.mode = self.mode.clone().switch_to_place_piece(piece, true);
     }
 
     pub fn action_delete(&mut self) {
@@ -155,7 +249,70 @@ impl Editor {
     }
 
     pub fn action_save(&mut self) {
-        self.save(&self.config.default_save_path);
+        self.save_solution();
+    }
+
+    pub fn action_save_solution_as(&mut self, name: String) {
+        if !solutions::is_valid_name(&name) {
+            warn!("Invalid solution name {:?}, not saving", name);
+            return;
+        }
+
+        self.solution_name = name;
+        self.save_solution();
+    }
+
+    pub fn action_load_solution(&mut self, name: String) {
+        let path = solutions::path(
+            &self.config.solutions_dir,
+            self.machine.level.as_ref(),
+            &name,
+        );
+
+        if let Some(machine) = solutions::load(&path) {
+            if machine.level != self.machine.level {
+                warn!("Solution {:?} is for a different level, ignoring", path);
+                return;
+            }
+
+            self.set_machine(machine);
+            self.solution_name = name;
+        }
+    }
+
+    /// Highlights the blocks that differ from a stored solution.
+    pub fn action_compare_with_solution(&mut self, name: String) {
+        let path = solutions::path(
+            &self.config.solutions_dir,
+            self.machine.level.as_ref(),
+            &name,
+        );
+
+        if let Some(other) = solutions::load(&path) {
+            if other.size() != self.machine.size() {
+                warn!("Solution {:?} has a different size, not comparing", path);
+                return;
+            }
+
+            self.comparison = Some(Comparison::new(&name, other, &self.machine));
+        }
+    }
+
+    pub fn action_stop_comparing(&mut self) {
+        self.comparison = None;
+    }
+
+    /// Undoes all the differences to the compared solution, as a single edit
+    /// that can be undone.
+    pub fn action_revert_to_comparison(&mut self) {
+        let edit = self
+            .comparison
+            .as_ref()
+            .map(|comparison| Edit::from_changes(&self.machine.diff(&comparison.other)));
+
+        if let Some(edit) = edit {
+            self.run_and_track_edit(edit);
+        }
     }
 
     pub fn action_layer_up(&mut self) {
@@ -177,6 +334,50 @@ impl Editor {
         }
     }
 
+    pub fn action_set_layer_visibility(&mut self, visibility: LayerVisibility) {
+        self.layer_visibility = visibility;
+    }
+
+    pub fn action_next_layer_visibility(&mut self) {
+        self.layer_visibility = self.layer_visibility.next();
+    }
+
+    pub fn action_set_collision_policy(&mut self, policy: CollisionPolicy) {
+        self.collision_policy = policy;
+    }
+
+    pub fn action_next_collision_policy(&mut self) {
+        self.collision_policy = self.collision_policy.next();
+    }
+
+    pub fn action_set_quick_replace(&mut self, quick_replace: bool) {
+        self.quick_replace = quick_replace;
+    }
+
+    pub fn action_set_build_on_faces(&mut self, build_on_faces: bool) {
+        self.build_on_faces = build_on_faces;
+    }
+
+    pub fn action_set_auto_connect_pipes(&mut self, auto_connect_pipes: bool) {
+        self.auto_connect_pipes = auto_connect_pipes;
+    }
+
+    pub fn action_set_symmetry(&mut self, symmetry: Symmetry) {
+        self.symmetry = symmetry;
+    }
+
+    pub fn action_next_symmetry(&mut self) {
+        self.symmetry = self.symmetry.next();
+    }
+
+    /// Moves the symmetry center to the grid position that the mouse is
+    /// pointing to.
+    pub fn action_set_symmetry_center(&mut self) {
+        if let Some(mouse_grid_pos) = self.mouse_grid_pos {
+            self.symmetry_center = mouse_grid_pos;
+        }
+    }
+
     pub fn action_layer_down(&mut self) {
         if self.machine.is_valid_layer(self.current_layer - 1) {
             self.current_layer -= 1;
@@ -219,7 +420,113 @@ impl Editor {
         self.mode = Mode::new_pipe_tool();
     }
 
+    pub fn action_measure_mode(&mut self) {
+        self.mode = Mode::new_measure();
+    }
+
+    pub fn action_erase_mode(&mut self) {
+        self.mode = Mode::new_erase(self.erase_filter);
+    }
+
+    /// Restricts the eraser to blocks of the given type, or lets it erase
+    /// all blocks if `None`.
+    pub fn action_set_erase_filter(&mut self, filter: Option<BlockType>) {
+        self.erase_filter = filter;
+
+        if let Mode::Erase { .. } = self.mode {
+            self.mode = Mode::new_erase(filter);
+        }
+    }
+
+    pub fn action_wall_mode(&mut self) {
+        self.mode = Mode::new_wall();
+    }
+
+    /// Builds walls around the bounding box of the selection, leaving one
+    /// block of space on each side.
+    pub fn action_wall_selection(&mut self) {
+        let edit = self.mode.selection().and_then(|selection| {
+            let first = *selection.iter().next()?;
+            let (min, max) = selection.iter().fold((first, first), |(min, max), pos| {
+                (
+                    grid::box_corners(&min, pos).0,
+                    grid::box_corners(&max, pos).1,
+                )
+            });
+            let margin = grid::Vector3::new(1, 1, 1);
+
+            Some(self.wall_edit(&(min - margin), &(max + margin)))
+        });
+
+        if let Some(edit) = edit {
+            self.run_and_track_edit(edit);
+        } else {
+            warn!("Nothing selected to build walls around, ignoring");
+        }
+    }
+
+    pub fn action_dismiss_measurement(&mut self) {
+        self.measurement = None;
+    }
+
+    /// Moves the camera to the block and selects it.
+    pub fn action_focus_on(&mut self, pos: grid::Point3) {
+        let mut selection = SelectionMode::new(false);
+        selection.push_if_correct_layer(pos.z, pos);
+
+        self.mode = Mode::new_selection(selection).make_consistent_with_machine(&self.machine);
+        self.current_layer = pos.z;
+        self.focus = Some(pos);
+    }
+
+    pub fn action_open_search(&mut self) {
+        if self.search.is_none() {
+            self.search = Some(Search::default());
+        }
+    }
+
+    pub fn action_close_search(&mut self) {
+        self.search = None;
+    }
+
+    /// Looks for blocks by name and focuses on the first one that is found.
+    pub fn action_search(&mut self, query: String) {
+        let mut search = Search::new(&self.machine, &query);
+
+        if let Some(pos) = search.next() {
+            self.action_focus_on(pos);
+        }
+
+        self.search = Some(search);
+    }
+
+    pub fn action_search_next(&mut self) {
+        if let Some(pos) = self.search.as_mut().and_then(Search::next) {
+            self.action_focus_on(pos);
+        }
+    }
+
+    pub fn action_search_select(&mut self, index: usize) {
+        let pos = self.search.as_mut().and_then(|search| {
+            search.current = Some(index).filter(|&index| index < search.results.len());
+            search.current_pos()
+        });
+
+        if let Some(pos) = pos {
+            self.action_focus_on(pos);
+        }
+    }
+
     pub fn action_place_block_mode(&mut self, block: Block) {
+        if let Some(level) = self.machine.level.as_ref() {
+            if !level.constraints.is_block_allowed(&block) {
+                self.violation = Some(Violation::BlockNotAllowed {
+                    block_type: block.block_type(),
+                });
+                return;
+            }
+        }
+
         // TODO: Maintain current rotation when switching to a different block
         // to place.
         let piece = Piece::new_origin_block(PlacedBlock { block });
@@ -227,10 +534,49 @@ impl Editor {
         self.mode = self.mode.clone().switch_to_place_piece(piece, false);
     }
 
+    /// Starts placing a copy of the block that the mouse is pointing to,
+    /// including its rotation and kind.
+    pub fn action_pipette(&mut self) {
+        let placed_block = self
+            .mouse_block_pos
+            .and_then(|pos| self.machine.get(&pos))
+            .filter(|placed_block| placed_block.block.is_player_block())
+            .cloned();
+
+        if let Some(placed_block) = placed_block {
+            self.action_place_block_mode(placed_block.block);
+        }
+    }
+
+    /// Copies the selected blocks into a sandbox machine, so that they can be
+    /// run in isolation from the rest of the machine.
+    pub fn action_isolate_selection(&mut self) {
+        let machine = self
+            .mode
+            .selection()
+            .and_then(|selection| isolate::test_machine(&self.machine, selection.iter().cloned()));
+
+        if machine.is_none() {
+            warn!("Nothing selected to test, ignoring");
+        }
+
+        self.isolated_machine = machine;
+    }
+
     pub fn action_cancel(&mut self) {
         self.mode = match &self.mode {
             Mode::DragAndDrop { selection, .. } => Mode::new_selection(selection.clone()),
             Mode::PipeTool { last_pos, .. } if last_pos.is_some() => Mode::new_pipe_tool(),
+            Mode::Measure { start } if start.is_some() => Mode::new_measure(),
+            Mode::Erase {
+                filter,
+                rect_start: Some(_),
+            } => Mode::new_erase(*filter),
+            Mode::Wall { start: Some(_) } => Mode::new_wall(),
+            Mode::Measure { .. } if self.measurement.is_some() => {
+                self.measurement = None;
+                Mode::new_measure()
+            }
             Mode::PlacePiece { outer, .. } => (**outer).clone(),
             _ => Mode::new_select(),
         };
@@ -238,11 +584,15 @@ impl Editor {
 
     pub fn action_rotate_cw(&mut self) {
         let mut edit = None;
+        let mut rotate_selection = None;
 
         match &mut self.mode {
             Mode::PlacePiece { piece, .. } => {
                 piece.rotate_cw_xy();
             }
+            Mode::Select { selection } if !selection.is_empty() => {
+                rotate_selection = Some(selection.clone());
+            }
             Mode::Select { .. } => {
                 if let Some(mouse_block_pos) = self.mouse_block_pos {
                     edit = Some(Edit::RotateCWXY(vec![mouse_block_pos]));
@@ -259,15 +609,22 @@ impl Editor {
         if let Some(edit) = edit {
             self.run_and_track_edit(edit);
         }
+        if let Some(selection) = rotate_selection {
+            self.action_rotate_selection(selection, 1);
+        }
     }
 
     pub fn action_rotate_ccw(&mut self) {
         let mut edit = None;
+        let mut rotate_selection = None;
 
         match &mut self.mode {
             Mode::PlacePiece { piece, .. } => {
                 piece.rotate_ccw_xy();
             }
+            Mode::Select { selection } if !selection.is_empty() => {
+                rotate_selection = Some(selection.clone());
+            }
             Mode::Select { .. } => {
                 if let Some(mouse_block_pos) = self.mouse_block_pos {
                     edit = Some(Edit::RotateCCWXY(vec![mouse_block_pos]));
@@ -284,6 +641,18 @@ impl Editor {
         if let Some(edit) = edit {
             self.run_and_track_edit(edit);
         }
+        if let Some(selection) = rotate_selection {
+            // Three clockwise steps make one counterclockwise step.
+            self.action_rotate_selection(selection, 3);
+        }
+    }
+
+    /// Rotates the arrangement of the selected blocks around the center of
+    /// the selection.
+    fn action_rotate_selection(&mut self, selection: SelectionMode, num_steps_cw: usize) {
+        if let Some(gizmo) = self.rotate_gizmo(&selection) {
+            self.mode = self.rotate_selection(selection, gizmo.pivot, num_steps_cw);
+        }
     }
 
     pub fn action_mirror_y(&mut self) {
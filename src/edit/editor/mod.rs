@@ -2,29 +2,46 @@ mod action;
 mod render;
 pub mod ui;
 
-use std::collections::{HashMap, VecDeque};
-use std::fs::File;
-use std::path::Path;
-use std::time::Duration;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 
 use coarse_prof::profile;
-use log::{info, warn};
+use log::warn;
 use nalgebra as na;
 
 use glium::glutin::{self, MouseButton, WindowEvent};
 
 use rendology::Camera;
 
+use crate::content::Blueprint;
 use crate::edit_camera_view::EditCameraView;
 use crate::input_state::InputState;
 use crate::machine::grid;
-use crate::machine::{Block, Machine, PlacedBlock, SavedMachine};
+use crate::machine::level::Violation;
+use crate::machine::stats::Stats;
+use crate::machine::validate::Problem;
+use crate::machine::{Block, BlockType, Machine, PlacedBlock};
+use crate::render::blip_style::BlipStyle;
 
+use crate::edit::compare::Comparison;
 use crate::edit::config::ModifiedKey;
-use crate::edit::{pick, Config, Edit, Mode, Piece, SelectionMode};
-
-/// Maximal length of the undo queue.
-pub const MAX_UNDOS: usize = 1000;
+use crate::edit::search::Search;
+use crate::edit::{
+    auto_connect, can_rotate_cw_xy_around, erasable_positions, pick, rect_positions,
+    rotate_positions_cw_xy, solutions, wall_positions, CollisionPolicy, Config, Edit, History,
+    LayerVisibility, Measurement, Mode, Piece, RotateGizmo, SelectionMode, Symmetry,
+};
+
+/// Edits that are undone as a whole, e.g. the blocks placed while dragging
+/// the mouse.
+struct Transaction {
+    /// Edit that undoes all of the transaction's edits so far.
+    undo_edit: Edit,
+
+    /// Positions that have been changed in the transaction. They are shown as
+    /// a preview until the transaction is committed.
+    positions: HashSet<grid::Point3>,
+}
 
 pub struct Editor {
     /// Configuration for the editor, e.g. shortcuts.
@@ -39,17 +56,48 @@ pub struct Editor {
     /// Clipboard.
     clipboard: Option<Piece>,
 
-    /// Edits that undo the last performed edits, in the order that the edits
-    /// were performed.
-    undo: VecDeque<Edit>,
+    /// Blueprints of the enabled content packs.
+    blueprints: Vec<Blueprint>,
+
+    /// How blips are drawn in spawners and duplicators.
+    blip_style: BlipStyle,
 
-    /// Edits that redo the last performed undos, in the order that the undos
-    /// were performed.
-    redo: Vec<Edit>,
+    /// Edits that undo or redo the last performed edits.
+    history: History,
+
+    /// Time of the last placement of a single block, if the last edit was
+    /// one. Following placements are undone together with it.
+    last_placement: Option<Instant>,
+
+    /// Edits that have been run since the transaction was begun. They are
+    /// added to the undo history only when the transaction is committed.
+    transaction: Option<Transaction>,
 
     /// Layer being edited. Blocks are placed only in the current layer.
     current_layer: isize,
 
+    /// How layers other than the current one are shown.
+    layer_visibility: LayerVisibility,
+
+    /// What happens to blocks that are in the way when pasting.
+    collision_policy: CollisionPolicy,
+
+    /// Whether placed blocks are oriented like the blocks they replace.
+    quick_replace: bool,
+
+    /// Whether blocks are placed next to the face of the block that is
+    /// clicked on.
+    build_on_faces: bool,
+
+    /// Whether placed pipes are connected to adjacent holes.
+    auto_connect_pipes: bool,
+
+    /// How placements and removals are mirrored.
+    symmetry: Symmetry,
+
+    /// Position that placements are mirrored at.
+    symmetry_center: grid::Point3,
+
     /// Grid position the mouse is currently pointing to, if any. The z
     /// coordinate is always set to `current_layer`. Note that the grid
     /// position may point outside of the grid.
@@ -57,20 +105,92 @@ pub struct Editor {
 
     /// Position of the *block* the mouse is currently pointing to, if any.
     mouse_block_pos: Option<grid::Point3>,
+
+    /// Face of the block the mouse is currently pointing to, if any.
+    mouse_block_face: Option<grid::Dir3>,
+
+    /// The last distance measured with the measurement tool. It is shown
+    /// until dismissed, even when switching to other modes.
+    measurement: Option<Measurement>,
+
+    /// Type of blocks that the eraser is restricted to, if any. It is kept
+    /// when switching to other modes.
+    erase_filter: Option<BlockType>,
+
+    /// Name under which the machine is saved as a solution.
+    solution_name: String,
+
+    /// Names of all stored solutions for the machine's level.
+    solution_names: Vec<String>,
+
+    /// The last level constraint that an edit was rejected for, shown as
+    /// feedback to the player.
+    violation: Option<Violation>,
+
+    /// Likely mistakes in the machine, updated whenever the machine changes.
+    problems: Vec<Problem>,
+
+    /// Statistics about the machine, updated whenever the machine changes.
+    stats: Stats,
+
+    /// Position that the camera should be moved to in the next update.
+    focus: Option<grid::Point3>,
+
+    /// Machine containing a copy of the selection, which should be run in
+    /// isolation next.
+    isolated_machine: Option<Machine>,
+
+    /// Blocks found by name, if the search is open.
+    search: Option<Search>,
+
+    /// Differences to another solution, which are highlighted.
+    comparison: Option<Comparison>,
+
+    /// Number of blocks that have been placed since the last call of
+    /// `take_num_blocks_placed`.
+    num_blocks_placed: usize,
 }
 
 impl Editor {
     pub fn new(config: &Config, machine: Machine) -> Editor {
+        let solution_names = solutions::list(&config.solutions_dir, machine.level.as_ref());
+        let problems = machine.validate();
+        let stats = machine.stats();
+        let symmetry_center = grid::Point3::from(machine.size() / 2);
+
         Editor {
             config: config.clone(),
             machine,
             mode: Mode::new_select(),
             clipboard: None,
-            undo: VecDeque::new(),
-            redo: Vec::new(),
+            blueprints: Vec::new(),
+            blip_style: BlipStyle::default(),
+            history: History::new(config.max_undos, config.max_undo_bytes),
+            last_placement: None,
+            transaction: None,
             current_layer: 0,
+            layer_visibility: LayerVisibility::default(),
+            collision_policy: CollisionPolicy::default(),
+            quick_replace: config.quick_replace,
+            build_on_faces: config.build_on_faces,
+            auto_connect_pipes: config.auto_connect_pipes,
+            symmetry: Symmetry::default(),
+            symmetry_center,
             mouse_grid_pos: None,
             mouse_block_pos: None,
+            mouse_block_face: None,
+            measurement: None,
+            erase_filter: None,
+            solution_name: solutions::DEFAULT_NAME.to_string(),
+            solution_names,
+            violation: None,
+            problems,
+            stats,
+            focus: None,
+            isolated_machine: None,
+            search: None,
+            comparison: None,
+            num_blocks_placed: 0,
         }
     }
 
@@ -78,8 +198,71 @@ impl Editor {
         &self.machine
     }
 
+    /// Replaces the machine that is being edited, e.g. when loading a
+    /// different solution. This clears the undo history.
+    pub fn set_machine(&mut self, machine: Machine) {
+        if machine.level != self.machine.level {
+            self.solution_name = solutions::DEFAULT_NAME.to_string();
+            self.solution_names =
+                solutions::list(&self.config.solutions_dir, machine.level.as_ref());
+            self.comparison = None;
+        }
+
+        if machine.size() != self.machine.size() {
+            self.symmetry_center = grid::Point3::from(machine.size() / 2);
+        }
+
+        self.machine = machine;
+        self.problems = self.machine.validate();
+        self.stats = self.machine.stats();
+        if let Some(search) = self.search.as_mut() {
+            search.update(&self.machine);
+        }
+        if let Some(comparison) = self.comparison.as_mut() {
+            comparison.update(&self.machine);
+        }
+        self.mode = Mode::new_select();
+        self.measurement = None;
+        self.violation = None;
+        self.history.clear();
+        self.last_placement = None;
+        self.transaction = None;
+
+        if !self.machine.is_valid_layer(self.current_layer) {
+            self.current_layer = 0;
+        }
+    }
+
+    /// Returns the machine that the player has requested to test in
+    /// isolation, if any.
+    pub fn take_isolated_machine(&mut self) -> Option<Machine> {
+        self.isolated_machine.take()
+    }
+
+    /// Returns the number of blocks that have been placed since the last
+    /// call, for the player's lifetime stats.
+    pub fn take_num_blocks_placed(&mut self) -> usize {
+        std::mem::replace(&mut self.num_blocks_placed, 0)
+    }
+
+    pub fn set_blueprints(&mut self, blueprints: Vec<Blueprint>) {
+        self.blueprints = blueprints;
+    }
+
+    pub fn set_blip_style(&mut self, blip_style: BlipStyle) {
+        self.blip_style = blip_style;
+    }
+
     pub fn run_edit(&mut self, edit: Edit) -> Edit {
         let undo_edit = edit.run(&mut self.machine);
+        self.problems = self.machine.validate();
+        self.stats = self.machine.stats();
+        if let Some(search) = self.search.as_mut() {
+            search.update(&self.machine);
+        }
+        if let Some(comparison) = self.comparison.as_mut() {
+            comparison.update(&self.machine);
+        }
 
         // Now that the machine has been mutated, we need to make sure there is
         // no spurious state left in the editing mode.
@@ -92,20 +275,215 @@ impl Editor {
         undo_edit
     }
 
+    /// Starts combining the following edits into a single undoable edit,
+    /// until `commit` is called. Does nothing if a transaction has already
+    /// been begun.
+    pub fn begin_transaction(&mut self) {
+        if self.transaction.is_none() {
+            self.transaction = Some(Transaction {
+                undo_edit: Edit::NoOp,
+                positions: HashSet::new(),
+            });
+        }
+    }
+
+    /// Adds the edits of the current transaction to the undo history as a
+    /// single edit.
+    pub fn commit(&mut self) {
+        if let Some(transaction) = self.transaction.take() {
+            match transaction.undo_edit {
+                Edit::NoOp => (),
+                undo_edit => self.history.push(undo_edit),
+            }
+
+            self.last_placement = None;
+        }
+    }
+
+    /// Returns true if the block at `pos` has been changed in a transaction
+    /// that has not been committed yet.
+    pub fn is_preview(&self, pos: &grid::Point3) -> bool {
+        self.transaction
+            .as_ref()
+            .map_or(false, |transaction| transaction.positions.contains(pos))
+    }
+
+    /// Adds the mirrored counterparts of the blocks that an edit sets,
+    /// according to the current symmetry.
+    fn with_symmetry(&self, edit: Edit) -> Edit {
+        match edit {
+            Edit::SetBlocks(blocks) => {
+                Edit::SetBlocks(self.symmetry.apply(&self.symmetry_center, blocks))
+            }
+            edit => edit,
+        }
+    }
+
+    /// Returns an edit that erases the blocks at the given positions and their
+    /// mirrored counterparts, as far as they match the eraser's filter.
+    fn erase_edit(&self, positions: Vec<grid::Point3>, filter: Option<BlockType>) -> Edit {
+        let positions = self
+            .symmetry
+            .apply(
+                &self.symmetry_center,
+                positions.into_iter().map(|pos| (pos, None)).collect(),
+            )
+            .into_iter()
+            .map(|(pos, _)| pos);
+
+        Edit::SetBlocks(
+            erasable_positions(&self.machine, positions, filter)
+                .into_iter()
+                .map(|pos| (pos, None))
+                .collect(),
+        )
+    }
+
+    /// Returns the positions that the eraser would erase if the mouse button
+    /// were released now, for previewing.
+    fn tentative_erase_positions(&self) -> Vec<grid::Point3> {
+        match &self.mode {
+            Mode::Erase {
+                filter,
+                rect_start: Some(rect_start),
+            } => self.mouse_grid_pos.map_or(Vec::new(), |mouse_grid_pos| {
+                erasable_positions(
+                    &self.machine,
+                    rect_positions(rect_start, &mouse_grid_pos).into_iter(),
+                    *filter,
+                )
+            }),
+            Mode::Erase {
+                filter,
+                rect_start: None,
+            } => erasable_positions(&self.machine, self.mouse_block_pos.into_iter(), *filter),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns an edit that builds walls of `Solid` blocks around the box
+    /// spanned by `a` and `b`.
+    fn wall_edit(&self, a: &grid::Point3, b: &grid::Point3) -> Edit {
+        Edit::SetBlocks(
+            wall_positions(&self.machine, a, b)
+                .into_iter()
+                .map(|pos| {
+                    (
+                        pos,
+                        Some(PlacedBlock {
+                            block: Block::Solid,
+                        }),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the positions at which walls would be built if the mouse
+    /// button were released now, for previewing.
+    fn tentative_wall_positions(&self) -> Vec<grid::Point3> {
+        match (&self.mode, self.mouse_grid_pos) {
+            (Mode::Wall { start: Some(start) }, Some(mouse_grid_pos)) => {
+                wall_positions(&self.machine, start, &mouse_grid_pos)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Connects the pipes that an edit places to their surroundings.
+    fn with_auto_connect(&self, edit: Edit) -> Edit {
+        match edit {
+            Edit::SetBlocks(blocks) => {
+                Edit::SetBlocks(auto_connect::connect_pipes(&self.machine, blocks))
+            }
+            edit => edit,
+        }
+    }
+
     pub fn run_and_track_edit(&mut self, edit: Edit) {
-        let undo_edit = self.run_edit(edit);
+        let is_placement = match &edit {
+            Edit::SetBlocks(blocks) => blocks.len() == 1 && blocks.values().all(Option::is_some),
+            _ => false,
+        };
+        let positions: Vec<grid::Point3> = match &edit {
+            Edit::SetBlocks(blocks) => blocks.keys().cloned().collect(),
+            _ => Vec::new(),
+        };
+        let num_placed = match &edit {
+            Edit::SetBlocks(blocks) => blocks.values().filter(|block| block.is_some()).count(),
+            _ => 0,
+        };
+
+        // Refuse edits that introduce violations of the level's constraints.
+        // Edits are checked before they are run, so that we do not need to
+        // copy the machine.
+        let constraints = self
+            .machine
+            .level
+            .as_ref()
+            .map(|level| level.constraints.clone())
+            .filter(|constraints| *constraints != Default::default());
+
+        let undo_edit = match constraints {
+            None => self.run_edit(edit),
+            Some(constraints) => match edit.blocks_to_set(&self.machine) {
+                Some(blocks) => {
+                    self.violation = constraints.check_set_blocks(&self.machine, &blocks);
+
+                    if self.violation.is_some() {
+                        Edit::NoOp
+                    } else {
+                        self.run_edit(edit)
+                    }
+                }
+                None => {
+                    // Pairs of edits are not created by the editor's tools, so
+                    // it is fine to fall back to comparing machines here.
+                    let before = self.machine.clone();
+                    let undo_edit = self.run_edit(edit);
+                    self.violation = constraints.check_change(&before, &self.machine);
+
+                    if self.violation.is_some() {
+                        self.run_edit(undo_edit);
+                        Edit::NoOp
+                    } else {
+                        undo_edit
+                    }
+                }
+            },
+        };
+
+        match undo_edit {
+            Edit::NoOp => (),
+            _ => self.num_blocks_placed += num_placed,
+        }
 
         match undo_edit {
             Edit::NoOp => {
                 // Don't pollute undo queue with edits that do nothing
             }
+            undo_edit if self.transaction.is_some() => {
+                // Safe to unwrap, since we have checked that there is a
+                // transaction. The new edit needs to be undone first.
+                let transaction = self.transaction.as_mut().unwrap();
+                let previous = std::mem::replace(&mut transaction.undo_edit, Edit::NoOp);
+                transaction.undo_edit = Edit::compose(undo_edit, previous);
+                transaction.positions.extend(positions);
+            }
             undo_edit => {
-                self.undo.push_back(undo_edit);
-                if self.undo.len() > MAX_UNDOS {
-                    self.undo.pop_front();
+                let now = Instant::now();
+                let coalesce = is_placement
+                    && self.last_placement.map_or(false, |last_placement| {
+                        now.duration_since(last_placement) < self.config.undo_coalesce_duration
+                    });
+
+                if coalesce {
+                    self.history.push_coalesced(undo_edit);
+                } else {
+                    self.history.push(undo_edit);
                 }
 
-                self.redo.clear();
+                self.last_placement = if is_placement { Some(now) } else { None };
             }
         }
     }
@@ -119,6 +497,12 @@ impl Editor {
     ) {
         profile!("editor");
 
+        if let Some(pos) = self.focus.take() {
+            // The target's height is snapped to the current layer below.
+            let center = crate::render::machine::block_center(&pos);
+            edit_camera_view.set_target(center);
+        }
+
         edit_camera_view.set_target(na::Point3::new(
             edit_camera_view.target().x,
             edit_camera_view.target().y,
@@ -132,18 +516,127 @@ impl Editor {
             &edit_camera_view.eye(),
             &input_state.mouse_window_pos(),
         );
-        self.mouse_block_pos = pick::pick_block(
+        let mouse_block = pick::pick_block_face(
             &self.machine,
             camera,
             &edit_camera_view.eye(),
             &input_state.mouse_window_pos(),
-            |block_pos| self.mode.impacts_layer(self.current_layer, block_pos.z),
+            |block_pos| self.is_pickable(block_pos),
         );
+        self.mouse_block_pos = mouse_block.map(|(pos, _face)| pos);
+        self.mouse_block_face = mouse_block.and_then(|(_pos, face)| face);
 
         self.update_input(input_state, camera);
     }
 
+    /// Returns true if the block at the position can be clicked on. Blocks in
+    /// layers that are hidden or ghosted can not be, so that they do not get
+    /// in the way of editing the layers below.
+    fn is_pickable(&self, block_pos: &grid::Point3) -> bool {
+        self.mode.impacts_layer(self.current_layer, block_pos.z)
+            && self
+                .layer_visibility
+                .is_shown(self.current_layer, block_pos.z)
+            && !self
+                .layer_visibility
+                .is_ghosted(self.current_layer, block_pos.z)
+    }
+
+    /// Rotates the arrangement of the selected blocks clockwise around
+    /// `pivot`, keeping the rotated blocks selected. Returns the selection
+    /// mode that the editor should be in afterwards.
+    fn rotate_selection(
+        &mut self,
+        selection: SelectionMode,
+        pivot: grid::Point3,
+        num_steps_cw: usize,
+    ) -> Mode {
+        let positions: Vec<_> = selection.iter().cloned().collect();
+
+        if num_steps_cw % 4 == 0 {
+            return Mode::new_selection(selection);
+        }
+
+        if !can_rotate_cw_xy_around(&self.machine, &positions, &pivot, num_steps_cw) {
+            warn!("Can not rotate selection, since it would collide with other blocks");
+            return Mode::new_selection(selection);
+        }
+
+        let rotated_positions = rotate_positions_cw_xy(&positions, &pivot, num_steps_cw);
+
+        self.run_and_track_edit(Edit::RotateXYAround {
+            positions,
+            pivot,
+            num_steps_cw,
+        });
+
+        if rotated_positions
+            .iter()
+            .all(|p| self.machine.is_block_at(p))
+        {
+            self.overwrite_selection(
+                rotated_positions.into_iter(),
+                Mode::new_selection(selection),
+            )
+        } else {
+            // The edit has been rejected, e.g. due to the level's constraints.
+            Mode::new_selection(selection)
+        }
+    }
+
+    /// Returns the gizmo for rotating the selected blocks, if any are selected.
+    fn rotate_gizmo(&self, selection: &SelectionMode) -> Option<RotateGizmo> {
+        RotateGizmo::around(selection.iter(), self.current_layer)
+    }
+
+    /// Returns the position at which a piece is placed. When pointing at the
+    /// face of a block, this is the free position next to that face, so
+    /// that blocks can be built against each other in all directions.
+    /// Otherwise, it is the mouse position in the current layer.
+    ///
+    /// Blocks that have been placed in the current drag are not built
+    /// against, since they would otherwise keep growing towards the camera.
+    fn mouse_place_pos(&self) -> Option<grid::Point3> {
+        let face_pos = self
+            .mouse_block_pos
+            .filter(|_| self.build_on_faces)
+            .filter(|block_pos| !self.is_preview(block_pos))
+            .and_then(|block_pos| {
+                self.mouse_block_face
+                    .map(|face| block_pos + face.to_vector())
+            })
+            .filter(|p| self.machine.is_valid_pos(p) && !self.machine.is_block_at(p));
+
+        face_pos.or(self.mouse_grid_pos)
+    }
+
+    /// Returns the position that the measurement tool would pick. Blocks are
+    /// preferred, so that positions in other layers can be measured.
+    fn mouse_measure_pos(&self) -> Option<grid::Point3> {
+        self.mouse_block_pos
+            .or(self.mouse_grid_pos)
+            .filter(|p| self.machine.is_valid_pos(p))
+    }
+
+    /// Returns the measurement that is currently being made, i.e. from the
+    /// first clicked position to the mouse.
+    fn tentative_measurement(&self) -> Option<Measurement> {
+        match &self.mode {
+            Mode::Measure { start: Some(start) } => self
+                .mouse_measure_pos()
+                .map(|end| Measurement { start: *start, end }),
+            _ => None,
+        }
+    }
+
     fn update_input(&mut self, input_state: &InputState, camera: &Camera) {
+        // Dragging has stopped once the mouse buttons are released.
+        if !input_state.is_button_pressed(MouseButton::Left)
+            && !input_state.is_button_pressed(MouseButton::Right)
+        {
+            self.commit();
+        }
+
         let mut edit = None;
 
         self.mode = match self.mode.clone() {
@@ -193,9 +686,11 @@ impl Editor {
             }
             Mode::Select { selection, .. } if input_state.is_button_pressed(MouseButton::Right) => {
                 if let Some(mouse_block_pos) = self.mouse_block_pos {
-                    edit = Some(Edit::SetBlocks(maplit::hashmap! {
+                    // Blocks removed while dragging are undone together.
+                    self.begin_transaction();
+                    edit = Some(self.with_symmetry(Edit::SetBlocks(maplit::hashmap! {
                         mouse_block_pos => None,
-                    }));
+                    })));
                 }
 
                 Mode::Select { selection }
@@ -244,11 +739,25 @@ impl Editor {
                 is_paste,
                 outer,
             } if input_state.is_button_pressed(MouseButton::Left) => {
-                if let Some(mouse_grid_pos) = self.mouse_grid_pos {
+                if let Some(place_pos) = self.mouse_place_pos() {
                     let mut piece = piece.clone();
-                    piece.shift(&mouse_grid_pos.coords);
-
-                    let edit = piece.as_place_edit();
+                    piece.shift(&place_pos.coords);
+
+                    // Blocks placed while dragging are undone together.
+                    let edit = if is_paste {
+                        piece.as_place_edit_with_policy(&self.machine, self.collision_policy)
+                    } else if self.quick_replace {
+                        piece.with_replacements(&self.machine).as_place_edit()
+                    } else {
+                        piece.as_place_edit()
+                    };
+                    let edit = self.with_symmetry(edit);
+                    let edit = if !is_paste && self.auto_connect_pipes {
+                        self.with_auto_connect(edit)
+                    } else {
+                        edit
+                    };
+                    self.begin_transaction();
                     self.run_and_track_edit(edit);
                 }
 
@@ -265,9 +774,10 @@ impl Editor {
             } if input_state.is_button_pressed(MouseButton::Right) => {
                 if !is_paste {
                     if let Some(mouse_grid_pos) = self.mouse_grid_pos {
-                        edit = Some(Edit::SetBlocks(maplit::hashmap! {
+                        self.begin_transaction();
+                        edit = Some(self.with_symmetry(Edit::SetBlocks(maplit::hashmap! {
                             mouse_grid_pos => None,
-                        }));
+                        })));
                     }
 
                     Mode::PlacePiece {
@@ -318,9 +828,10 @@ impl Editor {
                 if input_state.is_button_pressed(MouseButton::Right) =>
             {
                 if let Some(mouse_grid_pos) = self.mouse_grid_pos {
-                    edit = Some(Edit::SetBlocks(maplit::hashmap! {
+                    self.begin_transaction();
+                    edit = Some(self.with_symmetry(Edit::SetBlocks(maplit::hashmap! {
                         mouse_grid_pos => None,
-                    }));
+                    })));
                 }
 
                 Mode::new_pipe_tool()
@@ -329,14 +840,23 @@ impl Editor {
                 // Abort placement.
                 Mode::new_pipe_tool()
             }
+            Mode::Measure { start: Some(_) }
+                if input_state.is_button_pressed(MouseButton::Right) =>
+            {
+                // Abort measuring.
+                Mode::new_measure()
+            }
             Mode::PipeTool { blocks, .. } if !input_state.is_button_pressed(MouseButton::Left) => {
                 // Finish placement.
                 edit = Some(Edit::set_blocks_combine(
                     &self.machine,
-                    blocks
-                        .iter()
-                        .map(|(pos, block)| (*pos, Some(block.clone())))
-                        .collect(),
+                    self.symmetry.apply(
+                        &self.symmetry_center,
+                        blocks
+                            .iter()
+                            .map(|(pos, block)| (*pos, Some(block.clone())))
+                            .collect(),
+                    ),
                 ));
 
                 Mode::new_pipe_tool()
@@ -349,6 +869,81 @@ impl Editor {
                 // Continue in pipe tool placement mode
                 self.update_input_continue_pipe_tool(last_pos, blocks)
             }
+            Mode::Erase {
+                filter,
+                rect_start: Some(_),
+            } if input_state.is_button_pressed(MouseButton::Right) => {
+                // Abort the rectangle.
+                Mode::new_erase(filter)
+            }
+            Mode::Erase {
+                filter,
+                rect_start: Some(rect_start),
+            } if !input_state.is_button_pressed(MouseButton::Left) => {
+                // Erase everything in the rectangle once it is released.
+                if let Some(mouse_grid_pos) = self.mouse_grid_pos {
+                    edit =
+                        Some(self.erase_edit(rect_positions(&rect_start, &mouse_grid_pos), filter));
+                }
+
+                Mode::new_erase(filter)
+            }
+            Mode::Erase {
+                filter,
+                rect_start: None,
+            } if input_state.is_button_pressed(MouseButton::Left) => {
+                if let Some(mouse_block_pos) = self.mouse_block_pos {
+                    // Blocks erased while dragging are undone together.
+                    self.begin_transaction();
+                    edit = Some(self.erase_edit(vec![mouse_block_pos], filter));
+                }
+
+                Mode::new_erase(filter)
+            }
+            Mode::Wall { start: Some(_) } if input_state.is_button_pressed(MouseButton::Right) => {
+                // Abort the box.
+                Mode::new_wall()
+            }
+            Mode::Wall { start: Some(start) }
+                if !input_state.is_button_pressed(MouseButton::Left) =>
+            {
+                // Build the walls once the box is released.
+                if let Some(mouse_grid_pos) = self.mouse_grid_pos {
+                    edit = Some(self.wall_edit(&start, &mouse_grid_pos));
+                }
+
+                Mode::new_wall()
+            }
+            Mode::RotateSelection { selection, .. }
+                if input_state.is_button_pressed(MouseButton::Right) =>
+            {
+                // Abort rotating.
+                Mode::new_selection(selection)
+            }
+            Mode::RotateSelection {
+                selection,
+                gizmo,
+                num_steps_cw,
+            } if input_state.is_button_pressed(MouseButton::Left) => {
+                // Follow the mouse with the handle.
+                let num_steps_cw = self
+                    .mouse_grid_pos
+                    .map_or(num_steps_cw, |pos| gizmo.num_steps_cw(&pos));
+
+                Mode::RotateSelection {
+                    selection,
+                    gizmo,
+                    num_steps_cw,
+                }
+            }
+            Mode::RotateSelection {
+                selection,
+                gizmo,
+                num_steps_cw,
+            } => {
+                // The handle has been released, so rotate the selected blocks.
+                self.rotate_selection(selection, gizmo.pivot, num_steps_cw)
+            }
             x => {
                 // No mode update.
                 x
@@ -447,7 +1042,33 @@ impl Editor {
         ui::Input {
             config: self.config.clone(),
             current_layer: self.current_layer,
+            layer_visibility: self.layer_visibility,
+            collision_policy: self.collision_policy,
+            quick_replace: self.quick_replace,
+            build_on_faces: self.build_on_faces,
+            auto_connect_pipes: self.auto_connect_pipes,
+            symmetry: self.symmetry,
+            symmetry_center: self.symmetry_center,
             mode: self.mode.clone(),
+            measurement: self.measurement,
+            tentative_measurement: self.tentative_measurement(),
+            solution_name: self.solution_name.clone(),
+            solution_names: self.solution_names.clone(),
+            blueprint_ids: self
+                .blueprints
+                .iter()
+                .map(|blueprint| blueprint.id.clone())
+                .collect(),
+            constraints: self
+                .machine
+                .level
+                .as_ref()
+                .map(|level| level.constraints.clone()),
+            stats: self.stats.clone(),
+            search: self.search.clone(),
+            comparison: self.comparison.clone(),
+            violation: self.violation.as_ref().map(ToString::to_string),
+            problems: self.problems.clone(),
         }
     }
 
@@ -472,6 +1093,11 @@ impl Editor {
     }
 
     fn on_key_press(&mut self, key: ModifiedKey) {
+        if self.search.is_some() && key == self.config.search_next_key {
+            self.action_search_next();
+            return;
+        }
+
         // Action shortcuts
         if key == self.config.undo_key {
             self.action_undo();
@@ -485,6 +1111,14 @@ impl Editor {
             self.action_layer_up();
         } else if key == self.config.layer_down_key {
             self.action_layer_down();
+        } else if key == self.config.layer_visibility_key {
+            self.action_next_layer_visibility();
+        } else if key == self.config.collision_policy_key {
+            self.action_next_collision_policy();
+        } else if key == self.config.symmetry_key {
+            self.action_next_symmetry();
+        } else if key == self.config.symmetry_center_key {
+            self.action_set_symmetry_center();
         } else if key == self.config.select_all_key {
             self.action_select_all();
         } else if key == self.config.select_key {
@@ -493,6 +1127,14 @@ impl Editor {
             self.action_select_layer_bound_mode();
         } else if key == self.config.pipe_tool_key {
             self.action_pipe_tool_mode();
+        } else if key == self.config.measure_key {
+            self.action_measure_mode();
+        } else if key == self.config.erase_key {
+            self.action_erase_mode();
+        } else if key == self.config.wall_key {
+            self.action_wall_mode();
+        } else if key == self.config.wall_selection_key {
+            self.action_wall_selection();
         } else if key == self.config.cancel_key {
             self.action_cancel();
         } else if key == self.config.cut_key {
@@ -509,6 +1151,12 @@ impl Editor {
             self.action_rotate_ccw();
         } else if key == self.config.mirror_y_key {
             self.action_mirror_y();
+        } else if key == self.config.pipette_key {
+            self.action_pipette();
+        } else if key == self.config.isolate_key {
+            self.action_isolate_selection();
+        } else if key == self.config.search_key {
+            self.action_open_search();
         }
 
         // Switch to specific layer
@@ -542,12 +1190,27 @@ impl Editor {
         button: glutin::MouseButton,
         modifiers: glutin::ModifiersState,
     ) {
+        if button == glutin::MouseButton::Middle && state == glutin::ElementState::Pressed {
+            self.action_pipette();
+            return;
+        }
+
         self.mode = match self.mode.clone() {
             Mode::Select { selection, .. }
                 if button == glutin::MouseButton::Left
                     && state == glutin::ElementState::Pressed =>
             {
-                self.on_left_mouse_click_select(input_state, modifiers, selection)
+                match self.rotate_gizmo(&selection) {
+                    Some(gizmo) if self.mouse_grid_pos == Some(gizmo.handle_pos(0)) => {
+                        // Clicked on the handle of the rotation gizmo.
+                        Mode::RotateSelection {
+                            selection,
+                            gizmo,
+                            num_steps_cw: 0,
+                        }
+                    }
+                    _ => self.on_left_mouse_click_select(input_state, modifiers, selection),
+                }
             }
             Mode::PipeTool { .. }
                 if button == glutin::MouseButton::Left
@@ -576,6 +1239,37 @@ impl Editor {
                     Mode::new_pipe_tool()
                 }
             }
+            Mode::Erase { filter, .. }
+                if button == glutin::MouseButton::Left
+                    && state == glutin::ElementState::Pressed
+                    && modifiers.shift =>
+            {
+                Mode::Erase {
+                    filter,
+                    rect_start: self.mouse_grid_pos,
+                }
+            }
+            Mode::Wall { .. }
+                if button == glutin::MouseButton::Left
+                    && state == glutin::ElementState::Pressed =>
+            {
+                Mode::Wall {
+                    start: self.mouse_grid_pos,
+                }
+            }
+            Mode::Measure { start }
+                if button == glutin::MouseButton::Left
+                    && state == glutin::ElementState::Pressed =>
+            {
+                match (start, self.mouse_measure_pos()) {
+                    (None, Some(pos)) => Mode::Measure { start: Some(pos) },
+                    (Some(start), Some(end)) => {
+                        self.measurement = Some(Measurement { start, end });
+                        Mode::new_measure()
+                    }
+                    (start, None) => Mode::Measure { start },
+                }
+            }
             x => x,
         }
     }
@@ -679,28 +1373,22 @@ impl Editor {
         }
     }
 
-    fn save(&self, path: &Path) {
-        info!("Saving current machine to file {:?}", path);
-
-        match File::create(path) {
-            Ok(file) => {
-                let saved_machine = SavedMachine::from_machine(&self.machine);
-                if let Err(err) = serde_json::to_writer_pretty(file, &saved_machine) {
-                    warn!(
-                        "Error while saving machine to file {:?}: {}",
-                        path.to_str(),
-                        err
-                    );
-                }
-            }
-            Err(err) => {
-                warn!(
-                    "Could not open file {:?} for writing: {}",
-                    path.to_str(),
-                    err
-                );
+    fn save_solution(&mut self) {
+        let path = solutions::path(
+            &self.config.solutions_dir,
+            self.machine.level.as_ref(),
+            &self.solution_name,
+        );
+        if let Some(level) = self.machine.level.as_ref() {
+            for violation in level.constraints.check(&self.machine) {
+                warn!("Saving machine that violates constraints: {}", violation);
             }
-        };
+        }
+
+        solutions::save(&self.machine, &path);
+
+        self.solution_names =
+            solutions::list(&self.config.solutions_dir, self.machine.level.as_ref());
     }
 
     fn pipe_tool_connect_pipe(
@@ -3,13 +3,30 @@ use nalgebra as na;
 
 use rendology::{basic_obj, BasicObj};
 
-use crate::edit::{Editor, Mode, Piece};
+use crate::edit::{CollisionPolicy, Editor, Measurement, Mode, Piece, RotateGizmo, Symmetry};
 use crate::exec::TickTime;
+use crate::machine::diff::ChangeKind;
 use crate::machine::{grid, Block, PlacedBlock};
 use crate::render::{self, Stage};
 
 pub const GRID_OFFSET_Z: f32 = 0.00;
 
+/// Alpha of blocks that have been changed in a transaction that has not been
+/// committed yet.
+pub const PREVIEW_ALPHA: f32 = 0.8;
+
+/// Alpha of the ghost of blocks that are about to be placed.
+pub const PLACE_GHOST_ALPHA: f32 = 0.7;
+
+/// Color that highlights blocks which differ from the compared solution.
+pub fn change_color(kind: ChangeKind) -> [f32; 4] {
+    match kind {
+        ChangeKind::Added => [0.2, 0.9, 0.2, 1.0],
+        ChangeKind::Removed => [1.0, 0.2, 0.2, 1.0],
+        ChangeKind::Changed => [1.0, 0.9, 0.1, 1.0],
+    }
+}
+
 impl Editor {
     pub fn render(&mut self, out: &mut Stage) {
         profile!("editor");
@@ -25,7 +42,11 @@ impl Editor {
             &mut out.solid,
         );
 
-        let filter = |pos| {
+        let filter = |pos: &grid::Point3| {
+            if !self.layer_visibility.is_shown(self.current_layer, pos.z) {
+                return false;
+            }
+
             // Don't render blocks that are going to be overwritten by the pipe
             // tool. Otherwise it may look a bit confusing if the same grid
             // position contains two different pipes.
@@ -36,14 +57,18 @@ impl Editor {
             }
         };
 
-        let unfocus = |pos: &grid::Point3| {
-            let tentative_die = if let Mode::DragAndDrop { selection, .. } = &self.mode {
-                selection.contains(pos)
-            } else {
-                false
-            };
+        let alpha = |pos: &grid::Point3| {
+            let tentative_die = self.is_tentative_die(pos);
 
-            tentative_die || !self.mode.impacts_layer(self.current_layer, pos.z)
+            if self.layer_visibility.is_ghosted(self.current_layer, pos.z) {
+                render::machine::GHOST_ALPHA
+            } else if tentative_die || !self.mode.impacts_layer(self.current_layer, pos.z) {
+                render::machine::UNFOCUS_ALPHA
+            } else if self.is_preview(pos) {
+                PREVIEW_ALPHA
+            } else {
+                1.0
+            }
         };
 
         render::machine::render_machine(
@@ -51,7 +76,10 @@ impl Editor {
             &TickTime::zero(),
             None,
             filter,
-            unfocus,
+            alpha,
+            |_| false,
+            |_| None,
+            &self.blip_style,
             out,
         );
 
@@ -65,6 +93,11 @@ impl Editor {
             Mode::Select { selection, .. } => {
                 self.render_selection(selection.iter(), out);
 
+                if let Some(gizmo) = self.rotate_gizmo(selection) {
+                    let is_hovered = self.mouse_grid_pos == Some(gizmo.handle_pos(0));
+                    self.render_rotate_gizmo(&gizmo, 0, is_hovered, out);
+                }
+
                 if let Some(mouse_block_pos) = self.mouse_block_pos {
                     self.render_block_wireframe(
                         &mouse_block_pos,
@@ -111,19 +144,65 @@ impl Editor {
                     ..Default::default()
                 });
             }
-            Mode::PlacePiece { piece, .. } => {
-                if let Some(mouse_grid_pos) = self.mouse_grid_pos {
-                    self.render_piece_to_place(piece, &mouse_grid_pos, out);
+            Mode::PlacePiece {
+                piece, is_paste, ..
+            } => {
+                if let Some(place_pos) = self.mouse_place_pos() {
+                    // Show only what is actually going to be pasted.
+                    let skip_occupied = *is_paste && self.collision_policy == CollisionPolicy::Skip;
+
+                    if !*is_paste && self.quick_replace {
+                        // Show the blocks in the orientation they are going
+                        // to be placed with.
+                        let mut placed = piece.clone();
+                        placed.shift(&place_pos.coords);
+
+                        self.render_piece_to_place(
+                            &placed.with_replacements(&self.machine),
+                            &grid::Point3::origin(),
+                            false,
+                            out,
+                        );
+                    } else {
+                        self.render_piece_to_place(piece, &place_pos, skip_occupied, out);
+                    }
+
+                    // Show the mirrored counterparts that are placed as well.
+                    for transform in self.symmetry.transforms(&self.symmetry_center) {
+                        let mut mirrored = piece.clone();
+                        mirrored.shift(&place_pos.coords);
+                        mirrored.transform(&transform);
+
+                        self.render_tentative_blocks(mirrored.iter(), true, out);
+                    }
                 }
             }
             Mode::DragAndDrop { piece, selection } => {
                 if let Some(mouse_grid_pos) = self.mouse_grid_pos {
-                    self.render_piece_to_place(&piece, &mouse_grid_pos, out);
+                    self.render_piece_to_place(&piece, &mouse_grid_pos, false, out);
 
                     //let selection: Vec<_> = piece.iter().map(|(pos, _)| *pos);
                     self.render_selection(selection.iter(), out);
                 }
             }
+            Mode::RotateSelection {
+                selection,
+                gizmo,
+                num_steps_cw,
+            } => {
+                self.render_selection(selection.iter(), out);
+                self.render_rotate_gizmo(gizmo, *num_steps_cw, true, out);
+
+                if *num_steps_cw % 4 != 0 {
+                    let mut piece =
+                        Piece::new_from_selection(&self.machine, selection.iter().cloned());
+                    for _ in 0..*num_steps_cw {
+                        piece.rotate_cw_xy_around(&gizmo.pivot);
+                    }
+
+                    self.render_tentative_blocks(piece.iter(), true, out);
+                }
+            }
             Mode::PipeTool {
                 last_pos, blocks, ..
             } => {
@@ -171,6 +250,7 @@ impl Editor {
                             &block_center,
                             &block_transform,
                             0.5,
+                            &self.blip_style,
                             out,
                         );
                         out.dither = false;
@@ -217,9 +297,221 @@ impl Editor {
                     );
                 }
             }
+            Mode::Measure { .. } => {
+                if let Some(measurement) = self.tentative_measurement() {
+                    self.render_measurement(
+                        &measurement,
+                        &na::Vector4::new(0.6, 0.9, 1.0, 1.0),
+                        out,
+                    );
+                } else if let Some(mouse_pos) = self.mouse_measure_pos() {
+                    self.render_block_wireframe(
+                        &mouse_pos,
+                        1.0,
+                        20.0,
+                        &na::Vector4::new(0.6, 0.9, 1.0, 1.0),
+                        out,
+                    );
+                }
+            }
+            Mode::Erase { rect_start, .. } => {
+                let color = na::Vector4::new(0.9, 0.2, 0.2, 1.0);
+
+                for pos in self.tentative_erase_positions() {
+                    self.render_block_wireframe(&pos, 1.0, 20.0, &color, out);
+                }
+
+                if let (Some(rect_start), Some(mouse_grid_pos)) = (rect_start, self.mouse_grid_pos)
+                {
+                    for pos in &[*rect_start, mouse_grid_pos] {
+                        self.render_block_wireframe(pos, 0.7, 7.0, &color, out);
+                    }
+                }
+            }
+            Mode::Wall { start } => {
+                let color = na::Vector4::new(0.8, 0.8, 0.8, 1.0);
+
+                self.render_tentative_blocks(
+                    self.tentative_wall_positions().into_iter().map(|pos| {
+                        (
+                            pos,
+                            PlacedBlock {
+                                block: Block::Solid,
+                            },
+                        )
+                    }),
+                    false,
+                    out,
+                );
+
+                let corners = start.iter().cloned().chain(self.mouse_grid_pos);
+                for pos in corners {
+                    self.render_block_wireframe(&pos, 1.0, 20.0, &color, out);
+                }
+            }
+        }
+
+        if let Some(measurement) = self.measurement.as_ref() {
+            self.render_measurement(measurement, &na::Vector4::new(0.0, 0.7, 1.0, 1.0), out);
+        }
+
+        self.render_symmetry(out);
+        self.render_comparison(out);
+    }
+
+    /// Highlights the blocks that differ from the compared solution. Removed
+    /// blocks are only outlined, since they are not part of the machine.
+    fn render_comparison(&self, out: &mut Stage) {
+        let comparison = match self.comparison.as_ref() {
+            Some(comparison) => comparison,
+            None => return,
+        };
+
+        for change in comparison.changes.iter() {
+            if !self
+                .layer_visibility
+                .is_shown(self.current_layer, change.pos.z)
+            {
+                continue;
+            }
+
+            let color = change_color(change.kind());
+            let color = na::Vector4::new(color[0], color[1], color[2], color[3]);
+
+            self.render_block_wireframe(&change.pos, 1.0, 12.0, &color, out);
         }
     }
 
+    /// Shows the axes that placements are mirrored at in the current layer.
+    fn render_symmetry(&self, out: &mut Stage) {
+        let (mirror_x, mirror_y) = match self.symmetry {
+            Symmetry::Off => return,
+            Symmetry::X => (true, false),
+            Symmetry::Y => (false, true),
+            Symmetry::XY => (true, true),
+        };
+
+        let grid_size: na::Vector3<f32> = na::convert(self.machine.size());
+        let center: na::Point3<f32> = na::convert(self.symmetry_center);
+        let z = self.current_layer as f32 + 0.5 + GRID_OFFSET_Z;
+        let color = na::Vector4::new(0.3, 0.6, 1.0, 1.0);
+
+        if mirror_x {
+            render::machine::render_cuboid_wireframe(
+                &render::machine::Cuboid {
+                    center: na::Point3::new(center.x + 0.5, grid_size.y / 2.0, z),
+                    size: na::Vector3::new(1.0, grid_size.y, 1.0),
+                },
+                0.05,
+                &color,
+                &mut out.solid,
+            );
+        }
+
+        if mirror_y {
+            render::machine::render_cuboid_wireframe(
+                &render::machine::Cuboid {
+                    center: na::Point3::new(grid_size.x / 2.0, center.y + 0.5, z),
+                    size: na::Vector3::new(grid_size.x, 1.0, 1.0),
+                },
+                0.05,
+                &color,
+                &mut out.solid,
+            );
+        }
+    }
+
+    /// Returns true if the block at `pos` is about to be moved away, e.g. by
+    /// dragging or rotating the selection.
+    fn is_tentative_die(&self, pos: &grid::Point3) -> bool {
+        match &self.mode {
+            Mode::DragAndDrop { selection, .. } => selection.contains(pos),
+            Mode::RotateSelection {
+                selection,
+                num_steps_cw,
+                ..
+            } => *num_steps_cw % 4 != 0 && selection.contains(pos),
+            _ => false,
+        }
+    }
+
+    /// Shows a ring around the pivot of the gizmo, with a handle that can be
+    /// dragged around the ring to rotate the selection.
+    fn render_rotate_gizmo(
+        &self,
+        gizmo: &RotateGizmo,
+        num_steps_cw: usize,
+        is_active: bool,
+        out: &mut Stage,
+    ) {
+        const NUM_SEGMENTS: usize = 48;
+
+        let center = render::machine::block_center(&gizmo.pivot);
+        let radius = gizmo.radius as f32;
+        let color = na::Vector4::new(0.9, 0.8, 0.2, 1.0);
+
+        let ring_point = |i: usize| {
+            let angle = i as f32 / NUM_SEGMENTS as f32 * 2.0 * std::f32::consts::PI;
+            center + radius * na::Vector3::new(angle.cos(), angle.sin(), 0.0)
+        };
+
+        for i in 0..NUM_SEGMENTS {
+            render::machine::render_line(
+                &render::machine::Line {
+                    start: ring_point(i),
+                    end: ring_point(i + 1),
+                    roll: 0.0,
+                    thickness: 0.05,
+                    color,
+                },
+                &na::Matrix4::identity(),
+                &mut out.plain,
+            );
+        }
+
+        let handle_color = if is_active {
+            na::Vector4::new(1.0, 0.95, 0.6, 1.0)
+        } else {
+            color
+        };
+        let handle_center = render::machine::block_center(&gizmo.handle_pos(num_steps_cw));
+        let transform =
+            na::Matrix4::new_translation(&handle_center.coords) * na::Matrix4::new_scaling(0.4);
+
+        out.plain[BasicObj::Cube].add(basic_obj::Instance {
+            transform,
+            color: handle_color,
+            ..Default::default()
+        });
+    }
+
+    fn render_measurement(
+        &self,
+        measurement: &Measurement,
+        color: &na::Vector4<f32>,
+        out: &mut Stage,
+    ) {
+        for pos in &[measurement.start, measurement.end] {
+            self.render_block_wireframe(pos, 1.0, 20.0, color, out);
+        }
+
+        if measurement.start == measurement.end {
+            return;
+        }
+
+        render::machine::render_line(
+            &render::machine::Line {
+                start: render::machine::block_center(&measurement.start),
+                end: render::machine::block_center(&measurement.end),
+                roll: 0.0,
+                thickness: 0.04,
+                color: *color,
+            },
+            &na::Matrix4::identity(),
+            &mut out.solid,
+        );
+    }
+
     fn render_selection<'a>(
         &self,
         selection: impl Iterator<Item = &'a grid::Point3>,
@@ -238,12 +530,48 @@ impl Editor {
         show_invalid: bool,
         out: &mut Stage,
     ) -> bool {
+        let blocks: Vec<_> = blocks
+            .map(|(pos, placed_block)| {
+                let is_valid = self.machine.is_valid_pos(&pos);
+                let tentative_die = self.is_tentative_die(&pos);
+                let can_place = tentative_die || !self.machine.is_block_at(&pos);
+                let can_combine = !tentative_die
+                    && self.machine.get(&pos).map_or(false, |old_placed_block| {
+                        old_placed_block
+                            .block
+                            .combine(&placed_block.block)
+                            .is_some()
+                    });
+
+                (pos, placed_block, is_valid, can_place, can_combine)
+            })
+            .collect();
+
+        // The whole piece is tinted by whether it can be placed as it is, so
+        // that invalid placements are noticed before clicking.
+        let all_can_place = blocks
+            .iter()
+            .all(|(_, _, is_valid, can_place, can_combine)| {
+                *is_valid && (*can_place || *can_combine)
+            });
+        let tint = if all_can_place {
+            na::Vector3::new(0.2, 0.9, 0.3)
+        } else {
+            na::Vector3::new(0.9, 0.15, 0.1)
+        };
+
         let mut any_pos_valid = false;
+        let mut ghost_stage = Stage::default();
 
-        for (pos, placed_block) in blocks {
+        for (pos, placed_block, is_valid, can_place, can_combine) in blocks {
             let block_center = render::machine::block_center(&pos);
             let block_transform = render::machine::placed_block_transform(&placed_block);
 
+            // Render into a separate stage first, so that the block can be
+            // tinted as a whole.
+            ghost_stage.clear();
+            ghost_stage.dither = true;
+
             render::machine::render_block(
                 &placed_block,
                 &TickTime::zero(),
@@ -252,26 +580,14 @@ impl Editor {
                 None,
                 &block_center,
                 &block_transform,
-                0.8,
-                out,
+                PLACE_GHOST_ALPHA,
+                &self.blip_style,
+                &mut ghost_stage,
             );
 
-            // TODO: Render tentative blocks as non-shadowed?
+            render::machine::add_tinted(&mut ghost_stage, &tint, out);
 
-            let is_valid = self.machine.is_valid_pos(&pos);
-            let tentative_die = if let Mode::DragAndDrop { selection, .. } = &self.mode {
-                selection.contains(&pos)
-            } else {
-                false
-            };
-            let can_place = tentative_die || !self.machine.is_block_at(&pos);
-            let can_combine = !tentative_die
-                && self.machine.get(&pos).map_or(false, |old_placed_block| {
-                    old_placed_block
-                        .block
-                        .combine(&placed_block.block)
-                        .is_some()
-                });
+            // TODO: Render tentative blocks as non-shadowed?
 
             if show_invalid {
                 if !is_valid || (!can_place && !can_combine) {
@@ -340,10 +656,17 @@ impl Editor {
         );
     }
 
-    fn render_piece_to_place(&self, piece: &Piece, piece_pos: &grid::Point3, out: &mut Stage) {
+    fn render_piece_to_place(
+        &self,
+        piece: &Piece,
+        piece_pos: &grid::Point3,
+        skip_occupied: bool,
+        out: &mut Stage,
+    ) {
         let blocks = piece
             .iter()
-            .map(|(pos, block)| (pos + piece_pos.coords, block));
+            .map(|(pos, block)| (pos + piece_pos.coords, block))
+            .filter(|(pos, _)| !skip_occupied || !self.machine.is_block_at(pos));
         let any_pos_valid = self.render_tentative_blocks(blocks, true, out);
 
         // Show how far above zero the piece is.
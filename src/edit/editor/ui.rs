@@ -1,18 +1,61 @@
 use imgui::{im_str, ImString};
 
+use rendology::Camera;
+
+use crate::edit::compare::Comparison;
 use crate::edit::editor::action::Action;
+use crate::edit::editor::render::change_color;
+use crate::edit::search::Search;
 use crate::edit::Config;
-use crate::edit::Mode;
+use crate::edit::{CollisionPolicy, LayerVisibility, Measurement, Mode, Symmetry};
+use crate::locale::Locale;
+use crate::machine::diff::ChangeKind;
+use crate::machine::level::Constraints;
+use crate::machine::registry::{self, Category};
+use crate::machine::stats::Stats;
+use crate::machine::validate::{Problem, Severity};
+use crate::machine::{grid, Block, BlockType};
+use crate::render;
 
 const BUTTON_H: f32 = 25.0;
 const BUTTON_W: f32 = 66.25;
 const BG_ALPHA: f32 = 0.8;
 
+/// Search results beyond this number are not listed, but can still be cycled
+/// through.
+const MAX_SEARCH_RESULTS: usize = 100;
+
 #[derive(Clone, Debug)]
 pub struct Input {
     pub config: Config,
     pub current_layer: isize,
+    pub layer_visibility: LayerVisibility,
+    pub collision_policy: CollisionPolicy,
+    pub quick_replace: bool,
+    pub build_on_faces: bool,
+    pub auto_connect_pipes: bool,
+    pub symmetry: Symmetry,
+    pub symmetry_center: grid::Point3,
     pub mode: Mode,
+    pub measurement: Option<Measurement>,
+    pub tentative_measurement: Option<Measurement>,
+    pub solution_name: String,
+    pub solution_names: Vec<String>,
+    pub blueprint_ids: Vec<String>,
+    pub constraints: Option<Constraints>,
+    pub stats: Stats,
+    pub search: Option<Search>,
+    pub comparison: Option<Comparison>,
+    pub violation: Option<String>,
+    pub problems: Vec<Problem>,
+}
+
+impl Input {
+    fn is_block_allowed(&self, block: &Block) -> bool {
+        self.constraints
+            .as_ref()
+            .map_or(true, |constraints| constraints.is_block_allowed(block))
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -20,7 +63,40 @@ pub struct Output {
     pub actions: Vec<Action>,
 }
 
-pub fn run(input: &Input, ui: &imgui::Ui, output: &mut Output) {
+/// UI state that needs to persist between frames in the main thread.
+#[derive(Clone, Debug)]
+pub struct State {
+    pub new_solution_name: ImString,
+    pub search_query: ImString,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            new_solution_name: ImString::with_capacity(64),
+            search_query: ImString::with_capacity(64),
+        }
+    }
+}
+
+pub fn run(
+    input: &Input,
+    camera: &Camera,
+    locale: &Locale,
+    ui: &imgui::Ui,
+    state: &mut State,
+    output: &mut Output,
+) {
+    if let Some(measurement) = input.tentative_measurement.as_ref() {
+        ui_measurement(measurement, false, camera, ui, output);
+    } else if let Some(measurement) = input.measurement.as_ref() {
+        ui_measurement(measurement, true, camera, ui, output);
+    }
+
+    if let Some(search) = input.search.as_ref() {
+        ui_search(input, search, ui, state, output);
+    }
+
     imgui::Window::new(im_str!("Editor"))
         .horizontal_scrollbar(true)
         .always_auto_resize(true)
@@ -30,29 +106,309 @@ pub fn run(input: &Input, ui: &imgui::Ui, output: &mut Output) {
         .content_size([200.0, 0.0])
         .collapsible(false)
         .build(&ui, || {
-            imgui::TreeNode::new(ui, im_str!("Layer"))
+            if let Some(violation) = input.violation.as_ref() {
+                ui.text_colored([1.0, 0.3, 0.3, 1.0], &ImString::new(violation));
+            }
+            if input
+                .constraints
+                .as_ref()
+                .map_or(false, |constraints| *constraints != Constraints::default())
+            {
+                imgui::TreeNode::new(
+                    ui,
+                    &ImString::new(locale.label("ui.editor.constraints", "Constraints")),
+                )
                 .opened(true, imgui::Condition::FirstUseEver)
                 .build(|| {
-                    ui_layers(&input, ui, output);
+                    ui_constraints(&input, ui);
                 });
-            imgui::TreeNode::new(ui, im_str!("Modes"))
+            }
+            if !input.problems.is_empty() {
+                imgui::TreeNode::new(
+                    ui,
+                    &ImString::new(locale.label("ui.editor.problems", "Problems")),
+                )
                 .opened(true, imgui::Condition::FirstUseEver)
                 .build(|| {
-                    ui_modes(&input, ui, output);
+                    ui_problems(&input, ui, output);
                 });
-            imgui::TreeNode::new(ui, im_str!("Blocks"))
+            }
+            imgui::TreeNode::new(ui, &ImString::new(locale.label("ui.editor.layer", "Layer")))
                 .opened(true, imgui::Condition::FirstUseEver)
                 .build(|| {
-                    ui_blocks(&input, ui, output);
+                    ui_layers(&input, ui, output);
                 });
-            imgui::TreeNode::new(ui, im_str!("Actions"))
+            imgui::TreeNode::new(
+                ui,
+                &ImString::new(locale.label("ui.editor.symmetry", "Symmetry")),
+            )
+            .opened(false, imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui_symmetry(&input, ui, output);
+            });
+            if let Mode::PlacePiece { is_paste: true, .. } = &input.mode {
+                imgui::TreeNode::new(ui, &ImString::new(locale.label("ui.editor.paste", "Paste")))
+                    .opened(true, imgui::Condition::FirstUseEver)
+                    .build(|| {
+                        ui_paste(&input, ui, output);
+                    });
+            }
+            imgui::TreeNode::new(ui, &ImString::new(locale.label("ui.editor.modes", "Modes")))
                 .opened(true, imgui::Condition::FirstUseEver)
                 .build(|| {
-                    ui_actions(&input, ui, output);
+                    ui_modes(&input, ui, output);
+                });
+            imgui::TreeNode::new(
+                ui,
+                &ImString::new(locale.label("ui.editor.blocks", "Blocks")),
+            )
+            .opened(true, imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui_blocks(&input, locale, ui, output);
+            });
+            if !input.blueprint_ids.is_empty() {
+                imgui::TreeNode::new(
+                    ui,
+                    &ImString::new(locale.label("ui.editor.blueprints", "Blueprints")),
+                )
+                .opened(false, imgui::Condition::FirstUseEver)
+                .build(|| {
+                    ui_blueprints(&input, ui, output);
+                });
+            }
+            imgui::TreeNode::new(
+                ui,
+                &ImString::new(locale.label("ui.editor.actions", "Actions")),
+            )
+            .opened(true, imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui_actions(&input, ui, output);
+            });
+            imgui::TreeNode::new(
+                ui,
+                &ImString::new(locale.label("ui.editor.statistics", "Statistics")),
+            )
+            .opened(false, imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui_stats(&input.stats, locale, ui);
+            });
+            imgui::TreeNode::new(
+                ui,
+                &ImString::new(locale.label("ui.editor.solutions", "Solutions")),
+            )
+            .opened(false, imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui_solutions(&input, ui, state, output);
+            });
+            if !input.solution_names.is_empty() {
+                imgui::TreeNode::new(
+                    ui,
+                    &ImString::new(locale.label("ui.editor.compare", "Compare")),
+                )
+                .opened(false, imgui::Condition::FirstUseEver)
+                .build(|| {
+                    ui_compare(&input, ui, output);
                 });
+            }
+        });
+}
+
+/// Shows the distances of a measurement next to the middle of the measured
+/// line.
+fn ui_measurement(
+    measurement: &Measurement,
+    dismissable: bool,
+    camera: &Camera,
+    ui: &imgui::Ui,
+    output: &mut Output,
+) {
+    let start = render::machine::block_center(&measurement.start);
+    let end = render::machine::block_center(&measurement.end);
+    let middle = start + (end - start) / 2.0;
+    let pos = camera.project_to_viewport(&middle);
+
+    // The camera's viewport is in physical pixels, while imgui positions are
+    // scaled down on high-DPI displays.
+    let scale = ui.io().display_framebuffer_scale;
+
+    imgui::Window::new(im_str!("Measurement"))
+        .position(
+            [pos.x / scale[0] + 10.0, pos.y / scale[1] + 10.0],
+            imgui::Condition::Always,
+        )
+        .title_bar(false)
+        .always_auto_resize(true)
+        .movable(false)
+        .resizable(false)
+        .bg_alpha(BG_ALPHA)
+        .build(&ui, || {
+            ui.text(&ImString::new(measurement.label()));
+
+            if dismissable && ui.small_button(im_str!("Dismiss")) {
+                output.actions.push(Action::DismissMeasurement);
+            }
         });
 }
 
+fn ui_search(
+    input: &Input,
+    search: &Search,
+    ui: &imgui::Ui,
+    state: &mut State,
+    output: &mut Output,
+) {
+    imgui::Window::new(im_str!("Search"))
+        .position(
+            [ui.io().display_size[0] / 2.0, 10.0],
+            imgui::Condition::FirstUseEver,
+        )
+        .position_pivot([0.5, 0.0])
+        .always_auto_resize(true)
+        .collapsible(false)
+        .bg_alpha(BG_ALPHA)
+        .build(&ui, || {
+            ui.input_text(im_str!("Block name"), &mut state.search_query)
+                .build();
+
+            // Also runs the search when it is reopened with the previous
+            // query still in the text box.
+            let query = state.search_query.to_str();
+            if query != search.query {
+                output.actions.push(Action::Search(query.to_string()));
+            }
+
+            for (index, pos) in search.results.iter().enumerate().take(MAX_SEARCH_RESULTS) {
+                let label = format!("{}, {}, {}##search{}", pos.x, pos.y, pos.z, index);
+                let selectable = imgui::Selectable::new(&ImString::new(label))
+                    .selected(search.current == Some(index));
+
+                if selectable.build(ui) {
+                    output.actions.push(Action::SearchSelect(index));
+                }
+            }
+
+            if search.results.len() > MAX_SEARCH_RESULTS {
+                ui.text_disabled(&ImString::new(format!(
+                    "... and {} more",
+                    search.results.len() - MAX_SEARCH_RESULTS
+                )));
+            }
+            if !search.query.trim().is_empty() && search.results.is_empty() {
+                ui.text_disabled(im_str!("No blocks found."));
+            }
+
+            ui.separator();
+
+            if ui.button(im_str!("Next"), [BUTTON_W, BUTTON_H]) {
+                output.actions.push(Action::SearchNext);
+            }
+            if ui.is_item_hovered() {
+                let text = format!(
+                    "Show the next block that was found.\n\nShortcut: {}",
+                    input.config.search_next_key
+                );
+                ui.tooltip(|| ui.text(&ImString::new(text)));
+            }
+
+            ui.same_line(0.0);
+
+            if ui.button(im_str!("Close"), [BUTTON_W, BUTTON_H]) {
+                output.actions.push(Action::CloseSearch);
+            }
+        });
+}
+
+fn ui_constraints(input: &Input, ui: &imgui::Ui) {
+    let constraints = match input.constraints.as_ref() {
+        Some(constraints) => constraints,
+        None => return,
+    };
+
+    if let Some(max) = constraints.max_num_blocks {
+        ui.text(&ImString::new(format!(
+            "Blocks: {} / {}",
+            input.stats.num_blocks, max
+        )));
+    }
+    if let Some(max) = constraints.max_footprint {
+        ui.text(&ImString::new(format!(
+            "Footprint: {} / {}",
+            input.stats.footprint, max
+        )));
+    }
+    if !constraints.forbidden_regions.is_empty() {
+        ui.text(&ImString::new(format!(
+            "Forbidden regions: {}",
+            constraints.forbidden_regions.len()
+        )));
+    }
+}
+
+fn ui_stats(stats: &Stats, locale: &Locale, ui: &imgui::Ui) {
+    ui.text(&ImString::new(format!("Blocks: {}", stats.num_blocks)));
+    ui.text(&ImString::new(format!("Footprint: {}", stats.footprint)));
+    ui.text(&ImString::new(format!(
+        "Inputs / outputs: {} / {}",
+        stats.num_inputs, stats.num_outputs
+    )));
+    ui.text(&ImString::new(format!(
+        "Estimated tick cost: {}",
+        stats.tick_cost
+    )));
+    if ui.is_item_hovered() {
+        ui.tooltip(|| {
+            ui.text(im_str!(
+                "Number of blocks that are simulated in every tick."
+            ))
+        });
+    }
+
+    if !stats.blocks_by_type.is_empty() {
+        ui.separator();
+
+        for (block_type, count) in stats.blocks_by_type.iter() {
+            let name = locale.block_name(&block_type.example());
+            ui.text(&ImString::new(format!("{}: {}", name, count)));
+        }
+    }
+
+    ui.separator();
+
+    for (layer, count) in stats.blocks_per_layer.iter().enumerate() {
+        ui.text(&ImString::new(format!(
+            "Layer {}: {} blocks ({:.0}%)",
+            layer,
+            count,
+            stats.layer_utilization(layer) * 100.0
+        )));
+    }
+}
+
+fn ui_problems(input: &Input, ui: &imgui::Ui, output: &mut Output) {
+    for (i, problem) in input.problems.iter().enumerate() {
+        let color = match problem.severity() {
+            Severity::Warning => [1.0, 0.8, 0.3, 1.0],
+            Severity::Error => [1.0, 0.3, 0.3, 1.0],
+        };
+        ui.text_colored(color, im_str!("\u{25A0}"));
+        ui.same_line(0.0);
+
+        let label = format!("{}##problem{}", problem, i);
+        if imgui::Selectable::new(&ImString::new(label)).build(ui) {
+            if let Some(pos) = problem.pos {
+                output.actions.push(Action::FocusOn(pos));
+            }
+        }
+        if let Some(pos) = problem.pos.filter(|_| ui.is_item_hovered()) {
+            let text = format!(
+                "Click to show the block at {}, {}, {}.",
+                pos.x, pos.y, pos.z
+            );
+            ui.tooltip(|| ui.text(&ImString::new(text)));
+        }
+    }
+}
+
 fn ui_layers(input: &Input, ui: &imgui::Ui, output: &mut Output) {
     ui.text(&ImString::new(input.current_layer.to_string()));
     ui.same_line_with_spacing(0.0, 20.0);
@@ -78,6 +434,67 @@ fn ui_layers(input: &Input, ui: &imgui::Ui, output: &mut Output) {
         let text = format!("Go up a layer.\n\nShortcut: {}", input.config.layer_up_key);
         ui.tooltip(|| ui.text(&ImString::new(text)));
     }
+
+    for &visibility in LayerVisibility::ALL {
+        let selectable = imgui::Selectable::new(&ImString::new(visibility.name()))
+            .selected(input.layer_visibility == visibility);
+        if selectable.build(ui) {
+            output.actions.push(Action::SetLayerVisibility(visibility));
+        }
+        if ui.is_item_hovered() {
+            let text = format!(
+                "Change how other layers are shown.\n\nShortcut to cycle: {}",
+                input.config.layer_visibility_key
+            );
+            ui.tooltip(|| ui.text(&ImString::new(text)));
+        }
+    }
+}
+
+fn ui_symmetry(input: &Input, ui: &imgui::Ui, output: &mut Output) {
+    for &symmetry in Symmetry::ALL {
+        let selectable = imgui::Selectable::new(&ImString::new(symmetry.name()))
+            .selected(input.symmetry == symmetry);
+        if selectable.build(ui) {
+            output.actions.push(Action::SetSymmetry(symmetry));
+        }
+        if ui.is_item_hovered() {
+            let text = format!(
+                "Change how placed and removed blocks are mirrored.\n\nShortcut to cycle: {}",
+                input.config.symmetry_key
+            );
+            ui.tooltip(|| ui.text(&ImString::new(text)));
+        }
+    }
+
+    ui.text_disabled(&ImString::new(format!(
+        "Center: ({}, {})",
+        input.symmetry_center.x, input.symmetry_center.y,
+    )));
+    if ui.is_item_hovered() {
+        let text = format!(
+            "Placements are mirrored at this position.\n\nShortcut to move to the mouse: {}",
+            input.config.symmetry_center_key
+        );
+        ui.tooltip(|| ui.text(&ImString::new(text)));
+    }
+}
+
+fn ui_paste(input: &Input, ui: &imgui::Ui, output: &mut Output) {
+    for &policy in CollisionPolicy::ALL {
+        let selectable = imgui::Selectable::new(&ImString::new(policy.name()))
+            .selected(input.collision_policy == policy);
+        if selectable.build(ui) {
+            output.actions.push(Action::SetCollisionPolicy(policy));
+        }
+        if ui.is_item_hovered() {
+            let text = format!(
+                "Change what happens to blocks that are in the way when pasting.\n\nShortcut to cycle: {}",
+                input.config.collision_policy_key
+            );
+            ui.tooltip(|| ui.text(&ImString::new(text)));
+        }
+    }
 }
 
 fn ui_modes(input: &Input, ui: &imgui::Ui, output: &mut Output) {
@@ -91,6 +508,7 @@ fn ui_modes(input: &Input, ui: &imgui::Ui, output: &mut Output) {
             existing_selection, ..
         } => Some(existing_selection),
         Mode::DragAndDrop { selection, .. } => Some(selection),
+        Mode::RotateSelection { selection, .. } => Some(selection),
         _ => None,
     };
 
@@ -136,7 +554,9 @@ fn ui_modes(input: &Input, ui: &imgui::Ui, output: &mut Output) {
         Mode::PipeTool { .. } => true,
         _ => false,
     };
-    let selectable = imgui::Selectable::new(im_str!("Place pipes")).selected(selected);
+    let selectable = imgui::Selectable::new(im_str!("Place pipes"))
+        .selected(selected)
+        .disabled(!input.is_block_allowed(&Block::GeneralPipe(Default::default())));
     if selectable.build(ui) {
         output.actions.push(Action::PipeToolMode);
     }
@@ -147,11 +567,95 @@ fn ui_modes(input: &Input, ui: &imgui::Ui, output: &mut Output) {
         );
         ui.tooltip(|| ui.text(&ImString::new(text)));
     }
+    ui.next_column();
+
+    ui.text_disabled(&ImString::new(format!("{}", input.config.measure_key)));
+    ui.next_column();
+
+    let selected = match &input.mode {
+        Mode::Measure { .. } => true,
+        _ => false,
+    };
+    let selectable = imgui::Selectable::new(im_str!("Measure")).selected(selected);
+    if selectable.build(ui) {
+        output.actions.push(Action::MeasureMode);
+    }
+    if ui.is_item_hovered() {
+        let text = format!(
+            "Click two positions to measure their distance. Blips need at least as many ticks as the Manhattan distance.\n\nShortcut: {}",
+            input.config.measure_key
+        );
+        ui.tooltip(|| ui.text(&ImString::new(text)));
+    }
+    ui.next_column();
+
+    ui.text_disabled(&ImString::new(format!("{}", input.config.wall_key)));
+    ui.next_column();
+
+    let selected = match &input.mode {
+        Mode::Wall { .. } => true,
+        _ => false,
+    };
+    let selectable = imgui::Selectable::new(im_str!("Build walls"))
+        .selected(selected)
+        .disabled(!input.is_block_allowed(&Block::Solid));
+    if selectable.build(ui) {
+        output.actions.push(Action::WallMode);
+    }
+    if ui.is_item_hovered() {
+        let text = format!(
+            "Drag out a box to surround it with solid walls, e.g. for containing stray blips. Change the layer while dragging to make the box higher.\n\nShortcut: {}",
+            input.config.wall_key
+        );
+        ui.tooltip(|| ui.text(&ImString::new(text)));
+    }
+    ui.next_column();
+
+    ui.text_disabled(&ImString::new(format!("{}", input.config.erase_key)));
+    ui.next_column();
+
+    let erase_filter = match &input.mode {
+        Mode::Erase { filter, .. } => Some(*filter),
+        _ => None,
+    };
+    let selectable = imgui::Selectable::new(im_str!("Erase")).selected(erase_filter.is_some());
+    if selectable.build(ui) {
+        output.actions.push(Action::EraseMode);
+    }
+    if ui.is_item_hovered() {
+        let text = format!(
+            "Drag over blocks to erase them. Hold shift when starting to drag to erase a rectangle in the current layer.\n\nShortcut: {}",
+            input.config.erase_key
+        );
+        ui.tooltip(|| ui.text(&ImString::new(text)));
+    }
+
+    if let Some(erase_filter) = erase_filter {
+        ui.next_column();
+        ui.next_column();
+
+        let mut filters = vec![None];
+        filters.extend(
+            BlockType::ALL
+                .iter()
+                .filter(|block_type| registry::get(**block_type).is_player_block)
+                .map(|block_type| Some(*block_type)),
+        );
+
+        for filter in filters {
+            let name = filter.map_or("All blocks", |block_type| registry::get(block_type).name);
+            let selectable = imgui::Selectable::new(&ImString::new(format!("  {}", name)))
+                .selected(filter == erase_filter);
+            if selectable.build(ui) {
+                output.actions.push(Action::SetEraseFilter(filter));
+            }
+        }
+    }
 
     ui.columns(1, im_str!("ui_modes_end"), false);
 }
 
-fn ui_blocks(input: &Input, ui: &imgui::Ui, output: &mut Output) {
+fn ui_blocks(input: &Input, locale: &Locale, ui: &imgui::Ui, output: &mut Output) {
     ui.columns(2, im_str!("ui_blocks"), false);
     ui.set_column_width(0, 50.0);
 
@@ -160,26 +664,102 @@ fn ui_blocks(input: &Input, ui: &imgui::Ui, output: &mut Output) {
         _ => None,
     };
 
-    for (block_key, block) in input.config.block_keys.clone().iter() {
+    // List the blocks grouped by their category in the registry, keeping the
+    // order of the configured keys within each category.
+    let mut block_keys = input.config.block_keys.clone();
+    block_keys.sort_by_key(|(_, block)| {
+        let category = registry::get(block.block_type()).category;
+        Category::ALL.iter().position(|c| *c == category)
+    });
+
+    let mut cur_category = None;
+
+    for (block_key, block) in block_keys.iter() {
+        let category = registry::get(block.block_type()).category;
+        if cur_category != Some(category) {
+            cur_category = Some(category);
+
+            ui.next_column();
+            ui.text_disabled(&ImString::new(category.name()));
+            ui.next_column();
+        }
+
         ui.text_disabled(&ImString::new(format!("{}", block_key)));
         ui.next_column();
 
-        let name = &ImString::new(block.name());
+        let name = &ImString::new(locale.block_name(block));
         let selected = cur_block
             .as_ref()
             .map_or(false, |(_, placed_block)| placed_block.block == *block);
-        let selectable = imgui::Selectable::new(name).selected(selected);
+        let allowed = input.is_block_allowed(block);
+        let selectable = imgui::Selectable::new(name)
+            .selected(selected)
+            .disabled(!allowed);
         if selectable.build(ui) {
             output.actions.push(Action::PlaceBlockMode(block.clone()));
         }
         if ui.is_item_hovered() {
-            let text = format!("{}\n\nShortcut: {}", block.description(), block_key);
+            let description = locale.block_description(block);
+            let text = if allowed {
+                locale.format(
+                    "ui.editor.block_shortcut",
+                    "{description}\n\nShortcut: {key}",
+                    &[
+                        ("description", description),
+                        ("key", &block_key.to_string()),
+                    ],
+                )
+            } else {
+                locale.format(
+                    "ui.editor.block_not_allowed",
+                    "{description}\n\nThis block is not allowed in the current level.",
+                    &[("description", description)],
+                )
+            };
             ui.tooltip(|| ui.text(&ImString::new(text)));
         }
         ui.next_column();
     }
 
     ui.columns(1, im_str!("ui_blocks_end"), false);
+
+    let mut quick_replace = input.quick_replace;
+    if ui.checkbox(im_str!("Keep orientation"), &mut quick_replace) {
+        output.actions.push(Action::SetQuickReplace(quick_replace));
+    }
+    if ui.is_item_hovered() {
+        ui.tooltip(|| {
+            ui.text(im_str!(
+                "Orient blocks placed over different blocks like the blocks they replace."
+            ))
+        });
+    }
+
+    let mut build_on_faces = input.build_on_faces;
+    if ui.checkbox(im_str!("Build on faces"), &mut build_on_faces) {
+        output.actions.push(Action::SetBuildOnFaces(build_on_faces));
+    }
+    if ui.is_item_hovered() {
+        ui.tooltip(|| {
+            ui.text(im_str!(
+                "Place blocks next to the side of the block you click on, even in other layers."
+            ))
+        });
+    }
+
+    let mut auto_connect_pipes = input.auto_connect_pipes;
+    if ui.checkbox(im_str!("Connect pipes"), &mut auto_connect_pipes) {
+        output
+            .actions
+            .push(Action::SetAutoConnectPipes(auto_connect_pipes));
+    }
+    if ui.is_item_hovered() {
+        ui.tooltip(|| {
+            ui.text(im_str!(
+                "Turn placed pipes towards adjacent holes, bending neighboring pipes to join them."
+            ))
+        });
+    }
 }
 
 fn ui_actions(input: &Input, ui: &imgui::Ui, output: &mut Output) {
@@ -299,4 +879,129 @@ fn ui_actions(input: &Input, ui: &imgui::Ui, output: &mut Output) {
         );
         ui.tooltip(|| ui.text(&ImString::new(text)));
     }
+
+    if ui.button(im_str!("Test"), [BUTTON_W, BUTTON_H]) {
+        output.actions.push(Action::IsolateSelection);
+    }
+    if ui.is_item_hovered() {
+        let text = format!(
+            "Run selected blocks on their own, with sources attached to their open holes. Stopping returns to the machine.\n\nShortcut: {}",
+            input.config.isolate_key,
+        );
+        ui.tooltip(|| ui.text(&ImString::new(text)));
+    }
+
+    ui.same_line(0.0);
+
+    if ui.button(im_str!("Find"), [BUTTON_W, BUTTON_H]) {
+        output.actions.push(Action::OpenSearch);
+    }
+    if ui.is_item_hovered() {
+        let text = format!(
+            "Find blocks by name.\n\nShortcut: {}",
+            input.config.search_key,
+        );
+        ui.tooltip(|| ui.text(&ImString::new(text)));
+    }
+
+    if ui.button(im_str!("Walls"), [BUTTON_W, BUTTON_H]) {
+        output.actions.push(Action::WallSelection);
+    }
+    if ui.is_item_hovered() {
+        let text = format!(
+            "Surround selected blocks with solid walls.\n\nShortcut: {}",
+            input.config.wall_selection_key,
+        );
+        ui.tooltip(|| ui.text(&ImString::new(text)));
+    }
+}
+
+fn ui_blueprints(input: &Input, ui: &imgui::Ui, output: &mut Output) {
+    for id in input.blueprint_ids.iter() {
+        if imgui::Selectable::new(&ImString::new(id)).build(ui) {
+            output.actions.push(Action::PlaceBlueprint(id.clone()));
+        }
+    }
+}
+
+fn ui_solutions(input: &Input, ui: &imgui::Ui, state: &mut State, output: &mut Output) {
+    for name in input.solution_names.iter() {
+        let selectable =
+            imgui::Selectable::new(&ImString::new(name)).selected(*name == input.solution_name);
+        if selectable.build(ui) {
+            output.actions.push(Action::LoadSolution(name.clone()));
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip(|| ui.text(im_str!("Load this solution, discarding unsaved changes.")));
+        }
+    }
+
+    ui.separator();
+
+    ui.input_text(im_str!("Name"), &mut state.new_solution_name)
+        .build();
+
+    if ui.button(im_str!("Save as"), [BUTTON_W, BUTTON_H]) {
+        let name = state.new_solution_name.to_str().trim().to_string();
+        output.actions.push(Action::SaveSolutionAs(name));
+    }
+    if ui.is_item_hovered() {
+        let text = format!(
+            "Save the machine as a new solution with the given name.\n\nCurrent solution: {}",
+            input.solution_name,
+        );
+        ui.tooltip(|| ui.text(&ImString::new(text)));
+    }
+}
+
+fn ui_compare(input: &Input, ui: &imgui::Ui, output: &mut Output) {
+    for name in input.solution_names.iter() {
+        let is_compared = input
+            .comparison
+            .as_ref()
+            .map_or(false, |comparison| comparison.name == *name);
+        let label = format!("{}##compare", name);
+        let selectable = imgui::Selectable::new(&ImString::new(label)).selected(is_compared);
+        if selectable.build(ui) {
+            output
+                .actions
+                .push(Action::CompareWithSolution(name.clone()));
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip(|| {
+                ui.text(im_str!(
+                    "Highlight the blocks that differ from this solution."
+                ))
+            });
+        }
+    }
+
+    let comparison = match input.comparison.as_ref() {
+        Some(comparison) => comparison,
+        None => return,
+    };
+
+    ui.separator();
+
+    for &(kind, label) in &[
+        (ChangeKind::Added, "Added"),
+        (ChangeKind::Removed, "Removed"),
+        (ChangeKind::Changed, "Changed"),
+    ] {
+        ui.text_colored(
+            change_color(kind),
+            &ImString::new(format!("{}: {}", label, comparison.count(kind))),
+        );
+    }
+
+    if ui.button(im_str!("Revert"), [BUTTON_W, BUTTON_H]) {
+        output.actions.push(Action::RevertToComparison);
+    }
+    if ui.is_item_hovered() {
+        ui.tooltip(|| ui.text(im_str!("Undo all differences to the compared solution.")));
+    }
+    ui.same_line(0.0);
+    if ui.button(im_str!("Stop"), [BUTTON_W, BUTTON_H]) {
+        output.actions.push(Action::StopComparing);
+    }
 }
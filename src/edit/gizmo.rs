@@ -0,0 +1,70 @@
+use crate::machine::grid;
+
+/// A handle for rotating the selection by dragging it around a pivot with the
+/// mouse. The selection is rotated in steps of 90 degrees around the z axis.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RotateGizmo {
+    /// Position that the selection is rotated around.
+    pub pivot: grid::Point3,
+
+    /// Distance from the pivot to the handle, in grid cells.
+    pub radius: isize,
+}
+
+impl RotateGizmo {
+    /// Returns a gizmo that pivots at the center of the given positions, with
+    /// its handle just outside of them. The gizmo is shown in `layer`.
+    pub fn around<'a>(
+        positions: impl Iterator<Item = &'a grid::Point3>,
+        layer: isize,
+    ) -> Option<Self> {
+        let positions: Vec<_> = positions.collect();
+
+        let min_x = positions.iter().map(|p| p.x).min()?;
+        let max_x = positions.iter().map(|p| p.x).max()?;
+        let min_y = positions.iter().map(|p| p.y).min()?;
+        let max_y = positions.iter().map(|p| p.y).max()?;
+
+        let pivot = grid::Point3::new(
+            (min_x + max_x).div_euclid(2),
+            (min_y + max_y).div_euclid(2),
+            layer,
+        );
+        let radius = positions
+            .iter()
+            .map(|p| (p.x - pivot.x).abs().max((p.y - pivot.y).abs()))
+            .max()?
+            + 2;
+
+        Some(Self { pivot, radius })
+    }
+
+    /// Returns the grid position of the handle when the selection is rotated
+    /// by `num_steps_cw`.
+    pub fn handle_pos(&self, num_steps_cw: usize) -> grid::Point3 {
+        let offset = match num_steps_cw % 4 {
+            0 => grid::Vector3::new(self.radius, 0, 0),
+            1 => grid::Vector3::new(0, -self.radius, 0),
+            2 => grid::Vector3::new(-self.radius, 0, 0),
+            _ => grid::Vector3::new(0, self.radius, 0),
+        };
+
+        self.pivot + offset
+    }
+
+    /// Returns the number of clockwise 90 degree steps that the selection is
+    /// rotated by when the handle is dragged to `pos`.
+    pub fn num_steps_cw(&self, pos: &grid::Point3) -> usize {
+        let delta = pos - self.pivot;
+        if delta.x == 0 && delta.y == 0 {
+            return 0;
+        }
+
+        // The handle starts out at angle zero. Clockwise rotation decreases
+        // the angle.
+        let angle = (delta.y as f32).atan2(delta.x as f32);
+        let steps = (-angle / std::f32::consts::FRAC_PI_2).round() as isize;
+
+        steps.rem_euclid(4) as usize
+    }
+}
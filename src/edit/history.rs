@@ -0,0 +1,121 @@
+use std::collections::VecDeque;
+
+use crate::edit::Edit;
+
+/// The edits that undo and redo the last performed edits. The number of edits
+/// and the memory that they use are limited, dropping the oldest edits first.
+#[derive(Debug, Clone)]
+pub struct History {
+    /// Edits that undo the last performed edits, in the order that the edits
+    /// were performed.
+    undo: VecDeque<Edit>,
+
+    /// Edits that redo the last performed undos, in the order that the undos
+    /// were performed.
+    redo: Vec<Edit>,
+
+    /// Approximate number of bytes used by the edits in `undo` and `redo`.
+    num_bytes: usize,
+
+    max_len: usize,
+    max_bytes: usize,
+}
+
+impl History {
+    pub fn new(max_len: usize, max_bytes: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            num_bytes: 0,
+            max_len,
+            max_bytes,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.num_bytes = 0;
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    pub fn num_bytes(&self) -> usize {
+        self.num_bytes
+    }
+
+    /// Remembers the edit that undoes a newly performed edit. This forgets
+    /// about any undos that could be redone.
+    pub fn push(&mut self, undo_edit: Edit) {
+        self.clear_redo();
+        self.push_undo(undo_edit);
+    }
+
+    /// Merges the edit that undoes a newly performed edit with the last undo
+    /// edit, so that both are undone at once.
+    pub fn push_coalesced(&mut self, undo_edit: Edit) {
+        self.clear_redo();
+
+        match self.undo.pop_back() {
+            Some(last) => {
+                self.num_bytes -= last.num_bytes();
+
+                // The new edit needs to be undone first.
+                self.push_undo(Edit::compose(undo_edit, last));
+            }
+            None => self.push_undo(undo_edit),
+        }
+    }
+
+    /// Takes the edit that undoes the last performed edit. The edit that
+    /// redoes it needs to be given to `push_redo`.
+    pub fn pop_undo(&mut self) -> Option<Edit> {
+        let undo_edit = self.undo.pop_back()?;
+        self.num_bytes -= undo_edit.num_bytes();
+
+        Some(undo_edit)
+    }
+
+    pub fn push_redo(&mut self, redo_edit: Edit) {
+        self.num_bytes += redo_edit.num_bytes();
+        self.redo.push(redo_edit);
+    }
+
+    /// Takes the edit that redoes the last undo. The edit that undoes it again
+    /// needs to be given to `push_undo`.
+    pub fn pop_redo(&mut self) -> Option<Edit> {
+        let redo_edit = self.redo.pop()?;
+        self.num_bytes -= redo_edit.num_bytes();
+
+        Some(redo_edit)
+    }
+
+    /// Remembers an edit that undoes something, without forgetting about the
+    /// undos that could be redone.
+    pub fn push_undo(&mut self, undo_edit: Edit) {
+        self.num_bytes += undo_edit.num_bytes();
+        self.undo.push_back(undo_edit);
+
+        // Always keep the last edit, so that it can be undone no matter how
+        // large it is.
+        while self.undo.len() > 1
+            && (self.undo.len() > self.max_len || self.num_bytes > self.max_bytes)
+        {
+            if let Some(oldest) = self.undo.pop_front() {
+                self.num_bytes -= oldest.num_bytes();
+            }
+        }
+    }
+
+    fn clear_redo(&mut self) {
+        for redo_edit in self.redo.drain(..) {
+            self.num_bytes -= redo_edit.num_bytes();
+        }
+    }
+}
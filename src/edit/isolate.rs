@@ -0,0 +1,81 @@
+use std::collections::HashSet;
+
+use crate::machine::grid;
+use crate::machine::{BlipKind, Block, Machine, PlacedBlock};
+
+/// Number of free cells around the isolated blocks in the XY plane. This
+/// leaves room for the blocks that feed the open holes.
+const MARGIN: isize = 1;
+
+/// Returns a sandbox machine that contains only the player's blocks at
+/// `positions`, so that they can be tested in isolation. Returns `None` if
+/// there are no such blocks.
+///
+/// Open holes of the blocks are fed automatically: wind sources are attached
+/// to holes that wind can only flow into, while blip spawns are attached to
+/// buttons. Holes that let wind through in both directions, such as the ends
+/// of pipes, are left open.
+pub fn test_machine(
+    machine: &Machine,
+    positions: impl Iterator<Item = grid::Point3>,
+) -> Option<Machine> {
+    let blocks: Vec<(grid::Point3, PlacedBlock)> = positions
+        .filter_map(|pos| machine.get(&pos).map(|block| (pos, block.clone())))
+        .filter(|(_, placed_block)| placed_block.block.is_player_block())
+        .collect();
+
+    let min_x = blocks.iter().map(|(pos, _)| pos.x).min()?;
+    let min_y = blocks.iter().map(|(pos, _)| pos.y).min()?;
+    let min_z = blocks.iter().map(|(pos, _)| pos.z).min()?;
+    let max_x = blocks.iter().map(|(pos, _)| pos.x).max()?;
+    let max_y = blocks.iter().map(|(pos, _)| pos.y).max()?;
+    let max_z = blocks.iter().map(|(pos, _)| pos.z).max()?;
+
+    let offset = grid::Vector3::new(MARGIN - min_x, MARGIN - min_y, -min_z);
+    let size = grid::Vector3::new(
+        max_x - min_x + 1 + 2 * MARGIN,
+        max_y - min_y + 1 + 2 * MARGIN,
+        max_z - min_z + 1,
+    );
+
+    let mut test_machine = Machine::new_sandbox(size);
+    let occupied: HashSet<grid::Point3> = blocks.iter().map(|(pos, _)| pos + offset).collect();
+
+    for (pos, placed_block) in blocks.iter() {
+        test_machine.set(&(pos + offset), Some(placed_block.clone()));
+    }
+
+    for (pos, placed_block) in blocks.iter() {
+        let pos = pos + offset;
+
+        for &dir in &grid::Dir3::ALL_XY {
+            let neighbor_pos = pos + dir.to_vector();
+
+            if occupied.contains(&neighbor_pos) || test_machine.is_block_at(&neighbor_pos) {
+                continue;
+            }
+
+            if let Some(block) = feeding_block(&placed_block.block, dir) {
+                test_machine.set(&neighbor_pos, Some(PlacedBlock { block }));
+            }
+        }
+    }
+
+    Some(test_machine)
+}
+
+/// Returns a block that feeds the hole of `block` that is facing `dir`, if
+/// there is one.
+fn feeding_block(block: &Block, dir: grid::Dir3) -> Option<Block> {
+    if block.has_button(dir) && block.has_move_hole(dir, false) {
+        Some(Block::BlipSpawn {
+            out_dir: dir.invert(),
+            kind: BlipKind::A,
+            num_spawns: None,
+        })
+    } else if block.has_wind_hole_in(dir, false) && !block.has_wind_hole_out(dir, false) {
+        Some(Block::WindSource)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,47 @@
+use nalgebra as na;
+
+use crate::machine::grid;
+
+/// Distances between two grid positions, shown by the editor's measurement
+/// tool. Since blips move one block per tick, the Manhattan distance is the
+/// minimal number of ticks a blip needs to travel from one position to the
+/// other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Measurement {
+    pub start: grid::Point3,
+    pub end: grid::Point3,
+}
+
+impl Measurement {
+    /// Returns the absolute distance along each axis.
+    pub fn axis_distances(&self) -> grid::Vector3 {
+        let delta = self.end - self.start;
+
+        grid::Vector3::new(delta.x.abs(), delta.y.abs(), delta.z.abs())
+    }
+
+    pub fn manhattan_distance(&self) -> isize {
+        let d = self.axis_distances();
+
+        d.x + d.y + d.z
+    }
+
+    pub fn straight_distance(&self) -> f32 {
+        let d: na::Vector3<f32> = na::convert(self.end - self.start);
+
+        d.norm()
+    }
+
+    pub fn label(&self) -> String {
+        let d = self.axis_distances();
+
+        format!(
+            "x: {}  y: {}  z: {}\nManhattan: {}\nStraight: {:.2}",
+            d.x,
+            d.y,
+            d.z,
+            self.manhattan_distance(),
+            self.straight_distance(),
+        )
+    }
+}
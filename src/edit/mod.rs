@@ -1,20 +1,209 @@
+pub mod auto_connect;
+pub mod compare;
 pub mod config;
 pub mod editor;
+pub mod gizmo;
+pub mod history;
+pub mod isolate;
+pub mod measure;
 pub mod mode;
 pub mod pick;
 pub mod piece;
+pub mod search;
+pub mod solutions;
+#[cfg(test)]
+mod tests;
 
 use std::collections::HashMap;
+use std::mem;
 
+use crate::machine::diff::BlockChange;
 use crate::machine::grid;
-use crate::machine::{Block, Machine, PlacedBlock};
+use crate::machine::{Block, BlockType, Machine, PlacedBlock};
 
 pub use config::Config;
 pub use editor::Editor;
+pub use gizmo::RotateGizmo;
+pub use history::History;
+pub use measure::Measurement;
 pub use mode::{Mode, SelectionMode};
-pub use piece::Piece;
+pub use piece::{Piece, Transform};
+
+/// How the editor shows layers other than the one being edited. This helps
+/// with editing the lower layers of tall machines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerVisibility {
+    ShowAll,
+    GhostAbove,
+    HideAbove,
+    OnlyCurrent,
+}
+
+impl Default for LayerVisibility {
+    fn default() -> Self {
+        LayerVisibility::ShowAll
+    }
+}
+
+impl LayerVisibility {
+    pub const ALL: &'static [LayerVisibility] = &[
+        LayerVisibility::ShowAll,
+        LayerVisibility::GhostAbove,
+        LayerVisibility::HideAbove,
+        LayerVisibility::OnlyCurrent,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LayerVisibility::ShowAll => "All layers",
+            LayerVisibility::GhostAbove => "Ghost layers above",
+            LayerVisibility::HideAbove => "Hide layers above",
+            LayerVisibility::OnlyCurrent => "Only current layer",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|v| *v == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Returns true if blocks in `layer` are shown at all.
+    pub fn is_shown(self, current_layer: isize, layer: isize) -> bool {
+        match self {
+            LayerVisibility::ShowAll | LayerVisibility::GhostAbove => true,
+            LayerVisibility::HideAbove => layer <= current_layer,
+            LayerVisibility::OnlyCurrent => layer == current_layer,
+        }
+    }
+
+    /// Returns true if blocks in `layer` are shown only faintly.
+    pub fn is_ghosted(self, current_layer: isize, layer: isize) -> bool {
+        self == LayerVisibility::GhostAbove && layer > current_layer
+    }
+}
+
+/// What happens to blocks of the machine that are in the way of a pasted
+/// piece.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    /// Replace the blocks that are in the way.
+    Overwrite,
+
+    /// Paste only into cells that are free.
+    Skip,
+
+    /// Do not paste at all if any block is in the way.
+    Abort,
+}
+
+impl Default for CollisionPolicy {
+    fn default() -> Self {
+        CollisionPolicy::Overwrite
+    }
+}
+
+impl CollisionPolicy {
+    pub const ALL: &'static [CollisionPolicy] = &[
+        CollisionPolicy::Overwrite,
+        CollisionPolicy::Skip,
+        CollisionPolicy::Abort,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CollisionPolicy::Overwrite => "Overwrite blocks",
+            CollisionPolicy::Skip => "Skip occupied cells",
+            CollisionPolicy::Abort => "Abort if occupied",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|p| *p == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// Mirrors blocks that are placed or removed in the editor, which helps with
+/// building balanced machines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    Off,
+
+    /// Mirror x coordinates at the symmetry center.
+    X,
+
+    /// Mirror y coordinates at the symmetry center.
+    Y,
+
+    /// Mirror both x and y coordinates at the symmetry center.
+    XY,
+}
+
+impl Default for Symmetry {
+    fn default() -> Self {
+        Symmetry::Off
+    }
+}
+
+impl Symmetry {
+    pub const ALL: &'static [Symmetry] = &[Symmetry::Off, Symmetry::X, Symmetry::Y, Symmetry::XY];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Symmetry::Off => "No symmetry",
+            Symmetry::X => "Mirror x",
+            Symmetry::Y => "Mirror y",
+            Symmetry::XY => "Mirror x and y",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|s| *s == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Returns the transforms that map positions to their mirrored
+    /// counterparts.
+    pub fn transforms(self, center: &grid::Point3) -> Vec<Transform> {
+        let mirror_x = Transform::around(center, Transform::MirrorY);
+        let mirror_y = Transform::around(center, Transform::MirrorX);
+
+        match self {
+            Symmetry::Off => Vec::new(),
+            Symmetry::X => vec![mirror_x],
+            Symmetry::Y => vec![mirror_y],
+            Symmetry::XY => vec![
+                mirror_x.clone(),
+                mirror_y.clone(),
+                Transform::Seq(vec![mirror_x, mirror_y]),
+            ],
+        }
+    }
+
+    /// Adds the mirrored counterparts of blocks that are to be set. Blocks
+    /// that are given explicitly take precedence over mirrored ones.
+    pub fn apply(
+        self,
+        center: &grid::Point3,
+        blocks: HashMap<grid::Point3, Option<PlacedBlock>>,
+    ) -> HashMap<grid::Point3, Option<PlacedBlock>> {
+        let mut result = HashMap::new();
+
+        for transform in self.transforms(center).iter() {
+            for (pos, block) in blocks.iter() {
+                let block = block.clone().map(|mut placed_block| {
+                    placed_block.block.mutate_dirs(|dir| transform * dir);
+                    placed_block
+                });
+
+                result.insert(transform * *pos, block);
+            }
+        }
 
-// TODO: Unit tests for undo/redo
+        result.extend(blocks);
+        result
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum Edit {
@@ -27,6 +216,15 @@ pub enum Edit {
     /// Rotate blocks counterclockwise.
     RotateCCWXY(Vec<grid::Point3>),
 
+    /// Move blocks by rotating them clockwise around a pivot, the given
+    /// number of times. The blocks are rotated themselves as well. Nothing
+    /// happens if the blocks would collide with other blocks.
+    RotateXYAround {
+        positions: Vec<grid::Point3>,
+        pivot: grid::Point3,
+        num_steps_cw: usize,
+    },
+
     /// Switch to the next kind.
     NextKind(Vec<grid::Point3>),
 
@@ -35,6 +233,20 @@ pub enum Edit {
 }
 
 impl Edit {
+    /// Returns an edit that applies changes as returned by `Machine::diff`.
+    pub fn from_changes(changes: &[BlockChange]) -> Edit {
+        if changes.is_empty() {
+            Edit::NoOp
+        } else {
+            Edit::SetBlocks(
+                changes
+                    .iter()
+                    .map(|change| (change.pos, change.after.clone()))
+                    .collect(),
+            )
+        }
+    }
+
     /// Returns an editor operation that combines blocks whenever possible.
     pub fn set_blocks_combine(
         machine: &Machine,
@@ -115,6 +327,52 @@ impl Edit {
         }*/
     }
 
+    /// Returns the blocks that running the edit on `machine` would set, so
+    /// that the edit can be checked before it is run. Edits that change blocks
+    /// only in place, i.e. rotating them or switching their kind, set nothing.
+    /// Returns `None` for pairs of edits, which can not be checked this way.
+    pub fn blocks_to_set(
+        &self,
+        machine: &Machine,
+    ) -> Option<HashMap<grid::Point3, Option<PlacedBlock>>> {
+        match self {
+            Edit::NoOp | Edit::RotateCWXY(_) | Edit::RotateCCWXY(_) | Edit::NextKind(_) => {
+                Some(HashMap::new())
+            }
+            Edit::SetBlocks(blocks) => Some(blocks.clone()),
+            Edit::RotateXYAround {
+                positions,
+                pivot,
+                num_steps_cw,
+            } => {
+                let positions: Vec<_> = positions
+                    .iter()
+                    .filter(|p| machine.is_block_at(p))
+                    .cloned()
+                    .collect();
+
+                if !can_rotate_cw_xy_around(machine, &positions, pivot, *num_steps_cw) {
+                    return Some(HashMap::new());
+                }
+
+                let mut piece = Piece::new_from_selection(machine, positions.iter().cloned());
+                for _ in 0..num_steps_cw % 4 {
+                    piece.rotate_cw_xy_around(pivot);
+                }
+
+                let mut blocks: HashMap<_, _> = positions.into_iter().map(|p| (p, None)).collect();
+                blocks.extend(
+                    piece
+                        .iter()
+                        .map(|(p, placed_block)| (p, Some(placed_block))),
+                );
+
+                Some(blocks)
+            }
+            Edit::Pair(_, _) => None,
+        }
+    }
+
     /// Apply the edit operation to a machine and return an edit operation to
     /// undo what was done.
     pub fn run(self, machine: &mut Machine) -> Edit {
@@ -179,6 +437,43 @@ impl Edit {
                     Edit::RotateCWXY(points)
                 }
             }
+            Edit::RotateXYAround {
+                positions,
+                pivot,
+                num_steps_cw,
+            } => {
+                let positions: Vec<_> = positions
+                    .into_iter()
+                    .filter(|p| machine.is_block_at(p))
+                    .collect();
+
+                if num_steps_cw % 4 == 0
+                    || positions.is_empty()
+                    || !can_rotate_cw_xy_around(machine, &positions, &pivot, num_steps_cw)
+                {
+                    Edit::NoOp
+                } else {
+                    let mut piece = Piece::new_from_selection(machine, positions.iter().cloned());
+                    for _ in 0..num_steps_cw % 4 {
+                        piece.rotate_cw_xy_around(&pivot);
+                    }
+
+                    for p in &positions {
+                        machine.set(p, None);
+                    }
+                    for (p, placed_block) in piece.iter() {
+                        machine.set(&p, Some(placed_block));
+                    }
+
+                    // Rotating the rest of the way around brings everything
+                    // back into place.
+                    Edit::RotateXYAround {
+                        positions: piece.iter().map(|(p, _)| p).collect(),
+                        pivot,
+                        num_steps_cw: 4 - num_steps_cw % 4,
+                    }
+                }
+            }
             Edit::NextKind(points) => {
                 for p in &points {
                     if let Some(placed_block) = machine.get_mut(p) {
@@ -205,6 +500,26 @@ impl Edit {
         }
     }
 
+    /// Approximates the memory used by the edit, so that the size of the undo
+    /// history can be limited.
+    pub fn num_bytes(&self) -> usize {
+        let data = match self {
+            Edit::NoOp => 0,
+            Edit::SetBlocks(blocks) => {
+                blocks.capacity() * mem::size_of::<(grid::Point3, Option<PlacedBlock>)>()
+            }
+            Edit::RotateCWXY(points) | Edit::RotateCCWXY(points) | Edit::NextKind(points) => {
+                points.capacity() * mem::size_of::<grid::Point3>()
+            }
+            Edit::RotateXYAround { positions, .. } => {
+                positions.capacity() * mem::size_of::<grid::Point3>()
+            }
+            Edit::Pair(a, b) => a.num_bytes() + b.num_bytes(),
+        };
+
+        mem::size_of::<Edit>() + data
+    }
+
     pub fn compose(a: Edit, b: Edit) -> Edit {
         match (a, b) {
             (Edit::NoOp, b) => b,
@@ -221,6 +536,76 @@ impl Edit {
     }
 }
 
+/// Returns the positions that `positions` are moved to by rotating them
+/// clockwise around `pivot`, `num_steps_cw` times.
+pub fn rotate_positions_cw_xy(
+    positions: &[grid::Point3],
+    pivot: &grid::Point3,
+    num_steps_cw: usize,
+) -> Vec<grid::Point3> {
+    let rotate = Transform::around(pivot, Transform::RotateCWXY);
+
+    positions
+        .iter()
+        .map(|p| (0..num_steps_cw % 4).fold(*p, |p, _| &rotate * p))
+        .collect()
+}
+
+/// Returns true if the blocks at `positions` can be rotated around `pivot`
+/// without leaving the grid or running into blocks that are not rotated
+/// along.
+pub fn can_rotate_cw_xy_around(
+    machine: &Machine,
+    positions: &[grid::Point3],
+    pivot: &grid::Point3,
+    num_steps_cw: usize,
+) -> bool {
+    rotate_positions_cw_xy(positions, pivot, num_steps_cw)
+        .iter()
+        .all(|p| machine.is_valid_pos(p) && (!machine.is_block_at(p) || positions.contains(p)))
+}
+
+/// Returns the positions among `positions` whose blocks the eraser removes,
+/// i.e. blocks that the player can place and that match `filter`, if given.
+pub fn erasable_positions(
+    machine: &Machine,
+    positions: impl Iterator<Item = grid::Point3>,
+    filter: Option<BlockType>,
+) -> Vec<grid::Point3> {
+    positions
+        .filter(|pos| {
+            machine.get(pos).map_or(false, |placed_block| {
+                placed_block.block.is_player_block()
+                    && filter.map_or(true, |block_type| {
+                        placed_block.block.block_type() == block_type
+                    })
+            })
+        })
+        .collect()
+}
+
+/// Returns the grid positions in the rectangle spanned by `a` and `b`, within
+/// the layer of `a`.
+pub fn rect_positions(a: &grid::Point3, b: &grid::Point3) -> Vec<grid::Point3> {
+    let (min_x, max_x) = (a.x.min(b.x), a.x.max(b.x));
+    let (min_y, max_y) = (a.y.min(b.y), a.y.max(b.y));
+
+    (min_y..=max_y)
+        .flat_map(|y| (min_x..=max_x).map(move |x| grid::Point3::new(x, y, a.z)))
+        .collect()
+}
+
+/// Returns the empty positions on the walls of the box spanned by `a` and
+/// `b`, which are to be filled with `Solid` blocks for containing stray
+/// blips.
+pub fn wall_positions(machine: &Machine, a: &grid::Point3, b: &grid::Point3) -> Vec<grid::Point3> {
+    let (min, max) = grid::box_corners(a, b);
+
+    grid::iter_box_walls(&min, &max)
+        .filter(|pos| machine.is_valid_pos(pos) && !machine.is_block_at(pos))
+        .collect()
+}
+
 pub fn count_inputs<'a>(blocks: impl Iterator<Item = &'a Option<PlacedBlock>>) -> usize {
     blocks
         .map(|block| match block {
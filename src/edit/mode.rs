@@ -2,8 +2,8 @@ use std::collections::HashMap;
 
 use nalgebra as na;
 
-use crate::edit::Piece;
-use crate::machine::{grid, Machine, PlacedBlock};
+use crate::edit::{Piece, RotateGizmo};
+use crate::machine::{grid, BlockType, Machine, PlacedBlock};
 
 /// Modes that the editor can be in.
 #[derive(Debug, Clone, PartialEq)]
@@ -58,10 +58,44 @@ pub enum Mode {
         outer: Box<Mode>,
     },
 
+    /// Rotate the selection by dragging the handle of a rotation gizmo.
+    RotateSelection {
+        selection: SelectionMode,
+        gizmo: RotateGizmo,
+
+        /// Number of clockwise 90 degree steps that the selection is going to
+        /// be rotated by when the handle is released.
+        num_steps_cw: usize,
+    },
+
     PipeTool {
         last_pos: Option<grid::Point3>,
         blocks: HashMap<grid::Point3, PlacedBlock>,
     },
+
+    /// Measure the distance between two grid positions.
+    Measure {
+        /// The first position that the user clicked on, if any.
+        start: Option<grid::Point3>,
+    },
+
+    /// Delete blocks by dragging over them, or in a rectangle of the current
+    /// layer when starting to drag with shift held.
+    Erase {
+        /// Type of the blocks to delete, if not all types.
+        filter: Option<BlockType>,
+
+        /// Corner of the rectangle that is being dragged, if any.
+        rect_start: Option<grid::Point3>,
+    },
+
+    /// Build walls of `Solid` blocks around a box, e.g. for containing stray
+    /// blips. The box is dragged out, and spans multiple layers if the layer
+    /// is changed while dragging.
+    Wall {
+        /// Corner of the box that is being dragged, if any.
+        start: Option<grid::Point3>,
+    },
 }
 
 impl Mode {
@@ -80,6 +114,21 @@ impl Mode {
         }
     }
 
+    pub fn new_measure() -> Self {
+        Mode::Measure { start: None }
+    }
+
+    pub fn new_erase(filter: Option<BlockType>) -> Self {
+        Mode::Erase {
+            filter,
+            rect_start: None,
+        }
+    }
+
+    pub fn new_wall() -> Self {
+        Mode::Wall { start: None }
+    }
+
     pub fn switch_to_place_piece(self, piece: Piece, is_paste: bool) -> Self {
         match self {
             Mode::PlacePiece { outer, .. } => Mode::PlacePiece {
@@ -103,6 +152,7 @@ impl Mode {
             Mode::RectSelect {
                 existing_selection, ..
             } => Some(existing_selection),
+            Mode::RotateSelection { selection, .. } => Some(selection),
             _ => None,
         }
     }
@@ -161,6 +211,13 @@ impl Mode {
 
                 Mode::DragAndDrop { selection, piece }
             }
+            Mode::RotateSelection { selection, .. } => {
+                // The gizmo depends on the selected positions, so give up on
+                // rotating.
+                let selection = selection.make_consistent_with_machine(machine);
+
+                Mode::Select { selection }
+            }
             Mode::PlacePiece {
                 piece,
                 is_paste,
@@ -195,7 +252,13 @@ impl Mode {
                 target_layer >= current_layer + piece.min_pos().z
                     && target_layer <= current_layer + piece.max_pos().z
             }
+            Mode::RotateSelection { selection, .. } => {
+                selection.impacts_layer(current_layer, target_layer)
+            }
             Mode::PipeTool { .. } => current_layer == target_layer,
+            Mode::Measure { .. } => true,
+            Mode::Erase { .. } => current_layer == target_layer,
+            Mode::Wall { .. } => true,
         }
     }
 }
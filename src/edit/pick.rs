@@ -6,7 +6,7 @@ use rendology::Camera;
 
 use crate::machine::{grid, Machine};
 use crate::render;
-use crate::util::intersection::{ray_aabb_intersection, ray_plane_intersection, Plane, Ray, AABB};
+use crate::util::intersection::{ray_grid_intersection, ray_plane_intersection, Plane, Ray};
 
 pub fn camera_ray(camera: &Camera, eye: &na::Point3<f32>, window_pos: &na::Point2<f32>) -> Ray {
     let p_near = camera.unproject_from_viewport(&na::Point3::new(window_pos.x, window_pos.y, -1.0));
@@ -55,37 +55,24 @@ pub fn pick_block(
     window_pos: &na::Point2<f32>,
     filter: impl Fn(&grid::Point3) -> bool,
 ) -> Option<grid::Point3> {
-    let ray = camera_ray(camera, eye, window_pos);
-
-    let mut closest_block = None;
-    for (_block_index, (block_pos, _placed_block)) in machine.iter_blocks() {
-        if !filter(block_pos) {
-            continue;
-        }
+    pick_block_face(machine, camera, eye, window_pos, filter).map(|(pos, _face)| pos)
+}
 
-        let center = render::machine::block_center(&block_pos);
-
-        let aabb = AABB {
-            min: center - na::Vector3::new(0.5, 0.5, 0.5),
-            max: center + na::Vector3::new(0.5, 0.5, 0.5),
-        };
-
-        if let Some(distance) = ray_aabb_intersection(&ray, &aabb) {
-            // TODO: Perform a tighter intersection check if AABB is a hit
-            closest_block = Some(closest_block.map_or(
-                (block_pos, distance),
-                |(closest_pos, closest_distance)| {
-                    if distance < closest_distance {
-                        (block_pos, distance)
-                    } else {
-                        (closest_pos, closest_distance)
-                    }
-                },
-            ));
-        }
-    }
+/// Returns the first block hit by the mouse ray, together with the face of
+/// the block that the ray enters through.
+pub fn pick_block_face(
+    machine: &Machine,
+    camera: &Camera,
+    eye: &na::Point3<f32>,
+    window_pos: &na::Point2<f32>,
+    filter: impl Fn(&grid::Point3) -> bool,
+) -> Option<(grid::Point3, Option<grid::Dir3>)> {
+    let ray = camera_ray(camera, eye, window_pos);
 
-    closest_block.map(|(pos, _distance)| *pos)
+    ray_grid_intersection(&ray, &machine.size(), |pos| {
+        machine.is_block_at(pos) && filter(pos)
+    })
+    .map(|hit| (hit.pos, hit.face))
 }
 
 pub fn pick_line(machine: &Machine, a: &grid::Point3, b: &grid::Point3) -> Vec<grid::Point3> {
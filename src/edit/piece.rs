@@ -1,18 +1,30 @@
 use std::ops::Mul;
 
-use crate::edit::Edit;
+use crate::edit::{CollisionPolicy, Edit};
 use crate::machine::grid;
-use crate::machine::{Machine, PlacedBlock};
+use crate::machine::{Block, Machine, PlacedBlock};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Transform {
     Shift(grid::Vector3),
     RotateCWXY,
     RotateCCWXY,
+    MirrorX,
     MirrorY,
     Seq(Vec<Transform>),
 }
 
+impl Transform {
+    /// Returns a transform that applies `inner` with `pivot` as the origin.
+    pub fn around(pivot: &grid::Point3, inner: Transform) -> Transform {
+        Transform::Seq(vec![
+            Transform::Shift(-pivot.coords),
+            inner,
+            Transform::Shift(pivot.coords),
+        ])
+    }
+}
+
 impl<'a> Mul<grid::Point3> for &'a Transform {
     type Output = grid::Point3;
 
@@ -21,6 +33,7 @@ impl<'a> Mul<grid::Point3> for &'a Transform {
             Transform::Shift(delta) => p + delta,
             Transform::RotateCWXY => grid::Point3::new(p.y, -p.x, p.z),
             Transform::RotateCCWXY => grid::Point3::new(-p.y, p.x, p.z),
+            Transform::MirrorX => grid::Point3::new(p.x, -p.y, p.z),
             Transform::MirrorY => grid::Point3::new(-p.x, p.y, p.z),
             Transform::Seq(inner) => inner.iter().fold(p, |p, transform| transform * p),
         }
@@ -43,6 +56,7 @@ impl<'a> Mul<grid::Dir3> for &'a Transform {
             Transform::Shift(_) => d,
             Transform::RotateCWXY => d.rotated_cw_xy(),
             Transform::RotateCCWXY => d.rotated_ccw_xy(),
+            Transform::MirrorX => d.mirrored_x(),
             Transform::MirrorY => d.mirrored_y(),
             Transform::Seq(inner) => inner.iter().fold(d, |d, transform| transform * d),
         }
@@ -97,12 +111,57 @@ impl Piece {
         self.transform(&Transform::Shift(*delta));
     }
 
+    /// Rotates the piece clockwise around its pivot, so that the block under
+    /// the cursor stays in place.
     pub fn rotate_cw_xy(&mut self) {
-        self.transform(&Transform::RotateCWXY);
+        let pivot = self.pivot();
+        self.rotate_cw_xy_around(&pivot);
     }
 
+    /// Rotates the piece counterclockwise around its pivot, so that the block
+    /// under the cursor stays in place.
     pub fn rotate_ccw_xy(&mut self) {
-        self.transform(&Transform::RotateCCWXY);
+        let pivot = self.pivot();
+        self.rotate_ccw_xy_around(&pivot);
+    }
+
+    pub fn rotate_cw_xy_around(&mut self, pivot: &grid::Point3) {
+        self.transform(&Transform::around(pivot, Transform::RotateCWXY));
+    }
+
+    pub fn rotate_ccw_xy_around(&mut self, pivot: &grid::Point3) {
+        self.transform(&Transform::around(pivot, Transform::RotateCCWXY));
+    }
+
+    /// Returns the position that the piece should be rotated around.
+    ///
+    /// Pieces are kept relative to the cursor, so we pivot at the origin if
+    /// there is a block. Otherwise, we fall back to the center of the piece,
+    /// which keeps the piece from jumping around the cursor.
+    pub fn pivot(&self) -> grid::Point3 {
+        if self.blocks.is_empty()
+            || self
+                .blocks
+                .iter()
+                .any(|(pos, _)| *pos == grid::Point3::origin())
+        {
+            grid::Point3::origin()
+        } else {
+            self.center()
+        }
+    }
+
+    /// Returns the cell in the middle of the piece's bounding box, rounding
+    /// down.
+    pub fn center(&self) -> grid::Point3 {
+        let min = self.min_pos();
+        let max = self.max_pos();
+
+        grid::Point3::new(
+            (min.x + max.x).div_euclid(2),
+            (min.y + max.y).div_euclid(2),
+            (min.z + max.z).div_euclid(2),
+        )
     }
 
     pub fn mirror_y(&mut self) {
@@ -123,6 +182,56 @@ impl Piece {
         Edit::SetBlocks(set_blocks)
     }
 
+    /// Returns an edit that places the piece, dealing with blocks of the
+    /// machine that are in the way according to `policy`.
+    pub fn as_place_edit_with_policy(&self, machine: &Machine, policy: CollisionPolicy) -> Edit {
+        match policy {
+            CollisionPolicy::Overwrite => self.as_place_edit(),
+            CollisionPolicy::Skip => Edit::SetBlocks(
+                self.iter()
+                    .filter(|(pos, _)| !machine.is_block_at(pos))
+                    .map(|(pos, block)| (pos, Some(block)))
+                    .collect(),
+            ),
+            CollisionPolicy::Abort => {
+                if self.iter().any(|(pos, _)| machine.is_block_at(&pos)) {
+                    Edit::NoOp
+                } else {
+                    self.as_place_edit()
+                }
+            }
+        }
+    }
+
+    /// Returns a copy of the piece where blocks that would replace a
+    /// different block of the machine are oriented like the block they
+    /// replace, so that e.g. pipes can be swapped for funnels in one click.
+    pub fn with_replacements(&self, machine: &Machine) -> Piece {
+        let blocks = self
+            .iter()
+            .map(|(pos, mut placed_block)| {
+                if let Some(old) = machine.get(&pos) {
+                    let has_holes = |block: &Block| {
+                        grid::Dir3::ALL
+                            .iter()
+                            .any(|dir| block.has_wind_hole(*dir, false))
+                    };
+
+                    if old.block != placed_block.block
+                        && has_holes(&old.block)
+                        && has_holes(&placed_block.block)
+                    {
+                        placed_block.block = placed_block.block.oriented_like(&old.block);
+                    }
+                }
+
+                (pos, placed_block)
+            })
+            .collect();
+
+        Piece::new(blocks)
+    }
+
     pub fn get_singleton(&self) -> Option<(grid::Point3, PlacedBlock)> {
         if let Some(entry) = self.blocks.iter().next() {
             if self.blocks.len() == 1 {
@@ -0,0 +1,69 @@
+use crate::machine::{grid, Machine};
+
+/// Blocks of the machine whose name matches a query.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Search {
+    pub query: String,
+
+    /// Positions of the matching blocks, ordered by layer and then by row.
+    pub results: Vec<grid::Point3>,
+
+    /// Index of the result that was focused last.
+    pub current: Option<usize>,
+}
+
+impl Search {
+    pub fn new(machine: &Machine, query: &str) -> Self {
+        Self {
+            query: query.to_string(),
+            results: find_blocks(machine, query),
+            current: None,
+        }
+    }
+
+    /// Looks for the query again, e.g. after the machine has been edited.
+    /// The current result is kept if the block is still found.
+    pub fn update(&mut self, machine: &Machine) {
+        let current_pos = self.current_pos();
+
+        self.results = find_blocks(machine, &self.query);
+        self.current = current_pos.and_then(|pos| self.results.iter().position(|p| *p == pos));
+    }
+
+    pub fn current_pos(&self) -> Option<grid::Point3> {
+        self.current
+            .and_then(|index| self.results.get(index).copied())
+    }
+
+    /// Advances to the next result, wrapping around at the end.
+    pub fn next(&mut self) -> Option<grid::Point3> {
+        if self.results.is_empty() {
+            return None;
+        }
+
+        let index = self
+            .current
+            .map_or(0, |index| (index + 1) % self.results.len());
+        self.current = Some(index);
+
+        self.current_pos()
+    }
+}
+
+/// Returns the positions of the blocks whose name contains `query`, ignoring
+/// case. An empty query matches nothing.
+pub fn find_blocks(machine: &Machine, query: &str) -> Vec<grid::Point3> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut positions: Vec<grid::Point3> = machine
+        .iter_blocks()
+        .filter(|(_, (_, placed_block))| placed_block.block.name().to_lowercase().contains(&query))
+        .map(|(_, (pos, _))| *pos)
+        .collect();
+
+    positions.sort_by_key(|pos| (pos.z, pos.y, pos.x));
+    positions
+}
@@ -0,0 +1,127 @@
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+
+use crate::machine::{text, Level, Machine, SavedMachine};
+
+/// Extension of files in the textual format of `machine::text`.
+pub const TEXT_EXTENSION: &str = "txt";
+
+/// Name of the solution that is used if the player has not chosen one.
+pub const DEFAULT_NAME: &str = "default";
+
+/// Directory name used for machines that do not belong to a level.
+pub const SANDBOX_DIR: &str = "sandbox";
+
+/// Solutions are stored per level in a directory structure of the form
+/// `<solutions_dir>/<level id>/<solution name>.json`.
+pub fn level_dir(solutions_dir: &Path, level: Option<&Level>) -> PathBuf {
+    solutions_dir.join(level.map_or_else(|| SANDBOX_DIR.to_string(), Level::id))
+}
+
+pub fn path(solutions_dir: &Path, level: Option<&Level>, name: &str) -> PathBuf {
+    level_dir(solutions_dir, level).join(format!("{}.json", name))
+}
+
+/// Returns the sorted names of all solutions that are stored for the level.
+pub fn list(solutions_dir: &Path, level: Option<&Level>) -> Vec<String> {
+    let dir = level_dir(solutions_dir, level);
+
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => {
+            // The directory does not exist until the first solution is saved.
+            return Vec::new();
+        }
+    };
+
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+        .filter_map(|path| {
+            path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .map(|stem| stem.to_string())
+        })
+        .collect();
+    names.sort();
+
+    names
+}
+
+/// Returns true if the name can be used as a file name for a solution.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == ' ')
+}
+
+pub fn save(machine: &Machine, path: &Path) {
+    info!("Saving machine to file {:?}", path);
+
+    if let Some(dir) = path.parent() {
+        if let Err(err) = fs::create_dir_all(dir) {
+            warn!("Could not create directory {:?}: {}", dir, err);
+            return;
+        }
+    }
+
+    match File::create(path) {
+        Ok(file) => {
+            let saved_machine = SavedMachine::from_machine(machine);
+            if let Err(err) = serde_json::to_writer_pretty(file, &saved_machine) {
+                warn!("Error while saving machine to file {:?}: {}", path, err);
+            }
+        }
+        Err(err) => {
+            warn!("Could not open file {:?} for writing: {}", path, err);
+        }
+    }
+}
+
+/// Loads a machine that was saved as JSON, or that is described in the
+/// textual format of `machine::text` if the file has the extension `txt`.
+pub fn load(path: &Path) -> Option<Machine> {
+    info!("Loading machine from file {:?}", path);
+
+    if path.extension().map_or(false, |ext| ext == TEXT_EXTENSION) {
+        return load_text(path);
+    }
+
+    match File::open(path) {
+        Ok(file) => {
+            let reader = BufReader::new(file);
+            match serde_json::from_reader::<_, SavedMachine>(reader) {
+                Ok(saved_machine) => Some(saved_machine.into_machine()),
+                Err(err) => {
+                    warn!("Error while loading machine from file {:?}: {}", path, err);
+                    None
+                }
+            }
+        }
+        Err(err) => {
+            warn!("Could not open file {:?} for reading: {}", path, err);
+            None
+        }
+    }
+}
+
+fn load_text(path: &Path) -> Option<Machine> {
+    match fs::read_to_string(path) {
+        Ok(s) => match text::parse(&s) {
+            Ok(machine) => Some(machine),
+            Err(err) => {
+                warn!("Error while loading machine from file {:?}: {}", path, err);
+                None
+            }
+        },
+        Err(err) => {
+            warn!("Could not open file {:?} for reading: {}", path, err);
+            None
+        }
+    }
+}
@@ -0,0 +1,475 @@
+use crate::edit::search::Search;
+use crate::edit::{
+    auto_connect, erasable_positions, isolate, rect_positions, CollisionPolicy, Edit, History,
+    Piece, RotateGizmo, Symmetry,
+};
+use crate::machine::grid::{Dir3, Point3, Vector3};
+use crate::machine::{BlipKind, Block, BlockType, Machine, PlacedBlock};
+
+fn place_solid(p: Point3) -> Edit {
+    Edit::SetBlocks(maplit::hashmap! {
+        p => Some(PlacedBlock { block: Block::Solid }),
+    })
+}
+
+/// Test that the history drops the oldest edits when it grows too long.
+#[test]
+fn test_history_max_len() {
+    let mut machine = Machine::new_sandbox(Vector3::new(10, 10, 1));
+    let mut history = History::new(3, std::usize::MAX);
+
+    for x in 0..5 {
+        history.push(place_solid(Point3::new(x, 0, 0)).run(&mut machine));
+    }
+
+    assert_eq!(history.undo_len(), 3);
+
+    while let Some(undo_edit) = history.pop_undo() {
+        undo_edit.run(&mut machine);
+    }
+
+    // The first two placements can no longer be undone.
+    assert_eq!(machine.num_blocks(), 2);
+    assert_eq!(history.num_bytes(), 0);
+}
+
+/// Test that the history drops the oldest edits when they use too much
+/// memory, but keeps the last edit in any case.
+#[test]
+fn test_history_max_bytes() {
+    let mut machine = Machine::new_sandbox(Vector3::new(10, 10, 1));
+    let edit_bytes = place_solid(Point3::new(0, 0, 0)).num_bytes();
+    let mut history = History::new(100, 2 * edit_bytes);
+
+    for x in 0..5 {
+        history.push(place_solid(Point3::new(x, 0, 0)).run(&mut machine));
+        assert!(history.num_bytes() <= 2 * edit_bytes);
+    }
+    assert_eq!(history.undo_len(), 2);
+
+    let huge_edit = Edit::SetBlocks(
+        (0..10)
+            .map(|y| {
+                (
+                    Point3::new(9, y, 0),
+                    Some(PlacedBlock {
+                        block: Block::Solid,
+                    }),
+                )
+            })
+            .collect(),
+    );
+    history.push(huge_edit.run(&mut machine));
+    assert_eq!(history.undo_len(), 1);
+}
+
+/// Test that coalesced placements are undone at once, restoring the state
+/// before the first placement.
+#[test]
+fn test_history_coalesce() {
+    let mut machine = Machine::new_sandbox(Vector3::new(10, 10, 1));
+    let mut history = History::new(100, std::usize::MAX);
+
+    history.push(place_solid(Point3::new(5, 5, 0)).run(&mut machine));
+    history.push(place_solid(Point3::new(0, 0, 0)).run(&mut machine));
+    for x in 1..4 {
+        history.push_coalesced(place_solid(Point3::new(x, 0, 0)).run(&mut machine));
+    }
+    assert_eq!(history.undo_len(), 2);
+
+    let undo_edit = history.pop_undo().unwrap();
+    history.push_redo(undo_edit.run(&mut machine));
+    assert_eq!(machine.num_blocks(), 1);
+
+    let redo_edit = history.pop_redo().unwrap();
+    history.push_undo(redo_edit.run(&mut machine));
+    assert_eq!(machine.num_blocks(), 5);
+
+    // New edits make redoing impossible.
+    let undo_edit = history.pop_undo().unwrap();
+    history.push_redo(undo_edit.run(&mut machine));
+    history.push(place_solid(Point3::new(9, 9, 0)).run(&mut machine));
+    assert_eq!(history.redo_len(), 0);
+}
+
+/// Test that pasting respects the collision policy for occupied cells.
+#[test]
+fn test_paste_collision_policy() {
+    let mut machine = Machine::new_sandbox(Vector3::new(10, 10, 1));
+    place_solid(Point3::new(1, 0, 0)).run(&mut machine);
+
+    let piece = Piece::new(
+        (0..3)
+            .map(|x| {
+                (
+                    Point3::new(x, 0, 0),
+                    PlacedBlock {
+                        block: Block::Solid,
+                    },
+                )
+            })
+            .collect(),
+    );
+
+    let mut overwrite = machine.clone();
+    piece
+        .as_place_edit_with_policy(&machine, CollisionPolicy::Overwrite)
+        .run(&mut overwrite);
+    assert_eq!(overwrite.num_blocks(), 3);
+
+    match piece.as_place_edit_with_policy(&machine, CollisionPolicy::Skip) {
+        Edit::SetBlocks(blocks) => {
+            assert_eq!(blocks.len(), 2);
+            assert!(!blocks.contains_key(&Point3::new(1, 0, 0)));
+        }
+        edit => panic!("Unexpected edit: {:?}", edit),
+    }
+
+    match piece.as_place_edit_with_policy(&machine, CollisionPolicy::Abort) {
+        Edit::NoOp => (),
+        edit => panic!("Unexpected edit: {:?}", edit),
+    }
+}
+
+fn solid_piece(positions: &[Point3]) -> Piece {
+    Piece::new(
+        positions
+            .iter()
+            .map(|pos| {
+                (
+                    *pos,
+                    PlacedBlock {
+                        block: Block::Solid,
+                    },
+                )
+            })
+            .collect(),
+    )
+}
+
+/// Test that rotating a piece keeps the block under the cursor in place, or
+/// the piece's center if there is no block under the cursor.
+#[test]
+fn test_piece_rotate_pivot() {
+    let mut piece = solid_piece(&[
+        Point3::new(-1, 0, 0),
+        Point3::new(0, 0, 0),
+        Point3::new(1, 0, 0),
+        Point3::new(1, 1, 0),
+    ]);
+    let original = piece.clone();
+
+    piece.rotate_cw_xy();
+    assert!(piece.iter().any(|(pos, _)| pos == Point3::origin()));
+
+    for _ in 0..3 {
+        piece.rotate_cw_xy();
+    }
+    assert_eq!(piece, original);
+
+    let mut piece = solid_piece(&[
+        Point3::new(3, 2, 0),
+        Point3::new(4, 2, 0),
+        Point3::new(5, 2, 0),
+    ]);
+    let center = piece.center();
+    assert_eq!(center, Point3::new(4, 2, 0));
+
+    piece.rotate_ccw_xy();
+    assert_eq!(piece.center(), center);
+    assert_eq!(piece.extent(), Vector3::new(1, 3, 1));
+}
+
+/// Test that symmetry adds mirrored counterparts with mirrored directions.
+#[test]
+fn test_symmetry_apply() {
+    let funnel = |flow_dir| {
+        Some(PlacedBlock {
+            block: Block::FunnelXY { flow_dir },
+        })
+    };
+    let center = Point3::new(4, 4, 0);
+    let blocks = maplit::hashmap! {
+        Point3::new(1, 2, 0) => funnel(Dir3::X_POS),
+        Point3::new(4, 3, 0) => None,
+    };
+
+    let mirrored = Symmetry::X.apply(&center, blocks.clone());
+    assert_eq!(mirrored.len(), 3);
+    assert_eq!(mirrored[&Point3::new(7, 2, 0)], funnel(Dir3::X_NEG));
+    assert_eq!(mirrored[&Point3::new(4, 3, 0)], None);
+
+    let mirrored = Symmetry::XY.apply(&center, blocks.clone());
+    assert_eq!(mirrored.len(), 6);
+    assert_eq!(mirrored[&Point3::new(1, 6, 0)], funnel(Dir3::X_POS));
+    assert_eq!(mirrored[&Point3::new(7, 6, 0)], funnel(Dir3::X_NEG));
+    assert_eq!(mirrored[&Point3::new(4, 5, 0)], None);
+
+    assert_eq!(Symmetry::Off.apply(&center, blocks.clone()), blocks);
+}
+
+/// Test that placing a block over a different one keeps the orientation of
+/// the replaced block.
+#[test]
+fn test_piece_with_replacements() {
+    let mut machine = Machine::new_sandbox(Vector3::new(10, 10, 1));
+    Edit::SetBlocks(maplit::hashmap! {
+        Point3::new(1, 1, 0) => Some(PlacedBlock {
+            block: Block::FunnelXY { flow_dir: Dir3::Y_POS },
+        }),
+        Point3::new(2, 1, 0) => Some(PlacedBlock { block: Block::Solid }),
+    })
+    .run(&mut machine);
+
+    let piece = Piece::new(vec![
+        (
+            Point3::new(1, 1, 0),
+            PlacedBlock {
+                block: Block::Delay {
+                    flow_dir: Dir3::X_POS,
+                },
+            },
+        ),
+        (
+            Point3::new(2, 1, 0),
+            PlacedBlock {
+                block: Block::Delay {
+                    flow_dir: Dir3::X_POS,
+                },
+            },
+        ),
+    ]);
+
+    let replaced = piece.with_replacements(&machine);
+    let block_at = |pos| {
+        replaced
+            .iter()
+            .find(|(p, _)| *p == pos)
+            .map(|(_, placed_block)| placed_block.block)
+    };
+
+    match block_at(Point3::new(1, 1, 0)) {
+        Some(Block::Delay { flow_dir }) => assert_eq!(flow_dir.0, Dir3::Y_POS.0),
+        block => panic!("Unexpected block: {:?}", block),
+    }
+
+    // Solid blocks have no orientation to keep.
+    assert_eq!(
+        block_at(Point3::new(2, 1, 0)),
+        Some(Block::Delay {
+            flow_dir: Dir3::X_POS
+        })
+    );
+}
+
+/// Test that the rotation gizmo maps handle positions to rotation steps, and
+/// that rotating around the pivot moves blocks and can be undone.
+#[test]
+fn test_rotate_gizmo() {
+    let positions = vec![Point3::new(2, 2, 0), Point3::new(4, 2, 0)];
+    let gizmo = RotateGizmo::around(positions.iter(), 0).unwrap();
+    assert_eq!(gizmo.pivot, Point3::new(3, 2, 0));
+
+    for steps in 0..4 {
+        assert_eq!(gizmo.num_steps_cw(&gizmo.handle_pos(steps)), steps);
+    }
+
+    let mut machine = Machine::new_sandbox(Vector3::new(10, 10, 1));
+    Edit::SetBlocks(maplit::hashmap! {
+        Point3::new(2, 2, 0) => Some(PlacedBlock {
+            block: Block::FunnelXY { flow_dir: Dir3::X_POS },
+        }),
+        Point3::new(4, 2, 0) => Some(PlacedBlock { block: Block::Solid }),
+    })
+    .run(&mut machine);
+
+    let undo_edit = Edit::RotateXYAround {
+        positions: positions.clone(),
+        pivot: gizmo.pivot,
+        num_steps_cw: 1,
+    }
+    .run(&mut machine);
+    assert_eq!(machine.num_blocks(), 2);
+    assert_eq!(
+        machine.get(&Point3::new(3, 3, 0)),
+        Some(&PlacedBlock {
+            block: Block::FunnelXY {
+                flow_dir: Dir3::Y_NEG
+            }
+        })
+    );
+    assert!(machine.is_block_at(&Point3::new(3, 1, 0)));
+
+    undo_edit.run(&mut machine);
+    assert_eq!(
+        machine.get(&Point3::new(2, 2, 0)),
+        Some(&PlacedBlock {
+            block: Block::FunnelXY {
+                flow_dir: Dir3::X_POS
+            }
+        })
+    );
+    assert!(machine.is_block_at(&Point3::new(4, 2, 0)));
+
+    // Blocks that are not rotated along are in the way.
+    place_solid(Point3::new(3, 1, 0)).run(&mut machine);
+    let edit = Edit::RotateXYAround {
+        positions,
+        pivot: gizmo.pivot,
+        num_steps_cw: 1,
+    };
+    match edit.run(&mut machine) {
+        Edit::NoOp => (),
+        edit => panic!("Unexpected edit: {:?}", edit),
+    }
+}
+
+/// Test that isolating blocks moves them into a fresh machine and feeds their
+/// open holes.
+#[test]
+fn test_isolate_test_machine() {
+    let mut machine = Machine::new_sandbox(Vector3::new(10, 10, 2));
+    Edit::SetBlocks(maplit::hashmap! {
+        Point3::new(5, 5, 1) => Some(PlacedBlock {
+            block: Block::FunnelXY { flow_dir: Dir3::X_POS },
+        }),
+        Point3::new(6, 5, 1) => Some(PlacedBlock {
+            block: Block::BlipWindSource { button_dir: Dir3::Y_NEG },
+        }),
+        Point3::new(0, 0, 0) => Some(PlacedBlock { block: Block::Solid }),
+    })
+    .run(&mut machine);
+
+    let positions = vec![
+        Point3::new(5, 5, 1),
+        Point3::new(6, 5, 1),
+        Point3::new(7, 7, 1),
+    ];
+    let test_machine = isolate::test_machine(&machine, positions.into_iter()).unwrap();
+
+    assert_eq!(test_machine.size(), Vector3::new(4, 3, 1));
+    assert!(test_machine.is_block_at(&Point3::new(1, 1, 0)));
+    assert!(test_machine.is_block_at(&Point3::new(2, 1, 0)));
+    assert_eq!(
+        test_machine.get(&Point3::new(2, 0, 0)),
+        Some(&PlacedBlock {
+            block: Block::BlipSpawn {
+                out_dir: Dir3::Y_POS,
+                kind: BlipKind::A,
+                num_spawns: None,
+            }
+        })
+    );
+    assert_eq!(test_machine.num_blocks(), 3);
+
+    assert!(isolate::test_machine(&machine, vec![Point3::new(7, 7, 1)].into_iter()).is_none());
+}
+
+#[test]
+fn test_search_cycle() {
+    let mut machine = Machine::new_sandbox(Vector3::new(10, 10, 2));
+    Edit::SetBlocks(maplit::hashmap! {
+        Point3::new(5, 5, 1) => Some(PlacedBlock { block: Block::WindSource }),
+        Point3::new(6, 5, 0) => Some(PlacedBlock { block: Block::WindSource }),
+        Point3::new(0, 0, 0) => Some(PlacedBlock { block: Block::Solid }),
+    })
+    .run(&mut machine);
+
+    let mut search = Search::new(&machine, "wind ");
+    assert_eq!(
+        search.results,
+        vec![Point3::new(6, 5, 0), Point3::new(5, 5, 1)]
+    );
+    assert_eq!(search.next(), Some(Point3::new(6, 5, 0)));
+    assert_eq!(search.next(), Some(Point3::new(5, 5, 1)));
+    assert_eq!(search.next(), Some(Point3::new(6, 5, 0)));
+
+    machine.remove(&Point3::new(6, 5, 0));
+    search.update(&machine);
+    assert_eq!(search.results, vec![Point3::new(5, 5, 1)]);
+    assert_eq!(search.current, None);
+
+    assert!(Search::new(&machine, "  ").results.is_empty());
+}
+
+/// Test that placed pipes turn towards holes and bend loose ends of
+/// neighboring pipes, and that this is undone together.
+#[test]
+fn test_auto_connect_pipes() {
+    let mut machine = Machine::new_sandbox(Vector3::new(5, 5, 1));
+    Edit::SetBlocks(maplit::hashmap! {
+        Point3::new(0, 0, 0) => Some(PlacedBlock { block: Block::WindSource }),
+    })
+    .run(&mut machine);
+
+    let vertical_pipe = Some(PlacedBlock {
+        block: Block::Pipe(Dir3::Y_NEG, Dir3::Y_POS),
+    });
+    let place_pipe = |machine: &Machine, pos| {
+        Edit::SetBlocks(auto_connect::connect_pipes(
+            machine,
+            maplit::hashmap! { pos => vertical_pipe.clone() },
+        ))
+    };
+
+    // The pipe is turned towards the wind source.
+    place_pipe(&machine, Point3::new(1, 0, 0)).run(&mut machine);
+    assert_eq!(
+        machine.get(&Point3::new(1, 0, 0)).unwrap().block,
+        Block::Pipe(Dir3::X_NEG, Dir3::X_POS)
+    );
+
+    // The loose end of the first pipe is bent towards the second one.
+    let undo = place_pipe(&machine, Point3::new(1, 1, 0)).run(&mut machine);
+    assert_eq!(
+        machine.get(&Point3::new(1, 0, 0)).unwrap().block,
+        Block::Pipe(Dir3::X_NEG, Dir3::Y_POS)
+    );
+    assert_eq!(
+        machine.get(&Point3::new(1, 1, 0)).unwrap().block,
+        Block::Pipe(Dir3::Y_NEG, Dir3::Y_POS)
+    );
+
+    undo.run(&mut machine);
+    assert_eq!(
+        machine.get(&Point3::new(1, 0, 0)).unwrap().block,
+        Block::Pipe(Dir3::X_NEG, Dir3::X_POS)
+    );
+    assert!(machine.get(&Point3::new(1, 1, 0)).is_none());
+}
+
+/// Test that the eraser only picks blocks of the filtered type, and never the
+/// inputs and outputs of a level.
+#[test]
+fn test_erasable_positions() {
+    let mut machine = Machine::new_sandbox(Vector3::new(5, 5, 1));
+    Edit::SetBlocks(maplit::hashmap! {
+        Point3::new(0, 0, 0) => Some(PlacedBlock { block: Block::WindSource }),
+        Point3::new(1, 0, 0) => Some(PlacedBlock {
+            block: Block::Pipe(Dir3::X_NEG, Dir3::X_POS),
+        }),
+        Point3::new(3, 3, 0) => Some(PlacedBlock { block: Block::Solid }),
+    })
+    .run(&mut machine);
+    machine.set(
+        &Point3::new(1, 1, 0),
+        Some(PlacedBlock {
+            block: Block::Input {
+                out_dir: Dir3::X_POS,
+                index: 0,
+            },
+        }),
+    );
+
+    let rect = rect_positions(&Point3::new(2, 2, 0), &Point3::new(0, 0, 0));
+    assert_eq!(rect.len(), 9);
+
+    assert_eq!(
+        erasable_positions(&machine, rect.iter().cloned(), None),
+        vec![Point3::new(0, 0, 0), Point3::new(1, 0, 0)]
+    );
+    assert_eq!(
+        erasable_positions(&machine, rect.into_iter(), Some(BlockType::Pipe)),
+        vec![Point3::new(1, 0, 0)]
+    );
+}
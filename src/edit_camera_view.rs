@@ -65,6 +65,17 @@ impl EditCameraView {
         }
     }
 
+    /// Returns a view of the given target from the given distance, e.g. to
+    /// show a whole machine.
+    pub fn looking_at(target: na::Point3<f32>, distance: f32) -> Self {
+        Self {
+            target,
+            min_distance: distance,
+            height: 0.75 * distance,
+            ..Self::new()
+        }
+    }
+
     pub fn target(&self) -> na::Point3<f32> {
         self.target
     }
@@ -73,6 +84,11 @@ impl EditCameraView {
         self.target = target;
     }
 
+    /// Rotates the view around the target.
+    pub fn rotate(&mut self, yaw_delta_radians: f32) {
+        self.yaw_radians += yaw_delta_radians;
+    }
+
     pub fn view(&self) -> na::Matrix4<f32> {
         let up = na::Vector3::new(0.0, 0.0, 1.0);
 
@@ -0,0 +1,113 @@
+//! Air blocks that are added to a machine for execution.
+//!
+//! Blips that leave the machine through a hole fall down through air, and
+//! wind that leaves a hole is shown going into air. The air blocks are kept
+//! next to the machine instead of being placed into it, so that starting
+//! execution does not copy the blocks that the machine shares with the
+//! editor.
+
+use std::collections::HashMap;
+use std::ops::Index;
+
+use log::info;
+
+use crate::machine::grid::{Point3, Vector3};
+use crate::machine::{Block, BlockIndex, Machine, PlacedBlock};
+
+/// A machine together with its air blocks. Air blocks are indexed after the
+/// blocks of the machine, so block indices of the machine stay valid.
+pub struct MachineWithAir {
+    machine: Machine,
+    air: Vec<(Point3, PlacedBlock)>,
+    air_indices: HashMap<Point3, BlockIndex>,
+}
+
+impl MachineWithAir {
+    pub fn new(machine: Machine) -> Self {
+        // Air blocks are indexed right after the machine's blocks, so there
+        // must not be any gaps.
+        assert!(machine.is_contiguous());
+
+        let air_positions = machine.air_positions();
+
+        info!("Adding {} air blocks to machine", air_positions.len());
+
+        let air: Vec<_> = air_positions
+            .into_iter()
+            .map(|pos| (pos, PlacedBlock { block: Block::Air }))
+            .collect();
+        let air_indices = air
+            .iter()
+            .enumerate()
+            .map(|(index, (pos, _))| (*pos, machine.num_blocks() + index))
+            .collect();
+
+        Self {
+            machine,
+            air,
+            air_indices,
+        }
+    }
+
+    /// Returns the machine without air blocks.
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn size(&self) -> Vector3 {
+        self.machine.size()
+    }
+
+    /// Returns the number of blocks, including air blocks.
+    pub fn num_blocks(&self) -> usize {
+        self.machine.num_blocks() + self.air.len()
+    }
+
+    pub fn get_index(&self, p: &Point3) -> Option<BlockIndex> {
+        self.machine
+            .get_index(p)
+            .or_else(|| self.air_indices.get(p).cloned())
+    }
+
+    pub fn get(&self, p: &Point3) -> Option<&PlacedBlock> {
+        self.get_with_index(p).map(|(_, placed_block)| placed_block)
+    }
+
+    pub fn get_with_index(&self, p: &Point3) -> Option<(BlockIndex, &PlacedBlock)> {
+        self.get_index(p).map(|index| (index, &self[index].1))
+    }
+
+    pub fn block_at_index(&self, index: BlockIndex) -> &Block {
+        &self[index].1.block
+    }
+
+    pub fn iter_indices(&self) -> impl Iterator<Item = BlockIndex> {
+        0..self.num_blocks()
+    }
+
+    /// Iterates over the blocks of the machine, followed by the air blocks.
+    pub fn iter_blocks(&self) -> impl Iterator<Item = (BlockIndex, &(Point3, PlacedBlock))> {
+        let num_machine_blocks = self.machine.num_blocks();
+
+        self.machine.iter_blocks().chain(
+            self.air
+                .iter()
+                .enumerate()
+                .map(move |(index, block)| (num_machine_blocks + index, block)),
+        )
+    }
+}
+
+impl Index<BlockIndex> for MachineWithAir {
+    type Output = (Point3, PlacedBlock);
+
+    fn index(&self, index: BlockIndex) -> &Self::Output {
+        let num_machine_blocks = self.machine.num_blocks();
+
+        if index < num_machine_blocks {
+            &self.machine.blocks.data[index]
+        } else {
+            &self.air[index - num_machine_blocks]
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Analysis of reference solutions for level authors. Running a solution for
+//! many generated examples shows how hard a level's spec is to satisfy, which
+//! helps with tuning its difficulty.
+
+use std::collections::BTreeMap;
+
+use rand::SeedableRng;
+use serde::Serialize;
+
+use crate::exec::{headless, Exec, LevelStatus};
+use crate::machine::{Machine, TickNum};
+
+/// Minimum, maximum and mean of a number of samples.
+#[derive(Debug, Clone, Serialize)]
+pub struct Stats {
+    pub min: usize,
+    pub max: usize,
+    pub mean: f64,
+}
+
+impl Stats {
+    /// Returns `None` if there are no samples.
+    pub fn new(samples: &[usize]) -> Option<Self> {
+        let min = *samples.iter().min()?;
+        let max = *samples.iter().max()?;
+        let mean = samples.iter().sum::<usize>() as f64 / samples.len() as f64;
+
+        Some(Stats { min, max, mean })
+    }
+}
+
+/// A run in which the solution did not complete the level.
+#[derive(Debug, Clone, Serialize)]
+pub struct Failure {
+    /// Seed that the level's example was generated with.
+    pub seed: u64,
+
+    pub status: LevelStatus,
+    pub reason: Option<String>,
+}
+
+/// How much leeway the level's inputs and outputs leave to solutions.
+#[derive(Debug, Clone, Serialize)]
+pub struct Tightness {
+    /// Length of the longest input or output sequence in each example. Since
+    /// inputs are fed one blip per tick, this is a rough lower bound for the
+    /// number of ticks that a solution needs.
+    pub min_ticks: Option<Stats>,
+
+    /// Number of blips that the outputs expect in each example.
+    pub num_output_blips: Option<Stats>,
+
+    /// Mean ratio of `min_ticks` to the number of ticks the solution needed
+    /// in the completed runs. Values close to 1 mean that the solution is
+    /// about as fast as the spec allows.
+    pub ratio: Option<f64>,
+}
+
+/// Report about running a solution for a number of generated examples.
+#[derive(Debug, Clone, Serialize)]
+pub struct Analysis {
+    /// Id of the level that was analyzed, if the machine has one.
+    pub level: Option<String>,
+
+    pub num_runs: usize,
+    pub num_completed: usize,
+
+    /// Number of ticks needed in the completed runs.
+    pub ticks: Option<Stats>,
+
+    pub failures: Vec<Failure>,
+
+    /// Number of blocks placed by the player, by type of block.
+    pub block_usage: BTreeMap<String, usize>,
+
+    pub num_blocks: usize,
+    pub max_num_blocks: Option<usize>,
+
+    pub footprint: usize,
+    pub max_footprint: Option<usize>,
+
+    pub tightness: Tightness,
+}
+
+/// Runs the machine for `num_runs` examples of its level, generated with the
+/// seeds `0..num_runs`, and for at most `max_ticks` each.
+pub fn analyze(machine: &Machine, num_runs: usize, max_ticks: TickNum) -> Analysis {
+    let mut ticks = Vec::new();
+    let mut failures = Vec::new();
+    let mut min_ticks = Vec::new();
+    let mut num_output_blips = Vec::new();
+    let mut ratios = Vec::new();
+
+    for seed in 0..num_runs as u64 {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let mut exec = Exec::new(machine.clone(), &mut rng);
+
+        let example_min_ticks = exec.level_progress().map(|progress| {
+            let inputs_outputs = &progress.inputs_outputs;
+            let input_lens = inputs_outputs.inputs.iter().map(Vec::len);
+            let output_lens = inputs_outputs.outputs.iter().map(Vec::len);
+
+            num_output_blips.push(inputs_outputs.outputs.iter().map(Vec::len).sum());
+
+            input_lens.chain(output_lens).max().unwrap_or(0)
+        });
+        if let Some(example_min_ticks) = example_min_ticks {
+            min_ticks.push(example_min_ticks);
+        }
+
+        let report = headless::run_exec(&mut exec, max_ticks);
+
+        if report.status == LevelStatus::Completed {
+            ticks.push(report.ticks);
+
+            if let Some(example_min_ticks) = example_min_ticks.filter(|_| report.ticks > 0) {
+                ratios.push(example_min_ticks as f64 / report.ticks as f64);
+            }
+        } else {
+            failures.push(Failure {
+                seed,
+                status: report.status,
+                reason: report.failure,
+            });
+        }
+    }
+
+    let mut block_usage = BTreeMap::new();
+    for (_, (_, placed_block)) in machine.iter_player_blocks() {
+        let block_type = format!("{:?}", placed_block.block.block_type());
+        *block_usage.entry(block_type).or_insert(0) += 1;
+    }
+
+    let constraints = machine.level.as_ref().map(|level| &level.constraints);
+
+    Analysis {
+        level: machine.level.as_ref().map(|level| level.id()),
+        num_runs,
+        num_completed: ticks.len(),
+        ticks: Stats::new(&ticks),
+        failures,
+        block_usage,
+        num_blocks: machine.num_player_blocks(),
+        max_num_blocks: constraints.and_then(|constraints| constraints.max_num_blocks),
+        footprint: machine.footprint(),
+        max_footprint: constraints.and_then(|constraints| constraints.max_footprint),
+        tightness: Tightness {
+            min_ticks: Stats::new(&min_ticks),
+            num_output_blips: Stats::new(&num_output_blips),
+            ratio: if ratios.is_empty() {
+                None
+            } else {
+                Some(ratios.iter().sum::<f64>() / ratios.len() as f64)
+            },
+        },
+    }
+}
@@ -73,7 +73,7 @@ impl AnimState {
 
         let out_deadend = exec.neighbor_map()[block_index].map(|dir, &neighbor_index| {
             if let Some(neighbor_index) = neighbor_index {
-                let neighbor_block = exec.machine().block_at_index(neighbor_index);
+                let neighbor_block = exec.machine_with_air().block_at_index(neighbor_index);
 
                 if !neighbor_block.has_wind_hole_in(dir.invert(), activation.is_some()) {
                     // If neighboring block has no wind connection in this
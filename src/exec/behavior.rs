@@ -0,0 +1,326 @@
+//! Tick behavior of each block type.
+//!
+//! Every block type has a `Behavior` in the block registry, see
+//! `machine::registry`. Execution calls its hooks in the phases of a tick.
+//! Block types without special effects use `Passive`, which only conducts
+//! wind according to the block's holes.
+
+use log::warn;
+
+use crate::machine::grid::{Dir3, DirMap3, Point3};
+use crate::machine::{BlipKind, Block, BlockIndex, TickNum};
+use crate::script;
+use crate::util::gen_vec::GenVec;
+
+use super::air::MachineWithAir;
+use super::neighbors::NeighborMap;
+use super::{advect_wind, Activation, Blip, BlipSpawnMode, BlockState, LevelProgress};
+
+/// State that is available when determining the wind of a block.
+pub struct WindContext<'a> {
+    pub block_index: BlockIndex,
+    pub machine: &'a MachineWithAir,
+    pub neighbor_map: &'a NeighborMap,
+    pub wind_out: &'a [DirMap3<bool>],
+    pub prev_activation: &'a [Activation],
+    pub activation: &'a [Activation],
+}
+
+impl<'a> WindContext<'a> {
+    /// Returns the wind that flows through the block from its neighbors.
+    pub fn advect(&self) -> DirMap3<bool> {
+        advect_wind(
+            self.block_index,
+            self.machine,
+            self.neighbor_map,
+            self.wind_out,
+            self.activation,
+        )
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.activation[self.block_index].is_some()
+    }
+
+    pub fn was_active(&self) -> bool {
+        self.prev_activation[self.block_index].is_some()
+    }
+}
+
+/// State that is available when a block checks if it activates itself.
+pub struct SelfActivateContext<'a> {
+    pub block_index: BlockIndex,
+    pub blocks: &'a MachineWithAir,
+    pub state: &'a BlockState,
+    pub level_progress: &'a mut Option<LevelProgress>,
+    pub neighbor_map: &'a NeighborMap,
+    pub next_blip_count: &'a [usize],
+}
+
+impl<'a> SelfActivateContext<'a> {
+    fn neighbor(&self, dir: Dir3) -> Option<BlockIndex> {
+        self.neighbor_map[self.block_index][dir]
+    }
+}
+
+/// State that is available when running the effect of an activated block.
+pub struct ActivateContext<'a> {
+    pub block_index: BlockIndex,
+    pub block_pos: &'a Point3,
+    pub state: &'a mut BlockState,
+    pub blips: &'a mut GenVec<Blip>,
+    pub neighbor_map: &'a NeighborMap,
+    pub next_blip_count: &'a [usize],
+    pub script: Option<&'a script::Runtime>,
+    pub cur_tick: TickNum,
+}
+
+impl<'a> ActivateContext<'a> {
+    /// Returns true if no blip will be at the neighbor in the given
+    /// direction in the next tick.
+    fn is_free(&self, dir: Dir3) -> bool {
+        self.neighbor_map[self.block_index][dir].map_or(true, |neighbor_index| {
+            self.next_blip_count[neighbor_index] == 0
+        })
+    }
+
+    fn spawn(&mut self, kind: BlipKind, dir: Dir3, spawn_mode: BlipSpawnMode) {
+        self.blips
+            .add(Blip::new(kind, *self.block_pos, dir, Some(dir), spawn_mode));
+    }
+}
+
+/// Hooks that define how a block type acts during execution. The block that
+/// is passed to each hook is of the type that the behavior is registered
+/// for.
+pub trait Behavior: Sync {
+    /// Returns the directions in which wind leaves the block in this tick.
+    fn wind_out(&self, _block: &Block, ctx: &WindContext) -> DirMap3<bool> {
+        ctx.advect()
+    }
+
+    /// Returns true if the block may activate itself, i.e. if
+    /// `self_activate` can return a blip kind. Such blocks keep their chunk
+    /// awake during execution.
+    fn is_spontaneous(&self) -> bool {
+        false
+    }
+
+    /// Returns true if blips in the block fall down, regardless of wind.
+    fn must_fall(&self, _block: &Block, _is_active: bool) -> bool {
+        false
+    }
+
+    /// Returns the kind of blip that the block activates itself with, if
+    /// any. This is checked in every tick.
+    fn self_activate(&self, _block: &Block, _ctx: &mut SelfActivateContext) -> Option<BlipKind> {
+        None
+    }
+
+    /// Runs the effect of the block one tick after it has been activated.
+    fn run_prev_activated(&self, _block: &Block, _ctx: &mut ActivateContext, _blip_kind: BlipKind) {
+    }
+
+    /// Runs the effect of the block in the tick in which it is activated.
+    fn run_activated(&self, _block: &Block, _ctx: &mut ActivateContext, _blip_kind: BlipKind) {}
+}
+
+pub struct Passive;
+
+impl Behavior for Passive {}
+
+pub struct WindSource;
+
+impl Behavior for WindSource {
+    fn wind_out(&self, _block: &Block, _ctx: &WindContext) -> DirMap3<bool> {
+        DirMap3::from_fn(|_| true)
+    }
+}
+
+pub struct BlipSpawn;
+
+impl Behavior for BlipSpawn {
+    fn is_spontaneous(&self) -> bool {
+        true
+    }
+
+    fn self_activate(&self, block: &Block, ctx: &mut SelfActivateContext) -> Option<BlipKind> {
+        if let Block::BlipSpawn { out_dir, kind, .. } = block {
+            if let Some(neighbor_index) = ctx.neighbor(*out_dir) {
+                // The blip spawn acts only if there is no blip at the output position.
+                let is_safe = ctx.next_blip_count[neighbor_index] == 0
+                    || ctx.blocks[neighbor_index]
+                        .1
+                        .block
+                        .is_blip_killer(Some(*out_dir))
+                        .is_some();
+                if is_safe && ctx.state.spawns_left.map_or(true, |n| n > 0) {
+                    return Some(*kind);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn run_activated(&self, block: &Block, ctx: &mut ActivateContext, _blip_kind: BlipKind) {
+        if let Block::BlipSpawn { out_dir, kind, .. } = block {
+            ctx.state.spawns_left = ctx.state.spawns_left.map(|n| n - 1);
+            ctx.spawn(*kind, *out_dir, BlipSpawnMode::Bridge);
+        }
+    }
+}
+
+pub struct BlipDuplicator;
+
+impl Behavior for BlipDuplicator {
+    fn run_activated(&self, block: &Block, ctx: &mut ActivateContext, blip_kind: BlipKind) {
+        if let Block::BlipDuplicator { out_dirs, .. } = block {
+            for &out_dir in &[out_dirs.0, out_dirs.1] {
+                if ctx.is_free(out_dir) {
+                    ctx.spawn(blip_kind, out_dir, BlipSpawnMode::Bridge);
+                }
+            }
+        }
+    }
+}
+
+pub struct BlipWindSource;
+
+impl Behavior for BlipWindSource {
+    fn wind_out(&self, block: &Block, ctx: &WindContext) -> DirMap3<bool> {
+        if ctx.is_active() {
+            DirMap3::from_fn(|dir| block.has_wind_source(dir))
+        } else {
+            DirMap3::from_fn(|_| false)
+        }
+    }
+}
+
+pub struct Input;
+
+impl Behavior for Input {
+    fn is_spontaneous(&self) -> bool {
+        true
+    }
+
+    fn wind_out(&self, block: &Block, _ctx: &WindContext) -> DirMap3<bool> {
+        match block {
+            Block::Input { out_dir, .. } => DirMap3::from_fn(|dir| dir == *out_dir),
+            _ => DirMap3::from_fn(|_| false),
+        }
+    }
+
+    fn self_activate(&self, block: &Block, ctx: &mut SelfActivateContext) -> Option<BlipKind> {
+        if let Block::Input { out_dir, index } = block {
+            if let Some(neighbor_index) = ctx.neighbor(*out_dir) {
+                // The input acts only if there is no blip at the output position.
+                if ctx.next_blip_count[neighbor_index] == 0 {
+                    return ctx
+                        .level_progress
+                        .as_mut()
+                        .and_then(|p| p.feed_input(*index));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn run_activated(&self, block: &Block, ctx: &mut ActivateContext, blip_kind: BlipKind) {
+        if let Block::Input { out_dir, .. } = block {
+            ctx.spawn(blip_kind, *out_dir, BlipSpawnMode::Bridge);
+        }
+    }
+}
+
+pub struct Air;
+
+impl Behavior for Air {
+    fn must_fall(&self, _block: &Block, _is_active: bool) -> bool {
+        true
+    }
+}
+
+pub struct DetectorBlipDuplicator;
+
+impl Behavior for DetectorBlipDuplicator {
+    fn run_activated(&self, block: &Block, ctx: &mut ActivateContext, blip_kind: BlipKind) {
+        if let Block::DetectorBlipDuplicator { out_dir, .. } = block {
+            ctx.spawn(blip_kind, *out_dir, BlipSpawnMode::Quick);
+        }
+    }
+}
+
+pub struct PipeButton;
+
+impl Behavior for PipeButton {
+    fn must_fall(&self, _block: &Block, is_active: bool) -> bool {
+        is_active
+    }
+}
+
+pub struct DetectorWindSource;
+
+impl Behavior for DetectorWindSource {
+    fn wind_out(&self, block: &Block, ctx: &WindContext) -> DirMap3<bool> {
+        let pipe = ctx.advect();
+
+        if ctx.is_active() {
+            DirMap3::from_fn(|dir| block.has_wind_source(dir) || pipe[dir])
+        } else {
+            pipe
+        }
+    }
+}
+
+pub struct BlipDeleter;
+
+impl Behavior for BlipDeleter {
+    fn run_activated(&self, block: &Block, ctx: &mut ActivateContext, blip_kind: BlipKind) {
+        if let Block::BlipDeleter { out_dirs } = block {
+            for &out_dir in &[out_dirs.0, out_dirs.1] {
+                if !ctx.is_free(out_dir) {
+                    ctx.spawn(blip_kind, out_dir, BlipSpawnMode::Bridge);
+                }
+            }
+        }
+    }
+}
+
+pub struct Delay;
+
+impl Behavior for Delay {
+    fn wind_out(&self, block: &Block, ctx: &WindContext) -> DirMap3<bool> {
+        match block {
+            Block::Delay { flow_dir } if ctx.was_active() => {
+                DirMap3::from_fn(|dir| dir == *flow_dir)
+            }
+            _ => DirMap3::from_fn(|_| false),
+        }
+    }
+
+    fn run_prev_activated(&self, block: &Block, ctx: &mut ActivateContext, blip_kind: BlipKind) {
+        if let Block::Delay { flow_dir } = block {
+            ctx.spawn(blip_kind, *flow_dir, BlipSpawnMode::Quick);
+        }
+    }
+}
+
+pub struct Scripted;
+
+impl Behavior for Scripted {
+    fn run_activated(&self, block: &Block, ctx: &mut ActivateContext, blip_kind: BlipKind) {
+        if let Block::Scripted { name, flow_dir, .. } = block {
+            let result = ctx.script.map_or(Err(script::Error::Disabled), |script| {
+                script.run_block(name, blip_kind, ctx.cur_tick)
+            });
+
+            match result {
+                Ok(Some(kind)) => ctx.spawn(kind, *flow_dir, BlipSpawnMode::Quick),
+                Ok(None) => (),
+                Err(err) => warn!("Scripted block {} failed: {}", name, err),
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Execution of machines without rendering, e.g. to verify solutions.
+
+use rand::Rng;
+use serde::Serialize;
+
+use crate::exec::{Exec, LevelStatus, Outcome, Score};
+use crate::machine::{Machine, TickNum};
+
+/// Summary of a headless run.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// Number of ticks that have been run.
+    pub ticks: TickNum,
+
+    pub status: LevelStatus,
+
+    /// Why the level was failed, if it was.
+    pub failure: Option<String>,
+
+    /// Metrics of the machine, if it has completed the level.
+    pub score: Option<Score>,
+
+    /// Number of blips that exist after the last tick.
+    pub num_blips: usize,
+}
+
+impl Report {
+    pub fn new(exec: &Exec) -> Self {
+        let status = exec.next_level_status();
+
+        let failure = match exec.outcome() {
+            Some(Outcome::Failure { reason, .. }) => Some(reason.to_string()),
+            _ => None,
+        };
+
+        let score = if status == LevelStatus::Completed {
+            Some(Score::new(exec.cur_tick(), exec.machine()))
+        } else {
+            None
+        };
+
+        Report {
+            ticks: exec.cur_tick(),
+            status,
+            failure,
+            score,
+            num_blips: exec.blips().len(),
+        }
+    }
+}
+
+/// Runs the machine until its level has been completed or failed, but for at
+/// most `max_ticks`. Machines without a level always run for `max_ticks`.
+pub fn run<R: Rng + ?Sized>(machine: Machine, max_ticks: TickNum, rng: &mut R) -> Report {
+    let mut exec = Exec::new(machine, rng);
+
+    run_exec(&mut exec, max_ticks)
+}
+
+/// Continues running an existing execution, like `run`.
+pub fn run_exec(exec: &mut Exec, max_ticks: TickNum) -> Report {
+    while exec.cur_tick() < max_ticks && exec.next_level_status() == LevelStatus::Running {
+        exec.update();
+    }
+
+    Report::new(exec)
+}
@@ -1,14 +1,102 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
 use crate::exec::Activation;
-use crate::machine::level::{self, InputsOutputs};
-use crate::machine::{BlipKind, Block, BlockIndex, Machine};
+use crate::machine::level::{self, InputsOutputs, Violation};
+use crate::machine::{BlipKind, Block, BlockIndex, Machine, TickNum};
+use crate::script;
 
-#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum LevelStatus {
     Running,
     Completed,
     Failed,
 }
 
+/// Reasons for failing a level.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum FailureReason {
+    /// An output has been activated by a blip that it did not expect.
+    UnexpectedBlip {
+        output_index: usize,
+
+        /// The blip kind that the output expected next, or `None` if the
+        /// output had already received all of its expected blips.
+        expected: Option<BlipKind>,
+
+        received: BlipKind,
+    },
+
+    /// The machine does not satisfy the constraints of the level.
+    ConstraintViolated(Violation),
+
+    /// The inputs and outputs of the level could not be generated, e.g.
+    /// because its script failed.
+    ScriptFailed(script::Error),
+}
+
+impl FailureReason {
+    /// Returns the index of the output that caused the failure, if any.
+    pub fn output_index(&self) -> Option<usize> {
+        match self {
+            FailureReason::UnexpectedBlip { output_index, .. } => Some(*output_index),
+            FailureReason::ConstraintViolated(_) | FailureReason::ScriptFailed(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for FailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailureReason::UnexpectedBlip {
+                output_index,
+                expected: Some(expected),
+                received,
+            } => write!(
+                f,
+                "Output {} expected a {} blip, but received a {} blip",
+                output_index, expected, received,
+            ),
+            FailureReason::UnexpectedBlip {
+                output_index,
+                expected: None,
+                received,
+            } => write!(
+                f,
+                "Output {} received a {} blip after it was already complete",
+                output_index, received,
+            ),
+            FailureReason::ConstraintViolated(violation) => write!(f, "{}", violation),
+            FailureReason::ScriptFailed(err) => {
+                write!(f, "Failed to generate the level's inputs: {}", err)
+            }
+        }
+    }
+}
+
+/// The final result of executing a machine for a level.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Outcome {
+    Success {
+        tick: TickNum,
+    },
+    Failure {
+        reason: FailureReason,
+        tick: TickNum,
+    },
+}
+
+impl Outcome {
+    pub fn tick(&self) -> TickNum {
+        match self {
+            Outcome::Success { tick } => *tick,
+            Outcome::Failure { tick, .. } => *tick,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Input {
     pub block_index: Option<BlockIndex>,
@@ -20,6 +108,33 @@ pub struct Output {
     pub block_index: Option<BlockIndex>,
     pub num_fed: usize,
     pub failed: bool,
+
+    /// The blip kind that caused the output to fail, if any.
+    pub unexpected: Option<BlipKind>,
+}
+
+/// How many of its expected blips an output has received so far.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct OutputProgress {
+    pub num_fed: usize,
+    pub num_expected: usize,
+    pub failed: bool,
+}
+
+impl OutputProgress {
+    pub fn is_completed(&self) -> bool {
+        !self.failed && self.num_fed == self.num_expected
+    }
+
+    /// Returns the fraction of expected blips that have been received, between
+    /// 0 and 1.
+    pub fn fraction(&self) -> f32 {
+        if self.num_expected == 0 {
+            1.0
+        } else {
+            self.num_fed as f32 / self.num_expected as f32
+        }
+    }
 }
 
 /// `LevelProgress` stores the progress through the current `InputsOutputs`
@@ -89,6 +204,7 @@ impl LevelProgress {
                     block_index,
                     num_fed: 0,
                     failed: false,
+                    unexpected: None,
                 }
             })
             .collect();
@@ -118,6 +234,28 @@ impl LevelProgress {
         })
     }
 
+    /// Returns the inputs that have not been fed yet into the input with the
+    /// given index.
+    pub fn remaining_inputs(&self, index: usize) -> &[Option<level::Input>] {
+        self.inputs.get(index).map_or(&[], |input| {
+            &self.inputs_outputs.inputs[index][input.num_fed..]
+        })
+    }
+
+    /// Returns the number of ticks until the input with the given index feeds
+    /// its next blip, together with the blip's kind. This assumes that the
+    /// input is not blocked by a blip in front of it.
+    pub fn next_input(&self, index: usize) -> Option<(usize, BlipKind)> {
+        self.remaining_inputs(index)
+            .iter()
+            .enumerate()
+            .find_map(|(i, input)| {
+                input.map(|input| match input {
+                    level::Input::Blip(kind) => (i + 1, kind),
+                })
+            })
+    }
+
     pub fn update_outputs(&mut self, next_activation: &[Activation]) {
         for (index, output) in self.outputs.iter_mut().enumerate() {
             let blip_kind = output
@@ -129,13 +267,22 @@ impl LevelProgress {
 
                 if output.num_fed < spec.len() && spec[output.num_fed] == blip_kind {
                     output.num_fed += 1;
-                } else {
+                } else if !output.failed {
                     output.failed = true;
+                    output.unexpected = Some(blip_kind);
                 }
             }
         }
     }
 
+    pub fn output_progress(&self, index: usize) -> Option<OutputProgress> {
+        self.outputs.get(index).map(|output| OutputProgress {
+            num_fed: output.num_fed,
+            num_expected: self.inputs_outputs.outputs[index].len(),
+            failed: output.failed,
+        })
+    }
+
     pub fn expected_output(&self, index: usize) -> Option<BlipKind> {
         self.outputs.get(index).and_then(|output| {
             let spec = &self.inputs_outputs.outputs[index];
@@ -148,6 +295,21 @@ impl LevelProgress {
         })
     }
 
+    pub fn failure_reason(&self) -> Option<FailureReason> {
+        self.outputs
+            .iter()
+            .enumerate()
+            .find_map(|(output_index, output)| {
+                output
+                    .unexpected
+                    .map(|received| FailureReason::UnexpectedBlip {
+                        output_index,
+                        expected: self.expected_output(output_index),
+                        received,
+                    })
+            })
+    }
+
     pub fn status(&self) -> LevelStatus {
         let any_failed = self.outputs.iter().any(|output| output.failed);
         let all_finished = self
@@ -165,3 +327,36 @@ impl LevelProgress {
         }
     }
 }
+
+/// Metrics of a machine that has successfully completed a level.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Score {
+    /// Number of ticks that were needed to feed all of the outputs.
+    pub num_ticks: TickNum,
+
+    /// Number of blocks that were placed by the player.
+    pub num_blocks: usize,
+
+    /// Volume of the bounding box around the blocks placed by the player.
+    pub footprint: usize,
+}
+
+impl Score {
+    pub fn new(num_ticks: TickNum, machine: &Machine) -> Self {
+        Self {
+            num_ticks,
+            num_blocks: machine.num_player_blocks(),
+            footprint: machine.footprint(),
+        }
+    }
+
+    /// Returns the best value of each metric, taken from two scores. Note that
+    /// the resulting score need not have been achieved by a single machine.
+    pub fn best(&self, other: &Score) -> Score {
+        Score {
+            num_ticks: self.num_ticks.min(other.num_ticks),
+            num_blocks: self.num_blocks.min(other.num_blocks),
+            footprint: self.footprint.min(other.footprint),
+        }
+    }
+}
@@ -1,26 +1,40 @@
+pub mod air;
+pub mod analysis;
 pub mod anim;
+pub mod behavior;
+pub mod headless;
 pub mod level;
 pub mod neighbors;
 pub mod play;
+pub mod race;
+pub mod sleep;
 #[cfg(test)]
 mod tests;
+pub mod timing;
 pub mod view;
+pub mod wind;
 
 use std::cmp;
-use std::collections::HashSet;
+use std::fmt;
 use std::mem;
 
 use coarse_prof::profile;
-use log::info;
+use log::{info, warn};
 use rand::Rng;
 
 use crate::machine::grid::{Dir3, DirMap3, Point3, Vector3};
-use crate::machine::{BlipKind, Block, BlockIndex, Machine, PlacedBlock, TickNum};
-use crate::util::vec_option::VecOption;
+use crate::machine::level::Input;
+use crate::machine::{registry, BlipKind, Block, BlockIndex, Machine, TickNum};
+use crate::script;
+use crate::util::gen_vec::{self, GenVec};
 
+use air::MachineWithAir;
+use behavior::{ActivateContext, SelfActivateContext, WindContext};
 use neighbors::NeighborMap;
+use sleep::Chunks;
+use wind::WindCache;
 
-pub use level::{LevelProgress, LevelStatus};
+pub use level::{FailureReason, LevelProgress, LevelStatus, Outcome, OutputProgress, Score};
 pub use play::TickTime;
 pub use view::ExecView;
 
@@ -127,7 +141,9 @@ pub struct Blip {
     pub status: BlipStatus,
 }
 
-pub type BlipIndex = usize;
+/// Stable handle of a blip. It does not refer to another blip after the
+/// blip has died.
+pub type BlipId = gen_vec::Id;
 
 impl Blip {
     fn new(
@@ -154,6 +170,31 @@ impl Blip {
     }
 }
 
+/// Reasons why a blip could not be spawned with `Exec::spawn_blip`.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum SpawnError {
+    /// There is no block at the position, so the blip would die right away.
+    NoBlock,
+
+    /// The block has no holes that blips could move through.
+    NoMoveHoles,
+
+    /// There already is a blip at the position.
+    Occupied,
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpawnError::NoBlock => write!(f, "There is no block to spawn a blip in"),
+            SpawnError::NoMoveHoles => write!(f, "Blips can not move through this block"),
+            SpawnError::Occupied => write!(f, "There already is a blip here"),
+        }
+    }
+}
+
+impl std::error::Error for SpawnError {}
+
 pub type Activation = Option<BlipKind>;
 
 pub struct BlocksState {
@@ -162,12 +203,10 @@ pub struct BlocksState {
 }
 
 impl BlocksState {
-    fn new_initial(machine: &Machine) -> Self {
-        // We assume that the machine's blocks are contiguous in memory, so that
-        // we can store block state as a Vec, instead of wasting memory or
-        // cycles on VecOption while executing.
-        assert!(machine.is_contiguous());
-
+    fn new_initial(machine: &MachineWithAir) -> Self {
+        // The machine's blocks are contiguous in memory, so that we can store
+        // block state as a Vec, instead of wasting memory or cycles on
+        // VecOption while executing.
         Self {
             wind_out: vec![DirMap3::default(); machine.num_blocks()],
             activation: vec![Activation::default(); machine.num_blocks()],
@@ -175,42 +214,207 @@ impl BlocksState {
     }
 }
 
+/// State of a block that changes during execution. This is kept separately
+/// from the machine, so that the blocks stay as they were placed in the
+/// editor.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct BlockState {
+    /// Number of blips that a blip spawn can still produce, if limited.
+    pub spawns_left: Option<usize>,
+
+    /// Set when the player has switched the block off during execution, to
+    /// see what happens without it. Disabled blocks neither emit wind nor
+    /// activate themselves.
+    pub disabled: bool,
+}
+
+impl BlockState {
+    fn new_initial(block: &Block) -> Self {
+        let spawns_left = match block {
+            Block::BlipSpawn { num_spawns, .. } => *num_spawns,
+            _ => None,
+        };
+
+        Self {
+            spawns_left,
+            disabled: false,
+        }
+    }
+}
+
+/// The phases that each tick of `Exec::update` runs through, in order.
+/// Blocks that depend on the order of events within a tick, e.g. a
+/// duplicator next to a moving blip, can be understood by looking at the
+/// state between phases.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Hash)]
+pub enum Phase {
+    /// Advance the state of the previous tick.
+    Advance,
+
+    /// Spawn and move wind.
+    Wind,
+
+    /// Remove dead blips.
+    CleanUp,
+
+    /// Move blips and determine their next movement direction.
+    Move,
+
+    /// Count the blips that will be at each block after moving.
+    Count,
+
+    /// Run the effects of activated blocks, possibly spawning blips.
+    Effects,
+
+    /// Determine the next activations and which blips die.
+    Activate,
+
+    /// Determine the next level progress.
+    Progress,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 8] = [
+        Phase::Advance,
+        Phase::Wind,
+        Phase::CleanUp,
+        Phase::Move,
+        Phase::Count,
+        Phase::Effects,
+        Phase::Activate,
+        Phase::Progress,
+    ];
+
+    /// The phases that are animated one after another when showing the
+    /// phases within ticks. The other phases have no visible effect.
+    pub const ANIMATED: [Phase; 3] = [Phase::Wind, Phase::Move, Phase::Effects];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Advance => "Advance",
+            Phase::Wind => "Wind update",
+            Phase::CleanUp => "Clean up",
+            Phase::Move => "Blip movement",
+            Phase::Count => "Count blips",
+            Phase::Effects => "Block effects",
+            Phase::Activate => "Activation",
+            Phase::Progress => "Level progress",
+        }
+    }
+}
+
 pub struct Exec {
     cur_tick: TickNum,
 
-    machine: Machine,
+    machine: MachineWithAir,
     neighbor_map: NeighborMap,
 
     level_progress: Option<LevelProgress>,
     next_level_progress: Option<LevelProgress>,
 
-    blips: VecOption<Blip>,
+    /// The outcome of the level, once it has been completed or failed.
+    outcome: Option<Outcome>,
+
+    blips: GenVec<Blip>,
 
     blocks: BlocksState,
     next_blocks: BlocksState,
 
     prev_activation: Vec<Activation>,
 
+    /// Runtime state of each block, indexed by `BlockIndex`.
+    block_states: Vec<BlockState>,
+
     next_blip_count: Vec<usize>,
+
+    /// Number of ticks in which each block has been busy, i.e. it was
+    /// activated or had a blip passing through.
+    activity: Vec<usize>,
+
+    /// The level's script, which defines the behavior of scripted blocks.
+    script: Option<script::Runtime>,
+
+    /// Chunks of the machine that can be skipped while they are idle. If
+    /// this is `None`, all blocks are updated in every tick.
+    chunks: Option<Chunks>,
+
+    /// Blocks whose wind needs to be recomputed. If this is `None`, wind is
+    /// recomputed for all blocks in every tick.
+    wind_cache: Option<WindCache>,
 }
 
 impl Exec {
     pub fn new<R: Rng + ?Sized>(mut machine: Machine, rng: &mut R) -> Exec {
-        // Make the machine's blocks contiguous in memory.
+        // Machines that break the level's constraints fail right away.
+        let outcome = machine
+            .level
+            .as_ref()
+            .and_then(|level| level.constraints.check(&machine).into_iter().next())
+            .map(|violation| {
+                info!("Machine violates level constraints: {}", violation);
+
+                Outcome::Failure {
+                    reason: FailureReason::ConstraintViolated(violation),
+                    tick: 0,
+                }
+            });
+
+        // Make the machine's blocks contiguous in memory. This copies the
+        // blocks only if some have been removed.
         machine.gc();
 
-        initialize_air_blocks(&mut machine);
+        let machine = MachineWithAir::new(machine);
 
         let neighbor_map = NeighborMap::new_from_machine(&machine);
-        let level_progress = machine.level.as_ref().map(|level| {
-            let inputs_outputs = level.spec.gen_inputs_outputs(rng);
-            LevelProgress::new(Some(&machine), inputs_outputs)
+
+        // Levels whose inputs can not be generated fail right away as well,
+        // instead of being completed with nothing to do.
+        let mut script_error = None;
+        let level_progress = machine.machine().level.as_ref().map(|level| {
+            let inputs_outputs = match level.gen_inputs_outputs(rng) {
+                Ok(inputs_outputs) => inputs_outputs,
+                Err(err) => {
+                    warn!(
+                        "Failed to generate inputs for level {}: {}",
+                        level.id(),
+                        err
+                    );
+                    script_error = Some(err);
+                    level.spec.gen_inputs_outputs(rng)
+                }
+            };
+            LevelProgress::new(Some(machine.machine()), inputs_outputs)
+        });
+        let outcome = outcome.or_else(|| {
+            script_error.map(|err| Outcome::Failure {
+                reason: FailureReason::ScriptFailed(err),
+                tick: 0,
+            })
         });
         let next_level_progress = level_progress.clone();
         let blocks = BlocksState::new_initial(&machine);
         let next_blocks = BlocksState::new_initial(&machine);
         let prev_activation = vec![None; machine.num_blocks()];
+        let block_states = machine
+            .iter_blocks()
+            .map(|(_, (_, placed_block))| BlockState::new_initial(&placed_block.block))
+            .collect();
         let next_blip_count = vec![0; machine.num_blocks()];
+        let activity = vec![0; machine.num_blocks()];
+        let chunks = Some(Chunks::new(&machine));
+        let wind_cache = Some(WindCache::new(machine.num_blocks()));
+        let script = machine
+            .machine()
+            .level
+            .as_ref()
+            .and_then(|level| level.script.as_ref())
+            .and_then(|source| match script::Runtime::new(source) {
+                Ok(runtime) => Some(runtime),
+                Err(err) => {
+                    warn!("Failed to load level script: {}", err);
+                    None
+                }
+            });
 
         Exec {
             cur_tick: 0,
@@ -218,15 +422,33 @@ impl Exec {
             neighbor_map,
             level_progress,
             next_level_progress,
-            blips: VecOption::new(),
+            outcome,
+            blips: GenVec::new(),
             blocks,
             next_blocks,
             prev_activation,
+            block_states,
             next_blip_count,
+            activity,
+            script,
+            chunks,
+            wind_cache,
         }
     }
 
+    pub fn cur_tick(&self) -> TickNum {
+        self.cur_tick
+    }
+
+    /// Returns the machine that is being executed, without air blocks.
     pub fn machine(&self) -> &Machine {
+        self.machine.machine()
+    }
+
+    /// Returns the machine together with the air blocks that are added for
+    /// execution. Use this for looking up blocks by `BlockIndex`, which may
+    /// refer to air blocks.
+    pub fn machine_with_air(&self) -> &MachineWithAir {
         &self.machine
     }
 
@@ -242,7 +464,92 @@ impl Exec {
         self.next_level_progress.as_ref()
     }
 
-    pub fn blips(&self) -> &VecOption<Blip> {
+    /// Returns the inputs that are still to be fed into each of the level's
+    /// input blocks, in order.
+    pub fn queued_inputs(&self) -> impl Iterator<Item = (BlockIndex, &[Option<Input>])> {
+        self.next_level_progress.iter().flat_map(|progress| {
+            progress
+                .inputs
+                .iter()
+                .enumerate()
+                .filter_map(move |(index, input)| {
+                    input
+                        .block_index
+                        .map(|block_index| (block_index, progress.remaining_inputs(index)))
+                })
+        })
+    }
+
+    /// Returns the number of ticks until each of the level's input blocks
+    /// feeds its next blip, together with the blip's kind.
+    pub fn input_countdowns(&self) -> impl Iterator<Item = (BlockIndex, usize, BlipKind)> + '_ {
+        self.next_level_progress.iter().flat_map(|progress| {
+            progress
+                .inputs
+                .iter()
+                .enumerate()
+                .filter_map(move |(index, input)| {
+                    let (num_ticks, kind) = progress.next_input(index)?;
+                    input
+                        .block_index
+                        .map(|block_index| (block_index, num_ticks, kind))
+                })
+        })
+    }
+
+    /// Returns how far each of the level's output blocks has come in
+    /// receiving its expected blips.
+    pub fn output_progress(&self) -> impl Iterator<Item = (BlockIndex, OutputProgress)> + '_ {
+        self.next_level_progress.iter().flat_map(|progress| {
+            progress
+                .outputs
+                .iter()
+                .enumerate()
+                .filter_map(move |(index, output)| {
+                    let output_progress = progress.output_progress(index)?;
+                    output
+                        .block_index
+                        .map(|block_index| (block_index, output_progress))
+                })
+        })
+    }
+
+    /// Returns the distance of the blip that is closest to an output which
+    /// still expects blips, if any. The distance is measured along the
+    /// grid's axes.
+    pub fn blip_dist_to_output(&self) -> Option<isize> {
+        let blocks = &self.machine;
+
+        self.output_progress()
+            .filter(|(_, progress)| !progress.is_completed() && !progress.failed)
+            .flat_map(|(block_index, _)| {
+                let output_pos = blocks[block_index].0;
+
+                self.blips.values().map(move |blip| {
+                    let delta = blip.pos - output_pos;
+                    delta.x.abs() + delta.y.abs() + delta.z.abs()
+                })
+            })
+            .min()
+    }
+
+    pub fn outcome(&self) -> Option<&Outcome> {
+        self.outcome.as_ref()
+    }
+
+    /// Returns the status of the level after the current tick. Machines
+    /// without a level are always running.
+    pub fn next_level_status(&self) -> LevelStatus {
+        match self.outcome {
+            Some(Outcome::Failure { .. }) => LevelStatus::Failed,
+            _ => self
+                .next_level_progress
+                .as_ref()
+                .map_or(LevelStatus::Running, LevelProgress::status),
+        }
+    }
+
+    pub fn blips(&self) -> &GenVec<Blip> {
         &self.blips
     }
 
@@ -258,7 +565,120 @@ impl Exec {
         &self.prev_activation
     }
 
+    pub fn block_states(&self) -> &[BlockState] {
+        &self.block_states
+    }
+
+    pub fn chunks(&self) -> Option<&Chunks> {
+        self.chunks.as_ref()
+    }
+
+    /// Enables or disables skipping idle chunks. Either way, the result of
+    /// execution is the same.
+    pub fn set_sleeping_enabled(&mut self, enabled: bool) {
+        if enabled != self.chunks.is_some() {
+            self.chunks = if enabled {
+                Some(Chunks::new(&self.machine))
+            } else {
+                None
+            };
+        }
+    }
+
+    /// Enables or disables recomputing wind only for blocks whose inputs
+    /// changed. Either way, the result of execution is the same.
+    pub fn set_incremental_wind_enabled(&mut self, enabled: bool) {
+        if enabled != self.wind_cache.is_some() {
+            self.wind_cache = if enabled {
+                Some(WindCache::new(self.machine.num_blocks()))
+            } else {
+                None
+            };
+        }
+    }
+
+    /// Returns for each block the number of ticks in which it has been busy.
+    pub fn activity(&self) -> &[usize] {
+        &self.activity
+    }
+
+    fn is_block_awake(&self, block_index: BlockIndex) -> bool {
+        self.chunks
+            .as_ref()
+            .map_or(true, |chunks| chunks.is_block_awake(block_index))
+    }
+
+    /// Switches the wind source or blip spawn at `pos` off, or back on, so
+    /// that the player can try out what happens without it. The machine
+    /// itself is not changed. Returns whether the block is disabled now, or
+    /// `None` if there is no block at `pos` that can be switched.
+    pub fn toggle_block_disabled(&mut self, pos: &Point3) -> Option<bool> {
+        let (block_index, placed_block) = self.machine.get_with_index(pos)?;
+
+        match placed_block.block {
+            Block::WindSource | Block::BlipSpawn { .. } => (),
+            _ => return None,
+        }
+
+        let state = &mut self.block_states[block_index];
+        state.disabled = !state.disabled;
+
+        // The block's wind changes, even if nothing else happens around it.
+        if let Some(wind_cache) = self.wind_cache.as_mut() {
+            wind_cache.mark_stale(block_index);
+        }
+        if let Some(chunks) = self.chunks.as_mut() {
+            chunks.wake_up_block(block_index);
+        }
+
+        Some(state.disabled)
+    }
+
+    /// Spawns a blip at `pos` between ticks, e.g. when the player clicks on a
+    /// block. The blip starts moving in the next tick. If `respect_holes` is
+    /// set, the block must have a hole that the blip can move through.
+    pub fn spawn_blip(
+        &mut self,
+        kind: BlipKind,
+        pos: &Point3,
+        respect_holes: bool,
+    ) -> Result<BlipId, SpawnError> {
+        let (block_index, placed_block) = self
+            .machine
+            .get_with_index(pos)
+            .ok_or(SpawnError::NoBlock)?;
+
+        let is_active = self.blocks.activation[block_index].is_some();
+        let move_hole = Dir3::ALL
+            .iter()
+            .copied()
+            .find(|&dir| placed_block.block.has_move_hole(dir, is_active));
+
+        if respect_holes && move_hole.is_none() {
+            return Err(SpawnError::NoMoveHoles);
+        }
+
+        let is_occupied = self
+            .blips
+            .values()
+            .any(|blip| blip.pos == *pos && !blip.status.is_dead());
+        if is_occupied {
+            return Err(SpawnError::Occupied);
+        }
+
+        let orient = move_hole.unwrap_or(Dir3::X_POS);
+        let blip = Blip::new(kind, *pos, orient, None, BlipSpawnMode::Quick);
+
+        Ok(self.blips.add(blip))
+    }
+
     pub fn update(&mut self) {
+        self.update_with_phases(|_, _| ());
+    }
+
+    /// Runs a tick like `update`, calling `on_phase_end` with the state at the
+    /// end of each phase.
+    pub fn update_with_phases(&mut self, mut on_phase_end: impl FnMut(Phase, &Exec)) {
         // 1) Advance state.
         self.level_progress = self.next_level_progress.clone();
 
@@ -272,22 +692,66 @@ impl Exec {
             *activation = None;
         }
 
+        on_phase_end(Phase::Advance, self);
+
         // 2) Spawn and move wind.
         {
             profile!("wind");
 
+            if let Some(chunks) = self.chunks.as_mut() {
+                let machine = &self.machine;
+                chunks.wake_up(
+                    &self.blips,
+                    |pos| machine.get_index(pos),
+                    &self.blocks.activation,
+                    &self.prev_activation,
+                );
+            }
+
+            if let Some(wind_cache) = self.wind_cache.as_mut() {
+                wind_cache.mark_activated(&self.blocks.activation, &self.prev_activation);
+            }
+
             for block_index in 0..self.machine.num_blocks() {
-                self.next_blocks.wind_out[block_index] = spawn_or_advect_wind(
-                    block_index,
-                    &self.machine,
+                let is_stale = self
+                    .wind_cache
+                    .as_ref()
+                    .map_or(true, |wind_cache| wind_cache.is_stale(block_index));
+
+                if !is_stale || !self.is_block_awake(block_index) {
+                    continue;
+                }
+
+                self.next_blocks.wind_out[block_index] = if self.block_states[block_index].disabled
+                {
+                    DirMap3::from_fn(|_| false)
+                } else {
+                    spawn_or_advect_wind(
+                        block_index,
+                        &self.machine,
+                        &self.neighbor_map,
+                        &self.blocks.wind_out,
+                        &self.prev_activation,
+                        &self.blocks.activation,
+                    )
+                };
+            }
+
+            if let Some(wind_cache) = self.wind_cache.as_mut() {
+                wind_cache.finish_wind(
                     &self.neighbor_map,
                     &self.blocks.wind_out,
-                    &self.prev_activation,
-                    &self.blocks.activation,
+                    &mut self.next_blocks.wind_out,
                 );
             }
+
+            if let Some(chunks) = self.chunks.as_mut() {
+                chunks.finish_wind(&self.blocks.wind_out, &mut self.next_blocks.wind_out);
+            }
         }
 
+        on_phase_end(Phase::Wind, self);
+
         // 3) Remove dead blips.
         {
             profile!("clean_up");
@@ -295,6 +759,8 @@ impl Exec {
             self.blips.retain(|blip| !blip.status.is_dead());
         }
 
+        on_phase_end(Phase::CleanUp, self);
+
         // 4) Perform blip movement as it was defined in the previous update,
         //    then determine new blip movement direction.
         {
@@ -321,6 +787,8 @@ impl Exec {
             }
         }
 
+        on_phase_end(Phase::Move, self);
+
         // 5) At each block, count blips that will be there next tick, after
         //    movement.
         {
@@ -350,14 +818,22 @@ impl Exec {
             }
         }
 
+        on_phase_end(Phase::Count, self);
+
         // 6) Run effects of blocks that are activated in this tick.
         {
             profile!("effects");
 
-            for block_index in self.machine.blocks.data.keys() {
+            for block_index in self.machine.iter_indices() {
+                // Blocks that can activate themselves keep their chunk awake.
+                if !self.is_block_awake(block_index) {
+                    continue;
+                }
+
                 if let Some(kind) = self_activate_block(
                     block_index,
-                    &self.machine.blocks.data,
+                    &self.machine,
+                    &self.block_states[block_index],
                     &mut self.level_progress,
                     &self.neighbor_map,
                     &self.next_blip_count,
@@ -367,26 +843,32 @@ impl Exec {
                 }
             }
 
-            for (block_index, (block_pos, placed_block)) in self.machine.blocks.data.iter_mut() {
-                if let Some(blip_kind) = self.prev_activation[block_index] {
-                    run_prev_activated_block(
-                        block_pos,
-                        &placed_block.block,
-                        blip_kind,
-                        &mut self.blips,
-                    );
+            for (block_index, (block_pos, placed_block)) in self.machine.iter_blocks() {
+                let prev_activation = self.prev_activation[block_index];
+                let activation = self.blocks.activation[block_index];
+
+                if prev_activation.is_none() && activation.is_none() {
+                    continue;
                 }
 
-                if let Some(blip_kind) = self.blocks.activation[block_index] {
-                    run_activated_block(
-                        block_index,
-                        block_pos,
-                        &mut placed_block.block,
-                        blip_kind,
-                        &mut self.blips,
-                        &self.neighbor_map,
-                        &self.next_blip_count,
-                    );
+                let behavior = registry::get(placed_block.block.block_type()).behavior;
+                let mut ctx = ActivateContext {
+                    block_index,
+                    block_pos,
+                    state: &mut self.block_states[block_index],
+                    blips: &mut self.blips,
+                    neighbor_map: &self.neighbor_map,
+                    next_blip_count: &self.next_blip_count,
+                    script: self.script.as_ref(),
+                    cur_tick: self.cur_tick,
+                };
+
+                if let Some(blip_kind) = prev_activation {
+                    behavior.run_prev_activated(&placed_block.block, &mut ctx, blip_kind);
+                }
+
+                if let Some(blip_kind) = activation {
+                    behavior.run_activated(&placed_block.block, &mut ctx, blip_kind);
                 }
             }
 
@@ -410,8 +892,29 @@ impl Exec {
                     }
                 }
             }
+
+            // Remember which blocks have been busy, for statistics.
+            for (block_index, activation) in self.blocks.activation.iter().enumerate() {
+                if activation.is_some() {
+                    self.activity[block_index] += 1;
+                }
+            }
+
+            for (_, blip) in self.blips.iter() {
+                if blip.status.is_spawning() {
+                    continue;
+                }
+
+                if let Some(block_index) = self.machine.get_index(&blip.pos) {
+                    if self.blocks.activation[block_index].is_none() {
+                        self.activity[block_index] += 1;
+                    }
+                }
+            }
         }
 
+        on_phase_end(Phase::Effects, self);
+
         // 7) Determine next activations based on blips and update blip status
         //    based on next position.
         //    Any code that modifies a blip's state to be dying is here.
@@ -462,6 +965,8 @@ impl Exec {
             }
         }
 
+        on_phase_end(Phase::Activate, self);
+
         // 8) Determine the next level progress based on block activations.
         //    This allows us to see if the level will be completed or failed
         //    next tick, so we can stop the playback early, allowing the player
@@ -473,49 +978,29 @@ impl Exec {
         });
 
         self.cur_tick += 1;
-    }
-}
-
-fn initialize_air_blocks(machine: &mut Machine) {
-    let air_blocks: HashSet<_> = {
-        let machine: &Machine = machine;
 
-        machine
-            .iter_blocks()
-            .flat_map(|(_, (pos, block))| {
-                Dir3::ALL.iter().flat_map(move |dir| {
-                    let mut result = Vec::new();
-
-                    let build_air = (block.block.has_wind_hole_out(*dir, false)
-                        && block.block.has_move_hole(*dir, false))
-                        || block.block.has_blip_spawn(*dir);
-
-                    if build_air {
-                        let mut iter_pos = pos + dir.to_vector();
-
-                        while machine.is_valid_pos(&iter_pos) && !machine.is_block_at(&iter_pos) {
-                            result.push(iter_pos);
-                            iter_pos.z -= 1;
-                        }
-                    }
-
-                    result
-                })
-            })
-            .collect()
-    };
-
-    info!("Adding {} air blocks to machine", air_blocks.len());
+        if self.outcome.is_none() {
+            let tick = self.cur_tick;
+
+            self.outcome =
+                self.next_level_progress
+                    .as_ref()
+                    .and_then(|progress| match progress.status() {
+                        LevelStatus::Running => None,
+                        LevelStatus::Completed => Some(Outcome::Success { tick }),
+                        LevelStatus::Failed => progress
+                            .failure_reason()
+                            .map(|reason| Outcome::Failure { reason, tick }),
+                    });
+        }
 
-    for pos in air_blocks {
-        assert!(!machine.is_block_at(&pos));
-        machine.set(&pos, Some(PlacedBlock { block: Block::Air }));
+        on_phase_end(Phase::Progress, self);
     }
 }
 
 fn advect_wind(
     block_index: BlockIndex,
-    machine: &Machine,
+    machine: &MachineWithAir,
     neighbor_map: &NeighborMap,
     wind_out: &[DirMap3<bool>],
     activation: &[Activation],
@@ -546,47 +1031,30 @@ fn advect_wind(
 
 fn spawn_or_advect_wind(
     block_index: BlockIndex,
-    machine: &Machine,
+    machine: &MachineWithAir,
     neighbor_map: &NeighborMap,
     wind_out: &[DirMap3<bool>],
     prev_activation: &[Activation],
     activation: &[Activation],
 ) -> DirMap3<bool> {
     let block = machine.block_at_index(block_index);
+    let ctx = WindContext {
+        block_index,
+        machine,
+        neighbor_map,
+        wind_out,
+        prev_activation,
+        activation,
+    };
 
-    match block {
-        Block::WindSource => DirMap3::from_fn(|_| true),
-        Block::BlipWindSource { .. } => {
-            if activation[block_index].is_some() {
-                DirMap3::from_fn(|dir| block.has_wind_source(dir))
-            } else {
-                DirMap3::from_fn(|_| false)
-            }
-        }
-        Block::Input { out_dir, .. } => DirMap3::from_fn(|dir| dir == *out_dir),
-        Block::DetectorWindSource { .. } => {
-            let pipe = advect_wind(block_index, machine, neighbor_map, wind_out, activation);
-
-            if activation[block_index].is_some() {
-                DirMap3::from_fn(|dir| block.has_wind_source(dir) || pipe[dir])
-            } else {
-                pipe
-            }
-        }
-        Block::Delay { flow_dir } => {
-            if prev_activation[block_index].is_some() {
-                DirMap3::from_fn(|dir| dir == *flow_dir)
-            } else {
-                DirMap3::from_fn(|_| false)
-            }
-        }
-        _ => advect_wind(block_index, machine, neighbor_map, wind_out, activation),
-    }
+    registry::get(block.block_type())
+        .behavior
+        .wind_out(block, &ctx)
 }
 
 fn blip_move_dir(
     blip: &Blip,
-    machine: &Machine,
+    machine: &MachineWithAir,
     neighbor_map: &NeighborMap,
     wind_out: &[DirMap3<bool>],
     next_wind_out: &[DirMap3<bool>],
@@ -625,11 +1093,9 @@ fn blip_move_dir(
 
     let num_can_move: usize = Dir3::ALL.iter().filter(|dir| can_move(**dir)).count();
 
-    let must_fall = match block {
-        Block::Air => true,
-        Block::PipeButton { .. } => is_active,
-        _ => false,
-    };
+    let must_fall = registry::get(block.block_type())
+        .behavior
+        .must_fall(block, is_active);
 
     let turn_to_side =
         |dir: Dir3| dir != blip.orient && can_move(dir) && block_wind_in[dir.invert()];
@@ -651,141 +1117,27 @@ fn blip_move_dir(
 
 fn self_activate_block(
     block_index: BlockIndex,
-    blocks: &VecOption<(Point3, PlacedBlock)>,
+    blocks: &MachineWithAir,
+    state: &BlockState,
     level_progress: &mut Option<LevelProgress>,
     neighbor_map: &NeighborMap,
     next_blip_count: &[usize],
 ) -> Option<BlipKind> {
-    match blocks[block_index].1.block.clone() {
-        Block::BlipSpawn {
-            out_dir,
-            kind,
-            num_spawns,
-        } => {
-            if let Some(neighbor_index) = neighbor_map[block_index][out_dir] {
-                // The blip spawn acts only if there is no blip at the output position.
-                let is_safe = next_blip_count[neighbor_index] == 0
-                    || blocks[neighbor_index]
-                        .1
-                        .block
-                        .is_blip_killer(Some(out_dir))
-                        .is_some();
-                if is_safe && num_spawns.map_or(true, |n| n > 0) {
-                    return Some(kind);
-                }
-            }
-        }
-        Block::Input { out_dir, index } => {
-            if let Some(neighbor_index) = neighbor_map[block_index][out_dir] {
-                // The input acts only if there is no blip at the output position.
-                if next_blip_count[neighbor_index] == 0 {
-                    return level_progress.as_mut().and_then(|p| p.feed_input(index));
-                }
-            }
-        }
-        _ => (),
+    if state.disabled {
+        return None;
     }
 
-    None
-}
-
-fn run_prev_activated_block(
-    block_pos: &Point3,
-    block: &Block,
-    blip_kind: BlipKind,
-    blips: &mut VecOption<Blip>,
-) {
-    match block {
-        Block::Delay { flow_dir } => {
-            blips.add(Blip::new(
-                blip_kind,
-                *block_pos,
-                *flow_dir,
-                Some(*flow_dir),
-                BlipSpawnMode::Quick,
-            ));
-        }
-        _ => (),
-    }
-}
+    let block = &blocks[block_index].1.block;
+    let mut ctx = SelfActivateContext {
+        block_index,
+        blocks,
+        state,
+        level_progress,
+        neighbor_map,
+        next_blip_count,
+    };
 
-fn run_activated_block(
-    block_index: BlockIndex,
-    block_pos: &Point3,
-    block: &mut Block,
-    blip_kind: BlipKind,
-    blips: &mut VecOption<Blip>,
-    neighbor_map: &NeighborMap,
-    next_blip_count: &[usize],
-) {
-    match block {
-        Block::BlipSpawn {
-            out_dir,
-            kind,
-            num_spawns,
-            ..
-        } => {
-            *num_spawns = num_spawns.map_or(None, |n| Some(n - 1));
-            blips.add(Blip::new(
-                *kind,
-                *block_pos,
-                *out_dir,
-                Some(*out_dir),
-                BlipSpawnMode::Bridge,
-            ));
-        }
-        Block::BlipDuplicator { out_dirs, .. } => {
-            for &out_dir in &[out_dirs.0, out_dirs.1] {
-                let neighbor_index = neighbor_map[block_index][out_dir];
-                let is_free = neighbor_index
-                    .map_or(true, |neighbor_index| next_blip_count[neighbor_index] == 0);
-
-                if is_free {
-                    blips.add(Blip::new(
-                        blip_kind,
-                        *block_pos,
-                        out_dir,
-                        Some(out_dir),
-                        BlipSpawnMode::Bridge,
-                    ));
-                }
-            }
-        }
-        Block::Input { out_dir, .. } => {
-            blips.add(Blip::new(
-                blip_kind,
-                *block_pos,
-                *out_dir,
-                Some(*out_dir),
-                BlipSpawnMode::Bridge,
-            ));
-        }
-        Block::DetectorBlipDuplicator { out_dir, .. } => {
-            blips.add(Blip::new(
-                blip_kind,
-                *block_pos,
-                *out_dir,
-                Some(*out_dir),
-                BlipSpawnMode::Quick,
-            ));
-        }
-        Block::BlipDeleter { out_dirs, .. } => {
-            for &out_dir in &[out_dirs.0, out_dirs.1] {
-                let neighbor_index = neighbor_map[block_index][out_dir];
-                let is_free = neighbor_index
-                    .map_or(true, |neighbor_index| next_blip_count[neighbor_index] == 0);
-
-                if !is_free {
-                    blips.add(Blip::new(
-                        blip_kind,
-                        *block_pos,
-                        out_dir,
-                        Some(out_dir),
-                        BlipSpawnMode::Bridge,
-                    ));
-                }
-            }
-        }
-        _ => (),
-    }
+    registry::get(block.block_type())
+        .behavior
+        .self_activate(block, &mut ctx)
 }
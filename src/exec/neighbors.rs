@@ -1,26 +1,19 @@
 use std::ops::Index;
 
 use crate::machine::grid::DirMap3;
-use crate::machine::{BlockIndex, Machine};
+use crate::machine::BlockIndex;
+
+use super::air::MachineWithAir;
 
 pub struct NeighborMap(Vec<DirMap3<Option<BlockIndex>>>);
 
 impl NeighborMap {
-    pub fn new_from_machine(machine: &Machine) -> Self {
-        assert!(machine.is_contiguous());
-
+    pub fn new_from_machine(machine: &MachineWithAir) -> Self {
         NeighborMap(
             machine
                 .iter_blocks()
                 .map(|(_, (pos, _))| {
-                    DirMap3::from_fn(|dir| {
-                        machine
-                            .blocks
-                            .indices
-                            .get(&(pos + dir.to_vector()))
-                            .cloned()
-                            .flatten()
-                    })
+                    DirMap3::from_fn(|dir| machine.get_index(&(pos + dir.to_vector())))
                 })
                 .collect(),
         )
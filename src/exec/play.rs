@@ -6,7 +6,9 @@ use imgui::{im_str, ImString};
 use log::info;
 use nalgebra as na;
 
-use crate::util::timer::{self, Timer};
+use crate::exec::Phase;
+use crate::machine::BlipKind;
+use crate::util::timer::{self, CatchUp, Timer};
 
 /// Possible choices in the UI for number of ticks per second to play.
 /// (Specifying these as strings instead of floats here is easier than figuring
@@ -17,12 +19,128 @@ pub const TICKS_PER_SEC_CHOICES: &[&str] = &[
 
 pub const MAX_TICKS_PER_UPDATE: usize = 1024;
 
+/// A condition under which execution pauses by itself.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum RunUntil {
+    /// Run until the given number of ticks has passed since starting.
+    Tick(usize),
+
+    /// Run until any of the level's outputs receives a blip.
+    OutputActivated,
+
+    /// Run until a block with a breakpoint is activated.
+    Breakpoint,
+}
+
+impl fmt::Display for RunUntil {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunUntil::Tick(tick) => write!(f, "until tick {}", tick),
+            RunUntil::OutputActivated => write!(f, "until an output receives a blip"),
+            RunUntil::Breakpoint => write!(f, "until a breakpoint is hit"),
+        }
+    }
+}
+
+/// How blips are spawned when the player right-clicks on a block during
+/// execution.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum SpawnMode {
+    /// Spawn a single blip per click.
+    Single,
+
+    /// Spawn a number of blips at the clicked block, one per tick.
+    Burst,
+
+    /// Spawn a blip at the mouse in every tick while the button is held.
+    Continuous,
+}
+
+impl SpawnMode {
+    pub fn next(self) -> SpawnMode {
+        match self {
+            SpawnMode::Single => SpawnMode::Burst,
+            SpawnMode::Burst => SpawnMode::Continuous,
+            SpawnMode::Continuous => SpawnMode::Single,
+        }
+    }
+}
+
+impl fmt::Display for SpawnMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SpawnMode::Single => "single",
+            SpawnMode::Burst => "burst",
+            SpawnMode::Continuous => "continuous",
+        })
+    }
+}
+
+/// The kind of blips that the player spawns, and how.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct SpawnBrush {
+    pub kind: BlipKind,
+    pub mode: SpawnMode,
+
+    /// Number of blips spawned by a click in `SpawnMode::Burst`.
+    pub burst_size: usize,
+}
+
+impl Default for SpawnBrush {
+    fn default() -> Self {
+        Self {
+            kind: BlipKind::A,
+            mode: SpawnMode::Single,
+            burst_size: 5,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub play_pause_key: VirtualKeyCode,
     pub stop_key: VirtualKeyCode,
+
+    /// Restarts execution from the beginning, feeding the level the same
+    /// inputs again.
+    pub restart_key: VirtualKeyCode,
     pub faster_key: VirtualKeyCode,
     pub slower_key: VirtualKeyCode,
+
+    /// Toggles animating the phases within each tick one after another, for
+    /// debugging machines that depend on the order of events in a tick.
+    pub sub_tick_phases_key: VirtualKeyCode,
+
+    /// Toggles drawing pipes as glass, so that blips can be seen through
+    /// them.
+    pub glass_pipes_key: VirtualKeyCode,
+
+    /// Number of ticks that are run when stepping.
+    pub step_ticks: usize,
+
+    /// Toggles ramping up the speed of execution while no blips are close
+    /// to the level's outputs.
+    pub auto_speed_key: VirtualKeyCode,
+
+    /// Number of ticks after which the speed is doubled when ramping.
+    pub auto_speed_ramp_ticks: usize,
+
+    /// Maximal factor by which ramping speeds up execution.
+    pub auto_speed_max_factor: u32,
+
+    /// Distance to an output at which blips cause execution to slow back
+    /// down to normal speed. The distance is scaled by the current factor,
+    /// so that fast blips are noticed early enough.
+    pub auto_speed_slow_down_dist: isize,
+
+    /// Cycles through the kinds of blips that are spawned by right-clicking.
+    pub spawn_kind_key: VirtualKeyCode,
+
+    /// Cycles through the ways in which blips are spawned by right-clicking.
+    pub spawn_mode_key: VirtualKeyCode,
+
+    /// Largest number of blips that can be chosen for a burst.
+    pub max_burst_size: usize,
 }
 
 impl Default for Config {
@@ -30,13 +148,24 @@ impl Default for Config {
         Self {
             play_pause_key: VirtualKeyCode::Space,
             stop_key: VirtualKeyCode::Escape,
+            restart_key: VirtualKeyCode::Back,
             faster_key: VirtualKeyCode::Add,
             slower_key: VirtualKeyCode::Subtract,
+            sub_tick_phases_key: VirtualKeyCode::F11,
+            glass_pipes_key: VirtualKeyCode::G,
+            step_ticks: 10,
+            auto_speed_key: VirtualKeyCode::U,
+            auto_speed_ramp_ticks: 20,
+            auto_speed_max_factor: 16,
+            auto_speed_slow_down_dist: 2,
+            spawn_kind_key: VirtualKeyCode::N,
+            spawn_mode_key: VirtualKeyCode::J,
+            max_burst_size: 32,
         }
     }
 }
 
-#[derive(PartialEq, Eq, Debug, Clone, Hash)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct TickTime {
     /// Number of ticks that have already passed since starting the simulation.
     pub num_ticks_passed: usize,
@@ -110,6 +239,49 @@ impl Status {
         self.time().tick_progress()
     }
 
+    /// Returns the time at which to render. While playing, this is `dt`
+    /// ahead of the simulated time, so that rendering is smooth between the
+    /// game's fixed steps. The time does not go past the current tick.
+    pub fn render_time(&self, dt: Duration) -> TickTime {
+        match self {
+            Status::Playing { time, .. } => {
+                let mut render_time = time.clone();
+                render_time.next_tick_timer += dt;
+
+                if render_time.tick_progress() > 1.0 {
+                    render_time.next_tick_timer.set_progress(1.0);
+                }
+
+                render_time
+            }
+            _ => self.time().clone(),
+        }
+    }
+
+    /// Combines this status with the status of the update before it, so
+    /// that the ticks of several updates can be run at once.
+    pub fn merge(self, prev: &Status) -> Status {
+        match (self, prev) {
+            (
+                Status::Playing {
+                    num_ticks_since_last_update,
+                    time,
+                    ..
+                },
+                Status::Playing {
+                    num_ticks_since_last_update: prev_num_ticks,
+                    prev_time,
+                    ..
+                },
+            ) => Status::Playing {
+                num_ticks_since_last_update: num_ticks_since_last_update + prev_num_ticks,
+                prev_time: prev_time.clone(),
+                time,
+            },
+            (status, _) => status,
+        }
+    }
+
     pub fn is_paused(&self) -> bool {
         match self {
             Status::Paused { .. } => true,
@@ -128,9 +300,38 @@ impl Status {
 pub struct Play {
     config: Config,
     ticks_per_sec_index: usize,
+    sub_tick_phases: bool,
+    glass_pipes: bool,
+
+    /// Condition under which execution is paused automatically, if any.
+    run_until: Option<RunUntil>,
+
+    /// Tick at which the condition of `run_until` has been reached. The next
+    /// update pauses execution at this tick.
+    run_until_reached: Option<usize>,
+
+    /// Whether the speed of execution is ramped up automatically.
+    auto_speed: bool,
+
+    /// Factor by which ramping currently speeds up execution.
+    speed_factor: u32,
+
+    /// Number of ticks that have passed since last doubling `speed_factor`.
+    ticks_since_ramp: usize,
+
+    /// Distance of the blip that is closest to an unfinished output, as
+    /// last reported by the update.
+    blip_dist_to_output: Option<isize>,
+
+    spawn_brush: SpawnBrush,
 
     play_pause_pressed: bool,
+    pause_pressed: bool,
     stop_pressed: bool,
+    restart_pressed: bool,
+
+    /// Set when execution has been restarted in the last update.
+    restarted: bool,
 }
 
 impl Play {
@@ -138,30 +339,192 @@ impl Play {
         Play {
             config: config.clone(),
             ticks_per_sec_index: 2,
+            sub_tick_phases: false,
+            glass_pipes: false,
+            run_until: None,
+            run_until_reached: None,
+            auto_speed: false,
+            speed_factor: 1,
+            ticks_since_ramp: 0,
+            blip_dist_to_output: None,
+            spawn_brush: SpawnBrush::default(),
             play_pause_pressed: false,
+            pause_pressed: false,
             stop_pressed: false,
+            restart_pressed: false,
+            restarted: false,
+        }
+    }
+
+    /// Starts, pauses or resumes execution in the next update, as if the
+    /// play/pause key had been pressed.
+    pub fn play_pause(&mut self) {
+        self.play_pause_pressed = true;
+    }
+
+    /// Stops execution in the next update, as if the stop key had been
+    /// pressed.
+    pub fn stop(&mut self) {
+        self.stop_pressed = true;
+    }
+
+    /// Returns true if the phases within each tick are animated one after
+    /// another.
+    /// Restarts execution in the next update, as if the restart key had been
+    /// pressed.
+    pub fn restart(&mut self) {
+        self.restart_pressed = true;
+    }
+
+    /// Returns true if execution has been restarted in the last update,
+    /// clearing the flag. The execution then needs to be set up again with
+    /// the same inputs.
+    pub fn take_restarted(&mut self) -> bool {
+        std::mem::replace(&mut self.restarted, false)
+    }
+
+    pub fn sub_tick_phases(&self) -> bool {
+        self.sub_tick_phases
+    }
+
+    pub fn spawn_brush(&self) -> SpawnBrush {
+        self.spawn_brush
+    }
+
+    pub fn run_until(&self) -> Option<RunUntil> {
+        self.run_until
+    }
+
+    /// Runs execution until the condition is reached, starting or resuming
+    /// it if needed.
+    pub fn start_run_until(&mut self, run_until: RunUntil, status: Option<&Status>) {
+        let is_playing = match status {
+            Some(Status::Playing { .. }) => true,
+            _ => false,
+        };
+
+        self.run_until = Some(run_until);
+        if !is_playing {
+            self.play_pause_pressed = true;
+        }
+    }
+
+    /// Runs execution for the configured number of ticks.
+    pub fn step(&mut self, status: Option<&Status>) {
+        let tick = status.map_or(0, |status| status.time().num_ticks_passed);
+        self.start_run_until(RunUntil::Tick(tick + self.config.step_ticks), status);
+    }
+
+    /// Pauses execution in the next update, since the condition of
+    /// `run_until` has been reached at the given tick. Execution may have
+    /// stopped before running all the ticks of the last update, so the time
+    /// is set back to that tick.
+    pub fn on_run_until_reached(&mut self, tick: usize) {
+        if self.run_until.take().is_some() {
+            self.run_until_reached = Some(tick);
+            self.pause_pressed = true;
+        }
+    }
+
+    /// Sets the distance of the blip that is closest to an unfinished
+    /// output, which is used to slow down when ramping the speed.
+    pub fn set_blip_dist_to_output(&mut self, dist: Option<isize>) {
+        self.blip_dist_to_output = dist;
+    }
+
+    /// Doubles the speed every few ticks while no blip is close to an
+    /// output, falling back to normal speed as soon as one is.
+    fn ramp_speed(&mut self, num_ticks: usize) {
+        let slow_down_dist = self.config.auto_speed_slow_down_dist * self.speed_factor as isize;
+        let is_output_close = self
+            .blip_dist_to_output
+            .map_or(false, |dist| dist <= slow_down_dist);
+
+        if !self.auto_speed || is_output_close {
+            self.speed_factor = 1;
+            self.ticks_since_ramp = 0;
+            return;
+        }
+
+        self.ticks_since_ramp += num_ticks;
+
+        if self.ticks_since_ramp >= self.config.auto_speed_ramp_ticks {
+            self.speed_factor =
+                (self.speed_factor * 2).min(self.config.auto_speed_max_factor.max(1));
+            self.ticks_since_ramp = 0;
         }
     }
 
+    /// Returns true if pipes are drawn as glass.
+    pub fn glass_pipes(&self) -> bool {
+        self.glass_pipes
+    }
+
     pub fn update_status(&mut self, dt: Duration, status: Option<&Status>) -> Option<Status> {
         let play_pause_pressed = self.play_pause_pressed;
         let stop_pressed = self.stop_pressed;
+        let restart_pressed = self.restart_pressed;
+        let pause_pressed = self.pause_pressed;
+        let run_until_reached = self.run_until_reached.take();
 
         self.play_pause_pressed = false;
         self.stop_pressed = false;
+        self.restart_pressed = false;
+        self.pause_pressed = false;
+
+        // Pausing or stopping by hand cancels running until a condition.
+        let is_playing = match status {
+            Some(Status::Playing { .. }) => true,
+            _ => false,
+        };
+        if stop_pressed || (is_playing && play_pause_pressed) {
+            self.run_until = None;
+        }
+
+        // Ramping always starts over at normal speed.
+        if !is_playing || restart_pressed {
+            self.speed_factor = 1;
+            self.ticks_since_ramp = 0;
+        }
 
         // Can unwrap here since TICKS_PER_SEC_CHOICES contains
         // only valid floats.
-        let tick_period = timer::hz_to_period(
+        let mut tick_period = timer::hz_to_period(
             TICKS_PER_SEC_CHOICES[self.ticks_per_sec_index]
                 .parse()
                 .unwrap(),
         );
 
+        // Give each animated phase as much time as a whole tick would get
+        // otherwise.
+        if self.sub_tick_phases {
+            tick_period *= Phase::ANIMATED.len() as u32;
+        }
+
+        tick_period /= self.speed_factor;
+
         match &status {
-            Some(Status::Playing { time, .. }) if play_pause_pressed => {
+            Some(status) if restart_pressed && !stop_pressed => {
+                info!("Restarting exec at time {}", status.time());
+                self.restarted = true;
+
+                Some(Status::Playing {
+                    num_ticks_since_last_update: 0,
+                    prev_time: None,
+                    time: TickTime {
+                        num_ticks_passed: 0,
+                        next_tick_timer: Timer::new(tick_period),
+                    },
+                })
+            }
+            Some(Status::Playing { time, .. }) if play_pause_pressed || pause_pressed => {
+                let mut time = time.clone();
+                if let Some(tick) = run_until_reached {
+                    time.num_ticks_passed = tick;
+                }
+
                 info!("Pausing exec at time {}", time);
-                Some(Status::Paused { time: time.clone() })
+                Some(Status::Paused { time })
             }
             Some(Status::Playing { .. }) if stop_pressed => None,
             Some(Status::Playing { time, .. }) => {
@@ -171,10 +534,14 @@ impl Play {
                 // 0 and 1.
                 let mut new_time = time.clone();
                 new_time.next_tick_timer.set_period(tick_period);
-                new_time.next_tick_timer += dt;
+                new_time
+                    .next_tick_timer
+                    .set_catch_up(CatchUp::Skip(MAX_TICKS_PER_UPDATE));
 
-                let num_ticks_since_last_update = new_time.next_tick_timer.trigger_n();
-                new_time.num_ticks_passed += num_ticks_since_last_update.min(MAX_TICKS_PER_UPDATE);
+                let num_ticks_since_last_update = new_time.next_tick_timer.drain_triggers(dt);
+                new_time.num_ticks_passed += num_ticks_since_last_update;
+
+                self.ramp_speed(num_ticks_since_last_update);
 
                 Some(Status::Playing {
                     num_ticks_since_last_update,
@@ -235,12 +602,15 @@ impl Play {
         }
     }
 
-    pub fn on_event(&mut self, event: &WindowEvent) {
+    /// Handles shortcuts. Since this is also called in the editor, the
+    /// shortcuts of the spawn brush, whose keys may be shared with editor
+    /// tools, are only handled while executing.
+    pub fn on_event(&mut self, event: &WindowEvent, is_executing: bool) {
         match event {
             WindowEvent::KeyboardInput { input, .. } => {
                 if input.state == ElementState::Pressed {
                     if let Some(keycode) = input.virtual_keycode {
-                        self.on_key_press(keycode);
+                        self.on_key_press(keycode, is_executing);
                     }
                 }
             }
@@ -248,11 +618,13 @@ impl Play {
         }
     }
 
-    fn on_key_press(&mut self, keycode: VirtualKeyCode) {
+    fn on_key_press(&mut self, keycode: VirtualKeyCode, is_executing: bool) {
         if keycode == self.config.play_pause_key {
             self.play_pause_pressed = true;
         } else if keycode == self.config.stop_key {
             self.stop_pressed = true;
+        } else if keycode == self.config.restart_key {
+            self.restart_pressed = true;
         } else if keycode == self.config.faster_key {
             if self.ticks_per_sec_index + 1 < TICKS_PER_SEC_CHOICES.len() {
                 self.ticks_per_sec_index += 1;
@@ -261,6 +633,16 @@ impl Play {
             if self.ticks_per_sec_index > 0 {
                 self.ticks_per_sec_index -= 1;
             }
+        } else if keycode == self.config.sub_tick_phases_key {
+            self.sub_tick_phases = !self.sub_tick_phases;
+        } else if keycode == self.config.glass_pipes_key {
+            self.glass_pipes = !self.glass_pipes;
+        } else if keycode == self.config.auto_speed_key {
+            self.auto_speed = !self.auto_speed;
+        } else if is_executing && keycode == self.config.spawn_kind_key {
+            self.spawn_brush.kind = self.spawn_brush.kind.next();
+        } else if is_executing && keycode == self.config.spawn_mode_key {
+            self.spawn_brush.mode = self.spawn_brush.mode.next();
         }
     }
 
@@ -272,8 +654,18 @@ impl Play {
         let is_finished = status.map_or(false, |status| status.is_finished());
 
         let title = format!(
-            "Play @ {}Hz###Play",
-            TICKS_PER_SEC_CHOICES[self.ticks_per_sec_index]
+            "Play @ {}Hz{}{}###Play",
+            TICKS_PER_SEC_CHOICES[self.ticks_per_sec_index],
+            if self.speed_factor > 1 {
+                format!(" x{}", self.speed_factor)
+            } else {
+                String::new()
+            },
+            if self.sub_tick_phases {
+                " (phases)"
+            } else {
+                ""
+            },
         );
         imgui::Window::new(&ImString::new(title))
             .horizontal_scrollbar(true)
@@ -304,6 +696,22 @@ impl Play {
 
                 ui.same_line(0.0);
 
+                let selectable = imgui::Selectable::new(im_str!("⏮"))
+                    .disabled(is_stopped)
+                    .size([21.0, 0.0]);
+                if selectable.build(ui) {
+                    self.restart_pressed = true;
+                }
+                if ui.is_item_hovered() {
+                    let text = format!(
+                        "Restart machine execution with the same inputs.\n\nShortcut: {:?}",
+                        self.config.restart_key
+                    );
+                    ui.tooltip(|| ui.text(&ImString::new(text)));
+                }
+
+                ui.same_line(0.0);
+
                 let symbol = if is_paused || is_stopped {
                     im_str!("▶")
                 } else {
@@ -324,6 +732,48 @@ impl Play {
                     ui.tooltip(|| ui.text(&ImString::new(text)));
                 }
 
+                ui.same_line(0.0);
+
+                let selectable = imgui::Selectable::new(im_str!("⏭"))
+                    .disabled(is_finished)
+                    .size([21.0, 0.0]);
+                if selectable.build(ui) {
+                    self.step(status);
+                }
+                if ui.is_item_hovered() {
+                    let text = format!("Run {} ticks, then pause.", self.config.step_ticks);
+                    ui.tooltip(|| ui.text(&ImString::new(text)));
+                }
+
+                ui.same_line(0.0);
+
+                let selectable = imgui::Selectable::new(im_str!("⇥"))
+                    .disabled(is_finished)
+                    .size([21.0, 0.0]);
+                if selectable.build(ui) {
+                    self.start_run_until(RunUntil::OutputActivated, status);
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip(|| ui.text("Run until an output receives a blip, then pause."));
+                }
+
+                ui.same_line(0.0);
+
+                let selectable = imgui::Selectable::new(im_str!("⏯"))
+                    .disabled(is_finished)
+                    .size([21.0, 0.0]);
+                if selectable.build(ui) {
+                    self.start_run_until(RunUntil::Breakpoint, status);
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip(|| {
+                        ui.text(
+                            "Run until a block with a breakpoint is activated, then pause.\n\n\
+                             Breakpoints are toggled for the block at the mouse during execution.",
+                        )
+                    });
+                }
+
                 ui.same_line_with_spacing(0.0, 30.0);
 
                 let selectable = imgui::Selectable::new(im_str!("-"))
@@ -359,7 +809,95 @@ impl Play {
                     ui.tooltip(|| ui.text(&ImString::new(text)));
                 }
 
+                ui.same_line(0.0);
+                let selectable = imgui::Selectable::new(im_str!("»"))
+                    .selected(self.auto_speed)
+                    .size([15.0, 0.0]);
+                if selectable.build(ui) {
+                    self.auto_speed = !self.auto_speed;
+                }
+                if ui.is_item_hovered() {
+                    let text = format!(
+                        "Ramp up the speed while no blips are close to the outputs, \
+                         slowing down again when one is.\n\nShortcut: {:?}",
+                        self.config.auto_speed_key
+                    );
+                    ui.tooltip(|| ui.text(&ImString::new(text)));
+                }
+
                 ui.set_window_font_scale(1.0);
+
+                self.ui_spawn_brush(ui);
+
+                if let Some(run_until) = self.run_until {
+                    ui.text(&ImString::new(format!("Running {}", run_until)));
+                }
             });
     }
+
+    fn ui_spawn_brush(&mut self, ui: &imgui::Ui) {
+        ui.text(im_str!("Spawn:"));
+
+        for &kind in [BlipKind::A, BlipKind::B].iter() {
+            ui.same_line(0.0);
+
+            let label = format!("{}##spawn_kind", kind);
+            let selectable = imgui::Selectable::new(&ImString::new(label))
+                .selected(self.spawn_brush.kind == kind)
+                .size([45.0, 0.0]);
+            if selectable.build(ui) {
+                self.spawn_brush.kind = kind;
+            }
+            if ui.is_item_hovered() {
+                let text = format!(
+                    "Right-click on blocks to spawn {} blips.\n\nShortcut to cycle: {:?}",
+                    kind, self.config.spawn_kind_key
+                );
+                ui.tooltip(|| ui.text(&ImString::new(text)));
+            }
+        }
+
+        let modes = [SpawnMode::Single, SpawnMode::Burst, SpawnMode::Continuous];
+
+        for (index, &mode) in modes.iter().enumerate() {
+            if index == 0 {
+                ui.same_line_with_spacing(0.0, 20.0);
+            } else {
+                ui.same_line(0.0);
+            }
+
+            let selectable = imgui::Selectable::new(&ImString::new(mode.to_string()))
+                .selected(self.spawn_brush.mode == mode)
+                .size([70.0, 0.0]);
+            if selectable.build(ui) {
+                self.spawn_brush.mode = mode;
+            }
+            if ui.is_item_hovered() {
+                let description = match mode {
+                    SpawnMode::Single => "Spawn one blip per click.".to_string(),
+                    SpawnMode::Burst => format!(
+                        "Spawn {} blips per click, one in each tick.",
+                        self.spawn_brush.burst_size
+                    ),
+                    SpawnMode::Continuous => {
+                        "Spawn a blip in each tick while the button is held.".to_string()
+                    }
+                };
+                let text = format!(
+                    "{}\n\nShortcut to cycle: {:?}",
+                    description, self.config.spawn_mode_key
+                );
+                ui.tooltip(|| ui.text(&ImString::new(text)));
+            }
+        }
+
+        if self.spawn_brush.mode == SpawnMode::Burst {
+            let mut burst_size = self.spawn_brush.burst_size as i32;
+            let max_burst_size = self.config.max_burst_size.max(2) as i32;
+
+            imgui::Slider::new(im_str!("Blips per burst"), 2..=max_burst_size)
+                .build(ui, &mut burst_size);
+            self.spawn_brush.burst_size = burst_size as usize;
+        }
+    }
 }
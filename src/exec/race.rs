@@ -0,0 +1,188 @@
+//! Runs machines against each other on the same level, e.g. for head-to-head
+//! races between players. All machines are fed the same inputs and are
+//! advanced in lockstep, so the first one to complete the level wins.
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::exec::headless::Report;
+use crate::exec::{Exec, LevelStatus};
+use crate::machine::{Level, Machine, TickNum};
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Speed at which races are run in the game.
+    pub ticks_per_sec: f64,
+
+    /// Races end without a winner after this many ticks.
+    pub max_ticks: TickNum,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            ticks_per_sec: 10.0,
+            max_ticks: 10000,
+        }
+    }
+}
+
+pub struct Racer {
+    pub name: String,
+    pub exec: Exec,
+
+    /// How the racer's run ended, once it has completed or failed the level.
+    pub report: Option<Report>,
+}
+
+impl Racer {
+    pub fn is_running(&self) -> bool {
+        self.report.is_none()
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.report
+            .as_ref()
+            .map_or(false, |report| report.status == LevelStatus::Completed)
+    }
+
+    /// Returns the fraction of the expected outputs that the machine has
+    /// produced so far.
+    pub fn progress(&self) -> f32 {
+        let level_progress = match self.exec.next_level_progress() {
+            Some(level_progress) => level_progress,
+            None => return 0.0,
+        };
+
+        let expected: usize = level_progress
+            .inputs_outputs
+            .outputs
+            .iter()
+            .map(Vec::len)
+            .sum();
+        let fed: usize = level_progress
+            .outputs
+            .iter()
+            .map(|output| output.num_fed)
+            .sum();
+
+        if expected == 0 {
+            1.0
+        } else {
+            fed as f32 / expected as f32
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RaceResult {
+    /// The racer with the given index was the first to complete the level.
+    Winner(usize),
+
+    /// Multiple racers completed the level in the same tick.
+    Draw,
+
+    /// All racers have failed the level, or the race has run out of ticks.
+    NoWinner,
+}
+
+pub struct Race {
+    pub level: Level,
+    pub racers: Vec<Racer>,
+
+    /// The race ends without a winner once this many ticks have been run.
+    pub max_ticks: TickNum,
+}
+
+impl Race {
+    /// Starts a race between the given named machines. The same `seed` gives
+    /// the same inputs to every machine. Machines must be built for `level`.
+    pub fn new(
+        level: Level,
+        machines: Vec<(String, Machine)>,
+        seed: u64,
+        max_ticks: TickNum,
+    ) -> Result<Race, String> {
+        let mut racers = Vec::new();
+
+        for (name, machine) in machines {
+            if machine.level.as_ref() != Some(&level) {
+                return Err(format!("Machine {:?} is not built for the level", name));
+            }
+
+            let mut rng = StdRng::seed_from_u64(seed);
+            let exec = Exec::new(machine, &mut rng);
+
+            racers.push(Racer {
+                name,
+                exec,
+                report: None,
+            });
+        }
+
+        let mut race = Race {
+            level,
+            racers,
+            max_ticks,
+        };
+
+        // Machines that violate the level's constraints fail right away.
+        race.check_finished();
+
+        Ok(race)
+    }
+
+    pub fn cur_tick(&self) -> TickNum {
+        self.racers
+            .iter()
+            .map(|racer| racer.exec.cur_tick())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Runs one tick for each of the racers that are still running. Nothing
+    /// happens once the race has a result.
+    pub fn update(&mut self) {
+        if self.result().is_some() {
+            return;
+        }
+
+        for racer in self.racers.iter_mut().filter(|racer| racer.is_running()) {
+            racer.exec.update();
+        }
+
+        self.check_finished();
+    }
+
+    /// Returns the result of the race, or `None` while it is still open.
+    pub fn result(&self) -> Option<RaceResult> {
+        let winners: Vec<usize> = self
+            .racers
+            .iter()
+            .enumerate()
+            .filter(|(_, racer)| racer.is_completed())
+            .map(|(index, _)| index)
+            .collect();
+
+        // Racers are advanced in lockstep, so the first completions all
+        // happen in the same tick.
+        match winners.as_slice() {
+            [index] => Some(RaceResult::Winner(*index)),
+            [_, _, ..] => Some(RaceResult::Draw),
+            [] if self.racers.iter().all(|racer| !racer.is_running())
+                || self.cur_tick() >= self.max_ticks =>
+            {
+                Some(RaceResult::NoWinner)
+            }
+            [] => None,
+        }
+    }
+
+    fn check_finished(&mut self) {
+        for racer in self.racers.iter_mut().filter(|racer| racer.is_running()) {
+            if racer.exec.next_level_status() != LevelStatus::Running {
+                racer.report = Some(Report::new(&racer.exec));
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Skipping idle parts of a machine during execution.
+//!
+//! The machine's grid is divided into chunks. A chunk sleeps if nothing can
+//! change in it: its wind did not change in the previous tick, neither did
+//! the wind of its neighboring chunks, and none of its blocks have been
+//! activated or carry blips. The wind of sleeping chunks is kept as is
+//! instead of being recomputed. As soon as a neighbor pushes different wind
+//! or blips across the boundary, the chunk wakes up again.
+//!
+//! Chunks with blocks that can activate themselves, e.g. blip spawns, never
+//! sleep.
+
+use crate::machine::grid::{DirMap3, Grid3, Point3, Vector3};
+use crate::machine::{registry, BlockIndex};
+use crate::util::gen_vec::GenVec;
+
+use super::air::MachineWithAir;
+use super::{Activation, Blip};
+
+/// Size of a chunk along each axis.
+pub const CHUNK_SIZE: isize = 8;
+
+pub struct Chunks {
+    /// The chunk of each block, indexed by `BlockIndex`.
+    block_chunk: Vec<usize>,
+
+    /// The blocks of each chunk.
+    chunk_blocks: Vec<Vec<BlockIndex>>,
+
+    /// The chunks that are next to each chunk.
+    neighbors: Vec<Vec<usize>>,
+
+    /// Chunks that contain blocks which can activate themselves.
+    always_awake: Vec<bool>,
+
+    /// Whether the wind of the chunk changed in the previous tick.
+    changed: Vec<bool>,
+
+    /// Whether any of the chunk's blocks was activated or had a blip in the
+    /// previous tick.
+    busy: Vec<bool>,
+
+    awake: Vec<bool>,
+}
+
+impl Chunks {
+    pub fn new(machine: &MachineWithAir) -> Self {
+        let size = machine.size();
+        let num_chunks_axis = |n: isize| ((n + CHUNK_SIZE - 1) / CHUNK_SIZE).max(1);
+        let chunk_grid: Grid3<()> = Grid3::new(Vector3::new(
+            num_chunks_axis(size.x),
+            num_chunks_axis(size.y),
+            num_chunks_axis(size.z),
+        ));
+        let chunk_index = |chunk: &Point3| chunk_grid.node_index(chunk);
+        let num_chunks = chunk_grid.size();
+        let total = (num_chunks.x * num_chunks.y * num_chunks.z) as usize;
+
+        let mut block_chunk = Vec::with_capacity(machine.num_blocks());
+        let mut chunk_blocks = vec![Vec::new(); total];
+        let mut always_awake = vec![false; total];
+
+        for (block_index, (pos, placed_block)) in machine.iter_blocks() {
+            let chunk = chunk_index(&Point3::new(
+                pos.x / CHUNK_SIZE,
+                pos.y / CHUNK_SIZE,
+                pos.z / CHUNK_SIZE,
+            ));
+
+            block_chunk.push(chunk);
+            chunk_blocks[chunk].push(block_index);
+
+            if registry::get(placed_block.block.block_type())
+                .behavior
+                .is_spontaneous()
+            {
+                always_awake[chunk] = true;
+            }
+        }
+
+        let mut neighbors = vec![Vec::new(); total];
+        for chunk in chunk_grid.positions() {
+            for (_, neighbor) in chunk_grid.neighbors(&chunk) {
+                neighbors[chunk_index(&chunk)].push(chunk_index(&neighbor));
+            }
+        }
+
+        // Everything is awake in the first tick, since the initial wind has
+        // not been computed yet.
+        Self {
+            block_chunk,
+            chunk_blocks,
+            neighbors,
+            always_awake,
+            changed: vec![true; total],
+            busy: vec![true; total],
+            awake: vec![true; total],
+        }
+    }
+
+    pub fn num_chunks(&self) -> usize {
+        self.chunk_blocks.len()
+    }
+
+    pub fn num_awake(&self) -> usize {
+        self.awake.iter().filter(|awake| **awake).count()
+    }
+
+    pub fn is_block_awake(&self, block_index: BlockIndex) -> bool {
+        self.awake[self.block_chunk[block_index]]
+    }
+
+    /// Makes sure that the chunk of a block and its neighbors are awake in
+    /// the coming tick, e.g. since the player has switched the block off.
+    pub fn wake_up_block(&mut self, block_index: BlockIndex) {
+        self.changed[self.block_chunk[block_index]] = true;
+    }
+
+    /// Determines which chunks are awake in the coming tick. This needs to
+    /// be called before wind is computed.
+    pub fn wake_up(
+        &mut self,
+        blips: &GenVec<Blip>,
+        block_indices: impl Fn(&Point3) -> Option<BlockIndex>,
+        activation: &[Activation],
+        prev_activation: &[Activation],
+    ) {
+        let mut busy = vec![false; self.num_chunks()];
+
+        for (_, blip) in blips.iter() {
+            for pos in &[blip.pos, blip.next_pos()] {
+                if let Some(block_index) = block_indices(pos) {
+                    busy[self.block_chunk[block_index]] = true;
+                }
+            }
+        }
+
+        for (block_index, chunk) in self.block_chunk.iter().enumerate() {
+            if activation[block_index].is_some() || prev_activation[block_index].is_some() {
+                busy[*chunk] = true;
+            }
+        }
+
+        for chunk in 0..self.num_chunks() {
+            self.awake[chunk] = self.always_awake[chunk]
+                || busy[chunk]
+                || self.busy[chunk]
+                || self.changed[chunk]
+                || self.neighbors[chunk]
+                    .iter()
+                    .any(|neighbor| self.changed[*neighbor]);
+        }
+
+        self.busy = busy;
+    }
+
+    /// Keeps the wind of sleeping chunks, and remembers which chunks had
+    /// their wind changed. This needs to be called after wind has been
+    /// computed for the awake chunks.
+    pub fn finish_wind(&mut self, wind_out: &[DirMap3<bool>], next_wind_out: &mut [DirMap3<bool>]) {
+        for (chunk, blocks) in self.chunk_blocks.iter().enumerate() {
+            if self.awake[chunk] {
+                self.changed[chunk] = blocks
+                    .iter()
+                    .any(|&block_index| wind_out[block_index] != next_wind_out[block_index]);
+            } else {
+                for &block_index in blocks {
+                    next_wind_out[block_index] = wind_out[block_index].clone();
+                }
+
+                self.changed[chunk] = false;
+            }
+        }
+    }
+}
@@ -1,10 +1,24 @@
-use rand::Rng;
+use std::time::Duration;
 
+use proptest::collection::vec;
+use proptest::prelude::*;
+use rand::{Rng, SeedableRng};
+
+use crate::bench;
 use crate::edit::piece::{Piece, Transform};
-use crate::exec::{BlipSpawnMode, BlipStatus, Exec};
+use crate::exec::play::{self, Play, RunUntil};
+use crate::exec::race::{Race, RaceResult};
+use crate::exec::{
+    analysis, headless, timing, BlipId, BlipSpawnMode, BlipStatus, Exec, FailureReason,
+    LevelProgress, LevelStatus, Outcome, Phase, SpawnError,
+};
 use crate::machine::grid::{Dir3, Point3};
-use crate::machine::string_util::blocks_from_string;
-use crate::machine::{grid, Block, Machine, PlacedBlock};
+use crate::machine::level::{Constraints, Level, Spec, Violation};
+use crate::machine::string_util::{blocks_from_string, machine_from_string};
+use crate::machine::template::Template;
+use crate::machine::tests::arb_machine;
+use crate::machine::{grid, BlipKind, Block, Machine, PlacedBlock};
+use crate::script;
 
 /// Test that wind flows one grid block per tick.
 #[test]
@@ -179,6 +193,33 @@ fn test_blip_duplicator_and_single_blip_movement() {
     });
 }
 
+/// Test that blocks count the ticks in which blips pass or activate them.
+#[test]
+fn test_block_activity() {
+    let m = "
+◉-------┐
+ ┷     -┿-
+";
+
+    test_transform_invariant(&blocks_from_string(m), |t, exec| {
+        for _ in 0..20 {
+            exec.update();
+        }
+
+        let activity = |p: Point3| exec.activity()[exec.machine().get_index(&p).unwrap()];
+
+        // The wind source never sees a blip.
+        assert_eq!(activity(t * (0, 0, 0)), 0);
+
+        // Pipes are passed by the single blip once, and the duplicator is
+        // activated once.
+        for x in 2..=7 {
+            assert_eq!(activity(t * (x, 0, 0)), 1);
+        }
+        assert_eq!(activity(t * (8, 1, 0)), 1);
+    });
+}
+
 /// Test blip duplicator inversion and blip movement.
 #[test]
 fn test_blip_duplicator_inversion_and_blip_movement() {
@@ -231,12 +272,664 @@ fn test_blip_duplicator_inversion_and_blip_movement() {
     });
 }
 
+/// Test that the blips of the demo template reach its duplicator.
+#[test]
+fn test_demo_template_duplicates_blips() {
+    let machine = Template::Demo.new_machine(grid::Vector3::new(20, 20, 1), None);
+    let duplicator_pos = machine
+        .iter_blocks()
+        .find(|(_, (_, placed_block))| match placed_block.block {
+            Block::BlipDuplicator { .. } => true,
+            _ => false,
+        })
+        .map(|(_, (pos, _))| *pos)
+        .unwrap();
+
+    let mut exec = Exec::new(machine, &mut rand::thread_rng());
+    for _ in 0..20 {
+        exec.update();
+    }
+
+    // Copies of the blips leave the duplicator on both sides.
+    for &dir in &[Dir3::X_NEG, Dir3::X_POS] {
+        assert!(next_blip_index(&exec, duplicator_pos + dir.to_vector()).is_some());
+    }
+}
+
+/// Test that a failed output reports which blip it expected and received.
+#[test]
+fn test_output_failure_reason() {
+    let level = Level {
+        size: grid::Vector3::new(9, 9, 1),
+        spec: Spec::Clock {
+            pattern: vec![BlipKind::A, BlipKind::B],
+        },
+        constraints: Default::default(),
+        script: None,
+        pack: None,
+    };
+    let machine = Machine::new_from_level(level.clone());
+    let inputs_outputs = level.spec.gen_inputs_outputs(&mut rand::thread_rng());
+
+    let mut progress = LevelProgress::new(Some(&machine), inputs_outputs);
+    let output_block_index = progress.outputs[0].block_index.unwrap();
+
+    let mut activation = vec![None; machine.num_blocks()];
+    activation[output_block_index] = Some(BlipKind::A);
+
+    // The first blip is expected by the clock pattern.
+    progress.update_outputs(&activation);
+    assert_eq!(progress.status(), LevelStatus::Running);
+    assert_eq!(progress.failure_reason(), None);
+
+    let output_progress = progress.output_progress(0).unwrap();
+    assert_eq!(output_progress.num_fed, 1);
+    assert!(!output_progress.failed);
+
+    // The second one is not.
+    progress.update_outputs(&activation);
+    assert_eq!(progress.status(), LevelStatus::Failed);
+    assert_eq!(
+        progress.failure_reason(),
+        Some(FailureReason::UnexpectedBlip {
+            output_index: 0,
+            expected: Some(BlipKind::B),
+            received: BlipKind::A,
+        })
+    );
+    assert!(progress.output_progress(0).unwrap().failed);
+}
+
+/// Test that machines breaking the level's constraints fail right away.
+#[test]
+fn test_constraint_violation_fails() {
+    let level = Level {
+        size: grid::Vector3::new(9, 9, 1),
+        spec: Spec::Clock {
+            pattern: vec![BlipKind::A, BlipKind::B],
+        },
+        constraints: Constraints {
+            max_num_blocks: Some(0),
+            ..Default::default()
+        },
+        script: None,
+        pack: None,
+    };
+    let mut machine = Machine::new_from_level(level);
+    machine.set(
+        &Point3::new(4, 4, 0),
+        Some(PlacedBlock {
+            block: Block::Solid,
+        }),
+    );
+
+    let exec = Exec::new(machine, &mut rand::thread_rng());
+
+    assert_eq!(
+        exec.outcome(),
+        Some(&Outcome::Failure {
+            reason: FailureReason::ConstraintViolated(Violation::TooManyBlocks {
+                num_blocks: 1,
+                max: 0,
+            }),
+            tick: 0,
+        })
+    );
+}
+
+/// Test that scripted levels fail right away if their inputs can not be
+/// generated, instead of being completed without any outputs.
+#[test]
+fn test_scripted_level_fails_without_runtime() {
+    let scripts = if cfg!(feature = "scripting") {
+        vec![(None, script::Error::Missing)]
+    } else {
+        vec![
+            (None, script::Error::Missing),
+            (
+                Some("function generate(seed) end".to_string()),
+                script::Error::Disabled,
+            ),
+        ]
+    };
+
+    for (script, error) in scripts {
+        let level = Level {
+            size: grid::Vector3::new(9, 9, 1),
+            spec: Spec::Scripted {
+                name: "scripted".to_string(),
+                description: String::new(),
+                input_dim: 1,
+                output_dim: 1,
+            },
+            constraints: Default::default(),
+            script,
+            pack: None,
+        };
+        let mut exec = Exec::new(Machine::new_from_level(level), &mut rand::thread_rng());
+
+        let outcome = Some(Outcome::Failure {
+            reason: FailureReason::ScriptFailed(error),
+            tick: 0,
+        });
+        assert_eq!(exec.outcome(), outcome.as_ref());
+        assert_eq!(exec.next_level_status(), LevelStatus::Failed);
+
+        exec.update();
+        assert_eq!(exec.outcome(), outcome.as_ref());
+    }
+}
+
+/// Test that headless runs stop at the tick limit or once the level is
+/// failed.
+#[test]
+fn test_headless_run_stops() {
+    let mut level = Level {
+        size: grid::Vector3::new(9, 9, 1),
+        spec: Spec::Clock {
+            pattern: vec![BlipKind::A, BlipKind::B],
+        },
+        constraints: Default::default(),
+        script: None,
+        pack: None,
+    };
+
+    // Nothing reaches the output of an empty machine.
+    let machine = Machine::new_from_level(level.clone());
+    let report = headless::run(machine, 50, &mut rand::thread_rng());
+    assert_eq!(report.ticks, 50);
+    assert_eq!(report.status, LevelStatus::Running);
+    assert_eq!(report.score, None);
+
+    level.constraints.max_num_blocks = Some(0);
+    let mut machine = Machine::new_from_level(level);
+    machine.set(
+        &Point3::new(4, 4, 0),
+        Some(PlacedBlock {
+            block: Block::Solid,
+        }),
+    );
+
+    let report = headless::run(machine, 50, &mut rand::thread_rng());
+    assert_eq!(report.ticks, 0);
+    assert_eq!(report.status, LevelStatus::Failed);
+    assert!(report.failure.is_some());
+}
+
+/// Test that all duplicators of the synthetic benchmark machine are fed with
+/// blips.
+#[test]
+fn test_bench_duplicator_grid_is_busy() {
+    let machine = bench::duplicator_grid(3, 2);
+    let mut exec = Exec::new(machine, &mut rand::thread_rng());
+
+    for _ in 0..30 {
+        exec.update();
+    }
+
+    let mut num_duplicators = 0;
+    for (index, (_, placed_block)) in exec.machine().iter_blocks() {
+        if let Block::BlipDuplicator { .. } = placed_block.block {
+            assert!(exec.activity()[index] > 0);
+            num_duplicators += 1;
+        }
+    }
+
+    assert_eq!(num_duplicators, 6);
+}
+
+/// Test that the analysis of a machine that never completes its level
+/// reports every run as failed.
+#[test]
+fn test_analysis_of_incomplete_machine() {
+    let level = Level {
+        size: grid::Vector3::new(9, 9, 1),
+        spec: Spec::Clock {
+            pattern: vec![BlipKind::A, BlipKind::B],
+        },
+        constraints: Default::default(),
+        script: None,
+        pack: None,
+    };
+    let mut machine = Machine::new_from_level(level);
+    machine.set(
+        &Point3::new(4, 4, 0),
+        Some(PlacedBlock {
+            block: Block::Solid,
+        }),
+    );
+
+    let analysis = analysis::analyze(&machine, 3, 20);
+    assert_eq!(analysis.num_runs, 3);
+    assert_eq!(analysis.num_completed, 0);
+    assert!(analysis.ticks.is_none());
+    assert_eq!(analysis.failures.len(), 3);
+    assert_eq!(analysis.block_usage.get("Solid"), Some(&1));
+    assert_eq!(analysis.tightness.min_ticks.unwrap().max, 20);
+    assert!(analysis.tightness.ratio.is_none());
+}
+
+/// Test that running until a condition pauses at the tick where it is
+/// reached, even if that happens partway through an update that was meant to
+/// run more ticks.
+#[test]
+fn test_run_until_reached_mid_frame() {
+    let m = "
+◉-----
+ ┻
+";
+    let mut exec = Exec::new(machine_from_string(m), &mut rand::thread_rng());
+    let mut play = Play::new(&play::Config::default());
+
+    play.start_run_until(RunUntil::Tick(3), None);
+    let status = play.update_status(Duration::from_secs(0), None);
+    let status = play.update_status(Duration::from_secs(10), status.as_ref());
+
+    // Run the ticks of the update like the update thread does.
+    let num_ticks = match status {
+        Some(play::Status::Playing {
+            num_ticks_since_last_update,
+            ..
+        }) => num_ticks_since_last_update,
+        _ => panic!("Execution is not playing: {:?}", status),
+    };
+    assert!(num_ticks > 3);
+
+    for _ in 0..num_ticks {
+        exec.update();
+
+        if exec.cur_tick() >= 3 {
+            break;
+        }
+    }
+
+    play.on_run_until_reached(exec.cur_tick());
+    let status = play.update_status(Duration::from_secs(1), status.as_ref());
+
+    match &status {
+        Some(play::Status::Paused { time }) => assert_eq!(time.num_ticks_passed, 3),
+        _ => panic!("Execution is not paused: {:?}", status),
+    }
+    assert_eq!(play.run_until(), None);
+
+    // Stepping continues from the tick that execution has actually reached.
+    play.step(status.as_ref());
+    assert_eq!(
+        play.run_until(),
+        Some(RunUntil::Tick(3 + play::Config::default().step_ticks))
+    );
+}
+
+/// Test that starting execution does not copy the machine's blocks, even
+/// though air blocks are added around its holes.
+#[test]
+fn test_exec_shares_machine_blocks() {
+    let m = "
+◉-----
+ ┻
+";
+    let machine = machine_from_string(m);
+    let exec = Exec::new(machine.clone(), &mut rand::thread_rng());
+
+    assert!(machine.is_shared());
+    assert_eq!(exec.machine(), &machine);
+    assert!(exec.machine_with_air().num_blocks() > machine.num_blocks());
+    assert_eq!(
+        exec.machine_with_air().get(&Point3::new(0, 1, 0)),
+        Some(&PlacedBlock { block: Block::Air })
+    );
+}
+
+/// Test that running a tick phase by phase reports every phase in order, and
+/// does not change the outcome of the tick.
+#[test]
+fn test_update_with_phases() {
+    let m = "
+◉-------┐
+ ┷     -┿-
+";
+    let machine = machine_from_string(m);
+
+    let mut exec = Exec::new(machine.clone(), &mut rand::thread_rng());
+    let mut phased_exec = Exec::new(machine, &mut rand::thread_rng());
+
+    for _ in 0..20 {
+        exec.update();
+
+        let mut phases = Vec::new();
+        phased_exec.update_with_phases(|phase, _| phases.push(phase));
+
+        assert_eq!(phases, Phase::ALL.to_vec());
+        assert_eq!(
+            exec.blips().values().collect::<Vec<_>>(),
+            phased_exec.blips().values().collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            exec.next_blocks().wind_out,
+            phased_exec.next_blocks().wind_out
+        );
+    }
+}
+
+#[test]
+fn test_spawn_blip() {
+    let m = "
+◉----
+";
+
+    let mut machine = machine_from_string(m);
+    machine.set(
+        &Point3::new(4, 0, 0),
+        Some(PlacedBlock {
+            block: Block::Solid,
+        }),
+    );
+
+    let mut exec = Exec::new(machine, &mut rand::thread_rng());
+
+    assert_eq!(
+        exec.spawn_blip(BlipKind::A, &Point3::new(5, 0, 0), false),
+        Err(SpawnError::NoBlock)
+    );
+    assert_eq!(
+        exec.spawn_blip(BlipKind::A, &Point3::new(4, 0, 0), true),
+        Err(SpawnError::NoMoveHoles)
+    );
+
+    let blip_index = exec
+        .spawn_blip(BlipKind::B, &Point3::new(1, 0, 0), true)
+        .unwrap();
+    assert_eq!(
+        exec.spawn_blip(BlipKind::A, &Point3::new(1, 0, 0), true),
+        Err(SpawnError::Occupied)
+    );
+
+    // The blip is carried along by the wind.
+    for _ in 0..3 {
+        exec.update();
+    }
+    assert_eq!(exec.blips()[blip_index].kind, BlipKind::B);
+    assert_eq!(exec.blips()[blip_index].pos, Point3::new(2, 0, 0));
+}
+
+/// Test that switching off blocks during execution stops their wind and
+/// spawns, also when wind is cached and chunks are sleeping.
+#[test]
+fn test_toggle_block_disabled() {
+    let m = "
+◉-----
+ ┻
+";
+
+    let mut exec = Exec::new(machine_from_string(m), &mut rand::thread_rng());
+    let end_index = exec.machine().get_index(&Point3::new(5, 0, 0)).unwrap();
+    let has_wind_at_end = |exec: &Exec| {
+        exec.next_blocks().wind_out[end_index]
+            .values()
+            .any(|wind| *wind)
+    };
+
+    assert_eq!(exec.toggle_block_disabled(&Point3::new(1, 0, 0)), None);
+    assert_eq!(
+        exec.toggle_block_disabled(&Point3::new(1, 1, 0)),
+        Some(true)
+    );
+
+    for _ in 0..10 {
+        exec.update();
+    }
+    assert!(exec.blips().is_empty());
+    assert!(has_wind_at_end(&exec));
+
+    assert_eq!(
+        exec.toggle_block_disabled(&Point3::new(0, 0, 0)),
+        Some(true)
+    );
+    for _ in 0..10 {
+        exec.update();
+    }
+    assert!(!has_wind_at_end(&exec));
+
+    assert_eq!(
+        exec.toggle_block_disabled(&Point3::new(0, 0, 0)),
+        Some(false)
+    );
+    assert_eq!(
+        exec.toggle_block_disabled(&Point3::new(1, 1, 0)),
+        Some(false)
+    );
+    for _ in 0..10 {
+        exec.update();
+    }
+    assert!(has_wind_at_end(&exec));
+    assert!(!exec.blips().is_empty());
+
+    // The machine itself is left as it was.
+    assert_eq!(
+        exec.machine().get(&Point3::new(0, 0, 0)).unwrap().block,
+        Block::WindSource
+    );
+}
+
+/// Test that the id of a blip does not refer to another blip after it has
+/// died, even if its slot is reused.
+#[test]
+fn test_stale_blip_id() {
+    let m = "
+◉---[
+";
+
+    let mut exec = Exec::new(machine_from_string(m), &mut rand::thread_rng());
+    let blip_id = exec
+        .spawn_blip(BlipKind::A, &Point3::new(1, 0, 0), true)
+        .unwrap();
+
+    // The blip dies when it presses the button of the wind source.
+    for _ in 0..10 {
+        exec.update();
+    }
+    assert!(exec.blips().get(blip_id).is_none());
+
+    let new_blip_id = exec
+        .spawn_blip(BlipKind::B, &Point3::new(1, 0, 0), true)
+        .unwrap();
+    assert_eq!(new_blip_id.index, blip_id.index);
+    assert!(exec.blips().get(blip_id).is_none());
+    assert_eq!(exec.blips()[new_blip_id].kind, BlipKind::B);
+}
+
+#[test]
+fn test_block_state_is_separate_from_machine() {
+    let m = "
+◉┠---
+";
+
+    let machine = machine_from_string(m);
+    let mut exec = Exec::new(machine.clone(), &mut rand::thread_rng());
+    let spawn_index = exec.machine().get_index(&Point3::new(1, 0, 0)).unwrap();
+
+    assert_eq!(exec.block_states()[spawn_index].spawns_left, Some(1));
+
+    for _ in 0..5 {
+        exec.update();
+    }
+
+    // The spawn has used up its blip, but the block itself is unchanged.
+    assert_eq!(exec.block_states()[spawn_index].spawns_left, Some(0));
+    assert_eq!(
+        exec.machine().get(&Point3::new(1, 0, 0)),
+        machine.get(&Point3::new(1, 0, 0))
+    );
+}
+
+/// Test that skipping idle chunks does not change the result of execution.
+#[test]
+fn test_sleeping_chunks() {
+    // A long pipe, which becomes idle once the wind has reached its end, and a
+    // blip source, which keeps its chunk awake.
+    let m = format!("◉{}\n.\n┣---\n", "-".repeat(40));
+    let machine = machine_from_string(&m);
+
+    let mut exec = Exec::new(machine.clone(), &mut rand::thread_rng());
+    let mut reference = Exec::new(machine, &mut rand::thread_rng());
+    reference.set_sleeping_enabled(false);
+
+    for _ in 0..100 {
+        exec.update();
+        reference.update();
+
+        assert_eq!(
+            exec.next_blocks().wind_out,
+            reference.next_blocks().wind_out
+        );
+
+        let blips = |exec: &Exec| {
+            exec.blips()
+                .iter()
+                .map(|(_, blip)| (blip.pos, blip.kind, blip.status))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(blips(&exec), blips(&reference));
+    }
+
+    let chunks = exec.chunks().unwrap();
+    assert!(chunks.num_awake() < chunks.num_chunks());
+}
+
+/// Checks invariants that must hold after every tick, no matter what the
+/// machine looks like.
+fn check_tick_invariants(exec: &Exec) -> Result<(), TestCaseError> {
+    let machine = exec.machine_with_air();
+    let num_blocks = machine.num_blocks();
+
+    // Block indices stay contiguous, and agree with the grid and the state.
+    prop_assert!(exec.machine().is_contiguous());
+    prop_assert_eq!(exec.blocks().wind_out.len(), num_blocks);
+    prop_assert_eq!(exec.next_blocks().wind_out.len(), num_blocks);
+    prop_assert_eq!(exec.blocks().activation.len(), num_blocks);
+    prop_assert_eq!(exec.next_blocks().activation.len(), num_blocks);
+    prop_assert_eq!(exec.prev_activation().len(), num_blocks);
+    prop_assert_eq!(exec.activity().len(), num_blocks);
+
+    for (block_index, (pos, _)) in machine.iter_blocks() {
+        prop_assert_eq!(machine.get_index(pos), Some(block_index));
+
+        for &dir in &Dir3::ALL {
+            prop_assert_eq!(
+                exec.neighbor_map()[block_index][dir],
+                machine.get_index(&(pos + dir.to_vector()))
+            );
+        }
+    }
+
+    // Blips that leave the blocks die before they get there.
+    for (_, blip) in exec.blips().iter() {
+        prop_assert!(
+            machine.get(&blip.pos).is_some(),
+            "Blip is not on a block: {:?}",
+            blip
+        );
+    }
+
+    // Wind only flows out of blocks that create it, or that got it from a
+    // neighbor in the previous tick.
+    for (block_index, (pos, placed_block)) in machine.iter_blocks() {
+        let is_source = match placed_block.block {
+            Block::WindSource
+            | Block::BlipWindSource { .. }
+            | Block::DetectorWindSource { .. }
+            | Block::Input { .. }
+            | Block::Delay { .. } => true,
+            _ => false,
+        };
+        let has_wind_out = exec.next_blocks().wind_out[block_index]
+            .values()
+            .any(|flow| *flow);
+        let has_wind_in = Dir3::ALL.iter().any(|&dir| {
+            exec.neighbor_map()[block_index][dir].map_or(false, |neighbor_index| {
+                exec.blocks().wind_out[neighbor_index][dir.invert()]
+            })
+        });
+
+        prop_assert!(
+            is_source || !has_wind_out || has_wind_in,
+            "Wind out of {:?} at {:?} without a source",
+            placed_block.block,
+            pos
+        );
+    }
+
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    /// Runs random machines for many ticks, checking that execution neither
+    /// panics nor breaks its invariants.
+    ///
+    /// Blips are also spawned by hand at random positions in between ticks.
+    #[test]
+    fn test_exec_tick_invariants(
+        machine in arb_machine(),
+        seed in any::<u64>(),
+        spawns in vec((0..100usize, (0..8isize, 0..8isize, 0..4isize), any::<bool>()), 0..8),
+    ) {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let mut exec = Exec::new(machine, &mut rng);
+
+        for tick in 0..100 {
+            for &(spawn_tick, (x, y, z), respect_holes) in spawns.iter() {
+                if spawn_tick == tick {
+                    // Errors are fine, as long as they leave no blip behind.
+                    let _ = exec.spawn_blip(BlipKind::A, &Point3::new(x, y, z), respect_holes);
+                }
+            }
+
+            exec.update();
+            check_tick_invariants(&exec)?;
+        }
+    }
+
+    /// Compares incremental wind recomputation with recomputing the wind of
+    /// all blocks in every tick.
+    #[test]
+    fn test_incremental_wind(
+        machine in arb_machine(),
+        seed in any::<u64>(),
+        spawns in vec((0..50usize, (0..8isize, 0..8isize, 0..4isize)), 0..8),
+    ) {
+        let mut exec = Exec::new(machine.clone(), &mut rand::rngs::StdRng::seed_from_u64(seed));
+        let mut naive = Exec::new(machine, &mut rand::rngs::StdRng::seed_from_u64(seed));
+        naive.set_incremental_wind_enabled(false);
+        naive.set_sleeping_enabled(false);
+
+        for tick in 0..50 {
+            for &(spawn_tick, (x, y, z)) in spawns.iter() {
+                if spawn_tick == tick {
+                    let pos = Point3::new(x, y, z);
+                    prop_assert_eq!(
+                        exec.spawn_blip(BlipKind::A, &pos, true),
+                        naive.spawn_blip(BlipKind::A, &pos, true)
+                    );
+                }
+            }
+
+            exec.update();
+            naive.update();
+
+            prop_assert_eq!(&exec.next_blocks().wind_out, &naive.next_blocks().wind_out);
+            prop_assert_eq!(&exec.next_blocks().activation, &naive.next_blocks().activation);
+        }
+    }
+}
+
 fn next_wind_out(exec: &Exec, p: Point3, d: Dir3) -> bool {
     let block_index = exec.machine().get_index(&p).unwrap();
     exec.next_blocks().wind_out[block_index][d]
 }
 
-fn next_blip_index(exec: &Exec, p: Point3) -> Option<usize> {
+fn next_blip_index(exec: &Exec, p: Point3) -> Option<BlipId> {
     exec.blips()
         .iter()
         .find(|(_, blip)| blip.next_pos() == p)
@@ -330,3 +1023,132 @@ fn random_shift_to_non_negative(min_pos: &grid::Point3) -> Transform {
 
     Transform::Shift(grid::Vector3::new(shift_x, shift_y, shift_z))
 }
+
+/// Test that timing curves keep the start and end of ticks in place, and that
+/// they are linear at high tick rates.
+#[test]
+fn test_timing_curves() {
+    let curves = [
+        timing::Curve::Linear,
+        timing::Curve::Smooth,
+        timing::Curve::EaseIn,
+        timing::Curve::EaseOut,
+    ];
+
+    for &curve in curves.iter() {
+        assert_eq!(curve.apply(0.0), 0.0);
+        assert_eq!(curve.apply(1.0), 1.0);
+        assert_eq!(curve.apply(1.5), 1.0);
+    }
+
+    let config = timing::Config {
+        blip_move: timing::Curve::Smooth,
+        ..timing::Config::default()
+    };
+    assert_eq!(
+        config.curve(timing::Anim::BlipMove, 2.0),
+        timing::Curve::Smooth
+    );
+    assert_eq!(
+        config.curve(timing::Anim::BlipMove, config.max_eased_ticks_per_sec * 2.0),
+        timing::Curve::Linear
+    );
+}
+
+/// Returns a machine for the identity level that connects the input to the
+/// output with the pipes in `s`.
+fn id_level_machine(s: &str) -> Machine {
+    let level = Level {
+        size: grid::Vector3::new(9, 9, 1),
+        spec: Spec::Id { dim: 1 },
+        constraints: Default::default(),
+        script: None,
+        pack: None,
+    };
+
+    let mut machine = Machine::new_from_level(level);
+    for (pos, block) in blocks_from_string(s) {
+        machine.set(&pos, Some(PlacedBlock { block }));
+    }
+
+    machine
+}
+
+/// Test that the first machine to complete the level wins a race, when both
+/// machines are fed the same inputs.
+#[test]
+fn test_race() {
+    let straight = ".\n.\n.\n.\n.-------";
+    let detour = ".\n.\n.\n.┌-----┐\n.┘.....└";
+    let level = id_level_machine("").level.unwrap();
+    let entries = |a: &str, b: &str| {
+        vec![
+            ("a".to_string(), id_level_machine(a)),
+            ("b".to_string(), id_level_machine(b)),
+        ]
+    };
+    let run = |mut race: Race| {
+        while race.result().is_none() {
+            race.update();
+        }
+        race
+    };
+
+    let race = run(Race::new(level.clone(), entries(detour, straight), 42, 1000).unwrap());
+    assert_eq!(race.result(), Some(RaceResult::Winner(1)));
+    assert!(race.racers[1].is_completed());
+    assert!(race.racers[0].is_running());
+
+    let race = run(Race::new(level.clone(), entries(straight, straight), 42, 1000).unwrap());
+    assert_eq!(race.result(), Some(RaceResult::Draw));
+
+    let race = run(Race::new(level.clone(), entries("", ""), 42, 50).unwrap());
+    assert_eq!(race.result(), Some(RaceResult::NoWinner));
+    assert_eq!(race.cur_tick(), 50);
+
+    let sandbox = Machine::new_sandbox(grid::Vector3::new(9, 9, 1));
+    assert!(Race::new(level, vec![("c".to_string(), sandbox)], 42, 1000).is_err());
+}
+
+/// Test that merging the status of consecutive updates keeps all of their
+/// ticks, so that none are lost when the game advances by several steps in
+/// one frame.
+#[test]
+fn test_merge_play_status() {
+    let mut play = Play::new(&play::Config::default());
+    play.play_pause();
+
+    let start = play.update_status(Duration::from_secs(0), None).unwrap();
+    let first = play
+        .update_status(Duration::from_secs(1), Some(&start))
+        .unwrap();
+    let second = play
+        .update_status(Duration::from_secs(1), Some(&first))
+        .unwrap();
+
+    match (&first, second.clone().merge(&first)) {
+        (
+            play::Status::Playing {
+                num_ticks_since_last_update: first_ticks,
+                ..
+            },
+            play::Status::Playing {
+                num_ticks_since_last_update,
+                prev_time,
+                time,
+            },
+        ) => {
+            assert!(*first_ticks > 0);
+            assert_eq!(time.num_ticks_passed, num_ticks_since_last_update);
+            assert_eq!(prev_time.map(|time| time.num_ticks_passed), Some(0));
+        }
+        status => panic!("Execution is not playing: {:?}", status),
+    }
+
+    // Pausing is not undone by merging.
+    play.play_pause();
+    let paused = play
+        .update_status(Duration::from_secs(1), Some(&second))
+        .unwrap();
+    assert!(paused.merge(&second).is_paused());
+}
@@ -0,0 +1,82 @@
+/// Curves that map progress within a tick to the progress of an animation.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Hash)]
+pub enum Curve {
+    /// Animate at constant speed, so that consecutive ticks blend into each
+    /// other.
+    Linear,
+
+    /// Start and end slowly, which makes it easier to follow single ticks.
+    Smooth,
+
+    /// Start slowly and end at full speed.
+    EaseIn,
+
+    /// Start at full speed and end slowly.
+    EaseOut,
+}
+
+impl Curve {
+    /// Maps `t` in `[0, 1]` to the progress of the animation, which is also
+    /// in `[0, 1]`.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.max(0.0).min(1.0);
+
+        match self {
+            Curve::Linear => t,
+            Curve::Smooth => t * t * (3.0 - 2.0 * t),
+            Curve::EaseIn => t * t,
+            Curve::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+        }
+    }
+}
+
+/// Things that are animated within a tick.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Hash)]
+pub enum Anim {
+    /// Blips moving from one block to the next.
+    BlipMove,
+
+    /// Blips popping into existence.
+    BlipSpawn,
+
+    /// Wind flowing into the next block.
+    Wind,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub blip_move: Curve,
+    pub blip_spawn: Curve,
+    pub wind: Curve,
+
+    /// Above this number of ticks per second, all animations are linear.
+    /// Eased curves pause at the start or end of every tick, which looks like
+    /// strobing when ticks are short.
+    pub max_eased_ticks_per_sec: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            blip_move: Curve::Linear,
+            blip_spawn: Curve::Linear,
+            wind: Curve::Linear,
+            max_eased_ticks_per_sec: 8.0,
+        }
+    }
+}
+
+impl Config {
+    /// Returns the curve to use for `anim` when playing at `ticks_per_sec`.
+    pub fn curve(&self, anim: Anim, ticks_per_sec: f32) -> Curve {
+        if ticks_per_sec > self.max_eased_ticks_per_sec {
+            return Curve::Linear;
+        }
+
+        match anim {
+            Anim::BlipMove => self.blip_move,
+            Anim::BlipSpawn => self.blip_spawn,
+            Anim::Wind => self.wind,
+        }
+    }
+}
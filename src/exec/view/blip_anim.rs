@@ -3,6 +3,7 @@ use std::time::Duration;
 
 use nalgebra as na;
 
+use crate::exec::timing::Curve;
 use crate::exec::{Blip, BlipDieMode, BlipSpawnMode, BlipStatus};
 use crate::machine::grid::{self, Dir3};
 use crate::render;
@@ -19,15 +20,29 @@ pub struct Input {
 
     // This is not actually `Blip` state, but derived from wind state.
     is_on_wind: bool,
+
+    // Configuration of the animation.
+    move_curve: Curve,
+    spawn_curve: Curve,
+    curved_turns: bool,
 }
 
 impl Input {
-    pub fn from_blip(blip: &Blip, is_on_wind: bool) -> Self {
+    pub fn from_blip(
+        blip: &Blip,
+        is_on_wind: bool,
+        move_curve: Curve,
+        spawn_curve: Curve,
+        curved_turns: bool,
+    ) -> Self {
         Self {
             orient: blip.orient,
             move_dir: blip.move_dir,
             status: blip.status,
             is_on_wind,
+            move_curve,
+            spawn_curve,
+            curved_turns,
         }
     }
 
@@ -38,6 +53,14 @@ impl Input {
     pub fn is_turning(&self) -> bool {
         self.move_dir.map_or(false, |dir| dir != self.orient)
     }
+
+    /// Should the blip follow a curve instead of turning on the spot?
+    pub fn is_curving(&self) -> bool {
+        self.curved_turns
+            && self.is_turning()
+            && !self.status.is_spawning()
+            && !self.status.is_pressing_button()
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Debug, Hash)]
@@ -96,14 +119,20 @@ pub fn value_anim(input: Input) -> pareen::AnimBox<f32, Value> {
         .map(Dir3::to_vector)
         .unwrap_or(na::Vector3::zeros());
     let delta_f32: na::Vector3<f32> = na::convert(delta);
+    let orient_f32: na::Vector3<f32> = na::convert(input.orient.to_vector());
+    let is_curving = input.is_curving();
 
-    let size = size_anim(input.status);
+    let size = size_anim(input.status, input.spawn_curve);
     let move_rot = move_rot_anim(input);
 
     move_rot
         .zip(size)
         .map(move |((move_progress, rot), size)| {
-            let trans = na::Translation::from(move_progress * delta_f32);
+            let trans = na::Translation::from(if is_curving {
+                turn_curve(&orient_f32, &delta_f32, move_progress)
+            } else {
+                move_progress * delta_f32
+            });
 
             let face_dirs = [
                 rot.transform_vector(&na::Vector3::new(0.0, 0.0, 1.0)),
@@ -126,21 +155,39 @@ pub fn value_anim(input: Input) -> pareen::AnimBox<f32, Value> {
         .into_box()
 }
 
-pub fn size_anim(status: BlipStatus) -> pareen::AnimBox<f32, f32> {
+/// Position on a path that leaves the block center in direction `orient` and
+/// then bends to enter the next block in direction `delta`. This is a cubic
+/// Bézier curve, so that the blip keeps its direction when it starts turning
+/// and when it arrives.
+fn turn_curve(orient: &na::Vector3<f32>, delta: &na::Vector3<f32>, t: f32) -> na::Vector3<f32> {
+    let bend = 0.3;
+    let p1 = orient * bend;
+    let p2 = delta * (1.0 - bend);
+
+    p1 * (3.0 * (1.0 - t).powi(2) * t) + p2 * (3.0 * (1.0 - t) * t.powi(2)) + delta * t.powi(3)
+}
+
+pub fn size_anim(status: BlipStatus, spawn_curve: Curve) -> pareen::AnimBox<f32, f32> {
     match status {
         BlipStatus::Spawning(mode) => {
             // Animate spawning the blip
-            match mode {
+            let spawn_anim = match mode {
                 /*BlipSpawnMode::Ease =>
                 pareen::constant(0.0).seq_squeeze(0.75, spawn_anim()),*/
                 BlipSpawnMode::Quick => spawn_anim().seq_squeeze(0.5, 1.0).into_box(),
                 BlipSpawnMode::Bridge => spawn_anim().seq_squeeze(0.5, 1.0).into_box(),
-            }
+            };
+
+            spawn_anim
+                .map_time(move |t| spawn_curve.apply(t))
+                .into_box()
         }
         BlipStatus::Existing => pareen::constant(1.0).into_box(),
-        BlipStatus::LiveToDie(spawn_mode, die_mode) => size_anim(BlipStatus::Spawning(spawn_mode))
-            .switch(0.5, size_anim(BlipStatus::Dying(die_mode)))
-            .into_box(),
+        BlipStatus::LiveToDie(spawn_mode, die_mode) => {
+            size_anim(BlipStatus::Spawning(spawn_mode), spawn_curve)
+                .switch(0.5, size_anim(BlipStatus::Dying(die_mode), spawn_curve))
+                .into_box()
+        }
         BlipStatus::Dying(die_mode) => match die_mode {
             BlipDieMode::PopEarly => die_anim().seq_squeeze(0.6, 0.0).into_box(),
             BlipDieMode::PopMiddle => pareen::constant(1.0)
@@ -228,10 +275,18 @@ fn accelerate() -> pareen::AnimBox<f32, f32> {
         .into_box()
 }
 
+fn curve_anim(curve: Curve) -> pareen::AnimBox<f32, f32> {
+    match curve {
+        Curve::Linear => pareen::id().into_box(),
+        curve => pareen::fun(move |t: f32| curve.apply(t)).into_box(),
+    }
+}
+
 fn normal_move_rot_anim(input: Input) -> pareen::AnimBox<f32, (f32, na::UnitQuaternion<f32>)> {
     // Move the blip
     let status = input.status;
     let is_turning = input.is_turning();
+    let move_curve = input.move_curve;
     let move_anim = || {
         pareen::cond(
             status.is_bridge_spawning(),
@@ -240,7 +295,7 @@ fn normal_move_rot_anim(input: Input) -> pareen::AnimBox<f32, (f32, na::UnitQuat
                 is_turning,
                 pareen::constant(0.0).seq_squeeze(0.2, accelerate()),
                 //pareen::constant(0.0).seq_ease_in(0.2, easer::functions::Quad, 0.6, pareen::fun(|t| t + 0.8)),
-                pareen::id(),
+                curve_anim(move_curve),
             ),
         )
         .into_box()
@@ -1,15 +1,15 @@
 use nalgebra as na;
 
 use crate::exec::view::Config;
-use crate::exec::{Blip, BlipDieMode, BlipIndex, BlipStatus, Exec};
+use crate::exec::{Blip, BlipDieMode, BlipId, BlipStatus, Exec};
 
 pub enum TransduceEvent {
     BlipDeath {
-        blip_index: BlipIndex,
+        blip_index: BlipId,
         time: f32,
     },
     BlipSliver {
-        blip_index: BlipIndex,
+        blip_index: BlipId,
         start_time: f32,
         duration: f32,
     },
@@ -30,7 +30,7 @@ const MAX_CLOSE_DISTANCE: f32 = 10.0;
 pub fn iter_nearby_blips<'a>(
     exec: &'a Exec,
     eye_pos: &'a na::Point3<f32>,
-) -> impl Iterator<Item = (BlipIndex, f32, &'a Blip)> {
+) -> impl Iterator<Item = (BlipId, f32, &'a Blip)> {
     exec.blips().iter().filter_map(move |(blip_index, blip)| {
         let blip_pos: na::Point3<f32> = na::convert(blip.pos);
         let delta = blip_pos - eye_pos;
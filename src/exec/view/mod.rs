@@ -1,12 +1,18 @@
 mod blip_anim;
 mod event;
+pub mod showcase;
 
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::mem;
 use std::time::Duration;
 
 use coarse_prof::profile;
+use log::info;
 use nalgebra as na;
+use rand::SeedableRng;
 
-use glium::glutin::{self, WindowEvent};
+use glium::glutin::{self, MouseButton, WindowEvent};
 
 use rendology::particle::Particle;
 use rendology::{basic_obj, BasicObj, Camera, Light, RenderList};
@@ -14,18 +20,63 @@ use rendology::{basic_obj, BasicObj, Camera, Light, RenderList};
 use crate::edit::pick;
 use crate::edit_camera_view::EditCameraView;
 use crate::exec::anim::{AnimState, WindDeadend, WindLife};
-use crate::exec::{Blip, BlipStatus, Exec, LevelProgress, LevelStatus, TickTime};
+use crate::exec::play::{RunUntil, SpawnBrush, SpawnMode};
+use crate::exec::timing::{self, Anim};
+use crate::exec::{
+    Blip, BlipId, BlipStatus, Exec, LevelProgress, LevelStatus, Outcome, Phase, Score, TickTime,
+};
 use crate::input_state::InputState;
 use crate::machine::grid::{Dir3, Point3};
-use crate::machine::{grid, BlipKind, Machine};
+use crate::machine::{grid, level, BlipKind, Block, BlockIndex, Machine, TickNum};
 use crate::render;
+use crate::render::blip_style::BlipStyle;
+use crate::util::intersection::Ray;
 
 use event::TransduceEvent;
+use showcase::Showcase;
 
 #[derive(Debug, Clone)]
 pub struct Config {
     particle_budget_per_tick: usize,
     close_particle_budget_fraction: f32,
+    max_queued_inputs_shown: usize,
+
+    /// Maximal number of ticks shown in the countdown above input blocks.
+    /// Inputs that feed their next blip later than that show a full
+    /// countdown.
+    max_input_countdown_shown: usize,
+
+    curved_blip_turns: bool,
+
+    /// How animations progress within each tick.
+    pub timing: timing::Config,
+
+    /// Number of ticks that the trail behind blips covers. Zero disables
+    /// trails.
+    blip_trail_ticks: f32,
+
+    /// Minimal distance in ticks between two samples of a blip's trail.
+    blip_trail_spacing: f32,
+
+    /// When more than one tick passes between two frames, blips are also
+    /// drawn faintly at their positions in the ticks in between, so that they
+    /// do not appear to teleport. This is the maximal number of such
+    /// positions drawn per blip. Zero disables blending.
+    max_frame_blend_samples: usize,
+
+    /// Toggles tinting blocks by how often they have been busy.
+    heatmap_key: glutin::VirtualKeyCode,
+
+    /// Toggles a breakpoint on the block at the mouse position.
+    breakpoint_key: glutin::VirtualKeyCode,
+
+    showcase: showcase::Config,
+
+    /// Records the path of every blip, so that the whole path of a blip is
+    /// known when the player starts following it. Otherwise, the path is
+    /// recorded only from then on. Meant for debugging, since the paths of
+    /// all blips are kept in memory.
+    pub record_blip_paths: bool,
 }
 
 impl Default for Config {
@@ -33,6 +84,17 @@ impl Default for Config {
         Self {
             particle_budget_per_tick: 500_000,
             close_particle_budget_fraction: 0.3,
+            max_queued_inputs_shown: 8,
+            max_input_countdown_shown: 8,
+            curved_blip_turns: true,
+            timing: timing::Config::default(),
+            blip_trail_ticks: 0.5,
+            blip_trail_spacing: 0.04,
+            max_frame_blend_samples: 8,
+            heatmap_key: glutin::VirtualKeyCode::H,
+            breakpoint_key: glutin::VirtualKeyCode::B,
+            showcase: showcase::Config::default(),
+            record_blip_paths: false,
         }
     }
 }
@@ -43,40 +105,195 @@ impl Config {
     }
 }
 
+/// A blip that the player has pinned by clicking on it.
+#[derive(Debug, Clone)]
+pub struct FollowedBlip {
+    pub index: BlipId,
+    pub kind: BlipKind,
+
+    /// Cells that the blip has visited, together with the tick in which it
+    /// was there.
+    pub path: Vec<(TickNum, grid::Point3)>,
+
+    /// False once the blip has been removed.
+    pub alive: bool,
+
+    /// Whether the camera moves along with the blip.
+    pub track: bool,
+}
+
+/// Where and when a blip has been spawned.
+#[derive(Debug, Clone)]
+pub struct BlipOrigin {
+    pub tick: TickNum,
+    pub pos: grid::Point3,
+
+    /// Name of the block that spawned the blip.
+    pub block_name: String,
+}
+
+/// Details about a blip, shown when the player hovers it with the mouse.
+#[derive(Debug, Clone)]
+pub struct BlipInfo {
+    pub kind: BlipKind,
+    pub pos: grid::Point3,
+    pub move_dir: Option<Dir3>,
+
+    /// Number of ticks since the blip has been spawned, if known.
+    pub age: Option<TickNum>,
+
+    pub origin: Option<BlipOrigin>,
+}
+
+/// Radius around the center of blips in which they can be picked with the
+/// mouse.
+const BLIP_PICK_RADIUS: f32 = 0.3;
+
+/// The phase of the current tick that is being animated, when the phases
+/// within ticks are shown one after another.
+#[derive(Debug, Clone)]
+pub struct PhaseStatus {
+    pub tick: TickNum,
+    pub phase: Phase,
+
+    /// Short descriptions of what happened in the phases of the current tick.
+    pub changes: Vec<(Phase, String)>,
+}
+
 pub struct ExecView {
     config: Config,
 
     exec: Exec,
 
+    /// Seed that the level's inputs were generated with.
+    seed: u64,
+
     mouse_block_pos: Option<grid::Point3>,
 
     blip_anim_cache: blip_anim::Cache,
 
+    /// Recent positions of each blip, together with the time at which they
+    /// were sampled.
+    blip_trails: HashMap<BlipId, VecDeque<(f32, na::Point3<f32>)>>,
+
     transduce_events: Vec<(f32, TransduceEvent)>,
     particle_budget: Vec<f32>,
+
+    show_heatmap: bool,
+
+    blip_style: BlipStyle,
+
+    /// Paths of the existing blips, if enabled in the config.
+    blip_paths: HashMap<BlipId, Vec<(TickNum, grid::Point3)>>,
+
+    followed_blip: Option<FollowedBlip>,
+
+    /// Blip positions after each of the ticks that have been run since the
+    /// last frame was rendered.
+    tick_history: Vec<Vec<(BlipKind, grid::Point3)>>,
+
+    /// Where the existing blips have been spawned.
+    blip_origins: HashMap<BlipId, BlipOrigin>,
+
+    /// Centers of the blips as they were rendered last, so that blips can be
+    /// picked where they are seen in between grid positions.
+    blip_centers: Vec<(BlipId, na::Point3<f32>)>,
+
+    /// The blip that the mouse points to, if any.
+    hovered_blip: Option<BlipId>,
+
+    /// Moves the camera along the machine, if enabled by the player.
+    showcase: Option<Showcase>,
+
+    /// Blocks at which execution pauses when they are activated, if the
+    /// player runs until a breakpoint.
+    breakpoints: HashSet<BlockIndex>,
+
+    /// Whether the phases within ticks are animated one after another.
+    sub_tick_phases: bool,
+
+    /// Whether pipes are drawn as glass, so that blips inside of them can be
+    /// seen.
+    glass_pipes: bool,
+
+    /// What happened in the phases of the current tick, if sub-tick phases
+    /// are shown.
+    phase_changes: Vec<(Phase, String)>,
+
+    /// How blips are spawned when the player right-clicks on a block.
+    spawn_brush: SpawnBrush,
+
+    /// Bursts of blips that are being spawned, one per tick, with the number
+    /// of blips that remain to be spawned.
+    spawn_bursts: Vec<(grid::Point3, BlipKind, usize)>,
+
+    /// Whether blips are being spawned continuously, since the right mouse
+    /// button is held.
+    is_spawning: bool,
+
+    /// Whether execution is paused, in which case clicking on wind sources
+    /// and blip spawns switches them off or back on.
+    is_paused: bool,
 }
 
 impl ExecView {
     pub fn new(config: &Config, machine: Machine) -> ExecView {
+        Self::new_with_seed(config, machine, rand::random())
+    }
+
+    /// Sets up execution with the level's inputs generated from `seed`, so
+    /// that a run can be repeated.
+    pub fn new_with_seed(config: &Config, machine: Machine, seed: u64) -> ExecView {
         ExecView {
             config: config.clone(),
-            exec: Exec::new(machine, &mut rand::thread_rng()),
+            exec: Exec::new(machine, &mut rand::rngs::StdRng::seed_from_u64(seed)),
+            seed,
             mouse_block_pos: None,
             blip_anim_cache: blip_anim::Cache::default(),
+            blip_trails: HashMap::new(),
             transduce_events: Vec::new(),
             particle_budget: Vec::new(),
+            show_heatmap: false,
+            blip_style: BlipStyle::default(),
+            blip_paths: HashMap::new(),
+            followed_blip: None,
+            tick_history: Vec::new(),
+            blip_origins: HashMap::new(),
+            blip_centers: Vec::new(),
+            hovered_blip: None,
+            showcase: None,
+            breakpoints: HashSet::new(),
+            sub_tick_phases: false,
+            glass_pipes: false,
+            phase_changes: Vec::new(),
+            spawn_brush: SpawnBrush::default(),
+            spawn_bursts: Vec::new(),
+            is_spawning: false,
+            is_paused: false,
         }
     }
 
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn set_blip_style(&mut self, blip_style: BlipStyle) {
+        self.blip_style = blip_style;
+    }
+
     pub fn update(
         &mut self,
-        _dt: Duration,
+        dt: Duration,
         input_state: &InputState,
         camera: &Camera,
         edit_camera_view: &EditCameraView,
     ) {
         profile!("exec_view");
 
+        if let Some(showcase) = self.showcase.as_mut() {
+            showcase.update(dt.as_secs_f32());
+        }
+
         self.mouse_block_pos = pick::pick_block(
             self.exec.machine(),
             camera,
@@ -84,12 +301,109 @@ impl ExecView {
             &input_state.mouse_window_pos(),
             |_| true,
         );
+
+        let ray = pick::camera_ray(
+            camera,
+            &edit_camera_view.eye(),
+            &input_state.mouse_window_pos(),
+        );
+        self.hovered_blip = self.pick_blip(&ray);
+    }
+
+    /// Runs a tick, returning true if the condition for pausing has been
+    /// reached.
+    pub fn run_tick_until(&mut self, run_until: RunUntil) -> bool {
+        let num_output_blips = self.num_output_blips();
+
+        self.run_tick();
+
+        match run_until {
+            RunUntil::Tick(tick) => self.exec.cur_tick() >= tick,
+            RunUntil::OutputActivated => self.num_output_blips() > num_output_blips,
+            RunUntil::Breakpoint => {
+                let activation = &self.exec.next_blocks().activation;
+                self.breakpoints
+                    .iter()
+                    .any(|&block_index| activation[block_index].is_some())
+            }
+        }
+    }
+
+    /// Returns the number of blips that the level's outputs have received.
+    fn num_output_blips(&self) -> usize {
+        self.exec
+            .output_progress()
+            .map(|(_, progress)| progress.num_fed)
+            .sum()
+    }
+
+    fn toggle_breakpoint_at_mouse(&mut self) {
+        let block_index = self
+            .mouse_block_pos
+            .and_then(|pos| self.exec.machine().get_index(&pos));
+
+        if let Some(block_index) = block_index {
+            if !self.breakpoints.remove(&block_index) {
+                self.breakpoints.insert(block_index);
+            }
+        }
+    }
+
+    /// Returns the blip closest to the ray's origin that is hit by the ray.
+    fn pick_blip(&self, ray: &Ray) -> Option<BlipId> {
+        let speed_sq = ray.velocity.norm_squared();
+        if speed_sq == 0.0 {
+            return None;
+        }
+
+        self.blip_centers
+            .iter()
+            .filter(|(index, _)| self.exec.blips().get(*index).is_some())
+            .filter_map(|&(index, center)| {
+                let t = (center - ray.origin).dot(&ray.velocity) / speed_sq;
+                let closest = ray.origin + t * ray.velocity;
+
+                if t >= 0.0 && (center - closest).norm() <= BLIP_PICK_RADIUS {
+                    Some((index, t))
+                } else {
+                    None
+                }
+            })
+            .min_by(|(_, t1), (_, t2)| t1.partial_cmp(t2).unwrap_or(Ordering::Equal))
+            .map(|(index, _)| index)
     }
 
     pub fn run_tick(&mut self) {
         profile!("tick");
 
-        self.exec.update();
+        if self.sub_tick_phases {
+            let mut changes = Vec::new();
+            self.exec.update_with_phases(|phase, exec| {
+                if let Some(change) = describe_phase(phase, exec) {
+                    changes.push((phase, change));
+                }
+            });
+            self.phase_changes = changes;
+        } else {
+            self.exec.update();
+        }
+
+        if self.config.record_blip_paths {
+            self.record_blip_paths();
+        }
+        self.record_blip_origins();
+        self.update_followed_blip();
+        self.continue_spawning();
+
+        if self.config.max_frame_blend_samples > 0 {
+            self.tick_history.push(
+                self.exec
+                    .blips()
+                    .values()
+                    .map(|blip| (blip.kind, blip.pos))
+                    .collect(),
+            );
+        }
 
         // The blip animation cache is indexed by the tick progress, among other
         // things. The tick progress offsets depend entirely on frame times, so
@@ -98,39 +412,683 @@ impl ExecView {
         self.blip_anim_cache.clear();
     }
 
+    /// Spawns the blips of the brush at the mouse when the player
+    /// right-clicks.
+    fn start_spawning(&mut self) {
+        let pos = match self.mouse_block_pos {
+            Some(pos) => pos,
+            None => return,
+        };
+        let brush = self.spawn_brush;
+
+        if let Err(err) = self.exec.spawn_blip(brush.kind, &pos, true) {
+            info!("Not spawning blip at {:?}: {}", pos, err);
+        }
+
+        match brush.mode {
+            SpawnMode::Single => (),
+            SpawnMode::Burst => {
+                if brush.burst_size > 1 {
+                    self.spawn_bursts
+                        .push((pos, brush.kind, brush.burst_size - 1));
+                }
+            }
+            SpawnMode::Continuous => self.is_spawning = true,
+        }
+    }
+
+    /// Spawns the next blips of bursts, and of continuous spawning at the
+    /// mouse. Spawning is skipped in ticks in which a blip is in the way, but
+    /// bursts still count these ticks.
+    fn continue_spawning(&mut self) {
+        let bursts = mem::replace(&mut self.spawn_bursts, Vec::new());
+
+        for (pos, kind, remaining) in bursts {
+            let _ = self.exec.spawn_blip(kind, &pos, true);
+
+            if remaining > 1 {
+                self.spawn_bursts.push((pos, kind, remaining - 1));
+            }
+        }
+
+        if self.is_spawning && self.spawn_brush.mode == SpawnMode::Continuous {
+            if let Some(pos) = self.mouse_block_pos {
+                let _ = self.exec.spawn_blip(self.spawn_brush.kind, &pos, true);
+            }
+        }
+    }
+
+    fn record_blip_paths(&mut self) {
+        let cur_tick = self.exec.cur_tick();
+        let blips = self.exec.blips();
+
+        self.blip_paths
+            .retain(|&index, _| blips.get(index).is_some());
+
+        for (index, blip) in blips.iter() {
+            // Blip ids are never reused, so a new blip always starts a new
+            // path.
+            self.blip_paths
+                .entry(index)
+                .or_insert_with(Vec::new)
+                .push((cur_tick, blip.pos));
+        }
+    }
+
+    fn record_blip_origins(&mut self) {
+        let cur_tick = self.exec.cur_tick();
+        let blips = self.exec.blips();
+        let machine = self.exec.machine();
+
+        self.blip_origins
+            .retain(|&index, _| blips.get(index).is_some());
+
+        for (index, blip) in blips.iter() {
+            // Blips only spawn once, so their origin is recorded only once.
+            if blip.status.is_spawning() {
+                let block_name = machine
+                    .get(&blip.pos)
+                    .map_or_else(String::new, |placed_block| placed_block.block.name());
+
+                self.blip_origins.insert(
+                    index,
+                    BlipOrigin {
+                        tick: cur_tick,
+                        pos: blip.pos,
+                        block_name,
+                    },
+                );
+            }
+        }
+    }
+
+    fn update_followed_blip(&mut self) {
+        let followed = match self
+            .followed_blip
+            .as_mut()
+            .filter(|followed| followed.alive)
+        {
+            Some(followed) => followed,
+            None => return,
+        };
+
+        // A spawning blip at the same index is a new blip, the followed one
+        // has been removed.
+        match self.exec.blips().get(followed.index) {
+            Some(blip) if !blip.status.is_spawning() => {
+                followed.path.push((self.exec.cur_tick(), blip.pos));
+            }
+            _ => {
+                followed.alive = false;
+                followed.track = false;
+            }
+        }
+    }
+
+    /// Starts following the blip that the mouse points to. Clicking on the
+    /// followed blip again stops following it. Returns false if there is no
+    /// blip at the mouse.
+    fn follow_blip_at_mouse(&mut self) -> bool {
+        let blip = match self.hovered_blip {
+            Some(index) => self.exec.blips().get(index).map(|blip| (index, blip)),
+            None => {
+                let mouse_block_pos = match self.mouse_block_pos {
+                    Some(pos) => pos,
+                    None => return false,
+                };
+
+                self.exec
+                    .blips()
+                    .iter()
+                    .find(|(_, blip)| blip.pos == mouse_block_pos)
+            }
+        };
+
+        if let Some((index, blip)) = blip {
+            let is_followed = self
+                .followed_blip
+                .as_ref()
+                .map_or(false, |followed| followed.alive && followed.index == index);
+
+            self.followed_blip = if is_followed {
+                None
+            } else {
+                let path = self
+                    .blip_paths
+                    .get(&index)
+                    .cloned()
+                    .unwrap_or_else(|| vec![(self.exec.cur_tick(), blip.pos)]);
+
+                Some(FollowedBlip {
+                    index,
+                    kind: blip.kind,
+                    path,
+                    alive: true,
+                    track: false,
+                })
+            };
+
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Switches the wind source or blip spawn at the mouse off, or back on.
+    fn toggle_block_disabled_at_mouse(&mut self) {
+        let pos = match self.mouse_block_pos {
+            Some(pos) => pos,
+            None => return,
+        };
+
+        if let Some(disabled) = self.exec.toggle_block_disabled(&pos) {
+            info!(
+                "{} block at {:?} until execution is stopped",
+                if disabled { "Disabling" } else { "Enabling" },
+                pos
+            );
+        }
+    }
+
+    pub fn followed_blip(&self) -> Option<&FollowedBlip> {
+        self.followed_blip.as_ref()
+    }
+
+    /// Returns details about the blip that the mouse points to, if any.
+    pub fn hovered_blip(&self) -> Option<BlipInfo> {
+        let index = self.hovered_blip?;
+        let blip = self.exec.blips().get(index)?;
+        let origin = self.blip_origins.get(&index).cloned();
+
+        Some(BlipInfo {
+            kind: blip.kind,
+            pos: blip.pos,
+            move_dir: blip.move_dir,
+            age: origin
+                .as_ref()
+                .map(|origin| self.exec.cur_tick() - origin.tick),
+            origin,
+        })
+    }
+
+    pub fn unfollow_blip(&mut self) {
+        self.followed_blip = None;
+    }
+
+    pub fn set_track_followed_blip(&mut self, track: bool) {
+        if let Some(followed) = self.followed_blip.as_mut() {
+            followed.track = track && followed.alive;
+        }
+    }
+
+    /// Returns the camera movement for showcasing the machine, if the player
+    /// has enabled it.
+    pub fn showcase(&self) -> Option<&Showcase> {
+        self.showcase.as_ref()
+    }
+
+    /// Returns the position that the camera should move to, if it is
+    /// tracking the followed blip.
+    pub fn tracked_blip_target(&self) -> Option<na::Point3<f32>> {
+        self.followed_blip
+            .as_ref()
+            .filter(|followed| followed.track)
+            .and_then(|followed| self.exec.blips().get(followed.index))
+            .map(|blip| render::machine::block_center(&blip.pos))
+    }
+
+    pub fn set_sub_tick_phases(&mut self, sub_tick_phases: bool) {
+        if sub_tick_phases != self.sub_tick_phases {
+            self.sub_tick_phases = sub_tick_phases;
+            self.phase_changes.clear();
+        }
+    }
+
+    pub fn set_glass_pipes(&mut self, glass_pipes: bool) {
+        self.glass_pipes = glass_pipes;
+    }
+
+    pub fn set_spawn_brush(&mut self, spawn_brush: SpawnBrush) {
+        self.spawn_brush = spawn_brush;
+    }
+
+    pub fn set_paused(&mut self, is_paused: bool) {
+        self.is_paused = is_paused;
+    }
+
+    /// Returns the phase that is being animated at `time`, if sub-tick phases
+    /// are shown.
+    pub fn phase_status(&self, time: &TickTime) -> Option<PhaseStatus> {
+        if !self.sub_tick_phases {
+            return None;
+        }
+
+        let num_phases = Phase::ANIMATED.len();
+        let index = (time.tick_progress() * num_phases as f32) as usize;
+
+        Some(PhaseStatus {
+            tick: self.exec.cur_tick(),
+            phase: Phase::ANIMATED[index.min(num_phases - 1)],
+            changes: self.phase_changes.clone(),
+        })
+    }
+
+    pub fn cur_tick(&self) -> TickNum {
+        self.exec.cur_tick()
+    }
+
     pub fn next_level_status(&self) -> LevelStatus {
-        self.exec
-            .next_level_progress()
-            .map_or(LevelStatus::Running, LevelProgress::status)
+        self.exec.next_level_status()
     }
 
     pub fn level_progress(&self) -> Option<&LevelProgress> {
         self.exec.level_progress()
     }
 
+    pub fn blip_dist_to_output(&self) -> Option<isize> {
+        self.exec.blip_dist_to_output()
+    }
+
+    pub fn outcome(&self) -> Option<&Outcome> {
+        self.exec.outcome()
+    }
+
+    /// Returns the score of the machine, if it has completed the level.
+    pub fn score(&self) -> Option<Score> {
+        if self.next_level_status() == LevelStatus::Completed {
+            Some(Score::new(self.exec.cur_tick(), self.exec.machine()))
+        } else {
+            None
+        }
+    }
+
     pub fn on_event(&mut self, event: &WindowEvent) {
         match event {
             WindowEvent::KeyboardInput { input, .. } => self.on_keyboard_input(*input),
+            WindowEvent::MouseInput {
+                state: glutin::ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                if !self.follow_blip_at_mouse() && self.is_paused {
+                    self.toggle_block_disabled_at_mouse();
+                }
+            }
+            WindowEvent::MouseInput {
+                state: glutin::ElementState::Pressed,
+                button: MouseButton::Right,
+                ..
+            } => self.start_spawning(),
+            WindowEvent::MouseInput {
+                state: glutin::ElementState::Released,
+                button: MouseButton::Right,
+                ..
+            } => self.is_spawning = false,
             _ => (),
         }
     }
 
-    fn on_keyboard_input(&mut self, _input: glutin::KeyboardInput) {}
+    fn on_keyboard_input(&mut self, input: glutin::KeyboardInput) {
+        if input.state == glutin::ElementState::Pressed
+            && input.virtual_keycode == Some(self.config.heatmap_key)
+        {
+            self.show_heatmap = !self.show_heatmap;
+        }
+
+        if input.state == glutin::ElementState::Pressed
+            && input.virtual_keycode == Some(self.config.breakpoint_key)
+        {
+            self.toggle_breakpoint_at_mouse();
+        }
+
+        if input.state == glutin::ElementState::Pressed
+            && input.virtual_keycode == Some(self.config.showcase.toggle_key)
+        {
+            self.showcase = if self.showcase.is_some() {
+                None
+            } else {
+                Some(Showcase::new(&self.config.showcase, self.exec.machine()))
+            };
+        }
+    }
+
+    /// Returns for each block how busy it has been compared to the busiest
+    /// block, on a logarithmic scale from zero to one.
+    fn heatmap(&self) -> Vec<f32> {
+        let activity = self.exec.activity();
+        let max = activity.iter().copied().max().unwrap_or(0);
+
+        if max == 0 {
+            return vec![0.0; activity.len()];
+        }
+
+        let scale = (max as f32).ln_1p();
+        activity
+            .iter()
+            .map(|&count| (count as f32).ln_1p() / scale)
+            .collect()
+    }
 
     pub fn render(&mut self, time: &TickTime, out: &mut render::Stage) {
         profile!("exec_view");
 
+        let heatmap = if self.show_heatmap {
+            Some(self.heatmap())
+        } else {
+            None
+        };
+        let machine = self.exec.machine();
+        let block_states = self.exec.block_states();
+        let glass_pipes = self.glass_pipes;
+
+        // When showing sub-tick phases, wind, blips and block effects are
+        // animated one after another, instead of all at once.
+        let (wind_time, blip_time, effects_time) = if self.sub_tick_phases {
+            (
+                phase_time(time, Phase::Wind),
+                phase_time(time, Phase::Move),
+                phase_time(time, Phase::Effects),
+            )
+        } else {
+            (time.clone(), time.clone(), time.clone())
+        };
+
         render::machine::render_machine(
-            &self.exec.machine(),
-            time,
+            machine,
+            &effects_time,
             Some(&self.exec),
             |_| true,
-            |_| false,
+            |pos| {
+                let is_disabled = machine
+                    .get_index(pos)
+                    .map_or(false, |block_index| block_states[block_index].disabled);
+
+                if is_disabled {
+                    render::machine::UNFOCUS_ALPHA
+                } else {
+                    1.0
+                }
+            },
+            |pos| {
+                // Disabled pipes stay transparent, so that they can still be
+                // told apart.
+                let is_disabled = machine
+                    .get_index(pos)
+                    .map_or(false, |block_index| block_states[block_index].disabled);
+
+                glass_pipes
+                    && !is_disabled
+                    && machine
+                        .get(pos)
+                        .map_or(false, |placed_block| placed_block.block.is_pipe())
+            },
+            |pos| {
+                let heatmap = heatmap.as_ref()?;
+                let block_index = machine.get_index(pos)?;
+
+                Some(render::machine::heat_color(heatmap[block_index]))
+            },
+            &self.blip_style,
             out,
         );
 
-        self.render_blocks(time, out);
-        self.render_blips(time, out);
+        self.render_blocks(&wind_time, out);
+        self.render_blips(&blip_time, out);
+        self.render_frame_blend(out);
+        self.render_queued_inputs(out);
+        self.render_output_progress(out);
+        self.render_input_countdowns(out);
+        self.render_failure(out);
+        self.render_breakpoints(out);
+        self.render_disabled_blocks(out);
+        self.render_followed_blip(out);
+    }
+
+    /// Draws the blips faintly at the positions they had in the ticks that
+    /// have passed since the last frame, older positions being fainter. This
+    /// has no effect if at most one tick has passed.
+    fn render_frame_blend(&mut self, out: &mut render::Stage) {
+        let history = mem::replace(&mut self.tick_history, Vec::new());
+
+        // The last tick is shown by the blips themselves.
+        let num_ticks = history.len().saturating_sub(1);
+        if num_ticks == 0 {
+            return;
+        }
+
+        let num_samples = num_ticks.min(self.config.max_frame_blend_samples);
+
+        for i in 0..num_samples {
+            // Spread the samples evenly over the ticks, ending at the newest.
+            let tick = num_ticks - 1 - i * num_ticks / num_samples;
+            let fade = 1.0 - i as f32 / num_samples as f32;
+
+            for (kind, pos) in history[tick].iter() {
+                let color = self.blip_style.color(*kind);
+                let center = render::machine::block_center(pos);
+
+                out.solid_dither[BasicObj::Cube].add(basic_obj::Instance {
+                    transform: na::Matrix4::new_translation(&center.coords)
+                        * na::Matrix4::new_scaling(0.3),
+                    color: na::Vector4::new(color.x, color.y, color.z, 0.4 * fade),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    /// Shows the cells that the followed blip has visited, and highlights the
+    /// blip itself.
+    fn render_followed_blip(&self, out: &mut render::Stage) {
+        let followed = match self.followed_blip.as_ref() {
+            Some(followed) => followed,
+            None => return,
+        };
+
+        let color = self.blip_style.color(followed.kind);
+
+        for (_, pos) in followed.path.iter() {
+            let center = render::machine::block_center(pos);
+
+            out.solid_dither[BasicObj::Cube].add(basic_obj::Instance {
+                transform: na::Matrix4::new_translation(&center.coords)
+                    * na::Matrix4::new_scaling(0.12),
+                color: na::Vector4::new(color.x, color.y, color.z, 0.4),
+                ..Default::default()
+            });
+        }
+
+        if let Some(blip) = self
+            .exec
+            .blips()
+            .get(followed.index)
+            .filter(|_| followed.alive)
+        {
+            let center = render::machine::block_center(&blip.pos);
+            let transform =
+                na::Matrix4::new_translation(&center.coords) * na::Matrix4::new_scaling(0.6);
+
+            render::machine::render_line_wireframe(
+                4.0,
+                &na::Vector4::new(color.x, color.y, color.z, 1.0),
+                &transform,
+                out,
+            );
+        }
+    }
+
+    /// Shows the upcoming blips of each level input as a queue of markers
+    /// behind the input block. Markers are consumed as the inputs are fed.
+    fn render_queued_inputs(&self, out: &mut render::Stage) {
+        for (block_index, queued) in self.exec.queued_inputs() {
+            let (block_pos, placed_block) = &self.exec.machine().blocks.data[block_index];
+            let out_dir = match placed_block.block {
+                Block::Input { out_dir, .. } => out_dir,
+                _ => continue,
+            };
+
+            let center = render::machine::block_center(block_pos);
+            let back: na::Vector3<f32> = na::convert(out_dir.invert().to_vector());
+
+            for (i, input) in queued
+                .iter()
+                .take(self.config.max_queued_inputs_shown)
+                .enumerate()
+            {
+                // Empty slots still take up space, so that the markers keep
+                // their timing relative to each other.
+                let kind = match input {
+                    Some(level::Input::Blip(kind)) => *kind,
+                    None => continue,
+                };
+
+                let pos = center + back * (0.8 + 0.4 * i as f32);
+                let transform = na::Matrix4::new_translation(&pos.coords);
+                let scaling = na::Vector3::new(0.15, 0.15, 0.15);
+
+                render::machine::render_outline(&transform, &scaling, 1.0, out);
+
+                let color = self.blip_style.color(kind);
+                self.blip_style.render_kind(
+                    kind,
+                    &(transform * na::Matrix4::new_nonuniform_scaling(&scaling)),
+                    &na::Vector4::new(color.x, color.y, color.z, 1.0),
+                    true,
+                    out,
+                );
+            }
+        }
+    }
+
+    /// Shows a column of markers above each input block, one for each tick
+    /// until the input feeds its next blip. The markers have the color of
+    /// that blip.
+    fn render_input_countdowns(&self, out: &mut render::Stage) {
+        for (block_index, num_ticks, kind) in self.exec.input_countdowns() {
+            let (block_pos, _) = &self.exec.machine().blocks.data[block_index];
+            let base = render::machine::block_center(block_pos) + na::Vector3::z() * 0.7;
+            let color = self.blip_style.color(kind);
+
+            for i in 0..num_ticks.min(self.config.max_input_countdown_shown) {
+                // The marker of the upcoming tick is the largest one.
+                let size = if i == 0 { 0.12 } else { 0.07 };
+                let pos = base + na::Vector3::z() * (0.15 * i as f32);
+
+                out.plain[BasicObj::Cube].add(basic_obj::Instance {
+                    transform: na::Matrix4::new_translation(&pos.coords)
+                        * na::Matrix4::new_scaling(size),
+                    color: na::Vector4::new(color.x, color.y, color.z, 1.0),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    /// Shows a bar above each output block that fills up as the output
+    /// receives its expected blips.
+    fn render_output_progress(&self, out: &mut render::Stage) {
+        const WIDTH: f32 = 0.8;
+        const THICKNESS: f32 = 0.06;
+
+        for (block_index, progress) in self.exec.output_progress() {
+            let (block_pos, _) = &self.exec.machine().blocks.data[block_index];
+            let bar_center = render::machine::block_center(block_pos) + na::Vector3::z() * 0.8;
+
+            out.plain[BasicObj::Cube].add(basic_obj::Instance {
+                transform: na::Matrix4::new_translation(&bar_center.coords)
+                    * na::Matrix4::new_nonuniform_scaling(&na::Vector3::new(
+                        WIDTH, THICKNESS, THICKNESS,
+                    )),
+                color: na::Vector4::new(0.1, 0.1, 0.1, 1.0),
+                ..Default::default()
+            });
+
+            // The filled part is slightly thicker, so that it is drawn on top
+            // of the background. Failed outputs have the whole bar filled.
+            let fraction = if progress.failed {
+                1.0
+            } else {
+                progress.fraction()
+            };
+            let fill = WIDTH * fraction;
+            if fill > 0.0 {
+                let fill_center = bar_center - na::Vector3::x() * (WIDTH - fill) / 2.0;
+                let color = render::machine::output_status_color(progress.failed, true);
+
+                out.plain[BasicObj::Cube].add(basic_obj::Instance {
+                    transform: na::Matrix4::new_translation(&fill_center.coords)
+                        * na::Matrix4::new_nonuniform_scaling(&na::Vector3::new(
+                            fill,
+                            THICKNESS * 1.2,
+                            THICKNESS * 1.2,
+                        )),
+                    color: render::machine::block_color(&color, 1.0),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    fn render_breakpoints(&self, out: &mut render::Stage) {
+        let color = na::Vector4::new(0.9, 0.1, 0.1, 1.0);
+
+        for &block_index in &self.breakpoints {
+            let (block_pos, _) = &self.exec.machine().blocks.data[block_index];
+            let center = render::machine::block_center(block_pos);
+            let transform =
+                na::Matrix4::new_translation(&center.coords) * na::Matrix4::new_scaling(1.05);
+
+            render::machine::render_line_wireframe(8.0, &color, &transform, out);
+        }
+    }
+
+    /// Marks the blocks that the player has switched off during execution.
+    fn render_disabled_blocks(&self, out: &mut render::Stage) {
+        let color = na::Vector4::new(0.4, 0.4, 0.4, 1.0);
+
+        for (block_index, state) in self.exec.block_states().iter().enumerate() {
+            if !state.disabled {
+                continue;
+            }
+
+            let (block_pos, _) = &self.exec.machine().blocks.data[block_index];
+            let center = render::machine::block_center(block_pos);
+            let transform =
+                na::Matrix4::new_translation(&center.coords) * na::Matrix4::new_scaling(1.02);
+
+            render::machine::render_line_wireframe(4.0, &color, &transform, out);
+        }
+    }
+
+    /// Highlights the output that caused the level to fail, if any.
+    fn render_failure(&self, out: &mut render::Stage) {
+        let output_index = match self.exec.outcome() {
+            Some(Outcome::Failure { reason, .. }) => reason.output_index(),
+            _ => None,
+        };
+        let output_index = match output_index {
+            Some(output_index) => output_index,
+            None => return,
+        };
+
+        let block_index = self
+            .exec
+            .next_level_progress()
+            .and_then(|progress| progress.outputs.get(output_index))
+            .and_then(|output| output.block_index);
+
+        if let Some(block_index) = block_index {
+            let (block_pos, _) = &self.exec.machine().blocks.data[block_index];
+            let center = render::machine::block_center(block_pos);
+            let transform =
+                na::Matrix4::new_translation(&center.coords) * na::Matrix4::new_scaling(1.1);
+            let color = render::machine::block_color(
+                &render::machine::output_status_color(true, false),
+                1.0,
+            );
+
+            render::machine::render_line_wireframe(15.0, &color, &transform, out);
+        }
     }
 
     pub fn transduce(
@@ -186,7 +1144,7 @@ impl ExecView {
                     }
 
                     let blip = &self.exec.blips()[*blip_index];
-                    let anim_input = self.blip_anim_input(blip);
+                    let anim_input = self.blip_anim_input(blip, time);
                     let anim_value = self
                         .blip_anim_cache
                         .get_or_insert(blip_anim::Key::at_time_f32(*die_time, anim_input));
@@ -196,7 +1154,7 @@ impl ExecView {
 
                     Self::kill_particles(
                         time.num_ticks_passed as f32 + die_time,
-                        blip.kind,
+                        self.blip_style.color(blip.kind),
                         &(anim_value.center(&blip.pos) + dir * 0.2),
                         &-dir,
                         budget_fraction,
@@ -213,7 +1171,7 @@ impl ExecView {
                     }
 
                     let blip = &self.exec.blips()[*blip_index];
-                    let anim_input = self.blip_anim_input(blip);
+                    let anim_input = self.blip_anim_input(blip, time);
 
                     let sub_tick_duration = 1.0 / (budget_fraction * num_particles as f32);
                     let mut current_time = progress_start;
@@ -243,7 +1201,7 @@ impl ExecView {
                                 life_duration,
                                 start_pos,
                                 velocity,
-                                color: render::machine::blip_color(blip.kind),
+                                color: self.blip_style.color(blip.kind),
                                 size: 0.01 * 10.0f32.sqrt(),
                                 friction,
                             };
@@ -267,7 +1225,7 @@ impl ExecView {
 
     fn kill_particles(
         spawn_time: f32,
-        kind: BlipKind,
+        color: na::Vector3<f32>,
         pos: &na::Point3<f32>,
         tangent: &na::Vector3<f32>,
         budget_fraction: f32,
@@ -300,7 +1258,7 @@ impl ExecView {
                 life_duration,
                 start_pos: *pos,
                 velocity,
-                color: render::machine::blip_color(kind),
+                color,
                 size: 0.03 * size_factor,
                 friction: velocity.norm() / life_duration,
             };
@@ -329,14 +1287,21 @@ impl ExecView {
                 start: in_t,
                 end: out_t,
                 phase: 2.0 * phase * std::f32::consts::PI,
+                idle_anim: 0.0,
+                color: na::Vector4::zeros(),
             });
         }
     }
 
     fn render_blocks(&self, time: &TickTime, out: &mut render::Stage) {
-        let blocks = &self.exec.machine().blocks;
-
-        for (block_index, (block_pos, placed_block)) in blocks.data.iter() {
+        let machine = self.exec.machine_with_air();
+        let wind_progress = self
+            .config
+            .timing
+            .curve(Anim::Wind, ticks_per_sec(time))
+            .apply(time.tick_progress());
+
+        for (block_index, (block_pos, placed_block)) in machine.iter_blocks() {
             let anim_state = AnimState::from_exec_block(&self.exec, block_index);
 
             for &dir in &Dir3::ALL {
@@ -361,7 +1326,7 @@ impl ExecView {
                     WindLife::None => (),
                     WindLife::Appearing => {
                         // Interpolate, i.e. draw partial line
-                        let out_t = time.tick_progress();
+                        let out_t = wind_progress;
                         self.render_wind(block_pos, dir, 0.0, out_t.min(max), out);
                     }
                     WindLife::Existing => {
@@ -370,7 +1335,7 @@ impl ExecView {
                     }
                     WindLife::Disappearing => {
                         // Interpolate, i.e. draw partial line
-                        let in_t = time.tick_progress();
+                        let in_t = wind_progress;
                         self.render_wind(block_pos, dir, in_t.min(max), 1.0f32.min(max), out);
                     }
                 }
@@ -381,8 +1346,10 @@ impl ExecView {
     fn render_blips(&mut self, time: &TickTime, out: &mut render::Stage) {
         profile!("blips");
 
-        for (_index, blip) in self.exec.blips().iter() {
-            let anim_input = self.blip_anim_input(blip);
+        self.blip_centers.clear();
+
+        for (index, blip) in self.exec.blips().iter() {
+            let anim_input = self.blip_anim_input(blip, time);
             let anim_value = self
                 .blip_anim_cache
                 .get_or_insert(blip_anim::Key::at_time_f32(
@@ -402,35 +1369,192 @@ impl ExecView {
 
             render::machine::render_outline(&transform, &scaling, 1.0, out);
 
-            let color = render::machine::blip_color(blip.kind);
-            let params = basic_obj::Instance {
-                color: na::Vector4::new(color.x, color.y, color.z, 1.0),
-                transform: transform * na::Matrix4::new_nonuniform_scaling(&scaling),
-                ..Default::default()
-            };
-            out.solid_glow[BasicObj::Cube].add(params);
+            let color = self.blip_style.color(blip.kind);
+            self.blip_style.render_kind(
+                blip.kind,
+                &(transform * na::Matrix4::new_nonuniform_scaling(&scaling)),
+                &na::Vector4::new(color.x, color.y, color.z, 1.0),
+                true,
+                out,
+            );
+
+            self.blip_centers
+                .push((index, anim_value.center(&blip.pos)));
 
             let intensity = anim_value.scaling.x * 10.0;
             out.lights.push(Light {
                 position: anim_value.center(&blip.pos),
                 //attenuation: na::Vector4::new(1.0, 6.0, 30.0, 0.0),
                 attenuation: na::Vector4::new(1.0, 0.0, 0.0, 7.0),
-                color: intensity * render::machine::blip_color(blip.kind),
+                color: intensity * color,
                 ..Default::default()
             });
+
+            if self.config.blip_trail_ticks > 0.0 {
+                let trail = self.blip_trails.entry(index).or_insert_with(VecDeque::new);
+                add_trail_sample(
+                    trail,
+                    time.to_f32(),
+                    anim_value.center(&blip.pos),
+                    self.config.blip_trail_spacing,
+                );
+            }
+        }
+
+        self.render_blip_trails(time, out);
+    }
+
+    fn render_blip_trails(&mut self, time: &TickTime, out: &mut render::Stage) {
+        let now = time.to_f32();
+        let max_age = self.config.blip_trail_ticks;
+
+        // Forget about blips that have been removed, and about old samples.
+        let alive: HashSet<BlipId> = self.exec.blips().keys().collect();
+        self.blip_trails.retain(|index, trail| {
+            while trail.front().map_or(false, |(t, _)| now - t > max_age) {
+                trail.pop_front();
+            }
+
+            alive.contains(index) && !trail.is_empty()
+        });
+
+        for (&index, trail) in self.blip_trails.iter() {
+            let color = self.blip_style.color(self.exec.blips()[index].kind);
+
+            // Skip the newest sample, which is hidden by the blip itself.
+            for &(t, pos) in trail.iter().rev().skip(1) {
+                let fade = 1.0 - (now - t) / max_age;
+                let size = 0.1 * fade;
+
+                out.solid_dither[BasicObj::Cube].add(basic_obj::Instance {
+                    transform: na::Matrix4::new_translation(&pos.coords)
+                        * na::Matrix4::new_scaling(size),
+                    color: na::Vector4::new(color.x, color.y, color.z, 0.6 * fade),
+                    ..Default::default()
+                });
+            }
         }
     }
 
-    fn blip_anim_input(&self, blip: &Blip) -> blip_anim::Input {
+    fn blip_anim_input(&self, blip: &Blip, time: &TickTime) -> blip_anim::Input {
         let is_on_wind = blip.move_dir.map_or(false, |dir| {
             self.exec
-                .machine()
+                .machine_with_air()
                 .get_index(&blip.pos)
                 .map_or(false, |block_index| {
                     self.exec.next_blocks().wind_out[block_index][dir]
                 })
         });
 
-        blip_anim::Input::from_blip(blip, is_on_wind)
+        let ticks_per_sec = ticks_per_sec(time);
+
+        blip_anim::Input::from_blip(
+            blip,
+            is_on_wind,
+            self.config.timing.curve(Anim::BlipMove, ticks_per_sec),
+            self.config.timing.curve(Anim::BlipSpawn, ticks_per_sec),
+            self.config.curved_blip_turns,
+        )
     }
 }
+
+/// Returns the number of ticks per second at which execution is played.
+fn ticks_per_sec(time: &TickTime) -> f32 {
+    1.0 / time.next_tick_timer.period().as_secs_f32()
+}
+
+/// Returns the time at which `phase` is animated, when the animated phases are
+/// shown one after another within each tick.
+fn phase_time(time: &TickTime, phase: Phase) -> TickTime {
+    let num_phases = Phase::ANIMATED.len() as f32;
+    let index = Phase::ANIMATED
+        .iter()
+        .position(|&animated| animated == phase)
+        .unwrap_or(0);
+    let progress = time.tick_progress() * num_phases - index as f32;
+
+    let mut phase_time = time.clone();
+    phase_time
+        .next_tick_timer
+        .set_progress(progress.max(0.0).min(1.0));
+    phase_time
+}
+
+/// Describes what happened in a phase of the tick, for phases that are worth
+/// mentioning.
+fn describe_phase(phase: Phase, exec: &Exec) -> Option<String> {
+    match phase {
+        Phase::Wind => {
+            let num_blocks = exec
+                .next_blocks()
+                .wind_out
+                .iter()
+                .filter(|wind_out| wind_out.iter().any(|(_, &wind)| wind))
+                .count();
+
+            Some(format!("{} blocks emit wind", num_blocks))
+        }
+        Phase::Move => {
+            let num_moving = exec
+                .blips()
+                .values()
+                .filter(|blip| blip.move_dir.is_some())
+                .count();
+
+            Some(format!(
+                "{} of {} blips moving",
+                num_moving,
+                exec.blips().len()
+            ))
+        }
+        Phase::Effects => {
+            let num_activated = exec
+                .blocks()
+                .activation
+                .iter()
+                .filter(|activation| activation.is_some())
+                .count();
+            let num_spawned = exec
+                .blips()
+                .values()
+                .filter(|blip| blip.status.is_spawning())
+                .count();
+
+            Some(format!(
+                "{} blocks activated, {} blips spawned",
+                num_activated, num_spawned
+            ))
+        }
+        Phase::Activate => {
+            let num_dying = exec
+                .blips()
+                .values()
+                .filter(|blip| blip.status.is_dead())
+                .count();
+
+            Some(format!("{} blips die", num_dying))
+        }
+        _ => None,
+    }
+}
+
+/// Adds a sample to the end of a blip's trail. Samples that are too close in
+/// time to the last one are skipped, so that trails do not depend on the
+/// frame rate. If the blip jumped, e.g. because the simulation was restarted
+/// or its index was reused, the trail starts anew.
+fn add_trail_sample(
+    trail: &mut VecDeque<(f32, na::Point3<f32>)>,
+    time: f32,
+    pos: na::Point3<f32>,
+    spacing: f32,
+) {
+    if let Some(&(last_time, last_pos)) = trail.back() {
+        if time < last_time || (pos - last_pos).norm() > 1.5 {
+            trail.clear();
+        } else if time - last_time < spacing {
+            return;
+        }
+    }
+
+    trail.push_back((time, pos));
+}
@@ -0,0 +1,95 @@
+use nalgebra as na;
+
+use glium::glutin;
+
+use crate::machine::Machine;
+use crate::render;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub toggle_key: glutin::VirtualKeyCode,
+
+    /// Speed at which the camera target moves along the flow path.
+    pub blocks_per_sec: f32,
+
+    /// Speed at which the camera circles around its target.
+    pub orbit_degrees_per_sec: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            toggle_key: glutin::VirtualKeyCode::C,
+            blocks_per_sec: 1.5,
+            orbit_degrees_per_sec: 10.0,
+        }
+    }
+}
+
+/// Moves the camera back and forth along the path that blips take from the
+/// inputs to the outputs, while slowly circling around it. Machines without
+/// such a path are circled around their center.
+#[derive(Debug, Clone)]
+pub struct Showcase {
+    config: Config,
+
+    /// Centers of the blocks along the path.
+    path: Vec<na::Point3<f32>>,
+
+    /// Distance that the target has travelled, counting each pass along the
+    /// path.
+    distance: f32,
+}
+
+impl Showcase {
+    pub fn new(config: &Config, machine: &Machine) -> Self {
+        let mut path: Vec<_> = machine
+            .flow_path()
+            .iter()
+            .map(render::machine::block_center)
+            .collect();
+
+        if path.is_empty() {
+            let size: na::Vector3<f32> = na::convert(machine.size());
+            path.push(na::Point3::from(size / 2.0));
+        }
+
+        Self {
+            config: config.clone(),
+            path,
+            distance: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, dt_secs: f32) {
+        self.distance += dt_secs * self.config.blocks_per_sec;
+    }
+
+    /// Returns the change in yaw of the camera for a frame of `dt_secs`.
+    pub fn yaw_delta(&self, dt_secs: f32) -> f32 {
+        dt_secs * self.config.orbit_degrees_per_sec.to_radians()
+    }
+
+    /// Returns the position that the camera should look at.
+    pub fn target(&self) -> na::Point3<f32> {
+        let num_segments = self.path.len() - 1;
+        if num_segments == 0 {
+            return self.path[0];
+        }
+
+        // Go back on every other pass, so that the camera does not jump.
+        let pass_length = num_segments as f32;
+        let pass = (self.distance / pass_length) as usize;
+        let offset = self.distance % pass_length;
+        let offset = if pass % 2 == 0 {
+            offset
+        } else {
+            pass_length - offset
+        };
+
+        let index = (offset as usize).min(num_segments - 1);
+        let t = offset - index as f32;
+
+        self.path[index] + (self.path[index + 1] - self.path[index]) * t
+    }
+}
@@ -0,0 +1,89 @@
+//! Incremental recomputation of wind.
+//!
+//! Once wind has spread through a machine, it stays the same unless a block
+//! is activated, e.g. when a blip presses the button of a wind source. The
+//! wind of a block only needs to be recomputed if its own activation or the
+//! wind of one of its neighbors changed. All other blocks keep the wind of
+//! the previous tick.
+
+use crate::machine::grid::{Dir3, DirMap3};
+use crate::machine::BlockIndex;
+
+use super::neighbors::NeighborMap;
+use super::Activation;
+
+pub struct WindCache {
+    /// Blocks whose wind needs to be recomputed in the coming tick.
+    stale: Vec<bool>,
+
+    /// Blocks that were activated in the previous tick, either in the
+    /// current or the previous activation.
+    was_activated: Vec<bool>,
+}
+
+impl WindCache {
+    pub fn new(num_blocks: usize) -> Self {
+        // Initially, the wind of all blocks needs to be computed.
+        Self {
+            stale: vec![true; num_blocks],
+            was_activated: vec![false; num_blocks],
+        }
+    }
+
+    pub fn is_stale(&self, block_index: BlockIndex) -> bool {
+        self.stale[block_index]
+    }
+
+    /// Marks a block whose wind needs to be recomputed in the coming tick,
+    /// e.g. since the player has switched it off.
+    pub fn mark_stale(&mut self, block_index: BlockIndex) {
+        self.stale[block_index] = true;
+    }
+
+    /// Marks the blocks whose activation changes the inputs of their wind.
+    /// This needs to be called before wind is computed.
+    pub fn mark_activated(&mut self, activation: &[Activation], prev_activation: &[Activation]) {
+        for block_index in 0..self.stale.len() {
+            let is_activated =
+                activation[block_index].is_some() || prev_activation[block_index].is_some();
+
+            if is_activated || self.was_activated[block_index] {
+                self.stale[block_index] = true;
+            }
+
+            self.was_activated[block_index] = is_activated;
+        }
+    }
+
+    /// Keeps the wind of blocks that were not recomputed, and determines the
+    /// blocks that need to be recomputed in the next tick. This needs to be
+    /// called after wind has been computed for the stale blocks.
+    pub fn finish_wind(
+        &mut self,
+        neighbor_map: &NeighborMap,
+        wind_out: &[DirMap3<bool>],
+        next_wind_out: &mut [DirMap3<bool>],
+    ) {
+        let mut next_stale = vec![false; self.stale.len()];
+
+        for block_index in 0..self.stale.len() {
+            if !self.stale[block_index] {
+                next_wind_out[block_index] = wind_out[block_index].clone();
+                continue;
+            }
+
+            if next_wind_out[block_index] != wind_out[block_index] {
+                // The neighbors get different wind from us in the next tick.
+                next_stale[block_index] = true;
+
+                for dir in &Dir3::ALL {
+                    if let Some(neighbor_index) = neighbor_map[block_index][*dir] {
+                        next_stale[neighbor_index] = true;
+                    }
+                }
+            }
+        }
+
+        self.stale = next_stale;
+    }
+}
@@ -0,0 +1,73 @@
+//! Fixed-timestep game loop.
+//!
+//! The time of each frame is accumulated, and the game state is advanced in
+//! steps of a fixed duration. Time that is left over is carried into the
+//! next frame. Rendering is interpolated by the fraction of a step that is
+//! left over, so that it stays smooth even if the frame rate is not a
+//! multiple of the step rate.
+
+use std::time::Duration;
+
+use crate::config::ClockConfig;
+use crate::util::timer::Timer;
+
+/// How far the game advances in one frame.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    /// Number of steps that the game state is advanced by.
+    pub num_steps: usize,
+
+    /// Duration of each step.
+    pub step: Duration,
+
+    /// Progress towards the next step, between 0 and 1.
+    pub alpha: f32,
+
+    /// Time that the frame took, limited to the maximal frame time.
+    pub frame_time: Duration,
+}
+
+impl Frame {
+    /// Returns a frame in which the game does not advance.
+    pub fn idle(step: Duration) -> Self {
+        Self {
+            num_steps: 0,
+            step,
+            alpha: 0.0,
+            frame_time: Duration::from_secs(0),
+        }
+    }
+
+    /// Returns the time by which rendering is ahead of the simulation.
+    pub fn interpolation(&self) -> Duration {
+        self.step.mul_f32(self.alpha)
+    }
+}
+
+pub struct Clock {
+    timer: Timer,
+    max_frame_time: Duration,
+}
+
+impl Clock {
+    pub fn new(config: &ClockConfig) -> Self {
+        Self {
+            timer: Timer::new(config.step),
+            max_frame_time: config.max_frame_time,
+        }
+    }
+
+    /// Accumulates the time that the last frame took, returning the steps
+    /// that the game needs to advance by.
+    pub fn advance(&mut self, frame_time: Duration) -> Frame {
+        let frame_time = frame_time.min(self.max_frame_time);
+        let num_steps = self.timer.drain_triggers(frame_time);
+
+        Frame {
+            num_steps,
+            step: self.timer.period(),
+            alpha: self.timer.progress().min(1.0),
+            frame_time,
+        }
+    }
+}
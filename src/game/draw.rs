@@ -1,3 +1,9 @@
+use std::path::PathBuf;
+
+use glium::Surface;
+use log::warn;
+use nalgebra as na;
+
 use crate::config::Config;
 use crate::render;
 
@@ -8,21 +14,45 @@ pub struct Input<'a> {
 
 pub struct Draw {
     render_pipeline: render::Pipeline,
+
+    /// The configuration that the render pipeline was created with. This can
+    /// differ from the requested one if some components failed to create.
+    pipeline_config: render::Config,
 }
 
 impl Draw {
+    /// Creates the render pipeline. If this fails, components of the pipeline
+    /// are disabled one by one until creation succeeds, so that the game can
+    /// still be played on weaker GPUs.
     pub fn create<F: glium::backend::Facade>(
         facade: &F,
         config: &Config,
-    ) -> Result<Self, rendology::pipeline::CreationError> {
-        // TODO: Account for DPI in initialization
-        let render_pipeline = render::Pipeline::create(
-            facade,
-            &config.render_pipeline,
-            config.view.window_size.into(),
-        )?;
+        target_size: (u32, u32),
+        meshes: &[(String, PathBuf)],
+    ) -> Result<Self, render::Error> {
+        let mut pipeline_config = config.render_pipeline.clone();
+
+        let mut render_pipeline = loop {
+            match render::Pipeline::create(facade, &pipeline_config, target_size) {
+                Ok(render_pipeline) => break render_pipeline,
+                Err(err) => match reduce_pipeline_config(&mut pipeline_config) {
+                    Some(component) => {
+                        warn!("{}. Retrying without {}", err, component);
+                    }
+                    None => return Err(err),
+                },
+            }
+        };
+        render_pipeline.load_meshes(facade, meshes);
 
-        Ok(Draw { render_pipeline })
+        Ok(Draw {
+            render_pipeline,
+            pipeline_config,
+        })
+    }
+
+    pub fn pipeline_config(&self) -> &render::Config {
+        &self.pipeline_config
     }
 
     pub fn draw<F: glium::backend::Facade, S: glium::Surface>(
@@ -30,12 +60,83 @@ impl Draw {
         facade: &F,
         input: &Input,
         target: &mut S,
-    ) -> Result<(), rendology::DrawError> {
+    ) -> Result<(), render::Error> {
         self.render_pipeline
             .draw_frame(facade, &input.context, input.stage, target)
     }
 
+    /// Draws the frame into an offscreen target of the given size, returning
+    /// its pixels. This allows taking screenshots at a higher resolution than
+    /// that of the window.
+    pub fn draw_to_image<F: glium::backend::Facade>(
+        &mut self,
+        facade: &F,
+        input: &Input,
+        size: (u32, u32),
+    ) -> Result<image::RgbaImage, String> {
+        let texture = glium::texture::Texture2d::empty_with_format(
+            facade,
+            glium::texture::UncompressedFloatFormat::U8U8U8U8,
+            glium::texture::MipmapsOption::NoMipmap,
+            size.0,
+            size.1,
+        )
+        .map_err(|err| format!("{:?}", err))?;
+        let depth = glium::framebuffer::DepthRenderBuffer::new(
+            facade,
+            glium::texture::DepthFormat::I24,
+            size.0,
+            size.1,
+        )
+        .map_err(|err| format!("{:?}", err))?;
+        let mut target =
+            glium::framebuffer::SimpleFrameBuffer::with_depth_buffer(facade, &texture, &depth)
+                .map_err(|err| format!("{:?}", err))?;
+        target.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+
+        let mut context = input.context.clone();
+        context.rendology.camera.viewport_size = na::Vector2::new(size.0 as f32, size.1 as f32);
+
+        self.render_pipeline
+            .draw_frame(facade, &context, input.stage, &mut target)
+            .map_err(|err| err.to_string())?;
+
+        let pixels: glium::texture::RawImage2d<u8> = texture.read();
+        let image =
+            image::RgbaImage::from_raw(pixels.width, pixels.height, pixels.data.into_owned())
+                .ok_or_else(|| "Unexpected size of screenshot data".to_string())?;
+
+        // OpenGL's origin is the bottom left corner.
+        Ok(image::imageops::flip_vertical(&image))
+    }
+
     pub fn clean_up_after_exec(&mut self) {
         self.render_pipeline.clear_particles();
     }
 }
+
+/// Disables the most expensive component that is still enabled in `config`,
+/// returning its name. Returns `None` if there is nothing left to disable.
+fn reduce_pipeline_config(config: &mut render::Config) -> Option<&'static str> {
+    if config.depth_of_field.is_some() {
+        config.depth_of_field = None;
+        Some("depth of field")
+    } else if config.rendology.glow.is_some() {
+        config.rendology.glow = None;
+        Some("glow")
+    } else if config.rendology.shadow_mapping.is_some() {
+        config.rendology.shadow_mapping = None;
+        Some("shadow mapping")
+    } else if config.rendology.deferred_shading.is_some() {
+        config.rendology.deferred_shading = None;
+        Some("deferred shading")
+    } else if config.rendology.hdr.is_some() {
+        config.rendology.hdr = None;
+        Some("HDR")
+    } else if config.rendology.fxaa.is_some() {
+        config.rendology.fxaa = None;
+        Some("FXAA")
+    } else {
+        None
+    }
+}
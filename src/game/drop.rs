@@ -0,0 +1,106 @@
+use std::fs;
+use std::path::Path;
+
+use imgui::{im_str, ImString};
+use log::{info, warn};
+
+use crate::edit::solutions;
+use crate::game::Game;
+use crate::machine::{text, Level, Machine, SavedMachine};
+
+/// The contents of a file that has been dropped onto the window.
+#[derive(Debug, Clone)]
+pub enum DroppedFile {
+    /// A saved machine or blueprint, which is opened in the editor.
+    Machine(Machine),
+
+    /// A level, which is started with an empty machine.
+    Level(Level),
+}
+
+/// Loads a file that has been dropped onto the window. JSON files may contain
+/// either a machine or a level.
+pub fn load(path: &Path) -> Result<DroppedFile, String> {
+    let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+
+    let is_text = path
+        .extension()
+        .map_or(false, |ext| ext == solutions::TEXT_EXTENSION);
+    let is_json = path.extension().map_or(false, |ext| ext == "json");
+
+    if is_text {
+        text::parse(&contents)
+            .map(DroppedFile::Machine)
+            .map_err(|err| err.to_string())
+    } else if is_json {
+        match serde_json::from_str::<SavedMachine>(&contents) {
+            Ok(saved_machine) => Ok(DroppedFile::Machine(saved_machine.into_machine())),
+            Err(machine_err) => match serde_json::from_str::<Level>(&contents) {
+                Ok(level) => Ok(DroppedFile::Level(level)),
+                Err(_) => Err(format!("Not a machine or level: {}", machine_err)),
+            },
+        }
+    } else {
+        Err(format!(
+            "Unsupported file type, expected .json or .{}",
+            solutions::TEXT_EXTENSION
+        ))
+    }
+}
+
+impl Game {
+    pub fn on_dropped_file(&mut self, path: &Path) {
+        info!("Loading dropped file {:?}", path);
+
+        if !self.state.is_edit() && !self.state.is_menu() {
+            self.drop_error = Some("Stop the execution before loading a file.".to_string());
+            return;
+        }
+
+        match load(path) {
+            Ok(DroppedFile::Machine(machine)) => self.start_machine(machine),
+            Ok(DroppedFile::Level(level)) => self.start_machine(Machine::new_from_level(level)),
+            Err(err) => {
+                warn!("Could not load dropped file {:?}: {}", path, err);
+                self.drop_error = Some(format!(
+                    "Could not load {}:\n{}",
+                    path.file_name().map_or_else(
+                        || path.display().to_string(),
+                        |name| name.to_string_lossy().into_owned()
+                    ),
+                    err
+                ));
+            }
+        }
+    }
+
+    pub(super) fn ui_drop_error(&mut self, ui: &imgui::Ui) {
+        let message = match self.drop_error.as_ref() {
+            Some(message) => message,
+            None => return,
+        };
+
+        let mut dismiss = false;
+
+        imgui::Window::new(im_str!("Loading failed"))
+            .position(
+                [ui.io().display_size[0] / 2.0, ui.io().display_size[1] / 2.0],
+                imgui::Condition::Always,
+            )
+            .position_pivot([0.5, 0.5])
+            .always_auto_resize(true)
+            .collapsible(false)
+            .build(&ui, || {
+                ui.text(&ImString::new(message.clone()));
+                ui.separator();
+
+                if ui.button(im_str!("OK"), [120.0, 20.0]) {
+                    dismiss = true;
+                }
+            });
+
+        if dismiss {
+            self.drop_error = None;
+        }
+    }
+}
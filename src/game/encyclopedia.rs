@@ -0,0 +1,259 @@
+use std::path::Path;
+use std::rc::Rc;
+
+use log::{info, warn};
+use nalgebra as na;
+
+use glium::texture::{MipmapsOption, Texture2d, UncompressedFloatFormat};
+use glium::Surface;
+use imgui::{im_str, ImString};
+
+use rendology::Camera;
+
+use crate::config::Config;
+use crate::exec::TickTime;
+use crate::locale::Locale;
+use crate::machine::grid::{Axis3, Dir3, Point3, Sign};
+use crate::machine::{registry, Block, BlockType, PlacedBlock};
+use crate::render;
+use crate::render::blip_style::BlipStyle;
+
+const PREVIEW_SIZE: u32 = 128;
+
+/// A help screen that lists every type of block. Everything shown is derived
+/// from the block definitions, with the exception of the usage examples.
+#[derive(Default)]
+pub struct Encyclopedia {
+    /// Offscreen-rendered previews of each block type, registered as imgui
+    /// textures. These are created lazily when the encyclopedia is first
+    /// opened.
+    previews: Option<Vec<Option<imgui::TextureId>>>,
+}
+
+impl Encyclopedia {
+    pub fn has_previews(&self) -> bool {
+        self.previews.is_some()
+    }
+
+    pub fn create_previews<F: glium::backend::Facade>(
+        &mut self,
+        facade: &F,
+        config: &Config,
+        textures: &mut imgui::Textures<Rc<Texture2d>>,
+    ) {
+        info!("Rendering block previews");
+
+        let mut pipeline = match render::Pipeline::create(
+            facade,
+            &config.render_pipeline,
+            (PREVIEW_SIZE, PREVIEW_SIZE),
+        ) {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                warn!("Failed to create pipeline for block previews: {}", err);
+                self.previews = Some(vec![None; BlockType::ALL.len()]);
+                return;
+            }
+        };
+
+        // Blip shapes other than cubes are builtin meshes.
+        pipeline.load_meshes(
+            facade,
+            &render::mesh::list_dir(Path::new(render::mesh::BUILTIN_DIR)),
+        );
+
+        let previews = BlockType::ALL
+            .iter()
+            .map(|block_type| {
+                let texture = render_preview(
+                    facade,
+                    &mut pipeline,
+                    &config.blip_style,
+                    &block_type.example(),
+                );
+
+                match texture {
+                    Ok(texture) => Some(textures.insert(Rc::new(texture))),
+                    Err(err) => {
+                        warn!("Failed to render preview of {:?}: {}", block_type, err);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        self.previews = Some(previews);
+    }
+
+    pub fn ui(&self, locale: &Locale, ui: &imgui::Ui, opened: &mut bool) {
+        imgui::Window::new(im_str!("Blocks"))
+            .size([600.0, 700.0], imgui::Condition::FirstUseEver)
+            .opened(opened)
+            .bg_alpha(0.9)
+            .build(&ui, || {
+                for (index, block_type) in BlockType::ALL.iter().enumerate() {
+                    let block = block_type.example();
+                    let preview = self.previews.as_ref().and_then(|previews| previews[index]);
+
+                    let label = format!("{}###{}", locale.block_name(&block), block.key());
+
+                    imgui::TreeNode::new(ui, &ImString::new(label))
+                        .opened(false, imgui::Condition::FirstUseEver)
+                        .build(|| {
+                            ui_block(&block, preview, locale, ui);
+                        });
+                }
+            });
+    }
+}
+
+fn ui_block(block: &Block, preview: Option<imgui::TextureId>, locale: &Locale, ui: &imgui::Ui) {
+    if let Some(texture_id) = preview {
+        // Rendered textures are upside down.
+        imgui::Image::new(texture_id, [PREVIEW_SIZE as f32, PREVIEW_SIZE as f32])
+            .uv0([0.0, 1.0])
+            .uv1([1.0, 0.0])
+            .build(ui);
+    }
+
+    let usage = locale.get(
+        &format!("block.{}.usage", block.key()),
+        registry::get(block.block_type()).usage,
+    );
+
+    ui.text_wrapped(&ImString::new(locale.block_description(block)));
+    ui.spacing();
+    ui.text_wrapped(&ImString::new(usage));
+    ui.spacing();
+
+    ui_holes(block, ui);
+}
+
+/// Shows which directions of the block let wind and blips through.
+fn ui_holes(block: &Block, ui: &imgui::Ui) {
+    let columns = ["Side", "Wind in", "Wind out", "Wind source", "Blips"];
+
+    ui.columns(columns.len() as i32, im_str!("holes"), true);
+
+    for column in columns.iter() {
+        ui.text_disabled(&ImString::new(*column));
+        ui.next_column();
+    }
+
+    for &dir in &Dir3::ALL {
+        let holes = [
+            block.has_wind_hole_in(dir, false),
+            block.has_wind_hole_out(dir, false),
+            block.has_wind_source(dir),
+            block.has_move_hole(dir, false),
+        ];
+
+        ui.text(&ImString::new(dir_name(dir)));
+        ui.next_column();
+
+        for &hole in holes.iter() {
+            ui.text(if hole { im_str!("■") } else { im_str!("·") });
+            ui.next_column();
+        }
+    }
+
+    ui.columns(1, im_str!("holes_end"), false);
+}
+
+pub(super) fn dir_name(dir: Dir3) -> String {
+    let sign = match dir.1 {
+        Sign::Pos => "+",
+        Sign::Neg => "-",
+    };
+    let axis = match dir.0 {
+        Axis3::X => "X",
+        Axis3::Y => "Y",
+        Axis3::Z => "Z",
+    };
+
+    format!("{}{}", sign, axis)
+}
+
+fn render_preview<F: glium::backend::Facade>(
+    facade: &F,
+    pipeline: &mut render::Pipeline,
+    blip_style: &BlipStyle,
+    block: &Block,
+) -> Result<Texture2d, String> {
+    let texture = Texture2d::empty_with_format(
+        facade,
+        UncompressedFloatFormat::U8U8U8U8,
+        MipmapsOption::NoMipmap,
+        PREVIEW_SIZE,
+        PREVIEW_SIZE,
+    )
+    .map_err(|err| format!("{:?}", err))?;
+    let depth = glium::framebuffer::DepthRenderBuffer::new(
+        facade,
+        glium::texture::DepthFormat::I24,
+        PREVIEW_SIZE,
+        PREVIEW_SIZE,
+    )
+    .map_err(|err| format!("{:?}", err))?;
+
+    let mut stage = render::Stage::default();
+    let tick_time = TickTime::zero();
+    let placed_block = PlacedBlock {
+        block: block.clone(),
+    };
+    let center = render::machine::block_center(&Point3::origin());
+
+    render::machine::render_block(
+        &placed_block,
+        &tick_time,
+        None,
+        None,
+        None,
+        &center,
+        &render::machine::placed_block_transform(&placed_block),
+        1.0,
+        blip_style,
+        &mut stage,
+    );
+
+    let main_light_pos = center + na::Vector3::new(3.0, -4.0, 6.0);
+    stage.lights.push(rendology::Light {
+        position: main_light_pos,
+        attenuation: na::Vector4::new(1.0, 0.0, 0.0, 0.0),
+        color: na::Vector3::new(1.0, 1.0, 1.0),
+        is_main: true,
+        ..Default::default()
+    });
+
+    let viewport_size = na::Vector2::new(PREVIEW_SIZE as f32, PREVIEW_SIZE as f32);
+    let projection = na::Perspective3::new(1.0, 45.0f32.to_radians(), 0.1, 100.0);
+    let mut camera = Camera::new(viewport_size, projection.to_homogeneous());
+    camera.view = na::Matrix4::look_at_rh(
+        &(center + na::Vector3::new(1.6, -2.0, 1.8)),
+        &center,
+        &na::Vector3::z(),
+    );
+
+    let context = render::Context {
+        rendology: rendology::Context {
+            camera,
+            main_light_pos,
+            main_light_center: center,
+            ambient_light: na::Vector3::new(0.3, 0.3, 0.3),
+        },
+        tick_time,
+        time: 0.0,
+        depth_of_field: false,
+    };
+
+    let mut target =
+        glium::framebuffer::SimpleFrameBuffer::with_depth_buffer(facade, &texture, &depth)
+            .map_err(|err| format!("{:?}", err))?;
+    target.clear_color_and_depth((0.1, 0.1, 0.1, 1.0), 1.0);
+
+    pipeline
+        .draw_frame(facade, &context, &stage, &mut target)
+        .map_err(|err| err.to_string())?;
+
+    Ok(texture)
+}
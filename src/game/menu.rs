@@ -0,0 +1,401 @@
+use imgui::{im_str, ImString};
+use log::info;
+
+use crate::edit::solutions;
+use crate::game::{Game, State};
+use crate::machine::grid;
+use crate::machine::template::{self, Template};
+use crate::machine::Machine;
+use crate::workshop::ItemKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Page {
+    Main,
+
+    /// Selection of the levels to play.
+    Campaign,
+
+    /// Choice of the size, level and template of a new machine.
+    NewMachine,
+
+    /// Selection of a saved sandbox machine.
+    LoadMachine,
+
+    /// Selection of the level for a race.
+    Race,
+
+    /// Selection of the solutions that race each other on a level, given by
+    /// its index in the content's levels.
+    RaceSetup(usize),
+
+    /// The player's lifetime stats and achievements.
+    Profile,
+}
+
+/// The choices for a new machine that is being set up in the menu.
+#[derive(Debug, Clone)]
+pub struct NewMachineSetup {
+    pub size: [i32; 3],
+
+    /// Index of the level to attach in the content's levels, if any.
+    pub level: Option<usize>,
+
+    pub template: Template,
+}
+
+impl NewMachineSetup {
+    pub fn new(size: &grid::Vector3) -> Self {
+        Self {
+            size: [size.x as i32, size.y as i32, size.z as i32],
+            level: None,
+            template: Template::Empty,
+        }
+    }
+}
+
+impl Game {
+    pub fn open_menu(&mut self) {
+        info!("Opening main menu");
+
+        self.state = State::Menu { page: Page::Main };
+        self.next_input_stage.menu = Some(true);
+    }
+
+    fn close_menu(&mut self) {
+        self.state = State::Edit;
+        self.next_input_stage.menu = Some(false);
+    }
+
+    /// Closes the menu, continuing with the given machine in the editor.
+    pub(super) fn start_machine(&mut self, machine: Machine) {
+        self.next_input_stage.load_machine = Some(machine);
+        self.close_menu();
+    }
+
+    pub(super) fn ui_menu(&mut self, page: Page, ui: &imgui::Ui) {
+        let mut next_page = page;
+        let mut close = false;
+        let mut start_machine = None;
+
+        let button_size = [240.0, 30.0];
+        let display_size = ui.io().display_size;
+
+        imgui::Window::new(im_str!("Main menu"))
+            .title_bar(false)
+            .movable(false)
+            .resizable(false)
+            .position(
+                [display_size[0] / 2.0, display_size[1] / 2.0],
+                imgui::Condition::Always,
+            )
+            .position_pivot([0.5, 0.5])
+            .always_auto_resize(true)
+            .bg_alpha(0.8)
+            .build(&ui, || {
+                ui.text(im_str!("Ultimate Scale"));
+                ui.separator();
+
+                match page {
+                    Page::Main => {
+                        let continue_label = self.locale.label("ui.menu.continue", "Continue");
+                        if ui.button(&ImString::new(continue_label), button_size) {
+                            close = true;
+                        }
+
+                        let campaign_label = self.locale.label("ui.menu.campaign", "Campaign");
+                        if ui.button(&ImString::new(campaign_label), button_size) {
+                            next_page = Page::Campaign;
+                        }
+
+                        let new_label = self.locale.label("ui.menu.new_machine", "New machine");
+                        if ui.button(&ImString::new(new_label), button_size) {
+                            self.new_machine_setup =
+                                NewMachineSetup::new(&self.config.editor.sandbox_size);
+                            next_page = Page::NewMachine;
+                        }
+
+                        let load_label = self.locale.label("ui.menu.load", "Load machine");
+                        if ui.button(&ImString::new(load_label), button_size) {
+                            next_page = Page::LoadMachine;
+                        }
+
+                        let race_label = self.locale.label("ui.menu.race", "Race");
+                        if ui.button(&ImString::new(race_label), button_size) {
+                            next_page = Page::Race;
+                        }
+
+                        let profile_label = self.locale.label("ui.menu.profile", "Profile");
+                        if ui.button(&ImString::new(profile_label), button_size) {
+                            next_page = Page::Profile;
+                        }
+
+                        let settings_label = self.locale.label("ui.menu.settings", "Settings");
+                        if ui.button(&ImString::new(settings_label), button_size) {
+                            self.show_config_ui = !self.show_config_ui;
+                        }
+
+                        let quit_label = self.locale.label("ui.menu.quit", "Quit");
+                        if ui.button(&ImString::new(quit_label), button_size) {
+                            self.quit_requested = true;
+                        }
+                    }
+                    Page::Campaign => {
+                        for level in self.content.levels() {
+                            // Mark levels that have been completed before.
+                            let completed = self.progress.best_score(&level).is_some();
+                            let label = format!(
+                                "{} {}##{}",
+                                if completed { "\u{25A0}" } else { "\u{25A1}" },
+                                self.locale.level_description(&level),
+                                level.id(),
+                            );
+
+                            if ui.button(&ImString::new(label), button_size) {
+                                start_machine = Some(Machine::new_from_level(level));
+                            }
+                        }
+                    }
+                    Page::NewMachine => {
+                        self.ui_new_machine(ui);
+
+                        let create_label = self.locale.label("ui.menu.create", "Create");
+                        if ui.button(&ImString::new(create_label), button_size) {
+                            start_machine = Some(self.new_machine());
+                        }
+                    }
+                    Page::LoadMachine => {
+                        let solutions_dir = &self.config.editor.solutions_dir;
+                        let names = solutions::list(solutions_dir, None);
+
+                        if names.is_empty() {
+                            ui.text(im_str!("No machines have been saved yet."));
+                        }
+
+                        for name in names {
+                            if ui.button(&ImString::new(name.as_str()), button_size) {
+                                let path = solutions::path(solutions_dir, None, &name);
+                                start_machine = solutions::load(&path);
+                            }
+                        }
+
+                        if !self.workshop.items().is_empty() {
+                            ui.separator();
+                            ui.text(im_str!("Workshop"));
+
+                            start_machine = start_machine.or_else(|| self.ui_workshop(ui));
+                        }
+                    }
+                    Page::Race => {
+                        for (index, level) in self.content.levels().into_iter().enumerate() {
+                            let label = format!(
+                                "{}##race{}",
+                                self.locale.level_description(&level),
+                                level.id(),
+                            );
+
+                            if ui.button(&ImString::new(label), button_size) {
+                                self.race_setup = Default::default();
+                                next_page = Page::RaceSetup(index);
+                            }
+                        }
+                    }
+                    Page::RaceSetup(index) => {
+                        if let Some(level) = self.content.levels().get(index).cloned() {
+                            self.ui_race_setup(&level, ui);
+
+                            let start_label = self.locale.label("ui.menu.start_race", "Start race");
+                            if ui.button(&ImString::new(start_label), button_size)
+                                && self.start_race(&level)
+                            {
+                                close = true;
+                            }
+                        }
+                    }
+                    Page::Profile => {
+                        self.ui_profile(ui);
+                    }
+                }
+
+                if page != Page::Main {
+                    ui.separator();
+
+                    let back_label = self.locale.label("ui.menu.back", "Back");
+                    if ui.button(&ImString::new(back_label), button_size) {
+                        next_page = Page::Main;
+                    }
+                }
+            });
+
+        if let Some(machine) = start_machine {
+            self.start_machine(machine);
+        } else if close {
+            self.close_menu();
+        } else if next_page != page {
+            self.state = State::Menu { page: next_page };
+        }
+    }
+
+    fn ui_new_machine(&mut self, ui: &imgui::Ui) {
+        let setup = &mut self.new_machine_setup;
+
+        let max_size = self.config.editor.max_new_machine_size;
+        imgui::Slider::new(im_str!("Width"), 1..=max_size.x as i32).build(ui, &mut setup.size[0]);
+        imgui::Slider::new(im_str!("Depth"), 1..=max_size.y as i32).build(ui, &mut setup.size[1]);
+        imgui::Slider::new(im_str!("Height"), 1..=max_size.z as i32).build(ui, &mut setup.size[2]);
+
+        ui.separator();
+        ui.text(im_str!("Template"));
+
+        for &template in Template::ALL.iter() {
+            if imgui::Selectable::new(&ImString::new(template.name()))
+                .selected(setup.template == template)
+                .build(ui)
+            {
+                setup.template = template;
+            }
+        }
+
+        ui.separator();
+        ui.text(im_str!("Level"));
+
+        if imgui::Selectable::new(im_str!("None (sandbox)"))
+            .selected(setup.level.is_none())
+            .build(ui)
+        {
+            setup.level = None;
+        }
+
+        for (index, level) in self.content.levels().into_iter().enumerate() {
+            let label = format!(
+                "{}##new_machine{}",
+                self.locale.level_description(&level),
+                level.id(),
+            );
+
+            if imgui::Selectable::new(&ImString::new(label))
+                .selected(setup.level == Some(index))
+                .build(ui)
+            {
+                setup.level = Some(index);
+            }
+        }
+
+        let level = setup
+            .level
+            .and_then(|index| self.content.levels().get(index).cloned());
+        let size = grid::Vector3::new(
+            setup.size[0] as isize,
+            setup.size[1] as isize,
+            setup.size[2] as isize,
+        );
+        let machine_size = template::machine_size(size, level.as_ref());
+        if machine_size != size {
+            ui.text_colored(
+                [1.0, 0.8, 0.3, 1.0],
+                &ImString::new(format!(
+                    "The machine will have a size of {}x{}x{} to fit the level.",
+                    machine_size.x, machine_size.y, machine_size.z
+                )),
+            );
+        }
+    }
+
+    /// Creates the machine that has been set up in the menu.
+    fn new_machine(&self) -> Machine {
+        let setup = &self.new_machine_setup;
+        let size = grid::Vector3::new(
+            setup.size[0] as isize,
+            setup.size[1] as isize,
+            setup.size[2] as isize,
+        );
+        let level = setup
+            .level
+            .and_then(|index| self.content.levels().get(index).cloned());
+
+        info!(
+            "Creating machine of size {:?} with template {:?}",
+            size, setup.template
+        );
+
+        setup.template.new_machine(size, level)
+    }
+
+    /// Lists the workshop items that match the player's query. Returns the
+    /// machine of the item that the player has chosen to load, if any.
+    fn ui_workshop(&mut self, ui: &imgui::Ui) -> Option<Machine> {
+        ui.input_text(im_str!("Search"), &mut self.workshop_query)
+            .build();
+        if ui.is_item_hovered() {
+            ui.tooltip(|| ui.text(im_str!("Words of the title, or beginnings of tags.")));
+        }
+
+        let mut chosen = None;
+
+        for item in self.workshop.search(None, self.workshop_query.to_str()) {
+            let kind = match item.kind {
+                ItemKind::Machine => "Machine",
+                ItemKind::Blueprint => "Blueprint",
+                ItemKind::Level => "Level",
+            };
+            let label = format!("{} ({})##{}", item.title, kind, item.id);
+
+            if ui.button(&ImString::new(label), [240.0, 30.0]) {
+                chosen = Some(item.clone());
+            }
+            if ui.is_item_hovered() && (!item.description.is_empty() || !item.tags.is_empty()) {
+                let text = format!("{}\n\nTags: {}", item.description, item.tags.join(", "));
+                ui.tooltip(|| ui.text(&ImString::new(text.trim())));
+            }
+        }
+
+        let item = chosen?;
+        match item.kind {
+            ItemKind::Machine | ItemKind::Blueprint => self.workshop.load_machine(&item),
+            ItemKind::Level => self.workshop.load_level(&item).map(Machine::new_from_level),
+        }
+    }
+
+    fn ui_profile(&self, ui: &imgui::Ui) {
+        let stats = &self.progress.stats;
+        let playtime_mins = (stats.playtime_secs / 60.0) as u64;
+
+        ui.text(&ImString::new(format!(
+            "Blocks placed: {}",
+            stats.blocks_placed
+        )));
+        ui.text(&ImString::new(format!(
+            "Ticks simulated: {}",
+            stats.ticks_simulated
+        )));
+        ui.text(&ImString::new(format!(
+            "Levels solved: {}",
+            self.progress.levels_solved()
+        )));
+        ui.text(&ImString::new(format!(
+            "Playtime: {}h {:02}m",
+            playtime_mins / 60,
+            playtime_mins % 60
+        )));
+
+        ui.separator();
+        ui.text(im_str!("Achievements"));
+
+        for definition in self.achievement_definitions.iter() {
+            if self.progress.achievements.is_unlocked(&definition.id) {
+                ui.text_colored(
+                    [1.0, 0.85, 0.2, 1.0],
+                    &ImString::new(format!("[x] {}", definition.title)),
+                );
+            } else {
+                ui.text_colored(
+                    [0.6, 0.6, 0.6, 1.0],
+                    &ImString::new(format!("[ ] {}", definition.title)),
+                );
+            }
+
+            if ui.is_item_hovered() {
+                ui.tooltip(|| ui.text(&ImString::new(definition.description.clone())));
+            }
+        }
+    }
+}
@@ -1,89 +1,246 @@
+mod clock;
 mod draw;
+mod drop;
+mod encyclopedia;
+mod menu;
+mod race;
+mod state;
+mod toast;
 mod ui;
 mod update;
 
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::time::Duration;
 
 use coarse_prof::profile;
-use log::info;
+use log::{info, warn};
 
 use glium::glutin;
 
-use crate::config::Config;
-use crate::edit::Editor;
+use crate::achievements::{self, Definition};
+use crate::config::{Config, ViewConfig, WindowMode};
+use crate::content::Content;
+use crate::edit::{editor, Editor};
 use crate::exec::play::{self, Play};
-use crate::exec::LevelStatus;
+use crate::exec::{LevelStatus, Score};
+use crate::input_route::Route;
 use crate::input_state::InputState;
-use crate::machine::Machine;
+use crate::locale::{self, Locale};
+use crate::machine::{Level, Machine, SavedMachine};
+use crate::net::share;
+use crate::photo_mode;
+use crate::progress::Progress;
+use crate::render;
 use crate::util::stats;
+use crate::workshop::Workshop;
 
+use clock::{Clock, Frame};
 use draw::Draw;
+use encyclopedia::Encyclopedia;
+use menu::NewMachineSetup;
+use race::{RaceRun, RaceSetup};
+use state::State;
+use toast::Toast;
 use update::{Update, UpdateRunner};
 
+/// Results of a machine that has completed a level.
+#[derive(Debug, Clone)]
+pub struct Results {
+    pub level: Level,
+    pub machine: SavedMachine,
+    pub score: Score,
+
+    /// Best score that had been achieved before this run, if any.
+    pub prev_best: Option<Score>,
+
+    /// Scores of all runs that have completed the level, including this one.
+    pub history: Vec<Score>,
+}
+
 pub struct Game {
     config: Config,
 
     update: UpdateRunner,
     draw: Draw,
 
+    /// Divides the time of frames into the fixed steps of the simulation.
+    clock: Clock,
+
+    /// Size of the render target in physical pixels.
     target_size: (u32, u32),
 
     last_output: Option<update::Output>,
     next_input_stage: update::InputStage,
+    editor_ui_state: editor::ui::State,
 
+    state: State,
     play: Play,
-    play_status: Option<play::Status>,
+
+    /// Set when the player wants to restart execution. Execution is then
+    /// stopped for one update and started again afterwards.
+    retry_exec: bool,
+
+    progress: Progress,
+    achievement_definitions: Vec<Definition>,
+
+    /// Notifications that are currently shown, e.g. about unlocked
+    /// achievements.
+    toasts: Vec<Toast>,
+
+    share_client: Option<share::Client>,
+    shared_solutions: Option<Vec<share::SharedSolution>>,
+
+    encyclopedia: Encyclopedia,
+    show_encyclopedia: bool,
+
+    content: Content,
+    show_content: bool,
+
+    /// Shareable items in the local workshop directory.
+    workshop: Workshop,
+
+    /// Query for the workshop items shown in the menu.
+    workshop_query: imgui::ImString,
+
+    locale: Locale,
+
+    /// Languages that can be selected in the config window.
+    languages: Vec<String>,
 
     debug_frame_time: stats::Variable,
     show_config_ui: bool,
     show_debug_ui: bool,
     recreate_render_pipeline: bool,
+
+    /// Set when the window mode has been changed, so that it needs to be
+    /// applied to the window.
+    view_changed: bool,
+    monitor_names: Vec<String>,
+
+    take_screenshot: bool,
+
+    /// Set when the player has chosen to quit in the main menu.
+    quit_requested: bool,
+
+    /// The last error of the render pipeline.
+    render_error: Option<String>,
+
+    /// Whether the last render error is still shown on screen.
+    show_render_error: bool,
+
+    /// Why the last file that was dropped onto the window could not be
+    /// loaded, until the player dismisses it.
+    drop_error: Option<String>,
+
+    /// The choices for a new machine in the menu.
+    new_machine_setup: NewMachineSetup,
+
+    /// The solutions picked for a race in the menu.
+    race_setup: RaceSetup,
+
+    /// The race that is being run, if any.
+    race: Option<RaceRun>,
 }
 
 impl Game {
     pub fn create<F: glium::backend::Facade>(
         facade: &F,
         config: &Config,
+        hidpi_factor: f64,
+        content: Content,
         initial_machine: Machine,
-    ) -> Result<Game, rendology::pipeline::CreationError> {
+    ) -> Result<Game, render::Error> {
         info!("Creating resources");
 
-        let editor = Editor::new(&config.editor, initial_machine);
+        let mut editor = Editor::new(&config.editor, initial_machine);
+        editor.set_blueprints(content.blueprints());
+        editor.set_blip_style(config.blip_style);
+
+        // Render targets are sized in physical pixels.
+        let target_size = config.view.window_size.to_physical(hidpi_factor).into();
+
         let mut update = UpdateRunner::spawn(Update::new_editor(config, editor));
-        let draw = Draw::create(facade, config)?;
+        let draw = Draw::create(facade, config, target_size, &mesh_paths(&content))?;
 
-        // TODO: Account for DPI in initialization
-        let target_size = config.view.window_size.into();
+        // Show the components that had to be disabled in the config UI.
+        let config = &Config {
+            render_pipeline: draw.pipeline_config().clone(),
+            ..config.clone()
+        };
 
         // Kick off the update loop, so that we get our first `update::Output`
         // to draw.
         update.send_input(update::InputStage::default().into_input(
-            Duration::from_secs(0),
+            Frame::idle(config.clock.step),
             target_size,
-            InputState::empty(1.0),
+            InputState::empty(hidpi_factor),
             None,
         ));
 
         let play = Play::new(&config.play);
+        let progress = Progress::load(&config.progress.path);
+        let share_client = if config.share.enabled {
+            Some(share::Client::new(&config.share))
+        } else {
+            None
+        };
 
         Ok(Game {
             config: config.clone(),
             update,
             draw,
+            clock: Clock::new(&config.clock),
             target_size,
             last_output: None,
             next_input_stage: update::InputStage::default(),
+            editor_ui_state: Default::default(),
+            state: State::Edit,
             play,
-            play_status: None,
+            retry_exec: false,
+            progress,
+            achievement_definitions: achievements::load_definitions(&config.achievements),
+            toasts: Vec::new(),
+            share_client,
+            shared_solutions: None,
+            encyclopedia: Encyclopedia::default(),
+            show_encyclopedia: false,
+            content,
+            show_content: false,
+            workshop: Workshop::load(&config.workshop),
+            workshop_query: imgui::ImString::with_capacity(64),
+            locale: Locale::load(&config.locale),
+            languages: locale::languages(&config.locale),
             debug_frame_time: stats::Variable::new(Duration::from_secs(1)),
             show_config_ui: false,
             show_debug_ui: false,
             recreate_render_pipeline: false,
+            view_changed: false,
+            monitor_names: Vec::new(),
+            take_screenshot: false,
+            quit_requested: false,
+            render_error: None,
+            show_render_error: false,
+            drop_error: None,
+            new_machine_setup: NewMachineSetup::new(&config.editor.sandbox_size),
+            race_setup: RaceSetup::default(),
+            race: None,
         })
     }
 
-    pub fn update(&mut self, dt: Duration, input_state: &InputState) {
-        self.debug_frame_time.record(dt.as_secs_f32());
+    /// Advances the game by the time that the last frame took. The game
+    /// state is advanced only in whole steps of the clock, see `clock`.
+    pub fn update(&mut self, frame_time: Duration, input_state: &InputState) {
+        self.debug_frame_time.record(frame_time.as_secs_f32());
+
+        let frame = self.clock.advance(frame_time);
+        let dt = frame.frame_time;
+
+        self.progress.stats.playtime_secs += dt.as_secs_f64();
+
+        self.poll_share_client();
+        self.update_race(dt);
+        self.update_toasts(dt);
 
         {
             profile!("recv");
@@ -92,28 +249,41 @@ impl Game {
             // so we can wait here until we receive the output.
             let output = self.update.recv_output();
 
-            // If execution has ended (due to the level being failed or
-            // completed), update the play status.
-            if output.next_level_status != Some(LevelStatus::Running) {
-                self.play_status = match self.play_status.clone() {
-                    Some(play::Status::Playing { time, .. }) => {
-                        Some(play::Status::Finished { time })
-                    }
-                    x => x,
+            // Pause right away when running until a condition, so that no
+            // more ticks are run than needed to reach it.
+            if let Some(tick) = output.run_until_reached {
+                self.play.on_run_until_reached(tick);
+            }
+
+            let state = std::mem::replace(&mut self.state, State::Edit);
+            self.state = self.update_state_steps(&frame, state, &output);
+
+            if let State::Exec {
+                play_status:
+                    play::Status::Playing {
+                        num_ticks_since_last_update,
+                        ..
+                    },
+            } = self.state
+            {
+                if num_ticks_since_last_update > 0 {
+                    self.progress.stats.ticks_simulated += num_ticks_since_last_update as u64;
+                    self.on_achievement_event(&achievements::Event::TicksRun);
                 }
             }
 
-            self.last_output = Some(output);
-        }
+            if output.num_blocks_placed > 0 {
+                self.progress.stats.blocks_placed += output.num_blocks_placed as u64;
+            }
 
-        // Note that play status may be set to `Finished` above in this
-        // function, so order is important here.
-        let old_play_status = self.play_status.clone();
-        self.play_status = self.play.update_status(dt, self.play_status.as_ref());
+            self.play
+                .set_blip_dist_to_output(output.blip_dist_to_output);
 
-        // Did we just stop execution?
-        if old_play_status.is_some() && self.play_status.is_none() {
-            self.draw.clean_up_after_exec();
+            if self.play.take_restarted() {
+                self.next_input_stage.restart_exec = true;
+            }
+
+            self.last_output = Some(output);
         }
 
         {
@@ -121,23 +291,200 @@ impl Game {
 
             // Submit the next input for the update thread. Updating can then run
             // at the same time as drawing the previous output.
+            self.next_input_stage.sub_tick_phases = self.play.sub_tick_phases();
+            self.next_input_stage.glass_pipes = self.play.glass_pipes();
+            self.next_input_stage.spawn_brush = self.play.spawn_brush();
+            self.next_input_stage.run_until = self.play.run_until();
+
             let next_input_stage =
                 std::mem::replace(&mut self.next_input_stage, Default::default());
             let next_input = next_input_stage.into_input(
-                dt,
+                frame,
                 self.target_size,
                 input_state.clone(),
-                self.play_status.clone(),
+                self.state.play_status(),
             );
 
             self.update.send_input(next_input);
         }
     }
 
-    pub fn create_resources<F: glium::backend::Facade>(
+    /// Advances the state by each step of the frame, returning the next one.
+    /// The ticks of all the steps are combined into one status, so that the
+    /// update thread runs them together.
+    fn update_state_steps(
         &mut self,
-        facade: &F,
-    ) -> Result<(), rendology::pipeline::CreationError> {
+        frame: &Frame,
+        state: State,
+        output: &update::Output,
+    ) -> State {
+        // Input still needs to be handled in frames without any steps.
+        let (num_updates, step) = if frame.num_steps > 0 {
+            (frame.num_steps, frame.step)
+        } else {
+            (1, Duration::from_secs(0))
+        };
+
+        let mut state = state;
+        let mut prev_play_status: Option<play::Status> = None;
+
+        for _ in 0..num_updates {
+            state = self.update_state(step, state, output);
+
+            prev_play_status = match &mut state {
+                State::Exec { play_status } => {
+                    if let Some(prev_play_status) = prev_play_status.as_ref() {
+                        *play_status = play_status.clone().merge(prev_play_status);
+                    }
+
+                    Some(play_status.clone())
+                }
+                _ => None,
+            };
+        }
+
+        state
+    }
+
+    /// Advances the state, returning the next one.
+    fn update_state(&mut self, dt: Duration, state: State, output: &update::Output) -> State {
+        match state {
+            State::Edit => {
+                if self.retry_exec || output.start_isolated_exec {
+                    self.retry_exec = false;
+                    self.play.play_pause();
+                }
+
+                match self.play.update_status(dt, None) {
+                    Some(play_status) => State::Exec { play_status },
+                    None => State::Edit,
+                }
+            }
+            State::Exec {
+                play_status: play::Status::Playing { ref time, .. },
+            } if output.next_level_status != Some(LevelStatus::Running) => {
+                // Execution has ended, due to the level being failed or
+                // completed.
+                let time = time.clone();
+                let results = self.record_results(output);
+
+                self.update_state(dt, State::Results { time, results }, output)
+            }
+            State::Exec { play_status } => match self.play.update_status(dt, Some(&play_status)) {
+                Some(play_status) => State::Exec { play_status },
+                None => self.stop_exec(),
+            },
+            State::Results { time, results } => {
+                let play_status = play::Status::Finished { time };
+
+                match self.play.update_status(dt, Some(&play_status)) {
+                    Some(play::Status::Finished { time }) => State::Results { time, results },
+                    Some(play_status) => State::Exec { play_status },
+                    None => self.stop_exec(),
+                }
+            }
+            State::Menu { .. } | State::Photo { .. } => {
+                // Execution can not be started from the menu, and the world
+                // is frozen in photo mode.
+                state
+            }
+        }
+    }
+
+    fn stop_exec(&mut self) -> State {
+        self.draw.clean_up_after_exec();
+
+        // Keep the lifetime stats, e.g. the number of ticks simulated.
+        self.progress.save(&self.config.progress.path);
+
+        State::Edit
+    }
+
+    fn record_results(&mut self, output: &update::Output) -> Option<Results> {
+        let level = output.level_progress.as_ref().map(|(level, _)| level);
+
+        if let (Some(score), Some(machine), Some(level)) =
+            (output.score, output.completed_machine.as_ref(), level)
+        {
+            info!("Completed level with score: {:?}", score);
+
+            let prev_best = self.progress.best_score(level).copied();
+
+            self.progress.record_score(level, &score);
+            self.progress.save(&self.config.progress.path);
+
+            let history = self
+                .progress
+                .level_stats(level)
+                .map_or_else(Vec::new, |stats| stats.history.clone());
+
+            let stats = machine.clone().into_machine().stats();
+            self.on_achievement_event(&achievements::Event::LevelCompleted {
+                level,
+                score: &score,
+                stats: &stats,
+            });
+
+            Some(Results {
+                level: level.clone(),
+                machine: machine.clone(),
+                score,
+                prev_best,
+                history,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn poll_share_client(&mut self) {
+        let events = self
+            .share_client
+            .as_ref()
+            .map_or_else(Vec::new, share::Client::poll);
+
+        for event in events {
+            match event {
+                share::Event::Uploaded(Ok(())) => {
+                    info!("Shared solution successfully");
+                }
+                share::Event::Uploaded(Err(err)) => {
+                    warn!("Failed to share solution: {}", err);
+                }
+                share::Event::Downloaded(level_id, Ok(solutions)) => {
+                    info!(
+                        "Downloaded {} shared solutions for level {}",
+                        solutions.len(),
+                        level_id
+                    );
+
+                    // The server is not trusted to send machines that we can
+                    // load.
+                    let solutions = solutions
+                        .into_iter()
+                        .filter(|solution| match solution.machine.check() {
+                            Ok(()) => true,
+                            Err(err) => {
+                                warn!("Skipping shared solution by {}: {}", solution.author, err);
+                                false
+                            }
+                        })
+                        .collect();
+                    self.shared_solutions = Some(solutions);
+                }
+                share::Event::Downloaded(level_id, Err(err)) => {
+                    warn!(
+                        "Failed to download shared solutions for level {}: {}",
+                        level_id, err
+                    );
+                }
+            }
+        }
+    }
+
+    /// Recreates the render pipeline if its config has changed. If this
+    /// fails, the previous pipeline is kept and the error is shown.
+    pub fn create_resources<F: glium::backend::Facade>(&mut self, facade: &F) {
         if self.recreate_render_pipeline {
             info!(
                 "Recreating render pipeline with config: {:?}",
@@ -146,40 +493,140 @@ impl Game {
 
             self.recreate_render_pipeline = false;
 
-            self.draw = Draw::create(facade, &self.config)?;
+            match Draw::create(
+                facade,
+                &self.config,
+                self.target_size,
+                &mesh_paths(&self.content),
+            ) {
+                Ok(draw) => self.draw = draw,
+                Err(err) => self.on_render_error(err),
+            }
+
+            self.config.render_pipeline = self.draw.pipeline_config().clone();
         }
+    }
 
-        Ok(())
+    /// Creates resources that need to be registered with imgui, once they are
+    /// needed.
+    pub fn create_ui_textures<F: glium::backend::Facade>(
+        &mut self,
+        facade: &F,
+        textures: &mut imgui::Textures<Rc<glium::texture::Texture2d>>,
+    ) {
+        if self.show_encyclopedia && !self.encyclopedia.has_previews() {
+            self.encyclopedia
+                .create_previews(facade, &self.config, textures);
+        }
     }
 
     pub fn draw<F: glium::backend::Facade, S: glium::Surface>(
         &mut self,
         facade: &F,
         target: &mut S,
-    ) -> Result<(), rendology::DrawError> {
+    ) {
         self.target_size = target.get_dimensions();
 
-        if let Some(output) = self.last_output.take() {
+        if let Some(mut output) = self.last_output.take() {
+            let (is_menu, is_photo) = (self.state.is_menu(), self.state.is_photo());
+            let pipeline_config = &self.config.render_pipeline;
+
+            let show_vignette = (is_menu && pipeline_config.vignette.menu)
+                || (is_photo && pipeline_config.vignette.photo_mode);
+            if show_vignette {
+                render::vignette::render(
+                    &pipeline_config.vignette,
+                    &output.render_context.rendology.camera.viewport_size,
+                    &mut output.render_stage,
+                );
+            }
+
+            output.render_context.depth_of_field = pipeline_config
+                .depth_of_field
+                .as_ref()
+                .map_or(false, |config| {
+                    (is_menu && config.menu) || (is_photo && config.photo_mode)
+                });
+
             let input = draw::Input {
                 stage: &output.render_stage,
                 context: output.render_context.clone(),
             };
-            self.draw.draw(facade, &input, target)?;
+
+            if self.take_screenshot {
+                self.take_screenshot = false;
+                self.save_screenshot(facade, &input);
+            }
+
+            if let Err(err) = self.draw.draw(facade, &input, target) {
+                self.on_render_error(err);
+            }
         }
+    }
+
+    fn on_render_error(&mut self, err: render::Error) {
+        let message = err.to_string();
+
+        // Drawing can fail in every frame, so log each error only once.
+        if self.render_error.as_ref() != Some(&message) {
+            warn!("{}", message);
+            self.render_error = Some(message);
+            self.show_render_error = true;
+        }
+    }
+
+    fn save_screenshot<F: glium::backend::Facade>(&mut self, facade: &F, input: &draw::Input) {
+        let scale = self.config.photo_mode.render_scale.max(1);
+        let size = (self.target_size.0 * scale, self.target_size.1 * scale);
 
-        Ok(())
+        match self.draw.draw_to_image(facade, input, size) {
+            Ok(image) => photo_mode::save_screenshot(&self.config.photo_mode, &image),
+            Err(err) => warn!("Failed to render screenshot: {}", err),
+        }
     }
 
-    pub fn on_event(&mut self, input_state: &InputState, event: &glutin::WindowEvent) {
+    /// Handles a window event that has not been captured by the UI. `route`
+    /// tells which of the game's layers should react to the event.
+    pub fn on_event(
+        &mut self,
+        input_state: &InputState,
+        event: &glutin::WindowEvent,
+        route: Route,
+    ) {
         self.next_input_stage
             .window_events
-            .push((input_state.clone(), event.clone()));
+            .push((input_state.clone(), event.clone(), route));
 
-        self.play.on_event(event);
+        // Execution can not be controlled in the menu, and in photo mode, so
+        // that it stays paused.
+        if route.tool && !self.state.is_menu() && !self.state.is_photo() {
+            let is_executing = self.state.play_status().is_some();
+            self.play.on_event(event, is_executing);
+        }
 
         // Some shortcuts for debugging
         if let glutin::WindowEvent::KeyboardInput { input, .. } = event {
             if input.state == glutin::ElementState::Pressed
+                && input.virtual_keycode == Some(glutin::VirtualKeyCode::F10)
+                && self.state.is_edit()
+            {
+                self.open_menu();
+            } else if input.state == glutin::ElementState::Pressed
+                && !self.state.is_menu()
+                && input.virtual_keycode == Some(self.config.photo_mode.toggle_key)
+            {
+                self.toggle_photo_mode();
+            } else if input.state == glutin::ElementState::Pressed
+                && self.state.is_photo()
+                && input.virtual_keycode == Some(self.config.photo_mode.screenshot_key)
+            {
+                self.take_screenshot = true;
+            } else if input.state == glutin::ElementState::Pressed
+                && input.modifiers.alt
+                && input.virtual_keycode == Some(glutin::VirtualKeyCode::Return)
+            {
+                self.set_window_mode(self.config.view.window_mode.toggled());
+            } else if input.state == glutin::ElementState::Pressed
                 && input.virtual_keycode == Some(glutin::VirtualKeyCode::F5)
             {
                 self.show_config_ui = !self.show_config_ui;
@@ -187,14 +634,85 @@ impl Game {
                 && input.virtual_keycode == Some(glutin::VirtualKeyCode::F6)
             {
                 self.show_debug_ui = !self.show_debug_ui;
+            } else if input.state == glutin::ElementState::Pressed
+                && input.virtual_keycode == Some(glutin::VirtualKeyCode::F7)
+            {
+                self.show_encyclopedia = !self.show_encyclopedia;
+            } else if input.state == glutin::ElementState::Pressed
+                && input.virtual_keycode == Some(glutin::VirtualKeyCode::F8)
+            {
+                self.show_content = !self.show_content;
             }
         }
     }
 
+    fn toggle_photo_mode(&mut self) {
+        let state = std::mem::replace(&mut self.state, State::Edit);
+
+        self.state = if state.is_photo() {
+            info!("Leaving photo mode");
+            state.leave_photo()
+        } else {
+            info!("Entering photo mode");
+            state.enter_photo()
+        };
+
+        self.next_input_stage.photo_mode = Some(self.state.is_photo());
+    }
+
+    /// Saves the player's progress, including the playtime, before the game
+    /// exits.
+    pub fn on_quit(&self) {
+        self.progress.save(&self.config.progress.path);
+    }
+
+    pub fn is_quit_requested(&self) -> bool {
+        self.quit_requested
+    }
+
+    pub fn set_monitor_names(&mut self, monitor_names: Vec<String>) {
+        self.monitor_names = monitor_names;
+    }
+
+    pub fn set_window_mode(&mut self, window_mode: WindowMode) {
+        self.config.view.window_mode = window_mode;
+        self.view_changed = true;
+    }
+
+    /// Returns the view config if the window mode has been changed since the
+    /// last call.
+    pub fn take_view_change(&mut self) -> Option<ViewConfig> {
+        if self.view_changed {
+            self.view_changed = false;
+            Some(self.config.view.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Keeps track of the window's size. The render pipeline resizes its
+    /// targets by itself, once it is drawn to the resized window.
     pub fn on_window_resize<F: glium::backend::Facade>(
         &mut self,
         _facade: &F,
-        _new_window_size: glutin::dpi::LogicalSize,
+        new_window_size: glutin::dpi::LogicalSize,
+        hidpi_factor: f64,
     ) {
+        // Remember the size chosen by the player, so that it is restored when
+        // switching back from fullscreen.
+        if self.config.view.window_mode == WindowMode::Windowed {
+            self.config.view.window_size = new_window_size;
+        }
+
+        self.target_size = new_window_size.to_physical(hidpi_factor).into();
     }
 }
+
+/// Returns the meshes that are shipped with the game, followed by the meshes
+/// of the enabled content packs.
+fn mesh_paths(content: &Content) -> Vec<(String, PathBuf)> {
+    let mut meshes = render::mesh::list_dir(Path::new(render::mesh::BUILTIN_DIR));
+    meshes.extend(content.meshes());
+
+    meshes
+}
@@ -0,0 +1,216 @@
+use std::time::Duration;
+
+use imgui::{im_str, ImString};
+use log::{info, warn};
+
+use crate::edit::solutions;
+use crate::exec::race::{Race, RaceResult};
+use crate::exec::LevelStatus;
+use crate::game::Game;
+use crate::machine::Level;
+
+/// Number of machines that take part in a hot-seat race.
+pub const NUM_RACERS: usize = 2;
+
+/// A race that is being shown, advanced at the configured speed.
+pub struct RaceRun {
+    pub race: Race,
+
+    /// Time that has passed since the last tick.
+    pub tick_time: Duration,
+}
+
+/// The solutions that have been chosen for a race that is being set up.
+#[derive(Debug, Clone, Default)]
+pub struct RaceSetup {
+    pub picks: [Option<String>; NUM_RACERS],
+    pub error: Option<String>,
+}
+
+impl Game {
+    /// Starts a race between the picked solutions of each player. Returns
+    /// false if the race could not be started.
+    pub(super) fn start_race(&mut self, level: &Level) -> bool {
+        let solutions_dir = &self.config.editor.solutions_dir;
+        let mut machines = Vec::new();
+
+        for name in self.race_setup.picks.iter() {
+            let name = match name {
+                Some(name) => name,
+                None => {
+                    self.race_setup.error = Some("Pick a solution for each player.".to_string());
+                    return false;
+                }
+            };
+
+            let path = solutions::path(solutions_dir, Some(level), name);
+            match solutions::load(&path) {
+                Some(machine) => machines.push((name.clone(), machine)),
+                None => {
+                    self.race_setup.error = Some(format!("Could not load solution {:?}.", name));
+                    return false;
+                }
+            }
+        }
+
+        info!("Starting race on level {}", level.id());
+
+        match Race::new(
+            level.clone(),
+            machines,
+            rand::random(),
+            self.config.race.max_ticks,
+        ) {
+            Ok(race) => {
+                self.race = Some(RaceRun {
+                    race,
+                    tick_time: Duration::from_secs(0),
+                });
+                self.race_setup = RaceSetup::default();
+                true
+            }
+            Err(err) => {
+                warn!("Could not start race: {}", err);
+                self.race_setup.error = Some(err);
+                false
+            }
+        }
+    }
+
+    pub(super) fn update_race(&mut self, dt: Duration) {
+        let run = match self.race.as_mut() {
+            Some(run) => run,
+            None => return,
+        };
+
+        let tick_period = Duration::from_secs_f64(1.0 / self.config.race.ticks_per_sec.max(0.1));
+
+        run.tick_time += dt;
+        while run.tick_time >= tick_period {
+            run.tick_time -= tick_period;
+            run.race.update();
+        }
+    }
+
+    pub(super) fn ui_race_setup(&mut self, level: &Level, ui: &imgui::Ui) {
+        let names = solutions::list(&self.config.editor.solutions_dir, Some(level));
+
+        if names.is_empty() {
+            ui.text(im_str!("No solutions have been saved for this level yet."));
+            return;
+        }
+
+        ui.columns(NUM_RACERS as i32, im_str!("race_setup"), true);
+
+        for player in 0..NUM_RACERS {
+            ui.text(&ImString::new(format!("Player {}", player + 1)));
+
+            for name in names.iter() {
+                let label = format!("{}##race{}", name, player);
+                let selected = self.race_setup.picks[player].as_ref() == Some(name);
+
+                if imgui::Selectable::new(&ImString::new(label))
+                    .selected(selected)
+                    .build(ui)
+                {
+                    self.race_setup.picks[player] = Some(name.clone());
+                }
+            }
+
+            ui.next_column();
+        }
+
+        ui.columns(1, im_str!("race_setup_end"), false);
+
+        if let Some(error) = self.race_setup.error.as_ref() {
+            ui.text_colored([1.0, 0.3, 0.3, 1.0], &ImString::new(error.clone()));
+        }
+    }
+
+    /// Shows the racers next to each other, so that their progress can be
+    /// compared while the race is running.
+    pub(super) fn ui_race(&mut self, ui: &imgui::Ui) {
+        let run = match self.race.as_ref() {
+            Some(run) => run,
+            None => return,
+        };
+
+        let mut close = false;
+        let result = run.race.result();
+
+        imgui::Window::new(im_str!("Race"))
+            .position(
+                [ui.io().display_size[0] / 2.0, 10.0],
+                imgui::Condition::Always,
+            )
+            .position_pivot([0.5, 0.0])
+            .always_auto_resize(true)
+            .collapsible(false)
+            .build(&ui, || {
+                ui.text(&ImString::new(format!(
+                    "{} - tick {}",
+                    self.locale.level_description(&run.race.level),
+                    run.race.cur_tick()
+                )));
+                ui.separator();
+
+                ui.columns(run.race.racers.len() as i32, im_str!("race"), true);
+
+                for (index, racer) in run.race.racers.iter().enumerate() {
+                    ui.text(&ImString::new(format!(
+                        "Player {}: {}",
+                        index + 1,
+                        racer.name
+                    )));
+                    ui.text(&ImString::new(format!(
+                        "Outputs: {:.0}%",
+                        racer.progress() * 100.0
+                    )));
+
+                    let status = match racer.report.as_ref() {
+                        None if result.is_some() => "Did not finish".to_string(),
+                        None => "Running".to_string(),
+                        Some(report) => match report.status {
+                            LevelStatus::Completed => {
+                                format!("Completed in {} ticks", report.ticks)
+                            }
+                            LevelStatus::Failed => format!(
+                                "Failed: {}",
+                                report.failure.as_ref().map_or("", String::as_str)
+                            ),
+                            LevelStatus::Running => "Running".to_string(),
+                        },
+                    };
+                    ui.text(&ImString::new(status));
+
+                    ui.next_column();
+                }
+
+                ui.columns(1, im_str!("race_end"), false);
+
+                if let Some(result) = result {
+                    ui.separator();
+
+                    let text = match result {
+                        RaceResult::Winner(index) => format!(
+                            "Player {} wins with {}!",
+                            index + 1,
+                            run.race.racers[index].name
+                        ),
+                        RaceResult::Draw => "It's a draw!".to_string(),
+                        RaceResult::NoWinner => "Nobody completed the level.".to_string(),
+                    };
+                    ui.text_colored([0.3, 1.0, 0.3, 1.0], &ImString::new(text));
+                }
+
+                let close_label = if result.is_some() { "Close" } else { "Abort" };
+                if ui.button(&ImString::new(close_label), [120.0, 20.0]) {
+                    close = true;
+                }
+            });
+
+        if close {
+            self.race = None;
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::exec::play::{self, TickTime};
+
+use super::menu;
+use super::Results;
+
+/// The top-level state of the game, which decides how updates, events and
+/// the UI are handled.
+#[derive(Debug, Clone)]
+pub enum State {
+    /// The main menu is shown in front of the editor's machine.
+    Menu { page: menu::Page },
+
+    /// The player is building a machine in the editor.
+    Edit,
+
+    /// The machine is being executed.
+    Exec { play_status: play::Status },
+
+    /// Execution has ended, and the outcome is shown. `results` is only set
+    /// if the machine has completed its level.
+    Results {
+        time: TickTime,
+        results: Option<Results>,
+    },
+
+    /// The world is frozen, so that the player can take photos. The previous
+    /// state is restored afterwards, resuming execution if it was running.
+    Photo { previous: Box<State>, resume: bool },
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State::Edit
+    }
+}
+
+impl State {
+    /// Returns the play status that is used by the update thread to drive
+    /// execution. Execution is running if and only if this is `Some`.
+    pub fn play_status(&self) -> Option<play::Status> {
+        match self {
+            State::Menu { .. } | State::Edit => None,
+            State::Exec { play_status } => Some(play_status.clone()),
+            State::Results { time, .. } => Some(play::Status::Finished { time: time.clone() }),
+            State::Photo { previous, .. } => previous.play_status(),
+        }
+    }
+
+    pub fn is_edit(&self) -> bool {
+        match self {
+            State::Edit => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_menu(&self) -> bool {
+        match self {
+            State::Menu { .. } => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_photo(&self) -> bool {
+        match self {
+            State::Photo { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Enters photo mode, pausing execution if it is running.
+    pub fn enter_photo(self) -> State {
+        match self {
+            State::Exec {
+                play_status: play::Status::Playing { time, .. },
+            } => State::Photo {
+                previous: Box::new(State::Exec {
+                    play_status: play::Status::Paused { time },
+                }),
+                resume: true,
+            },
+            state @ State::Photo { .. } => state,
+            state => State::Photo {
+                previous: Box::new(state),
+                resume: false,
+            },
+        }
+    }
+
+    /// Leaves photo mode, returning to the state that was active before.
+    pub fn leave_photo(self) -> State {
+        match self {
+            State::Photo { previous, resume } => match *previous {
+                State::Exec {
+                    play_status: play::Status::Paused { time },
+                } if resume => State::Exec {
+                    play_status: play::Status::Playing {
+                        num_ticks_since_last_update: 0,
+                        prev_time: None,
+                        time,
+                    },
+                },
+                previous => previous,
+            },
+            state => state,
+        }
+    }
+}
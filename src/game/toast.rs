@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use imgui::{im_str, ImString};
+
+use crate::achievements::Event;
+use crate::game::Game;
+
+/// Notification that is shown in the corner of the screen for a while.
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub title: String,
+    pub text: String,
+    pub remaining: Duration,
+}
+
+impl Game {
+    /// Checks if the event unlocks any achievements, showing a notification
+    /// for each of them. Progress is saved only if something has been
+    /// unlocked.
+    pub(super) fn on_achievement_event(&mut self, event: &Event) {
+        let unlocked = self.progress.achievements.on_event(
+            &self.achievement_definitions,
+            &self.progress.stats,
+            event,
+        );
+
+        if unlocked.is_empty() {
+            return;
+        }
+
+        let toast_duration = Duration::from_secs_f32(self.config.achievements.toast_secs.max(0.0));
+
+        for definition in unlocked {
+            self.toasts.push(Toast {
+                title: format!("Achievement unlocked: {}", definition.title),
+                text: definition.description.clone(),
+                remaining: toast_duration,
+            });
+        }
+
+        self.progress.save(&self.config.progress.path);
+    }
+
+    pub(super) fn update_toasts(&mut self, dt: Duration) {
+        for toast in self.toasts.iter_mut() {
+            toast.remaining = toast.remaining.checked_sub(dt).unwrap_or_default();
+        }
+
+        self.toasts
+            .retain(|toast| toast.remaining > Duration::from_secs(0));
+    }
+
+    pub(super) fn ui_toasts(&self, ui: &imgui::Ui) {
+        if self.toasts.is_empty() {
+            return;
+        }
+
+        let display_size = ui.io().display_size;
+
+        imgui::Window::new(im_str!("Notifications"))
+            .position(
+                [display_size[0] - 10.0, display_size[1] - 10.0],
+                imgui::Condition::Always,
+            )
+            .position_pivot([1.0, 1.0])
+            .title_bar(false)
+            .movable(false)
+            .resizable(false)
+            .always_auto_resize(true)
+            .bg_alpha(0.8)
+            .build(&ui, || {
+                for (index, toast) in self.toasts.iter().enumerate() {
+                    if index > 0 {
+                        ui.separator();
+                    }
+
+                    ui.text_colored([1.0, 0.85, 0.2, 1.0], &ImString::new(toast.title.clone()));
+                    ui.text(&ImString::new(toast.text.clone()));
+                }
+            });
+    }
+}
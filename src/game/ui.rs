@@ -5,32 +5,89 @@ use imgui::{im_str, ImString};
 use rendology::basic_obj::BasicObj;
 use rendology::fxaa;
 
+use crate::config::WindowMode;
 use crate::edit::editor;
-use crate::exec::{LevelProgress, LevelStatus};
-use crate::game::Game;
-use crate::machine::{level, Level};
-use crate::render;
+use crate::exec::view::{BlipInfo, FollowedBlip, PhaseStatus};
+use crate::exec::{LevelProgress, LevelStatus, Outcome, Score};
+use crate::game::{encyclopedia, Game, Results, State};
+use crate::locale::Locale;
+use crate::machine::{level, Level, Machine};
+use crate::net::share;
+use crate::render::blip_style::{BlipShape, Palette};
+use crate::tutorial;
 
 impl Game {
     pub fn ui(&mut self, ui: &imgui::Ui) {
-        let editor_ui_input = self
-            .last_output
-            .as_ref()
-            .and_then(|o| o.editor_ui_input.as_ref());
-        if let Some(editor_ui_input) = editor_ui_input {
+        self.ui_render_error(ui);
+        self.ui_drop_error(ui);
+        self.ui_toasts(ui);
+
+        match self.state {
+            State::Menu { page } => {
+                self.ui_menu(page, ui);
+
+                if self.show_config_ui {
+                    self.ui_config(ui);
+                }
+
+                return;
+            }
+            State::Photo { .. } => {
+                // Nothing should obstruct the view when taking photos.
+                return;
+            }
+            _ => (),
+        }
+
+        let editor_ui_input = self.last_output.as_ref().and_then(|o| {
+            o.editor_ui_input
+                .as_ref()
+                .map(|input| (input, &o.render_context.rendology.camera))
+        });
+        if let Some((editor_ui_input, camera)) = editor_ui_input {
             editor::ui::run(
                 editor_ui_input,
+                camera,
+                &self.locale,
                 ui,
+                &mut self.editor_ui_state,
                 &mut self.next_input_stage.editor_ui_output,
             );
         }
 
+        // Windows are positioned in imgui's coordinates, which are scaled down
+        // from physical pixels on high-DPI displays.
+        let display_size = ui.io().display_size;
         self.play.ui(
-            na::Vector2::new(self.target_size.0 as f32, self.target_size.1 as f32),
-            self.play_status.as_ref(),
+            na::Vector2::new(display_size[0], display_size[1]),
+            self.state.play_status().as_ref(),
             ui,
         );
 
+        let followed_blip = self
+            .last_output
+            .as_ref()
+            .and_then(|o| o.followed_blip.clone());
+        if let Some(followed_blip) = followed_blip {
+            self.ui_followed_blip(&followed_blip, ui);
+        }
+
+        let phase_status = self
+            .last_output
+            .as_ref()
+            .and_then(|o| o.phase_status.clone());
+        if let Some(phase_status) = phase_status {
+            ui_phase_status(&phase_status, ui);
+        }
+
+        let hovered_blip = self
+            .last_output
+            .as_ref()
+            .and_then(|o| o.hovered_blip.clone());
+        if let Some(hovered_blip) = hovered_blip {
+            ui_hovered_blip(&hovered_blip, ui);
+        }
+
         if self.show_config_ui {
             self.ui_config(ui);
         }
@@ -39,6 +96,15 @@ impl Game {
             self.ui_debug(ui);
         }
 
+        if self.show_encyclopedia {
+            self.encyclopedia
+                .ui(&self.locale, ui, &mut self.show_encyclopedia);
+        }
+
+        if self.show_content {
+            self.ui_content(ui);
+        }
+
         let level_progress = self
             .last_output
             .as_ref()
@@ -46,52 +112,104 @@ impl Game {
         if let Some((level, progress)) = level_progress {
             self.ui_level_progress(&level, &progress, ui);
         }
+
+        let outcome = self
+            .last_output
+            .as_ref()
+            .and_then(|o| o.outcome.clone())
+            .filter(|_| match self.state {
+                State::Results { .. } => true,
+                _ => false,
+            });
+        if let Some(outcome) = outcome {
+            self.ui_end_of_run(&outcome, ui);
+        }
+
+        if self.shared_solutions.is_some() {
+            self.ui_shared_solutions(ui);
+        }
+
+        if self.race.is_some() {
+            self.ui_race(ui);
+        }
+
+        let tutorial_hint = self
+            .last_output
+            .as_ref()
+            .and_then(|o| o.tutorial_hint.clone());
+        if let Some(hint) = tutorial_hint {
+            self.ui_tutorial(&hint, ui);
+        }
     }
 
     fn ui_config(&mut self, ui: &imgui::Ui) {
         imgui::Window::new(im_str!("Config"))
             .horizontal_scrollbar(true)
             .position(
-                [self.target_size.0 as f32, 10.0],
+                [ui.io().display_size[0], 10.0],
                 imgui::Condition::FirstUseEver,
             )
             .position_pivot([1.0, 0.0])
             .always_auto_resize(true)
             .bg_alpha(0.8)
             .build(&ui, || {
-                let mut shadow_mapping = self.config.render_pipeline.shadow_mapping.is_some();
+                let mut shadow_mapping = self
+                    .config
+                    .render_pipeline
+                    .rendology
+                    .shadow_mapping
+                    .is_some();
                 if ui.checkbox(im_str!("Shadow mapping"), &mut shadow_mapping) {
-                    self.config.render_pipeline.shadow_mapping = if shadow_mapping {
+                    self.config.render_pipeline.rendology.shadow_mapping = if shadow_mapping {
                         Some(Default::default())
                     } else {
                         None
                     };
                 }
 
-                let mut deferred_shading = self.config.render_pipeline.deferred_shading.is_some();
+                let mut deferred_shading = self
+                    .config
+                    .render_pipeline
+                    .rendology
+                    .deferred_shading
+                    .is_some();
                 if ui.checkbox(im_str!("Deferred shading"), &mut deferred_shading) {
-                    self.config.render_pipeline.deferred_shading = if deferred_shading {
+                    self.config.render_pipeline.rendology.deferred_shading = if deferred_shading {
                         Some(Default::default())
                     } else {
                         None
                     };
                 }
 
-                let mut glow = self.config.render_pipeline.glow.is_some();
+                let mut glow = self.config.render_pipeline.rendology.glow.is_some();
                 if ui.checkbox(im_str!("Glow"), &mut glow) {
-                    self.config.render_pipeline.glow =
+                    self.config.render_pipeline.rendology.glow =
                         if glow { Some(Default::default()) } else { None };
                 }
 
-                let mut gamma = self.config.render_pipeline.gamma_correction.unwrap_or(1.0);
+                let mut gamma = self
+                    .config
+                    .render_pipeline
+                    .rendology
+                    .gamma_correction
+                    .unwrap_or(1.0);
 
                 imgui::Slider::new(im_str!("Gamma"), 0.3..=4.0).build(ui, &mut gamma);
 
-                self.config.render_pipeline.gamma_correction = Some(gamma);
+                self.config.render_pipeline.rendology.gamma_correction = Some(gamma);
 
-                let mut hdr = self.config.render_pipeline.hdr.is_some();
+                let mut hdr = self.config.render_pipeline.rendology.hdr.is_some();
                 if ui.checkbox(im_str!("HDR"), &mut hdr) {
-                    self.config.render_pipeline.hdr = if hdr { Some(42.0) } else { None };
+                    self.config.render_pipeline.rendology.hdr = if hdr { Some(42.0) } else { None };
+                }
+
+                let mut depth_of_field = self.config.render_pipeline.depth_of_field.is_some();
+                if ui.checkbox(im_str!("Depth of field"), &mut depth_of_field) {
+                    self.config.render_pipeline.depth_of_field = if depth_of_field {
+                        Some(Default::default())
+                    } else {
+                        None
+                    };
                 }
 
                 ui.separator();
@@ -99,6 +217,7 @@ impl Game {
                 let mut fxaa_quality = self
                     .config
                     .render_pipeline
+                    .rendology
                     .fxaa
                     .as_ref()
                     .map(|config| config.quality);
@@ -119,7 +238,7 @@ impl Game {
                     Some(fxaa::Quality::High),
                 );
 
-                self.config.render_pipeline.fxaa =
+                self.config.render_pipeline.rendology.fxaa =
                     fxaa_quality.map(|quality| fxaa::Config { quality });
 
                 ui.separator();
@@ -127,6 +246,104 @@ impl Game {
                 if ui.button(im_str!("Apply"), [80.0, 20.0]) {
                     self.recreate_render_pipeline = true;
                 }
+
+                ui.separator();
+
+                ui.checkbox(
+                    im_str!("Vignette in menu"),
+                    &mut self.config.render_pipeline.vignette.menu,
+                );
+                ui.checkbox(
+                    im_str!("Vignette in photo mode"),
+                    &mut self.config.render_pipeline.vignette.photo_mode,
+                );
+                imgui::Slider::new(im_str!("Vignette strength"), 0.0..=1.0)
+                    .build(ui, &mut self.config.render_pipeline.vignette.strength);
+
+                if let Some(depth_of_field) = self.config.render_pipeline.depth_of_field.as_mut() {
+                    ui.checkbox(im_str!("Depth of field in menu"), &mut depth_of_field.menu);
+                    ui.checkbox(
+                        im_str!("Depth of field in photo mode"),
+                        &mut depth_of_field.photo_mode,
+                    );
+                }
+
+                ui.separator();
+
+                ui.text("Window mode (Alt+Enter)");
+                let mut window_mode = self.config.view.window_mode;
+                for &mode in WindowMode::ALL {
+                    ui.radio_button(&ImString::new(mode.name()), &mut window_mode, mode);
+                }
+
+                let mut monitor = self.config.view.monitor;
+                if self.monitor_names.len() > 1 {
+                    ui.radio_button(im_str!("Primary monitor"), &mut monitor, None);
+                    for (index, name) in self.monitor_names.iter().enumerate() {
+                        let label = format!("{}##monitor{}", name, index);
+                        ui.radio_button(&ImString::new(label), &mut monitor, Some(index));
+                    }
+                }
+
+                if window_mode != self.config.view.window_mode
+                    || monitor != self.config.view.monitor
+                {
+                    self.config.view.monitor = monitor;
+                    self.set_window_mode(window_mode);
+                }
+
+                ui.separator();
+
+                ui.text("Screenshot resolution (F9, F12)");
+                for &scale in &[1, 2, 4] {
+                    let label = format!("{}x##render_scale", scale);
+                    ui.radio_button(
+                        &ImString::new(label),
+                        &mut self.config.photo_mode.render_scale,
+                        scale,
+                    );
+                }
+
+                ui.separator();
+
+                let mut blip_style = self.config.blip_style;
+
+                ui.text("Blip palette");
+                for &palette in Palette::ALL {
+                    ui.radio_button(
+                        &ImString::new(palette.name()),
+                        &mut blip_style.palette,
+                        palette,
+                    );
+                }
+
+                ui.checkbox(im_str!("Blip shapes"), &mut blip_style.shape_coding);
+                if blip_style.shape_coding {
+                    ui_blip_shape("A", &mut blip_style.shape_a, ui);
+                    ui_blip_shape("B", &mut blip_style.shape_b, ui);
+                }
+
+                // Unlike the render pipeline, the blip style is applied
+                // immediately.
+                if blip_style != self.config.blip_style {
+                    self.config.blip_style = blip_style;
+                    self.next_input_stage.blip_style = Some(blip_style);
+                }
+
+                ui.separator();
+
+                ui.text("Language");
+                let mut language = self.config.locale.language.clone();
+                for option in self.languages.iter() {
+                    if ui.radio_button_bool(&ImString::new(option.as_str()), *option == language) {
+                        language = option.clone();
+                    }
+                }
+
+                if language != self.config.locale.language {
+                    self.config.locale.language = language;
+                    self.locale = Locale::load(&self.config.locale);
+                }
             });
     }
 
@@ -134,7 +351,7 @@ impl Game {
         imgui::Window::new(im_str!("Debug"))
             .horizontal_scrollbar(true)
             .position(
-                [self.target_size.0 as f32, 300.0],
+                [ui.io().display_size[0], 300.0],
                 imgui::Condition::FirstUseEver,
             )
             .position_pivot([1.0, 0.0])
@@ -176,46 +393,515 @@ impl Game {
 
     fn ui_level_progress(&mut self, level: &Level, example: &LevelProgress, ui: &imgui::Ui) {
         let next_level_status = self.last_output.as_ref().and_then(|o| o.next_level_status);
+        let outcome = self.last_output.as_ref().and_then(|o| o.outcome.clone());
 
         imgui::Window::new(im_str!("Level"))
             .horizontal_scrollbar(true)
             .position(
-                [self.target_size.0 as f32 / 2.0, 10.0],
+                [ui.io().display_size[0] / 2.0, 10.0],
                 imgui::Condition::FirstUseEver,
             )
             .position_pivot([0.5, 0.0])
             .always_auto_resize(true)
             .bg_alpha(0.8)
             .build(&ui, || {
-                let goal = "Goal: ".to_string() + &level.spec.description();
-                ui.bullet_text(&ImString::new(&goal));
+                let locale = &self.locale;
+
+                let goal = locale.format(
+                    "ui.level.goal",
+                    "Goal: {goal}",
+                    &[("goal", &locale.level_description(level))],
+                );
+                ui.bullet_text(&ImString::new(goal));
 
                 let status = if let Some(status) = next_level_status {
                     match status {
-                        LevelStatus::Running => "Running",
-                        LevelStatus::Completed => "Completed!",
-                        LevelStatus::Failed => "Failed",
+                        LevelStatus::Running => locale.get("ui.level.running", "Running"),
+                        LevelStatus::Completed => locale.get("ui.level.completed", "Completed!"),
+                        LevelStatus::Failed => locale.get("ui.level.failed", "Failed"),
                     }
                 } else {
-                    "Editing"
+                    locale.get("ui.level.editing", "Editing")
                 };
 
-                ui.bullet_text(&ImString::new(&("Status: ".to_string() + status)));
+                ui.bullet_text(&ImString::new(locale.format(
+                    "ui.level.status",
+                    "Status: {status}",
+                    &[("status", status)],
+                )));
+
+                if let Some(Outcome::Failure { reason, tick }) = outcome.as_ref() {
+                    ui.bullet_text(&ImString::new(locale.format(
+                        "ui.level.failure",
+                        "Tick {tick}: {reason}",
+                        &[("tick", &tick.to_string()), ("reason", &reason.to_string())],
+                    )));
+                }
+
+                imgui::TreeNode::new(
+                    ui,
+                    &ImString::new(locale.label("ui.level.show_example", "Show example")),
+                )
+                .opened(false, imgui::Condition::FirstUseEver)
+                .build(|| {
+                    self.ui_show_example(example, ui);
+
+                    // When not executing, allow generating a new level
+                    // example to show.
+                    if next_level_status.is_none() {
+                        let label = locale.label("ui.level.generate", "Generate");
+                        if ui.button(&ImString::new(label), [80.0, 20.0]) {
+                            self.next_input_stage.generate_level_example = true;
+                        }
+                    }
+                });
+            });
+    }
+
+    fn ui_tutorial(&mut self, hint: &tutorial::Hint, ui: &imgui::Ui) {
+        imgui::Window::new(im_str!("Tutorial"))
+            .position(
+                [
+                    ui.io().display_size[0] / 2.0,
+                    ui.io().display_size[1] - 100.0,
+                ],
+                imgui::Condition::FirstUseEver,
+            )
+            .position_pivot([0.5, 1.0])
+            .always_auto_resize(true)
+            .bg_alpha(0.8)
+            .build(&ui, || {
+                let locale = &self.locale;
+
+                ui.text_disabled(&ImString::new(locale.format(
+                    "ui.tutorial.step",
+                    "Step {step} of {num_steps}",
+                    &[
+                        ("step", &(hint.step_index + 1).to_string()),
+                        ("num_steps", &hint.num_steps.to_string()),
+                    ],
+                )));
+                ui.text(&ImString::new(&hint.message));
+
+                ui.separator();
+
+                if hint.needs_acknowledge {
+                    let label = locale.label("ui.tutorial.next", "Next");
+                    if ui.button(&ImString::new(label), [80.0, 20.0]) {
+                        self.next_input_stage.acknowledge_tutorial = true;
+                    }
 
-                imgui::TreeNode::new(ui, im_str!("Show example"))
-                    .opened(false, imgui::Condition::FirstUseEver)
-                    .build(|| {
-                        self.ui_show_example(example, ui);
+                    ui.same_line(0.0);
+                }
 
-                        // When not executing, allow generating a new level
-                        // example to show.
-                        if next_level_status.is_none() {
-                            if ui.button(im_str!("Generate"), [80.0, 20.0]) {
-                                self.next_input_stage.generate_level_example = true;
+                let label = locale.label("ui.tutorial.skip", "Skip tutorial");
+                if ui.button(&ImString::new(label), [120.0, 20.0]) {
+                    self.next_input_stage.skip_tutorial = true;
+                }
+            });
+    }
+
+    fn ui_content(&mut self, ui: &imgui::Ui) {
+        let mut opened = true;
+        let mut set_enabled = None;
+        let mut move_up = None;
+        let mut move_down = None;
+        let mut load_level = None;
+
+        imgui::Window::new(im_str!("Content packs"))
+            .size([500.0, 400.0], imgui::Condition::FirstUseEver)
+            .opened(&mut opened)
+            .bg_alpha(0.9)
+            .build(&ui, || {
+                if self.content.packs().is_empty() {
+                    ui.text_wrapped(&ImString::new(format!(
+                        "No content packs found in {:?}.",
+                        self.config.content.dir
+                    )));
+                }
+
+                for (index, pack) in self.content.packs().iter().enumerate() {
+                    let id = ui.push_id(index as i32);
+
+                    let mut enabled = pack.enabled;
+                    if ui.checkbox(&ImString::new(pack.title()), &mut enabled) {
+                        set_enabled = Some((index, enabled));
+                    }
+
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Up")) {
+                        move_up = Some(index);
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Down")) {
+                        move_down = Some(index);
+                    }
+
+                    imgui::TreeNode::new(ui, im_str!("Details"))
+                        .opened(false, imgui::Condition::FirstUseEver)
+                        .build(|| {
+                            ui.text_disabled(&ImString::new(format!("Namespace: {}", pack.name)));
+                            if let Some(author) = pack.manifest.author.as_ref() {
+                                ui.text_disabled(&ImString::new(format!("Author: {}", author)));
+                            }
+                            if let Some(description) = pack.manifest.description.as_ref() {
+                                ui.text_wrapped(&ImString::new(description));
                             }
+                            ui.text(&ImString::new(format!(
+                                "{} levels, {} blueprints, {} meshes",
+                                pack.levels.len(),
+                                pack.blueprints.len(),
+                                pack.meshes.len(),
+                            )));
+
+                            for level in pack.levels.iter() {
+                                let label = format!(
+                                    "{} ({})",
+                                    self.locale.level_description(level),
+                                    level.id()
+                                );
+                                let selectable = imgui::Selectable::new(&ImString::new(label))
+                                    .disabled(!pack.enabled);
+                                if selectable.build(ui) {
+                                    load_level = Some(level.clone());
+                                }
+                            }
+                        });
+
+                    id.pop(ui);
+                }
+            });
+
+        if !opened {
+            self.show_content = false;
+        }
+
+        if let Some((index, enabled)) = set_enabled {
+            self.content.set_enabled(index, enabled);
+        }
+        if let Some(index) = move_up {
+            self.content.move_up(index);
+        }
+        if let Some(index) = move_down {
+            self.content.move_down(index);
+        }
+        if set_enabled.is_some() || move_up.is_some() || move_down.is_some() {
+            self.next_input_stage.blueprints = Some(self.content.blueprints());
+
+            // Reload meshes.
+            self.recreate_render_pipeline = true;
+        }
+        if let Some(level) = load_level {
+            self.play.stop();
+            self.next_input_stage.load_machine = Some(Machine::new_from_level(level));
+        }
+    }
+
+    /// Shows the cells that the followed blip has visited in each tick.
+    fn ui_followed_blip(&mut self, followed: &FollowedBlip, ui: &imgui::Ui) {
+        let mut track = followed.track;
+        let mut unfollow = false;
+
+        imgui::Window::new(im_str!("Followed blip"))
+            .position(
+                [ui.io().display_size[0], 0.0],
+                imgui::Condition::FirstUseEver,
+            )
+            .position_pivot([1.0, 0.0])
+            .always_auto_resize(true)
+            .bg_alpha(0.8)
+            .build(&ui, || {
+                ui.text(&ImString::new(format!(
+                    "Blip {} ({})",
+                    followed.kind,
+                    if followed.alive { "alive" } else { "removed" },
+                )));
+
+                if followed.alive {
+                    ui.checkbox(im_str!("Track with camera"), &mut track);
+                }
+                if ui.button(im_str!("Stop following"), [140.0, 25.0]) {
+                    unfollow = true;
+                }
+
+                ui.separator();
+
+                imgui::ChildWindow::new(im_str!("Path"))
+                    .size([220.0, 200.0])
+                    .build(ui, || {
+                        for (tick, pos) in followed.path.iter().rev() {
+                            ui.text(&ImString::new(format!(
+                                "Tick {}: {}, {}, {}",
+                                tick, pos.x, pos.y, pos.z
+                            )));
                         }
                     });
             });
+
+        if track != followed.track {
+            self.next_input_stage.track_followed_blip = Some(track);
+        }
+        if unfollow {
+            self.next_input_stage.unfollow_blip = true;
+        }
+    }
+
+    fn ui_render_error(&mut self, ui: &imgui::Ui) {
+        let message = match self.render_error.as_ref() {
+            Some(message) if self.show_render_error => message,
+            _ => return,
+        };
+
+        let mut dismiss = false;
+
+        imgui::Window::new(im_str!("Error"))
+            .position(
+                [ui.io().display_size[0] / 2.0, ui.io().display_size[1] / 2.0],
+                imgui::Condition::Always,
+            )
+            .position_pivot([0.5, 0.5])
+            .always_auto_resize(true)
+            .collapsible(false)
+            .build(&ui, || {
+                ui.text(im_str!("Rendering failed:"));
+                ui.text(&ImString::new(message.clone()));
+                ui.separator();
+
+                if ui.button(im_str!("OK"), [120.0, 20.0]) {
+                    dismiss = true;
+                }
+            });
+
+        if dismiss {
+            self.show_render_error = false;
+        }
+    }
+
+    fn ui_end_of_run(&mut self, outcome: &Outcome, ui: &imgui::Ui) {
+        let results = match &self.state {
+            State::Results { results, .. } => results.clone(),
+            _ => None,
+        };
+        let next_level = results
+            .as_ref()
+            .and_then(|results| level::next_level(&self.content.levels(), &results.level));
+
+        let mut retry = false;
+        let mut back_to_editor = false;
+        let mut go_to_next_level = false;
+
+        imgui::Window::new(im_str!("Results"))
+            .position(
+                [ui.io().display_size[0] / 2.0, ui.io().display_size[1] / 2.0],
+                imgui::Condition::FirstUseEver,
+            )
+            .position_pivot([0.5, 0.5])
+            .always_auto_resize(true)
+            .bg_alpha(0.8)
+            .build(&ui, || {
+                match outcome {
+                    Outcome::Success { tick } => {
+                        ui.text(&ImString::new(format!(
+                            "Level completed after {} ticks!",
+                            tick
+                        )));
+                    }
+                    Outcome::Failure { reason, tick } => {
+                        ui.text(&ImString::new(format!("Level failed at tick {}:", tick)));
+                        ui.bullet_text(&ImString::new(reason.to_string()));
+                    }
+                }
+
+                if let Some(results) = results.as_ref() {
+                    ui.separator();
+                    self.ui_results(results, ui);
+                }
+
+                ui.separator();
+
+                if ui.button(im_str!("Retry"), [120.0, 20.0]) {
+                    retry = true;
+                }
+
+                ui.same_line(0.0);
+
+                if ui.button(im_str!("Back to editor"), [120.0, 20.0]) {
+                    back_to_editor = true;
+                }
+
+                if next_level.is_some() {
+                    ui.same_line(0.0);
+
+                    if ui.button(im_str!("Next level"), [120.0, 20.0]) {
+                        go_to_next_level = true;
+                    }
+                }
+            });
+
+        if retry {
+            // Execution needs to be stopped for one update, so that it starts
+            // from scratch afterwards.
+            self.play.stop();
+            self.retry_exec = true;
+        } else if back_to_editor {
+            self.play.stop();
+        } else if go_to_next_level {
+            if let Some(next_level) = next_level {
+                self.play.stop();
+                self.next_input_stage.load_machine = Some(Machine::new_from_level(next_level));
+            }
+        }
+    }
+
+    fn ui_results(&self, results: &Results, ui: &imgui::Ui) {
+        let metrics: [(&str, fn(&Score) -> usize); 3] = [
+            ("Ticks", |score| score.num_ticks),
+            ("Blocks", |score| score.num_blocks),
+            ("Footprint", |score| score.footprint),
+        ];
+
+        for (name, metric) in metrics.iter() {
+            let value = metric(&results.score);
+            let text = match results.prev_best.as_ref().map(metric) {
+                Some(prev_best) if value < prev_best => {
+                    format!("{}: {} (new best, was {})", name, value, prev_best)
+                }
+                Some(prev_best) => format!("{}: {} (best: {})", name, value, prev_best),
+                None => format!("{}: {}", name, value),
+            };
+
+            ui.bullet_text(&ImString::new(text));
+
+            // TODO: Show community data next to the local history, once
+            // there is a way to get it.
+            let history: Vec<usize> = results.history.iter().map(metric).collect();
+            self.ui_histogram(&history, value, ui);
+        }
+
+        if let Some(share_client) = self.share_client.as_ref() {
+            ui.separator();
+
+            if ui.button(im_str!("Share"), [80.0, 20.0]) {
+                share_client.upload(share::SharedSolution {
+                    level_id: results.level.id(),
+                    author: self.config.share.author.clone(),
+                    score: Some(results.score),
+                    machine: results.machine.clone(),
+                });
+            }
+
+            ui.same_line(0.0);
+
+            if ui.button(im_str!("Browse"), [80.0, 20.0]) {
+                share_client.download(results.level.id());
+            }
+        }
+    }
+
+    fn ui_shared_solutions(&mut self, ui: &imgui::Ui) {
+        let mut opened = true;
+        let mut load_machine = None;
+
+        let current_level = self
+            .last_output
+            .as_ref()
+            .and_then(|o| o.level_progress.as_ref())
+            .map(|(level, _)| level.clone());
+
+        if let Some(solutions) = self.shared_solutions.as_ref() {
+            imgui::Window::new(im_str!("Shared solutions"))
+                .opened(&mut opened)
+                .position(
+                    [ui.io().display_size[0] / 2.0, 300.0],
+                    imgui::Condition::FirstUseEver,
+                )
+                .position_pivot([0.5, 0.0])
+                .always_auto_resize(true)
+                .bg_alpha(0.8)
+                .build(&ui, || {
+                    if solutions.is_empty() {
+                        ui.text(im_str!("No solutions have been shared yet."));
+                    }
+
+                    for (index, solution) in solutions.iter().enumerate() {
+                        let text = if let Some(score) = solution.score.as_ref() {
+                            format!(
+                                "{}: {} ticks, {} blocks, footprint {}",
+                                solution.author, score.num_ticks, score.num_blocks, score.footprint,
+                            )
+                        } else {
+                            solution.author.clone()
+                        };
+                        ui.bullet_text(&ImString::new(text));
+
+                        // Machines can only be loaded into the editor, not
+                        // while executing.
+                        if self.state.is_edit() && solution.machine.level == current_level {
+                            ui.same_line(0.0);
+
+                            let label = ImString::new(format!("Load##{}", index));
+                            if ui.small_button(&label) {
+                                load_machine = Some(solution.machine.clone().into_machine());
+                            }
+                        }
+                    }
+                });
+        }
+
+        if load_machine.is_some() {
+            self.next_input_stage.load_machine = load_machine;
+        }
+
+        if !opened {
+            self.shared_solutions = None;
+        }
+    }
+
+    /// Shows a histogram of the given values, highlighting the bar that
+    /// contains `current`.
+    fn ui_histogram(&self, values: &[usize], current: usize, ui: &imgui::Ui) {
+        let num_bars = 10;
+        let bar_width = 16.0;
+        let bar_spacing = 2.0;
+        let max_bar_height = 40.0;
+        let bar_color = [0.5, 0.5, 0.5];
+        let current_color = [1.0, 1.0, 1.0];
+
+        let min_value = values.iter().copied().min().unwrap_or(current);
+        let max_value = values.iter().copied().max().unwrap_or(current);
+        let bar_range = (max_value - min_value) / num_bars + 1;
+        let bar_index = |value: usize| ((value - min_value) / bar_range).min(num_bars - 1);
+
+        let mut counts = vec![0; num_bars];
+        for &value in values {
+            counts[bar_index(value)] += 1;
+        }
+        let max_count = counts.iter().copied().max().unwrap_or(0).max(1);
+
+        let draw_list = ui.get_window_draw_list();
+        let cursor_pos = ui.cursor_screen_pos();
+
+        for (index, &count) in counts.iter().enumerate() {
+            let height = max_bar_height * count as f32 / max_count as f32;
+            let x = cursor_pos[0] + index as f32 * (bar_width + bar_spacing);
+            let y = cursor_pos[1] + max_bar_height;
+            let color = if index == bar_index(current) {
+                current_color
+            } else {
+                bar_color
+            };
+
+            draw_list.add_rect_filled_multicolor(
+                [x, y - height],
+                [x + bar_width, y],
+                color,
+                color,
+                color,
+                color,
+            );
+        }
+
+        ui.dummy([num_bars as f32 * (bar_width + bar_spacing), max_bar_height]);
     }
 
     fn ui_show_example(&self, example: &LevelProgress, ui: &imgui::Ui) {
@@ -278,7 +964,7 @@ impl Game {
 
             match input {
                 Some(level::Input::Blip(kind)) => {
-                    let color: [f32; 3] = render::machine::blip_color(kind).into();
+                    let color: [f32; 3] = self.config.blip_style.color(kind).into();
                     let cursor_pos = ui.cursor_screen_pos();
 
                     let border_a = [cursor_pos[0] - border_margin, cursor_pos[1] - border_margin];
@@ -302,14 +988,36 @@ impl Game {
                         );
                     }
 
-                    draw_list.add_rect_filled_multicolor(
-                        cursor_pos,
-                        [cursor_pos[0] + blip_size, cursor_pos[1] + blip_size],
-                        color,
-                        color,
-                        color,
-                        color,
-                    );
+                    let min = cursor_pos;
+                    let max = [cursor_pos[0] + blip_size, cursor_pos[1] + blip_size];
+
+                    match self.config.blip_style.shape(kind) {
+                        BlipShape::Cube => {
+                            draw_list
+                                .add_rect_filled_multicolor(min, max, color, color, color, color);
+                        }
+                        BlipShape::Sphere => {
+                            let center = [
+                                cursor_pos[0] + blip_size / 2.0,
+                                cursor_pos[1] + blip_size / 2.0,
+                            ];
+                            draw_list
+                                .add_circle(center, blip_size / 2.0, color)
+                                .filled(true)
+                                .build();
+                        }
+                        BlipShape::Tetrahedron => {
+                            draw_list
+                                .add_triangle(
+                                    [min[0] + blip_size / 2.0, min[1]],
+                                    [max[0], max[1]],
+                                    [min[0], max[1]],
+                                    color,
+                                )
+                                .filled(true)
+                                .build();
+                        }
+                    }
                 }
                 None => (),
             }
@@ -318,3 +1026,76 @@ impl Game {
         }
     }
 }
+
+fn ui_blip_shape(kind_name: &str, shape: &mut BlipShape, ui: &imgui::Ui) {
+    ui.text(&ImString::new(format!("Shape of {}", kind_name)));
+
+    for &option in BlipShape::ALL {
+        let label = format!("{}##{}", option.name(), kind_name);
+        ui.radio_button(&ImString::new(label), shape, option);
+    }
+}
+
+fn ui_hovered_blip(info: &BlipInfo, ui: &imgui::Ui) {
+    ui.tooltip(|| {
+        ui.text(&ImString::new(format!("Blip {}", info.kind)));
+        ui.text(&ImString::new(format!(
+            "Position: {}, {}, {}",
+            info.pos.x, info.pos.y, info.pos.z
+        )));
+        ui.text(&ImString::new(format!(
+            "Moving: {}",
+            info.move_dir
+                .map_or_else(|| "no".to_string(), encyclopedia::dir_name)
+        )));
+
+        if let Some(age) = info.age {
+            ui.text(&ImString::new(format!("Age: {} ticks", age)));
+        }
+        if let Some(origin) = info.origin.as_ref() {
+            ui.text(&ImString::new(format!(
+                "Spawned by {} at {}, {}, {}",
+                origin.block_name, origin.pos.x, origin.pos.y, origin.pos.z
+            )));
+        }
+    });
+}
+
+/// Names the phase of the tick that is being animated, and lists what happened
+/// in each phase of the tick.
+fn ui_phase_status(status: &PhaseStatus, ui: &imgui::Ui) {
+    imgui::Window::new(im_str!("Tick phases"))
+        .position(
+            [ui.io().display_size[0] / 2.0, 10.0],
+            imgui::Condition::Always,
+        )
+        .position_pivot([0.5, 0.0])
+        .movable(false)
+        .always_auto_resize(true)
+        .title_bar(false)
+        .bg_alpha(0.8)
+        .build(&ui, || {
+            ui.set_window_font_scale(1.5);
+            ui.text(&ImString::new(format!(
+                "Tick {}: {}",
+                status.tick,
+                status.phase.name()
+            )));
+            ui.set_window_font_scale(1.0);
+
+            ui.separator();
+
+            for (phase, change) in status.changes.iter() {
+                let color = if *phase == status.phase {
+                    [1.0, 1.0, 1.0, 1.0]
+                } else {
+                    [0.6, 0.6, 0.6, 1.0]
+                };
+
+                ui.text_colored(
+                    color,
+                    &ImString::new(format!("{}: {}", phase.name(), change)),
+                );
+            }
+        });
+}
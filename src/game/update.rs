@@ -9,30 +9,83 @@ use nalgebra as na;
 use rendology::Camera;
 
 use crate::config::Config;
+use crate::content::Blueprint;
 use crate::edit::{editor, Editor};
 use crate::edit_camera_view::{EditCameraView, EditCameraViewInput};
-use crate::exec::{play, ExecView, LevelProgress, LevelStatus, TickTime};
+use crate::exec::play::{RunUntil, SpawnBrush};
+use crate::exec::view::{BlipInfo, FollowedBlip, PhaseStatus};
+use crate::exec::{play, ExecView, LevelProgress, LevelStatus, Outcome, Score, TickTime};
+use crate::input_route::Route;
 use crate::input_state::InputState;
-use crate::machine::Level;
+use crate::machine::{grid, Level, Machine, SavedMachine, TickNum};
+use crate::photo_mode::{FreeCamera, FreeCameraInput};
 use crate::render;
+use crate::render::blip_style::BlipStyle;
+use crate::tutorial::{self, Tutorial};
+
+use super::clock::Frame;
+
+/// Speed at which the camera rotates around the machine behind the main
+/// menu.
+const MENU_ROTATE_RADIANS_PER_SEC: f32 = 0.1;
+
+/// How quickly the camera catches up with a blip that it is tracking.
+const TRACK_BLIP_SPEED: f32 = 5.0;
 
 #[derive(Debug, Clone, Default)]
 pub struct InputStage {
-    pub window_events: Vec<(InputState, glutin::WindowEvent)>,
+    pub window_events: Vec<(InputState, glutin::WindowEvent, Route)>,
     pub editor_ui_output: editor::ui::Output,
     pub generate_level_example: bool,
+    pub load_machine: Option<Machine>,
+    pub acknowledge_tutorial: bool,
+    pub skip_tutorial: bool,
+
+    /// Set when the enabled content packs have changed.
+    pub blueprints: Option<Vec<Blueprint>>,
+
+    /// Set when the player has changed how blips are drawn.
+    pub blip_style: Option<BlipStyle>,
+
+    /// Set when the player enters or leaves photo mode.
+    pub photo_mode: Option<bool>,
+
+    /// Set when the main menu is opened or closed.
+    pub menu: Option<bool>,
+
+    /// Set when the player stops following a blip.
+    pub unfollow_blip: bool,
+
+    /// Set when the player toggles tracking the followed blip with the
+    /// camera.
+    pub track_followed_blip: Option<bool>,
+
+    /// Whether the phases within ticks are animated one after another.
+    pub sub_tick_phases: bool,
+
+    /// Whether pipes are drawn as glass during execution.
+    pub glass_pipes: bool,
+
+    /// How blips are spawned when right-clicking during execution.
+    pub spawn_brush: SpawnBrush,
+
+    /// Condition under which execution is to be paused, if any.
+    pub run_until: Option<RunUntil>,
+
+    /// Set when execution is restarted with the same inputs.
+    pub restart_exec: bool,
 }
 
 impl InputStage {
     pub fn into_input(
         self,
-        dt: Duration,
+        frame: Frame,
         target_size: (u32, u32),
         input_state: InputState,
         play_status: Option<play::Status>,
     ) -> Input {
         Input {
-            dt,
+            frame,
             target_size,
             input_state,
             play_status,
@@ -42,19 +95,60 @@ impl InputStage {
 }
 
 pub struct Input {
-    pub dt: Duration,
+    pub frame: Frame,
     pub target_size: (u32, u32),
     pub input_state: InputState,
     pub play_status: Option<play::Status>,
     pub stage: InputStage,
 }
 
+impl Input {
+    /// Returns the time that the last frame took. The camera and the UI
+    /// move by this, independently of the simulation's steps.
+    pub fn dt(&self) -> Duration {
+        self.frame.frame_time
+    }
+}
+
 pub struct Output {
     pub render_stage: render::Stage,
     pub render_context: render::Context,
     pub editor_ui_input: Option<editor::ui::Input>,
     pub level_progress: Option<(Level, LevelProgress)>,
     pub next_level_status: Option<LevelStatus>,
+    pub outcome: Option<Outcome>,
+    pub score: Option<Score>,
+
+    /// The machine that was executed, if it has completed the level.
+    pub completed_machine: Option<SavedMachine>,
+
+    pub tutorial_hint: Option<tutorial::Hint>,
+
+    /// The blip that the player follows while executing, if any.
+    pub followed_blip: Option<FollowedBlip>,
+
+    /// The phase of the tick that is being animated, if sub-tick phases are
+    /// shown.
+    pub phase_status: Option<PhaseStatus>,
+
+    /// The blip that the mouse points to while execution is paused.
+    pub hovered_blip: Option<BlipInfo>,
+
+    /// Set when the player has requested to test a part of the machine in
+    /// isolation, so that execution should be started.
+    pub start_isolated_exec: bool,
+
+    /// Number of blocks that have been placed in the editor since the last
+    /// output.
+    pub num_blocks_placed: usize,
+
+    /// Set to the tick at which the condition of `InputStage::run_until` has
+    /// been reached, so that execution should be paused there.
+    pub run_until_reached: Option<TickNum>,
+
+    /// Distance of the blip that is closest to an unfinished output during
+    /// execution, if any. Used for ramping the speed of execution.
+    pub blip_dist_to_output: Option<isize>,
 }
 
 enum Command {
@@ -165,6 +259,15 @@ impl Drop for UpdateRunner {
     }
 }
 
+/// A part of the machine that is executed on its own, instead of the editor's
+/// machine.
+struct Isolated {
+    machine: Machine,
+
+    /// Camera target to return to when execution stops.
+    prev_camera_target: na::Point3<f32>,
+}
+
 pub struct Update {
     config: Config,
 
@@ -173,11 +276,31 @@ pub struct Update {
     edit_camera_view: EditCameraView,
     edit_camera_view_input: EditCameraViewInput,
 
+    /// Camera that is used instead of the edit camera in photo mode.
+    free_camera: Option<FreeCamera>,
+    free_camera_input: FreeCameraInput,
+
+    /// Camera that slowly rotates around the machine behind the main menu.
+    menu_camera_view: Option<EditCameraView>,
+
     editor: Editor,
     exec_view: Option<ExecView>,
 
     /// Current input/output example to show for the level.
     level_progress: Option<LevelProgress>,
+
+    /// Tutorial for the current level, if any.
+    tutorial: Option<tutorial::State>,
+
+    /// Part of the machine that is tested in isolation, if any.
+    isolated: Option<Isolated>,
+
+    /// Time that has passed in the game, for idle animations.
+    time: Duration,
+
+    /// Set to the tick at which the condition for pausing execution has been
+    /// reached in this update.
+    run_until_reached: Option<TickNum>,
 }
 
 impl Update {
@@ -193,10 +316,8 @@ impl Update {
         let edit_camera_view = EditCameraView::new();
         let edit_camera_view_input = EditCameraViewInput::new(&config.camera);
 
-        let level_progress = editor.machine().level.as_ref().map(|level| {
-            let inputs_outputs = level.spec.gen_inputs_outputs(&mut rand::thread_rng());
-            LevelProgress::new(None, inputs_outputs)
-        });
+        let level_progress = gen_level_example(&editor);
+        let tutorial = gen_tutorial(config, &editor);
 
         Self {
             config: config.clone(),
@@ -204,23 +325,74 @@ impl Update {
             camera,
             edit_camera_view,
             edit_camera_view_input,
+            free_camera: None,
+            free_camera_input: FreeCameraInput::new(&config.photo_mode),
+            menu_camera_view: None,
             editor,
             exec_view: None,
             level_progress,
+            tutorial,
+            isolated: None,
+            time: Duration::from_secs(0),
+            run_until_reached: None,
         }
     }
 
     pub fn update(&mut self, input: Input) -> Output {
         let mut render_stage = render::Stage::default();
-        self.sync_with_play_status(input.play_status.as_ref(), &mut render_stage);
+        self.time += input.dt();
+
+        if input.stage.restart_exec {
+            self.restart_exec();
+        }
+
+        self.sync_with_play_status(
+            input.play_status.as_ref(),
+            input.stage.run_until,
+            &mut render_stage,
+        );
+
+        if let Some(exec_view) = self.exec_view.as_mut() {
+            exec_view.set_sub_tick_phases(input.stage.sub_tick_phases);
+            exec_view.set_glass_pipes(input.stage.glass_pipes);
+            exec_view.set_spawn_brush(input.stage.spawn_brush);
+            exec_view.set_paused(
+                input
+                    .play_status
+                    .as_ref()
+                    .map_or(false, play::Status::is_paused),
+            );
+        }
 
         let viewport_size =
             na::Vector2::new(input.target_size.0 as f32, input.target_size.1 as f32);
         self.camera.viewport_size = viewport_size;
         self.camera.projection = perspective_matrix(self.fov, &viewport_size);
 
-        for (_, window_event) in input.stage.window_events.iter() {
-            self.edit_camera_view_input.on_event(window_event);
+        if let Some(photo_mode) = input.stage.photo_mode {
+            self.free_camera = if photo_mode {
+                Some(FreeCamera::look_at(
+                    self.edit_camera_view.eye(),
+                    self.edit_camera_view.target(),
+                    self.fov,
+                ))
+            } else {
+                None
+            };
+        }
+
+        if let Some(menu) = input.stage.menu {
+            self.menu_camera_view = if menu {
+                Some(menu_camera_view(self.editor.machine()))
+            } else {
+                None
+            };
+        }
+
+        for (_, window_event, route) in input.stage.window_events.iter() {
+            if route.camera && self.free_camera.is_none() && self.menu_camera_view.is_none() {
+                self.edit_camera_view_input.on_event(window_event);
+            }
 
             // Print thread-local profiling:
             if let glutin::WindowEvent::KeyboardInput { input, .. } = window_event {
@@ -236,70 +408,204 @@ impl Update {
             }
         }
 
+        if let Some(blueprints) = input.stage.blueprints.as_ref() {
+            self.editor.set_blueprints(blueprints.clone());
+        }
+
+        if let Some(blip_style) = input.stage.blip_style {
+            self.config.blip_style = blip_style;
+            self.editor.set_blip_style(blip_style);
+
+            if let Some(exec_view) = self.exec_view.as_mut() {
+                exec_view.set_blip_style(blip_style);
+            }
+        }
+
+        if let Some(menu_camera_view) = self.menu_camera_view.as_mut() {
+            // Main menu: the machine is shown in the background, without
+            // reacting to input.
+            menu_camera_view.rotate(input.dt().as_secs_f32() * MENU_ROTATE_RADIANS_PER_SEC);
+            self.camera.view = menu_camera_view.view();
+
+            return self.render(input, render_stage);
+        }
+
+        if let Some(free_camera) = self.free_camera.as_mut() {
+            // Photo mode: the editor and execution do not receive any input,
+            // only the free camera moves.
+            self.free_camera_input.update(
+                input.dt().as_secs_f32(),
+                &input.input_state,
+                free_camera,
+            );
+            self.camera.view = free_camera.view();
+            self.camera.projection = perspective_matrix(free_camera.fov_radians, &viewport_size);
+
+            return self.render(input, render_stage);
+        }
+
         if let Some(exec_view) = self.exec_view.as_mut() {
             // Execution mode
 
-            for (_, window_event) in input.stage.window_events.iter() {
-                exec_view.on_event(window_event);
+            for (_, window_event, route) in input.stage.window_events.iter() {
+                if route.tool {
+                    exec_view.on_event(window_event);
+                }
+            }
+
+            if input.stage.unfollow_blip {
+                exec_view.unfollow_blip();
+            }
+            if let Some(track) = input.stage.track_followed_blip {
+                exec_view.set_track_followed_blip(track);
             }
 
             exec_view.update(
-                input.dt,
+                input.dt(),
                 &input.input_state,
                 &self.camera,
                 &self.edit_camera_view,
             );
 
+            if let Some(target) = exec_view.tracked_blip_target() {
+                let current = self.edit_camera_view.target();
+                let t = (input.dt().as_secs_f32() * TRACK_BLIP_SPEED).min(1.0);
+
+                self.edit_camera_view
+                    .set_target(current + (target - current) * t);
+            } else if let Some(showcase) = exec_view.showcase() {
+                let current = self.edit_camera_view.target();
+                let t = (input.dt().as_secs_f32() * TRACK_BLIP_SPEED).min(1.0);
+
+                self.edit_camera_view
+                    .set_target(current + (showcase.target() - current) * t);
+                self.edit_camera_view
+                    .rotate(showcase.yaw_delta(input.dt().as_secs_f32()));
+            }
+
             self.level_progress = exec_view.level_progress().cloned();
         } else {
             // Editor mode
 
-            for (input_state, window_event) in input.stage.window_events.iter() {
-                self.editor.on_event(input_state, window_event);
+            for (input_state, window_event, route) in input.stage.window_events.iter() {
+                if route.tool {
+                    self.editor.on_event(input_state, window_event);
+                }
+            }
+
+            if let Some(machine) = input.stage.load_machine.as_ref() {
+                let level_changed = machine.level != self.editor.machine().level;
+
+                self.editor.set_machine(machine.clone());
+
+                if level_changed {
+                    self.level_progress = gen_level_example(&self.editor);
+                    self.tutorial = gen_tutorial(&self.config, &self.editor);
+                }
             }
 
             self.editor.on_ui_output(&input.stage.editor_ui_output);
             self.editor.update(
-                input.dt,
+                input.dt(),
                 &input.input_state,
                 &self.camera,
                 &mut self.edit_camera_view,
             );
 
             if input.stage.generate_level_example {
-                self.level_progress = self.editor.machine().level.as_ref().map(|level| {
-                    let inputs_outputs = level.spec.gen_inputs_outputs(&mut rand::thread_rng());
-                    LevelProgress::new(None, inputs_outputs)
+                self.level_progress = gen_level_example(&self.editor);
+            }
+
+            if let Some(machine) = self.editor.take_isolated_machine() {
+                self.isolated = Some(Isolated {
+                    machine,
+                    prev_camera_target: self.edit_camera_view.target(),
                 });
             }
         }
 
         self.edit_camera_view_input.update(
-            input.dt.as_secs_f32(),
+            input.dt().as_secs_f32(),
             &input.input_state,
             &mut self.edit_camera_view,
         );
         self.camera.view = self.edit_camera_view.view();
 
+        self.update_tutorial(&input.stage);
+
         self.render(input, render_stage)
     }
 
+    fn update_tutorial(&mut self, stage: &InputStage) {
+        if let Some(tutorial) = self.tutorial.as_mut() {
+            if stage.skip_tutorial {
+                tutorial.skip();
+            }
+
+            let context = tutorial::Context {
+                machine: self.editor.machine(),
+                is_executing: self.exec_view.is_some(),
+                level_status: self.exec_view.as_ref().map(ExecView::next_level_status),
+                acknowledged: stage.acknowledge_tutorial,
+            };
+            tutorial.update(&context);
+
+            if tutorial.is_finished() {
+                self.tutorial = None;
+            }
+        }
+    }
+
+    /// Sets up execution again from the beginning, using the same seed for
+    /// generating the level's inputs.
+    fn restart_exec(&mut self) {
+        if let Some(seed) = self.exec_view.as_ref().map(ExecView::seed) {
+            let mut exec_view =
+                ExecView::new_with_seed(&self.config.exec, self.exec_machine(), seed);
+            exec_view.set_blip_style(self.config.blip_style);
+
+            self.exec_view = Some(exec_view);
+        }
+    }
+
+    /// Returns the machine that is to be executed.
+    fn exec_machine(&self) -> Machine {
+        self.isolated.as_ref().map_or_else(
+            || self.editor.machine().clone(),
+            |isolated| isolated.machine.clone(),
+        )
+    }
+
     pub fn sync_with_play_status(
         &mut self,
         play_status: Option<&play::Status>,
+        run_until: Option<RunUntil>,
         render_stage: &mut render::Stage,
     ) {
         // Do we need to start/stop execution?
         if self.exec_view.is_some() != play_status.is_some() {
             if play_status.is_some() {
                 // Start execution
-                self.exec_view = Some(ExecView::new(
-                    &self.config.exec,
-                    self.editor.machine().clone(),
-                ));
+                let mut exec_view = ExecView::new(&self.config.exec, self.exec_machine());
+                exec_view.set_blip_style(self.config.blip_style);
+
+                if let Some(isolated) = self.isolated.as_ref() {
+                    let size: na::Vector3<f32> = na::convert(isolated.machine.size());
+                    self.edit_camera_view
+                        .set_target(na::Point3::from(size / 2.0));
+                }
+
+                self.exec_view = Some(exec_view);
             } else {
                 // Stop execution
                 self.exec_view = None;
+
+                if let Some(isolated) = self.isolated.take() {
+                    // Return to the editor's machine.
+                    self.edit_camera_view
+                        .set_target(isolated.prev_camera_target);
+                    self.level_progress = gen_level_example(&self.editor);
+                }
             }
         }
 
@@ -340,7 +646,14 @@ impl Update {
             }
 
             for _ in 0..*num_ticks_since_last_update {
-                exec_view.run_tick();
+                if let Some(run_until) = run_until {
+                    if exec_view.run_tick_until(run_until) {
+                        self.run_until_reached = Some(exec_view.cur_tick());
+                        break;
+                    }
+                } else {
+                    exec_view.run_tick();
+                }
 
                 if exec_view.next_level_status() != LevelStatus::Running {
                     break;
@@ -360,16 +673,49 @@ impl Update {
     fn render(&mut self, input: Input, mut render_stage: render::Stage) -> Output {
         profile!("render");
 
+        let mut phase_status = None;
+        let mut hovered_blip = None;
+
         if let Some(exec_view) = self.exec_view.as_mut() {
             // Safe to unwrap here, since we have synchronized execution status
             // above.
-            let tick_time = input.play_status.as_ref().unwrap().time();
+            let tick_time = input
+                .play_status
+                .as_ref()
+                .unwrap()
+                .render_time(input.frame.interpolation());
+
+            exec_view.render(&tick_time, &mut render_stage);
+            phase_status = exec_view.phase_status(&tick_time);
 
-            exec_view.render(tick_time, &mut render_stage);
+            if input
+                .play_status
+                .as_ref()
+                .map_or(false, |status| status.is_paused())
+            {
+                hovered_blip = exec_view.hovered_blip();
+            }
         } else {
             self.editor.render(&mut render_stage);
         }
 
+        if let Some(tutorial) = self.tutorial.as_ref() {
+            for pos in tutorial.target_positions(self.editor.machine()) {
+                render_tutorial_target(&pos, &mut render_stage);
+            }
+        }
+
+        if self.config.view.sort_instances {
+            let eye = self
+                .camera
+                .view
+                .try_inverse()
+                .map_or_else(na::Point3::origin, |inverse| {
+                    inverse.transform_point(&na::Point3::origin())
+                });
+            render_stage.sort(&eye);
+        }
+
         let main_light_pos = na::Point3::new(
             15.0 + 20.0 * (std::f32::consts::PI / 4.0).cos(),
             15.0 + 20.0 * (std::f32::consts::PI / 4.0).sin(),
@@ -384,6 +730,7 @@ impl Update {
             ..Default::default()
         });
 
+        let interpolation = input.frame.interpolation();
         let render_context = render::Context {
             rendology: rendology::Context {
                 camera: self.camera.clone(),
@@ -393,7 +740,9 @@ impl Update {
             },
             tick_time: input
                 .play_status
-                .map_or_else(TickTime::zero, |status| status.time().clone()),
+                .map_or_else(TickTime::zero, |status| status.render_time(interpolation)),
+            time: self.time.as_secs_f32(),
+            depth_of_field: false,
         };
 
         let editor_ui_input = if self.exec_view.is_none() {
@@ -414,16 +763,101 @@ impl Update {
             .as_ref()
             .map(|exec_view| exec_view.next_level_status());
 
+        let outcome = self
+            .exec_view
+            .as_ref()
+            .and_then(|exec_view| exec_view.outcome().cloned());
+        let score = self.exec_view.as_ref().and_then(ExecView::score);
+        let completed_machine = score.map(|_| SavedMachine::from_machine(self.editor.machine()));
+
         Output {
             render_stage,
             render_context,
             editor_ui_input,
             level_progress,
             next_level_status,
+            outcome,
+            score,
+            completed_machine,
+            tutorial_hint: self.tutorial.as_ref().and_then(tutorial::State::hint),
+            followed_blip: self
+                .exec_view
+                .as_ref()
+                .and_then(|exec_view| exec_view.followed_blip().cloned()),
+            phase_status,
+            hovered_blip,
+            start_isolated_exec: self.isolated.is_some() && self.exec_view.is_none(),
+            num_blocks_placed: self.editor.take_num_blocks_placed(),
+            run_until_reached: self.run_until_reached.take(),
+            blip_dist_to_output: self
+                .exec_view
+                .as_ref()
+                .and_then(ExecView::blip_dist_to_output),
+        }
+    }
+}
+
+/// Returns a view that shows the whole machine, or the center of its grid if
+/// it is empty.
+fn menu_camera_view(machine: &Machine) -> EditCameraView {
+    let size: na::Vector3<f32> = na::convert(machine.size());
+
+    let (min, max) = if machine.num_blocks() == 0 {
+        (na::Vector3::zeros(), size)
+    } else {
+        machine
+            .iter_blocks()
+            .fold((size, na::Vector3::zeros()), |(min, max), (_, (pos, _))| {
+                let pos: na::Vector3<f32> = na::convert(pos.coords);
+                (na::inf(&min, &pos), na::sup(&max, &pos))
+            })
+    };
+
+    let center = na::Point3::from((min + max) / 2.0);
+    let extent = (max - min).norm();
+
+    EditCameraView::looking_at(center, extent.max(5.0))
+}
+
+/// Generates a new input/output example to show for the editor's level.
+fn gen_level_example(editor: &Editor) -> Option<LevelProgress> {
+    let level = editor.machine().level.as_ref()?;
+
+    match level.gen_inputs_outputs(&mut rand::thread_rng()) {
+        Ok(inputs_outputs) => Some(LevelProgress::new(None, inputs_outputs)),
+        Err(err) => {
+            warn!(
+                "Failed to generate inputs for level {}: {}",
+                level.id(),
+                err
+            );
+            None
         }
     }
 }
 
+/// Starts the tutorial for the editor's level, if there is one.
+fn gen_tutorial(config: &Config, editor: &Editor) -> Option<tutorial::State> {
+    if !config.tutorial.enabled {
+        return None;
+    }
+
+    editor
+        .machine()
+        .level
+        .as_ref()
+        .and_then(|level| Tutorial::for_level(&config.tutorial, level))
+        .map(tutorial::State::new)
+}
+
+fn render_tutorial_target(pos: &grid::Point3, out: &mut render::Stage) {
+    let center = render::machine::block_center(pos);
+    let transform = na::Matrix4::new_translation(&center.coords) * na::Matrix4::new_scaling(1.1);
+    let color = render::machine::block_color(&na::Vector3::new(1.0, 0.8, 0.2), 1.0);
+
+    render::machine::render_line_wireframe(10.0, &color, &transform, out);
+}
+
 fn perspective_matrix(fov_radians: f32, viewport_size: &na::Vector2<f32>) -> na::Matrix4<f32> {
     let projection =
         na::Perspective3::new(viewport_size.x / viewport_size.y, fov_radians, 0.1, 10000.0);
@@ -0,0 +1,137 @@
+//! Decides which parts of the game receive each window event, so that e.g.
+//! scrolling in a UI window does not also zoom the camera.
+//!
+//! Events are offered to the layers in order of priority: first the UI, then
+//! the tools of the editor and of execution, and then the camera. The UI is
+//! handled by imgui, which sees every event and tells us whether it wants the
+//! keyboard or mouse. A layer that receives a mouse button press captures the
+//! mouse until all buttons are released again.
+
+use std::collections::HashSet;
+
+use glium::glutin::{ElementState, MouseButton, WindowEvent};
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Layer {
+    Ui,
+    Tool,
+    Camera,
+}
+
+/// Which input the UI wants to handle by itself at the moment.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub struct UiFocus {
+    pub keyboard: bool,
+    pub mouse: bool,
+}
+
+/// The layers below the UI that should handle an event.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub struct Route {
+    pub tool: bool,
+    pub camera: bool,
+}
+
+impl Route {
+    pub fn all() -> Self {
+        Self {
+            tool: true,
+            camera: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    pub fn reaches(self, layer: Layer) -> bool {
+        match layer {
+            Layer::Ui => false,
+            Layer::Tool => self.tool,
+            Layer::Camera => self.camera,
+        }
+    }
+
+    pub fn is_none(self) -> bool {
+        !self.tool && !self.camera
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Router {
+    /// The layer that captures the mouse, while any of `pressed_buttons` is
+    /// held down.
+    mouse_capture: Option<Layer>,
+
+    pressed_buttons: HashSet<MouseButton>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the layers below the UI that should handle `event`.
+    ///
+    /// Releasing keys and mouse buttons always reaches every layer, so that
+    /// no layer thinks that they are still held down.
+    pub fn route(&mut self, focus: UiFocus, event: &WindowEvent) -> Route {
+        match event {
+            WindowEvent::KeyboardInput { input, .. } => {
+                if input.state == ElementState::Released || !focus.keyboard {
+                    Route::all()
+                } else {
+                    Route::none()
+                }
+            }
+            WindowEvent::ReceivedCharacter(_) => Route::none(),
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => {
+                    let layer = self.mouse_layer(focus);
+
+                    if self.pressed_buttons.is_empty() {
+                        self.mouse_capture = Some(layer);
+                    }
+                    self.pressed_buttons.insert(*button);
+
+                    // Only the tools react to mouse buttons below the UI.
+                    Route {
+                        tool: layer != Layer::Ui,
+                        camera: false,
+                    }
+                }
+                ElementState::Released => {
+                    self.pressed_buttons.remove(button);
+                    if self.pressed_buttons.is_empty() {
+                        self.mouse_capture = None;
+                    }
+
+                    Route::all()
+                }
+            },
+            WindowEvent::MouseWheel { .. } => {
+                if self.mouse_layer(focus) == Layer::Ui {
+                    Route::none()
+                } else {
+                    Route {
+                        tool: false,
+                        camera: true,
+                    }
+                }
+            }
+            WindowEvent::Focused(false) => {
+                self.pressed_buttons.clear();
+                self.mouse_capture = None;
+
+                Route::all()
+            }
+            _ => Route::all(),
+        }
+    }
+
+    /// Returns the layer that currently receives mouse events.
+    fn mouse_layer(&self, focus: UiFocus) -> Layer {
+        self.mouse_capture
+            .unwrap_or(if focus.mouse { Layer::Ui } else { Layer::Tool })
+    }
+}
@@ -0,0 +1,27 @@
+// Needed for pareen stuff
+#![type_length_limit = "600000000"]
+
+//#![feature(type_alias_impl_trait)]
+
+#[macro_use]
+pub mod util;
+pub mod achievements;
+pub mod bench;
+pub mod config;
+pub mod content;
+pub mod edit;
+pub mod edit_camera_view;
+pub mod exec;
+pub mod game;
+pub mod input_route;
+pub mod input_state;
+pub mod locale;
+pub mod machine;
+pub mod net;
+pub mod photo_mode;
+pub mod progress;
+pub mod render;
+pub mod script;
+pub mod tutorial;
+pub mod window_mode;
+pub mod workshop;
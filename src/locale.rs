@@ -0,0 +1,147 @@
+//! Translations of user-facing strings.
+//!
+//! English strings are part of the code and are used whenever a translation
+//! is missing. A translation is a flat JSON map from keys to strings, stored
+//! as `<dir>/<language>.json`:
+//!
+//! ```text
+//! {
+//!     "block.wind_source.name": "Windquelle",
+//!     "block.scripted.name": "Skriptblock \"{name}\"",
+//!     "ui.level.goal": "Ziel: {goal}"
+//! }
+//! ```
+//!
+//! Strings may contain arguments in braces, which are filled in by
+//! `Locale::format`.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use log::{info, warn};
+
+use crate::machine::{Block, Level};
+
+/// Language of the strings in the code, which needs no translation file.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub dir: PathBuf,
+    pub language: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("locale"),
+            language: DEFAULT_LANGUAGE.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Locale {
+    strings: HashMap<String, String>,
+}
+
+impl Locale {
+    /// Loads the translation for the configured language. If it does not
+    /// exist or is invalid, the English strings are used.
+    pub fn load(config: &Config) -> Locale {
+        if config.language == DEFAULT_LANGUAGE {
+            return Locale::default();
+        }
+
+        let path = config.dir.join(format!("{}.json", config.language));
+        info!("Loading translation from file {:?}", path);
+
+        let strings = match File::open(&path) {
+            Ok(file) => {
+                let reader = BufReader::new(file);
+                serde_json::from_reader(reader).unwrap_or_else(|err| {
+                    warn!("Error while loading translation {:?}: {}", path, err);
+                    HashMap::new()
+                })
+            }
+            Err(err) => {
+                warn!("Could not open file {:?} for reading: {}", path, err);
+                HashMap::new()
+            }
+        };
+
+        Locale { strings }
+    }
+
+    /// Returns the translation of the string with the given key, or `default`
+    /// if there is none.
+    pub fn get<'a>(&'a self, key: &str, default: &'a str) -> &'a str {
+        self.strings.get(key).map_or(default, String::as_str)
+    }
+
+    /// Returns the translation of the string with the given key, replacing
+    /// each `{name}` with the value of the argument of that name.
+    pub fn format(&self, key: &str, default: &str, args: &[(&str, &str)]) -> String {
+        args.iter()
+            .fold(self.get(key, default).to_string(), |s, (name, value)| {
+                s.replace(&format!("{{{}}}", name), value)
+            })
+    }
+
+    /// Returns the translation as an imgui label whose ID is the key, so that
+    /// widgets keep their state when the language changes.
+    pub fn label(&self, key: &str, default: &str) -> String {
+        format!("{}###{}", self.get(key, default), key)
+    }
+
+    pub fn block_name(&self, block: &Block) -> String {
+        let key = format!("block.{}.name", block.key());
+
+        // Translations can refer to the name of scripted blocks. The English
+        // name already contains it.
+        let script_name = match block {
+            Block::Scripted { name, .. } => name.as_str(),
+            _ => "",
+        };
+
+        self.format(&key, &block.name(), &[("name", script_name)])
+    }
+
+    pub fn block_description(&self, block: &Block) -> &str {
+        self.get(
+            &format!("block.{}.description", block.key()),
+            block.description(),
+        )
+    }
+
+    /// Returns the goal of the level, which can be translated for each level
+    /// id.
+    pub fn level_description(&self, level: &Level) -> String {
+        let key = format!("level.{}.description", level.id());
+
+        self.get(&key, &level.spec.description()).to_string()
+    }
+}
+
+/// Returns the languages that can be selected, i.e. English and all languages
+/// that have a translation file, sorted by name.
+pub fn languages(config: &Config) -> Vec<String> {
+    let mut languages: Vec<String> = fs::read_dir(&config.dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+                .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+                .filter(|language| language != DEFAULT_LANGUAGE)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    languages.push(DEFAULT_LANGUAGE.to_string());
+    languages.sort();
+
+    languages
+}
@@ -0,0 +1,218 @@
+//! Queries about where wind and blips can flow in machines. These are based
+//! on the machine's `Graph`, and are meant for lints, heuristics and
+//! tutorials that need to know more about a machine than its single blocks.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::machine::graph::{EdgeKind, Graph};
+use crate::machine::grid::Dir3;
+use crate::machine::{Block, BlockIndex, Machine};
+
+impl Graph {
+    /// Returns the blocks that can be reached from `starts` by following
+    /// edges of the given kind, including the starts themselves.
+    pub fn reachable(
+        &self,
+        starts: impl IntoIterator<Item = BlockIndex>,
+        kind: EdgeKind,
+    ) -> HashSet<BlockIndex> {
+        let out_edges = self.out_edges(kind);
+
+        let mut reached: HashSet<BlockIndex> = starts.into_iter().collect();
+        let mut queue: Vec<BlockIndex> = reached.iter().cloned().collect();
+
+        while let Some(index) = queue.pop() {
+            for &edge_index in out_edges.get(&index).into_iter().flatten() {
+                let next_index = self.edges[edge_index].to;
+                if reached.insert(next_index) {
+                    queue.push(next_index);
+                }
+            }
+        }
+
+        reached
+    }
+
+    /// Returns the loops in which wind or blips can circulate, each as a
+    /// sorted list of the blocks that belong to it. Going back and forth
+    /// between two neighbors, as is possible in any pipe, does not count as
+    /// a loop.
+    pub fn loops(&self, kind: EdgeKind) -> Vec<Vec<BlockIndex>> {
+        let out_edges = self.out_edges(kind);
+
+        // Look for cycles in the graph whose nodes are our edges, connecting
+        // each edge to the edges that continue it without turning back.
+        let successors = |edge_index: usize| -> Vec<usize> {
+            let edge = &self.edges[edge_index];
+
+            out_edges
+                .get(&edge.to)
+                .into_iter()
+                .flatten()
+                .cloned()
+                .filter(|&next_index| self.edges[next_index].to != edge.from)
+                .collect()
+        };
+
+        let mut loops: Vec<Vec<BlockIndex>> =
+            strongly_connected_components(self.edges.len(), successors)
+                .into_iter()
+                .filter(|component| component.len() > 1)
+                .map(|component| {
+                    let mut blocks: Vec<BlockIndex> = component
+                        .iter()
+                        .map(|&edge_index| self.edges[edge_index].from)
+                        .collect();
+                    blocks.sort();
+                    blocks.dedup();
+                    blocks
+                })
+                .collect();
+
+        // A loop can be passed in both directions, which gives two components
+        // with the same blocks.
+        loops.sort();
+        loops.dedup();
+        loops
+    }
+
+    fn out_edges(&self, kind: EdgeKind) -> HashMap<BlockIndex, Vec<usize>> {
+        let mut out_edges: HashMap<BlockIndex, Vec<usize>> = HashMap::new();
+
+        for (edge_index, edge) in self.edges.iter().enumerate() {
+            if edge.kind == kind {
+                out_edges.entry(edge.from).or_default().push(edge_index);
+            }
+        }
+
+        out_edges
+    }
+}
+
+impl Machine {
+    /// Returns the blocks that blips can reach from the level's inputs by
+    /// moving through holes or being spawned, including the inputs.
+    pub fn reachable_from_inputs(&self) -> HashSet<BlockIndex> {
+        let inputs = self
+            .iter_blocks()
+            .filter(|(_, (_, placed_block))| match placed_block.block {
+                Block::Input { .. } => true,
+                _ => false,
+            })
+            .map(|(index, _)| index);
+
+        Graph::from_machine(self).reachable(inputs, EdgeKind::Blip)
+    }
+
+    /// Returns the blocks that have holes for wind, but that are not
+    /// connected to any wind source, so they can never receive wind.
+    pub fn blocks_without_wind(&self) -> Vec<BlockIndex> {
+        let sources = self
+            .iter_blocks()
+            .filter(|(_, (_, placed_block))| {
+                Dir3::ALL
+                    .iter()
+                    .any(|&dir| placed_block.block.has_wind_source(dir))
+            })
+            .map(|(index, _)| index);
+
+        let reached = Graph::from_machine(self).reachable(sources, EdgeKind::Wind);
+
+        self.iter_blocks()
+            .filter(|(index, (_, placed_block))| {
+                !reached.contains(index)
+                    && Dir3::ALL
+                        .iter()
+                        .any(|&dir| placed_block.block.has_wind_hole_in(dir, false))
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Returns the loops in which blips can circulate.
+    pub fn blip_loops(&self) -> Vec<Vec<BlockIndex>> {
+        Graph::from_machine(self).loops(EdgeKind::Blip)
+    }
+}
+
+/// Finds the strongly connected components of a directed graph with nodes
+/// `0..num_nodes`, using Tarjan's algorithm. The recursion is unrolled, so
+/// that large machines do not overflow the stack.
+fn strongly_connected_components(
+    num_nodes: usize,
+    successors: impl Fn(usize) -> Vec<usize>,
+) -> Vec<Vec<usize>> {
+    let mut order: Vec<Option<usize>> = vec![None; num_nodes];
+    let mut lowlink = vec![0; num_nodes];
+    let mut on_stack = vec![false; num_nodes];
+    let mut stack = Vec::new();
+    let mut next_order = 0;
+    let mut components = Vec::new();
+
+    for root in 0..num_nodes {
+        if order[root].is_some() {
+            continue;
+        }
+
+        order[root] = Some(next_order);
+        lowlink[root] = next_order;
+        next_order += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        // Each entry holds a node, its successors, and the position of the
+        // next successor to visit.
+        let mut call_stack = vec![(root, successors(root), 0)];
+
+        while !call_stack.is_empty() {
+            let step = {
+                let (node, node_successors, pos) = call_stack.last_mut().unwrap();
+                let next = node_successors.get(*pos).cloned();
+                *pos += 1;
+                (*node, next)
+            };
+
+            match step {
+                (node, Some(next)) => match order[next] {
+                    None => {
+                        order[next] = Some(next_order);
+                        lowlink[next] = next_order;
+                        next_order += 1;
+                        stack.push(next);
+                        on_stack[next] = true;
+
+                        call_stack.push((next, successors(next), 0));
+                    }
+                    Some(order_next) if on_stack[next] => {
+                        lowlink[node] = lowlink[node].min(order_next);
+                    }
+                    Some(_) => (),
+                },
+                (node, None) => {
+                    call_stack.pop();
+
+                    if let Some((parent, _, _)) = call_stack.last() {
+                        lowlink[*parent] = lowlink[*parent].min(lowlink[node]);
+                    }
+
+                    if order[node] == Some(lowlink[node]) {
+                        let mut component = Vec::new();
+                        loop {
+                            let member = stack.pop().unwrap();
+                            on_stack[member] = false;
+                            component.push(member);
+
+                            if member == node {
+                                break;
+                            }
+                        }
+
+                        components.push(component);
+                    }
+                }
+            }
+        }
+    }
+
+    components
+}
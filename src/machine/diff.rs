@@ -0,0 +1,113 @@
+//! Differences between machines, e.g. between two iterations of a solution,
+//! and merging of the changes that were made to a machine in two different
+//! ways.
+
+use std::collections::BTreeMap;
+
+use crate::machine::grid::Point3;
+use crate::machine::{Machine, PlacedBlock};
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A position at which two machines have different blocks.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct BlockChange {
+    pub pos: Point3,
+    pub before: Option<PlacedBlock>,
+    pub after: Option<PlacedBlock>,
+}
+
+impl BlockChange {
+    pub fn kind(&self) -> ChangeKind {
+        match (&self.before, &self.after) {
+            (None, Some(_)) => ChangeKind::Added,
+            (Some(_), None) => ChangeKind::Removed,
+            _ => ChangeKind::Changed,
+        }
+    }
+}
+
+/// The result of merging two machines that have both been derived from the
+/// same base machine.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Merge {
+    pub machine: Machine,
+
+    /// Positions that have been changed differently in both machines. These
+    /// keep the block of the first machine.
+    pub conflicts: Vec<Point3>,
+}
+
+impl Machine {
+    /// Returns the changes that turn this machine into `other`, ordered by
+    /// position. The machines' levels are not compared.
+    pub fn diff(&self, other: &Machine) -> Vec<BlockChange> {
+        let mut blocks: BTreeMap<(isize, isize, isize), BlockChange> = BTreeMap::new();
+
+        for (_, (pos, placed_block)) in self.iter_blocks() {
+            blocks.insert(
+                (pos.z, pos.y, pos.x),
+                BlockChange {
+                    pos: *pos,
+                    before: Some(placed_block.clone()),
+                    after: None,
+                },
+            );
+        }
+
+        for (_, (pos, placed_block)) in other.iter_blocks() {
+            blocks
+                .entry((pos.z, pos.y, pos.x))
+                .or_insert_with(|| BlockChange {
+                    pos: *pos,
+                    before: None,
+                    after: None,
+                })
+                .after = Some(placed_block.clone());
+        }
+
+        blocks
+            .into_iter()
+            .map(|(_, change)| change)
+            .filter(|change| change.before != change.after)
+            .collect()
+    }
+
+    /// Applies changes as returned by `diff`. Changes outside of the machine
+    /// are ignored.
+    pub fn apply_changes(&mut self, changes: &[BlockChange]) {
+        for change in changes {
+            if self.is_valid_pos(&change.pos) {
+                self.set(&change.pos, change.after.clone());
+            }
+        }
+    }
+}
+
+/// Merges the changes from `base` to `theirs` into `ours`. Where `ours` has
+/// been changed differently at the same position, the block of `ours` is kept
+/// and a conflict is reported.
+pub fn merge(base: &Machine, ours: &Machine, theirs: &Machine) -> Merge {
+    let mut machine = ours.clone();
+    let mut conflicts = Vec::new();
+
+    for change in base.diff(theirs) {
+        let our_block = ours.get(&change.pos).cloned();
+
+        if our_block == change.after {
+            // Both have made the same change.
+            continue;
+        } else if our_block == change.before {
+            machine.apply_changes(&[change]);
+        } else {
+            conflicts.push(change.pos);
+        }
+    }
+
+    Merge { machine, conflicts }
+}
@@ -0,0 +1,130 @@
+//! Exports the connectivity of machines as graphs, so that they can be
+//! analyzed or drawn with external tools.
+
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::machine::grid::{Dir3, Point3};
+use crate::machine::{BlockIndex, BlockType, Machine};
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize)]
+pub struct Node {
+    pub index: BlockIndex,
+    pub pos: Point3,
+    pub block_type: BlockType,
+    pub name: String,
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug, Serialize)]
+pub enum EdgeKind {
+    /// Wind can flow from one block into the other.
+    Wind,
+
+    /// Blips can move or be spawned from one block into the other.
+    Blip,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize)]
+pub struct Edge {
+    pub from: BlockIndex,
+    pub to: BlockIndex,
+    pub dir: Dir3,
+    pub kind: EdgeKind,
+}
+
+/// Blocks of a machine, with directed edges between neighbors that wind or
+/// blips can pass between.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+impl Graph {
+    pub fn from_machine(machine: &Machine) -> Self {
+        let nodes = machine
+            .iter_blocks()
+            .map(|(index, (pos, placed_block))| Node {
+                index,
+                pos: *pos,
+                block_type: placed_block.block.block_type(),
+                name: placed_block.block.name(),
+            })
+            .collect();
+
+        let mut edges = Vec::new();
+
+        for (index, (pos, placed_block)) in machine.iter_blocks() {
+            let block = &placed_block.block;
+
+            for &dir in Dir3::ALL.iter() {
+                let (neighbor_index, neighbor) =
+                    match machine.get_with_index(&(pos + dir.to_vector())) {
+                        Some((neighbor_index, neighbor)) => (neighbor_index, &neighbor.block),
+                        None => continue,
+                    };
+
+                if block.has_wind_hole_out(dir, false)
+                    && neighbor.has_wind_hole_in(dir.invert(), false)
+                {
+                    edges.push(Edge {
+                        from: index,
+                        to: neighbor_index,
+                        dir,
+                        kind: EdgeKind::Wind,
+                    });
+                }
+
+                if block.has_blip_spawn(dir)
+                    || (block.has_move_hole(dir, false)
+                        && neighbor.has_move_hole(dir.invert(), false))
+                {
+                    edges.push(Edge {
+                        from: index,
+                        to: neighbor_index,
+                        dir,
+                        kind: EdgeKind::Blip,
+                    });
+                }
+            }
+        }
+
+        Graph { nodes, edges }
+    }
+
+    /// Returns the graph in the DOT language of Graphviz. Wind edges are
+    /// drawn in blue, and blip edges in black.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+
+        writeln!(dot, "digraph machine {{").unwrap();
+        writeln!(dot, "    node [shape=box];").unwrap();
+
+        for node in self.nodes.iter() {
+            writeln!(
+                dot,
+                "    {} [label=\"{}\\n({}, {}, {})\"];",
+                node.index,
+                node.name.replace('"', "\\\""),
+                node.pos.x,
+                node.pos.y,
+                node.pos.z,
+            )
+            .unwrap();
+        }
+
+        for edge in self.edges.iter() {
+            let color = match edge.kind {
+                EdgeKind::Wind => "blue",
+                EdgeKind::Blip => "black",
+            };
+
+            writeln!(dot, "    {} -> {} [color={}];", edge.from, edge.to, color).unwrap();
+        }
+
+        writeln!(dot, "}}").unwrap();
+
+        dot
+    }
+}
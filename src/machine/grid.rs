@@ -129,6 +129,14 @@ impl Dir3 {
         Dir3(axis, sign)
     }
 
+    pub fn mirrored_x(self) -> Dir3 {
+        if self.0 == Axis3::Y {
+            self.invert()
+        } else {
+            self
+        }
+    }
+
     pub fn mirrored_y(self) -> Dir3 {
         if self.0 == Axis3::X {
             self.invert()
@@ -254,6 +262,57 @@ impl<T: Default + Copy> Grid3<T> {
             data: vec![Default::default(); n],
         }
     }
+
+    /// Returns a grid of the given size that keeps the values at positions
+    /// that are valid in both grids.
+    pub fn resized(&self, size: Vector3) -> Grid3<T> {
+        let mut grid = Grid3::new(size);
+
+        for p in grid.clip_region(
+            &Point3::origin(),
+            &(Point3::origin() + self.size.add_scalar(-1)),
+        ) {
+            grid[p] = self[p];
+        }
+
+        grid
+    }
+}
+
+/// Iterates over the positions in the box between `min` and `max`, both
+/// inclusive. The x coordinate changes fastest, matching the layout of
+/// `Grid3`.
+pub fn iter_box(min: &Point3, max: &Point3) -> impl Iterator<Item = Point3> {
+    let (min, max) = (*min, *max);
+
+    (min.z..=max.z).flat_map(move |z| {
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| Point3::new(x, y, z)))
+    })
+}
+
+/// Returns the smallest and largest corner of the box spanned by `a` and `b`.
+pub fn box_corners(a: &Point3, b: &Point3) -> (Point3, Point3) {
+    (
+        Point3::from(a.coords.zip_map(&b.coords, isize::min)),
+        Point3::from(a.coords.zip_map(&b.coords, isize::max)),
+    )
+}
+
+/// Iterates over the positions on the walls of the box between `min` and
+/// `max`, leaving out the interior. Boxes that span at least three layers
+/// are closed by a floor and a ceiling, while flatter boxes only get the
+/// sides.
+pub fn iter_box_walls(min: &Point3, max: &Point3) -> impl Iterator<Item = Point3> {
+    let (min, max) = (*min, *max);
+    let is_closed = max.z - min.z >= 2;
+
+    iter_box(&min, &max).filter(move |p| {
+        p.x == min.x
+            || p.x == max.x
+            || p.y == min.y
+            || p.y == max.y
+            || (is_closed && (p.z == min.z || p.z == max.z))
+    })
 }
 
 impl<T> Grid3<T> {
@@ -285,6 +344,57 @@ impl<T> Grid3<T> {
     pub fn size(&self) -> Vector3 {
         self.size
     }
+
+    /// Iterates over all positions of the grid.
+    pub fn positions(&self) -> impl Iterator<Item = Point3> {
+        iter_box(
+            &Point3::origin(),
+            &(Point3::origin() + self.size.add_scalar(-1)),
+        )
+    }
+
+    /// Iterates over the valid positions in the box between `min` and
+    /// `max`, both inclusive.
+    pub fn clip_region(&self, min: &Point3, max: &Point3) -> impl Iterator<Item = Point3> {
+        let min = min.coords.zip_map(&Vector3::zeros(), isize::max);
+        let max = max.coords.zip_map(&self.size.add_scalar(-1), isize::min);
+
+        iter_box(&Point3::from(min), &Point3::from(max))
+    }
+
+    /// Iterates over the values in the box between `min` and `max`, both
+    /// inclusive. Positions outside of the grid are skipped.
+    pub fn iter_region(&self, min: &Point3, max: &Point3) -> impl Iterator<Item = (Point3, &T)> {
+        self.clip_region(min, max).map(move |p| (p, &self[p]))
+    }
+
+    /// Iterates over the neighbors of a position that are within the grid.
+    pub fn neighbors(&self, p: &Point3) -> impl Iterator<Item = (Dir3, Point3)> + '_ {
+        let p = *p;
+
+        Dir3::ALL
+            .iter()
+            .map(move |dir| (*dir, p + dir.to_vector()))
+            .filter(move |(_, neighbor)| self.is_valid_pos(neighbor))
+    }
+}
+
+impl<T: Clone> Grid3<T> {
+    /// Sets all values in the box between `min` and `max`, both inclusive.
+    /// Positions outside of the grid are skipped.
+    pub fn fill_region(&mut self, min: &Point3, max: &Point3, value: T) {
+        for p in self.clip_region(min, max) {
+            self[p] = value.clone();
+        }
+    }
+}
+
+impl<T: Clone + Default> Grid3<T> {
+    /// Resets all values in the box between `min` and `max`, both
+    /// inclusive.
+    pub fn clear_region(&mut self, min: &Point3, max: &Point3) {
+        self.fill_region(min, max, T::default());
+    }
 }
 
 impl<T> Index<Point3> for Grid3<T> {
@@ -1,17 +1,373 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::iter;
 
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 
-use crate::machine::{grid, BlipKind};
+use crate::machine::{bounding_volume, grid, BlipKind, Block, BlockType, Machine, PlacedBlock};
+use crate::script;
 
 #[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
 pub struct Level {
     pub size: grid::Vector3,
     pub spec: Spec,
+
+    #[serde(default)]
+    pub constraints: Constraints,
+
+    /// Lua source for generating inputs and outputs of `Spec::Scripted` and
+    /// for defining the behavior of `Block::Scripted`. See `crate::script`.
+    #[serde(default)]
+    pub script: Option<String>,
+
+    /// Name of the content pack that the level comes from, if any. Used for
+    /// namespacing the level's id. See `crate::content`.
+    #[serde(default)]
+    pub pack: Option<String>,
+}
+
+impl Level {
+    /// Returns a short name that identifies the level, e.g. for storing
+    /// solutions.
+    ///
+    /// Parameters that the builtin levels have in common are left out of the
+    /// id, so that the ids of the builtin levels, and with them the places
+    /// where their solutions are stored, stay the same.
+    pub fn id(&self) -> String {
+        let id = match &self.spec {
+            Spec::Id { dim } => format!("id_{}", dim),
+            Spec::Clock { pattern } if pattern[..] == CLOCK_DEFAULT_PATTERN => "clock".to_string(),
+            Spec::Clock { pattern } => format!(
+                "clock_{}",
+                pattern
+                    .iter()
+                    .map(|kind| match kind {
+                        BlipKind::A => 'a',
+                        BlipKind::B => 'b',
+                    })
+                    .collect::<String>()
+            ),
+            Spec::BitwiseMax => "o_beats_g".to_string(),
+            Spec::MakeItN { n, max } if *max == MAKE_IT_N_DEFAULT_MAX => format!("make_it_{}", n),
+            Spec::MakeItN { n, max } => format!("make_it_{}_max_{}", n, max),
+            Spec::MultiplyByN { n, max } if *max == MULTIPLY_BY_N_DEFAULT_MAX => {
+                format!("mul_by_{}", n)
+            }
+            Spec::MultiplyByN { n, max } => format!("mul_by_{}_max_{}", n, max),
+            Spec::Scripted { name, .. } => name.clone(),
+        };
+
+        match &self.pack {
+            Some(pack) => format!("{}.{}", pack, id),
+            None => id,
+        }
+    }
+
+    /// Generates inputs and outputs for the level. This fails for scripted
+    /// levels if the script is missing or broken, or if the game is built
+    /// without scripting.
+    pub fn gen_inputs_outputs<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> Result<InputsOutputs, script::Error> {
+        match &self.spec {
+            Spec::Scripted { .. } => {
+                let source = self.script.as_ref().ok_or(script::Error::Missing)?;
+
+                script::Runtime::new(source)?.generate(rng.gen())
+            }
+            spec => Ok(spec.gen_inputs_outputs(rng)),
+        }
+    }
+}
+
+/// Pattern of the builtin clock level.
+pub const CLOCK_DEFAULT_PATTERN: [BlipKind; 2] = [BlipKind::A, BlipKind::B];
+
+/// Maximal number of input blips of the builtin `Spec::MakeItN` levels.
+pub const MAKE_IT_N_DEFAULT_MAX: usize = 30;
+
+/// Maximal number of input blips of the builtin `Spec::MultiplyByN` levels.
+pub const MULTIPLY_BY_N_DEFAULT_MAX: usize = 15;
+
+/// Returns all levels, in the order in which they are meant to be played.
+pub fn levels() -> Vec<Level> {
+    vec![
+        Level {
+            size: grid::Vector3::new(9, 9, 1),
+            spec: Spec::Clock {
+                pattern: CLOCK_DEFAULT_PATTERN.to_vec(),
+            },
+            constraints: Constraints::default(),
+            script: None,
+            pack: None,
+        },
+        Level {
+            size: grid::Vector3::new(27, 27, 4),
+            spec: Spec::Id { dim: 3 },
+            constraints: Constraints::default(),
+            script: None,
+            pack: None,
+        },
+        Level {
+            size: grid::Vector3::new(19, 19, 2),
+            spec: Spec::BitwiseMax,
+            constraints: Constraints::default(),
+            script: None,
+            pack: None,
+        },
+        Level {
+            size: grid::Vector3::new(19, 19, 2),
+            spec: Spec::MakeItN {
+                n: 3,
+                max: MAKE_IT_N_DEFAULT_MAX,
+            },
+            constraints: Constraints::default(),
+            script: None,
+            pack: None,
+        },
+        Level {
+            size: grid::Vector3::new(60, 60, 15),
+            spec: Spec::MakeItN {
+                n: 10,
+                max: MAKE_IT_N_DEFAULT_MAX,
+            },
+            constraints: Constraints::default(),
+            script: None,
+            pack: None,
+        },
+        Level {
+            size: grid::Vector3::new(30, 30, 30),
+            spec: Spec::MultiplyByN {
+                n: 3,
+                max: MULTIPLY_BY_N_DEFAULT_MAX,
+            },
+            constraints: Constraints::default(),
+            script: None,
+            pack: None,
+        },
+    ]
+}
+
+/// Returns the level that follows the given level in `levels`, if any.
+pub fn next_level(levels: &[Level], level: &Level) -> Option<Level> {
+    levels
+        .iter()
+        .skip_while(|other| *other != level)
+        .nth(1)
+        .cloned()
+}
+
+/// An axis-aligned box of grid positions, including both corners.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct Region {
+    pub min: grid::Point3,
+    pub max: grid::Point3,
+}
+
+impl Region {
+    pub fn contains(&self, pos: &grid::Point3) -> bool {
+        (0..3).all(|i| self.min[i] <= pos[i] && pos[i] <= self.max[i])
+    }
+}
+
+/// Restrictions on the machines that may be built for a level.
+#[derive(PartialEq, Eq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Constraints {
+    /// Types of blocks that may be placed. If `None`, all types are allowed.
+    pub allowed_blocks: Option<Vec<BlockType>>,
+
+    /// Maximal number of blocks that may be placed.
+    pub max_num_blocks: Option<usize>,
+
+    /// Maximal volume of the bounding box around the placed blocks.
+    pub max_footprint: Option<usize>,
+
+    /// Regions in which no blocks may be placed.
+    pub forbidden_regions: Vec<Region>,
+}
+
+/// A way in which a machine violates the constraints of its level.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Violation {
+    BlockNotAllowed { block_type: BlockType },
+    ForbiddenRegion { pos: grid::Point3 },
+    TooManyBlocks { num_blocks: usize, max: usize },
+    FootprintTooLarge { footprint: usize, max: usize },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::BlockNotAllowed { block_type } => write!(
+                f,
+                "Blocks of type {:?} are not allowed in this level",
+                block_type,
+            ),
+            Violation::ForbiddenRegion { pos } => write!(
+                f,
+                "Blocks may not be placed at {}, {}, {}",
+                pos.x, pos.y, pos.z,
+            ),
+            Violation::TooManyBlocks { num_blocks, max } => write!(
+                f,
+                "The machine has {} blocks, but at most {} are allowed",
+                num_blocks, max,
+            ),
+            Violation::FootprintTooLarge { footprint, max } => write!(
+                f,
+                "The machine has a footprint of {}, but at most {} is allowed",
+                footprint, max,
+            ),
+        }
+    }
 }
 
-impl Level {}
+impl Constraints {
+    pub fn is_block_allowed(&self, block: &Block) -> bool {
+        !block.is_player_block()
+            || self
+                .allowed_blocks
+                .as_ref()
+                .map_or(true, |allowed| allowed.contains(&block.block_type()))
+    }
+
+    pub fn is_pos_allowed(&self, pos: &grid::Point3) -> bool {
+        !self
+            .forbidden_regions
+            .iter()
+            .any(|region| region.contains(pos))
+    }
+
+    fn check_block(&self, pos: &grid::Point3, block: &Block) -> Option<Violation> {
+        if !self.is_block_allowed(block) {
+            Some(Violation::BlockNotAllowed {
+                block_type: block.block_type(),
+            })
+        } else if block.is_player_block() && !self.is_pos_allowed(pos) {
+            Some(Violation::ForbiddenRegion { pos: *pos })
+        } else {
+            None
+        }
+    }
+
+    /// Returns all the ways in which the machine violates the constraints.
+    pub fn check(&self, machine: &Machine) -> Vec<Violation> {
+        let mut violations: Vec<Violation> = machine
+            .iter_blocks()
+            .filter_map(|(_, (pos, placed_block))| self.check_block(pos, &placed_block.block))
+            .collect();
+
+        let num_blocks = machine.num_player_blocks();
+        if let Some(max) = self.max_num_blocks.filter(|max| num_blocks > *max) {
+            violations.push(Violation::TooManyBlocks { num_blocks, max });
+        }
+
+        let footprint = machine.footprint();
+        if let Some(max) = self.max_footprint.filter(|max| footprint > *max) {
+            violations.push(Violation::FootprintTooLarge { footprint, max });
+        }
+
+        violations
+    }
+
+    /// Checks if changing a machine from `before` to `after` introduces a new
+    /// violation. Changes that do not make existing violations worse are
+    /// accepted, so that players can fix machines that are out of bounds.
+    pub fn check_change(&self, before: &Machine, after: &Machine) -> Option<Violation> {
+        let new_block_violation = after
+            .iter_blocks()
+            .filter(|(_, (pos, placed_block))| before.get(pos) != Some(placed_block))
+            .find_map(|(_, (pos, placed_block))| self.check_block(pos, &placed_block.block));
+
+        if new_block_violation.is_some() {
+            return new_block_violation;
+        }
+
+        let num_blocks = after.num_player_blocks();
+        if let Some(max) = self.max_num_blocks {
+            if num_blocks > max && num_blocks > before.num_player_blocks() {
+                return Some(Violation::TooManyBlocks { num_blocks, max });
+            }
+        }
+
+        let footprint = after.footprint();
+        if let Some(max) = self.max_footprint {
+            if footprint > max && footprint > before.footprint() {
+                return Some(Violation::FootprintTooLarge { footprint, max });
+            }
+        }
+
+        None
+    }
+
+    /// Checks if setting `blocks` in `machine` would introduce a new
+    /// violation, like `check_change`. The machine is not changed, so that
+    /// edits can be checked before they are run, without copying the machine.
+    pub fn check_set_blocks(
+        &self,
+        machine: &Machine,
+        blocks: &HashMap<grid::Point3, Option<PlacedBlock>>,
+    ) -> Option<Violation> {
+        let valid_blocks: Vec<_> = blocks
+            .iter()
+            .filter(|(pos, _)| machine.is_valid_pos(pos))
+            .collect();
+
+        let new_block_violation = valid_blocks
+            .iter()
+            .filter_map(|(pos, placed_block)| placed_block.as_ref().map(|b| (pos, b)))
+            .filter(|(pos, placed_block)| machine.get(pos) != Some(placed_block))
+            .find_map(|(pos, placed_block)| self.check_block(pos, &placed_block.block));
+
+        if new_block_violation.is_some() {
+            return new_block_violation;
+        }
+
+        let is_player_block = |placed_block: Option<&PlacedBlock>| {
+            placed_block.map_or(false, |placed_block| placed_block.block.is_player_block())
+        };
+
+        if let Some(max) = self.max_num_blocks {
+            let num_added = valid_blocks
+                .iter()
+                .filter(|(_, placed_block)| is_player_block(placed_block.as_ref()))
+                .count();
+            let num_removed = valid_blocks
+                .iter()
+                .filter(|(pos, _)| is_player_block(machine.get(pos)))
+                .count();
+
+            if num_added > num_removed {
+                let num_blocks = machine.num_player_blocks() + num_added - num_removed;
+
+                if num_blocks > max {
+                    return Some(Violation::TooManyBlocks { num_blocks, max });
+                }
+            }
+        }
+
+        if let Some(max) = self.max_footprint {
+            let kept = machine
+                .iter_player_blocks()
+                .map(|(_, (pos, _))| *pos)
+                .filter(|pos| !blocks.contains_key(pos));
+            let added = valid_blocks
+                .iter()
+                .filter(|(_, placed_block)| is_player_block(placed_block.as_ref()))
+                .map(|(pos, _)| **pos);
+            let footprint = bounding_volume(kept.chain(added));
+
+            if footprint > max && footprint > machine.footprint() {
+                return Some(Violation::FootprintTooLarge { footprint, max });
+            }
+        }
+
+        None
+    }
+}
 
 #[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Input {
@@ -26,11 +382,29 @@ pub struct InputsOutputs {
 
 #[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
 pub enum Spec {
-    Id { dim: usize },
-    Clock { pattern: Vec<BlipKind> },
+    Id {
+        dim: usize,
+    },
+    Clock {
+        pattern: Vec<BlipKind>,
+    },
     BitwiseMax,
-    MakeItN { n: usize, max: usize },
-    MultiplyByN { n: usize, max: usize },
+    MakeItN {
+        n: usize,
+        max: usize,
+    },
+    MultiplyByN {
+        n: usize,
+        max: usize,
+    },
+
+    /// A level whose inputs and outputs are generated by the level's script.
+    Scripted {
+        name: String,
+        description: String,
+        input_dim: usize,
+        output_dim: usize,
+    },
 }
 
 pub fn gen_blip_kind<R: Rng + ?Sized>(rng: &mut R) -> BlipKind {
@@ -66,6 +440,7 @@ impl Spec {
             Spec::BitwiseMax => 2,
             Spec::MakeItN { .. } => 1,
             Spec::MultiplyByN { .. } => 1,
+            Spec::Scripted { input_dim, .. } => input_dim,
         }
     }
 
@@ -76,6 +451,7 @@ impl Spec {
             Spec::BitwiseMax => 1,
             Spec::MakeItN { .. } => 1,
             Spec::MultiplyByN { .. } => 1,
+            Spec::Scripted { output_dim, .. } => output_dim,
         }
     }
 
@@ -86,9 +462,13 @@ impl Spec {
             Spec::BitwiseMax => format!("{} beats {}", BlipKind::B, BlipKind::A),
             Spec::MakeItN { n, .. } => format!("Round up to the next multiple of {}", n),
             Spec::MultiplyByN { n, .. } => format!("Multiply by {}", n),
+            Spec::Scripted { description, .. } => description.clone(),
         }
     }
 
+    /// Generates inputs and outputs for the spec. Note that scripted specs
+    /// need the level's script, so they need to be generated through
+    /// `Level::gen_inputs_outputs`. Here, they are left empty.
     pub fn gen_inputs_outputs<R: Rng + ?Sized>(&self, rng: &mut R) -> InputsOutputs {
         match self {
             Spec::Id { dim } => {
@@ -129,7 +509,8 @@ impl Spec {
                 InputsOutputs { inputs, outputs }
             }
             Spec::MakeItN { n, max } => {
-                let len_input: usize = rng.gen_range(1, *max);
+                // `max` is exclusive, but there is always at least one input.
+                let len_input: usize = rng.gen_range(1, (*max).max(2));
                 let len_output = (len_input / n + (len_input % n > 0) as usize) * n;
                 let inputs = vec![iter::repeat(Some(Input::Blip(BlipKind::A)))
                     .take(len_input)
@@ -142,7 +523,7 @@ impl Spec {
                 InputsOutputs { inputs, outputs }
             }
             Spec::MultiplyByN { n, max } => {
-                let len_input: usize = rng.gen_range(1, *max);
+                let len_input: usize = rng.gen_range(1, (*max).max(2));
 
                 let len_output = len_input * n;
                 let inputs = vec![iter::repeat(Some(Input::Blip(BlipKind::A)))
@@ -155,6 +536,10 @@ impl Spec {
 
                 InputsOutputs { inputs, outputs }
             }
+            Spec::Scripted { .. } => InputsOutputs {
+                inputs: vec![Vec::new(); self.input_dim()],
+                outputs: vec![Vec::new(); self.output_dim()],
+            },
         }
     }
 }
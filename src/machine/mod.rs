@@ -1,9 +1,21 @@
+pub mod analysis;
+pub mod diff;
+pub mod graph;
 pub mod grid;
 pub mod level;
-#[cfg(test)]
+pub mod registry;
+pub mod stats;
 pub mod string_util;
+pub mod template;
+pub mod text;
+pub mod validate;
+
+#[cfg(test)]
+pub(crate) mod tests;
 
+use std::collections::HashSet;
 use std::fmt;
+use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 
@@ -12,7 +24,7 @@ use crate::util::vec_option::VecOption;
 
 use grid::{Axis3, Dir3, DirMap3, Grid3, Point3, Sign, Vector3};
 
-pub use level::Level;
+pub use level::{Constraints, Level};
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum BlipKind {
@@ -48,7 +60,77 @@ impl fmt::Display for BlipKind {
 
 pub type TickNum = usize;
 
+/// Maximal number of grid positions in a machine. Machines are stored in a
+/// dense grid, so this bounds the memory of machines that come from files or
+/// from the network.
+pub const MAX_NUM_POSITIONS: isize = 1 << 22;
+
+/// Returns true if `size` can be used for a machine, i.e. all dimensions are
+/// positive and the grid is not too large.
+pub fn is_valid_size(size: &Vector3) -> bool {
+    size.x > 0
+        && size.y > 0
+        && size.z > 0
+        && size
+            .x
+            .checked_mul(size.y)
+            .and_then(|n| n.checked_mul(size.z))
+            .map_or(false, |n| n <= MAX_NUM_POSITIONS)
+}
+
+/// Types of blocks, disregarding the parameters of each block.
+///
+/// All of the pipe variants share the same type.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug, Hash, Serialize, Deserialize)]
+pub enum BlockType {
+    Pipe,
+    Funnel,
+    WindSource,
+    BlipSpawn,
+    BlipDuplicator,
+    BlipWindSource,
+    Solid,
+    Input,
+    Output,
+    Air,
+    DetectorBlipDuplicator,
+    PipeButton,
+    DetectorWindSource,
+    BlipDeleter,
+    Delay,
+    Scripted,
+}
+
+impl BlockType {
+    pub const ALL: [BlockType; 16] = [
+        BlockType::Pipe,
+        BlockType::Funnel,
+        BlockType::WindSource,
+        BlockType::BlipSpawn,
+        BlockType::BlipDuplicator,
+        BlockType::BlipWindSource,
+        BlockType::Solid,
+        BlockType::Input,
+        BlockType::Output,
+        BlockType::Air,
+        BlockType::DetectorBlipDuplicator,
+        BlockType::PipeButton,
+        BlockType::DetectorWindSource,
+        BlockType::BlipDeleter,
+        BlockType::Delay,
+        BlockType::Scripted,
+    ];
+
+    /// Returns a block of this type in its default orientation.
+    pub fn example(self) -> Block {
+        (registry::get(self).example)()
+    }
+}
+
 /// Definition of a block in the machine.
+///
+/// Blocks are purely declarative. State that changes while the machine is
+/// executed, e.g. the number of spawns left, is kept in `exec::BlockState`.
 #[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
 pub enum Block {
     Pipe(Dir3, Dir3),
@@ -99,6 +181,18 @@ pub enum Block {
     Delay {
         flow_dir: Dir3,
     },
+
+    /// A block whose behavior is defined by the level's script. When a blip
+    /// enters the block against `flow_dir`, the script function `name` is
+    /// called and may emit a blip in `flow_dir`.
+    Scripted {
+        name: String,
+        flow_dir: Dir3,
+
+        /// Name of a mesh in the render mesh registry to draw the block with.
+        #[serde(default)]
+        mesh: Option<String>,
+    },
 }
 
 impl Block {
@@ -162,6 +256,60 @@ impl Block {
             Block::DetectorWindSource { .. } => "Blip detector".to_string(),
             Block::BlipDeleter { .. } => "Blip deleter".to_string(),
             Block::Delay { .. } => "Delay".to_string(),
+            Block::Scripted { name, .. } => format!("Scripted block \"{}\"", name),
+        }
+    }
+
+    /// Returns an identifier for the variant of the block, which is used as
+    /// the key of the block's name and description in translations. Blocks
+    /// with the same key have the same name and description.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Block::Pipe(a, b) if a.0 != Axis3::Z && a.0 == b.0 => "pipe_straight",
+            Block::Pipe(a, b) if a.0 != Axis3::Z && b.0 != Axis3::Z && a.0 != b.0 => "pipe_curve",
+            Block::Pipe(a, b) if a.0 == Axis3::Z && a.0 == b.0 => "pipe_up_down",
+            Block::Pipe(a, b) if (*a == Dir3::Z_NEG || *b == Dir3::Z_NEG) && a.0 != b.0 => {
+                "pipe_curve_down"
+            }
+            Block::Pipe(a, b) if (*a == Dir3::Z_POS || *b == Dir3::Z_POS) && a.0 != b.0 => {
+                "pipe_curve_up"
+            }
+            Block::Pipe(_, _) => "pipe",
+            Block::PipeMergeXY => "pipe_crossing",
+            Block::GeneralPipe(dirs) => {
+                if grid::is_straight(dirs) {
+                    "general_pipe_straight"
+                } else {
+                    "general_pipe"
+                }
+            }
+            Block::FunnelXY { .. } => "funnel",
+            Block::WindSource => "wind_source",
+            Block::BlipSpawn {
+                num_spawns: None, ..
+            } => "blip_source",
+            Block::BlipSpawn {
+                num_spawns: Some(1),
+                ..
+            } => "blip_spawn_one",
+            Block::BlipSpawn {
+                num_spawns: Some(_),
+                ..
+            } => "blip_spawn",
+            Block::BlipDuplicator { kind: Some(_), .. } => "picky_copier",
+            Block::BlipDuplicator { kind: None, .. } => "copier",
+            Block::BlipWindSource { .. } => "wind_button",
+            Block::Solid => "solid",
+            Block::Input { .. } => "input",
+            Block::Output { .. } => "output",
+            Block::DetectorBlipDuplicator { kind: Some(_), .. } => "picky_detector_blip_copier",
+            Block::DetectorBlipDuplicator { kind: None, .. } => "detector_blip_copier",
+            Block::Air => "air",
+            Block::PipeButton { .. } => "pipe_button",
+            Block::DetectorWindSource { .. } => "blip_detector",
+            Block::BlipDeleter { .. } => "blip_deleter",
+            Block::Delay { .. } => "delay",
+            Block::Scripted { .. } => "scripted",
         }
     }
 
@@ -199,9 +347,40 @@ impl Block {
             Block::DetectorWindSource { .. } => "Spawns one thrust of wind if it detects a blip in itself.",
             Block::BlipDeleter { .. } => "Destroys blips that are in its way, if activated.",
             Block::Delay { .. } => "Delays blip movement by one tick.",
+            Block::Scripted { .. } => "Transforms blips as defined by the level's script.",
         }
     }
 
+    pub fn block_type(&self) -> BlockType {
+        match self {
+            Block::Pipe(_, _) => BlockType::Pipe,
+            Block::PipeMergeXY => BlockType::Pipe,
+            Block::GeneralPipe(_) => BlockType::Pipe,
+            Block::FunnelXY { .. } => BlockType::Funnel,
+            Block::WindSource => BlockType::WindSource,
+            Block::BlipSpawn { .. } => BlockType::BlipSpawn,
+            Block::BlipDuplicator { .. } => BlockType::BlipDuplicator,
+            Block::BlipWindSource { .. } => BlockType::BlipWindSource,
+            Block::Solid => BlockType::Solid,
+            Block::Input { .. } => BlockType::Input,
+            Block::Output { .. } => BlockType::Output,
+            Block::Air => BlockType::Air,
+            Block::DetectorBlipDuplicator { .. } => BlockType::DetectorBlipDuplicator,
+            Block::PipeButton { .. } => BlockType::PipeButton,
+            Block::DetectorWindSource { .. } => BlockType::DetectorWindSource,
+            Block::BlipDeleter { .. } => BlockType::BlipDeleter,
+            Block::Delay { .. } => BlockType::Delay,
+            Block::Scripted { .. } => BlockType::Scripted,
+        }
+    }
+
+    /// Returns true if the block can be placed by the player. Inputs and
+    /// outputs are given by the level, and air blocks are only inserted for
+    /// execution.
+    pub fn is_player_block(&self) -> bool {
+        registry::get(self.block_type()).is_player_block
+    }
+
     pub fn is_pipe(&self) -> bool {
         match self {
             Block::Pipe(_, _) => true,
@@ -289,6 +468,9 @@ impl Block {
             Block::Delay { flow_dir } => {
                 *flow_dir = f(*flow_dir);
             }
+            Block::Scripted { flow_dir, .. } => {
+                *flow_dir = f(*flow_dir);
+            }
         }
     }
 
@@ -316,6 +498,7 @@ impl Block {
             Block::DetectorWindSource { axis } => dir.0 == *axis,
             Block::BlipDeleter { out_dirs } => dir != out_dirs.0 && dir != out_dirs.1,
             Block::Delay { flow_dir } => dir == *flow_dir || dir == flow_dir.invert(),
+            Block::Scripted { flow_dir, .. } => dir == *flow_dir || dir == flow_dir.invert(),
         }
     }
 
@@ -400,6 +583,7 @@ impl Block {
             }
             Block::BlipDeleter { .. } => Some(BlipDieMode::PressButton),
             Block::Delay { .. } => Some(BlipDieMode::PressButton),
+            Block::Scripted { .. } => Some(BlipDieMode::PressButton),
             _ => None,
         }
     }
@@ -424,6 +608,7 @@ impl Block {
             Block::DetectorWindSource { .. } => true,
             Block::BlipDeleter { .. } => dir.is_some(),
             Block::Delay { flow_dir } => dir == Some(flow_dir.invert()),
+            Block::Scripted { flow_dir, .. } => dir == Some(flow_dir.invert()),
             _ => false,
         }
     }
@@ -439,6 +624,43 @@ impl Block {
         }
     }
 
+    /// Returns the block rotated in the XY plane such that its wind holes
+    /// match the holes of `other` as well as possible. Ties are broken in
+    /// favor of fewer rotations.
+    pub fn oriented_like(&self, other: &Block) -> Block {
+        let score = |block: &Block| {
+            Dir3::ALL
+                .iter()
+                .map(|dir| {
+                    match (
+                        block.has_wind_hole(*dir, false),
+                        other.has_wind_hole(*dir, false),
+                    ) {
+                        (true, true) => 2,
+                        (false, false) => 0,
+                        _ => -1,
+                    }
+                })
+                .sum::<isize>()
+        };
+
+        let mut rotated = self.clone();
+        let mut best = self.clone();
+        let mut best_score = score(self);
+
+        for _ in 0..3 {
+            rotated.mutate_dirs(|dir| dir.rotated_cw_xy());
+
+            let rotated_score = score(&rotated);
+            if rotated_score > best_score {
+                best = rotated.clone();
+                best_score = rotated_score;
+            }
+        }
+
+        best
+    }
+
     pub fn can_connect_by_pipe(&self, dir_out: Dir3) -> bool {
         let is_pipe = if let Block::GeneralPipe(_) = self {
             true
@@ -466,7 +688,10 @@ pub struct Blocks {
 
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct Machine {
-    pub blocks: Blocks,
+    /// The blocks are shared between copies of the machine until one of them
+    /// is modified. This way, cloning a machine is cheap, e.g. when starting
+    /// execution, while the editor's machine stays untouched.
+    pub blocks: Arc<Blocks>,
     pub level: Option<Level>,
 }
 
@@ -477,7 +702,7 @@ impl Machine {
         level: &Option<Level>,
     ) -> Self {
         let mut indices = Grid3::new(*size);
-        let mut data = VecOption::new();
+        let mut data = VecOption::with_capacity(slice.len());
 
         for (pos, placed_block) in slice {
             let mut placed_block = placed_block.clone();
@@ -489,31 +714,39 @@ impl Machine {
         let blocks = Blocks { indices, data };
 
         Machine {
-            blocks,
+            blocks: Arc::new(blocks),
             level: level.clone(),
         }
     }
 
     pub fn new_sandbox(size: Vector3) -> Self {
         Self {
-            blocks: Blocks {
+            blocks: Arc::new(Blocks {
                 indices: Grid3::new(size),
                 data: VecOption::new(),
-            },
+            }),
             level: None,
         }
     }
 
     pub fn new_from_level(level: Level) -> Self {
+        let size = level.size;
+        Self::new_from_level_with_size(level, size)
+    }
+
+    /// Creates a machine for the level whose grid has the given size, which
+    /// may be smaller than the level's bounds. The inputs and outputs are
+    /// placed at the sides of the grid.
+    pub fn new_from_level_with_size(level: Level, size: Vector3) -> Self {
         let mut machine = Self {
-            blocks: Blocks {
-                indices: Grid3::new(level.size),
+            blocks: Arc::new(Blocks {
+                indices: Grid3::new(size),
                 data: VecOption::new(),
-            },
+            }),
             level: Some(level.clone()),
         };
 
-        let input_y_start = level.size.y / 2 + level.spec.input_dim() as isize / 2;
+        let input_y_start = size.y / 2 + level.spec.input_dim() as isize / 2;
 
         for index in 0..level.spec.input_dim() {
             machine.set(
@@ -527,11 +760,11 @@ impl Machine {
             );
         }
 
-        let output_y_start = level.size.y / 2 + level.spec.output_dim() as isize / 2;
+        let output_y_start = size.y / 2 + level.spec.output_dim() as isize / 2;
 
         for index in 0..level.spec.output_dim() {
             machine.set(
-                &Point3::new(level.size.x - 1, output_y_start - index as isize, 0),
+                &Point3::new(size.x - 1, output_y_start - index as isize, 0),
                 Some(PlacedBlock {
                     block: Block::Output {
                         in_dir: Dir3::X_NEG,
@@ -569,11 +802,20 @@ impl Machine {
     }
 
     pub fn get_mut(&mut self, p: &Point3) -> Option<&mut PlacedBlock> {
-        self.blocks
-            .indices
-            .get(p)
-            .and_then(|id| *id)
-            .map(move |id| &mut self.blocks.data[id].1)
+        let id = self.get_index(p)?;
+
+        Some(&mut self.blocks_mut().data[id].1)
+    }
+
+    /// Returns the blocks for modification, copying them first if they are
+    /// shared with another machine.
+    pub fn blocks_mut(&mut self) -> &mut Blocks {
+        Arc::make_mut(&mut self.blocks)
+    }
+
+    /// Returns true if the blocks are shared with a copy of the machine.
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.blocks) > 1
     }
 
     pub fn get_index(&self, p: &Point3) -> Option<BlockIndex> {
@@ -598,15 +840,17 @@ impl Machine {
         self.remove(p);
 
         if let Some(block) = block {
-            let id = self.blocks.data.add((*p, block));
-            self.blocks.indices[*p] = Some(id);
+            let blocks = self.blocks_mut();
+            let id = blocks.data.add((*p, block));
+            blocks.indices[*p] = Some(id);
         }
     }
 
     pub fn remove(&mut self, p: &Point3) -> Option<(BlockIndex, PlacedBlock)> {
         if let Some(Some(id)) = self.blocks.indices.get(p).cloned() {
-            self.blocks.indices[*p] = None;
-            self.blocks.data.remove(id).map(|(data_pos, block)| {
+            let blocks = self.blocks_mut();
+            blocks.indices[*p] = None;
+            blocks.data.remove(id).map(|(data_pos, block)| {
                 assert!(data_pos == *p);
                 (id, block)
             })
@@ -619,11 +863,46 @@ impl Machine {
         self.blocks.data.iter()
     }
 
+    /// Iterates over the blocks in the box between `min` and `max`, both
+    /// inclusive.
+    pub fn iter_blocks_in_region(
+        &self,
+        min: &Point3,
+        max: &Point3,
+    ) -> impl Iterator<Item = (BlockIndex, &(Point3, PlacedBlock))> {
+        self.blocks
+            .indices
+            .iter_region(min, max)
+            .filter_map(move |(_, index)| index.map(|index| (index, &self.blocks.data[index])))
+    }
+
+    /// Iterates over the blocks that have been placed by the player.
+    pub fn iter_player_blocks(&self) -> impl Iterator<Item = (BlockIndex, &(Point3, PlacedBlock))> {
+        self.iter_blocks()
+            .filter(|(_, (_, placed_block))| placed_block.block.is_player_block())
+    }
+
+    pub fn num_player_blocks(&self) -> usize {
+        self.iter_player_blocks().count()
+    }
+
+    /// Returns the volume of the bounding box around the blocks placed by the
+    /// player.
+    pub fn footprint(&self) -> usize {
+        bounding_volume(self.iter_player_blocks().map(|(_, (pos, _))| *pos))
+    }
+
     pub fn gc(&mut self) {
-        self.blocks.data.gc();
+        // Avoid copying shared blocks if there is nothing to do.
+        if self.is_contiguous() {
+            return;
+        }
 
-        for (index, (grid_pos, _)) in self.blocks.data.iter() {
-            self.blocks.indices[*grid_pos] = Some(index);
+        let blocks = self.blocks_mut();
+        blocks.data.gc();
+
+        for (index, (grid_pos, _)) in blocks.data.iter() {
+            blocks.indices[*grid_pos] = Some(index);
         }
     }
 
@@ -634,6 +913,49 @@ impl Machine {
     pub fn num_blocks(&self) -> usize {
         self.blocks.data.len()
     }
+
+    /// Returns the free positions that are filled with air blocks for
+    /// execution, so that blips can fall down from holes.
+    pub fn air_positions(&self) -> HashSet<Point3> {
+        self.iter_blocks()
+            .flat_map(|(_, (pos, block))| {
+                Dir3::ALL.iter().flat_map(move |dir| {
+                    let mut result = Vec::new();
+
+                    let build_air = (block.block.has_wind_hole_out(*dir, false)
+                        && block.block.has_move_hole(*dir, false))
+                        || block.block.has_blip_spawn(*dir);
+
+                    if build_air {
+                        let mut iter_pos = pos + dir.to_vector();
+
+                        while self.is_valid_pos(&iter_pos) && !self.is_block_at(&iter_pos) {
+                            result.push(iter_pos);
+                            iter_pos.z -= 1;
+                        }
+                    }
+
+                    result
+                })
+            })
+            .collect()
+    }
+}
+
+/// Returns the volume of the bounding box around the given positions.
+pub fn bounding_volume(positions: impl Iterator<Item = Point3>) -> usize {
+    let mut positions = positions.map(|pos| pos.coords);
+
+    if let Some(first) = positions.next() {
+        let (min, max) = positions.fold((first, first), |(min, max), pos| {
+            (min.zip_map(&pos, isize::min), max.zip_map(&pos, isize::max))
+        });
+        let size = max - min + Vector3::new(1, 1, 1);
+
+        (size.x * size.y * size.z) as usize
+    } else {
+        0
+    }
 }
 
 /// Stores only the data necessary for restoring a machine.
@@ -660,6 +982,34 @@ impl SavedMachine {
         }
     }
 
+    /// Checks that the machine can be restored with `into_machine`, which
+    /// panics for invalid sizes or blocks outside of the grid. Machines that
+    /// do not come from us, e.g. shared solutions, need to be checked first.
+    pub fn check(&self) -> Result<(), String> {
+        if !is_valid_size(&self.size) {
+            return Err(format!("invalid size {:?}", self.size));
+        }
+
+        let mut positions = HashSet::new();
+        for (pos, _) in &self.block_data {
+            let in_grid = pos.x >= 0
+                && pos.x < self.size.x
+                && pos.y >= 0
+                && pos.y < self.size.y
+                && pos.z >= 0
+                && pos.z < self.size.z;
+
+            if !in_grid {
+                return Err(format!("block at {:?} is outside of the grid", pos));
+            }
+            if !positions.insert(*pos) {
+                return Err(format!("more than one block at {:?}", pos));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn into_machine(self) -> Machine {
         // TODO: Make use of moving
         Machine::new_from_block_data(&self.size, &self.block_data, &self.level)
@@ -0,0 +1,321 @@
+//! Registry of block types.
+//!
+//! Everything that is defined once per block type is bundled in a
+//! `BlockDef`: its name and description, the palette category it is listed
+//! in, an example block, an optional mesh to render it with, and its
+//! behavior during execution. The parameters of a block, e.g. its
+//! orientation, are still given by its `Block` variant, which also defines
+//! its hole layout.
+//!
+//! Adding a block type means adding a variant to `Block` and `BlockType`,
+//! and an entry to `DEFS` here.
+
+use crate::exec::behavior::{self, Behavior};
+use crate::machine::grid::{Axis3, Dir3, DirMap3};
+use crate::machine::{BlipKind, Block, BlockType};
+
+/// Groups of block types in the editor's palette.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Hash)]
+pub enum Category {
+    /// Blocks that conduct wind and blips.
+    Pipes,
+
+    /// Blocks that produce wind.
+    Wind,
+
+    /// Blocks that produce, copy or remove blips.
+    Blips,
+
+    /// Blocks that are given by the level or inserted for execution, which
+    /// the player can not place.
+    Level,
+
+    Experimental,
+}
+
+impl Category {
+    pub const ALL: [Category; 5] = [
+        Category::Pipes,
+        Category::Wind,
+        Category::Blips,
+        Category::Level,
+        Category::Experimental,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Pipes => "Pipes",
+            Category::Wind => "Wind",
+            Category::Blips => "Blips",
+            Category::Level => "Level",
+            Category::Experimental => "Experimental",
+        }
+    }
+}
+
+pub struct BlockDef {
+    pub block_type: BlockType,
+
+    /// Identifier of the block type, used e.g. in translation keys.
+    pub key: &'static str,
+
+    pub name: &'static str,
+    pub description: &'static str,
+
+    /// Hint on how to use the block, shown in the encyclopedia.
+    pub usage: &'static str,
+
+    pub category: Category,
+
+    /// Whether the block can be placed by the player. Inputs and outputs are
+    /// given by the level, and air blocks are only inserted for execution.
+    pub is_player_block: bool,
+
+    /// Name of a mesh in the render mesh registry. If given, the block is
+    /// drawn with this mesh instead of the builtin rendering.
+    pub mesh: Option<&'static str>,
+
+    /// Returns a block of this type in its default orientation.
+    pub example: fn() -> Block,
+
+    pub behavior: &'static dyn Behavior,
+}
+
+/// Definitions of all block types, in the order of `BlockType::ALL`.
+static DEFS: [BlockDef; 16] = [
+    BlockDef {
+        block_type: BlockType::Pipe,
+        key: "pipe",
+        name: "Pipe",
+        description: "Conducts both wind and blips.",
+        usage: "Connect a wind source to a blip spawn with pipes to move blips along.",
+        category: Category::Pipes,
+        is_player_block: true,
+        mesh: None,
+        example: || Block::GeneralPipe(DirMap3::from_fn(|dir| dir.0 == Axis3::X)),
+        behavior: &behavior::Passive,
+    },
+    BlockDef {
+        block_type: BlockType::Funnel,
+        key: "funnel",
+        name: "Funnel",
+        description: "Conducts in only one direction.",
+        usage: "Use funnels to let wind and blips pass in only one direction.",
+        category: Category::Pipes,
+        is_player_block: true,
+        mesh: None,
+        example: || Block::FunnelXY {
+            flow_dir: Dir3::X_POS,
+        },
+        behavior: &behavior::Passive,
+    },
+    BlockDef {
+        block_type: BlockType::WindSource,
+        key: "wind_source",
+        name: "Wind source",
+        description: "Produces a stream of wind in all directions.",
+        usage: "Place next to pipes to keep blips moving continuously.",
+        category: Category::Wind,
+        is_player_block: true,
+        mesh: None,
+        example: || Block::WindSource,
+        behavior: &behavior::WindSource,
+    },
+    BlockDef {
+        block_type: BlockType::BlipSpawn,
+        key: "blip_source",
+        name: "Blip source",
+        description: "Produces a stream of blips.",
+        usage: "Produces a steady stream of blips when its output is free.",
+        category: Category::Blips,
+        is_player_block: true,
+        mesh: None,
+        example: || Block::BlipSpawn {
+            out_dir: Dir3::X_POS,
+            kind: BlipKind::A,
+            num_spawns: None,
+        },
+        behavior: &behavior::BlipSpawn,
+    },
+    BlockDef {
+        block_type: BlockType::BlipDuplicator,
+        key: "copier",
+        name: "Copier",
+        description: "Produces two copies of whatever blip activates it.",
+        usage: "Send a blip into a copier to split it into two blips leaving on either side.",
+        category: Category::Blips,
+        is_player_block: true,
+        mesh: None,
+        example: || Block::BlipDuplicator {
+            out_dirs: (Dir3::X_NEG, Dir3::X_POS),
+            kind: None,
+        },
+        behavior: &behavior::BlipDuplicator,
+    },
+    BlockDef {
+        block_type: BlockType::BlipWindSource,
+        key: "wind_button",
+        name: "Wind button",
+        description: "Spawns one thrust of wind when activated by a blip.",
+        usage: "Let a blip press the button to release a single thrust of wind, e.g. for counters.",
+        category: Category::Wind,
+        is_player_block: true,
+        mesh: None,
+        example: || Block::BlipWindSource {
+            button_dir: Dir3::Y_NEG,
+        },
+        behavior: &behavior::BlipWindSource,
+    },
+    BlockDef {
+        block_type: BlockType::Solid,
+        key: "solid",
+        name: "Solid",
+        description: "Prevents blip movement.",
+        usage: "Block off blips and wind, e.g. to build walls.",
+        category: Category::Pipes,
+        is_player_block: true,
+        mesh: None,
+        example: || Block::Solid,
+        behavior: &behavior::Passive,
+    },
+    BlockDef {
+        block_type: BlockType::Input,
+        key: "input",
+        name: "Input",
+        description: "Input of the machine.",
+        usage: "Inputs are given by the level and feed the blips to process.",
+        category: Category::Level,
+        is_player_block: false,
+        mesh: None,
+        example: || Block::Input {
+            out_dir: Dir3::X_POS,
+            index: 0,
+        },
+        behavior: &behavior::Input,
+    },
+    BlockDef {
+        block_type: BlockType::Output,
+        key: "output",
+        name: "Output",
+        description: "Output of the machine.",
+        usage: "Outputs are given by the level and check the blips they receive.",
+        category: Category::Level,
+        is_player_block: false,
+        mesh: None,
+        example: || Block::Output {
+            in_dir: Dir3::X_NEG,
+            index: 0,
+        },
+        behavior: &behavior::Passive,
+    },
+    BlockDef {
+        block_type: BlockType::Air,
+        key: "air",
+        name: "Air",
+        description: "Allows blips to fall freely.",
+        usage: "Air is filled in automatically when execution starts.",
+        category: Category::Level,
+        is_player_block: false,
+        mesh: None,
+        example: || Block::Air,
+        behavior: &behavior::Air,
+    },
+    BlockDef {
+        block_type: BlockType::DetectorBlipDuplicator,
+        key: "detector_blip_copier",
+        name: "Detector blip copier",
+        description: "Copies blips that pass through it.",
+        usage: "Copies blips that pass through it.",
+        category: Category::Experimental,
+        is_player_block: true,
+        mesh: None,
+        example: || Block::DetectorBlipDuplicator {
+            out_dir: Dir3::X_NEG,
+            flow_axis: Axis3::Y,
+            kind: None,
+        },
+        behavior: &behavior::DetectorBlipDuplicator,
+    },
+    BlockDef {
+        block_type: BlockType::PipeButton,
+        key: "pipe_button",
+        name: "Pipe button",
+        description: "Conducts wind and blips only if none of the buttons is pressed.",
+        usage: "Block a pipe while a blip rests on one of its buttons.",
+        category: Category::Pipes,
+        is_player_block: true,
+        mesh: None,
+        example: || Block::PipeButton { axis: Axis3::X },
+        behavior: &behavior::PipeButton,
+    },
+    BlockDef {
+        block_type: BlockType::DetectorWindSource,
+        key: "blip_detector",
+        name: "Blip detector",
+        description: "Spawns one thrust of wind if it detects a blip in itself.",
+        usage: "Place in a blip's path to trigger wind elsewhere whenever it passes.",
+        category: Category::Wind,
+        is_player_block: true,
+        mesh: None,
+        example: || Block::DetectorWindSource { axis: Axis3::X },
+        behavior: &behavior::DetectorWindSource,
+    },
+    BlockDef {
+        block_type: BlockType::BlipDeleter,
+        key: "blip_deleter",
+        name: "Blip deleter",
+        description: "Destroys blips that are in its way, if activated.",
+        usage: "Remove unwanted blips when activated by wind.",
+        category: Category::Blips,
+        is_player_block: true,
+        mesh: None,
+        example: || Block::BlipDeleter {
+            out_dirs: (Dir3::X_NEG, Dir3::X_POS),
+        },
+        behavior: &behavior::BlipDeleter,
+    },
+    BlockDef {
+        block_type: BlockType::Delay,
+        key: "delay",
+        name: "Delay",
+        description: "Delays blip movement by one tick.",
+        usage: "Hold blips back by one tick to synchronize paths.",
+        category: Category::Pipes,
+        is_player_block: true,
+        mesh: None,
+        example: || Block::Delay {
+            flow_dir: Dir3::X_POS,
+        },
+        behavior: &behavior::Delay,
+    },
+    BlockDef {
+        block_type: BlockType::Scripted,
+        key: "scripted",
+        name: "Scripted block",
+        description: "Transforms blips as defined by the level's script.",
+        usage: "Levels with scripts may provide blocks with custom behavior.",
+        category: Category::Experimental,
+        is_player_block: true,
+        mesh: None,
+        example: || Block::Scripted {
+            name: String::new(),
+            flow_dir: Dir3::X_POS,
+            mesh: None,
+        },
+        behavior: &behavior::Scripted,
+    },
+];
+
+/// Returns the definition of a block type.
+pub fn get(block_type: BlockType) -> &'static BlockDef {
+    let def = &DEFS[block_type as usize];
+    debug_assert!(def.block_type == block_type);
+
+    def
+}
+
+/// Returns the definitions of all block types, in the order of
+/// `BlockType::ALL`.
+pub fn all() -> &'static [BlockDef] {
+    &DEFS
+}
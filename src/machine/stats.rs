@@ -0,0 +1,87 @@
+//! Aggregate numbers about machines, shown to the player while editing.
+
+use std::collections::BTreeMap;
+
+use crate::machine::{Block, BlockType, Machine};
+
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Stats {
+    /// Number of blocks placed by the player for each type that occurs in the
+    /// machine, sorted by type.
+    pub blocks_by_type: Vec<(BlockType, usize)>,
+
+    /// Number of blocks placed by the player.
+    pub num_blocks: usize,
+
+    /// Volume of the bounding box around the blocks placed by the player.
+    pub footprint: usize,
+
+    /// Number of blocks placed by the player in each layer, starting at the
+    /// bottom.
+    pub blocks_per_layer: Vec<usize>,
+
+    /// Number of cells in each layer.
+    pub layer_area: usize,
+
+    pub num_inputs: usize,
+    pub num_outputs: usize,
+
+    /// Rough cost of simulating one tick. This is the number of blocks that
+    /// execution iterates over, including the air blocks that are inserted
+    /// below holes.
+    pub tick_cost: usize,
+}
+
+impl Stats {
+    /// Returns the fraction of cells in `layer` that are occupied by blocks of
+    /// the player.
+    pub fn layer_utilization(&self, layer: usize) -> f32 {
+        if self.layer_area == 0 {
+            return 0.0;
+        }
+
+        self.blocks_per_layer.get(layer).copied().unwrap_or(0) as f32 / self.layer_area as f32
+    }
+}
+
+impl Machine {
+    pub fn stats(&self) -> Stats {
+        let size = self.size();
+
+        let mut blocks_by_type = BTreeMap::new();
+        let mut blocks_per_layer = vec![0; size.z.max(0) as usize];
+        let mut num_inputs = 0;
+        let mut num_outputs = 0;
+
+        for (_, (pos, placed_block)) in self.iter_blocks() {
+            match placed_block.block {
+                Block::Input { .. } => num_inputs += 1,
+                Block::Output { .. } => num_outputs += 1,
+                _ => (),
+            }
+
+            if !placed_block.block.is_player_block() {
+                continue;
+            }
+
+            *blocks_by_type
+                .entry(placed_block.block.block_type())
+                .or_insert(0) += 1;
+
+            if let Some(count) = blocks_per_layer.get_mut(pos.z as usize) {
+                *count += 1;
+            }
+        }
+
+        Stats {
+            blocks_by_type: blocks_by_type.into_iter().collect(),
+            num_blocks: self.num_player_blocks(),
+            footprint: self.footprint(),
+            blocks_per_layer,
+            layer_area: (size.x.max(0) * size.y.max(0)) as usize,
+            num_inputs,
+            num_outputs,
+            tick_cost: self.num_blocks() + self.air_positions().len(),
+        }
+    }
+}
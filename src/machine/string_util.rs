@@ -1,5 +1,5 @@
-use crate::machine::grid::{Dir3, Point3};
-use crate::machine::{BlipKind, Block};
+use crate::machine::grid::{Dir3, Point3, Vector3};
+use crate::machine::{BlipKind, Block, Machine, PlacedBlock};
 
 pub fn blocks_from_string(s: &str) -> Vec<(Point3, Block)> {
     s.lines()
@@ -13,11 +13,32 @@ pub fn blocks_from_string(s: &str) -> Vec<(Point3, Block)> {
         .collect()
 }
 
+/// Builds a machine that is just large enough to contain the blocks given in
+/// the string.
+pub fn machine_from_string(s: &str) -> Machine {
+    let blocks: Vec<_> = blocks_from_string(s)
+        .into_iter()
+        .map(|(pos, block)| (pos, PlacedBlock { block }))
+        .collect();
+
+    let max_pos = blocks.iter().fold(Point3::origin(), |max, (pos, _)| {
+        Point3::new(max.x.max(pos.x), max.y.max(pos.y), max.z.max(pos.z))
+    });
+    let size = max_pos.coords + Vector3::new(1, 1, 1);
+
+    Machine::new_from_block_data(&size, &blocks, &None)
+}
+
 pub fn block_from_char(c: char) -> Option<Block> {
     if c == '.' || c == ' ' {
         return None;
     }
 
+    Some(known_block_from_char(c).unwrap_or_else(|| panic!("No block for {}", c)))
+}
+
+/// Returns the block drawn by `c`, or `None` if there is no block for `c`.
+pub fn known_block_from_char(c: char) -> Option<Block> {
     let block = match c {
         '-' => Block::Pipe(Dir3::X_NEG, Dir3::X_POS),
         '|' => Block::Pipe(Dir3::Y_NEG, Dir3::Y_POS),
@@ -109,7 +130,7 @@ pub fn block_from_char(c: char) -> Option<Block> {
 
         '☐' => Block::Solid,
 
-        _ => panic!("No block for {}", c),
+        _ => return None,
     };
 
     Some(block)
@@ -0,0 +1,126 @@
+//! Starting points for new machines, which the player can choose from when
+//! creating a machine in the menu.
+
+use log::warn;
+
+use crate::machine::grid::{Point3, Vector3};
+use crate::machine::level::Level;
+use crate::machine::string_util::blocks_from_string;
+use crate::machine::{Block, Machine, PlacedBlock};
+
+/// A small machine in which blips are carried by wind into a duplicator. The
+/// spawn at the bottom left emits into the pipe above it, and the pipe at the
+/// top right turns down into the duplicator, which sends copies to both sides.
+const DEMO: &str = "◉-------┐\n ┻     -┿-\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Template {
+    /// Nothing but the grid.
+    Empty,
+
+    /// Solid blocks around the border of the bottom layer.
+    Walls,
+
+    /// A small working machine in the center of the bottom layer.
+    Demo,
+}
+
+impl Template {
+    pub const ALL: [Template; 3] = [Template::Empty, Template::Walls, Template::Demo];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Template::Empty => "Empty",
+            Template::Walls => "Walls",
+            Template::Demo => "Demo machine",
+        }
+    }
+
+    /// Creates a machine from the template, with the size given by
+    /// `machine_size`. If a level is given, the machine gets the level's
+    /// inputs and outputs, placed according to the machine's size. The level
+    /// itself is kept as it is, so that the machine counts as a solution.
+    pub fn new_machine(self, size: Vector3, level: Option<Level>) -> Machine {
+        let size = machine_size(size, level.as_ref());
+
+        let mut machine = match level {
+            Some(level) => Machine::new_from_level_with_size(level, size),
+            None => Machine::new_sandbox(size),
+        };
+
+        let blocks: Vec<(Point3, Block)> = match self {
+            Template::Empty => Vec::new(),
+            Template::Walls => border_positions(&size)
+                .into_iter()
+                .map(|pos| (pos, Block::Solid))
+                .collect(),
+            Template::Demo => {
+                let blocks = blocks_from_string(DEMO);
+                let demo_size = blocks.iter().fold(Vector3::zeros(), |demo_size, (pos, _)| {
+                    demo_size.zip_map(&(pos.coords + Vector3::new(1, 1, 1)), isize::max)
+                });
+
+                if demo_size.x > size.x || demo_size.y > size.y {
+                    warn!(
+                        "Machine of size {:?} is too small for the demo, leaving it empty",
+                        size
+                    );
+                    return machine;
+                }
+
+                let offset =
+                    Vector3::new((size.x - demo_size.x) / 2, (size.y - demo_size.y) / 2, 0);
+                blocks
+                    .into_iter()
+                    .map(|(pos, block)| (pos + offset, block))
+                    .collect()
+            }
+        };
+
+        // Blocks of the level take precedence over those of the template.
+        for (pos, block) in blocks {
+            if machine.is_valid_pos(&pos) && machine.get(&pos).is_none() {
+                machine.set(&pos, Some(PlacedBlock { block }));
+            }
+        }
+
+        machine
+    }
+}
+
+/// Returns the smallest size that a new machine can have, such that there is
+/// room for the inputs and outputs of the level, if any.
+pub fn min_size(level: Option<&Level>) -> Vector3 {
+    let dim = level.map_or(0, |level| {
+        level.spec.input_dim().max(level.spec.output_dim()) as isize
+    });
+
+    Vector3::new(3, (dim + 2).max(3), 1)
+}
+
+/// Returns the size of a new machine that the player asked for with `size`.
+/// The machine has room for the inputs and outputs of the level, if any, and
+/// does not exceed the level's bounds.
+pub fn machine_size(size: Vector3, level: Option<&Level>) -> Vector3 {
+    let size = match level {
+        Some(level) => size.zip_map(&level.size, isize::min),
+        None => size,
+    };
+
+    size.zip_map(&min_size(level), isize::max)
+}
+
+/// Returns the positions on the border of the bottom layer.
+fn border_positions(size: &Vector3) -> Vec<Point3> {
+    let mut positions = Vec::new();
+
+    for x in 0..size.x {
+        for y in 0..size.y {
+            if x == 0 || y == 0 || x == size.x - 1 || y == size.y - 1 {
+                positions.push(Point3::new(x, y, 0));
+            }
+        }
+    }
+
+    positions
+}
@@ -0,0 +1,768 @@
+use std::collections::{HashMap, HashSet};
+
+use proptest::collection::{btree_map, vec};
+use proptest::option;
+use proptest::prelude::*;
+use proptest::sample::select;
+
+use crate::content::{self, Content};
+use crate::machine::diff::{merge, ChangeKind};
+use crate::machine::graph::{EdgeKind, Graph};
+use crate::machine::grid::{self, Axis3, Dir3, DirMap3, Grid3, Point3, Vector3};
+use crate::machine::level::{Constraints, Level, Region, Spec};
+use crate::machine::registry;
+use crate::machine::string_util::machine_from_string;
+use crate::machine::template::Template;
+use crate::machine::text;
+use crate::machine::validate::{ProblemKind, Severity};
+use crate::machine::{BlipKind, Block, BlockType, Machine, PlacedBlock, SavedMachine};
+
+fn arb_blip_kind() -> impl Strategy<Value = BlipKind> {
+    prop_oneof![Just(BlipKind::A), Just(BlipKind::B)]
+}
+
+fn arb_axis() -> impl Strategy<Value = Axis3> {
+    select(Axis3::ALL.to_vec())
+}
+
+fn arb_dir() -> impl Strategy<Value = Dir3> {
+    select(Dir3::ALL.to_vec())
+}
+
+fn arb_name() -> impl Strategy<Value = String> {
+    "[a-z_]{1,8}"
+}
+
+fn arb_point(size: Vector3) -> impl Strategy<Value = Point3> {
+    (0..size.x, 0..size.y, 0..size.z).prop_map(|(x, y, z)| Point3::new(x, y, z))
+}
+
+/// Generates blocks that are not deprecated, i.e. that are kept as they are
+/// when creating a machine.
+fn arb_block() -> impl Strategy<Value = Block> {
+    let simple = prop_oneof![
+        any::<[bool; 6]>().prop_map(|holes| Block::GeneralPipe(DirMap3(holes))),
+        arb_dir().prop_map(|flow_dir| Block::FunnelXY { flow_dir }),
+        Just(Block::WindSource),
+        Just(Block::Solid),
+        Just(Block::Air),
+        arb_dir().prop_map(|button_dir| Block::BlipWindSource { button_dir }),
+        arb_axis().prop_map(|axis| Block::PipeButton { axis }),
+        arb_axis().prop_map(|axis| Block::DetectorWindSource { axis }),
+        arb_dir().prop_map(|flow_dir| Block::Delay { flow_dir }),
+    ];
+
+    let configured = prop_oneof![
+        (arb_dir(), arb_blip_kind(), option::of(0..10usize)).prop_map(
+            |(out_dir, kind, num_spawns)| Block::BlipSpawn {
+                out_dir,
+                kind,
+                num_spawns,
+            }
+        ),
+        (arb_dir(), arb_dir(), option::of(arb_blip_kind())).prop_map(|(a, b, kind)| {
+            Block::BlipDuplicator {
+                out_dirs: (a, b),
+                kind,
+            }
+        }),
+        (arb_dir(), arb_axis(), option::of(arb_blip_kind())).prop_map(
+            |(out_dir, flow_axis, kind)| Block::DetectorBlipDuplicator {
+                out_dir,
+                flow_axis,
+                kind,
+            }
+        ),
+        (arb_dir(), arb_dir()).prop_map(|(a, b)| Block::BlipDeleter { out_dirs: (a, b) }),
+        (arb_dir(), 0..4usize).prop_map(|(out_dir, index)| Block::Input { out_dir, index }),
+        (arb_dir(), 0..4usize).prop_map(|(in_dir, index)| Block::Output { in_dir, index }),
+        (arb_name(), arb_dir(), option::of(arb_name())).prop_map(|(name, flow_dir, mesh)| {
+            Block::Scripted {
+                name,
+                flow_dir,
+                mesh,
+            }
+        }),
+    ];
+
+    prop_oneof![simple, configured]
+}
+
+fn arb_spec() -> impl Strategy<Value = Spec> {
+    prop_oneof![
+        (1..4usize).prop_map(|dim| Spec::Id { dim }),
+        vec(arb_blip_kind(), 1..6).prop_map(|pattern| Spec::Clock { pattern }),
+        Just(Spec::BitwiseMax),
+        (1..5usize, 1..20usize).prop_map(|(n, max)| Spec::MakeItN { n, max }),
+        (1..5usize, 1..20usize).prop_map(|(n, max)| Spec::MultiplyByN { n, max }),
+        (arb_name(), ".{0,20}", 0..3usize, 0..3usize).prop_map(
+            |(name, description, input_dim, output_dim)| Spec::Scripted {
+                name,
+                description,
+                input_dim,
+                output_dim,
+            }
+        ),
+    ]
+}
+
+fn arb_constraints(size: Vector3) -> impl Strategy<Value = Constraints> {
+    let region = (arb_point(size), arb_point(size)).prop_map(|(min, max)| Region { min, max });
+
+    (
+        option::of(vec(select(BlockType::ALL.to_vec()), 0..4)),
+        option::of(0..100usize),
+        option::of(0..100usize),
+        vec(region, 0..3),
+    )
+        .prop_map(
+            |(allowed_blocks, max_num_blocks, max_footprint, forbidden_regions)| Constraints {
+                allowed_blocks,
+                max_num_blocks,
+                max_footprint,
+                forbidden_regions,
+            },
+        )
+}
+
+fn arb_level(size: Vector3) -> impl Strategy<Value = Level> {
+    (
+        arb_spec(),
+        arb_constraints(size),
+        option::of(".{0,20}"),
+        option::of(arb_name()),
+    )
+        .prop_map(move |(spec, constraints, script, pack)| Level {
+            size,
+            spec,
+            constraints,
+            script,
+            pack,
+        })
+}
+
+/// Generates machines with blocks at distinct positions, optionally for a
+/// level.
+pub(crate) fn arb_machine() -> impl Strategy<Value = Machine> {
+    (1..8isize, 1..8isize, 1..4isize).prop_flat_map(|(x, y, z)| {
+        let size = Vector3::new(x, y, z);
+        let pos = arb_point(size).prop_map(|p| (p.x, p.y, p.z));
+
+        (
+            btree_map(pos, arb_block(), 0..32),
+            option::of(arb_level(size)),
+        )
+            .prop_map(move |(blocks, level)| {
+                let block_data: Vec<_> = blocks
+                    .into_iter()
+                    .map(|((x, y, z), block)| (Point3::new(x, y, z), PlacedBlock { block }))
+                    .collect();
+
+                Machine::new_from_block_data(&size, &block_data, &level)
+            })
+    })
+}
+
+/// The tests only compare machines through `PartialEq`, so that they also hold
+/// for other storage backends of `Blocks`.
+fn round_trip(machine: &Machine) -> Machine {
+    SavedMachine::from_machine(machine).into_machine()
+}
+
+proptest! {
+    #[test]
+    fn test_saved_machine_round_trip(machine in arb_machine()) {
+        prop_assert_eq!(round_trip(&machine), machine);
+    }
+
+    #[test]
+    fn test_saved_machine_serde_round_trip(machine in arb_machine()) {
+        let saved_machine = SavedMachine::from_machine(&machine);
+
+        let json = serde_json::to_string(&saved_machine).unwrap();
+        let loaded: SavedMachine = serde_json::from_str(&json).unwrap();
+
+        prop_assert_eq!(&loaded, &saved_machine);
+        prop_assert_eq!(loaded.check(), Ok(()));
+        prop_assert_eq!(loaded.into_machine(), machine);
+    }
+
+    /// Removing blocks leaves holes in the block data, which are not saved.
+    #[test]
+    fn test_saved_machine_round_trip_after_removal(
+        machine in arb_machine(),
+        removals in vec((0..8isize, 0..8isize, 0..4isize), 0..8),
+    ) {
+        let mut machine = machine;
+        for (x, y, z) in removals {
+            let pos = Point3::new(x, y, z);
+            if machine.is_valid_pos(&pos) {
+                machine.remove(&pos);
+            }
+        }
+
+        let loaded = round_trip(&machine);
+
+        machine.gc();
+        prop_assert_eq!(loaded, machine);
+    }
+
+    /// Rotating the blocks must not produce blocks that are changed when
+    /// loading the machine.
+    #[test]
+    fn test_saved_machine_round_trip_after_rotation(
+        machine in arb_machine(),
+        num_rotations in 0..4usize,
+    ) {
+        let mut machine = machine;
+        for (_, (_, placed_block)) in machine.blocks_mut().data.iter_mut() {
+            for _ in 0..num_rotations {
+                placed_block.block.mutate_dirs(Dir3::rotated_cw_xy);
+            }
+        }
+
+        prop_assert_eq!(round_trip(&machine), machine);
+    }
+
+    /// Checking blocks before setting them must agree with comparing the
+    /// machine before and after setting them.
+    #[test]
+    fn test_check_set_blocks(
+        machine in arb_machine(),
+        constraints in arb_constraints(Vector3::new(8, 8, 4)),
+        changes in vec(((0..9isize, 0..9isize, 0..5isize), option::of(arb_block())), 0..8),
+    ) {
+        let blocks: HashMap<_, _> = changes
+            .into_iter()
+            .map(|((x, y, z), block)| (Point3::new(x, y, z), block.map(|block| PlacedBlock { block })))
+            .collect();
+
+        let mut after = machine.clone();
+        for (pos, placed_block) in &blocks {
+            if after.is_valid_pos(pos) {
+                after.set(pos, placed_block.clone());
+            }
+        }
+
+        prop_assert_eq!(
+            constraints.check_set_blocks(&machine, &blocks).is_some(),
+            constraints.check_change(&machine, &after).is_some()
+        );
+    }
+}
+
+/// Test that saved machines which would panic when loaded are rejected.
+#[test]
+fn test_saved_machine_check() {
+    let block = |x, y, z| {
+        (
+            Point3::new(x, y, z),
+            PlacedBlock {
+                block: Block::Solid,
+            },
+        )
+    };
+    let saved_machine = |size, block_data| SavedMachine {
+        size,
+        block_data,
+        level: None,
+    };
+
+    assert!(saved_machine(Vector3::new(2, 2, 1), vec![block(1, 1, 0)])
+        .check()
+        .is_ok());
+
+    for &size in &[
+        Vector3::new(0, 2, 1),
+        Vector3::new(-2, 2, 1),
+        Vector3::new(1 << 12, 1 << 12, 1 << 12),
+        Vector3::new(std::isize::MAX, 2, 2),
+    ] {
+        assert!(saved_machine(size, vec![]).check().is_err());
+    }
+
+    for block_data in vec![
+        vec![block(2, 0, 0)],
+        vec![block(0, -1, 0)],
+        vec![block(0, 0, 0), block(0, 0, 0)],
+    ] {
+        assert!(saved_machine(Vector3::new(2, 2, 1), block_data)
+            .check()
+            .is_err());
+    }
+}
+
+#[test]
+fn test_validate_connected_machine() {
+    let m = "
+◉---┐
+ ┻  ⎴
+";
+
+    assert_eq!(machine_from_string(m).validate(), Vec::new());
+}
+
+#[test]
+fn test_validate_pipe_leads_nowhere() {
+    let m = "
+◉--
+ ┻
+";
+
+    let problems = machine_from_string(m).validate();
+
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].pos, Some(Point3::new(2, 0, 0)));
+    assert_eq!(
+        problems[0].kind,
+        ProblemKind::PipeLeadsNowhere { dir: Dir3::X_POS }
+    );
+    assert_eq!(problems[0].severity(), Severity::Warning);
+}
+
+#[test]
+fn test_validate_unconnected_hole() {
+    let m = "
+◉--▷
+";
+
+    let problems = machine_from_string(m).validate();
+
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].pos, Some(Point3::new(3, 0, 0)));
+    assert_eq!(
+        problems[0].kind,
+        ProblemKind::UnconnectedHole { dir: Dir3::X_POS }
+    );
+}
+
+#[test]
+fn test_stats() {
+    let m = "
+◉--▷
+";
+
+    let stats = machine_from_string(m).stats();
+
+    assert_eq!(stats.num_blocks, 4);
+    assert_eq!(stats.footprint, 4);
+    assert_eq!(
+        stats.blocks_by_type,
+        vec![
+            (BlockType::Pipe, 2),
+            (BlockType::Funnel, 1),
+            (BlockType::WindSource, 1),
+        ]
+    );
+    assert_eq!(stats.blocks_per_layer, vec![4]);
+    assert_eq!(stats.layer_utilization(0), 1.0);
+    assert_eq!(stats.num_inputs, 0);
+    assert_eq!(stats.tick_cost, 4);
+}
+
+#[test]
+fn test_flow_path() {
+    let mut machine = machine_from_string(
+        "
+┌-┐
+| |
+",
+    );
+    machine.set(
+        &Point3::new(0, 1, 0),
+        Some(PlacedBlock {
+            block: Block::Input {
+                out_dir: Dir3::Y_NEG,
+                index: 0,
+            },
+        }),
+    );
+    machine.set(
+        &Point3::new(2, 1, 0),
+        Some(PlacedBlock {
+            block: Block::Output {
+                in_dir: Dir3::Y_NEG,
+                index: 0,
+            },
+        }),
+    );
+
+    assert_eq!(
+        machine.flow_path(),
+        vec![
+            Point3::new(0, 1, 0),
+            Point3::new(0, 0, 0),
+            Point3::new(1, 0, 0),
+            Point3::new(2, 0, 0),
+            Point3::new(2, 1, 0),
+        ]
+    );
+
+    machine.remove(&Point3::new(1, 0, 0));
+    assert!(machine.flow_path().is_empty());
+}
+
+#[test]
+fn test_graph_from_machine() {
+    let m = "
+◉--
+";
+
+    let graph = Graph::from_machine(&machine_from_string(m));
+
+    assert_eq!(graph.nodes.len(), 3);
+
+    let edges: Vec<_> = graph
+        .edges
+        .iter()
+        .map(|edge| {
+            (
+                graph.nodes[edge.from].pos.x,
+                graph.nodes[edge.to].pos.x,
+                edge.kind,
+            )
+        })
+        .collect();
+
+    // Wind flows either way through pipes, but not into the source.
+    assert!(edges.contains(&(0, 1, EdgeKind::Wind)));
+    assert!(edges.contains(&(1, 2, EdgeKind::Wind)));
+    assert!(edges.contains(&(2, 1, EdgeKind::Wind)));
+    assert!(!edges.contains(&(1, 0, EdgeKind::Wind)));
+    assert!(edges.contains(&(1, 2, EdgeKind::Blip)));
+    assert!(edges.contains(&(2, 1, EdgeKind::Blip)));
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph machine {"));
+    assert!(dot.contains("0 -> 1 [color=blue];"));
+}
+
+#[test]
+fn test_reachable_from_inputs() {
+    let mut machine = machine_from_string(
+        "
+.-- -
+",
+    );
+    machine.set(
+        &Point3::new(0, 0, 0),
+        Some(PlacedBlock {
+            block: Block::Input {
+                out_dir: Dir3::X_POS,
+                index: 0,
+            },
+        }),
+    );
+
+    let reachable = machine.reachable_from_inputs();
+    let is_reachable = |x| reachable.contains(&machine.get_index(&Point3::new(x, 0, 0)).unwrap());
+
+    assert!(is_reachable(0));
+    assert!(is_reachable(1));
+    assert!(is_reachable(2));
+    assert!(!is_reachable(4));
+}
+
+#[test]
+fn test_blocks_without_wind() {
+    let machine = machine_from_string(
+        "
+◉-- -
+",
+    );
+
+    assert_eq!(
+        machine.blocks_without_wind(),
+        vec![machine.get_index(&Point3::new(4, 0, 0)).unwrap()]
+    );
+}
+
+#[test]
+fn test_blip_loops() {
+    let machine = machine_from_string(
+        "
+┌┐--
+└┘
+",
+    );
+
+    let ring: Vec<_> = [(0, 0), (1, 0), (0, 1), (1, 1)]
+        .iter()
+        .map(|&(x, y)| machine.get_index(&Point3::new(x, y, 0)).unwrap())
+        .collect();
+
+    // The straight pipes next to the ring do not form a loop of their own.
+    assert_eq!(machine.blip_loops(), vec![ring]);
+}
+
+#[test]
+fn test_template_new_machine() {
+    let level = Level {
+        size: Vector3::new(27, 27, 4),
+        spec: Spec::Id { dim: 3 },
+        constraints: Constraints::default(),
+        script: None,
+        pack: None,
+    };
+
+    // Too small for the level's inputs, so the machine is enlarged.
+    let machine = Template::Walls.new_machine(Vector3::new(2, 2, 1), Some(level.clone()));
+    assert_eq!(machine.size(), Vector3::new(3, 5, 1));
+    assert_eq!(machine.level.as_ref(), Some(&level));
+
+    // The inputs and outputs are part of the border, and are kept.
+    assert_eq!(machine.iter_blocks().count(), 12);
+    assert_eq!(
+        machine
+            .iter_blocks()
+            .filter(|(_, (_, placed_block))| placed_block.block == Block::Solid)
+            .count(),
+        6
+    );
+
+    // Larger than the level's bounds, so the machine is shrunk.
+    let machine = Template::Empty.new_machine(Vector3::new(60, 60, 40), Some(level.clone()));
+    assert_eq!(machine.size(), level.size);
+    assert_eq!(machine.level.as_ref(), Some(&level));
+    assert!(machine
+        .validate()
+        .iter()
+        .all(|problem| problem.kind != ProblemKind::OutOfBounds));
+
+    let machine = Template::Demo.new_machine(Vector3::new(20, 20, 2), None);
+    assert!(machine.level.is_none());
+    assert!(machine.blip_loops().is_empty());
+    assert!(machine.iter_blocks().count() > 0);
+
+    let machine = Template::Demo.new_machine(Vector3::new(4, 4, 1), None);
+    assert_eq!(machine.iter_blocks().count(), 0);
+}
+
+#[test]
+fn test_text_parse() {
+    let s = "
+# Blips go from the spawn to the right.
+size 4 2 1
+layer 0
+┷-▷
+end
+3 0 0 BlipSpawn out_dir=X- kind=B num_spawns=2
+";
+
+    let machine = text::parse(s).unwrap();
+
+    assert_eq!(machine.size(), Vector3::new(4, 2, 1));
+    assert_eq!(machine.num_blocks(), 4);
+    assert_eq!(
+        machine.get(&Point3::new(3, 0, 0)).unwrap().block,
+        Block::BlipSpawn {
+            out_dir: Dir3::X_NEG,
+            kind: BlipKind::B,
+            num_spawns: Some(2),
+        }
+    );
+
+    let written = text::write(&machine);
+    assert_eq!(text::write(&text::parse(&written).unwrap()), written);
+}
+
+#[test]
+fn test_text_parse_errors() {
+    let error = |s| text::parse(s).unwrap_err().line;
+
+    assert_eq!(error("size 2 2 2\n0 0 0 Solidd"), 2);
+    assert_eq!(error("\n\n0 0 0 Solid holes=X+"), 3);
+    assert_eq!(error("size 1 1 1\n1 0 0 Solid"), 2);
+    assert_eq!(error("layer 0\n-x\nend"), 2);
+    assert_eq!(error("layer 0\n--"), 1);
+
+    // Sizes are checked before allocating the grid.
+    assert_eq!(error("\nsize -1 1 1"), 2);
+    assert_eq!(error("size 0 1 1"), 1);
+    assert_eq!(error("size 100000 100000 100000"), 1);
+    assert_eq!(error("0 0 0 Solid\n100000 100000 100000 Solid"), 2);
+    assert_eq!(error("4000 0 0 Solid\n0 4000 0 Solid\n0 0 4000 Solid"), 3);
+}
+
+proptest! {
+    /// The text format does not include the level, so only the blocks are
+    /// compared.
+    #[test]
+    fn test_text_round_trip(machine in arb_machine()) {
+        let written = text::write(&machine);
+        let loaded = text::parse(&written).unwrap();
+
+        prop_assert_eq!(loaded.size(), machine.size());
+        prop_assert_eq!(text::write(&loaded), written);
+    }
+}
+
+#[test]
+fn test_diff_and_merge() {
+    let base = machine_from_string("┌-┐\n| |");
+
+    let mut ours = base.clone();
+    ours.set(
+        &Point3::new(1, 1, 0),
+        Some(PlacedBlock {
+            block: Block::Solid,
+        }),
+    );
+
+    let mut theirs = base.clone();
+    theirs.set(&Point3::new(1, 0, 0), None);
+    theirs.set(
+        &Point3::new(0, 1, 0),
+        Some(PlacedBlock {
+            block: Block::Solid,
+        }),
+    );
+
+    let changes = base.diff(&theirs);
+    assert_eq!(
+        changes
+            .iter()
+            .map(|change| change.kind())
+            .collect::<Vec<_>>(),
+        vec![ChangeKind::Removed, ChangeKind::Changed]
+    );
+
+    let mut applied = base.clone();
+    applied.apply_changes(&changes);
+    assert_eq!(applied, theirs);
+    assert!(theirs.diff(&theirs).is_empty());
+
+    let merged = merge(&base, &ours, &theirs);
+    assert!(merged.conflicts.is_empty());
+    assert_eq!(merged.machine.get(&Point3::new(1, 0, 0)), None);
+    assert!(merged.machine.get(&Point3::new(1, 1, 0)).is_some());
+
+    // Both sides changed the same block differently.
+    let mut ours = base.clone();
+    ours.set(&Point3::new(0, 1, 0), None);
+
+    let merged = merge(&base, &ours, &theirs);
+    assert_eq!(merged.conflicts, vec![Point3::new(0, 1, 0)]);
+    assert_eq!(merged.machine.get(&Point3::new(0, 1, 0)), None);
+}
+
+#[test]
+fn test_copy_on_write() {
+    let m = "
+◉---┐
+";
+    let mut machine = machine_from_string(m);
+    let copy = machine.clone();
+
+    // Cloning shares the blocks, and running a contiguous machine's garbage
+    // collection does not copy them.
+    machine.gc();
+    assert!(machine.is_shared());
+    assert!(copy.is_shared());
+
+    machine.set(
+        &Point3::new(0, 0, 0),
+        Some(PlacedBlock {
+            block: Block::Solid,
+        }),
+    );
+
+    assert!(!machine.is_shared());
+    assert_eq!(
+        copy.get(&Point3::new(0, 0, 0)).unwrap().block,
+        Block::WindSource
+    );
+    assert_eq!(
+        machine.get(&Point3::new(0, 0, 0)).unwrap().block,
+        Block::Solid
+    );
+}
+
+#[test]
+fn test_grid_region() {
+    let mut grid: Grid3<usize> = Grid3::new(Vector3::new(4, 3, 2));
+
+    // Regions are clipped to the grid.
+    grid.fill_region(&Point3::new(-1, 1, 1), &Point3::new(1, 5, 1), 1);
+    assert_eq!(grid.positions().filter(|p| grid[*p] == 1).count(), 4);
+    assert_eq!(
+        grid.iter_region(&Point3::new(0, 0, 1), &Point3::new(3, 2, 1))
+            .filter(|(_, value)| **value == 1)
+            .count(),
+        4
+    );
+
+    grid.clear_region(&Point3::new(0, 0, 0), &Point3::new(0, 2, 1));
+    assert_eq!(grid.positions().filter(|p| grid[*p] == 1).count(), 2);
+
+    let resized = grid.resized(Vector3::new(2, 2, 3));
+    assert_eq!(resized.size(), Vector3::new(2, 2, 3));
+    assert_eq!(resized[Point3::new(1, 1, 1)], 1);
+    assert_eq!(resized.positions().filter(|p| resized[*p] == 1).count(), 1);
+
+    assert_eq!(grid.neighbors(&Point3::new(0, 0, 0)).count(), 3);
+    assert_eq!(grid.neighbors(&Point3::new(1, 1, 0)).count(), 5);
+}
+
+#[test]
+fn test_grid_box_walls() {
+    let (min, max) = grid::box_corners(&Point3::new(3, 0, 2), &Point3::new(0, 4, 0));
+    assert_eq!((min, max), (Point3::new(0, 0, 0), Point3::new(3, 4, 2)));
+
+    // The interior of the 4x5x3 box is left out.
+    let walls: Vec<_> = grid::iter_box_walls(&min, &max).collect();
+    assert_eq!(walls.len(), 4 * 5 * 3 - 2 * 3);
+    assert!(!walls.contains(&Point3::new(1, 1, 1)));
+    assert!(walls.contains(&Point3::new(1, 1, 0)));
+
+    // Flat boxes have no floor.
+    let ring = grid::iter_box_walls(&Point3::new(0, 0, 0), &Point3::new(3, 4, 1));
+    assert_eq!(ring.count(), (4 * 5 - 2 * 3) * 2);
+}
+
+#[test]
+fn test_registry_order() {
+    // `registry::get` indexes the definitions by `BlockType`.
+    assert_eq!(registry::all().len(), BlockType::ALL.len());
+
+    for (i, block_type) in BlockType::ALL.iter().enumerate() {
+        assert_eq!(registry::all()[i].block_type, *block_type);
+        assert_eq!(registry::get(*block_type).block_type, *block_type);
+    }
+}
+
+#[test]
+fn test_level_ids_unique() {
+    let mut levels = Content::load(&content::Config::default()).levels();
+    let builtin_ids: Vec<String> = levels.iter().map(Level::id).collect();
+
+    // Solutions are stored by id, so the ids of builtin levels must not
+    // change.
+    for id in &[
+        "clock",
+        "id_3",
+        "o_beats_g",
+        "make_it_3",
+        "make_it_10",
+        "mul_by_3",
+    ] {
+        assert!(builtin_ids.contains(&id.to_string()));
+    }
+
+    // Levels that differ only in their parameters get different ids.
+    for spec in vec![
+        Spec::Clock {
+            pattern: vec![BlipKind::B, BlipKind::A, BlipKind::A],
+        },
+        Spec::MakeItN { n: 3, max: 20 },
+        Spec::MultiplyByN { n: 3, max: 20 },
+    ] {
+        levels.push(Level {
+            spec,
+            ..levels[0].clone()
+        });
+    }
+
+    let ids: HashSet<String> = levels.iter().map(Level::id).collect();
+    assert_eq!(ids.len(), levels.len());
+}
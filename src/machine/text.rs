@@ -0,0 +1,413 @@
+//! A textual format for machines, which is easier to write by hand than the
+//! JSON of saved machines. Each line is one of the following:
+//!
+//! - `size <x> <y> <z>`: Size of the machine's grid, which must be positive
+//!   and at most `MAX_NUM_POSITIONS` large. If omitted, the grid is just
+//!   large enough to contain all blocks.
+//! - `<x> <y> <z> <type> [<field>=<value> ...]`: A block of the given
+//!   `BlockType` at the given position. Fields that are not given keep the
+//!   values of `BlockType::example`. Directions are written as e.g. `X+` or
+//!   `Y-`, and pairs or sets of directions are separated by commas.
+//! - `layer <z>`: Starts a picture of layer `z`, drawn with the characters of
+//!   `string_util`, which ends with a line containing only `end`. The first
+//!   row of the picture is at `y = 0`.
+//!
+//! Empty lines and lines starting with `#` are ignored. `write` produces the
+//! block lines only, since pictures can not express all blocks.
+
+use std::fmt;
+
+use crate::machine::grid::{Axis3, Dir3, DirMap3, Point3, Sign, Vector3};
+use crate::machine::string_util::known_block_from_char;
+use crate::machine::{
+    is_valid_size, BlipKind, Block, BlockType, Machine, PlacedBlock, MAX_NUM_POSITIONS,
+};
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Error {
+    /// Line number, starting at one.
+    pub line: usize,
+
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub fn parse(s: &str) -> Result<Machine, Error> {
+    let mut size = None;
+    let mut blocks: Vec<(usize, Point3, Block)> = Vec::new();
+
+    let mut lines = s.lines().enumerate().map(|(index, line)| (index + 1, line));
+
+    while let Some((line_num, line)) = lines.next() {
+        let error = |message: String| Error {
+            line: line_num,
+            message,
+        };
+
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words[0] {
+            "size" => {
+                let v = parse_coords(&words[1..]).map_err(error)?;
+                let v = Vector3::new(v[0], v[1], v[2]);
+
+                if !is_valid_size(&v) {
+                    return Err(error(invalid_size_message(&v)));
+                }
+
+                size = Some(v);
+            }
+            "layer" => {
+                let z = match &words[1..] {
+                    [z] => parse_number(z).map_err(error)?,
+                    _ => return Err(error("Expected `layer <z>`".to_string())),
+                };
+
+                let mut terminated = false;
+
+                for (y, (row_num, row)) in (&mut lines).enumerate() {
+                    if row.trim() == "end" {
+                        terminated = true;
+                        break;
+                    }
+
+                    for (x, c) in row.chars().enumerate() {
+                        if c == '.' || c == ' ' {
+                            continue;
+                        }
+
+                        let block = known_block_from_char(c).ok_or_else(|| Error {
+                            line: row_num,
+                            message: format!("No block for `{}'", c),
+                        })?;
+                        let pos = Point3::new(x as isize, y as isize, z);
+
+                        blocks.push((row_num, pos, block));
+                    }
+                }
+
+                if !terminated {
+                    return Err(error("Layer is not terminated by `end`".to_string()));
+                }
+            }
+            _ => {
+                if words.len() < 4 {
+                    return Err(error(format!("Unknown line `{}'", line)));
+                }
+
+                let v = parse_coords(&words[0..3]).map_err(error)?;
+                let block = parse_block(words[3], &words[4..]).map_err(error)?;
+
+                blocks.push((line_num, Point3::new(v[0], v[1], v[2]), block));
+            }
+        }
+    }
+
+    let size = match size {
+        Some(size) => size,
+        None => {
+            let size = blocks.iter().fold(Vector3::zeros(), |size, (_, pos, _)| {
+                size.zip_map(&(pos.coords + Vector3::new(1, 1, 1)), isize::max)
+            });
+
+            // Blame the first block that is too far out on its own, or else
+            // the last one.
+            if !blocks.is_empty() && !is_valid_size(&size) {
+                let line = blocks
+                    .iter()
+                    .find(|(_, pos, _)| !is_valid_size(&(pos.coords + Vector3::new(1, 1, 1))))
+                    .or_else(|| blocks.last())
+                    .map_or(0, |(line, _, _)| *line);
+
+                return Err(Error {
+                    line,
+                    message: invalid_size_message(&size),
+                });
+            }
+
+            size
+        }
+    };
+
+    let mut machine = Machine::new_sandbox(size);
+
+    for (line, pos, block) in blocks {
+        if !machine.is_valid_pos(&pos) {
+            return Err(Error {
+                line,
+                message: "Block is outside of the machine".to_string(),
+            });
+        }
+
+        let block = block.replace_deprecated();
+        machine.set(&pos, Some(PlacedBlock { block }));
+    }
+
+    Ok(machine)
+}
+
+fn invalid_size_message(size: &Vector3) -> String {
+    format!(
+        "Invalid size {} {} {}: must be positive, with at most {} positions",
+        size.x, size.y, size.z, MAX_NUM_POSITIONS,
+    )
+}
+
+/// Writes the size and blocks of the machine, ordered by position. The
+/// machine's level is not included.
+pub fn write(machine: &Machine) -> String {
+    let size = machine.size();
+    let mut lines = vec![format!("size {} {} {}", size.x, size.y, size.z)];
+
+    let mut blocks: Vec<_> = machine.iter_blocks().map(|(_, block)| block).collect();
+    blocks.sort_by_key(|(pos, _)| (pos.z, pos.y, pos.x));
+
+    for (pos, placed_block) in blocks {
+        let block = placed_block.block.clone().replace_deprecated();
+        let mut line = format!("{} {} {} {:?}", pos.x, pos.y, pos.z, block.block_type());
+
+        for (field, value) in fields(&block) {
+            line.push_str(&format!(" {}={}", field, value));
+        }
+
+        lines.push(line);
+    }
+
+    lines.join("\n") + "\n"
+}
+
+fn parse_block(type_name: &str, fields: &[&str]) -> Result<Block, String> {
+    let block_type = BlockType::ALL
+        .iter()
+        .find(|block_type| format!("{:?}", block_type) == type_name)
+        .ok_or_else(|| format!("Unknown block type `{}'", type_name))?;
+
+    let mut block = block_type.example();
+
+    for field in fields {
+        let mut parts = field.splitn(2, '=');
+        let (name, value) = match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) => (name, value),
+            _ => return Err(format!("Expected `<field>=<value>`, got `{}'", field)),
+        };
+
+        set_field(&mut block, name, value)?;
+    }
+
+    Ok(block)
+}
+
+fn set_field(block: &mut Block, name: &str, value: &str) -> Result<(), String> {
+    match (block, name) {
+        (Block::GeneralPipe(holes), "holes") => {
+            let dirs = parse_dirs(value)?;
+            *holes = DirMap3::from_fn(|dir| dirs.contains(&dir));
+        }
+        (Block::FunnelXY { flow_dir }, "flow_dir")
+        | (Block::Delay { flow_dir }, "flow_dir")
+        | (Block::Scripted { flow_dir, .. }, "flow_dir") => *flow_dir = parse_dir(value)?,
+        (Block::BlipSpawn { out_dir, .. }, "out_dir")
+        | (Block::Input { out_dir, .. }, "out_dir")
+        | (Block::DetectorBlipDuplicator { out_dir, .. }, "out_dir") => {
+            *out_dir = parse_dir(value)?
+        }
+        (Block::BlipSpawn { kind, .. }, "kind") => *kind = parse_kind(value)?,
+        (Block::BlipSpawn { num_spawns, .. }, "num_spawns") => {
+            *num_spawns = Some(parse_number(value)?)
+        }
+        (Block::BlipDuplicator { out_dirs, .. }, "out_dirs")
+        | (Block::BlipDeleter { out_dirs }, "out_dirs") => *out_dirs = parse_dir_pair(value)?,
+        (Block::BlipDuplicator { kind, .. }, "kind")
+        | (Block::DetectorBlipDuplicator { kind, .. }, "kind") => *kind = Some(parse_kind(value)?),
+        (Block::BlipWindSource { button_dir }, "button_dir") => *button_dir = parse_dir(value)?,
+        (Block::Input { index, .. }, "index") | (Block::Output { index, .. }, "index") => {
+            *index = parse_number(value)?
+        }
+        (Block::Output { in_dir, .. }, "in_dir") => *in_dir = parse_dir(value)?,
+        (Block::DetectorBlipDuplicator { flow_axis, .. }, "flow_axis") => {
+            *flow_axis = parse_axis(value)?
+        }
+        (Block::PipeButton { axis }, "axis") | (Block::DetectorWindSource { axis }, "axis") => {
+            *axis = parse_axis(value)?
+        }
+        (Block::Scripted { name, .. }, "name") => *name = value.to_string(),
+        (Block::Scripted { mesh, .. }, "mesh") => *mesh = Some(value.to_string()),
+        (block, _) => {
+            return Err(format!(
+                "Unknown field `{}' for {:?}",
+                name,
+                block.block_type()
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the fields of the block in the form that `set_field` accepts.
+fn fields(block: &Block) -> Vec<(&'static str, String)> {
+    match block {
+        Block::Pipe(a, b) => vec![("holes", format!("{},{}", dir_str(*a), dir_str(*b)))],
+        Block::PipeMergeXY | Block::WindSource | Block::Solid | Block::Air => vec![],
+        Block::GeneralPipe(holes) => {
+            let dirs: Vec<_> = holes
+                .iter()
+                .filter(|(_, &hole)| hole)
+                .map(|(dir, _)| dir_str(dir))
+                .collect();
+            vec![("holes", dirs.join(","))]
+        }
+        Block::FunnelXY { flow_dir } | Block::Delay { flow_dir } => {
+            vec![("flow_dir", dir_str(*flow_dir))]
+        }
+        Block::BlipSpawn {
+            out_dir,
+            kind,
+            num_spawns,
+        } => {
+            let mut fields = vec![("out_dir", dir_str(*out_dir)), ("kind", kind_str(*kind))];
+            if let Some(num_spawns) = num_spawns {
+                fields.push(("num_spawns", num_spawns.to_string()));
+            }
+            fields
+        }
+        Block::BlipDuplicator { out_dirs, kind } => {
+            let mut fields = vec![("out_dirs", dir_pair_str(*out_dirs))];
+            if let Some(kind) = kind {
+                fields.push(("kind", kind_str(*kind)));
+            }
+            fields
+        }
+        Block::BlipWindSource { button_dir } => vec![("button_dir", dir_str(*button_dir))],
+        Block::Input { out_dir, index } => {
+            vec![("out_dir", dir_str(*out_dir)), ("index", index.to_string())]
+        }
+        Block::Output { in_dir, index } => {
+            vec![("in_dir", dir_str(*in_dir)), ("index", index.to_string())]
+        }
+        Block::DetectorBlipDuplicator {
+            out_dir,
+            flow_axis,
+            kind,
+        } => {
+            let mut fields = vec![
+                ("out_dir", dir_str(*out_dir)),
+                ("flow_axis", format!("{:?}", flow_axis)),
+            ];
+            if let Some(kind) = kind {
+                fields.push(("kind", kind_str(*kind)));
+            }
+            fields
+        }
+        Block::PipeButton { axis } | Block::DetectorWindSource { axis } => {
+            vec![("axis", format!("{:?}", axis))]
+        }
+        Block::BlipDeleter { out_dirs } => vec![("out_dirs", dir_pair_str(*out_dirs))],
+        Block::Scripted {
+            name,
+            flow_dir,
+            mesh,
+        } => {
+            let mut fields = vec![("name", name.clone()), ("flow_dir", dir_str(*flow_dir))];
+            if let Some(mesh) = mesh {
+                fields.push(("mesh", mesh.clone()));
+            }
+            fields
+        }
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(s: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("Invalid number `{}'", s))
+}
+
+fn parse_coords(words: &[&str]) -> Result<[isize; 3], String> {
+    match words {
+        [x, y, z] => Ok([parse_number(x)?, parse_number(y)?, parse_number(z)?]),
+        _ => Err(format!(
+            "Expected three coordinates, got `{}'",
+            words.join(" ")
+        )),
+    }
+}
+
+fn parse_axis(s: &str) -> Result<Axis3, String> {
+    match s {
+        "X" => Ok(Axis3::X),
+        "Y" => Ok(Axis3::Y),
+        "Z" => Ok(Axis3::Z),
+        _ => Err(format!("Invalid axis `{}'", s)),
+    }
+}
+
+fn parse_dir(s: &str) -> Result<Dir3, String> {
+    let error = || format!("Invalid direction `{}'", s);
+
+    let mut chars = s.chars();
+    let axis = match chars.next() {
+        Some('X') => Axis3::X,
+        Some('Y') => Axis3::Y,
+        Some('Z') => Axis3::Z,
+        _ => return Err(error()),
+    };
+    let sign = match (chars.next(), chars.next()) {
+        (Some('+'), None) => Sign::Pos,
+        (Some('-'), None) => Sign::Neg,
+        _ => return Err(error()),
+    };
+
+    Ok(Dir3(axis, sign))
+}
+
+fn parse_dirs(s: &str) -> Result<Vec<Dir3>, String> {
+    if s.is_empty() {
+        // Pipes without holes.
+        return Ok(Vec::new());
+    }
+
+    s.split(',').map(parse_dir).collect()
+}
+
+fn parse_dir_pair(s: &str) -> Result<(Dir3, Dir3), String> {
+    match parse_dirs(s)?.as_slice() {
+        [a, b] => Ok((*a, *b)),
+        _ => Err(format!("Expected two directions, got `{}'", s)),
+    }
+}
+
+fn parse_kind(s: &str) -> Result<BlipKind, String> {
+    match s {
+        "A" => Ok(BlipKind::A),
+        "B" => Ok(BlipKind::B),
+        _ => Err(format!("Invalid blip kind `{}'", s)),
+    }
+}
+
+fn dir_str(dir: Dir3) -> String {
+    let sign = match dir.1 {
+        Sign::Pos => '+',
+        Sign::Neg => '-',
+    };
+
+    format!("{:?}{}", dir.0, sign)
+}
+
+fn dir_pair_str(dirs: (Dir3, Dir3)) -> String {
+    format!("{},{}", dir_str(dirs.0), dir_str(dirs.1))
+}
+
+fn kind_str(kind: BlipKind) -> String {
+    format!("{:?}", kind)
+}
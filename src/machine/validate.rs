@@ -0,0 +1,277 @@
+//! Static checks for machines, pointing out likely mistakes before the
+//! machine is run.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use crate::machine::grid::{Dir3, Point3};
+use crate::machine::level::Violation;
+use crate::machine::{Block, Machine};
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug)]
+pub enum Severity {
+    /// The machine can run, but probably does not do what was intended.
+    Warning,
+
+    /// The machine can not be run for its level as it is.
+    Error,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ProblemKind {
+    /// A block has a hole through which it expects to receive or emit blips,
+    /// but there is no block to connect to in that direction.
+    UnconnectedHole { dir: Dir3 },
+
+    /// A pipe has an opening that does not connect to any block.
+    PipeLeadsNowhere { dir: Dir3 },
+
+    /// No input of the level is connected to this output.
+    UnreachableOutput { index: usize },
+
+    /// A block is outside of the level's bounds, e.g. because the machine was
+    /// loaded for a smaller level.
+    OutOfBounds,
+
+    /// The machine violates the constraints of its level.
+    Violation(Violation),
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Problem {
+    /// Position of the block that the problem is about, if any.
+    pub pos: Option<Point3>,
+
+    pub kind: ProblemKind,
+}
+
+impl Problem {
+    pub fn severity(&self) -> Severity {
+        match self.kind {
+            ProblemKind::UnconnectedHole { .. } => Severity::Warning,
+            ProblemKind::PipeLeadsNowhere { .. } => Severity::Warning,
+            ProblemKind::UnreachableOutput { .. } => Severity::Warning,
+            ProblemKind::OutOfBounds => Severity::Error,
+            ProblemKind::Violation(_) => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ProblemKind::UnconnectedHole { dir } => {
+                write!(f, "Block has an unconnected hole towards {:?}", dir)
+            }
+            ProblemKind::PipeLeadsNowhere { dir } => {
+                write!(f, "Pipe leads nowhere towards {:?}", dir)
+            }
+            ProblemKind::UnreachableOutput { index } => {
+                write!(f, "Output {} is not connected to any input", index + 1)
+            }
+            ProblemKind::OutOfBounds => write!(f, "Block is outside of the level's bounds"),
+            ProblemKind::Violation(violation) => write!(f, "{}", violation),
+        }
+    }
+}
+
+impl Machine {
+    /// Looks for likely mistakes in the machine. Problems are sorted by
+    /// severity, starting with errors.
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+
+        for (_, (pos, placed_block)) in self.iter_blocks() {
+            let block = &placed_block.block;
+
+            if let Some(level) = self.level.as_ref() {
+                let in_bounds = (0..3).all(|i| pos[i] < level.size[i]);
+                if !in_bounds {
+                    problems.push(Problem {
+                        pos: Some(*pos),
+                        kind: ProblemKind::OutOfBounds,
+                    });
+                }
+            }
+
+            for &dir in mandatory_holes(block).iter() {
+                if !self.can_pass(pos, dir) {
+                    problems.push(Problem {
+                        pos: Some(*pos),
+                        kind: ProblemKind::UnconnectedHole { dir },
+                    });
+                }
+            }
+
+            if let Block::GeneralPipe(holes) = block {
+                for (dir, _) in holes.iter().filter(|(_, &hole)| hole) {
+                    if !self.is_connected(pos, dir) {
+                        problems.push(Problem {
+                            pos: Some(*pos),
+                            kind: ProblemKind::PipeLeadsNowhere { dir },
+                        });
+                    }
+                }
+            }
+        }
+
+        problems.extend(self.unreachable_outputs());
+
+        if let Some(level) = self.level.as_ref() {
+            problems.extend(
+                level
+                    .constraints
+                    .check(self)
+                    .into_iter()
+                    .map(|violation| Problem {
+                        pos: match violation {
+                            Violation::ForbiddenRegion { pos } => Some(pos),
+                            _ => None,
+                        },
+                        kind: ProblemKind::Violation(violation),
+                    }),
+            );
+        }
+
+        problems.sort_by_key(|problem| std::cmp::Reverse(problem.severity()));
+        problems
+    }
+
+    /// Returns true if wind or blips can pass from the block at `pos` to its
+    /// neighbor in direction `dir`.
+    fn can_pass(&self, pos: &Point3, dir: Dir3) -> bool {
+        let block = match self.get(pos) {
+            Some(placed_block) => &placed_block.block,
+            None => return false,
+        };
+        let neighbor = match self.get(&(pos + dir.to_vector())) {
+            Some(placed_block) => &placed_block.block,
+            None => return false,
+        };
+
+        // Blips can be spawned into any neighboring block, even into the side
+        // of a pipe.
+        spawns_blips(block, dir)
+            || (has_any_hole(block, dir) && has_any_hole(neighbor, dir.invert()))
+    }
+
+    /// Returns true if the block at `pos` and its neighbor in direction `dir`
+    /// are connected in either direction.
+    fn is_connected(&self, pos: &Point3, dir: Dir3) -> bool {
+        self.can_pass(pos, dir) || self.can_pass(&(pos + dir.to_vector()), dir.invert())
+    }
+
+    /// Finds the outputs that can not be reached from any input by following
+    /// connected holes. Machines without inputs are not checked, since their
+    /// outputs are fed by other means.
+    fn unreachable_outputs(&self) -> Vec<Problem> {
+        let inputs: Vec<Point3> = self
+            .iter_blocks()
+            .filter(|(_, (_, placed_block))| match placed_block.block {
+                Block::Input { .. } => true,
+                _ => false,
+            })
+            .map(|(_, (pos, _))| *pos)
+            .collect();
+
+        if inputs.is_empty() {
+            return Vec::new();
+        }
+
+        let mut reached: HashSet<Point3> = inputs.iter().cloned().collect();
+        let mut queue = inputs;
+
+        while let Some(pos) = queue.pop() {
+            for &dir in Dir3::ALL.iter() {
+                let next_pos = pos + dir.to_vector();
+                if !reached.contains(&next_pos) && self.is_connected(&pos, dir) {
+                    reached.insert(next_pos);
+                    queue.push(next_pos);
+                }
+            }
+        }
+
+        self.iter_blocks()
+            .filter_map(|(_, (pos, placed_block))| match placed_block.block {
+                Block::Output { index, .. } if !reached.contains(pos) => Some(Problem {
+                    pos: Some(*pos),
+                    kind: ProblemKind::UnreachableOutput { index },
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the shortest chain of connected blocks that leads from an
+    /// input to an output, including both ends. This is roughly the path that
+    /// blips take through the machine. Returns an empty path if no output can
+    /// be reached from any input.
+    pub fn flow_path(&self) -> Vec<Point3> {
+        let mut queue: VecDeque<Point3> = self
+            .iter_blocks()
+            .filter(|(_, (_, placed_block))| match placed_block.block {
+                Block::Input { .. } => true,
+                _ => false,
+            })
+            .map(|(_, (pos, _))| *pos)
+            .collect();
+
+        // Maps each reached position to the position that it was reached from.
+        let mut parents: HashMap<Point3, Option<Point3>> =
+            queue.iter().map(|pos| (*pos, None)).collect();
+
+        while let Some(pos) = queue.pop_front() {
+            if let Some(Block::Output { .. }) = self.get(&pos).map(|placed| &placed.block) {
+                let mut path = vec![pos];
+                while let Some(Some(parent)) = parents.get(path.last().unwrap()) {
+                    path.push(*parent);
+                }
+                path.reverse();
+
+                return path;
+            }
+
+            for &dir in Dir3::ALL.iter() {
+                let next_pos = pos + dir.to_vector();
+                if !parents.contains_key(&next_pos) && self.can_pass(&pos, dir) {
+                    parents.insert(next_pos, Some(pos));
+                    queue.push_back(next_pos);
+                }
+            }
+        }
+
+        Vec::new()
+    }
+}
+
+/// Directions in which a block needs a neighbor in order to be of any use.
+fn mandatory_holes(block: &Block) -> Vec<Dir3> {
+    match block {
+        Block::FunnelXY { flow_dir } => vec![*flow_dir, flow_dir.invert()],
+        Block::BlipSpawn { out_dir, .. } => vec![*out_dir],
+        Block::BlipDuplicator { out_dirs, .. } => vec![out_dirs.0, out_dirs.1],
+        Block::Input { out_dir, .. } => vec![*out_dir],
+        Block::Output { in_dir, .. } => vec![*in_dir],
+        Block::DetectorBlipDuplicator { out_dir, .. } => vec![*out_dir],
+        Block::Delay { flow_dir } => vec![*flow_dir, flow_dir.invert()],
+        Block::Scripted { flow_dir, .. } => vec![*flow_dir, flow_dir.invert()],
+        _ => Vec::new(),
+    }
+}
+
+fn spawns_blips(block: &Block, dir: Dir3) -> bool {
+    match block {
+        Block::Input { out_dir, .. } => dir == *out_dir,
+        _ => block.has_blip_spawn(dir),
+    }
+}
+
+/// Returns true if anything can pass through the block in direction `dir`,
+/// be it wind, blips or the activation of a button.
+fn has_any_hole(block: &Block, dir: Dir3) -> bool {
+    block.has_wind_hole(dir, false)
+        || block.has_move_hole(dir, false)
+        || block.has_blip_spawn(dir)
+        || block.has_wind_source(dir)
+        || block.has_button(dir)
+}
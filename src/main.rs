@@ -1,33 +1,21 @@
-// Needed for pareen stuff
-#![type_length_limit = "600000000"]
-
-//#![feature(type_alias_impl_trait)]
-
-#[macro_use]
-mod util;
-mod config;
-mod edit;
-mod edit_camera_view;
-mod exec;
-mod game;
-mod input_state;
-mod machine;
-mod render;
-
-use std::fs::File;
-use std::io::BufReader;
+use std::path::Path;
 use std::thread;
 use std::time::{Duration, Instant};
 
 use clap::{App, Arg};
 use coarse_prof::profile;
-use glium::glutin;
-use log::info;
+use glium::{glutin, Surface};
+use imgui::{im_str, ImString};
+use log::{info, warn};
+
+use ultimate_scale::content::Content;
+use ultimate_scale::edit::solutions;
+use ultimate_scale::game::Game;
+use ultimate_scale::input_route::{Router, UiFocus};
+use ultimate_scale::input_state::InputState;
+use ultimate_scale::machine::Machine;
+use ultimate_scale::{config, window_mode};
 
-use game::Game;
-use input_state::InputState;
-use machine::level::{Level, Spec};
-use machine::{grid, BlipKind, Machine, SavedMachine};
 fn main() {
     simple_logger::init_with_level(log::Level::Info).unwrap();
 
@@ -39,7 +27,7 @@ fn main() {
                 .short("f")
                 .long("file")
                 .value_name("FILE")
-                .help("Load the given machine")
+                .help("Load the given machine, as JSON or in the text format if FILE ends in .txt")
                 .takes_value(true),
         )
         .arg(
@@ -50,13 +38,29 @@ fn main() {
                 .help("Play a specific level")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("record-blip-paths")
+                .long("record-blip-paths")
+                .help("Record the paths of all blips, for debugging"),
+        )
+        .arg(
+            Arg::with_name("language")
+                .long("language")
+                .value_name("LANGUAGE")
+                .help("Show the user interface in the given language")
+                .takes_value(true),
+        )
         .get_matches();
 
     let mut config: config::Config = Default::default();
-    config.render_pipeline.hdr = Some(1.0);
-    /*config.render_pipeline.shadow_mapping = None;
-    config.render_pipeline.deferred_shading = None;
-    config.render_pipeline.glow = None;*/
+    config.render_pipeline.rendology.hdr = Some(1.0);
+    config.exec.record_blip_paths = args.is_present("record-blip-paths");
+    if let Some(language) = args.value_of("language") {
+        config.locale.language = language.to_string();
+    }
+    /*config.render_pipeline.rendology.shadow_mapping = None;
+    config.render_pipeline.rendology.deferred_shading = None;
+    config.render_pipeline.rendology.glow = None;*/
     info!("Running with config: {:?}", config);
 
     info!("Opening glutin window");
@@ -64,13 +68,13 @@ fn main() {
     let display = {
         let window_builder = glutin::WindowBuilder::new()
             .with_dimensions(config.view.window_size)
-            .with_title("Ultimate Scale!")
-            .with_fullscreen(Some(events_loop.get_primary_monitor()));
+            .with_title("Ultimate Scale!");
         let context_builder = glutin::ContextBuilder::new();
         glium::Display::new(window_builder, context_builder, &events_loop).unwrap()
     };
     let gl_window = display.gl_window();
     let window = gl_window.window();
+    window_mode::apply(&window, &events_loop, &config.view);
 
     info!("Initializing imgui");
     let mut imgui = imgui::Context::create();
@@ -85,109 +89,72 @@ fn main() {
         imgui_winit_support::HiDpiMode::Rounded,
     );
 
-    {
-        let hidpi_factor = imgui_platform.hidpi_factor();
-        let font_size = (18.0 * hidpi_factor) as f32;
-
-        // Include some special characters in the glyph ranges
-        let glyph_ranges = imgui::FontGlyphRanges::from_slice(&[
-            0x0020, 0x00FF, // Basic Latin + Latin Supplement
-            0,
-        ]);
-
-        // Symbola has some additional symbols that DeJaVu lacks
-        let glyph_ranges_symbola = imgui::FontGlyphRanges::from_slice(&[
-            0x2190, 0x21FF, // Arrows
-            0x2300, 0x23FF, // Miscellaneous technical
-            0x25A0, 0x25FF, // Geometric shapes
-            0,
-        ]);
-
-        imgui.fonts().add_font(&[
-            imgui::FontSource::TtfData {
-                data: include_bytes!("../resources/DejaVuSans.ttf"),
-                size_pixels: font_size,
-                config: Some(imgui::FontConfig {
-                    glyph_ranges,
-                    ..imgui::FontConfig::default()
-                }),
-            },
-            imgui::FontSource::TtfData {
-                data: include_bytes!("../resources/Symbola_hint.ttf"),
-                size_pixels: font_size,
-                config: Some(imgui::FontConfig {
-                    glyph_ranges: glyph_ranges_symbola,
-                    ..imgui::FontConfig::default()
-                }),
-            },
-        ]);
-
-        imgui.io_mut().font_global_scale = (1.0 / hidpi_factor) as f32;
-    }
+    add_fonts(&mut imgui, imgui_platform.hidpi_factor());
 
     let mut imgui_renderer = imgui_glium_renderer::Renderer::init(&mut imgui, &display)
         .expect("Failed to initialize imgui_glium_renderer");
 
-    // TODO: Better level choosing
-    let level = if let Some(level) = args.value_of("level") {
-        if level == "id_3" {
-            Some(Level {
-                size: grid::Vector3::new(27, 27, 4),
-                spec: Spec::Id { dim: 3 },
-            })
-        } else if level == "clock" {
-            Some(Level {
-                size: grid::Vector3::new(9, 9, 1),
-                spec: Spec::Clock {
-                    pattern: vec![BlipKind::A, BlipKind::B],
-                },
-            })
-        } else if level == "o_beats_g" {
-            Some(Level {
-                size: grid::Vector3::new(19, 19, 2),
-                spec: Spec::BitwiseMax,
-            })
-        } else if level == "make_it_3" {
-            Some(Level {
-                size: grid::Vector3::new(19, 19, 2),
-                spec: Spec::MakeItN { n: 3, max: 30 },
-            })
-        } else if level == "make_it_10" {
-            Some(Level {
-                size: grid::Vector3::new(60, 60, 15),
-                spec: Spec::MakeItN { n: 10, max: 30 },
-            })
-        } else if level == "mul_by_3" {
-            Some(Level {
-                size: grid::Vector3::new(30, 30, 30),
-                spec: Spec::MultiplyByN { n: 3, max: 15 },
-            })
-        } else {
-            None
+    let content = Content::load(&config.content);
+
+    let level = args.value_of("level").and_then(|name| {
+        let level = content
+            .levels()
+            .into_iter()
+            .find(|level| level.id() == name);
+
+        if level.is_none() {
+            warn!("Unknown level \"{}\"", name);
         }
-    } else {
-        None
-    };
+
+        level
+    });
+
+    // The main menu is skipped if the player has chosen what to play.
+    let show_menu = args.value_of("file").is_none() && level.is_none();
 
     let initial_machine = if let Some(file) = args.value_of("file") {
-        info!("Loading machine from file `{}'", file);
-        let file = File::open(file).unwrap();
-        let reader = BufReader::new(file);
-        let saved_machine: SavedMachine = serde_json::from_reader(reader).unwrap();
-        saved_machine.into_machine()
+        solutions::load(Path::new(file)).expect("Could not load machine")
     } else if let Some(level) = level {
         info!("Running level \"{}\"", level.spec.description());
         Machine::new_from_level(level)
     } else {
         info!("Starting in sandbox mode");
-        let grid_size = grid::Vector3::new(60, 60, 40);
-        Machine::new_sandbox(grid_size)
+        Machine::new_sandbox(config.editor.sandbox_size)
     };
 
     let mut input_state = InputState::new(window);
 
-    let mut game = Game::create(&display, &config, initial_machine).unwrap();
+    let game = Game::create(
+        &display,
+        &config,
+        window.get_hidpi_factor(),
+        content,
+        initial_machine,
+    );
+    let mut game = match game {
+        Ok(game) => game,
+        Err(err) => {
+            warn!("Failed to create game: {}", err);
+
+            show_fatal_error(
+                &err.to_string(),
+                &mut events_loop,
+                &display,
+                window,
+                &mut imgui,
+                &mut imgui_platform,
+                &mut imgui_renderer,
+            );
+            return;
+        }
+    };
+    game.set_monitor_names(window_mode::monitor_names(&events_loop));
 
+    if show_menu {
+        game.open_menu();
+    }
+
+    let mut input_router = Router::new();
     let mut previous_clock = Instant::now();
     let mut previous_clock_imgui = Instant::now();
     let mut quit = false;
@@ -199,6 +166,7 @@ fn main() {
         // because resizing textures is somewhat costly, so it makes sense to
         // do it at most once per frame.
         let mut new_window_size = None;
+        let mut new_hidpi_factor = None;
 
         events_loop.poll_events(|event| {
             imgui_platform.handle_event(imgui.io_mut(), &window, &event);
@@ -211,24 +179,15 @@ fn main() {
                     // Do not forward events to the game if imgui currently
                     // wants to handle events (i.e. when the mouse is over a
                     // window).
-                    //
-                    // Note that we always forward release events to the game,
-                    // so that e.g. we do not keep scrolling the view camera.
-                    let forward_to_game = match event {
-                        glutin::WindowEvent::KeyboardInput { input, .. } => {
-                            !imgui.io().want_capture_keyboard
-                                || input.state == glutin::ElementState::Released
-                        }
-                        glutin::WindowEvent::MouseInput { state, .. } => {
-                            !imgui.io().want_capture_mouse
-                                || state == glutin::ElementState::Released
-                        }
-                        _ => true,
+                    let focus = UiFocus {
+                        keyboard: imgui.io().want_capture_keyboard,
+                        mouse: imgui.io().want_capture_mouse,
                     };
+                    let route = input_router.route(focus, &event);
 
-                    if forward_to_game {
+                    if !route.is_none() {
                         input_state.on_event(&event);
-                        game.on_event(&input_state, &event);
+                        game.on_event(&input_state, &event, route);
                     }
 
                     match event {
@@ -243,6 +202,12 @@ fn main() {
                         glutin::WindowEvent::Resized(viewport_size) => {
                             new_window_size = Some(viewport_size);
                         }
+                        glutin::WindowEvent::HiDpiFactorChanged(hidpi_factor) => {
+                            new_hidpi_factor = Some(hidpi_factor);
+                        }
+                        glutin::WindowEvent::DroppedFile(path) => {
+                            game.on_dropped_file(&path);
+                        }
                         glutin::WindowEvent::KeyboardInput { input, .. } => {
                             if input.state == glutin::ElementState::Pressed {
                                 match input.virtual_keycode {
@@ -261,10 +226,37 @@ fn main() {
             }
         });
 
+        if game.is_quit_requested() {
+            info!("Quitting");
+
+            quit = true;
+        }
+
+        if let Some(view_config) = game.take_view_change() {
+            window_mode::apply(&window, &events_loop, &view_config);
+        }
+
+        if let Some(hidpi_factor) = new_hidpi_factor {
+            info!("HiDPI factor changed to: {}", hidpi_factor);
+
+            // Re-rasterize the fonts, so that text stays sharp. Note that
+            // imgui_platform has already updated its own scale at this point.
+            imgui.fonts().clear();
+            add_fonts(&mut imgui, imgui_platform.hidpi_factor());
+            imgui_renderer
+                .reload_font_texture(&mut imgui)
+                .expect("Failed to reload imgui font texture");
+        }
+
+        // The window's logical size may stay the same when the HiDPI factor
+        // changes, but its physical size does not.
+        let new_window_size =
+            new_window_size.or_else(|| new_hidpi_factor.and_then(|_| window.get_inner_size()));
+
         if let Some(new_window_size) = new_window_size {
             info!("Window resized to: {:?}", new_window_size);
 
-            game.on_window_resize(&display, new_window_size);
+            game.on_window_resize(&display, new_window_size, window.get_hidpi_factor());
         }
 
         let now_clock = Instant::now();
@@ -273,7 +265,8 @@ fn main() {
 
         {
             profile!("create_resources");
-            game.create_resources(&display).unwrap();
+            game.create_resources(&display);
+            game.create_ui_textures(&display, imgui_renderer.textures());
         }
 
         {
@@ -304,7 +297,7 @@ fn main() {
                 display.draw()
             };
 
-            game.draw(&display, &mut target).unwrap();
+            game.draw(&display, &mut target);
 
             {
                 profile!("ui");
@@ -321,4 +314,113 @@ fn main() {
 
         thread::sleep(Duration::from_millis(0));
     }
+
+    game.on_quit();
+}
+
+/// Shows `message` in a window until the player quits. This is used for
+/// errors that prevent the game from starting at all.
+fn show_fatal_error(
+    message: &str,
+    events_loop: &mut glutin::EventsLoop,
+    display: &glium::Display,
+    window: &glutin::Window,
+    imgui: &mut imgui::Context,
+    imgui_platform: &mut imgui_winit_support::WinitPlatform,
+    imgui_renderer: &mut imgui_glium_renderer::Renderer,
+) {
+    let mut previous_clock_imgui = Instant::now();
+    let mut quit = false;
+
+    while !quit {
+        events_loop.poll_events(|event| {
+            imgui_platform.handle_event(imgui.io_mut(), window, &event);
+
+            if let glutin::Event::WindowEvent {
+                event: glutin::WindowEvent::CloseRequested,
+                ..
+            } = event
+            {
+                quit = true;
+            }
+        });
+
+        let imgui_io = imgui.io_mut();
+        imgui_platform
+            .prepare_frame(imgui_io, window)
+            .expect("Failed to start imgui frame");
+        previous_clock_imgui = imgui_io.update_delta_time(previous_clock_imgui);
+        let ui = imgui.frame();
+
+        imgui::Window::new(im_str!("Error"))
+            .position(
+                [ui.io().display_size[0] / 2.0, ui.io().display_size[1] / 2.0],
+                imgui::Condition::Always,
+            )
+            .position_pivot([0.5, 0.5])
+            .always_auto_resize(true)
+            .collapsible(false)
+            .build(&ui, || {
+                ui.text(im_str!("The game could not be started:"));
+                ui.text(&ImString::new(message));
+                ui.separator();
+
+                if ui.button(im_str!("Quit"), [120.0, 20.0]) {
+                    quit = true;
+                }
+            });
+
+        imgui_platform.prepare_render(&ui, window);
+        let ui_draw_data = ui.render();
+
+        let mut target = display.draw();
+        target.clear_color(0.1, 0.1, 0.1, 1.0);
+        imgui_renderer
+            .render(&mut target, &ui_draw_data)
+            .expect("Failed to render imgui frame");
+        target.finish().expect("Failed to swap buffers");
+
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Adds the fonts used by the UI. Fonts are rasterized at the physical size,
+/// and then scaled back down, so that text is sharp on high-DPI displays.
+fn add_fonts(imgui: &mut imgui::Context, hidpi_factor: f64) {
+    let font_size = (18.0 * hidpi_factor) as f32;
+
+    // Include some special characters in the glyph ranges
+    let glyph_ranges = imgui::FontGlyphRanges::from_slice(&[
+        0x0020, 0x00FF, // Basic Latin + Latin Supplement
+        0,
+    ]);
+
+    // Symbola has some additional symbols that DeJaVu lacks
+    let glyph_ranges_symbola = imgui::FontGlyphRanges::from_slice(&[
+        0x2190, 0x21FF, // Arrows
+        0x2300, 0x23FF, // Miscellaneous technical
+        0x25A0, 0x25FF, // Geometric shapes
+        0,
+    ]);
+
+    imgui.fonts().add_font(&[
+        imgui::FontSource::TtfData {
+            data: include_bytes!("../resources/DejaVuSans.ttf"),
+            size_pixels: font_size,
+            config: Some(imgui::FontConfig {
+                glyph_ranges,
+                ..imgui::FontConfig::default()
+            }),
+        },
+        imgui::FontSource::TtfData {
+            data: include_bytes!("../resources/Symbola_hint.ttf"),
+            size_pixels: font_size,
+            config: Some(imgui::FontConfig {
+                glyph_ranges: glyph_ranges_symbola,
+                ..imgui::FontConfig::default()
+            }),
+        },
+    ]);
+
+    imgui.io_mut().font_global_scale = (1.0 / hidpi_factor) as f32;
 }
@@ -0,0 +1,288 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::exec::Score;
+use crate::machine::SavedMachine;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Sharing is disabled by default, so that we never connect anywhere
+    /// without the player asking for it.
+    pub enabled: bool,
+
+    /// Base URL of the sharing server. Only plain `http://` is supported.
+    pub endpoint: String,
+
+    /// Name shown next to uploaded solutions.
+    pub author: String,
+
+    pub timeout: Duration,
+}
+
+/// Maximal size of responses from the sharing server, in bytes. Larger
+/// responses are rejected.
+pub const MAX_RESPONSE_SIZE: u64 = 16 * 1024 * 1024;
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: "http://localhost:8080/solutions".to_string(),
+            author: "anonymous".to_string(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// A solution as it is exchanged with the sharing server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedSolution {
+    pub level_id: String,
+    pub author: String,
+    pub score: Option<Score>,
+    pub machine: SavedMachine,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidEndpoint(String),
+    Io(io::Error),
+    InvalidResponse,
+    ResponseTooLarge,
+    Status(u16),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidEndpoint(endpoint) => write!(f, "invalid endpoint: {}", endpoint),
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::InvalidResponse => write!(f, "invalid HTTP response"),
+            Error::ResponseTooLarge => {
+                write!(f, "response is larger than {} bytes", MAX_RESPONSE_SIZE)
+            }
+            Error::Status(status) => write!(f, "server responded with status {}", status),
+            Error::Json(err) => write!(f, "JSON error: {}", err),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Json(err)
+    }
+}
+
+/// Uploads a solution, blocking until the server has responded.
+pub fn upload(config: &Config, solution: &SharedSolution) -> Result<(), Error> {
+    let body = serde_json::to_string(solution)?;
+    let path = format!("/{}", solution.level_id);
+
+    request(config, "POST", &path, Some(&body)).map(|_| ())
+}
+
+/// Downloads the solutions that have been shared for a level, blocking until
+/// the server has responded.
+pub fn download(config: &Config, level_id: &str) -> Result<Vec<SharedSolution>, Error> {
+    let path = format!("/{}", level_id);
+    let body = request(config, "GET", &path, None)?;
+
+    Ok(serde_json::from_str(&body)?)
+}
+
+/// Results of requests that were run in the background.
+#[derive(Debug)]
+pub enum Event {
+    Uploaded(Result<(), Error>),
+    Downloaded(String, Result<Vec<SharedSolution>, Error>),
+}
+
+/// Runs requests to the sharing server in background threads, so that the
+/// game does not freeze while waiting for the network.
+pub struct Client {
+    config: Config,
+
+    event_send: mpsc::Sender<Event>,
+    event_recv: mpsc::Receiver<Event>,
+}
+
+impl Client {
+    pub fn new(config: &Config) -> Self {
+        let (event_send, event_recv) = mpsc::channel();
+
+        Self {
+            config: config.clone(),
+            event_send,
+            event_recv,
+        }
+    }
+
+    pub fn upload(&self, solution: SharedSolution) {
+        info!(
+            "Uploading solution for level {} to {}",
+            solution.level_id, self.config.endpoint
+        );
+
+        let config = self.config.clone();
+        let event_send = self.event_send.clone();
+
+        thread::spawn(move || {
+            let result = upload(&config, &solution);
+
+            // The client may have been dropped in the meantime, in which case
+            // nobody is interested in the result anymore.
+            let _ = event_send.send(Event::Uploaded(result));
+        });
+    }
+
+    pub fn download(&self, level_id: String) {
+        info!(
+            "Downloading solutions for level {} from {}",
+            level_id, self.config.endpoint
+        );
+
+        let config = self.config.clone();
+        let event_send = self.event_send.clone();
+
+        thread::spawn(move || {
+            let result = download(&config, &level_id);
+            let _ = event_send.send(Event::Downloaded(level_id, result));
+        });
+    }
+
+    /// Returns the results of all requests that have finished since the last
+    /// call.
+    pub fn poll(&self) -> Vec<Event> {
+        self.event_recv.try_iter().collect()
+    }
+}
+
+struct Endpoint {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+fn parse_endpoint(endpoint: &str) -> Result<Endpoint, Error> {
+    let invalid = || Error::InvalidEndpoint(endpoint.to_string());
+
+    let rest = if endpoint.starts_with("http://") {
+        &endpoint["http://".len()..]
+    } else {
+        return Err(invalid());
+    };
+
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], rest[index..].trim_end_matches('/')),
+        None => (rest, ""),
+    };
+
+    let (host, port) = match authority.find(':') {
+        Some(index) => (
+            &authority[..index],
+            authority[index + 1..].parse().map_err(|_| invalid())?,
+        ),
+        None => (authority, 80),
+    };
+
+    if host.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(Endpoint {
+        host: host.to_string(),
+        port,
+        path: path.to_string(),
+    })
+}
+
+/// Connects to the endpoint, trying each of its addresses in turn. Unlike
+/// `TcpStream::connect`, this gives up after `timeout` for each address.
+fn connect(endpoint: &Endpoint, timeout: Duration) -> Result<TcpStream, Error> {
+    let mut last_err = None;
+
+    for addr in (endpoint.host.as_str(), endpoint.port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = Some(err),
+        }
+    }
+
+    Err(Error::Io(last_err.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "host has no addresses")
+    })))
+}
+
+/// Performs a minimal HTTP/1.0 request, returning the response body.
+///
+/// We use HTTP/1.0 so that the server closes the connection after responding
+/// and does not use chunked transfer encoding.
+fn request(config: &Config, method: &str, path: &str, body: Option<&str>) -> Result<String, Error> {
+    let endpoint = parse_endpoint(&config.endpoint)?;
+
+    let mut stream = connect(&endpoint, config.timeout)?;
+    stream.set_read_timeout(Some(config.timeout))?;
+    stream.set_write_timeout(Some(config.timeout))?;
+
+    let body = body.unwrap_or("");
+    write!(
+        stream,
+        "{} {}{} HTTP/1.0\r\n\
+         Host: {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         \r\n\
+         {}",
+        method,
+        endpoint.path,
+        path,
+        endpoint.host,
+        body.len(),
+        body,
+    )?;
+    stream.flush()?;
+
+    // Read one byte more than allowed, so that we can tell if the response
+    // was too large.
+    let mut response = String::new();
+    stream
+        .take(MAX_RESPONSE_SIZE + 1)
+        .read_to_string(&mut response)?;
+
+    if response.len() as u64 > MAX_RESPONSE_SIZE {
+        return Err(Error::ResponseTooLarge);
+    }
+
+    let status: u16 = response
+        .lines()
+        .next()
+        .and_then(|status_line| status_line.split_whitespace().nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or(Error::InvalidResponse)?;
+
+    if !(200..300).contains(&status) {
+        warn!(
+            "{} {}{} failed with status {}",
+            method, endpoint.path, path, status
+        );
+        return Err(Error::Status(status));
+    }
+
+    let body_start = response.find("\r\n\r\n").ok_or(Error::InvalidResponse)? + 4;
+
+    Ok(response[body_start..].to_string())
+}
@@ -0,0 +1,235 @@
+//! Photo mode, in which the world is paused and the player can fly around
+//! freely to take screenshots.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{info, warn};
+use nalgebra as na;
+
+use glium::glutin::VirtualKeyCode;
+
+use crate::input_state::InputState;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub toggle_key: VirtualKeyCode,
+    pub screenshot_key: VirtualKeyCode,
+
+    pub forward_key: VirtualKeyCode,
+    pub left_key: VirtualKeyCode,
+    pub backward_key: VirtualKeyCode,
+    pub right_key: VirtualKeyCode,
+    pub up_key: VirtualKeyCode,
+    pub down_key: VirtualKeyCode,
+    pub yaw_left_key: VirtualKeyCode,
+    pub yaw_right_key: VirtualKeyCode,
+    pub pitch_up_key: VirtualKeyCode,
+    pub pitch_down_key: VirtualKeyCode,
+    pub roll_left_key: VirtualKeyCode,
+    pub roll_right_key: VirtualKeyCode,
+    pub zoom_in_key: VirtualKeyCode,
+    pub zoom_out_key: VirtualKeyCode,
+    pub fast_move_key: VirtualKeyCode,
+
+    pub move_units_per_sec: f32,
+    pub fast_move_multiplier: f32,
+    pub rotate_degrees_per_sec: f32,
+    pub zoom_degrees_per_sec: f32,
+    pub min_fov_degrees: f32,
+    pub max_fov_degrees: f32,
+
+    /// Screenshots are rendered at this multiple of the window's resolution.
+    pub render_scale: u32,
+
+    /// Directory in which screenshots are saved.
+    pub dir: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            toggle_key: VirtualKeyCode::F9,
+            screenshot_key: VirtualKeyCode::F12,
+            forward_key: VirtualKeyCode::W,
+            left_key: VirtualKeyCode::A,
+            backward_key: VirtualKeyCode::S,
+            right_key: VirtualKeyCode::D,
+            up_key: VirtualKeyCode::R,
+            down_key: VirtualKeyCode::F,
+            yaw_left_key: VirtualKeyCode::Left,
+            yaw_right_key: VirtualKeyCode::Right,
+            pitch_up_key: VirtualKeyCode::Up,
+            pitch_down_key: VirtualKeyCode::Down,
+            roll_left_key: VirtualKeyCode::Q,
+            roll_right_key: VirtualKeyCode::E,
+            zoom_in_key: VirtualKeyCode::PageUp,
+            zoom_out_key: VirtualKeyCode::PageDown,
+            fast_move_key: VirtualKeyCode::LShift,
+            move_units_per_sec: 4.0,
+            fast_move_multiplier: 4.0,
+            rotate_degrees_per_sec: 60.0,
+            zoom_degrees_per_sec: 20.0,
+            min_fov_degrees: 10.0,
+            max_fov_degrees: 120.0,
+            render_scale: 1,
+            dir: PathBuf::from("screenshots"),
+        }
+    }
+}
+
+/// A camera that can be moved and rotated freely, starting from the view of
+/// the editor's camera.
+#[derive(Debug, Clone)]
+pub struct FreeCamera {
+    pub eye: na::Point3<f32>,
+    pub yaw_radians: f32,
+    pub pitch_radians: f32,
+    pub roll_radians: f32,
+    pub fov_radians: f32,
+}
+
+impl FreeCamera {
+    pub fn look_at(eye: na::Point3<f32>, target: na::Point3<f32>, fov_radians: f32) -> Self {
+        let direction = (target - eye).normalize();
+
+        Self {
+            eye,
+            yaw_radians: direction.y.atan2(direction.x),
+            pitch_radians: direction.z.asin(),
+            roll_radians: 0.0,
+            fov_radians,
+        }
+    }
+
+    pub fn forward(&self) -> na::Vector3<f32> {
+        na::Vector3::new(
+            self.pitch_radians.cos() * self.yaw_radians.cos(),
+            self.pitch_radians.cos() * self.yaw_radians.sin(),
+            self.pitch_radians.sin(),
+        )
+    }
+
+    pub fn right(&self) -> na::Vector3<f32> {
+        na::Vector3::new(self.yaw_radians.sin(), -self.yaw_radians.cos(), 0.0)
+    }
+
+    pub fn view(&self) -> na::Matrix4<f32> {
+        let forward = self.forward();
+        let roll =
+            na::Rotation3::from_axis_angle(&na::Unit::new_normalize(forward), self.roll_radians);
+        let up = roll.transform_vector(&self.right().cross(&forward));
+
+        na::Matrix4::look_at_rh(&self.eye, &(self.eye + forward), &up)
+    }
+}
+
+pub struct FreeCameraInput {
+    config: Config,
+}
+
+impl FreeCameraInput {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+
+    fn axis(&self, input_state: &InputState, neg: VirtualKeyCode, pos: VirtualKeyCode) -> f32 {
+        let mut value = 0.0;
+
+        if input_state.is_key_pressed(neg) {
+            value -= 1.0;
+        }
+        if input_state.is_key_pressed(pos) {
+            value += 1.0;
+        }
+
+        value
+    }
+
+    pub fn update(&self, dt_secs: f32, input_state: &InputState, camera: &mut FreeCamera) {
+        let fast_multiplier = if input_state.is_key_pressed(self.config.fast_move_key) {
+            self.config.fast_move_multiplier
+        } else {
+            1.0
+        };
+        let move_speed = dt_secs * self.config.move_units_per_sec * fast_multiplier;
+        let rotate_speed = dt_secs * self.config.rotate_degrees_per_sec.to_radians();
+        let zoom_speed = dt_secs * self.config.zoom_degrees_per_sec.to_radians();
+
+        let forward = self.axis(
+            input_state,
+            self.config.backward_key,
+            self.config.forward_key,
+        );
+        let right = self.axis(input_state, self.config.left_key, self.config.right_key);
+        let up = self.axis(input_state, self.config.down_key, self.config.up_key);
+
+        camera.eye += move_speed
+            * (forward * camera.forward()
+                + right * camera.right()
+                + up * na::Vector3::new(0.0, 0.0, 1.0));
+
+        camera.yaw_radians -= rotate_speed
+            * self.axis(
+                input_state,
+                self.config.yaw_left_key,
+                self.config.yaw_right_key,
+            );
+        camera.pitch_radians += rotate_speed
+            * self.axis(
+                input_state,
+                self.config.pitch_down_key,
+                self.config.pitch_up_key,
+            );
+        camera.roll_radians += rotate_speed
+            * self.axis(
+                input_state,
+                self.config.roll_left_key,
+                self.config.roll_right_key,
+            );
+        camera.fov_radians += zoom_speed
+            * self.axis(
+                input_state,
+                self.config.zoom_in_key,
+                self.config.zoom_out_key,
+            );
+
+        // Looking straight up or down would make the view direction parallel
+        // to the up vector.
+        let max_pitch = 89.0f32.to_radians();
+        camera.pitch_radians = camera.pitch_radians.max(-max_pitch).min(max_pitch);
+
+        camera.fov_radians = camera
+            .fov_radians
+            .max(self.config.min_fov_degrees.to_radians())
+            .min(self.config.max_fov_degrees.to_radians());
+    }
+}
+
+/// Saves a screenshot as a PNG file with a unique name in the configured
+/// directory.
+pub fn save_screenshot(config: &Config, image: &image::RgbaImage) {
+    if let Err(err) = fs::create_dir_all(&config.dir) {
+        warn!("Could not create directory {:?}: {}", config.dir, err);
+        return;
+    }
+
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis());
+    let path = config.dir.join(format!("screenshot_{}.png", millis));
+
+    info!(
+        "Saving {}x{} screenshot to file {:?}",
+        image.width(),
+        image.height(),
+        path
+    );
+
+    if let Err(err) = image.save(&path) {
+        warn!("Error while saving screenshot to file {:?}: {}", path, err);
+    }
+}
@@ -0,0 +1,140 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::achievements;
+use crate::exec::Score;
+use crate::machine::Level;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub path: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("progress.json"),
+        }
+    }
+}
+
+/// Statistics of the runs that have completed a level.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevelStats {
+    pub level: Level,
+
+    /// The best value of each metric that was achieved.
+    pub best: Score,
+
+    /// Scores of all runs that completed the level, in chronological order.
+    pub history: Vec<Score>,
+}
+
+/// Numbers about everything the player has done in the game.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LifetimeStats {
+    /// Number of blocks placed in the editor.
+    pub blocks_placed: u64,
+
+    /// Number of ticks that have been run in total, summed over all
+    /// executions.
+    pub ticks_simulated: u64,
+
+    /// Time spent in the game, in seconds.
+    pub playtime_secs: f64,
+}
+
+/// Progress of the player, persisted between sessions. This is the player's
+/// profile, which also backs the campaign and achievements.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Progress {
+    pub levels: Vec<LevelStats>,
+
+    #[serde(default)]
+    pub stats: LifetimeStats,
+
+    #[serde(default)]
+    pub achievements: achievements::State,
+}
+
+impl Progress {
+    /// Loads progress from the given file. If the file does not exist or is
+    /// invalid, we start with empty progress.
+    pub fn load(path: &Path) -> Self {
+        if !path.exists() {
+            info!("No progress file at {:?}, starting fresh", path);
+            return Self::default();
+        }
+
+        info!("Loading progress from file {:?}", path);
+
+        match File::open(path) {
+            Ok(file) => {
+                let reader = BufReader::new(file);
+                serde_json::from_reader(reader).unwrap_or_else(|err| {
+                    warn!("Error while loading progress from file {:?}: {}", path, err);
+                    Self::default()
+                })
+            }
+            Err(err) => {
+                warn!("Could not open file {:?} for reading: {}", path, err);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) {
+        info!("Saving progress to file {:?}", path);
+
+        match File::create(path) {
+            Ok(file) => {
+                if let Err(err) = serde_json::to_writer_pretty(file, self) {
+                    warn!("Error while saving progress to file {:?}: {}", path, err);
+                }
+            }
+            Err(err) => {
+                warn!("Could not open file {:?} for writing: {}", path, err);
+            }
+        }
+    }
+
+    /// Returns the number of levels that have been completed at least once.
+    pub fn levels_solved(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn level_stats(&self, level: &Level) -> Option<&LevelStats> {
+        self.levels.iter().find(|stats| stats.level == *level)
+    }
+
+    pub fn best_score(&self, level: &Level) -> Option<&Score> {
+        self.level_stats(level).map(|stats| &stats.best)
+    }
+
+    /// Records a new score for the level. Returns true if any of the metrics
+    /// has been improved.
+    pub fn record_score(&mut self, level: &Level, score: &Score) -> bool {
+        if let Some(stats) = self.levels.iter_mut().find(|stats| stats.level == *level) {
+            let new_best = stats.best.best(score);
+            let improved = new_best != stats.best;
+
+            stats.best = new_best;
+            stats.history.push(*score);
+
+            improved
+        } else {
+            self.levels.push(LevelStats {
+                level: level.clone(),
+                best: *score,
+                history: vec![*score],
+            });
+
+            true
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! How blip kinds are told apart when rendering.
+//!
+//! By default, blip kinds differ only in color. Players with color vision
+//! deficiencies can select a palette that is safe for them, and additionally
+//! give each kind its own shape. The shapes other than the cube are drawn as
+//! meshes that are shipped in the builtin mesh directory.
+
+use nalgebra as na;
+
+use rendology::{basic_obj, BasicObj};
+
+use crate::machine::BlipKind;
+use crate::render::machine::gamma_correct;
+use crate::render::{textured, Stage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Palette {
+    Default,
+
+    /// Blue and orange, which remain distinct for red-green color blindness.
+    Deuteranopia,
+
+    /// Blue and yellow, avoiding reds that appear dark without L cones.
+    Protanopia,
+
+    /// Vermillion and sky blue, avoiding blue-green and yellow-violet pairs.
+    Tritanopia,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::Default
+    }
+}
+
+impl Palette {
+    pub const ALL: &'static [Palette] = &[
+        Palette::Default,
+        Palette::Deuteranopia,
+        Palette::Protanopia,
+        Palette::Tritanopia,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Default => "Default",
+            Palette::Deuteranopia => "Deuteranopia",
+            Palette::Protanopia => "Protanopia",
+            Palette::Tritanopia => "Tritanopia",
+        }
+    }
+
+    pub fn blip_color(self, kind: BlipKind) -> na::Vector3<f32> {
+        let (a, b) = match self {
+            Palette::Default => (
+                na::Vector3::new(0.0, 128.0, 255.0) / 255.0,
+                na::Vector3::new(0.0, 0.737, 0.361),
+            ),
+            Palette::Deuteranopia => (
+                na::Vector3::new(0.0, 114.0, 178.0) / 255.0,
+                na::Vector3::new(230.0, 159.0, 0.0) / 255.0,
+            ),
+            Palette::Protanopia => (
+                na::Vector3::new(0.0, 114.0, 178.0) / 255.0,
+                na::Vector3::new(240.0, 228.0, 66.0) / 255.0,
+            ),
+            Palette::Tritanopia => (
+                na::Vector3::new(213.0, 94.0, 0.0) / 255.0,
+                na::Vector3::new(86.0, 180.0, 233.0) / 255.0,
+            ),
+        };
+
+        gamma_correct(&match kind {
+            BlipKind::A => a,
+            BlipKind::B => b,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlipShape {
+    Cube,
+    Sphere,
+    Tetrahedron,
+}
+
+impl BlipShape {
+    pub const ALL: &'static [BlipShape] =
+        &[BlipShape::Cube, BlipShape::Sphere, BlipShape::Tetrahedron];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BlipShape::Cube => "Cube",
+            BlipShape::Sphere => "Sphere",
+            BlipShape::Tetrahedron => "Tetrahedron",
+        }
+    }
+
+    /// Name of the builtin mesh that is used to draw the shape. Cubes are
+    /// drawn as `BasicObj::Cube`, so that they can glow.
+    pub fn mesh_name(self) -> Option<&'static str> {
+        match self {
+            BlipShape::Cube => None,
+            BlipShape::Sphere => Some("blip_sphere"),
+            BlipShape::Tetrahedron => Some("blip_tetrahedron"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlipStyle {
+    pub palette: Palette,
+
+    /// If true, blips of kind A and B are drawn with `shape_a` and `shape_b`
+    /// instead of as cubes.
+    pub shape_coding: bool,
+
+    pub shape_a: BlipShape,
+    pub shape_b: BlipShape,
+}
+
+impl Default for BlipStyle {
+    fn default() -> Self {
+        Self {
+            palette: Palette::Default,
+            shape_coding: false,
+            shape_a: BlipShape::Cube,
+            shape_b: BlipShape::Tetrahedron,
+        }
+    }
+}
+
+impl BlipStyle {
+    pub fn color(&self, kind: BlipKind) -> na::Vector3<f32> {
+        self.palette.blip_color(kind)
+    }
+
+    pub fn shape(&self, kind: BlipKind) -> BlipShape {
+        if !self.shape_coding {
+            return BlipShape::Cube;
+        }
+
+        match kind {
+            BlipKind::A => self.shape_a,
+            BlipKind::B => self.shape_b,
+        }
+    }
+
+    /// Renders a marker for the given blip kind. The transform should map the
+    /// unit cube around the origin to the marker's bounds.
+    pub fn render_kind(
+        &self,
+        kind: BlipKind,
+        transform: &na::Matrix4<f32>,
+        color: &na::Vector4<f32>,
+        glow: bool,
+        out: &mut Stage,
+    ) {
+        render_shape(self.shape(kind), transform, color, glow, out);
+    }
+}
+
+/// Renders a blip shape. Only cubes support glow and dithering, other shapes
+/// are always drawn as opaque meshes.
+pub fn render_shape(
+    shape: BlipShape,
+    transform: &na::Matrix4<f32>,
+    color: &na::Vector4<f32>,
+    glow: bool,
+    out: &mut Stage,
+) {
+    match shape.mesh_name() {
+        None => {
+            let list = if glow {
+                &mut out.solid_glow
+            } else {
+                out.solid()
+            };
+
+            list[BasicObj::Cube].add(basic_obj::Instance {
+                transform: *transform,
+                color: *color,
+                ..Default::default()
+            });
+        }
+        Some(mesh_name) => {
+            out.custom(mesh_name).add(textured::Instance {
+                transform: *transform,
+                color: *color,
+                ..Default::default()
+            });
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Decorative elements that sway in the wind to make machines feel alive.
+//!
+//! Decorations are drawn through the wind scene pass, using a nonzero
+//! `idle_anim` in their `wind::Instance`. Their meshes are built such that
+//! the point where they are attached to a block is at the origin, since
+//! vertices sway more the further they are from it.
+
+use nalgebra as na;
+
+use rendology::{basic_obj, CreationError, Mesh};
+
+use crate::machine::grid::{self, Dir3};
+use crate::machine::{Block, Machine};
+use crate::render::machine::{block_center, gamma_correct};
+use crate::render::object::MeshBuilder;
+use crate::render::{wind, Stage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Decoration {
+    Flag,
+    Grass,
+    Cable,
+}
+
+impl Decoration {
+    pub const NUM_INDICES: usize = 3;
+    pub const ALL: [Decoration; Self::NUM_INDICES] =
+        [Decoration::Flag, Decoration::Grass, Decoration::Cable];
+
+    pub fn to_index(self) -> usize {
+        match self {
+            Decoration::Flag => 0,
+            Decoration::Grass => 1,
+            Decoration::Cable => 2,
+        }
+    }
+
+    pub fn mesh(self) -> MeshBuilder {
+        let mut builder = MeshBuilder::new();
+
+        match self {
+            Decoration::Flag => {
+                builder.cylinder(
+                    &na::Point3::origin(),
+                    &na::Point3::new(0.0, 0.0, 0.45),
+                    0.012,
+                    6,
+                );
+
+                let corners = [
+                    na::Point3::new(0.0, 0.0, 0.28),
+                    na::Point3::new(0.25, 0.0, 0.32),
+                    na::Point3::new(0.25, 0.0, 0.42),
+                    na::Point3::new(0.0, 0.0, 0.45),
+                ];
+                double_sided_quad(&mut builder, corners);
+            }
+            Decoration::Grass => {
+                // A tuft of blades that lean away from the center.
+                for i in 0..5 {
+                    let angle = i as f32 / 5.0 * std::f32::consts::PI * 2.0;
+                    let dir = na::Vector3::new(angle.cos(), angle.sin(), 0.0);
+                    let side = na::Vector3::new(-dir.y, dir.x, 0.0) * 0.02;
+                    let base = na::Point3::from(dir * 0.06);
+                    let tip = base + dir * 0.05 + na::Vector3::z() * (0.12 + 0.02 * i as f32);
+
+                    builder.flat_triangle(base - side, base + side, tip);
+                    builder.flat_triangle(base + side, base - side, tip);
+                }
+            }
+            Decoration::Cable => {
+                let end = na::Point3::new(0.0, 0.0, -0.35);
+
+                builder.cylinder(&na::Point3::origin(), &end, 0.008, 6);
+                builder.cuboid(
+                    &(end - na::Vector3::new(0.025, 0.025, 0.05)),
+                    &(end + na::Vector3::new(0.025, 0.025, 0.0)),
+                );
+            }
+        }
+
+        builder
+    }
+
+    /// Amplitude of the sway per unit of distance from the attachment point.
+    pub fn idle_anim(self) -> f32 {
+        match self {
+            Decoration::Flag => 0.12,
+            Decoration::Grass => 0.25,
+            Decoration::Cable => 0.15,
+        }
+    }
+
+    pub fn color(self) -> na::Vector3<f32> {
+        match self {
+            Decoration::Flag => gamma_correct(&na::Vector3::new(0.9, 0.3, 0.25)),
+            Decoration::Grass => gamma_correct(&na::Vector3::new(0.35, 0.7, 0.25)),
+            Decoration::Cable => gamma_correct(&na::Vector3::new(0.2, 0.2, 0.2)),
+        }
+    }
+}
+
+/// Creates the meshes of all decorations, indexed by `Decoration::to_index`.
+pub fn create_meshes<F: glium::backend::Facade>(
+    facade: &F,
+) -> Result<Vec<Mesh<basic_obj::Vertex>>, CreationError> {
+    Decoration::ALL
+        .iter()
+        .map(|decoration| {
+            decoration
+                .mesh()
+                .build_with(facade, |p, n, _| basic_obj::Vertex {
+                    position: [p.x, p.y, p.z],
+                    normal: [n.x, n.y, n.z],
+                })
+        })
+        .collect()
+}
+
+/// Returns the decoration of a block and the point where it is attached, if
+/// the block has one. Only some of the blocks of a type are decorated, so
+/// that machines do not look too uniform.
+pub fn block_decoration(
+    machine: &Machine,
+    pos: &grid::Point3,
+    block: &Block,
+) -> Option<(Decoration, na::Point3<f32>)> {
+    let is_free = |dir: Dir3| {
+        machine
+            .get(&(pos + dir.to_vector()))
+            .map_or(true, |placed_block| placed_block.block.is_air())
+    };
+    let center = block_center(pos);
+    let top = center + na::Vector3::z() * 0.5;
+    let bottom = center - na::Vector3::z() * 0.5;
+
+    // Cheap hash of the position for picking which blocks to decorate.
+    let hash = (pos.x * 7 + pos.y * 13 + pos.z * 5).rem_euclid(4);
+
+    match block {
+        Block::WindSource if is_free(Dir3::Z_POS) => Some((Decoration::Flag, top)),
+        Block::Solid if is_free(Dir3::Z_POS) && hash != 0 => Some((Decoration::Grass, top)),
+        Block::GeneralPipe(dirs)
+            if grid::is_straight(dirs) && !dirs[Dir3::Z_POS] && pos.z > 0 && hash == 0 =>
+        {
+            if is_free(Dir3::Z_NEG) {
+                Some((Decoration::Cable, bottom))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+pub fn render_decoration(machine: &Machine, pos: &grid::Point3, block: &Block, out: &mut Stage) {
+    if let Some((decoration, anchor)) = block_decoration(machine, pos, block) {
+        let color = decoration.color();
+
+        out.decoration(decoration).add(wind::Instance {
+            transform: na::Matrix4::new_translation(&anchor.coords),
+            phase: 0.0,
+            start: 0.0,
+            end: 0.0,
+            idle_anim: decoration.idle_anim(),
+            color: na::Vector4::new(color.x, color.y, color.z, 1.0),
+        });
+    }
+}
+
+fn double_sided_quad(builder: &mut MeshBuilder, corners: [na::Point3<f32>; 4]) {
+    builder.quad(corners);
+    builder.quad([corners[3], corners[2], corners[1], corners[0]]);
+}
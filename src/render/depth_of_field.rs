@@ -0,0 +1,161 @@
+//! Blurring of the scene away from the focus, for backdrops such as the main
+//! menu and photo mode.
+//!
+//! When depth of field is applied, the scene is drawn into offscreen color
+//! and depth textures. A single pass then composes these onto the target,
+//! averaging samples on a disk whose radius grows with the distance to the
+//! focus. The focus is at the depth of the center of the screen, so the
+//! object that the camera looks at stays sharp.
+//!
+//! Whether depth of field is applied is decided for each frame, so it can be
+//! toggled without recreating the render pipeline. Screen-space objects are
+//! drawn afterwards and stay sharp.
+
+use nalgebra as na;
+
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction};
+use glium::Surface;
+
+use crate::render::error::{self, Error};
+use crate::render::offscreen::{self, Targets};
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Whether depth of field is applied behind the main menu.
+    pub menu: bool,
+
+    /// Whether depth of field is applied in photo mode, including
+    /// screenshots.
+    pub photo_mode: bool,
+
+    /// Radius of the strongest blur, in pixels.
+    pub max_radius: f32,
+
+    /// Distance from the focus at which the blur is strongest.
+    pub range: f32,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            menu: true,
+            photo_mode: false,
+            max_radius: 6.0,
+            range: 20.0,
+        }
+    }
+}
+
+const FRAGMENT_SHADER: &str = "
+    #version 330
+
+    uniform sampler2D color_texture;
+    uniform sampler2D depth_texture;
+    uniform mat4 inverse_projection;
+    uniform float max_radius;
+    uniform float range;
+
+    in vec2 v_tex_coord;
+    out vec4 f_color;
+
+    const int NUM_SAMPLES = 16;
+    const float GOLDEN_ANGLE = 2.39996323;
+
+    float view_distance(vec2 tex_coord) {
+        float depth = texture(depth_texture, tex_coord).r;
+        vec4 pos = inverse_projection * vec4(tex_coord * 2.0 - 1.0, depth * 2.0 - 1.0, 1.0);
+
+        return -pos.z / pos.w;
+    }
+
+    void main() {
+        float focus = view_distance(vec2(0.5, 0.5));
+        float blur = clamp(abs(view_distance(v_tex_coord) - focus) / range, 0.0, 1.0);
+        vec2 radius = blur * max_radius / vec2(textureSize(color_texture, 0));
+
+        // Samples on a spiral cover the disk evenly.
+        vec3 sum = texture(color_texture, v_tex_coord).rgb;
+        for (int i = 1; i < NUM_SAMPLES; i++) {
+            float r = sqrt(float(i) / float(NUM_SAMPLES));
+            float angle = float(i) * GOLDEN_ANGLE;
+            vec2 offset = vec2(cos(angle), sin(angle)) * r * radius;
+
+            sum += texture(color_texture, v_tex_coord + offset).rgb;
+        }
+
+        f_color = vec4(sum / float(NUM_SAMPLES), 1.0);
+    }
+";
+
+pub struct DepthOfField {
+    config: Config,
+    program: glium::Program,
+    targets: Targets,
+}
+
+impl DepthOfField {
+    pub fn create<F: glium::backend::Facade>(
+        facade: &F,
+        config: &Config,
+        target_size: (u32, u32),
+    ) -> Result<Self, Error> {
+        let program = glium::Program::from_source(
+            facade,
+            offscreen::SCREEN_QUAD_VERTEX_SHADER,
+            FRAGMENT_SHADER,
+            None,
+        )
+        .map_err(rendology::CreationError::ShaderBuild)
+        .map_err(error::creating("depth of field program"))?;
+        let targets = Targets::create(facade, target_size)?;
+
+        Ok(DepthOfField {
+            config: config.clone(),
+            program,
+            targets,
+        })
+    }
+
+    /// Draws the scene with `draw_scene` into the offscreen targets, and then
+    /// composes the blurred result onto `target`.
+    pub fn draw<F, S>(
+        &mut self,
+        facade: &F,
+        context: &rendology::Context,
+        target: &mut S,
+        draw_scene: impl FnOnce(&mut SimpleFrameBuffer) -> Result<(), Error>,
+    ) -> Result<(), Error>
+    where
+        F: glium::backend::Facade,
+        S: Surface,
+    {
+        self.targets
+            .draw(facade, target.get_dimensions(), draw_scene)?;
+
+        let inverse_projection: [[f32; 4]; 4] = context
+            .camera
+            .projection
+            .try_inverse()
+            .unwrap_or_else(na::Matrix4::identity)
+            .into();
+        let uniforms = glium::uniform! {
+            color_texture: self.targets.color()
+                .sampled()
+                .wrap_function(SamplerWrapFunction::Clamp)
+                .minify_filter(MinifySamplerFilter::Linear)
+                .magnify_filter(MagnifySamplerFilter::Linear),
+            depth_texture: self.targets.depth()
+                .sampled()
+                .wrap_function(SamplerWrapFunction::Clamp)
+                .minify_filter(MinifySamplerFilter::Nearest)
+                .magnify_filter(MagnifySamplerFilter::Nearest),
+            inverse_projection: inverse_projection,
+            max_radius: self.config.max_radius,
+            range: self.config.range.max(std::f32::EPSILON),
+        };
+
+        offscreen::draw_screen_quad(target, &self.program, &uniforms, &Default::default())
+            .map_err(error::drawing("depth of field"))
+    }
+}
@@ -0,0 +1,52 @@
+use std::fmt;
+
+use rendology::pipeline::CreationError;
+use rendology::DrawError;
+
+/// Errors of the render pipeline, together with what was being created or
+/// drawn when they occurred.
+#[derive(Debug)]
+pub enum Error {
+    Creation {
+        what: &'static str,
+        error: CreationError,
+    },
+    Draw {
+        what: &'static str,
+        error: DrawError,
+    },
+
+    /// An offscreen target could not be drawn to.
+    Framebuffer(glium::framebuffer::ValidationError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The errors of rendology implement only `Debug`.
+        match self {
+            Error::Creation { what, error } => write!(f, "Failed to create {}: {:?}", what, error),
+            Error::Draw { what, error } => write!(f, "Failed to draw {}: {:?}", what, error),
+            Error::Framebuffer(error) => write!(f, "Failed to create framebuffer: {:?}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Returns a function for `Result::map_err` that attaches `what` to errors
+/// that occur while creating resources.
+pub fn creating<E: Into<CreationError>>(what: &'static str) -> impl FnOnce(E) -> Error {
+    move |error| Error::Creation {
+        what,
+        error: error.into(),
+    }
+}
+
+/// Returns a function for `Result::map_err` that attaches `what` to errors
+/// that occur while drawing.
+pub fn drawing<E: Into<DrawError>>(what: &'static str) -> impl FnOnce(E) -> Error {
+    move |error| Error::Draw {
+        what,
+        error: error.into(),
+    }
+}
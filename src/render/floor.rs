@@ -2,6 +2,8 @@ use nalgebra as na;
 
 use rendology::{shader, Context, CoreInput, CreationError, Mesh, SceneCore};
 
+use crate::render::object::MeshBuilder;
+
 const SCALE: f32 = 5.0;
 
 #[derive(Clone, Debug)]
@@ -24,26 +26,17 @@ pub struct Vertex {
 glium::implement_vertex!(Vertex, position);
 
 pub fn create_mesh<F: glium::backend::Facade>(facade: &F) -> Result<Mesh<Vertex>, CreationError> {
-    let positions = vec![
-        [-1.0, -1.0, 0.0],
-        [1.0, -1.0, 0.0],
-        [1.0, 1.0, 0.0],
-        [-1.0, 1.0, 0.0],
-    ];
-
-    let vertices = positions
-        .iter()
-        .map(|&p| Vertex { position: p })
-        .collect::<Vec<_>>();
-
-    let indices = vec![0, 1, 2, 2, 3, 0];
-
-    Mesh::create_with_indices(
-        facade,
-        glium::index::PrimitiveType::TrianglesList,
-        &vertices,
-        &indices,
-    )
+    let mut builder = MeshBuilder::new();
+    builder.quad([
+        na::Point3::new(-1.0, -1.0, 0.0),
+        na::Point3::new(1.0, -1.0, 0.0),
+        na::Point3::new(1.0, 1.0, 0.0),
+        na::Point3::new(-1.0, 1.0, 0.0),
+    ]);
+
+    builder.build_with(facade, |p, _, _| Vertex {
+        position: [p.x, p.y, p.z],
+    })
 }
 
 pub struct Core;
@@ -0,0 +1,226 @@
+//! Glass that refracts the scene behind it, so that blips can be seen inside
+//! of pipes.
+//!
+//! Glass is not part of the scene. The scene is drawn into offscreen
+//! targets first, and then copied to the target together with its depth.
+//! Glass is drawn on top, depth tested against the scene, looking up the
+//! color of the scene at a screen-space offset along the glass's normal.
+
+use std::rc::Rc;
+
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::texture::Texture2d;
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter};
+use glium::Surface;
+
+use rendology::basic_obj::{Instance, Vertex};
+use rendology::{shader, Context, CoreInput, InstancingMode, SceneCore};
+
+use crate::render::error::{self, Error};
+use crate::render::offscreen::{self, Targets};
+
+/// How far the scene behind glass is shifted, as a fraction of the screen.
+pub const REFRACTION: f32 = 0.015;
+
+/// How much of the glass's own color is mixed into the scene behind it.
+pub const TINT: f32 = 0.2;
+
+#[derive(Clone)]
+pub struct Params {
+    /// Color of the scene without the glass.
+    pub scene_color: Rc<Texture2d>,
+    pub refraction: f32,
+    pub tint: f32,
+}
+
+rendology::impl_uniform_input!(
+    Params,
+    self => {
+        params_scene_color: &Texture2d = &*self.scene_color,
+        params_refraction: f32 = self.refraction,
+        params_tint: f32 = self.tint,
+    },
+);
+
+const V_VIEW_NORMAL: (&str, shader::VertexOutDef) = (
+    "v_view_normal",
+    shader::VertexOutDef(shader::Type::FloatVec3, shader::VertexOutQualifier::Smooth),
+);
+
+const V_COLOR: (&str, shader::VertexOutDef) = (
+    "v_color",
+    shader::VertexOutDef(shader::Type::FloatVec4, shader::VertexOutQualifier::Flat),
+);
+
+pub struct Core;
+
+impl CoreInput for Core {
+    type Params = Params;
+    type Instance = Instance;
+    type Vertex = Vertex;
+}
+
+impl SceneCore for Core {
+    fn scene_core(&self) -> shader::Core<(Context, Params), Instance, Vertex> {
+        let vertex = shader::VertexCore::empty()
+            .with_out(
+                shader::defs::V_WORLD_NORMAL,
+                "normalize(transpose(inverse(mat3(instance_transform))) * normal)",
+            )
+            .with_out(
+                shader::defs::V_WORLD_POS,
+                "instance_transform * vec4(position, 1.0)",
+            )
+            .with_out(
+                shader::defs::V_POS,
+                "context_camera_projection * context_camera_view * v_world_pos",
+            )
+            .with_out(
+                V_VIEW_NORMAL,
+                "normalize(mat3(context_camera_view) * v_world_normal)",
+            )
+            .with_out(V_COLOR, "instance_color");
+
+        let fragment = shader::FragmentCore::empty()
+            .with_in_def(V_VIEW_NORMAL)
+            .with_in_def(V_COLOR)
+            .with_defs(
+                "
+                vec4 glass_color() {
+                    vec3 normal = normalize(v_view_normal);
+                    vec2 screen_size = vec2(textureSize(params_scene_color, 0));
+                    vec2 tex_coord = gl_FragCoord.xy / screen_size
+                        - normal.xy * params_refraction;
+
+                    // Keep lookups at the edges of the screen in the scene.
+                    tex_coord = clamp(tex_coord, vec2(0.0), vec2(1.0));
+                    vec3 behind = texture(params_scene_color, tex_coord).rgb;
+
+                    // Glass reflects more at grazing angles, where it shows
+                    // more of its own color.
+                    float fresnel = pow(1.0 - abs(normal.z), 3.0);
+                    float tint = mix(params_tint, 1.0, fresnel * 0.5);
+
+                    return vec4(mix(behind, v_color.rgb, tint), 1.0);
+                }
+                ",
+            )
+            .with_out(shader::defs::F_COLOR, "glass_color()");
+
+        shader::Core { vertex, fragment }
+    }
+}
+
+const COPY_FRAGMENT_SHADER: &str = "
+    #version 330
+
+    uniform sampler2D color_texture;
+    uniform sampler2D depth_texture;
+
+    in vec2 v_tex_coord;
+    out vec4 f_color;
+
+    void main() {
+        f_color = texture(color_texture, v_tex_coord);
+        gl_FragDepth = texture(depth_texture, v_tex_coord).r;
+    }
+";
+
+pub struct Glass {
+    program: glium::Program,
+    copy_program: glium::Program,
+    targets: Targets,
+}
+
+impl Glass {
+    pub fn create<F: glium::backend::Facade>(
+        facade: &F,
+        target_size: (u32, u32),
+    ) -> Result<Self, Error> {
+        let program = Core
+            .scene_core()
+            .build_program(facade, InstancingMode::Vertex)
+            .map_err(rendology::CreationError::ShaderBuild)
+            .map_err(error::creating("glass program"))?;
+        let copy_program = glium::Program::from_source(
+            facade,
+            offscreen::SCREEN_QUAD_VERTEX_SHADER,
+            COPY_FRAGMENT_SHADER,
+            None,
+        )
+        .map_err(rendology::CreationError::ShaderBuild)
+        .map_err(error::creating("glass copy program"))?;
+        let targets = Targets::create(facade, target_size)?;
+
+        Ok(Glass {
+            program,
+            copy_program,
+            targets,
+        })
+    }
+
+    /// Returns the program for drawing glass with `Params` from
+    /// `draw_scene`.
+    pub fn program(&self) -> &glium::Program {
+        &self.program
+    }
+
+    /// Draws the scene with `draw_scene` into the offscreen targets, and
+    /// copies it to `target`, including its depth. Returns the parameters
+    /// for drawing glass on top of it.
+    pub fn draw_scene<F, S>(
+        &mut self,
+        facade: &F,
+        target: &mut S,
+        draw_scene: impl FnOnce(&mut SimpleFrameBuffer) -> Result<(), Error>,
+    ) -> Result<Params, Error>
+    where
+        F: glium::backend::Facade,
+        S: Surface,
+    {
+        self.targets
+            .draw(facade, target.get_dimensions(), draw_scene)?;
+
+        let uniforms = glium::uniform! {
+            color_texture: self.targets.color()
+                .sampled()
+                .minify_filter(MinifySamplerFilter::Nearest)
+                .magnify_filter(MagnifySamplerFilter::Nearest),
+            depth_texture: self.targets.depth()
+                .sampled()
+                .minify_filter(MinifySamplerFilter::Nearest)
+                .magnify_filter(MagnifySamplerFilter::Nearest),
+        };
+        let draw_params = glium::DrawParameters {
+            depth: glium::Depth {
+                test: glium::DepthTest::Overwrite,
+                write: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        offscreen::draw_screen_quad(target, &self.copy_program, &uniforms, &draw_params)
+            .map_err(error::drawing("scene behind glass"))?;
+
+        Ok(Params {
+            scene_color: self.targets.color().clone(),
+            refraction: REFRACTION,
+            tint: TINT,
+        })
+    }
+}
+
+/// Returns draw parameters for glass, which is depth tested against the
+/// scene that has been copied to the target.
+pub fn draw_params<'a>() -> glium::DrawParameters<'a> {
+    glium::DrawParameters {
+        backface_culling: glium::draw_parameters::BackfaceCullingMode::CullClockwise,
+        depth: glium::Depth {
+            test: glium::DepthTest::IfLess,
+            write: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
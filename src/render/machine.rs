@@ -1,16 +1,19 @@
 // What follows is horrible
 
+use std::collections::HashSet;
+
 use nalgebra as na;
 
 use rendology::{basic_obj, line, BasicObj, Light};
 
 use crate::machine::grid::{self, Axis3, Dir3, Sign};
-use crate::machine::{BlipKind, Block, Machine, PlacedBlock};
+use crate::machine::{registry, BlipKind, Block, BlockIndex, Machine, PlacedBlock};
 
 use crate::exec::anim::{AnimState, WindLife};
 use crate::exec::{Activation, Exec, LevelProgress, TickTime};
 
-use crate::render::{floor, Stage};
+use crate::render::blip_style::{render_shape, BlipShape, BlipStyle};
+use crate::render::{decoration, floor, textured, Stage};
 
 pub const PIPE_THICKNESS: f32 = 0.04;
 pub const MILL_THICKNESS: f32 = 0.2;
@@ -20,6 +23,8 @@ pub const OUTLINE_MARGIN: f32 = 0.000;
 pub const BRIDGE_MARGIN: f32 = 0.005;
 pub const BUTTON_LENGTH_MIN: f32 = 0.02;
 pub const BUTTON_LENGTH_MAX: f32 = 0.055;
+pub const UNFOCUS_ALPHA: f32 = 0.55;
+pub const GHOST_ALPHA: f32 = 0.2;
 
 const GAMMA: f32 = 2.2;
 
@@ -39,13 +44,6 @@ pub fn wind_stripe_color() -> na::Vector3<f32> {
     gamma_correct(&na::Vector3::new(1.0, 0.325, 0.286))
 }
 
-pub fn blip_color(kind: BlipKind) -> na::Vector3<f32> {
-    gamma_correct(&match kind {
-        BlipKind::A => na::Vector3::new(0.0, 128.0, 255.0) / 255.0,
-        BlipKind::B => na::Vector3::new(0.0, 0.737, 0.361),
-    })
-}
-
 pub fn pipe_color() -> na::Vector3<f32> {
     gamma_correct(&na::Vector3::new(0.85, 0.85, 0.85))
 }
@@ -120,6 +118,19 @@ pub fn pillar_color() -> na::Vector3<f32> {
     gamma_correct(&(na::Vector3::new(180.0, 132.0, 99.0) / 255.0))
 }
 
+/// Color for showing how busy a block is, given `heat` from zero to one.
+pub fn heat_color(heat: f32) -> na::Vector3<f32> {
+    let cold = na::Vector3::new(0.1, 0.2, 0.9);
+    let warm = na::Vector3::new(1.0, 0.9, 0.1);
+    let hot = na::Vector3::new(1.0, 0.1, 0.0);
+
+    gamma_correct(&if heat < 0.5 {
+        cold + (warm - cold) * heat * 2.0
+    } else {
+        warm + (hot - warm) * (heat - 0.5) * 2.0
+    })
+}
+
 pub fn block_color(color: &na::Vector3<f32>, alpha: f32) -> na::Vector4<f32> {
     na::Vector4::new(color.x, color.y, color.z, alpha)
 }
@@ -263,6 +274,68 @@ pub fn render_xy_grid(
     }
 }
 
+/// Parameters that animate the movement of a block while the machine is
+/// running. They are derived only from the block's `AnimState` and the time
+/// within the current tick, so that blocks move in sync with the simulation.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BlockAnim {
+    /// Rises from zero to one and back once during a tick in which the block
+    /// is activated.
+    pub pulse: f32,
+
+    /// Amount of wind flowing out of the block, fading in and out together
+    /// with the wind.
+    pub flow: f32,
+
+    /// Rises and falls once per tick while wind is flowing, scaled by `flow`.
+    pub beat: f32,
+
+    /// Angle that increases by a half turn per tick while wind is flowing.
+    /// Since wind takes half a tick to start or stop, the angle is only
+    /// continuous between ticks for shapes with quarter-turn symmetry.
+    pub spin: f32,
+}
+
+impl BlockAnim {
+    pub fn new(tick_time: &TickTime, anim_state: Option<&AnimState>) -> Self {
+        let anim_state = match anim_state {
+            Some(anim_state) => anim_state,
+            None => return Self::default(),
+        };
+
+        let t = tick_time.tick_progress();
+
+        let wave = (t * std::f32::consts::PI).sin().powi(2);
+        let pulse = if anim_state.activation.is_some() {
+            wave
+        } else {
+            0.0
+        };
+
+        // Take the strongest wind in any direction, where appearing or
+        // disappearing wind is faded linearly, and integrate it for the spin.
+        let (flow, spin) = anim_state
+            .wind_out
+            .values()
+            .map(|life| match life {
+                WindLife::None => (0.0, 0.0),
+                WindLife::Appearing => (t, t * t / 2.0),
+                WindLife::Existing => (1.0, t),
+                WindLife::Disappearing => (1.0 - t, t - t * t / 2.0),
+            })
+            .fold((0.0f32, 0.0f32), |(flow, spin), (f, s)| {
+                (flow.max(f), spin.max(s))
+            });
+
+        Self {
+            pulse,
+            flow,
+            beat: flow * wave,
+            spin: spin * std::f32::consts::PI,
+        }
+    }
+}
+
 pub fn blip_spawn_scaling_anim(
     activation: Option<BlipKind>,
 ) -> pareen::Anim<impl pareen::Fun<T = f32, V = f32>> {
@@ -586,9 +659,29 @@ pub fn render_block(
     center: &na::Point3<f32>,
     transform: &na::Matrix4<f32>,
     alpha: f32,
+    blip_style: &BlipStyle,
     out: &mut Stage,
 ) {
     let translation = na::Matrix4::new_translation(&center.coords);
+    let block_anim = BlockAnim::new(tick_time, anim_state);
+
+    // Block types may be drawn with a mesh from the registry instead of the
+    // builtin rendering below.
+    if let Some(mesh) = registry::get(placed_block.block.block_type()).mesh {
+        let color = anim_state
+            .and_then(|s| s.activation)
+            .map_or_else(inactive_blip_duplicator_color, |kind| {
+                blip_style.color(kind)
+            });
+
+        out.custom(mesh).add(textured::Instance {
+            transform: translation * transform,
+            color: block_color(&color, alpha),
+            ..Default::default()
+        });
+
+        return;
+    }
 
     match placed_block.block {
         Block::Pipe(dir_a, dir_b) => {
@@ -655,7 +748,8 @@ pub fn render_block(
             });
             render_outline(&cube_transform, &scaling, alpha, out);
 
-            let input_size = 0.3;
+            // The input part pumps while wind flows through the funnel.
+            let input_size = 0.3 * (1.0 + 0.2 * block_anim.beat);
             let input_transform = translation
                 * transform
                 * flow_dir.invert().to_rotation_mat_x()
@@ -680,7 +774,9 @@ pub fn render_block(
             );
         }
         Block::WindSource => {
-            let cube_transform = translation * transform;
+            let cube_transform = translation
+                * transform
+                * na::Matrix4::new_rotation(na::Vector3::z() * block_anim.spin);
             let scaling = na::Vector3::new(0.6, 0.6, 0.6);
 
             let render_list = if anim_state.is_some() {
@@ -722,7 +818,7 @@ pub fn render_block(
             let activation = anim_state.and_then(|s| s.activation);
             let scaling_anim = blip_spawn_scaling_anim(activation);
 
-            let cube_color = block_color(&blip_color(kind), alpha);
+            let cube_color = block_color(&blip_style.color(kind), alpha);
             let cube_transform = translation
                 * transform
                 * out_dir.to_rotation_mat_x()
@@ -732,11 +828,13 @@ pub fn render_block(
                 scaling_anim.as_ref() * pareen::constant(na::Vector3::new(0.5, 0.6, 0.6));
             let size = size_anim.eval(tick_time.tick_progress());
 
-            out.solid()[BasicObj::Cube].add(basic_obj::Instance {
-                transform: cube_transform * na::Matrix4::new_nonuniform_scaling(&size),
-                color: cube_color,
-                ..Default::default()
-            });
+            blip_style.render_kind(
+                kind,
+                &(cube_transform * na::Matrix4::new_nonuniform_scaling(&size)),
+                &cube_color,
+                false,
+                out,
+            );
 
             render_outline(&cube_transform, &size, alpha, out);
 
@@ -758,21 +856,31 @@ pub fn render_block(
             render_bridge(&bridge_anim.eval(tick_time.tick_progress()), transform, out);
         }
         Block::BlipDuplicator { out_dirs, kind, .. } => {
-            let cube_transform = translation * transform * out_dirs.0.to_rotation_mat_x();
+            // Twist the cube around its output axis while duplicating.
+            let cube_transform = translation
+                * transform
+                * out_dirs.0.to_rotation_mat_x()
+                * na::Matrix4::new_rotation(na::Vector3::x() * block_anim.pulse * 0.4);
             let activation = anim_state.and_then(|s| s.activation);
             let next_activation = anim_state.and_then(|s| s.next_activation);
-            let kind_color = activation.map_or_else(inactive_blip_duplicator_color, blip_color);
+            let kind_color = activation.map_or_else(inactive_blip_duplicator_color, |kind| {
+                blip_style.color(kind)
+            });
 
             let scaling_anim = blip_spawn_scaling_anim(activation);
             let size_anim =
                 scaling_anim.as_ref() * pareen::constant(na::Vector3::new(0.45, 0.6, 0.6));
             let size = size_anim.eval(tick_time.tick_progress());
 
-            out.solid()[BasicObj::Cube].add(basic_obj::Instance {
-                transform: cube_transform * na::Matrix4::new_nonuniform_scaling(&size),
-                color: block_color(&kind_color, alpha),
-                ..Default::default()
-            });
+            // Only show the shape of the kind that is being duplicated.
+            let shape = activation.map_or(BlipShape::Cube, |kind| blip_style.shape(kind));
+            render_shape(
+                shape,
+                &(cube_transform * na::Matrix4::new_nonuniform_scaling(&size)),
+                &block_color(&kind_color, alpha),
+                false,
+                out,
+            );
             render_outline(&cube_transform, &size, alpha, out);
 
             let bridge_length =
@@ -796,7 +904,7 @@ pub fn render_block(
 
             let button_length = button_length_anim(&activation, &next_activation, size.y)
                 .eval(tick_time.tick_progress());
-            let button_color = kind.map_or(button_color(), blip_color);
+            let button_color = kind.map_or(button_color(), |kind| blip_style.color(kind));
 
             for &dir in &Dir3::ALL {
                 if dir == out_dirs.0 || dir == out_dirs.1 {
@@ -908,7 +1016,9 @@ pub fn render_block(
             let rotation = na::Matrix4::from_euler_angles(angle, 0.0, 0.0);
 
             let color = block_color(
-                &active_blip_kind.map_or(na::Vector3::new(0.3, 0.3, 0.3), blip_color),
+                &active_blip_kind.map_or(na::Vector3::new(0.3, 0.3, 0.3), |kind| {
+                    blip_style.color(kind)
+                }),
                 alpha,
             );
 
@@ -955,15 +1065,10 @@ pub fn render_block(
 
             let status_color = |progress: Option<&LevelProgress>| {
                 let (failed, completed) = progress
-                    .and_then(|progress| {
-                        progress.outputs.get(index).map(|output| {
-                            let num_expected = progress.inputs_outputs.outputs[index].len();
-                            let completed = output.num_fed == num_expected;
-
-                            (output.failed, completed)
-                        })
-                    })
-                    .unwrap_or((false, false));
+                    .and_then(|progress| progress.output_progress(index))
+                    .map_or((false, false), |output| {
+                        (output.failed, output.num_fed == output.num_expected)
+                    });
 
                 output_status_color(failed, completed)
             };
@@ -975,7 +1080,7 @@ pub fn render_block(
 
             let expected_color_anim = pareen::constant(expected_output)
                 .seq(0.6, next_expected_output)
-                .map(|kind| kind.map_or(impatient_bridge_color(), blip_color))
+                .map(|kind| kind.map_or(impatient_bridge_color(), |kind| blip_style.color(kind)))
                 .map(|color| block_color(&color, alpha));
 
             let status_color_anim = pareen::constant(status_color(level_progress))
@@ -1009,7 +1114,7 @@ pub fn render_block(
         } => {
             let activation = anim_state.and_then(|s| s.activation.as_ref());
             let kind_color = match activation.or(kind.as_ref()) {
-                Some(kind) => blip_color(*kind),
+                Some(kind) => blip_style.color(*kind),
                 None => inactive_blip_duplicator_color(),
             };
             let pipe_color = block_color(&pipe_color(), alpha);
@@ -1166,7 +1271,7 @@ pub fn render_block(
                 * pos_dir.to_rotation_mat_x()
                 * na::Matrix4::new_nonuniform_scaling(&na::Vector3::new(0.005, size, size));
             let detector_color = block_color(
-                &activation.map_or_else(button_color, blip_color),
+                &activation.map_or_else(button_color, |kind| blip_style.color(kind)),
                 alpha * 0.5,
             );
 
@@ -1268,6 +1373,47 @@ pub fn render_block(
                 );
             }
         }
+        Block::Scripted {
+            flow_dir, ref mesh, ..
+        } => {
+            let activation = anim_state.and_then(|s| s.activation);
+            let color = activation.map_or_else(inactive_blip_duplicator_color, |kind| {
+                blip_style.color(kind)
+            });
+
+            if let Some(mesh) = mesh {
+                // Custom meshes are modeled with the flow in positive X
+                // direction.
+                out.custom(mesh).add(textured::Instance {
+                    transform: translation * transform * flow_dir.invert().to_rotation_mat_x(),
+                    color: block_color(&color, alpha),
+                    ..Default::default()
+                });
+            } else {
+                let pulsate =
+                    pulsator_size_anim(activation.is_some()).eval(tick_time.tick_progress());
+                let cube_transform = translation * transform;
+                let scaling = na::Vector3::new(0.6, 0.6, 0.6) * pulsate;
+
+                out.solid()[BasicObj::Cube].add(basic_obj::Instance {
+                    transform: cube_transform * na::Matrix4::new_nonuniform_scaling(&scaling),
+                    color: block_color(&color, alpha),
+                    ..Default::default()
+                });
+                render_outline(&cube_transform, &scaling, alpha, out);
+
+                let pipe_color = block_color(&pipe_color(), alpha);
+
+                render_half_pipe(center, transform, flow_dir, &pipe_color, out.solid());
+                render_half_pipe(
+                    center,
+                    transform,
+                    flow_dir.invert(),
+                    &pipe_color,
+                    out.solid(),
+                );
+            }
+        }
         Block::Delay { flow_dir } => {
             let prev_activation = anim_state.and_then(|s| s.prev_activation);
             let activation = anim_state.and_then(|s| s.activation);
@@ -1290,9 +1436,13 @@ pub fn render_block(
                     0.0,
                 ));
 
-            let part_1_color = activation.map_or_else(inactive_blip_duplicator_color, blip_color);
-            let part_2_color =
-                prev_activation.map_or_else(inactive_blip_duplicator_color, blip_color);
+            let part_1_color = activation.map_or_else(inactive_blip_duplicator_color, |kind| {
+                blip_style.color(kind)
+            });
+            let part_2_color = prev_activation
+                .map_or_else(inactive_blip_duplicator_color, |kind| {
+                    blip_style.color(kind)
+                });
 
             let part_1_pulsate =
                 pulsator_size_anim(activation.is_some()).eval(tick_time.tick_progress());
@@ -1403,15 +1553,49 @@ pub fn render_machine<'a>(
     tick_time: &TickTime,
     exec: Option<&Exec>,
     filter: impl Fn(&'a grid::Point3) -> bool,
-    unfocus: impl Fn(&'a grid::Point3) -> bool,
+    alpha: impl Fn(&'a grid::Point3) -> f32,
+    glass: impl Fn(&'a grid::Point3) -> bool,
+    tint: impl Fn(&'a grid::Point3) -> Option<na::Vector3<f32>>,
+    blip_style: &BlipStyle,
     out: &mut Stage,
 ) {
+    // Blocks that are tinted are first rendered into a separate stage, so that
+    // we can override the color of everything that belongs to them.
+    let mut tint_stage = Stage::default();
+
     out.floor.add(floor::Instance {
         size: na::Vector2::new(machine.size().x as f32, machine.size().y as f32),
     });
 
+    // Straight pipes are drawn in merged runs, so they are skipped below.
+    let pipe_runs = find_pipe_runs(machine, &filter, &alpha, &glass, &tint);
+    let merged_blocks: HashSet<BlockIndex> = pipe_runs
+        .iter()
+        .flat_map(|run| run.blocks.iter().cloned())
+        .collect();
+
+    for run in &pipe_runs {
+        out.dither = run.alpha < 1.0;
+        out.glass = run.glass;
+        render_pipe_run(run, out);
+    }
+    out.dither = false;
+    out.glass = false;
+
+    // Decorations are not drawn for transparent, glass or tinted blocks, since
+    // the wind shader supports none of them.
+    for (_, (block_pos, placed_block)) in machine.iter_blocks() {
+        if filter(&block_pos)
+            && alpha(&block_pos) >= 1.0
+            && !glass(&block_pos)
+            && tint(&block_pos).is_none()
+        {
+            decoration::render_decoration(machine, block_pos, &placed_block.block, out);
+        }
+    }
+
     for (block_index, (block_pos, placed_block)) in machine.iter_blocks() {
-        if !filter(&block_pos) {
+        if !filter(&block_pos) || merged_blocks.contains(&block_index) {
             continue;
         }
 
@@ -1422,12 +1606,19 @@ pub fn render_machine<'a>(
         let level_progress = exec.and_then(|exec| exec.level_progress());
         let next_level_progress = exec.and_then(|exec| exec.next_level_progress());
 
-        let alpha = if unfocus(&block_pos) {
+        // Transparent blocks are dithered.
+        let alpha = alpha(&block_pos);
+        if alpha < 1.0 {
             out.dither = true;
-            0.55
-        } else {
-            1.0
-        };
+        }
+        out.glass = glass(&block_pos);
+
+        let block_tint = tint(&block_pos);
+        if block_tint.is_some() {
+            tint_stage.clear();
+            tint_stage.dither = out.dither;
+            tint_stage.glass = out.glass;
+        }
 
         render_block(
             &placed_block,
@@ -1438,17 +1629,195 @@ pub fn render_machine<'a>(
             &center,
             &transform,
             alpha,
-            out,
+            blip_style,
+            if block_tint.is_some() {
+                &mut tint_stage
+            } else {
+                out
+            },
         );
 
+        if let Some(block_tint) = block_tint {
+            add_tinted(&mut tint_stage, &block_tint, out);
+        }
+
         if !placed_block.block.is_air() && !is_straight_pipe(&placed_block.block) {
             render_pillar(machine, block_pos, alpha, out);
         }
 
         out.dither = false;
+        out.glass = false;
+    }
+}
+
+/// A run of neighboring straight pipes along the same axis.
+struct PipeRun {
+    start: grid::Point3,
+    axis: Axis3,
+    alpha: f32,
+    glass: bool,
+    blocks: Vec<BlockIndex>,
+}
+
+/// Returns the axis of pipes that go straight through their block. Blocks
+/// that are drawn with a mesh from the registry are never merged.
+fn straight_pipe_axis(block: &Block) -> Option<Axis3> {
+    if registry::get(block.block_type()).mesh.is_some() {
+        return None;
+    }
+
+    match block {
+        Block::GeneralPipe(dirs) if grid::is_straight(dirs) => {
+            Dir3::ALL.iter().find(|&&dir| dirs[dir]).map(|dir| dir.0)
+        }
+        _ => None,
     }
 }
 
+/// Greedily merges straight pipes into maximal runs, so that each run can be
+/// drawn as one stretched cube instead of two half pipes per block. This
+/// avoids visible seams between the pipes and cuts down on instances.
+///
+/// Only pipes that would be drawn identically are merged, i.e. pipes that
+/// pass the filter, have the same alpha, are either all glass or not, and are
+/// not tinted.
+fn find_pipe_runs<'a>(
+    machine: &'a Machine,
+    filter: &impl Fn(&'a grid::Point3) -> bool,
+    alpha: &impl Fn(&'a grid::Point3) -> f32,
+    glass: &impl Fn(&'a grid::Point3) -> bool,
+    tint: &impl Fn(&'a grid::Point3) -> Option<na::Vector3<f32>>,
+) -> Vec<PipeRun> {
+    let mergeable = |pos: &grid::Point3| -> Option<(BlockIndex, Axis3, f32, bool)> {
+        let block_index = machine.get_index(pos)?;
+        let (pos, placed_block) = &machine.blocks.data[block_index];
+
+        if !filter(pos) || tint(pos).is_some() {
+            return None;
+        }
+
+        straight_pipe_axis(&placed_block.block)
+            .map(|axis| (block_index, axis, alpha(pos), glass(pos)))
+    };
+
+    let mut runs = Vec::new();
+
+    for (block_index, (pos, _)) in machine.iter_blocks() {
+        let (axis, run_alpha, run_glass) = match mergeable(pos) {
+            Some((_, axis, alpha, glass)) => (axis, alpha, glass),
+            None => continue,
+        };
+        let continues_run = |pos: &grid::Point3| {
+            mergeable(pos)
+                .filter(|&(_, other_axis, other_alpha, other_glass)| {
+                    other_axis == axis && other_alpha == run_alpha && other_glass == run_glass
+                })
+                .map(|(block_index, _, _, _)| block_index)
+        };
+
+        // Runs are only started at their first block.
+        let step = axis.to_vector();
+        if continues_run(&(pos - step)).is_some() {
+            continue;
+        }
+
+        let mut blocks = vec![block_index];
+        let mut next = pos + step;
+
+        while let Some(next_index) = continues_run(&next) {
+            blocks.push(next_index);
+            next += step;
+        }
+
+        runs.push(PipeRun {
+            start: *pos,
+            axis,
+            alpha: run_alpha,
+            glass: run_glass,
+            blocks,
+        });
+    }
+
+    runs
+}
+
+fn render_pipe_run(run: &PipeRun, out: &mut Stage) {
+    let length = run.blocks.len() as f32;
+    let dir = Dir3(run.axis, Sign::Pos);
+    let end = run.start + run.axis.to_vector() * (run.blocks.len() as isize - 1);
+    let center = na::center(&block_center(&run.start), &block_center(&end));
+
+    let scaling = na::Matrix4::new_nonuniform_scaling(&na::Vector3::new(
+        length,
+        PIPE_THICKNESS,
+        PIPE_THICKNESS,
+    ));
+
+    out.solid()[BasicObj::Cube].add(basic_obj::Instance {
+        transform: na::Matrix4::new_translation(&center.coords) * dir.to_rotation_mat_x() * scaling,
+        color: block_color(&pipe_color(), run.alpha),
+        ..Default::default()
+    });
+}
+
+/// How much the color of tinted objects is replaced by the tint.
+const TINT_AMOUNT: f32 = 0.75;
+
+/// Moves everything that has been rendered into `stage` to `out`, mixing the
+/// color of all objects with the tint. Outlines and lights are kept as they
+/// are.
+pub fn add_tinted(stage: &mut Stage, tint: &na::Vector3<f32>, out: &mut Stage) {
+    let tint_color = |color: &na::Vector4<f32>| {
+        na::Vector4::new(
+            color.x * (1.0 - TINT_AMOUNT) + tint.x * TINT_AMOUNT,
+            color.y * (1.0 - TINT_AMOUNT) + tint.y * TINT_AMOUNT,
+            color.z * (1.0 - TINT_AMOUNT) + tint.z * TINT_AMOUNT,
+            color.w,
+        )
+    };
+
+    let lists = vec![
+        (&stage.solid, &mut out.solid),
+        (&stage.solid_dither, &mut out.solid_dither),
+        (&stage.solid_glow, &mut out.solid_glow),
+        (&stage.solid_glass, &mut out.solid_glass),
+    ];
+
+    for (list, out_list) in lists {
+        for &object in &[
+            BasicObj::Cube,
+            BasicObj::Quad,
+            BasicObj::TessellatedCylinder,
+        ] {
+            for instance in list[object].as_slice() {
+                out_list[object].add(basic_obj::Instance {
+                    transform: instance.transform,
+                    color: tint_color(&instance.color),
+                });
+            }
+        }
+    }
+
+    for (mesh_name, list) in stage.custom.iter() {
+        for instance in list.as_slice() {
+            out.custom(mesh_name).add(textured::Instance {
+                color: tint_color(&instance.color),
+                ..instance.clone()
+            });
+        }
+    }
+
+    for instance in stage.lines.as_slice() {
+        out.lines.add(line::Instance {
+            transform: instance.transform,
+            color: instance.color,
+            thickness: instance.thickness,
+        });
+    }
+
+    out.lights.extend(stage.lights.drain(..));
+}
+
 fn is_straight_pipe(block: &Block) -> bool {
     match block {
         Block::GeneralPipe(dirs) => grid::is_straight(dirs),
@@ -0,0 +1,245 @@
+//! Loading of external meshes in the Wavefront OBJ format.
+//!
+//! Meshes are registered by name, so that blocks can refer to them without
+//! the mesh generation code knowing about them. If there is a PNG file next to
+//! the OBJ file with the same name, it is used as the mesh's texture.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+use nalgebra as na;
+
+use rendology::Mesh;
+
+use crate::render::object::MeshBuilder;
+use crate::render::textured;
+
+/// Directory that contains the meshes that are shipped with the game.
+pub const BUILTIN_DIR: &str = "resources";
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+    Creation(rendology::CreationError),
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> LoadError {
+        LoadError::Io(err)
+    }
+}
+
+impl From<rendology::CreationError> for LoadError {
+    fn from(err: rendology::CreationError) -> LoadError {
+        LoadError::Creation(err)
+    }
+}
+
+fn parse_floats(line: usize, parts: &[&str]) -> Result<Vec<f32>, LoadError> {
+    parts
+        .iter()
+        .map(|part| {
+            part.parse().map_err(|_| LoadError::Parse {
+                line,
+                message: format!("invalid number {:?}", part),
+            })
+        })
+        .collect()
+}
+
+/// Resolves a one-based, possibly negative OBJ index into a zero-based index.
+fn parse_index(line: usize, s: &str, len: usize) -> Result<usize, LoadError> {
+    let index: isize = s.parse().map_err(|_| LoadError::Parse {
+        line,
+        message: format!("invalid index {:?}", s),
+    })?;
+
+    let resolved = if index < 0 {
+        len as isize + index
+    } else {
+        index - 1
+    };
+
+    if resolved >= 0 && (resolved as usize) < len {
+        Ok(resolved as usize)
+    } else {
+        Err(LoadError::Parse {
+            line,
+            message: format!("index {} out of range", index),
+        })
+    }
+}
+
+/// Parses the geometry of an OBJ file. Materials and groups are ignored.
+/// Polygons are triangulated as fans, and faces without normals are shaded
+/// flat.
+pub fn parse_obj(source: &str) -> Result<MeshBuilder, LoadError> {
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut tex_coords = Vec::new();
+    let mut builder = MeshBuilder::new();
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let parts: Vec<&str> = line.split_whitespace().collect();
+
+        match parts.first() {
+            Some(&"v") if parts.len() >= 4 => {
+                let p = parse_floats(line_number, &parts[1..4])?;
+                positions.push(na::Point3::new(p[0], p[1], p[2]));
+            }
+            Some(&"vn") if parts.len() >= 4 => {
+                let n = parse_floats(line_number, &parts[1..4])?;
+                normals.push(na::Vector3::new(n[0], n[1], n[2]));
+            }
+            Some(&"vt") if parts.len() >= 3 => {
+                let t = parse_floats(line_number, &parts[1..3])?;
+                tex_coords.push(na::Vector2::new(t[0], t[1]));
+            }
+            Some(&"f") if parts.len() >= 4 => {
+                let mut face = Vec::new();
+
+                for vertex in &parts[1..] {
+                    let mut indices = vertex.split('/');
+                    let position =
+                        parse_index(line_number, indices.next().unwrap_or(""), positions.len())?;
+                    let tex_coord = match indices.next() {
+                        Some(s) if !s.is_empty() => {
+                            Some(parse_index(line_number, s, tex_coords.len())?)
+                        }
+                        _ => None,
+                    };
+                    let normal = match indices.next() {
+                        Some(s) if !s.is_empty() => {
+                            Some(parse_index(line_number, s, normals.len())?)
+                        }
+                        _ => None,
+                    };
+
+                    face.push((
+                        positions[position],
+                        normal.map(|i| normals[i]),
+                        tex_coord.map_or_else(na::Vector2::zeros, |i| tex_coords[i]),
+                    ));
+                }
+
+                for i in 1..face.len() - 1 {
+                    let triangle = [face[0], face[i], face[i + 1]];
+                    let flat_normal = (triangle[1].0 - triangle[0].0)
+                        .cross(&(triangle[2].0 - triangle[0].0))
+                        .try_normalize(std::f32::EPSILON)
+                        .unwrap_or_else(na::Vector3::z);
+
+                    let indices: Vec<u32> = triangle
+                        .iter()
+                        .map(|(position, normal, tex_coord)| {
+                            builder.vertex(*position, normal.unwrap_or(flat_normal), *tex_coord)
+                        })
+                        .collect();
+                    builder.triangle(indices[0], indices[1], indices[2]);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(builder)
+}
+
+pub fn load_obj<F: glium::backend::Facade>(
+    facade: &F,
+    path: &Path,
+) -> Result<Mesh<textured::Vertex>, LoadError> {
+    let source = fs::read_to_string(path)?;
+    let builder = parse_obj(&source)?;
+
+    Ok(builder.build(facade)?)
+}
+
+/// Returns names and paths of the OBJ files in the directory, where the name
+/// is the file stem.
+pub fn list_dir(dir: &Path) -> Vec<(String, PathBuf)> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut meshes: Vec<(String, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "obj"))
+        .filter_map(|path| {
+            let stem = path.file_stem()?.to_str()?.to_string();
+            Some((stem, path))
+        })
+        .collect();
+    meshes.sort();
+
+    meshes
+}
+
+pub struct Entry {
+    pub mesh: Mesh<textured::Vertex>,
+
+    /// Layer of the mesh's texture in `Registry::textures`, or zero if the
+    /// mesh has no texture.
+    pub texture_id: u32,
+}
+
+/// Loaded meshes, keyed by name, together with their textures.
+pub struct Registry {
+    meshes: HashMap<String, Entry>,
+    textures: textured::Textures,
+}
+
+impl Registry {
+    /// Loads the given meshes. Meshes that fail to load are skipped, and
+    /// meshes with an existing name replace the old mesh.
+    pub fn load<F: glium::backend::Facade>(
+        facade: &F,
+        meshes: &[(String, PathBuf)],
+    ) -> Result<Registry, glium::texture::TextureCreationError> {
+        let texture_paths: Vec<(String, PathBuf)> = meshes
+            .iter()
+            .map(|(name, path)| (name.clone(), path.with_extension("png")))
+            .filter(|(_, path)| path.exists())
+            .collect();
+        let textures = textured::Textures::load(facade, &texture_paths)?;
+
+        let mut meshes_by_name = HashMap::new();
+
+        for (name, path) in meshes {
+            info!("Loading mesh {} from file {:?}", name, path);
+
+            match load_obj(facade, path) {
+                Ok(mesh) => {
+                    let texture_id = textures.id(name).unwrap_or(0);
+                    meshes_by_name.insert(name.clone(), Entry { mesh, texture_id });
+                }
+                Err(err) => {
+                    warn!(
+                        "Failed to load mesh {} from file {:?}: {:?}",
+                        name, path, err
+                    );
+                }
+            }
+        }
+
+        Ok(Registry {
+            meshes: meshes_by_name,
+            textures,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Entry> {
+        self.meshes.get(name)
+    }
+
+    pub fn textures(&self) -> &textured::Textures {
+        &self.textures
+    }
+}
@@ -1,13 +1,29 @@
+pub mod blip_style;
+pub mod decoration;
+pub mod depth_of_field;
+mod error;
 pub mod floor;
+pub mod glass;
 pub mod machine;
+pub mod mesh;
+pub mod object;
+pub mod offscreen;
+pub mod sort;
+#[cfg(test)]
+mod tests;
+pub mod textured;
+pub mod vignette;
 pub mod wind;
 
+use std::collections::HashMap;
+use std::path::PathBuf;
+
 use nalgebra as na;
 
 use coarse_prof::profile;
+use log::warn;
 
 use rendology::particle::Particle;
-use rendology::pipeline::CreationError;
 use rendology::{
     basic_obj, dither, line, particle, BasicObj, Camera, Drawable, Instancing, InstancingMode,
     Light, Mesh, PlainScenePass, RenderList, SceneCore, ShadedScenePass, ShadedScenePassSetup,
@@ -16,16 +32,33 @@ use rendology::{
 
 use crate::exec::TickTime;
 
+use decoration::Decoration;
+use depth_of_field::DepthOfField;
+use glass::Glass;
+
+pub use error::Error;
+
 #[derive(Default)]
 pub struct Stage {
     pub dither: bool,
 
+    /// Whether solids are drawn as glass, which refracts the scene behind
+    /// it. Takes precedence over `dither`.
+    pub glass: bool,
+
     pub floor: RenderList<floor::Instance>,
     pub solid: basic_obj::RenderList<basic_obj::Instance>,
     pub solid_dither: basic_obj::RenderList<basic_obj::Instance>,
     pub solid_glow: basic_obj::RenderList<basic_obj::Instance>,
+    pub solid_glass: basic_obj::RenderList<basic_obj::Instance>,
     pub wind: RenderList<wind::Instance>,
 
+    /// Decorations, which are drawn with the wind shader.
+    pub decorations: HashMap<Decoration, RenderList<wind::Instance>>,
+
+    /// Instances of meshes in the `mesh::Registry`, keyed by mesh name.
+    pub custom: HashMap<String, RenderList<textured::Instance>>,
+
     pub lights: Vec<Light>,
 
     pub plain: basic_obj::RenderList<basic_obj::Instance>,
@@ -41,6 +74,13 @@ pub struct Stage {
 pub struct Context {
     pub rendology: rendology::Context,
     pub tick_time: TickTime,
+
+    /// Time in seconds that keeps running even while execution is paused.
+    /// This drives idle animations.
+    pub time: f32,
+
+    /// Whether depth of field is applied, if the pipeline has it.
+    pub depth_of_field: bool,
 }
 
 impl Stage {
@@ -49,7 +89,14 @@ impl Stage {
         self.solid.clear();
         self.solid_dither.clear();
         self.solid_glow.clear();
+        self.solid_glass.clear();
         self.wind.clear();
+        for list in self.decorations.values_mut() {
+            list.clear();
+        }
+        for list in self.custom.values_mut() {
+            list.clear();
+        }
         self.lights.clear();
         self.plain.clear();
         self.lines.clear();
@@ -58,30 +105,127 @@ impl Stage {
     }
 
     pub fn solid(&mut self) -> &mut basic_obj::RenderList<basic_obj::Instance> {
-        if self.dither {
+        if self.glass {
+            &mut self.solid_glass
+        } else if self.dither {
             &mut self.solid_dither
         } else {
             &mut self.solid
         }
     }
+
+    /// Orders the instances for drawing them as seen from `eye`. Opaque
+    /// objects are drawn front to back, while the alpha-blended lines are
+    /// drawn back to front.
+    pub fn sort(&mut self, eye: &na::Point3<f32>) {
+        profile!("sort");
+
+        let opaque_lists = vec![
+            &mut self.solid,
+            &mut self.solid_dither,
+            &mut self.solid_glow,
+            &mut self.solid_glass,
+            &mut self.plain,
+        ];
+
+        for list in opaque_lists {
+            for &object in &[
+                BasicObj::Cube,
+                BasicObj::Quad,
+                BasicObj::TessellatedCylinder,
+            ] {
+                sort::by_distance(&mut list[object], eye, sort::Order::FrontToBack, |i| {
+                    &i.transform
+                });
+            }
+        }
+
+        for list in self.custom.values_mut() {
+            sort::by_distance(list, eye, sort::Order::FrontToBack, |i| &i.transform);
+        }
+
+        sort::by_distance(&mut self.wind, eye, sort::Order::FrontToBack, |i| {
+            &i.transform
+        });
+        for list in self.decorations.values_mut() {
+            sort::by_distance(list, eye, sort::Order::FrontToBack, |i| &i.transform);
+        }
+        sort::by_distance(&mut self.lines, eye, sort::Order::BackToFront, |i| {
+            &i.transform
+        });
+    }
+
+    /// Returns true if anything is drawn as glass. Otherwise, the scene does
+    /// not need to be drawn offscreen first.
+    pub fn has_glass(&self) -> bool {
+        [
+            BasicObj::Cube,
+            BasicObj::Quad,
+            BasicObj::TessellatedCylinder,
+        ]
+        .iter()
+        .any(|&object| !self.solid_glass[object].as_slice().is_empty())
+    }
+
+    pub fn custom(&mut self, mesh_name: &str) -> &mut RenderList<textured::Instance> {
+        if !self.custom.contains_key(mesh_name) {
+            self.custom
+                .insert(mesh_name.to_string(), RenderList::default());
+        }
+
+        self.custom.get_mut(mesh_name).unwrap()
+    }
+
+    pub fn decoration(&mut self, decoration: Decoration) -> &mut RenderList<wind::Instance> {
+        self.decorations
+            .entry(decoration)
+            .or_insert_with(RenderList::default)
+    }
+}
+
+/// Configuration of the render pipeline, extending the one of rendology with
+/// the game's own post-processing.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub rendology: rendology::Config,
+    pub vignette: vignette::Config,
+    pub depth_of_field: Option<depth_of_field::Config>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            rendology: Default::default(),
+            vignette: Default::default(),
+            depth_of_field: Some(Default::default()),
+        }
+    }
 }
 
 pub struct Pipeline {
     floor_mesh: Mesh<floor::Vertex>,
     basic_obj_resources: basic_obj::Resources,
     line_mesh: Mesh<line::Point>,
+    decoration_meshes: Vec<Mesh<basic_obj::Vertex>>,
     plain_program: glium::Program,
+    meshes: Option<mesh::Registry>,
 
     rendology: rendology::Pipeline,
+    depth_of_field: Option<DepthOfField>,
+
+    /// Taken out while drawing the scene for glass.
+    glass: Option<Glass>,
 
     solid_shadow_pass: Option<ShadowPass<basic_obj::Core>>,
     wind_shadow_pass: Option<ShadowPass<wind::Core>>,
+    custom_shadow_pass: Option<ShadowPass<textured::Core>>,
 
     floor_scene_pass: ShadedScenePass<floor::Core>,
     solid_scene_pass: ShadedScenePass<basic_obj::Core>,
     solid_dither_scene_pass: ShadedScenePass<dither::Core<basic_obj::Core>>,
     solid_glow_scene_pass: ShadedScenePass<basic_obj::Core>,
     wind_scene_pass: ShadedScenePass<wind::Core>,
+    custom_scene_pass: ShadedScenePass<textured::Core>,
 
     plain_scene_pass: PlainScenePass<basic_obj::Core>,
     line_scene_pass: PlainScenePass<line::Core>,
@@ -92,7 +236,9 @@ pub struct Pipeline {
     solid_instancing: basic_obj::Instancing<basic_obj::Instance>,
     solid_dither_instancing: basic_obj::Instancing<basic_obj::Instance>,
     solid_glow_instancing: basic_obj::Instancing<basic_obj::Instance>,
+    solid_glass_instancing: basic_obj::Instancing<basic_obj::Instance>,
     wind_instancing: Instancing<wind::Instance>,
+    decoration_instancing: Vec<Instancing<wind::Instance>>,
     plain_instancing: basic_obj::Instancing<basic_obj::Instance>,
     line_instancing: Instancing<line::Instance>,
 }
@@ -100,102 +246,160 @@ pub struct Pipeline {
 impl Pipeline {
     pub fn create<F: glium::backend::Facade>(
         facade: &F,
-        config: &rendology::Config,
+        config: &Config,
         target_size: (u32, u32),
-    ) -> Result<Self, CreationError> {
-        let floor_mesh = floor::create_mesh(facade)?;
-        let basic_obj_resources = basic_obj::Resources::create(facade)?;
-        let line_mesh = line::create_mesh(facade)?;
+    ) -> Result<Self, Error> {
+        let floor_mesh = floor::create_mesh(facade).map_err(error::creating("floor mesh"))?;
+        let basic_obj_resources =
+            basic_obj::Resources::create(facade).map_err(error::creating("basic object meshes"))?;
+        let line_mesh = line::create_mesh(facade).map_err(error::creating("line mesh"))?;
+        let decoration_meshes =
+            decoration::create_meshes(facade).map_err(error::creating("decoration meshes"))?;
         let plain_program = basic_obj::Core
             .scene_core()
             .build_program(facade, InstancingMode::Uniforms)
-            .map_err(|e| CreationError::CreationError(rendology::CreationError::ShaderBuild(e)))?;
-
-        let rendology = rendology::Pipeline::create(facade, config, target_size)?;
-
-        let solid_shadow_pass =
-            rendology.create_shadow_pass(facade, basic_obj::Core, InstancingMode::Vertex)?;
-        let wind_shadow_pass =
-            rendology.create_shadow_pass(facade, wind::Core, InstancingMode::Vertex)?;
-
-        let floor_scene_pass = rendology.create_shaded_scene_pass(
-            facade,
-            floor::Core,
-            InstancingMode::Uniforms,
-            ShadedScenePassSetup {
-                draw_shadowed: true,
-                draw_glowing: false,
-            },
-        )?;
-        let solid_scene_pass = rendology.create_shaded_scene_pass(
-            facade,
-            basic_obj::Core,
-            InstancingMode::Vertex,
-            ShadedScenePassSetup {
-                draw_shadowed: true,
-                draw_glowing: false,
-            },
-        )?;
-        let solid_dither_scene_pass = rendology.create_shaded_scene_pass(
-            facade,
-            dither::Core(basic_obj::Core),
-            InstancingMode::Vertex,
-            ShadedScenePassSetup {
-                draw_shadowed: true,
-                draw_glowing: false,
-            },
-        )?;
-        let solid_glow_scene_pass = rendology.create_shaded_scene_pass(
-            facade,
-            basic_obj::Core,
-            InstancingMode::Vertex,
-            ShadedScenePassSetup {
-                draw_shadowed: true,
-                draw_glowing: true,
-            },
-        )?;
-        let wind_scene_pass = rendology.create_shaded_scene_pass(
-            facade,
-            wind::Core,
-            InstancingMode::Vertex,
-            ShadedScenePassSetup {
-                draw_shadowed: true,
-                draw_glowing: true,
-            },
-        )?;
-
-        let plain_scene_pass =
-            rendology.create_plain_scene_pass(facade, basic_obj::Core, InstancingMode::Vertex)?;
-        let line_scene_pass =
-            rendology.create_plain_scene_pass(facade, line::Core, InstancingMode::Vertex)?;
-
-        let particle_system = particle::System::create(facade, &Default::default())?;
-        let particle_scene_pass = rendology.create_plain_scene_pass(
-            facade,
-            particle_system.shader(),
-            InstancingMode::Uniforms,
-        )?;
-
-        let solid_instancing = basic_obj::Instancing::create(facade)?;
-        let solid_glow_instancing = basic_obj::Instancing::create(facade)?;
-        let solid_dither_instancing = basic_obj::Instancing::create(facade)?;
-        let wind_instancing = Instancing::create(facade)?;
-        let plain_instancing = basic_obj::Instancing::create(facade)?;
-        let line_instancing = Instancing::create(facade)?;
+            .map_err(rendology::CreationError::ShaderBuild)
+            .map_err(error::creating("plain program"))?;
+
+        let rendology = rendology::Pipeline::create(facade, &config.rendology, target_size)
+            .map_err(error::creating("render pipeline"))?;
+        let depth_of_field = config
+            .depth_of_field
+            .as_ref()
+            .map(|config| DepthOfField::create(facade, config, target_size))
+            .transpose()?;
+        let glass = Glass::create(facade, target_size)?;
+
+        let solid_shadow_pass = rendology
+            .create_shadow_pass(facade, basic_obj::Core, InstancingMode::Vertex)
+            .map_err(error::creating("shadow pass for solids"))?;
+        let wind_shadow_pass = rendology
+            .create_shadow_pass(facade, wind::Core, InstancingMode::Vertex)
+            .map_err(error::creating("shadow pass for wind"))?;
+        let custom_shadow_pass = rendology
+            .create_shadow_pass(facade, textured::Core, InstancingMode::Uniforms)
+            .map_err(error::creating("shadow pass for meshes"))?;
+
+        let floor_scene_pass = rendology
+            .create_shaded_scene_pass(
+                facade,
+                floor::Core,
+                InstancingMode::Uniforms,
+                ShadedScenePassSetup {
+                    draw_shadowed: true,
+                    draw_glowing: false,
+                },
+            )
+            .map_err(error::creating("scene pass for the floor"))?;
+        let solid_scene_pass = rendology
+            .create_shaded_scene_pass(
+                facade,
+                basic_obj::Core,
+                InstancingMode::Vertex,
+                ShadedScenePassSetup {
+                    draw_shadowed: true,
+                    draw_glowing: false,
+                },
+            )
+            .map_err(error::creating("scene pass for solids"))?;
+        let solid_dither_scene_pass = rendology
+            .create_shaded_scene_pass(
+                facade,
+                dither::Core(basic_obj::Core),
+                InstancingMode::Vertex,
+                ShadedScenePassSetup {
+                    draw_shadowed: true,
+                    draw_glowing: false,
+                },
+            )
+            .map_err(error::creating("scene pass for dithered solids"))?;
+        let solid_glow_scene_pass = rendology
+            .create_shaded_scene_pass(
+                facade,
+                basic_obj::Core,
+                InstancingMode::Vertex,
+                ShadedScenePassSetup {
+                    draw_shadowed: true,
+                    draw_glowing: true,
+                },
+            )
+            .map_err(error::creating("scene pass for glowing solids"))?;
+        let wind_scene_pass = rendology
+            .create_shaded_scene_pass(
+                facade,
+                wind::Core,
+                InstancingMode::Vertex,
+                ShadedScenePassSetup {
+                    draw_shadowed: true,
+                    draw_glowing: true,
+                },
+            )
+            .map_err(error::creating("scene pass for wind"))?;
+
+        let custom_scene_pass = rendology
+            .create_shaded_scene_pass(
+                facade,
+                textured::Core,
+                InstancingMode::Uniforms,
+                ShadedScenePassSetup {
+                    draw_shadowed: true,
+                    draw_glowing: false,
+                },
+            )
+            .map_err(error::creating("scene pass for meshes"))?;
+
+        let plain_scene_pass = rendology
+            .create_plain_scene_pass(facade, basic_obj::Core, InstancingMode::Vertex)
+            .map_err(error::creating("plain scene pass"))?;
+        let line_scene_pass = rendology
+            .create_plain_scene_pass(facade, line::Core, InstancingMode::Vertex)
+            .map_err(error::creating("line scene pass"))?;
+
+        let particle_system = particle::System::create(facade, &Default::default())
+            .map_err(error::creating("particle system"))?;
+        let particle_scene_pass = rendology
+            .create_plain_scene_pass(facade, particle_system.shader(), InstancingMode::Uniforms)
+            .map_err(error::creating("particle scene pass"))?;
+
+        let solid_instancing = basic_obj::Instancing::create(facade)
+            .map_err(error::creating("instancing for solids"))?;
+        let solid_glow_instancing = basic_obj::Instancing::create(facade)
+            .map_err(error::creating("instancing for glowing solids"))?;
+        let solid_dither_instancing = basic_obj::Instancing::create(facade)
+            .map_err(error::creating("instancing for dithered solids"))?;
+        let solid_glass_instancing = basic_obj::Instancing::create(facade)
+            .map_err(error::creating("instancing for glass"))?;
+        let wind_instancing =
+            Instancing::create(facade).map_err(error::creating("instancing for wind"))?;
+        let decoration_instancing = Decoration::ALL
+            .iter()
+            .map(|_| Instancing::create(facade))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(error::creating("instancing for decorations"))?;
+        let plain_instancing =
+            basic_obj::Instancing::create(facade).map_err(error::creating("plain instancing"))?;
+        let line_instancing =
+            Instancing::create(facade).map_err(error::creating("line instancing"))?;
 
         Ok(Self {
             floor_mesh,
             basic_obj_resources,
             line_mesh,
+            decoration_meshes,
             plain_program,
+            meshes: None,
             rendology,
+            depth_of_field,
+            glass: Some(glass),
             solid_shadow_pass,
             wind_shadow_pass,
+            custom_shadow_pass,
             floor_scene_pass,
             solid_scene_pass,
             solid_dither_scene_pass,
             solid_glow_scene_pass,
             wind_scene_pass,
+            custom_scene_pass,
             plain_scene_pass,
             line_scene_pass,
             particle_system,
@@ -203,12 +407,29 @@ impl Pipeline {
             solid_instancing,
             solid_dither_instancing,
             solid_glow_instancing,
+            solid_glass_instancing,
             wind_instancing,
+            decoration_instancing,
             plain_instancing,
             line_instancing,
         })
     }
 
+    /// Loads meshes that can then be drawn through `Stage::custom`.
+    pub fn load_meshes<F: glium::backend::Facade>(
+        &mut self,
+        facade: &F,
+        meshes: &[(String, PathBuf)],
+    ) {
+        self.meshes = match mesh::Registry::load(facade, meshes) {
+            Ok(registry) => Some(registry),
+            Err(err) => {
+                warn!("Failed to create mesh textures: {:?}", err);
+                None
+            }
+        };
+    }
+
     pub fn clear_particles(&mut self) {
         self.particle_system.clear();
     }
@@ -219,24 +440,152 @@ impl Pipeline {
         context: &Context,
         stage: &Stage,
         target: &mut S,
-    ) -> Result<(), rendology::DrawError> {
+    ) -> Result<(), Error> {
         {
             profile!("update_instances");
 
+            // TODO: `Instancing::update` uploads all instances again in every
+            // frame. Reusing persistently mapped buffers, or updating only
+            // the instances that changed, needs support in rendology, which
+            // owns the instance buffers.
+
             self.particle_system.spawn(stage.new_particles.as_slice());
 
-            self.solid_instancing.update(facade, &stage.solid)?;
+            self.solid_instancing
+                .update(facade, &stage.solid)
+                .map_err(error::drawing("instances"))?;
             self.solid_dither_instancing
-                .update(facade, &stage.solid_dither)?;
+                .update(facade, &stage.solid_dither)
+                .map_err(error::drawing("instances"))?;
             self.solid_glow_instancing
-                .update(facade, &stage.solid_glow)?;
+                .update(facade, &stage.solid_glow)
+                .map_err(error::drawing("instances"))?;
+            self.solid_glass_instancing
+                .update(facade, &stage.solid_glass)
+                .map_err(error::drawing("instances"))?;
             self.wind_instancing
-                .update(facade, &stage.wind.as_slice())?;
-            self.plain_instancing.update(facade, &stage.plain)?;
+                .update(facade, &stage.wind.as_slice())
+                .map_err(error::drawing("instances"))?;
+            for &decoration in &Decoration::ALL {
+                let instances = stage
+                    .decorations
+                    .get(&decoration)
+                    .map_or(&[][..], |list| list.as_slice());
+
+                self.decoration_instancing[decoration.to_index()]
+                    .update(facade, instances)
+                    .map_err(error::drawing("instances"))?;
+            }
+            self.plain_instancing
+                .update(facade, &stage.plain)
+                .map_err(error::drawing("instances"))?;
             self.line_instancing
-                .update(facade, stage.lines.as_slice())?;
+                .update(facade, stage.lines.as_slice())
+                .map_err(error::drawing("instances"))?;
+        }
+
+        let depth_of_field = if context.depth_of_field {
+            self.depth_of_field.take()
+        } else {
+            None
+        };
+
+        if let Some(mut depth_of_field) = depth_of_field {
+            let result = depth_of_field.draw(facade, &context.rendology, target, |framebuffer| {
+                self.draw_scene_and_glass(facade, context, stage, framebuffer)
+            });
+            self.depth_of_field = Some(depth_of_field);
+
+            result?;
+        } else {
+            self.draw_scene_and_glass(facade, context, stage, target)?;
         }
 
+        // Render screen-space stuff on top
+        profile!("ortho");
+
+        let ortho_projection = na::Matrix4::new_orthographic(
+            0.0,
+            context.rendology.camera.viewport_size.x,
+            context.rendology.camera.viewport_size.y,
+            0.0,
+            -10.0,
+            10.0,
+        );
+        let ortho_camera = Camera {
+            projection: ortho_projection,
+            view: na::Matrix4::identity(),
+            ..context.rendology.camera.clone()
+        };
+        let ortho_render_context = rendology::Context {
+            camera: ortho_camera,
+            ..context.rendology.clone()
+        };
+        let ortho_parameters = glium::DrawParameters {
+            blend: glium::draw_parameters::Blend::alpha_blending(),
+            ..Default::default()
+        };
+        stage
+            .ortho
+            .as_drawable(&self.basic_obj_resources)
+            .draw(
+                &self.plain_program,
+                &ortho_render_context,
+                &ortho_parameters,
+                target,
+            )
+            .map_err(error::drawing("screen-space objects"))?;
+
+        Ok(())
+    }
+
+    /// Draws everything except for screen-space objects. If there is glass,
+    /// the scene is drawn offscreen first, so that glass can refract it.
+    fn draw_scene_and_glass<F: glium::backend::Facade, S: glium::Surface>(
+        &mut self,
+        facade: &F,
+        context: &Context,
+        stage: &Stage,
+        target: &mut S,
+    ) -> Result<(), Error> {
+        let glass = if stage.has_glass() {
+            self.glass.take()
+        } else {
+            None
+        };
+
+        if let Some(mut glass) = glass {
+            let result = glass
+                .draw_scene(facade, target, |framebuffer| {
+                    self.draw_scene(facade, context, stage, framebuffer)
+                })
+                .and_then(|params| {
+                    self.solid_glass_instancing
+                        .as_drawable(&self.basic_obj_resources)
+                        .draw(
+                            glass.program(),
+                            &(context.rendology.clone(), params),
+                            &glass::draw_params(),
+                            target,
+                        )
+                        .map_err(error::drawing("glass"))
+                });
+            self.glass = Some(glass);
+
+            result
+        } else {
+            self.draw_scene(facade, context, stage, target)
+        }
+    }
+
+    /// Draws everything except for screen-space objects and glass.
+    fn draw_scene<F: glium::backend::Facade, S: glium::Surface>(
+        &mut self,
+        facade: &F,
+        context: &Context,
+        stage: &Stage,
+        target: &mut S,
+    ) -> Result<(), Error> {
         let scene_offset = Some(glium::draw_parameters::PolygonOffset {
             factor: 1.0,
             units: 1.0,
@@ -294,8 +643,15 @@ impl Pipeline {
                 wind_stripe_color.z,
                 1.0,
             ),
+            idle_time: context.time,
         };
         let wind_mesh = self.basic_obj_resources.mesh(BasicObj::TessellatedCylinder);
+        let decoration_drawables: Vec<_> = self
+            .decoration_instancing
+            .iter()
+            .zip(self.decoration_meshes.iter())
+            .map(|(instancing, mesh)| instancing.as_drawable(mesh))
+            .collect();
 
         let particle_params = particle::Params {
             time: context.tick_time.to_f32(),
@@ -303,15 +659,36 @@ impl Pipeline {
         self.particle_system
             .set_current_time(context.tick_time.to_f32());
 
-        self.rendology
-            .start_frame(facade, (0.0, 0.0, 0.0), context.rendology.clone(), target)?
+        let custom_drawables: Vec<_> = self.meshes.as_ref().map_or_else(Vec::new, |meshes| {
+            stage
+                .custom
+                .iter()
+                .filter(|(_, list)| !list.as_slice().is_empty())
+                .filter_map(|(name, list)| {
+                    meshes.get(name).map(|entry| {
+                        let params = textured::Params {
+                            textures: meshes.textures().array(),
+                            texture_id: entry.texture_id as f32,
+                        };
+
+                        (list.as_drawable(&entry.mesh), params)
+                    })
+                })
+                .collect()
+        });
+
+        let mut shadow_pass = self
+            .rendology
+            .start_frame(facade, (0.0, 0.0, 0.0), context.rendology.clone(), target)
+            .map_err(error::drawing("frame"))?
             .shadow_pass()
             .draw(
                 &self.solid_shadow_pass,
                 &self.solid_instancing.as_drawable(&self.basic_obj_resources),
                 &(),
                 &shaded_draw_params,
-            )?
+            )
+            .map_err(error::drawing("shadows of solids"))?
             .draw(
                 &self.solid_shadow_pass,
                 &self
@@ -319,7 +696,8 @@ impl Pipeline {
                     .as_drawable(&self.basic_obj_resources),
                 &(),
                 &shaded_draw_params,
-            )?
+            )
+            .map_err(error::drawing("shadows of dithered solids"))?
             .draw(
                 &self.solid_shadow_pass,
                 &self
@@ -327,26 +705,52 @@ impl Pipeline {
                     .as_drawable(&self.basic_obj_resources),
                 &(),
                 &shaded_draw_params,
-            )?
+            )
+            .map_err(error::drawing("shadows of glowing solids"))?
             .draw(
                 &self.wind_shadow_pass,
                 &self.wind_instancing.as_drawable(wind_mesh),
                 &wind_params,
                 &shaded_draw_params,
-            )?
+            )
+            .map_err(error::drawing("shadows of wind"))?;
+        for drawable in decoration_drawables.iter() {
+            shadow_pass = shadow_pass
+                .draw(
+                    &self.wind_shadow_pass,
+                    drawable,
+                    &wind_params,
+                    &shaded_draw_params,
+                )
+                .map_err(error::drawing("shadows of decorations"))?;
+        }
+        for (drawable, params) in custom_drawables.iter() {
+            shadow_pass = shadow_pass
+                .draw(
+                    &self.custom_shadow_pass,
+                    drawable,
+                    params,
+                    &shaded_draw_params,
+                )
+                .map_err(error::drawing("shadows of meshes"))?;
+        }
+
+        let mut shaded_scene_pass = shadow_pass
             .shaded_scene_pass()
             .draw(
                 &self.floor_scene_pass,
                 &stage.floor.as_drawable(&self.floor_mesh),
                 &(),
                 &shaded_draw_params,
-            )?
+            )
+            .map_err(error::drawing("floor"))?
             .draw(
                 &self.solid_scene_pass,
                 &self.solid_instancing.as_drawable(&self.basic_obj_resources),
                 &(),
                 &shaded_draw_params,
-            )?
+            )
+            .map_err(error::drawing("solids"))?
             .draw(
                 &self.solid_dither_scene_pass,
                 &self
@@ -354,7 +758,8 @@ impl Pipeline {
                     .as_drawable(&self.basic_obj_resources),
                 &(),
                 &shaded_draw_params,
-            )?
+            )
+            .map_err(error::drawing("dithered solids"))?
             .draw(
                 &self.solid_glow_scene_pass,
                 &self
@@ -362,67 +767,66 @@ impl Pipeline {
                     .as_drawable(&self.basic_obj_resources),
                 &(),
                 &shaded_draw_params,
-            )?
+            )
+            .map_err(error::drawing("glowing solids"))?
             .draw(
                 &self.wind_scene_pass,
                 &self.wind_instancing.as_drawable(wind_mesh),
                 &wind_params,
                 &shaded_draw_params,
-            )?
-            .compose(&stage.lights)?
+            )
+            .map_err(error::drawing("wind"))?;
+        for drawable in decoration_drawables.iter() {
+            shaded_scene_pass = shaded_scene_pass
+                .draw(
+                    &self.wind_scene_pass,
+                    drawable,
+                    &wind_params,
+                    &shaded_draw_params,
+                )
+                .map_err(error::drawing("decorations"))?;
+        }
+        for (drawable, params) in custom_drawables.iter() {
+            shaded_scene_pass = shaded_scene_pass
+                .draw(
+                    &self.custom_scene_pass,
+                    drawable,
+                    params,
+                    &shaded_draw_params,
+                )
+                .map_err(error::drawing("meshes"))?;
+        }
+
+        shaded_scene_pass
+            .compose(&stage.lights)
+            .map_err(error::drawing("lighting"))?
             .plain_scene_pass()
             .draw(
                 &self.plain_scene_pass,
                 &self.plain_instancing.as_drawable(&self.basic_obj_resources),
                 &(),
                 &plain_draw_params,
-            )?
+            )
+            .map_err(error::drawing("plain objects"))?
             .draw(
                 &self.particle_scene_pass,
                 &self.particle_system,
                 &particle_params,
                 &particle_draw_params,
-            )?
-            .postprocess()?
+            )
+            .map_err(error::drawing("particles"))?
+            .postprocess()
+            .map_err(error::drawing("postprocessing"))?
             .plain_scene_pass()
             .draw(
                 &self.line_scene_pass,
                 &self.line_instancing.as_drawable(&self.line_mesh),
                 &line::Params { feather: 1.0 },
                 &line_draw_params,
-            )?
-            .present()?;
-
-        // Render screen-space stuff on top
-        profile!("ortho");
-
-        let ortho_projection = na::Matrix4::new_orthographic(
-            0.0,
-            context.rendology.camera.viewport_size.x,
-            context.rendology.camera.viewport_size.y,
-            0.0,
-            -10.0,
-            10.0,
-        );
-        let ortho_camera = Camera {
-            projection: ortho_projection,
-            view: na::Matrix4::identity(),
-            ..context.rendology.camera.clone()
-        };
-        let ortho_render_context = rendology::Context {
-            camera: ortho_camera,
-            ..context.rendology.clone()
-        };
-        let ortho_parameters = glium::DrawParameters {
-            blend: glium::draw_parameters::Blend::alpha_blending(),
-            ..Default::default()
-        };
-        stage.ortho.as_drawable(&self.basic_obj_resources).draw(
-            &self.plain_program,
-            &ortho_render_context,
-            &ortho_parameters,
-            target,
-        )?;
+            )
+            .map_err(error::drawing("lines"))?
+            .present()
+            .map_err(error::drawing("frame"))?;
 
         Ok(())
     }
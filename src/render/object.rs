@@ -0,0 +1,390 @@
+//! Procedural construction of meshes.
+//!
+//! `MeshBuilder` collects indexed triangles with one normal and texture
+//! coordinate per vertex. All helpers use the same conventions, so that
+//! generated geometry can be combined freely:
+//!
+//! - Triangles are wound counter-clockwise when seen from the outside.
+//! - Normals point outwards.
+//! - Positions are given in block coordinates, i.e. a block spans from
+//!   `(0, 0, 0)` to `(1, 1, 1)`, with the z axis pointing up.
+//!
+//! OBJ files, the floor and decorations are turned into meshes this way.
+
+use nalgebra as na;
+
+use rendology::{CreationError, Mesh};
+
+use crate::machine::grid::{Axis3, Dir3};
+use crate::render::textured;
+
+#[derive(Debug, Clone, Default)]
+pub struct MeshBuilder {
+    pub positions: Vec<na::Point3<f32>>,
+    pub normals: Vec<na::Vector3<f32>>,
+    pub tex_coords: Vec<na::Vector2<f32>>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn num_vertices(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn num_triangles(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Adds a vertex, returning its index.
+    pub fn vertex(
+        &mut self,
+        position: na::Point3<f32>,
+        normal: na::Vector3<f32>,
+        tex_coord: na::Vector2<f32>,
+    ) -> u32 {
+        self.positions.push(position);
+        self.normals.push(normal);
+        self.tex_coords.push(tex_coord);
+
+        self.positions.len() as u32 - 1
+    }
+
+    pub fn triangle(&mut self, a: u32, b: u32, c: u32) -> &mut Self {
+        self.indices.extend_from_slice(&[a, b, c]);
+        self
+    }
+
+    /// Adds a flat triangle, computing its normal from the winding.
+    pub fn flat_triangle(
+        &mut self,
+        a: na::Point3<f32>,
+        b: na::Point3<f32>,
+        c: na::Point3<f32>,
+    ) -> &mut Self {
+        let normal = face_normal(&a, &b, &c);
+
+        let i = self.vertex(a, normal, na::Vector2::new(0.0, 0.0));
+        let j = self.vertex(b, normal, na::Vector2::new(1.0, 0.0));
+        let k = self.vertex(c, normal, na::Vector2::new(1.0, 1.0));
+
+        self.triangle(i, j, k)
+    }
+
+    /// Adds a flat quad with corners in counter-clockwise order.
+    pub fn quad(&mut self, corners: [na::Point3<f32>; 4]) -> &mut Self {
+        let normal = face_normal(&corners[0], &corners[1], &corners[2]);
+        let tex_coords = [
+            na::Vector2::new(0.0, 0.0),
+            na::Vector2::new(1.0, 0.0),
+            na::Vector2::new(1.0, 1.0),
+            na::Vector2::new(0.0, 1.0),
+        ];
+
+        let first = self.num_vertices() as u32;
+        for (corner, tex_coord) in corners.iter().zip(tex_coords.iter()) {
+            self.vertex(*corner, normal, *tex_coord);
+        }
+
+        self.triangle(first, first + 1, first + 2)
+            .triangle(first, first + 2, first + 3)
+    }
+
+    /// Adds an axis-aligned box between two corners.
+    pub fn cuboid(&mut self, min: &na::Point3<f32>, max: &na::Point3<f32>) -> &mut Self {
+        for &dir in &Dir3::ALL {
+            self.cuboid_face(min, max, dir);
+        }
+
+        self
+    }
+
+    /// Adds one face of an axis-aligned box. This can be used to leave out
+    /// faces that are hidden by neighboring geometry.
+    pub fn cuboid_face(
+        &mut self,
+        min: &na::Point3<f32>,
+        max: &na::Point3<f32>,
+        dir: Dir3,
+    ) -> &mut Self {
+        let (normal, tangent, bitangent) = dir_basis(dir);
+        let center = na::center(min, max);
+        let half = (max - min) / 2.0;
+
+        let n = normal.component_mul(&half);
+        let t = tangent.component_mul(&half);
+        let b = bitangent.component_mul(&half);
+
+        self.quad([
+            center + n - t - b,
+            center + n + t - b,
+            center + n + t + b,
+            center + n - t + b,
+        ])
+    }
+
+    /// Adds a cylinder that goes from `start` to `end`, approximated with
+    /// the given number of segments. The caps are closed.
+    pub fn cylinder(
+        &mut self,
+        start: &na::Point3<f32>,
+        end: &na::Point3<f32>,
+        radius: f32,
+        num_segments: usize,
+    ) -> &mut Self {
+        let profile = circle_profile(radius, num_segments);
+        self.extrude(&profile, &[*start, *end], true)
+    }
+
+    /// Extrudes a closed profile along a path of points. The profile is
+    /// given in the plane orthogonal to each path segment, in
+    /// counter-clockwise order. If `caps` is true, the ends are closed.
+    pub fn extrude(
+        &mut self,
+        profile: &[na::Vector2<f32>],
+        path: &[na::Point3<f32>],
+        caps: bool,
+    ) -> &mut Self {
+        assert!(profile.len() >= 3, "Profile needs at least three points");
+        assert!(path.len() >= 2, "Path needs at least two points");
+
+        // Place a ring of the profile at each point of the path. At inner
+        // points, the ring is oriented along the mean of the adjacent
+        // segment directions. The orientation of each ring is carried over
+        // from the previous one, so that the extrusion does not twist.
+        let mut rings: Vec<Vec<na::Point3<f32>>> = Vec::with_capacity(path.len());
+        let mut prev_u: Option<na::Vector3<f32>> = None;
+
+        for i in 0..path.len() {
+            let prev = path[i.saturating_sub(1)];
+            let next = path[(i + 1).min(path.len() - 1)];
+            let forward = (next - prev).normalize();
+
+            let u = prev_u
+                .and_then(|u| (u - forward * u.dot(&forward)).try_normalize(std::f32::EPSILON))
+                .unwrap_or_else(|| orthogonal_basis(&forward).0);
+            let v = forward.cross(&u);
+            prev_u = Some(u);
+
+            rings.push(
+                profile
+                    .iter()
+                    .map(|p| path[i] + u * p.x + v * p.y)
+                    .collect(),
+            );
+        }
+
+        let length: f32 = path.windows(2).map(|s| (s[1] - s[0]).norm()).sum();
+        let mut distance = 0.0;
+
+        for i in 0..path.len() - 1 {
+            let axis = path[i + 1] - path[i];
+            let (t_a, t_b) = (distance / length, (distance + axis.norm()) / length);
+            distance += axis.norm();
+
+            for j in 0..profile.len() {
+                let k = (j + 1) % profile.len();
+                let corners = [rings[i][j], rings[i][k], rings[i + 1][k], rings[i + 1][j]];
+
+                // Smooth normals pointing away from the path.
+                let normals: Vec<_> = corners
+                    .iter()
+                    .zip(&[path[i], path[i], path[i + 1], path[i + 1]])
+                    .map(|(corner, center)| {
+                        let radial = corner - center;
+                        (radial - axis * radial.dot(&axis) / axis.norm_squared()).normalize()
+                    })
+                    .collect();
+                let u_a = j as f32 / profile.len() as f32;
+                let u_b = (j + 1) as f32 / profile.len() as f32;
+                let tex_coords = [
+                    na::Vector2::new(u_a, t_a),
+                    na::Vector2::new(u_b, t_a),
+                    na::Vector2::new(u_b, t_b),
+                    na::Vector2::new(u_a, t_b),
+                ];
+
+                let first = self.num_vertices() as u32;
+                for l in 0..4 {
+                    self.vertex(corners[l], normals[l], tex_coords[l]);
+                }
+                self.triangle(first, first + 1, first + 2)
+                    .triangle(first, first + 2, first + 3);
+            }
+        }
+
+        if caps {
+            self.cap(&rings[0], true);
+            self.cap(&rings[rings.len() - 1], false);
+        }
+
+        self
+    }
+
+    /// Extrudes a closed profile through the centers of the blocks that are
+    /// visited when moving along the given directions, starting at the
+    /// center of the block at the origin.
+    pub fn extrude_dirs(
+        &mut self,
+        profile: &[na::Vector2<f32>],
+        dirs: &[Dir3],
+        caps: bool,
+    ) -> &mut Self {
+        let mut path = vec![na::Point3::new(0.5, 0.5, 0.5)];
+
+        for dir in dirs {
+            let last = path[path.len() - 1];
+            path.push(last + na::convert::<_, na::Vector3<f32>>(dir.to_vector()));
+        }
+
+        self.extrude(profile, &path, caps)
+    }
+
+    /// Appends the geometry of another builder.
+    pub fn append(&mut self, other: &MeshBuilder) -> &mut Self {
+        let offset = self.num_vertices() as u32;
+
+        self.positions.extend_from_slice(&other.positions);
+        self.normals.extend_from_slice(&other.normals);
+        self.tex_coords.extend_from_slice(&other.tex_coords);
+        self.indices
+            .extend(other.indices.iter().map(|index| index + offset));
+
+        self
+    }
+
+    /// Applies a transformation to all vertices.
+    pub fn transform(&mut self, transform: &na::Matrix4<f32>) -> &mut Self {
+        let normal_transform = transform
+            .fixed_slice::<na::U3, na::U3>(0, 0)
+            .try_inverse()
+            .map_or_else(na::Matrix3::identity, |inverse| inverse.transpose());
+
+        for position in self.positions.iter_mut() {
+            *position = transform.transform_point(position);
+        }
+        for normal in self.normals.iter_mut() {
+            *normal = (normal_transform * *normal)
+                .try_normalize(std::f32::EPSILON)
+                .unwrap_or(*normal);
+        }
+
+        self
+    }
+
+    /// Converts the vertices into a vertex type of a shader.
+    pub fn vertices<V>(
+        &self,
+        f: impl Fn(&na::Point3<f32>, &na::Vector3<f32>, &na::Vector2<f32>) -> V,
+    ) -> Vec<V> {
+        (0..self.num_vertices())
+            .map(|i| f(&self.positions[i], &self.normals[i], &self.tex_coords[i]))
+            .collect()
+    }
+
+    pub fn build_with<F, V>(
+        &self,
+        facade: &F,
+        f: impl Fn(&na::Point3<f32>, &na::Vector3<f32>, &na::Vector2<f32>) -> V,
+    ) -> Result<Mesh<V>, CreationError>
+    where
+        F: glium::backend::Facade,
+        V: glium::Vertex,
+    {
+        Mesh::create_with_indices(
+            facade,
+            glium::index::PrimitiveType::TrianglesList,
+            &self.vertices(f),
+            &self.indices,
+        )
+    }
+
+    /// Creates a mesh that can be drawn with the textured shader.
+    pub fn build<F: glium::backend::Facade>(
+        &self,
+        facade: &F,
+    ) -> Result<Mesh<textured::Vertex>, CreationError> {
+        self.build_with(facade, |p, n, t| textured::Vertex {
+            position: [p.x, p.y, p.z],
+            normal: [n.x, n.y, n.z],
+            tex_coord: [t.x, t.y],
+        })
+    }
+
+    fn cap(&mut self, ring: &[na::Point3<f32>], is_start: bool) {
+        let center = ring
+            .iter()
+            .fold(na::Vector3::zeros(), |sum, p| sum + p.coords)
+            / ring.len() as f32;
+        let center = na::Point3::from(center);
+
+        for j in 0..ring.len() {
+            let k = (j + 1) % ring.len();
+
+            if is_start {
+                self.flat_triangle(center, ring[k], ring[j]);
+            } else {
+                self.flat_triangle(center, ring[j], ring[k]);
+            }
+        }
+    }
+}
+
+/// Returns the points of a circle with the given radius, in
+/// counter-clockwise order.
+pub fn circle_profile(radius: f32, num_segments: usize) -> Vec<na::Vector2<f32>> {
+    (0..num_segments)
+        .map(|i| {
+            let angle = i as f32 / num_segments as f32 * 2.0 * std::f32::consts::PI;
+            na::Vector2::new(angle.cos(), angle.sin()) * radius
+        })
+        .collect()
+}
+
+/// Returns the points of a square with the given half size, in
+/// counter-clockwise order.
+pub fn square_profile(half_size: f32) -> Vec<na::Vector2<f32>> {
+    vec![
+        na::Vector2::new(-half_size, -half_size),
+        na::Vector2::new(half_size, -half_size),
+        na::Vector2::new(half_size, half_size),
+        na::Vector2::new(-half_size, half_size),
+    ]
+}
+
+fn face_normal(a: &na::Point3<f32>, b: &na::Point3<f32>, c: &na::Point3<f32>) -> na::Vector3<f32> {
+    (b - a)
+        .cross(&(c - a))
+        .try_normalize(std::f32::EPSILON)
+        .unwrap_or_else(na::Vector3::z)
+}
+
+/// Returns the normal of a face pointing in the given direction, together
+/// with two tangents such that `tangent x bitangent = normal`.
+fn dir_basis(dir: Dir3) -> (na::Vector3<f32>, na::Vector3<f32>, na::Vector3<f32>) {
+    let normal: na::Vector3<f32> = na::convert(dir.to_vector());
+    let tangent = match dir.0 {
+        Axis3::X => na::Vector3::y(),
+        Axis3::Y => na::Vector3::z(),
+        Axis3::Z => na::Vector3::x(),
+    };
+
+    (normal, tangent, normal.cross(&tangent))
+}
+
+/// Returns two unit vectors that are orthogonal to `forward` and to each
+/// other, such that `u x v = forward`.
+fn orthogonal_basis(forward: &na::Vector3<f32>) -> (na::Vector3<f32>, na::Vector3<f32>) {
+    let helper = if forward.z.abs() < 0.9 {
+        na::Vector3::z()
+    } else {
+        na::Vector3::x()
+    };
+    let u = helper.cross(forward).normalize();
+    let v = forward.cross(&u);
+
+    (u, v)
+}
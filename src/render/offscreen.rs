@@ -0,0 +1,113 @@
+//! Offscreen targets for passes that work on the result of drawing the
+//! scene, such as depth of field and glass.
+//!
+//! The scene is drawn into a color and a depth texture, which later passes
+//! sample by drawing a quad that covers the whole target.
+
+use std::rc::Rc;
+
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::texture::{
+    DepthFormat, DepthTexture2d, MipmapsOption, Texture2d, UncompressedFloatFormat,
+};
+use glium::Surface;
+
+use crate::render::error::{self, Error};
+
+/// Vertex shader for drawing a quad that covers the whole target. It is
+/// drawn with `draw_screen_quad` and passes the texture coordinates of the
+/// target to the fragment shader as `v_tex_coord`.
+pub const SCREEN_QUAD_VERTEX_SHADER: &str = "
+    #version 330
+
+    const vec2 corners[4] = vec2[4](
+        vec2(-1.0, -1.0),
+        vec2(1.0, -1.0),
+        vec2(-1.0, 1.0),
+        vec2(1.0, 1.0)
+    );
+
+    out vec2 v_tex_coord;
+
+    void main() {
+        vec2 corner = corners[gl_VertexID];
+
+        v_tex_coord = corner * 0.5 + 0.5;
+        gl_Position = vec4(corner, 0.0, 1.0);
+    }
+";
+
+pub fn draw_screen_quad<S: Surface, U: glium::uniforms::Uniforms>(
+    target: &mut S,
+    program: &glium::Program,
+    uniforms: &U,
+    draw_params: &glium::DrawParameters,
+) -> Result<(), glium::DrawError> {
+    target.draw(
+        glium::vertex::EmptyVertexAttributes { len: 4 },
+        glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip),
+        program,
+        uniforms,
+        draw_params,
+    )
+}
+
+pub struct Targets {
+    color: Rc<Texture2d>,
+    depth: DepthTexture2d,
+}
+
+impl Targets {
+    pub fn create<F: glium::backend::Facade>(facade: &F, size: (u32, u32)) -> Result<Self, Error> {
+        let color = Texture2d::empty_with_format(
+            facade,
+            UncompressedFloatFormat::U8U8U8U8,
+            MipmapsOption::NoMipmap,
+            size.0,
+            size.1,
+        )
+        .map_err(error::creating("offscreen color texture"))?;
+        let depth = DepthTexture2d::empty_with_format(
+            facade,
+            DepthFormat::I24,
+            MipmapsOption::NoMipmap,
+            size.0,
+            size.1,
+        )
+        .map_err(error::creating("offscreen depth texture"))?;
+
+        Ok(Targets {
+            color: Rc::new(color),
+            depth,
+        })
+    }
+
+    pub fn color(&self) -> &Rc<Texture2d> {
+        &self.color
+    }
+
+    pub fn depth(&self) -> &DepthTexture2d {
+        &self.depth
+    }
+
+    /// Clears the targets and draws into them with `draw`. The targets
+    /// follow the size of `target_size`, like the targets of the rest of the
+    /// pipeline do.
+    pub fn draw<F: glium::backend::Facade>(
+        &mut self,
+        facade: &F,
+        target_size: (u32, u32),
+        draw: impl FnOnce(&mut SimpleFrameBuffer) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        if self.color.dimensions() != target_size {
+            *self = Targets::create(facade, target_size)?;
+        }
+
+        let mut framebuffer =
+            SimpleFrameBuffer::with_depth_buffer(facade, &*self.color, &self.depth)
+                .map_err(Error::Framebuffer)?;
+        framebuffer.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+
+        draw(&mut framebuffer)
+    }
+}
@@ -0,0 +1,55 @@
+use std::cmp::Ordering;
+
+use nalgebra as na;
+
+use rendology::RenderList;
+
+/// The order in which instances are drawn, relative to the eye.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// Nearest instances first. For opaque objects, this lets hidden fragments
+    /// fail the depth test before they are shaded.
+    FrontToBack,
+
+    /// Farthest instances first, which is needed for blending to be correct.
+    BackToFront,
+}
+
+/// Reorders the instances of `list` by the distance of their origin from
+/// `eye`. Instances at the same distance keep their relative order.
+pub fn by_distance<I: Clone>(
+    list: &mut RenderList<I>,
+    eye: &na::Point3<f32>,
+    order: Order,
+    transform: impl Fn(&I) -> &na::Matrix4<f32>,
+) {
+    if list.as_slice().len() < 2 {
+        return;
+    }
+
+    let mut keyed: Vec<(f32, I)> = list
+        .as_slice()
+        .iter()
+        .map(|instance| (distance_squared(transform(instance), eye), instance.clone()))
+        .collect();
+
+    keyed.sort_by(|(a, _), (b, _)| {
+        let ordering = a.partial_cmp(b).unwrap_or(Ordering::Equal);
+
+        match order {
+            Order::FrontToBack => ordering,
+            Order::BackToFront => ordering.reverse(),
+        }
+    });
+
+    list.clear();
+    for (_, instance) in keyed {
+        list.add(instance);
+    }
+}
+
+fn distance_squared(transform: &na::Matrix4<f32>, eye: &na::Point3<f32>) -> f32 {
+    let origin = na::Point3::new(transform[(0, 3)], transform[(1, 3)], transform[(2, 3)]);
+
+    na::distance_squared(&origin, eye)
+}
@@ -0,0 +1,182 @@
+use nalgebra as na;
+
+use crate::machine::grid::Dir3;
+use crate::render::mesh::parse_obj;
+use crate::render::object::{square_profile, MeshBuilder};
+
+/// Returns the normal of each triangle, as given by its winding.
+fn winding_normals(builder: &MeshBuilder) -> Vec<na::Vector3<f32>> {
+    builder
+        .indices
+        .chunks(3)
+        .map(|triangle| {
+            let a = builder.positions[triangle[0] as usize];
+            let b = builder.positions[triangle[1] as usize];
+            let c = builder.positions[triangle[2] as usize];
+
+            (b - a).cross(&(c - a)).normalize()
+        })
+        .collect()
+}
+
+/// Checks the conventions of `MeshBuilder` for a convex mesh around
+/// `center`: triangles are wound counter-clockwise when seen from the
+/// outside, and vertex normals are unit length and point outwards.
+fn check_convex(builder: &MeshBuilder, center: &na::Point3<f32>) {
+    assert_eq!(builder.indices.len() % 3, 0);
+    assert_eq!(builder.normals.len(), builder.num_vertices());
+    assert_eq!(builder.tex_coords.len(), builder.num_vertices());
+    assert!(builder
+        .indices
+        .iter()
+        .all(|&index| (index as usize) < builder.num_vertices()));
+
+    for (triangle, winding_normal) in builder.indices.chunks(3).zip(winding_normals(builder)) {
+        let centroid = triangle.iter().fold(na::Vector3::zeros(), |sum, &index| {
+            sum + builder.positions[index as usize].coords
+        }) / 3.0;
+        let outwards = centroid - center.coords;
+
+        assert!(winding_normal.dot(&outwards) > 0.0);
+
+        for &index in triangle {
+            let normal = builder.normals[index as usize];
+
+            assert!((normal.norm() - 1.0).abs() < 1e-4);
+            assert!(normal.dot(&winding_normal) > 0.0);
+        }
+    }
+}
+
+#[test]
+fn test_cuboid() {
+    let min = na::Point3::new(0.0, 0.0, 0.0);
+    let max = na::Point3::new(1.0, 2.0, 3.0);
+
+    let mut builder = MeshBuilder::new();
+    builder.cuboid(&min, &max);
+
+    assert_eq!(builder.num_vertices(), 6 * 4);
+    assert_eq!(builder.num_triangles(), 6 * 2);
+    check_convex(&builder, &na::center(&min, &max));
+
+    for &dir in &Dir3::ALL {
+        let normal: na::Vector3<f32> = na::convert(dir.to_vector());
+        let num_facing = builder.normals.iter().filter(|n| **n == normal).count();
+
+        assert_eq!(num_facing, 4);
+    }
+}
+
+#[test]
+fn test_cylinder() {
+    let start = na::Point3::new(0.5, 0.5, 0.0);
+    let end = na::Point3::new(0.5, 0.5, 1.0);
+    let num_segments = 8;
+
+    let mut builder = MeshBuilder::new();
+    builder.cylinder(&start, &end, 0.25, num_segments);
+
+    // One quad per segment on the side, and a fan for each cap.
+    assert_eq!(
+        builder.num_vertices(),
+        num_segments * 4 + 2 * num_segments * 3
+    );
+    assert_eq!(builder.num_triangles(), num_segments * 2 + 2 * num_segments);
+    check_convex(&builder, &na::center(&start, &end));
+
+    // Normals on the side are orthogonal to the axis.
+    for normal in &builder.normals[..num_segments * 4] {
+        assert!(normal.z.abs() < 1e-4);
+    }
+}
+
+#[test]
+fn test_extrude_dirs() {
+    let profile = square_profile(0.1);
+    let dirs = [Dir3::X_POS, Dir3::Y_POS, Dir3::Z_POS];
+
+    let mut builder = MeshBuilder::new();
+    builder.extrude_dirs(&profile, &dirs, false);
+
+    assert_eq!(builder.num_vertices(), dirs.len() * profile.len() * 4);
+    assert_eq!(builder.num_triangles(), dirs.len() * profile.len() * 2);
+
+    // Rings of the profile are placed around the block centers.
+    let path = [
+        na::Point3::new(0.5, 0.5, 0.5),
+        na::Point3::new(1.5, 0.5, 0.5),
+        na::Point3::new(1.5, 1.5, 0.5),
+        na::Point3::new(1.5, 1.5, 1.5),
+    ];
+    for position in &builder.positions {
+        let dist = path
+            .iter()
+            .map(|p| (position - p).norm())
+            .fold(std::f32::INFINITY, f32::min);
+
+        assert!(dist <= 0.1 * 2.0f32.sqrt() + 1e-4);
+    }
+
+    for (normal, winding_normal) in builder
+        .indices
+        .chunks(3)
+        .map(|triangle| builder.normals[triangle[0] as usize])
+        .zip(winding_normals(&builder))
+    {
+        assert!(normal.dot(&winding_normal) > 0.0);
+    }
+}
+
+#[test]
+fn test_append_and_transform() {
+    let mut a = MeshBuilder::new();
+    a.cylinder(
+        &na::Point3::new(0.0, 0.0, 0.0),
+        &na::Point3::new(0.0, 0.0, 1.0),
+        0.5,
+        6,
+    );
+    let mut b = MeshBuilder::new();
+    b.cuboid(
+        &na::Point3::new(0.0, 0.0, 0.0),
+        &na::Point3::new(1.0, 1.0, 1.0),
+    );
+
+    let mut combined = a.clone();
+    combined.append(&b);
+
+    assert_eq!(combined.num_vertices(), a.num_vertices() + b.num_vertices());
+    assert_eq!(
+        combined.num_triangles(),
+        a.num_triangles() + b.num_triangles()
+    );
+    assert_eq!(
+        combined.indices[a.indices.len()],
+        b.indices[0] + a.num_vertices() as u32
+    );
+
+    // Scaling keeps normals unit length and pointing outwards.
+    b.transform(&na::Matrix4::new_nonuniform_scaling(&na::Vector3::new(
+        2.0, 1.0, 0.5,
+    )));
+    check_convex(&b, &na::Point3::new(1.0, 0.5, 0.25));
+}
+
+#[test]
+fn test_parse_obj() {
+    let source = "
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+f 1/1 2/1 3/1 4/1
+";
+    let builder = parse_obj(source).unwrap();
+
+    assert_eq!(builder.num_triangles(), 2);
+    assert!(builder.normals.iter().all(|n| *n == na::Vector3::z()));
+
+    assert!(parse_obj("v 0 0 0\nf 1 2 3\n").is_err());
+}
@@ -0,0 +1,207 @@
+//! Shading of textured meshes.
+//!
+//! All textures are kept in a single texture array of fixed size, so that
+//! instances can select their texture by layer. Layer 0 is plain white, which
+//! makes untextured instances look like `BasicObj` instances.
+//!
+//! Since the core only defines the color of fragments, it can be used with
+//! both forward and deferred shading.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use log::{info, warn};
+use nalgebra as na;
+
+use glium::texture::{RawImage2d, Texture2dArray};
+
+use rendology::{shader, Context, CoreInput, SceneCore};
+
+/// Width and height of each texture layer. Textures of different sizes are
+/// scaled to fit.
+pub const TEXTURE_SIZE: u32 = 128;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub tex_coord: [f32; 2],
+}
+
+glium::implement_vertex!(Vertex, position, normal, tex_coord);
+
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub transform: na::Matrix4<f32>,
+    pub color: na::Vector4<f32>,
+
+    /// Layer of the texture to use. If zero, the texture of the mesh is used.
+    pub texture_id: f32,
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Self {
+            transform: na::Matrix4::identity(),
+            color: na::Vector4::new(1.0, 1.0, 1.0, 1.0),
+            texture_id: 0.0,
+        }
+    }
+}
+
+rendology::impl_instance_input!(
+    Instance,
+    self => {
+        instance_transform: [[f32; 4]; 4] = self.transform,
+        instance_color: [f32; 4] = self.color,
+        instance_texture_id: f32 = self.texture_id,
+    },
+);
+
+#[derive(Clone)]
+pub struct Params {
+    pub textures: Rc<Texture2dArray>,
+
+    /// Layer of the texture that belongs to the mesh being drawn.
+    pub texture_id: f32,
+}
+
+rendology::impl_uniform_input!(
+    Params,
+    self => {
+        params_textures: &Texture2dArray = &*self.textures,
+        params_texture_id: f32 = self.texture_id,
+    },
+);
+
+const V_TEX_COORD: (&str, shader::VertexOutDef) = (
+    "v_tex_coord",
+    shader::VertexOutDef(shader::Type::FloatVec2, shader::VertexOutQualifier::Smooth),
+);
+
+const V_TEXTURE_ID: (&str, shader::VertexOutDef) = (
+    "v_texture_id",
+    shader::VertexOutDef(shader::Type::Float, shader::VertexOutQualifier::Flat),
+);
+
+const V_COLOR: (&str, shader::VertexOutDef) = (
+    "v_color",
+    shader::VertexOutDef(shader::Type::FloatVec4, shader::VertexOutQualifier::Flat),
+);
+
+pub struct Core;
+
+impl CoreInput for Core {
+    type Params = Params;
+    type Instance = Instance;
+    type Vertex = Vertex;
+}
+
+impl SceneCore for Core {
+    fn scene_core(&self) -> shader::Core<(Context, Params), Instance, Vertex> {
+        let vertex = shader::VertexCore::empty()
+            .with_out(
+                shader::defs::V_WORLD_NORMAL,
+                "normalize(transpose(inverse(mat3(instance_transform))) * normal)",
+            )
+            .with_out(
+                shader::defs::V_WORLD_POS,
+                "instance_transform * vec4(position, 1.0)",
+            )
+            .with_out(
+                shader::defs::V_POS,
+                "context_camera_projection * context_camera_view * v_world_pos",
+            )
+            .with_out(V_TEX_COORD, "tex_coord")
+            .with_out(
+                V_TEXTURE_ID,
+                "instance_texture_id > 0.0 ? instance_texture_id : params_texture_id",
+            )
+            .with_out(V_COLOR, "instance_color");
+
+        let fragment = shader::FragmentCore::empty()
+            .with_in_def(V_TEX_COORD)
+            .with_in_def(V_TEXTURE_ID)
+            .with_in_def(V_COLOR)
+            .with_out(
+                shader::defs::F_COLOR,
+                "v_color * texture(params_textures, vec3(v_tex_coord, v_texture_id))",
+            );
+
+        shader::Core { vertex, fragment }
+    }
+}
+
+/// Textures that are available for drawing, keyed by name.
+pub struct Textures {
+    array: Rc<Texture2dArray>,
+    ids: HashMap<String, u32>,
+}
+
+impl Textures {
+    /// Loads the given image files into a texture array. Images that fail to
+    /// load are skipped.
+    pub fn load<F: glium::backend::Facade>(
+        facade: &F,
+        textures: &[(String, PathBuf)],
+    ) -> Result<Textures, glium::texture::TextureCreationError> {
+        let white = vec![255; (TEXTURE_SIZE * TEXTURE_SIZE * 4) as usize];
+        let mut layers = vec![RawImage2d::from_raw_rgba(
+            white,
+            (TEXTURE_SIZE, TEXTURE_SIZE),
+        )];
+        let mut ids = HashMap::new();
+
+        for (name, path) in textures {
+            info!("Loading texture {} from file {:?}", name, path);
+
+            match load_image(path) {
+                Ok(image) => {
+                    ids.insert(name.clone(), layers.len() as u32);
+                    layers.push(image);
+                }
+                Err(err) => {
+                    warn!(
+                        "Failed to load texture {} from file {:?}: {}",
+                        name, path, err
+                    );
+                }
+            }
+        }
+
+        let array = Texture2dArray::new(facade, layers)?;
+
+        Ok(Textures {
+            array: Rc::new(array),
+            ids,
+        })
+    }
+
+    pub fn array(&self) -> Rc<Texture2dArray> {
+        self.array.clone()
+    }
+
+    /// Returns the layer of the texture with the given name.
+    pub fn id(&self, name: &str) -> Option<u32> {
+        self.ids.get(name).copied()
+    }
+}
+
+fn load_image(path: &Path) -> Result<RawImage2d<'static, u8>, String> {
+    let image = image::open(path).map_err(|err| err.to_string())?.to_rgba();
+    let image = image::imageops::resize(
+        &image,
+        TEXTURE_SIZE,
+        TEXTURE_SIZE,
+        image::imageops::FilterType::Triangle,
+    );
+
+    // OpenGL expects the first row at the bottom.
+    let image = image::imageops::flip_vertical(&image);
+
+    Ok(RawImage2d::from_raw_rgba(
+        image.into_raw(),
+        (TEXTURE_SIZE, TEXTURE_SIZE),
+    ))
+}
@@ -0,0 +1,91 @@
+//! Darkening of the screen's edges, for backdrops such as the main menu and
+//! photo mode.
+//!
+//! The vignette is drawn as nested frames of translucent screen-space quads,
+//! so it needs no pass of its own and can be toggled from one frame to the
+//! next without recreating the render pipeline.
+
+use nalgebra as na;
+
+use rendology::{basic_obj, BasicObj};
+
+use crate::render::Stage;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Whether the vignette is drawn behind the main menu.
+    pub menu: bool,
+
+    /// Whether the vignette is drawn in photo mode, including screenshots.
+    pub photo_mode: bool,
+
+    /// Opacity of the darkening at the edge of the screen.
+    pub strength: f32,
+
+    /// Fraction of the smaller side of the screen that the vignette covers
+    /// from each edge.
+    pub size: f32,
+
+    /// Number of frames that make up the gradient.
+    pub num_bands: usize,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            menu: true,
+            photo_mode: false,
+            strength: 0.6,
+            size: 0.25,
+            num_bands: 12,
+        }
+    }
+}
+
+pub fn render(config: &Config, viewport_size: &na::Vector2<f32>, out: &mut Stage) {
+    if config.num_bands == 0 || config.strength <= 0.0 {
+        return;
+    }
+
+    let width = config.size * viewport_size.x.min(viewport_size.y) / config.num_bands as f32;
+    let mut add_rect = |min: na::Vector2<f32>, size: na::Vector2<f32>, alpha: f32| {
+        out.ortho[BasicObj::Quad].add(basic_obj::Instance {
+            transform: na::Matrix4::new_translation(&na::Vector3::new(min.x, min.y, 0.0))
+                * na::Matrix4::new_nonuniform_scaling(&na::Vector3::new(size.x, size.y, 1.0)),
+            color: na::Vector4::new(0.0, 0.0, 0.0, alpha),
+            ..Default::default()
+        });
+    };
+
+    for i in 0..config.num_bands {
+        // Fade out quadratically towards the inside.
+        let t = 1.0 - (i as f32 + 0.5) / config.num_bands as f32;
+        let alpha = config.strength * t * t;
+
+        let offset = i as f32 * width;
+        let inner = viewport_size - na::Vector2::new(2.0, 2.0) * offset;
+        if inner.x <= 2.0 * width || inner.y <= 2.0 * width {
+            break;
+        }
+
+        let (w, h) = (inner.x, inner.y);
+        let o = offset;
+
+        add_rect(na::Vector2::new(o, o), na::Vector2::new(w, width), alpha);
+        add_rect(
+            na::Vector2::new(o, o + h - width),
+            na::Vector2::new(w, width),
+            alpha,
+        );
+        add_rect(
+            na::Vector2::new(o, o + width),
+            na::Vector2::new(width, h - 2.0 * width),
+            alpha,
+        );
+        add_rect(
+            na::Vector2::new(o + w - width, o + width),
+            na::Vector2::new(width, h - 2.0 * width),
+            alpha,
+        );
+    }
+}
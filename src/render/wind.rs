@@ -7,6 +7,10 @@ pub struct Params {
     pub tick_progress: f32,
     pub color: na::Vector4<f32>,
     pub stripe_color: na::Vector4<f32>,
+
+    /// Global wind phase that drives idle animations, in seconds. Unlike
+    /// the tick progress, this keeps running while execution is paused.
+    pub idle_time: f32,
 }
 
 #[derive(Debug, Clone)]
@@ -15,6 +19,13 @@ pub struct Instance {
     pub phase: f32,
     pub start: f32,
     pub end: f32,
+
+    /// Amplitude of swaying in the wind. Instances with a nonzero amplitude
+    /// are decorations, which are drawn in `color` instead of as a wind
+    /// stripe, and which sway more the further their vertices are from the
+    /// origin of the mesh.
+    pub idle_anim: f32,
+    pub color: na::Vector4<f32>,
 }
 
 rendology::impl_uniform_input!(
@@ -23,6 +34,7 @@ rendology::impl_uniform_input!(
         params_tick_progress: f32 = self.tick_progress,
         params_color: [f32; 4] = self.color,
         params_stripe_color: [f32; 4] = self.stripe_color,
+        params_idle_time: f32 = self.idle_time,
     },
 );
 
@@ -33,6 +45,8 @@ rendology::impl_instance_input!(
         instance_phase: f32 = self.phase,
         instance_start: f32 = self.start,
         instance_end: f32 = self.end,
+        instance_idle_anim: f32 = self.idle_anim,
+        instance_color: [f32; 4] = self.color,
     },
 );
 
@@ -51,6 +65,16 @@ const V_INSTANCE_END: (&str, shader::VertexOutDef) = (
     shader::VertexOutDef(shader::Type::Float, shader::VertexOutQualifier::Flat),
 );
 
+const V_IDLE_ANIM: (&str, shader::VertexOutDef) = (
+    "v_idle_anim",
+    shader::VertexOutDef(shader::Type::Float, shader::VertexOutQualifier::Flat),
+);
+
+const V_COLOR: (&str, shader::VertexOutDef) = (
+    "v_color",
+    shader::VertexOutDef(shader::Type::FloatVec4, shader::VertexOutQualifier::Flat),
+);
+
 pub struct Core;
 
 impl CoreInput for Core {
@@ -71,25 +95,40 @@ impl SceneCore for Core {
             )
             .with_body(
                 "
-                float angle = (position.x + 0.5) * PI * 4.0
-                    + params_tick_progress * PI * 2.0
-                    + instance_phase;
-
-                float rot_s = sin(angle);
-                float rot_c = cos(angle);
-                mat2 rot_m = mat2(rot_c, -rot_s, rot_s, rot_c);
-
-                float radius_scale = 1.0;
-                //radius_scale *= 0.5 - position.x - instance_start;
-                //radius_scale *= instance_end - 0.5 - position.x;
-
                 vec3 scaled_pos = position;
-                scaled_pos.yz *= scale;
-                scaled_pos.z += radius * radius_scale;
-
                 vec3 rot_normal = normal;
-                scaled_pos.yz = rot_m * scaled_pos.yz;
-                rot_normal.yz = rot_m * rot_normal.yz;
+
+                if (instance_idle_anim > 0.0) {
+                    // Decorations sway around their attachment point, with a
+                    // phase that depends on where they are in the world, so
+                    // that neighboring decorations do not move in lockstep.
+                    vec3 anchor = instance_transform[3].xyz;
+                    float sway_phase = params_idle_time * 2.0
+                        + anchor.x * 0.7
+                        + anchor.y * 1.3;
+                    float sway = instance_idle_anim * length(position);
+
+                    scaled_pos.x += sin(sway_phase) * sway;
+                    scaled_pos.y += 0.5 * cos(sway_phase * 1.3) * sway;
+                } else {
+                    float angle = (position.x + 0.5) * PI * 4.0
+                        + params_tick_progress * PI * 2.0
+                        + instance_phase;
+
+                    float rot_s = sin(angle);
+                    float rot_c = cos(angle);
+                    mat2 rot_m = mat2(rot_c, -rot_s, rot_s, rot_c);
+
+                    float radius_scale = 1.0;
+                    //radius_scale *= 0.5 - position.x - instance_start;
+                    //radius_scale *= instance_end - 0.5 - position.x;
+
+                    scaled_pos.yz *= scale;
+                    scaled_pos.z += radius * radius_scale;
+
+                    scaled_pos.yz = rot_m * scaled_pos.yz;
+                    rot_normal.yz = rot_m * rot_normal.yz;
+                }
                 ",
             )
             .with_out(
@@ -103,6 +142,8 @@ impl SceneCore for Core {
             .with_out(V_X, "0.5 - position.x")
             .with_out(V_INSTANCE_START, "instance_start")
             .with_out(V_INSTANCE_END, "instance_end")
+            .with_out(V_IDLE_ANIM, "instance_idle_anim")
+            .with_out(V_COLOR, "instance_color")
             .with_out(
                 shader::defs::V_POS,
                 "context_camera_projection * context_camera_view * v_world_pos",
@@ -112,10 +153,14 @@ impl SceneCore for Core {
             .with_in_def(V_X)
             .with_in_def(V_INSTANCE_START)
             .with_in_def(V_INSTANCE_END)
+            .with_in_def(V_IDLE_ANIM)
+            .with_in_def(V_COLOR)
             .with_defs(
                 "
                 vec4 wind_color() {
-                    if (v_x <= params_tick_progress + 0.01 && v_x > params_tick_progress - 0.3)
+                    if (v_idle_anim > 0.0)
+                        return v_color;
+                    else if (v_x <= params_tick_progress + 0.01 && v_x > params_tick_progress - 0.3)
                         return params_stripe_color;
                     else if (v_instance_end == 1.0 && v_x > 0.7 + params_tick_progress)
                         return params_stripe_color;
@@ -126,7 +171,8 @@ impl SceneCore for Core {
             )
             .with_body(
                 "
-                if (v_x < v_instance_start || v_x > v_instance_end || v_instance_start == v_instance_end)
+                if (v_idle_anim == 0.0
+                    && (v_x < v_instance_start || v_x > v_instance_end || v_instance_start == v_instance_end))
                     discard;
                 ",
             )
@@ -0,0 +1,207 @@
+//! Sandboxed Lua scripts that levels can use to generate their inputs and
+//! outputs, and to define the behavior of `Block::Scripted`.
+//!
+//! A level script may define the following globals:
+//!
+//! ```lua
+//! -- Returns one sequence per input and output. Inputs are "A", "B" or "-"
+//! -- for no blip; outputs are "A" or "B".
+//! function generate(seed)
+//!     math.randomseed(seed)
+//!     return { inputs = { { "A", "-", "B" } }, outputs = { { "B", "A" } } }
+//! end
+//!
+//! -- Called with the blip kind and the current tick whenever a blip enters a
+//! -- scripted block of the same name. Returns the kind of blip to emit, or
+//! -- nil.
+//! blocks = {
+//!     invert = function(kind, tick)
+//!         if kind == "A" then return "B" else return "A" end
+//!     end,
+//! }
+//! ```
+//!
+//! Scripting is only available if the game is built with the `scripting`
+//! feature.
+
+use std::fmt;
+
+#[cfg(feature = "scripting")]
+use crate::machine::level::Input;
+use crate::machine::level::InputsOutputs;
+use crate::machine::{BlipKind, TickNum};
+
+/// Maximal number of Lua instructions that a single call into a script may
+/// execute before it is aborted.
+pub const MAX_INSTRUCTIONS: u32 = 1_000_000;
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Error {
+    /// The game was built without the `scripting` feature.
+    Disabled,
+
+    /// The level needs a script, but it does not have one.
+    Missing,
+
+    Lua(String),
+    InvalidResult(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Disabled => write!(f, "scripting is not enabled in this build"),
+            Error::Missing => write!(f, "level has no script"),
+            Error::Lua(err) => write!(f, "script error: {}", err),
+            Error::InvalidResult(err) => write!(f, "invalid script result: {}", err),
+        }
+    }
+}
+
+#[cfg(feature = "scripting")]
+impl From<rlua::Error> for Error {
+    fn from(err: rlua::Error) -> Error {
+        Error::Lua(err.to_string())
+    }
+}
+
+#[cfg(feature = "scripting")]
+fn parse_blip_kind(s: &str) -> Result<BlipKind, Error> {
+    match s {
+        "A" => Ok(BlipKind::A),
+        "B" => Ok(BlipKind::B),
+        s => Err(Error::InvalidResult(format!("unknown blip kind {:?}", s))),
+    }
+}
+
+#[cfg(feature = "scripting")]
+fn parse_input(s: &str) -> Result<Option<Input>, Error> {
+    match s {
+        "-" => Ok(None),
+        s => parse_blip_kind(s).map(|kind| Some(Input::Blip(kind))),
+    }
+}
+
+#[cfg(feature = "scripting")]
+fn blip_kind_str(kind: BlipKind) -> &'static str {
+    match kind {
+        BlipKind::A => "A",
+        BlipKind::B => "B",
+    }
+}
+
+#[cfg(feature = "scripting")]
+fn parse_inputs_outputs(
+    inputs: Vec<Vec<String>>,
+    outputs: Vec<Vec<String>>,
+) -> Result<InputsOutputs, Error> {
+    let inputs = inputs
+        .iter()
+        .map(|seq| seq.iter().map(|s| parse_input(s)).collect())
+        .collect::<Result<_, _>>()?;
+    let outputs = outputs
+        .iter()
+        .map(|seq| seq.iter().map(|s| parse_blip_kind(s)).collect())
+        .collect::<Result<_, _>>()?;
+
+    Ok(InputsOutputs { inputs, outputs })
+}
+
+/// A loaded level script.
+pub struct Runtime {
+    #[cfg(feature = "scripting")]
+    lua: rlua::Lua,
+}
+
+#[cfg(feature = "scripting")]
+impl Runtime {
+    pub fn new(source: &str) -> Result<Runtime, Error> {
+        use rlua::StdLib;
+
+        // Only load libraries that do not give access to the outside world.
+        let lua = rlua::Lua::new_with(StdLib::BASE | StdLib::TABLE | StdLib::STRING | StdLib::MATH);
+
+        lua.context(|ctx| {
+            // The base library can still load files.
+            let globals = ctx.globals();
+            for name in &["dofile", "loadfile", "load", "require"] {
+                globals.set(*name, rlua::Value::Nil)?;
+            }
+
+            ctx.load(source).exec()
+        })?;
+
+        let runtime = Runtime { lua };
+        runtime.reset_instruction_limit();
+
+        Ok(runtime)
+    }
+
+    /// Restarts counting instructions towards `MAX_INSTRUCTIONS`.
+    fn reset_instruction_limit(&self) {
+        self.lua.set_hook(
+            rlua::HookTriggers {
+                every_nth_instruction: Some(MAX_INSTRUCTIONS),
+                ..Default::default()
+            },
+            |_, _| {
+                Err(rlua::Error::RuntimeError(
+                    "script exceeded instruction limit".to_string(),
+                ))
+            },
+        );
+    }
+
+    pub fn generate(&self, seed: u32) -> Result<InputsOutputs, Error> {
+        self.reset_instruction_limit();
+
+        let (inputs, outputs) = self.lua.context(|ctx| {
+            let generate: rlua::Function = ctx.globals().get("generate")?;
+            let result: rlua::Table = generate.call(seed)?;
+            let inputs: Vec<Vec<String>> = result.get("inputs")?;
+            let outputs: Vec<Vec<String>> = result.get("outputs")?;
+
+            Ok::<_, rlua::Error>((inputs, outputs))
+        })?;
+
+        parse_inputs_outputs(inputs, outputs)
+    }
+
+    pub fn run_block(
+        &self,
+        name: &str,
+        kind: BlipKind,
+        tick: TickNum,
+    ) -> Result<Option<BlipKind>, Error> {
+        self.reset_instruction_limit();
+
+        let result: Option<String> = self.lua.context(|ctx| {
+            let blocks: rlua::Table = ctx.globals().get("blocks")?;
+            let function: rlua::Function = blocks.get(name)?;
+
+            function.call((blip_kind_str(kind), tick as i64))
+        })?;
+
+        result.map(|s| parse_blip_kind(&s)).transpose()
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+impl Runtime {
+    pub fn new(_source: &str) -> Result<Runtime, Error> {
+        Err(Error::Disabled)
+    }
+
+    pub fn generate(&self, _seed: u32) -> Result<InputsOutputs, Error> {
+        Err(Error::Disabled)
+    }
+
+    pub fn run_block(
+        &self,
+        _name: &str,
+        _kind: BlipKind,
+        _tick: TickNum,
+    ) -> Result<Option<BlipKind>, Error> {
+        Err(Error::Disabled)
+    }
+}
@@ -0,0 +1,252 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::exec::LevelStatus;
+use crate::machine::grid::Point3;
+use crate::machine::{BlockType, Level, Machine};
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub enabled: bool,
+
+    /// Directory that is searched for tutorials. A tutorial in this directory
+    /// overrides the builtin tutorial of the level with the same id.
+    pub dir: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: PathBuf::from("tutorials"),
+        }
+    }
+}
+
+/// What to highlight in the machine while a step is active.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub enum Target {
+    /// All blocks of the given type.
+    Blocks(BlockType),
+
+    /// A single grid position.
+    Pos(Point3),
+}
+
+/// A condition that completes a tutorial step.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub enum Condition {
+    /// The player has read the message and clicked on "Next".
+    Acknowledge,
+
+    /// At least one block of the given type has been placed.
+    PlaceBlock(BlockType),
+
+    /// Execution has been started.
+    StartExec,
+
+    /// The level has been completed.
+    CompleteLevel,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct Step {
+    pub message: String,
+
+    #[serde(default)]
+    pub target: Option<Target>,
+
+    pub condition: Condition,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct Tutorial {
+    pub steps: Vec<Step>,
+}
+
+impl Tutorial {
+    /// Returns the tutorial for the given level, if there is one. Tutorials
+    /// are first looked up in the configured directory as `<level_id>.json`.
+    pub fn for_level(config: &Config, level: &Level) -> Option<Tutorial> {
+        let path = config.dir.join(format!("{}.json", level.id()));
+
+        if path.exists() {
+            if let Some(tutorial) = Self::load(&path) {
+                return Some(tutorial);
+            }
+        }
+
+        builtin(level)
+    }
+
+    pub fn load(path: &Path) -> Option<Tutorial> {
+        info!("Loading tutorial from file {:?}", path);
+
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) => {
+                warn!("Could not open file {:?}: {}", path, err);
+                return None;
+            }
+        };
+
+        let reader = BufReader::new(file);
+        match serde_json::from_reader(reader) {
+            Ok(tutorial) => Some(tutorial),
+            Err(err) => {
+                warn!("Error while loading tutorial from file {:?}: {}", path, err);
+                None
+            }
+        }
+    }
+}
+
+/// The state of the game that is relevant for completing tutorial steps.
+pub struct Context<'a> {
+    pub machine: &'a Machine,
+    pub is_executing: bool,
+    pub level_status: Option<LevelStatus>,
+    pub acknowledged: bool,
+}
+
+impl Condition {
+    pub fn is_met(&self, context: &Context) -> bool {
+        match self {
+            Condition::Acknowledge => context.acknowledged,
+            Condition::PlaceBlock(block_type) => context
+                .machine
+                .iter_player_blocks()
+                .any(|(_, (_, placed_block))| placed_block.block.block_type() == *block_type),
+            Condition::StartExec => context.is_executing,
+            Condition::CompleteLevel => context.level_status == Some(LevelStatus::Completed),
+        }
+    }
+}
+
+/// Information for showing the current tutorial step in the UI.
+#[derive(Debug, Clone)]
+pub struct Hint {
+    pub message: String,
+    pub step_index: usize,
+    pub num_steps: usize,
+    pub needs_acknowledge: bool,
+}
+
+/// Progress through a tutorial.
+#[derive(Debug, Clone)]
+pub struct State {
+    tutorial: Tutorial,
+    step_index: usize,
+}
+
+impl State {
+    pub fn new(tutorial: Tutorial) -> Self {
+        Self {
+            tutorial,
+            step_index: 0,
+        }
+    }
+
+    pub fn current_step(&self) -> Option<&Step> {
+        self.tutorial.steps.get(self.step_index)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.step_index >= self.tutorial.steps.len()
+    }
+
+    pub fn skip(&mut self) {
+        self.step_index = self.tutorial.steps.len();
+    }
+
+    /// Advances to the next step if the current step's condition is met.
+    pub fn update(&mut self, context: &Context) {
+        let is_met = self
+            .current_step()
+            .map_or(false, |step| step.condition.is_met(context));
+
+        if is_met {
+            self.step_index += 1;
+        }
+    }
+
+    pub fn hint(&self) -> Option<Hint> {
+        self.current_step().map(|step| Hint {
+            message: step.message.clone(),
+            step_index: self.step_index,
+            num_steps: self.tutorial.steps.len(),
+            needs_acknowledge: step.condition == Condition::Acknowledge,
+        })
+    }
+
+    /// Returns the positions of the blocks that should be highlighted for the
+    /// current step.
+    pub fn target_positions(&self, machine: &Machine) -> Vec<Point3> {
+        match self.current_step().and_then(|step| step.target.as_ref()) {
+            Some(Target::Blocks(block_type)) => machine
+                .iter_blocks()
+                .filter(|(_, (_, placed_block))| placed_block.block.block_type() == *block_type)
+                .map(|(_, (pos, _))| *pos)
+                .collect(),
+            Some(Target::Pos(pos)) => vec![*pos],
+            None => Vec::new(),
+        }
+    }
+}
+
+fn step(message: &str, target: Option<Target>, condition: Condition) -> Step {
+    Step {
+        message: message.to_string(),
+        target,
+        condition,
+    }
+}
+
+/// Returns the builtin tutorial for the given level, if any.
+fn builtin(level: &Level) -> Option<Tutorial> {
+    match level.id().as_str() {
+        "clock" => Some(Tutorial {
+            steps: vec![
+                step(
+                    "Welcome! Your goal is to send blips into the output on the right.\n\
+                     The output expects the pattern that is shown in the level window.",
+                    Some(Target::Blocks(BlockType::Output)),
+                    Condition::Acknowledge,
+                ),
+                step(
+                    "Blips are created by blip spawns.\n\
+                     Select a blip spawn in the editor and place it with the left mouse button.",
+                    None,
+                    Condition::PlaceBlock(BlockType::BlipSpawn),
+                ),
+                step(
+                    "Blips are moved by wind. Place a wind source.",
+                    None,
+                    Condition::PlaceBlock(BlockType::WindSource),
+                ),
+                step(
+                    "Wind flows through pipes. Use the pipe tool to connect your blocks \
+                     to the output.",
+                    Some(Target::Blocks(BlockType::Output)),
+                    Condition::PlaceBlock(BlockType::Pipe),
+                ),
+                step(
+                    "Start the simulation to see if your machine works.",
+                    None,
+                    Condition::StartExec,
+                ),
+                step(
+                    "Keep improving your machine until the output is satisfied.\n\
+                     You can change the color of blips with the color button.",
+                    Some(Target::Blocks(BlockType::Output)),
+                    Condition::CompleteLevel,
+                ),
+            ],
+        }),
+        _ => None,
+    }
+}
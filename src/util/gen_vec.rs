@@ -0,0 +1,177 @@
+//! An arena whose ids stay valid only as long as their element lives.
+//!
+//! Like `VecOption`, elements are stored in a `Vec` of slots, and slots of
+//! removed elements are reused. In addition, each slot has a generation that
+//! is increased whenever its element is removed. Ids carry the generation of
+//! their element, so an id that is held on to after its element has been
+//! removed does not refer to a different element that reuses the slot.
+
+use std::ops::{Index, IndexMut};
+
+/// Stable handle of an element in a `GenVec`.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug, Hash)]
+pub struct Id {
+    pub index: usize,
+    pub generation: u32,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct GenVec<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<usize>,
+    size: usize,
+}
+
+impl<T> Default for GenVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> GenVec<T> {
+    pub fn new() -> Self {
+        GenVec {
+            slots: Vec::new(),
+            free: Vec::new(),
+            size: 0,
+        }
+    }
+
+    pub fn add(&mut self, value: T) -> Id {
+        self.size += 1;
+
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index];
+            debug_assert!(slot.value.is_none());
+
+            slot.value = Some(value);
+            Id {
+                index,
+                generation: slot.generation,
+            }
+        } else {
+            self.slots.push(Slot {
+                generation: 0,
+                value: Some(value),
+            });
+            Id {
+                index: self.slots.len() - 1,
+                generation: 0,
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: Id) -> Option<T> {
+        let slot = self.slots.get_mut(id.index)?;
+
+        if slot.generation != id.generation {
+            return None;
+        }
+
+        let value = slot.value.take();
+
+        if value.is_some() {
+            slot.generation = slot.generation.wrapping_add(1);
+            self.size -= 1;
+            self.free.push(id.index);
+        }
+
+        value
+    }
+
+    pub fn contains(&self, id: Id) -> bool {
+        self.get(id).is_some()
+    }
+
+    pub fn get(&self, id: Id) -> Option<&T> {
+        self.slots
+            .get(id.index)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.value.as_ref())
+    }
+
+    pub fn get_mut(&mut self, id: Id) -> Option<&mut T> {
+        self.slots
+            .get_mut(id.index)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.value.as_mut())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Id, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.value.as_ref().map(|value| {
+                (
+                    Id {
+                        index,
+                        generation: slot.generation,
+                    },
+                    value,
+                )
+            })
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Id, &mut T)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let generation = slot.generation;
+
+                slot.value
+                    .as_mut()
+                    .map(|value| (Id { index, generation }, value))
+            })
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = Id> + '_ {
+        self.iter().map(|(id, _value)| id)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.iter().map(|(_id, value)| value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn retain(&mut self, mut f: impl FnMut(&T) -> bool) {
+        for index in 0..self.slots.len() {
+            let slot = &self.slots[index];
+            let remove = slot.value.as_ref().map_or(false, |value| !f(value));
+
+            if remove {
+                let id = Id {
+                    index,
+                    generation: slot.generation,
+                };
+                self.remove(id);
+            }
+        }
+    }
+}
+
+impl<T> Index<Id> for GenVec<T> {
+    type Output = T;
+
+    fn index(&self, id: Id) -> &T {
+        self.get(id).expect("Invalid GenVec id")
+    }
+}
+
+impl<T> IndexMut<Id> for GenVec<T> {
+    fn index_mut(&mut self, id: Id) -> &mut T {
+        self.get_mut(id).expect("Invalid GenVec id")
+    }
+}
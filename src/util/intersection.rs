@@ -2,6 +2,8 @@ use std::mem;
 
 use nalgebra as na;
 
+use crate::machine::grid::{self, Axis3, Dir3, Sign};
+
 pub struct Ray {
     pub origin: na::Point3<f32>,
     pub velocity: na::Vector3<f32>,
@@ -93,3 +95,177 @@ pub fn ray_aabb_intersection(ray: &Ray, aabb: &AABB) -> Option<f32> {
 
     Some(t_min)
 }
+
+/// A cell of a grid that is hit by a ray.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridHit {
+    pub pos: grid::Point3,
+
+    /// The face of the cell through which the ray enters, given as the
+    /// direction that points out of the cell. This is `None` if the ray
+    /// starts inside of the cell.
+    pub face: Option<Dir3>,
+
+    /// Time of impact on the ray.
+    pub t: f32,
+}
+
+/// Walks the cells of a grid with the given size that are hit by a ray, in
+/// front-to-back order. Each cell spans from its position to its position
+/// plus one along each axis.
+///
+/// As in:
+/// Amanatides and Woo, "A Fast Voxel Traversal Algorithm for Ray Tracing"
+pub fn ray_grid_traversal(ray: &Ray, size: &grid::Vector3) -> GridTraversal {
+    let size_float: na::Vector3<f32> = na::convert(*size);
+
+    // Clip the ray to the grid's bounding box.
+    let mut t_enter = 0.0f32;
+    let mut t_exit = std::f32::INFINITY;
+    let mut enter_axis = None;
+
+    for &axis in &Axis3::ALL {
+        let i = axis.to_index();
+
+        if ray.velocity[i] == 0.0 {
+            if ray.origin[i] < 0.0 || ray.origin[i] >= size_float[i] {
+                t_exit = -1.0;
+            }
+            continue;
+        }
+
+        let mut t_a = -ray.origin[i] / ray.velocity[i];
+        let mut t_b = (size_float[i] - ray.origin[i]) / ray.velocity[i];
+
+        if t_a > t_b {
+            mem::swap(&mut t_a, &mut t_b);
+        }
+
+        if t_a > t_enter {
+            t_enter = t_a;
+            enter_axis = Some(axis);
+        }
+
+        t_exit = t_exit.min(t_b);
+    }
+
+    if t_enter > t_exit || size.x <= 0 || size.y <= 0 || size.z <= 0 {
+        return GridTraversal {
+            size: *size,
+            cell: None,
+            face: None,
+            step: grid::Vector3::zeros(),
+            t_max: na::Vector3::zeros(),
+            t_delta: na::Vector3::zeros(),
+            t: t_enter,
+        };
+    }
+
+    let start = ray.origin + t_enter * ray.velocity;
+    let cell = grid::Point3::new(
+        (start.x.floor() as isize).max(0).min(size.x - 1),
+        (start.y.floor() as isize).max(0).min(size.y - 1),
+        (start.z.floor() as isize).max(0).min(size.z - 1),
+    );
+
+    let mut step = grid::Vector3::zeros();
+    let mut t_max = na::Vector3::repeat(std::f32::INFINITY);
+    let mut t_delta = na::Vector3::repeat(std::f32::INFINITY);
+
+    for &axis in &Axis3::ALL {
+        let i = axis.to_index();
+
+        if ray.velocity[i] > 0.0 {
+            step[i] = 1;
+            t_max[i] = (cell[i] as f32 + 1.0 - ray.origin[i]) / ray.velocity[i];
+            t_delta[i] = 1.0 / ray.velocity[i];
+        } else if ray.velocity[i] < 0.0 {
+            step[i] = -1;
+            t_max[i] = (cell[i] as f32 - ray.origin[i]) / ray.velocity[i];
+            t_delta[i] = -1.0 / ray.velocity[i];
+        }
+    }
+
+    let face = enter_axis.map(|axis| entry_face(axis, step[axis.to_index()]));
+
+    GridTraversal {
+        size: *size,
+        cell: Some(cell),
+        face,
+        step,
+        t_max,
+        t_delta,
+        t: t_enter,
+    }
+}
+
+/// Returns the first cell hit by a ray for which `is_occupied` is true.
+pub fn ray_grid_intersection(
+    ray: &Ray,
+    size: &grid::Vector3,
+    is_occupied: impl Fn(&grid::Point3) -> bool,
+) -> Option<GridHit> {
+    ray_grid_traversal(ray, size).find(|hit| is_occupied(&hit.pos))
+}
+
+/// Returns the face of a cell that is entered when stepping along an axis.
+fn entry_face(axis: Axis3, step: isize) -> Dir3 {
+    if step > 0 {
+        Dir3(axis, Sign::Neg)
+    } else {
+        Dir3(axis, Sign::Pos)
+    }
+}
+
+pub struct GridTraversal {
+    size: grid::Vector3,
+    cell: Option<grid::Point3>,
+    face: Option<Dir3>,
+    step: grid::Vector3,
+    t_max: na::Vector3<f32>,
+    t_delta: na::Vector3<f32>,
+    t: f32,
+}
+
+impl Iterator for GridTraversal {
+    type Item = GridHit;
+
+    fn next(&mut self) -> Option<GridHit> {
+        let cell = self.cell?;
+        let hit = GridHit {
+            pos: cell,
+            face: self.face,
+            t: self.t,
+        };
+
+        // Step along the axis whose cell boundary is crossed first.
+        let axis = if self.t_max.x < self.t_max.y {
+            if self.t_max.x < self.t_max.z {
+                Axis3::X
+            } else {
+                Axis3::Z
+            }
+        } else if self.t_max.y < self.t_max.z {
+            Axis3::Y
+        } else {
+            Axis3::Z
+        };
+        let i = axis.to_index();
+
+        let mut next_cell = cell;
+        next_cell[i] += self.step[i];
+
+        let is_valid = self.step[i] != 0 && next_cell[i] >= 0 && next_cell[i] < self.size[i];
+
+        if is_valid {
+            self.t = self.t_max[i];
+            self.t_max[i] += self.t_delta[i];
+            self.face = Some(entry_face(axis, self.step[i]));
+            self.cell = Some(next_cell);
+        } else {
+            self.cell = None;
+        }
+
+        Some(hit)
+    }
+}
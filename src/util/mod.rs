@@ -1,6 +1,9 @@
+pub mod gen_vec;
 #[macro_use]
 #[allow(dead_code)]
 pub mod intersection;
 pub mod stats;
+#[cfg(test)]
+mod tests;
 pub mod timer;
 pub mod vec_option;
@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use nalgebra as na;
+use rayon::prelude::*;
+
+use crate::machine::grid::{Dir3, Point3, Vector3};
+use crate::util::intersection::{ray_grid_intersection, ray_grid_traversal, Ray};
+use crate::util::timer::{CatchUp, Timer};
+use crate::util::vec_option::VecOption;
+
+fn vec_option_from(values: &[usize]) -> VecOption<usize> {
+    let mut vec = VecOption::new();
+
+    for &value in values {
+        vec.add(value);
+    }
+
+    vec
+}
+
+#[test]
+fn test_vec_option_retain() {
+    let mut vec = vec_option_from(&[0, 1, 2, 3, 4, 5]);
+    vec.retain(|value| value % 2 == 0);
+
+    assert_eq!(vec.len(), 3);
+    assert_eq!(vec.num_free(), 3);
+    assert_eq!(vec.iter_indices().collect::<Vec<_>>(), vec![0, 2, 4]);
+
+    // Free slots are reused.
+    assert_eq!(vec.add(6), 1);
+}
+
+#[test]
+fn test_vec_option_drain() {
+    let mut vec = vec_option_from(&[10, 11, 12]);
+    vec.remove(1);
+
+    assert_eq!(vec.drain().collect::<Vec<_>>(), vec![(0, 10), (2, 12)]);
+    assert!(vec.is_empty());
+    assert_eq!(vec.num_free(), 0);
+    assert_eq!(vec.add(13), 0);
+}
+
+#[test]
+fn test_vec_option_shrink_to_fit() {
+    let mut vec = vec_option_from(&[0, 1, 2, 3]);
+    vec.remove(1);
+    vec.remove(3);
+    vec.shrink_to_fit();
+
+    // Only the free slot in between occupied slots is kept.
+    assert_eq!(vec.num_free(), 1);
+    assert!(vec.capacity() >= 3);
+    assert_eq!(vec.add(4), 1);
+    assert_eq!(vec.add(5), 3);
+    assert_eq!(
+        vec.iter().collect::<Vec<_>>(),
+        vec![(0, &0), (1, &4), (2, &2), (3, &5)]
+    );
+}
+
+#[test]
+fn test_vec_option_par_iter() {
+    let mut vec = vec_option_from(&(0..100).collect::<Vec<_>>());
+    vec.retain(|value| value % 3 != 0);
+
+    vec.par_iter_mut().for_each(|(index, value)| {
+        assert_eq!(index, *value);
+        *value *= 2;
+    });
+
+    let mut par = vec
+        .par_iter()
+        .map(|(index, value)| (index, *value))
+        .collect::<Vec<_>>();
+    par.sort();
+
+    let seq = vec
+        .iter()
+        .map(|(index, value)| (index, *value))
+        .collect::<Vec<_>>();
+    assert_eq!(par, seq);
+}
+
+#[test]
+fn test_ray_grid_traversal() {
+    let size = Vector3::new(4, 4, 4);
+
+    // A ray from outside of the grid enters through the face that points
+    // towards its origin.
+    let ray = Ray {
+        origin: na::Point3::new(-2.0, 1.5, 2.5),
+        velocity: na::Vector3::new(1.0, 0.0, 0.0),
+    };
+    let cells: Vec<_> = ray_grid_traversal(&ray, &size).collect();
+    assert_eq!(cells.len(), 4);
+    assert_eq!(cells[0].pos, Point3::new(0, 1, 2));
+    assert_eq!(cells[0].face, Some(Dir3::X_NEG));
+    assert_eq!(cells[3].pos, Point3::new(3, 1, 2));
+
+    let hit = ray_grid_intersection(&ray, &size, |pos| pos.x == 2).unwrap();
+    assert_eq!(hit.pos, Point3::new(2, 1, 2));
+    assert_eq!(hit.face, Some(Dir3::X_NEG));
+    assert!((hit.t - 4.0).abs() < 1e-5);
+
+    // Looking down diagonally visits cells front-to-back without gaps.
+    let ray = Ray {
+        origin: na::Point3::new(0.5, 0.5, 10.0),
+        velocity: na::Vector3::new(0.3, 0.2, -1.0),
+    };
+    let cells: Vec<_> = ray_grid_traversal(&ray, &size).collect();
+    assert_eq!(cells[0].face, Some(Dir3::Z_POS));
+    for pair in cells.windows(2) {
+        let delta = pair[1].pos - pair[0].pos;
+        assert_eq!(delta.x.abs() + delta.y.abs() + delta.z.abs(), 1);
+        assert!(pair[1].t >= pair[0].t);
+    }
+
+    // Rays that start inside of a cell do not enter through a face.
+    let ray = Ray {
+        origin: na::Point3::new(1.5, 1.5, 1.5),
+        velocity: na::Vector3::new(0.0, -1.0, 0.0),
+    };
+    let hit = ray_grid_intersection(&ray, &size, |_| true).unwrap();
+    assert_eq!(hit.pos, Point3::new(1, 1, 1));
+    assert_eq!(hit.face, None);
+
+    // Rays that miss the grid do not visit any cells.
+    let ray = Ray {
+        origin: na::Point3::new(-1.0, 5.0, 0.5),
+        velocity: na::Vector3::new(1.0, 0.0, 0.0),
+    };
+    assert_eq!(ray_grid_traversal(&ray, &size).count(), 0);
+}
+
+#[test]
+fn test_timer_drain_triggers() {
+    let period = Duration::from_millis(100);
+    let mut timer = Timer::new(period);
+
+    assert_eq!(timer.drain_triggers(Duration::from_millis(250)), 2);
+    assert!((timer.progress() - 0.5).abs() < 1e-3);
+
+    timer.pause();
+    assert_eq!(timer.drain_triggers(Duration::from_secs(1)), 0);
+    timer.resume();
+
+    timer.set_scale(2.0);
+    assert_eq!(timer.drain_triggers(Duration::from_millis(100)), 2);
+    timer.set_scale(1.0);
+
+    // Periods beyond the limit are caught up on later.
+    timer.set_catch_up(CatchUp::Limit(3));
+    assert_eq!(timer.drain_triggers(Duration::from_millis(450)), 3);
+    assert_eq!(timer.drain_triggers(Duration::from_millis(0)), 2);
+
+    // Periods beyond the limit are dropped.
+    timer.set_catch_up(CatchUp::Skip(3));
+    assert_eq!(timer.drain_triggers(Duration::from_millis(1000)), 3);
+    assert_eq!(timer.drain_triggers(Duration::from_millis(0)), 0);
+}
@@ -13,11 +13,35 @@ pub fn hz_to_period(hz: f32) -> Duration {
     secs_to_duration(1.0 / hz)
 }
 
-/// A timer that can be used to trigger events that happen periodically.
+/// How a timer deals with more periods having elapsed at once than should
+/// be handled, e.g. after the game has been stalled for a while.
 #[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum CatchUp {
+    /// Handle all of the periods.
+    All,
+
+    /// Handle at most the given number of periods, keeping the remaining
+    /// time so that it can be caught up on later.
+    Limit(usize),
+
+    /// Handle at most the given number of periods, dropping the remaining
+    /// periods.
+    Skip(usize),
+}
+
+/// A timer that can be used to trigger events that happen periodically.
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub struct Timer {
     period: Duration,
     accum: Duration,
+
+    /// While paused, time that is added to the timer is ignored.
+    paused: bool,
+
+    /// Factor that time is multiplied with before being added to the timer.
+    scale: f32,
+
+    catch_up: CatchUp,
 }
 
 #[allow(dead_code)]
@@ -26,6 +50,9 @@ impl Timer {
         Timer {
             period,
             accum: Default::default(),
+            paused: false,
+            scale: 1.0,
+            catch_up: CatchUp::All,
         }
     }
 
@@ -37,6 +64,71 @@ impl Timer {
         self.accum
     }
 
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Set the factor by which the timer runs faster than the time that is
+    /// added to it.
+    pub fn set_scale(&mut self, scale: f32) {
+        assert!(scale >= 0.0, "Timer scale must not be negative");
+        self.scale = scale;
+    }
+
+    pub fn catch_up(&self) -> CatchUp {
+        self.catch_up
+    }
+
+    pub fn set_catch_up(&mut self, catch_up: CatchUp) {
+        self.catch_up = catch_up;
+    }
+
+    /// Add elapsed time to the timer, taking into account pausing and the
+    /// time scale.
+    pub fn advance(&mut self, dt: Duration) {
+        if self.paused {
+            return;
+        }
+
+        let dt = if self.scale == 1.0 {
+            dt
+        } else {
+            secs_to_duration(dt.as_secs_f32() * self.scale)
+        };
+
+        self.accum = self.accum.checked_add(dt).unwrap();
+    }
+
+    /// Add elapsed time to the timer and return the number of periods that
+    /// should be handled now, according to the timer's `CatchUp` policy.
+    pub fn drain_triggers(&mut self, dt: Duration) -> usize {
+        self.advance(dt);
+
+        let n = self.trigger_n();
+
+        match self.catch_up {
+            CatchUp::All => n,
+            CatchUp::Limit(max) if n > max => {
+                self.accum += self.period * (n - max) as u32;
+                max
+            }
+            CatchUp::Skip(max) => n.min(max),
+            CatchUp::Limit(_) => n,
+        }
+    }
+
     /// Change the period, updating the accumulated time so that the percentual
     /// progress is unchanged.
     pub fn set_period(&mut self, new_period: Duration) {
@@ -103,6 +195,6 @@ impl Timer {
 
 impl AddAssign<Duration> for Timer {
     fn add_assign(&mut self, other: Duration) {
-        self.accum = self.accum.checked_add(other).unwrap();
+        self.advance(other);
     }
 }
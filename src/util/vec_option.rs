@@ -2,6 +2,8 @@ use std::collections::VecDeque;
 use std::iter::Enumerate;
 use std::ops::{Index, IndexMut};
 
+use rayon::prelude::*;
+
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct VecOption<T> {
     data: Vec<Option<T>>,
@@ -18,6 +20,14 @@ impl<T> VecOption<T> {
         }
     }
 
+    pub fn with_capacity(capacity: usize) -> VecOption<T> {
+        VecOption {
+            data: Vec::with_capacity(capacity),
+            free: VecDeque::new(),
+            size: 0,
+        }
+    }
+
     pub fn add(&mut self, value: T) -> usize {
         self.size += 1;
 
@@ -57,10 +67,15 @@ impl<T> VecOption<T> {
         }
     }
 
-    pub fn keys(&self) -> impl Iterator<Item = usize> + '_ {
+    /// Iterates over the indices of occupied slots in ascending order.
+    pub fn iter_indices(&self) -> impl Iterator<Item = usize> + '_ {
         self.iter().map(|(index, _value)| index)
     }
 
+    pub fn keys(&self) -> impl Iterator<Item = usize> + '_ {
+        self.iter_indices()
+    }
+
     pub fn values(&self) -> impl Iterator<Item = &T> {
         self.iter().map(|(_index, value)| value)
     }
@@ -69,6 +84,32 @@ impl<T> VecOption<T> {
         self.size
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Returns the number of slots, occupied or not, that can be held
+    /// without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.data.reserve(additional);
+    }
+
+    /// Releases free slots at the end, as well as unused capacity. Indices
+    /// of occupied slots stay the same.
+    pub fn shrink_to_fit(&mut self) {
+        while let Some(None) = self.data.last() {
+            self.data.pop();
+        }
+
+        let len = self.data.len();
+        self.free.retain(|&index| index < len);
+        self.data.shrink_to_fit();
+    }
+
     #[allow(dead_code)]
     pub fn clear(&mut self) {
         self.data.clear();
@@ -84,6 +125,10 @@ impl<T> VecOption<T> {
         num
     }
 
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.data.get(index).and_then(Option::as_ref)
+    }
+
     pub fn retain(&mut self, mut f: impl FnMut(&T) -> bool) {
         for i in 0..self.data.len() {
             let remove = self.data[i].as_ref().map_or(false, |elem| !f(elem));
@@ -94,12 +139,42 @@ impl<T> VecOption<T> {
         }
     }
 
+    /// Removes all elements, yielding them together with their index. Slots
+    /// are released even if the iterator is not consumed.
+    pub fn drain(&mut self) -> impl Iterator<Item = (usize, T)> + '_ {
+        self.free.clear();
+        self.size = 0;
+
+        self.data
+            .drain(..)
+            .enumerate()
+            .filter_map(|(index, value)| value.map(|value| (index, value)))
+    }
+
     pub fn gc(&mut self) {
         self.data.retain(Option::is_some);
         self.free.clear();
     }
 }
 
+impl<T: Sync> VecOption<T> {
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = (usize, &T)> {
+        self.data
+            .par_iter()
+            .enumerate()
+            .filter_map(|(index, value)| value.as_ref().map(|value| (index, value)))
+    }
+}
+
+impl<T: Send> VecOption<T> {
+    pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = (usize, &mut T)> {
+        self.data
+            .par_iter_mut()
+            .enumerate()
+            .filter_map(|(index, value)| value.as_mut().map(|value| (index, value)))
+    }
+}
+
 impl<T> Index<usize> for VecOption<T> {
     type Output = T;
 
@@ -0,0 +1,68 @@
+//! Switching between windowed and fullscreen modes.
+
+use glium::glutin;
+use log::{info, warn};
+
+use crate::config::{ViewConfig, WindowMode};
+
+/// Returns the monitor selected by index, falling back to the primary monitor
+/// if there is no such monitor.
+pub fn monitor(events_loop: &glutin::EventsLoop, index: Option<usize>) -> glutin::MonitorId {
+    index
+        .and_then(|index| {
+            let monitor = events_loop.get_available_monitors().nth(index);
+
+            if monitor.is_none() {
+                warn!("No monitor with index {}, using primary monitor", index);
+            }
+
+            monitor
+        })
+        .unwrap_or_else(|| events_loop.get_primary_monitor())
+}
+
+/// Returns names of the available monitors, in the order that is used for
+/// selecting them by index.
+pub fn monitor_names(events_loop: &glutin::EventsLoop) -> Vec<String> {
+    events_loop
+        .get_available_monitors()
+        .enumerate()
+        .map(|(index, monitor)| {
+            monitor
+                .get_name()
+                .unwrap_or_else(|| format!("Monitor {}", index + 1))
+        })
+        .collect()
+}
+
+/// Puts the window into the configured mode. The window receives a resize
+/// event afterwards, which takes care of resizing the render targets.
+pub fn apply(window: &glutin::Window, events_loop: &glutin::EventsLoop, config: &ViewConfig) {
+    info!(
+        "Switching to window mode {:?} on monitor {:?}",
+        config.window_mode, config.monitor
+    );
+
+    let monitor = monitor(events_loop, config.monitor);
+
+    match config.window_mode {
+        WindowMode::Windowed => {
+            window.set_fullscreen(None);
+            window.set_decorations(true);
+            window.set_inner_size(config.window_size);
+        }
+        WindowMode::Borderless => {
+            window.set_fullscreen(None);
+            window.set_decorations(false);
+
+            // Cover the whole monitor, whose geometry is given in physical
+            // pixels.
+            let hidpi_factor = monitor.get_hidpi_factor();
+            window.set_position(monitor.get_position().to_logical(hidpi_factor));
+            window.set_inner_size(monitor.get_dimensions().to_logical(hidpi_factor));
+        }
+        WindowMode::Fullscreen => {
+            window.set_fullscreen(Some(monitor));
+        }
+    }
+}
@@ -0,0 +1,366 @@
+//! Local collection of shareable items, i.e. machines, blueprints and levels,
+//! together with an index of their metadata that can be browsed and
+//! searched.
+//!
+//! Items are stored in the workshop directory as follows:
+//!
+//! ```text
+//! <workshop_dir>/index.json          The `Index` of all items.
+//! <workshop_dir>/machines/*.json     Saved machines, or in the text format.
+//! <workshop_dir>/blueprints/*.json   Saved machines to place in the editor.
+//! <workshop_dir>/levels/*.json       Levels.
+//! ```
+//!
+//! An item may have a thumbnail, which is a PNG file next to the item with
+//! the same file stem. Files that are added by hand are picked up when the
+//! index is refreshed, using their file stem as their title.
+
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::edit::solutions;
+use crate::machine::{Level, Machine};
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub dir: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("workshop"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemKind {
+    Machine,
+    Blueprint,
+    Level,
+}
+
+impl ItemKind {
+    pub const ALL: &'static [ItemKind] = &[ItemKind::Machine, ItemKind::Blueprint, ItemKind::Level];
+
+    /// Name of the subdirectory that items of this kind are stored in.
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            ItemKind::Machine => "machines",
+            ItemKind::Blueprint => "blueprints",
+            ItemKind::Level => "levels",
+        }
+    }
+
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            ItemKind::Machine => &["json", solutions::TEXT_EXTENSION],
+            ItemKind::Blueprint | ItemKind::Level => &["json"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Item {
+    /// Unique id of the item, of the form `<kind dir>/<file name>`. This is
+    /// also its path relative to the workshop directory.
+    pub id: String,
+    pub kind: ItemKind,
+    pub title: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// Path of the thumbnail relative to the workshop directory, if any.
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+}
+
+impl Item {
+    /// Returns true if each word of the query is contained in the item's
+    /// title, or matches the beginning of one of its tags, ignoring case.
+    pub fn matches(&self, query: &str) -> bool {
+        let title = self.title.to_lowercase();
+
+        query.split_whitespace().all(|word| {
+            let word = word.to_lowercase();
+
+            title.contains(&word)
+                || self
+                    .tags
+                    .iter()
+                    .any(|tag| tag.to_lowercase().starts_with(&word))
+        })
+    }
+}
+
+/// Metadata of all items, persisted as `index.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Index {
+    pub items: Vec<Item>,
+}
+
+/// The workshop directory and its index.
+pub struct Workshop {
+    config: Config,
+    index: Index,
+}
+
+impl Workshop {
+    /// Loads the index and brings it up to date with the files in the
+    /// workshop directory.
+    pub fn load(config: &Config) -> Workshop {
+        let index_path = config.dir.join("index.json");
+        let index = if index_path.exists() {
+            load_json(&index_path).unwrap_or_default()
+        } else {
+            Index::default()
+        };
+
+        let mut workshop = Workshop {
+            config: config.clone(),
+            index,
+        };
+        workshop.refresh();
+
+        workshop
+    }
+
+    pub fn items(&self) -> &[Item] {
+        &self.index.items
+    }
+
+    pub fn item(&self, id: &str) -> Option<&Item> {
+        self.index.items.iter().find(|item| item.id == id)
+    }
+
+    /// Returns the items of the given kind that match the query, ordered by
+    /// title. An empty query matches all items.
+    pub fn search(&self, kind: Option<ItemKind>, query: &str) -> Vec<&Item> {
+        let mut items: Vec<&Item> = self
+            .index
+            .items
+            .iter()
+            .filter(|item| kind.map_or(true, |kind| item.kind == kind))
+            .filter(|item| item.matches(query))
+            .collect();
+        items.sort_by(|a, b| a.title.cmp(&b.title));
+
+        items
+    }
+
+    pub fn path(&self, item: &Item) -> PathBuf {
+        self.config.dir.join(&item.id)
+    }
+
+    pub fn thumbnail_path(&self, item: &Item) -> Option<PathBuf> {
+        item.thumbnail
+            .as_ref()
+            .map(|thumbnail| self.config.dir.join(thumbnail))
+    }
+
+    /// Loads the machine of a machine or blueprint item.
+    pub fn load_machine(&self, item: &Item) -> Option<Machine> {
+        match item.kind {
+            ItemKind::Machine | ItemKind::Blueprint => solutions::load(&self.path(item)),
+            ItemKind::Level => None,
+        }
+    }
+
+    pub fn load_level(&self, item: &Item) -> Option<Level> {
+        match item.kind {
+            ItemKind::Level => load_json(&self.path(item)),
+            ItemKind::Machine | ItemKind::Blueprint => None,
+        }
+    }
+
+    /// Stores a copy of the machine in the workshop directory, so that it can
+    /// be shared. An existing item with the same file name is replaced.
+    pub fn add_machine(
+        &mut self,
+        kind: ItemKind,
+        file_name: &str,
+        title: &str,
+        tags: Vec<String>,
+        machine: &Machine,
+    ) -> Option<&Item> {
+        if kind == ItemKind::Level || !solutions::is_valid_name(file_name) {
+            warn!("Can not add {:?} item {:?} to workshop", kind, file_name);
+            return None;
+        }
+
+        let id = format!("{}/{}.json", kind.dir_name(), file_name);
+        solutions::save(machine, &self.config.dir.join(&id));
+
+        let thumbnail = self.find_thumbnail(&id);
+
+        self.index.items.retain(|item| item.id != id);
+        self.index.items.push(Item {
+            id: id.clone(),
+            kind,
+            title: title.to_string(),
+            description: String::new(),
+            tags,
+            thumbnail,
+        });
+        self.save_index();
+
+        self.item(&id)
+    }
+
+    /// Updates the title, description and tags of an item.
+    pub fn set_metadata(&mut self, id: &str, title: &str, description: &str, tags: Vec<String>) {
+        match self.index.items.iter_mut().find(|item| item.id == id) {
+            Some(item) => {
+                item.title = title.to_string();
+                item.description = description.to_string();
+                item.tags = tags;
+            }
+            None => {
+                warn!("No workshop item with id {:?}", id);
+                return;
+            }
+        }
+
+        self.save_index();
+    }
+
+    /// Adds items for files that are not in the index yet, and removes items
+    /// whose files no longer exist. The metadata of other items is kept.
+    pub fn refresh(&mut self) {
+        let mut found = Vec::new();
+        for &kind in ItemKind::ALL {
+            found.extend(
+                list_files(&self.config.dir.join(kind.dir_name()), kind.extensions())
+                    .into_iter()
+                    .map(|(stem, file_name)| (kind, stem, file_name)),
+            );
+        }
+
+        let num_items = self.index.items.len();
+        self.index.items.retain(|item| {
+            found
+                .iter()
+                .any(|(kind, _, file_name)| item.id == format!("{}/{}", kind.dir_name(), file_name))
+        });
+        let mut changed = self.index.items.len() != num_items;
+
+        for (kind, stem, file_name) in found {
+            let id = format!("{}/{}", kind.dir_name(), file_name);
+            let thumbnail = self.find_thumbnail(&id);
+
+            match self.index.items.iter_mut().find(|item| item.id == id) {
+                Some(item) => {
+                    if item.thumbnail != thumbnail {
+                        item.thumbnail = thumbnail;
+                        changed = true;
+                    }
+                }
+                None => {
+                    info!("Adding {:?} to workshop index", id);
+
+                    self.index.items.push(Item {
+                        id,
+                        kind,
+                        title: stem,
+                        description: String::new(),
+                        tags: Vec::new(),
+                        thumbnail,
+                    });
+                    changed = true;
+                }
+            }
+        }
+
+        if changed {
+            self.save_index();
+        }
+    }
+
+    fn find_thumbnail(&self, id: &str) -> Option<String> {
+        let thumbnail = Path::new(id).with_extension("png");
+
+        if self.config.dir.join(&thumbnail).exists() {
+            thumbnail
+                .to_str()
+                .map(|thumbnail| thumbnail.replace('\\', "/"))
+        } else {
+            None
+        }
+    }
+
+    fn save_index(&self) {
+        let path = self.config.dir.join("index.json");
+        info!("Saving workshop index to file {:?}", path);
+
+        if let Err(err) = fs::create_dir_all(&self.config.dir) {
+            warn!("Could not create directory {:?}: {}", self.config.dir, err);
+            return;
+        }
+
+        match File::create(&path) {
+            Ok(file) => {
+                if let Err(err) = serde_json::to_writer_pretty(file, &self.index) {
+                    warn!(
+                        "Error while saving workshop index to file {:?}: {}",
+                        path, err
+                    );
+                }
+            }
+            Err(err) => {
+                warn!("Could not open file {:?} for writing: {}", path, err);
+            }
+        }
+    }
+}
+
+/// Returns the file stems and names of all files with one of the given
+/// extensions in the directory, sorted by file name.
+fn list_files(dir: &Path, extensions: &[&str]) -> Vec<(String, String)> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut files: Vec<(String, String)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .map_or(false, |ext| extensions.contains(&ext))
+        })
+        .filter_map(|path| {
+            let stem = path.file_stem()?.to_str()?.to_string();
+            let file_name = path.file_name()?.to_str()?.to_string();
+            Some((stem, file_name))
+        })
+        .collect();
+    files.sort();
+
+    files
+}
+
+fn load_json<T: serde::de::DeserializeOwned>(path: &Path) -> Option<T> {
+    match File::open(path) {
+        Ok(file) => match serde_json::from_reader(BufReader::new(file)) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                warn!("Error while loading file {:?}: {}", path, err);
+                None
+            }
+        },
+        Err(err) => {
+            warn!("Could not open file {:?} for reading: {}", path, err);
+            None
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Golden tests for the semantics of blocks. Each test runs a small machine
+//! for a fixed number of ticks and compares a trace of its blips and wind
+//! against a trace that has been recorded in `tests/golden/`.
+//!
+//! In addition, a set of machines from `test_machines/` is run for longer,
+//! comparing only a hash of each trace against `tests/golden/hashes.txt`, so
+//! that unintended changes in the behavior of larger machines are caught as
+//! well.
+//!
+//! If a change in behavior is intended, the traces can be recorded again by
+//! running the tests with the environment variable `UPDATE_GOLDEN` set.
+
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+use rand::SeedableRng;
+
+use ultimate_scale::exec::Exec;
+use ultimate_scale::machine::grid::{Dir3, Point3, Sign};
+use ultimate_scale::machine::string_util::machine_from_string;
+use ultimate_scale::machine::{Machine, SavedMachine};
+
+/// Machines from `test_machines/` whose trace hashes are compared.
+const HASHED_MACHINES: &[&str] = &[
+    "activate_at_same_time",
+    "blip_deleter",
+    "clock_level",
+    "clocks",
+    "crossings",
+    "detector_copier",
+    "falling_blips",
+    "hilbert",
+    "mod_3",
+    "mul_by_3_level",
+    "split_merge",
+    "swap_3",
+];
+
+/// Number of ticks that the machines with hashed traces are run.
+const HASHED_NUM_TICKS: usize = 200;
+
+/// A wind source feeding a pipe that contains funnels in both directions.
+#[test]
+fn test_golden_funnel() {
+    let m = "
+◉--▷--
+◉--◁--
+";
+
+    assert_golden("funnel", machine_from_string(m), 10);
+}
+
+/// A blip that is duplicated into two pipes.
+#[test]
+fn test_golden_duplicator() {
+    let m = "
+◉-------┐
+ ┷     -┿-
+";
+
+    assert_golden("duplicator", machine_from_string(m), 20);
+}
+
+/// Wind that splits up at an intersection, carrying a blip with it.
+#[test]
+fn test_golden_split() {
+    let m = "
+◉----┐
+ ┷   |
+  ---┼---
+     |
+     |
+";
+
+    assert_golden("split", machine_from_string(m), 12);
+}
+
+/// A wind source that is activated by blips hitting its button.
+#[test]
+fn test_golden_wind_source_timing() {
+    let m = "
+◉---┐
+ ┷  ⎴-----
+";
+
+    assert_golden("wind_source_timing", machine_from_string(m), 16);
+}
+
+/// Larger machines, which may also have levels, whose traces are compared by
+/// hash.
+#[test]
+fn test_golden_hashes() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let hashes_path = dir.join("tests").join("golden").join("hashes.txt");
+
+    let actual: Vec<String> = HASHED_MACHINES
+        .iter()
+        .map(|name| {
+            let path = dir.join("test_machines").join(format!("{}.json", name));
+            let json = fs::read_to_string(&path)
+                .unwrap_or_else(|err| panic!("Could not read {:?}: {}", path, err));
+            let machine: SavedMachine = serde_json::from_str(&json)
+                .unwrap_or_else(|err| panic!("Could not parse {:?}: {}", path, err));
+
+            let hash = fnv_hash(&trace(machine.into_machine(), HASHED_NUM_TICKS));
+            format!("{} {:016x}", name, hash)
+        })
+        .collect();
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&hashes_path, actual.join("\n") + "\n").unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&hashes_path).unwrap_or_else(|err| {
+        panic!(
+            "Could not read golden hashes {:?}: {}. Run with UPDATE_GOLDEN=1 to record them.",
+            hashes_path, err
+        )
+    });
+    let expected: Vec<&str> = expected.lines().collect();
+
+    for (actual_line, expected_line) in actual.iter().zip(expected.iter()) {
+        assert_eq!(
+            actual_line, expected_line,
+            "Trace hash differs from {:?}",
+            hashes_path,
+        );
+    }
+    assert_eq!(
+        actual.len(),
+        expected.len(),
+        "Number of trace hashes differs from {:?}",
+        hashes_path,
+    );
+}
+
+fn assert_golden(name: &str, machine: Machine, num_ticks: usize) {
+    let actual = trace(machine, num_ticks);
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.txt", name));
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, &actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|err| {
+        panic!(
+            "Could not read golden trace {:?}: {}. Run with UPDATE_GOLDEN=1 to record it.",
+            path, err
+        )
+    });
+
+    // Compare line by line, so that the first difference is easy to spot.
+    for (i, (actual_line, expected_line)) in actual.lines().zip(expected.lines()).enumerate() {
+        assert_eq!(
+            actual_line,
+            expected_line,
+            "Trace of `{}' differs from {:?} in line {}",
+            name,
+            path,
+            i + 1,
+        );
+    }
+    assert_eq!(
+        actual.lines().count(),
+        expected.lines().count(),
+        "Trace of `{}' differs from {:?} in length",
+        name,
+        path,
+    );
+}
+
+/// Runs the machine, recording the state of all blips and all outgoing wind
+/// after each tick.
+fn trace(machine: Machine, num_ticks: usize) -> String {
+    let mut exec = Exec::new(machine, &mut rand::rngs::StdRng::seed_from_u64(0));
+    let mut trace = String::new();
+
+    for _ in 0..num_ticks {
+        exec.update();
+
+        writeln!(trace, "tick {}", exec.cur_tick()).unwrap();
+
+        // Sort the lines, so that the traces do not depend on the order in
+        // which blips and blocks are stored.
+        let mut blips: Vec<String> = exec
+            .blips()
+            .values()
+            .map(|blip| {
+                format!(
+                    "  blip {:?} at {} orient {} move {} {:?}",
+                    blip.kind,
+                    point_str(&blip.pos),
+                    dir_str(blip.orient),
+                    blip.move_dir.map_or("-".to_string(), dir_str),
+                    blip.status,
+                )
+            })
+            .collect();
+        blips.sort();
+
+        let mut winds: Vec<String> = exec
+            .machine_with_air()
+            .iter_blocks()
+            .filter_map(|(index, (pos, _))| {
+                let dirs: Vec<String> = exec.next_blocks().wind_out[index]
+                    .iter()
+                    .filter(|(_, &wind)| wind)
+                    .map(|(dir, _)| dir_str(dir))
+                    .collect();
+
+                if dirs.is_empty() {
+                    None
+                } else {
+                    Some(format!(
+                        "  wind at {} out {}",
+                        point_str(pos),
+                        dirs.join(" ")
+                    ))
+                }
+            })
+            .collect();
+        winds.sort();
+
+        for line in blips.iter().chain(winds.iter()) {
+            writeln!(trace, "{}", line).unwrap();
+        }
+    }
+
+    trace
+}
+
+/// 64-bit FNV-1a hash. Unlike the standard library's hashers, it is
+/// guaranteed to stay the same across Rust versions.
+fn fnv_hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+fn point_str(p: &Point3) -> String {
+    format!("({},{},{})", p.x, p.y, p.z)
+}
+
+fn dir_str(dir: Dir3) -> String {
+    let sign = match dir.1 {
+        Sign::Pos => "+",
+        Sign::Neg => "-",
+    };
+
+    format!("{:?}{}", dir.0, sign)
+}